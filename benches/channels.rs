@@ -0,0 +1,42 @@
+//! Compare the backends of the in-memory channels on a local pipeline: run it once with the
+//! default backend and once with `--features channel-crossbeam`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use renoir::{BatchMode, RuntimeConfig, StreamContext};
+
+const BACKEND: &str = if cfg!(feature = "channel-crossbeam") {
+    "crossbeam"
+} else {
+    "flume"
+};
+
+const DATASET_SIZE: u64 = 1_000_000;
+
+/// Move all the elements between the replicas a few times, through the local channels.
+fn shuffle(batch_size: usize) {
+    let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+    env.stream_par_iter(0..DATASET_SIZE)
+        .batch_mode(BatchMode::fixed(batch_size))
+        .shuffle()
+        .map(|n| n.wrapping_mul(3))
+        .shuffle()
+        .group_by_sum(|n| n % 16, |n| n)
+        .for_each(std::mem::drop);
+    env.execute_blocking();
+}
+
+fn channels_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("channels/{BACKEND}"));
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(DATASET_SIZE));
+    for batch_size in [16, 1024] {
+        group.bench_function(format!("shuffle-batch-{batch_size}"), |b| {
+            b.iter(|| shuffle(black_box(batch_size)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, channels_benchmark);
+criterion_main!(benches);
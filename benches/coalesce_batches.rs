@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use renoir::operator::source::IteratorSource;
+use renoir::RuntimeConfig;
+use renoir::StreamContext;
+
+fn filter_shuffle(coalesce: bool, dataset: &'static [u32]) {
+    let config = RuntimeConfig::local(4).unwrap();
+    let env = StreamContext::new(config);
+
+    let source = IteratorSource::new(dataset.iter().cloned());
+    let stream = env
+        .stream(source)
+        .shuffle()
+        // keep about 1% of the items
+        .filter(|n| n % 100 == 0);
+    let stream = if coalesce {
+        stream
+            .coalesce_batches(1024, Duration::from_millis(10))
+            .into_boxed()
+    } else {
+        stream.into_boxed()
+    };
+    stream
+        .shuffle()
+        .map(|n| n.wrapping_mul(2))
+        .for_each(std::mem::drop);
+    env.execute_blocking();
+}
+
+fn coalesce_batches_benchmark(c: &mut Criterion) {
+    let seed = b"rstream2 by edomora97 and mark03".to_owned();
+    let r = &mut SmallRng::from_seed(seed);
+
+    const DATASET_SIZE: usize = 1_000_000;
+    let dataset = (0..DATASET_SIZE).map(|_| r.gen()).collect::<Vec<u32>>();
+    let dataset = dataset.leak() as &_;
+
+    let mut group = c.benchmark_group("coalesce_batches");
+    group.throughput(Throughput::Bytes(
+        (DATASET_SIZE * std::mem::size_of::<u32>()) as u64,
+    ));
+    group.bench_function("filter-shuffle", |b| {
+        b.iter(|| filter_shuffle(false, black_box(dataset)))
+    });
+    group.bench_function("filter-coalesce-shuffle", |b| {
+        b.iter(|| filter_shuffle(true, black_box(dataset)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, coalesce_batches_benchmark);
+criterion_main!(benches);
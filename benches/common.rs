@@ -26,8 +26,10 @@ pub fn remote_loopback_deploy(
         let address = format!("127.{hi}.{lo}.{host_id}");
         hosts.push(HostConfig {
             address,
+            bind_address: None,
             base_port: PORT_BASE,
             num_cores: cores_per_host,
+            weight: None,
             ssh: Default::default(),
             perf_path: None,
         });
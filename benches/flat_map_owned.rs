@@ -0,0 +1,54 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use renoir::StreamContext;
+
+fn cloning(lines: &'static [String]) {
+    let env = StreamContext::new_local();
+    env.stream_iter(lines.iter().cloned())
+        .flat_map(|line| {
+            line.split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .for_each(std::mem::drop);
+    env.execute_blocking();
+}
+
+fn owned(lines: &'static [String]) {
+    let env = StreamContext::new_local();
+    env.stream_iter(lines.iter().cloned())
+        .flat_map_iter_owned(|line, out| out.extend(line.split_whitespace().map(String::from)))
+        .for_each(std::mem::drop);
+    env.execute_blocking();
+}
+
+fn flat_map_owned_benchmark(c: &mut Criterion) {
+    let seed = b"rstream2 by edomora97 and mark03".to_owned();
+    let r = &mut SmallRng::from_seed(seed);
+
+    const LINES: usize = 100;
+    const WORDS_PER_LINE: usize = 10_000;
+    let lines = (0..LINES)
+        .map(|_| {
+            (0..WORDS_PER_LINE)
+                .map(|_| format!("{:x}", r.gen::<u32>()))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>();
+    let size = lines.iter().map(|l| l.len()).sum::<usize>();
+    let lines = lines.leak() as &_;
+
+    let mut group = c.benchmark_group("flat_map_owned");
+    group.throughput(Throughput::Bytes(size as u64));
+    group.bench_function("flat_map", |b| b.iter(|| cloning(black_box(lines))));
+    group.bench_function("flat_map_iter_owned", |b| {
+        b.iter(|| owned(black_box(lines)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, flat_map_owned_benchmark);
+criterion_main!(benches);
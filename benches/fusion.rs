@@ -0,0 +1,47 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use renoir::RuntimeConfig;
+use renoir::StreamContext;
+
+const DATASET_SIZE: u64 = 1_000_000;
+
+fn chained(n: u64) {
+    let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+    let _result = env
+        .stream_par_iter(move |i, p| (i * n / p)..((i + 1) * n / p))
+        .map(|x| x.wrapping_mul(3))
+        .map(|x| x ^ 0x5555)
+        .filter(|x| x % 3 != 0)
+        .map(|x| x.rotate_left(7))
+        .map(|x| x.wrapping_add(11))
+        .fold(0u64, |a, b| *a = a.wrapping_add(b))
+        .collect_vec();
+    env.execute_blocking();
+}
+
+fn fused(n: u64) {
+    let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+    let _result = env
+        .stream_par_iter(move |i, p| (i * n / p)..((i + 1) * n / p))
+        .fused(|f| {
+            f.map(|x| x.wrapping_mul(3))
+                .map(|x| x ^ 0x5555)
+                .filter(|x| x % 3 != 0)
+                .map(|x| x.rotate_left(7))
+                .map(|x| x.wrapping_add(11))
+        })
+        .fold(0u64, |a, b| *a = a.wrapping_add(b))
+        .collect_vec();
+    env.execute_blocking();
+}
+
+fn fusion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fusion");
+    group.throughput(Throughput::Elements(DATASET_SIZE));
+    group.bench_function("chained", |b| b.iter(|| chained(black_box(DATASET_SIZE))));
+    group.bench_function("fused", |b| b.iter(|| fused(black_box(DATASET_SIZE))));
+    group.finish();
+}
+
+criterion_group!(benches, fusion_benchmark);
+criterion_main!(benches);
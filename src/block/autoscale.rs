@@ -0,0 +1,153 @@
+use std::time::{Duration, Instant};
+
+/// Number of consecutive idle intervals after which an active replica is retired.
+const IDLE_INTERVALS: usize = 3;
+
+/// Configuration of the adaptive parallelism of a block.
+///
+/// The block is started with `max` replicas, but the items are forwarded only to the first
+/// `active` ones. The sender measures how long it is blocked waiting for the downstream replicas
+/// (the backpressure) and, at the end of every interval:
+///
+/// - if it was blocked for more than `scale_up` of the interval, another replica is activated;
+/// - if it was blocked for less than `scale_down` of the interval for some consecutive intervals,
+///   the last active replica is retired.
+///
+/// The number of active replicas is always kept between `min` and `max`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Autoscale {
+    pub(crate) min: usize,
+    pub(crate) max: usize,
+    pub(crate) interval: Duration,
+    pub(crate) scale_up: f64,
+    pub(crate) scale_down: f64,
+}
+
+impl Autoscale {
+    /// Scale the number of active replicas between `min` and `max` (both inclusive).
+    pub fn new(min: usize, max: usize) -> Self {
+        assert!(min > 0, "Autoscale: at least one replica must be active");
+        assert!(min <= max, "Autoscale: min must not be greater than max");
+        Self {
+            min,
+            max,
+            interval: Duration::from_millis(100),
+            scale_up: 0.5,
+            scale_down: 0.05,
+        }
+    }
+
+    /// How often the backpressure is evaluated (default 100ms).
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Fraction of the interval spent blocked above which a replica is activated (default 0.5).
+    pub fn scale_up(mut self, threshold: f64) -> Self {
+        self.scale_up = threshold;
+        self
+    }
+
+    /// Fraction of the interval spent blocked below which a replica is retired (default 0.05).
+    pub fn scale_down(mut self, threshold: f64) -> Self {
+        self.scale_down = threshold;
+        self
+    }
+}
+
+/// Keeps track of the observed backpressure and of the number of active replicas.
+#[derive(Debug, Clone)]
+pub(crate) struct AutoscaleController {
+    config: Autoscale,
+    active: usize,
+    /// Time spent blocked since the start of the interval.
+    blocked: Duration,
+    interval_start: Instant,
+    idle_intervals: usize,
+}
+
+impl AutoscaleController {
+    pub(crate) fn new(config: Autoscale) -> Self {
+        Self {
+            config,
+            active: config.min,
+            blocked: Duration::ZERO,
+            interval_start: Instant::now(),
+            idle_intervals: 0,
+        }
+    }
+
+    /// The number of replicas that currently receive the items.
+    pub(crate) fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Record the time spent sending a message, re-evaluating the number of active replicas at
+    /// the end of the interval.
+    pub(crate) fn record(&mut self, blocked: Duration) {
+        self.blocked += blocked;
+        let elapsed = self.interval_start.elapsed();
+        if elapsed < self.config.interval {
+            return;
+        }
+
+        // avoid dividing by zero with very short intervals
+        let elapsed = elapsed.max(Duration::from_nanos(1));
+        let pressure = self.blocked.as_secs_f64() / elapsed.as_secs_f64();
+        if pressure > self.config.scale_up {
+            self.idle_intervals = 0;
+            if self.active < self.config.max {
+                self.active += 1;
+                log::debug!(
+                    "autoscale: backpressure {pressure:.2}, scaling up to {} replicas",
+                    self.active
+                );
+            }
+        } else if pressure < self.config.scale_down {
+            self.idle_intervals += 1;
+            if self.idle_intervals >= IDLE_INTERVALS && self.active > self.config.min {
+                self.idle_intervals = 0;
+                self.active -= 1;
+                log::debug!(
+                    "autoscale: backpressure {pressure:.2}, scaling down to {} replicas",
+                    self.active
+                );
+            }
+        } else {
+            self.idle_intervals = 0;
+        }
+
+        self.blocked = Duration::ZERO;
+        self.interval_start = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Autoscale, AutoscaleController};
+
+    #[test]
+    fn autoscale_controller_bounds() {
+        let config = Autoscale::new(1, 3).interval(Duration::ZERO);
+        let mut controller = AutoscaleController::new(config);
+        assert_eq!(controller.active(), 1);
+
+        for _ in 0..10 {
+            controller.record(Duration::from_secs(10));
+        }
+        assert_eq!(controller.active(), 3);
+
+        // a single idle interval is not enough to scale down
+        controller.record(Duration::ZERO);
+        controller.record(Duration::from_secs(10));
+        assert_eq!(controller.active(), 3);
+
+        for _ in 0..20 {
+            controller.record(Duration::ZERO);
+        }
+        assert_eq!(controller.active(), 1);
+    }
+}
@@ -1,25 +1,30 @@
+use std::fmt::{Display, Formatter};
 use std::num::NonZeroUsize;
 use std::time::Duration;
 
 use coarsetime::Instant;
+use serde::{Deserialize, Serialize};
 
 use crate::network::{Coord, NetworkMessage, NetworkSender};
 use crate::operator::{ExchangeData, StreamElement};
 
 /// Which policy to use for batching the messages before sending them.
 ///
-/// Avoid constructing directly this enumeration, please use [`BatchMode::fixed()`] and
-/// [`BatchMode::adaptive()`] constructors.
+/// Avoid constructing directly this enumeration, please use [`BatchMode::fixed()`],
+/// [`BatchMode::adaptive()`] and [`BatchMode::periodic()`] constructors.
 ///
 /// The default batch mode is `Adaptive(1024, 50ms)`, meaning that a batch is flushed either when
 /// it has at least 1024 messages, or no message has been received in the last 50ms.
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub enum BatchMode {
     /// A batch is flushed only when the specified number of messages is present.
     Fixed(NonZeroUsize),
     /// A batch is flushed only when the specified number of messages is present or a timeout
     /// expires.
     Adaptive(NonZeroUsize, Duration),
+    /// A batch is flushed when the specified number of messages is present, and at least once
+    /// every interval, even if the messages keep arriving.
+    Periodic(NonZeroUsize, Duration),
 
     /// Send each message infdividually
     Single,
@@ -29,14 +34,14 @@ impl BatchMode {
     pub fn max_size(&self) -> usize {
         match self {
             BatchMode::Fixed(s) => s.get(),
-            BatchMode::Adaptive(s, _) => s.get(),
+            BatchMode::Adaptive(s, _) | BatchMode::Periodic(s, _) => s.get(),
             BatchMode::Single => 1,
         }
     }
 
     pub fn interval(&self) -> Option<Duration> {
         match self {
-            BatchMode::Adaptive(_, ts) => Some(*ts),
+            BatchMode::Adaptive(_, ts) | BatchMode::Periodic(_, ts) => Some(*ts),
             _ => None,
         }
     }
@@ -72,7 +77,7 @@ impl<Out: ExchangeData> Batcher<Out> {
     /// Put a message in the batch queue, it won't be sent immediately.
     pub(crate) fn enqueue(&mut self, message: StreamElement<Out>) {
         match self.mode {
-            BatchMode::Adaptive(n, max_delay) => {
+            BatchMode::Adaptive(n, max_delay) | BatchMode::Periodic(n, max_delay) => {
                 self.buffer.push(message);
                 let timeout_elapsed = self.last_send.elapsed() > max_delay.into();
                 if self.buffer.len() >= n.get() || timeout_elapsed {
@@ -87,7 +92,9 @@ impl<Out: ExchangeData> Batcher<Out> {
             }
             BatchMode::Single => {
                 let message = NetworkMessage::new_single(message, self.coord);
-                self.remote_sender.send(message).unwrap();
+                self.remote_sender
+                    .send(message)
+                    .unwrap_or_else(|e| panic!("{e}"));
             }
         }
     }
@@ -104,7 +111,9 @@ impl<Out: ExchangeData> Batcher<Out> {
             let mut batch = Vec::with_capacity(new_cap);
             std::mem::swap(&mut self.buffer, &mut batch);
             let message = NetworkMessage::new_batch(batch, self.coord);
-            self.remote_sender.send(message).unwrap();
+            self.remote_sender
+                .send(message)
+                .unwrap_or_else(|e| panic!("{e}"));
             self.last_send = Instant::now();
         }
     }
@@ -114,7 +123,9 @@ impl<Out: ExchangeData> Batcher<Out> {
         // Send the remaining messages
         if !self.buffer.is_empty() {
             let message = NetworkMessage::new_batch(self.buffer, self.coord);
-            self.remote_sender.send(message).unwrap();
+            self.remote_sender
+                .send(message)
+                .unwrap_or_else(|e| panic!("{e}"));
         }
     }
 }
@@ -133,6 +144,20 @@ impl BatchMode {
         )
     }
 
+    /// Construct a new `BatchMode::Periodic` with the given positive batch size and flush
+    /// interval.
+    ///
+    /// Unlike [`BatchMode::adaptive()`], whose timeout restarts every time a message is received,
+    /// the batches are flushed every `interval` also under a steady trickle of messages, bounding
+    /// the latency of the low-traffic connections.
+    pub fn periodic(size: usize, interval: Duration) -> BatchMode {
+        assert!(!interval.is_zero(), "The flush interval must be positive");
+        BatchMode::Periodic(
+            NonZeroUsize::new(size).expect("The batch size must be positive"),
+            interval,
+        )
+    }
+
     /// Construct a new `BatchMode::Single`.
     pub fn single() -> BatchMode {
         BatchMode::Single
@@ -141,7 +166,25 @@ impl BatchMode {
     pub fn max_delay(&self) -> Option<Duration> {
         match &self {
             BatchMode::Adaptive(_, max_delay) => Some(*max_delay),
-            BatchMode::Fixed(_) | BatchMode::Single => None,
+            BatchMode::Fixed(_) | BatchMode::Periodic(..) | BatchMode::Single => None,
+        }
+    }
+
+    pub fn flush_interval(&self) -> Option<Duration> {
+        match &self {
+            BatchMode::Periodic(_, interval) => Some(*interval),
+            BatchMode::Fixed(_) | BatchMode::Adaptive(..) | BatchMode::Single => None,
+        }
+    }
+}
+
+impl Display for BatchMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BatchMode::Fixed(size) => write!(f, "fixed({size})"),
+            BatchMode::Adaptive(size, max_delay) => write!(f, "adaptive({size}, {max_delay:?})"),
+            BatchMode::Periodic(size, interval) => write!(f, "periodic({size}, {interval:?})"),
+            BatchMode::Single => write!(f, "single"),
         }
     }
 }
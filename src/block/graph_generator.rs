@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
 use indexmap::IndexMap;
 
 use crate::{
@@ -5,26 +8,83 @@ use crate::{
     scheduler::BlockId,
 };
 
+/// A connection between two blocks whose ends report different types, see
+/// [`JobGraphGenerator::validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeMismatch {
+    /// The block that sends the elements.
+    pub from_block: BlockId,
+    /// The block that receives the elements.
+    pub to_block: BlockId,
+    /// The type of the elements sent, reported by the connection.
+    pub sent: DataType,
+    /// The types of the elements expected by the receivers of `to_block` for `from_block`.
+    pub received: Vec<DataType>,
+}
+
+impl Display for TypeMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let received = self
+            .received
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>()
+            .join(" or ");
+        write!(
+            f,
+            "block {} sends {} to block {}, which expects {received}",
+            self.from_block, self.sent, self.to_block
+        )
+    }
+}
+
 /// This struct is able to track the block structure of all the blocks of the job graph for later
 /// producing a diagram in dot format.
 #[derive(Clone, Debug)]
 pub struct JobGraphGenerator {
     /// The list of known blocks, indexed by block id.
     blocks: IndexMap<BlockId, BlockStructure, crate::block::CoordHasherBuilder>,
+    /// The number of replicas of each block.
+    replicas: IndexMap<BlockId, usize, crate::block::CoordHasherBuilder>,
+    /// The description of the operators of each block, shown in the tooltips.
+    descriptions: IndexMap<BlockId, String, crate::block::CoordHasherBuilder>,
 }
 
 impl JobGraphGenerator {
     pub fn new() -> Self {
         Self {
             blocks: Default::default(),
+            replicas: Default::default(),
+            descriptions: Default::default(),
         }
     }
 
-    /// Register a new block inside the generator.
+    /// Register a replica of a block inside the generator.
     ///
-    /// If a block with the same id has already been registered, the structure will be overwritten.
+    /// If a block with the same id has already been registered, the structure will be overwritten
+    /// and the block will count one more replica.
     pub fn add_block(&mut self, block_id: BlockId, structure: BlockStructure) {
         self.blocks.insert(block_id, structure);
+        *self.replicas.entry(block_id).or_default() += 1;
+    }
+
+    /// Register a block with the given number of replicas, like calling
+    /// [`add_block`](JobGraphGenerator::add_block) once for each of them.
+    pub fn add_block_replicas(
+        &mut self,
+        block_id: BlockId,
+        structure: BlockStructure,
+        replicas: usize,
+    ) {
+        self.blocks.insert(block_id, structure);
+        self.replicas.insert(block_id, replicas);
+    }
+
+    /// Set the description of the operators of a block, shown in the tooltips of its nodes.
+    ///
+    /// Hovering a node of the diagram rendered as SVG shows the description of its block.
+    pub fn describe_block(&mut self, block_id: BlockId, description: String) {
+        self.descriptions.insert(block_id, description);
     }
 
     /// Finalize the generator and generate a string representation of the job graph in dot format.
@@ -43,6 +103,143 @@ impl JobGraphGenerator {
         )
     }
 
+    /// Finalize the generator and generate a string representation of the job graph as a Mermaid
+    /// flowchart, which can be embedded in Markdown documents.
+    ///
+    /// The diagram has the same blocks, operators and connections as the one in dot format, but
+    /// without the tooltips.
+    pub fn finalize_mermaid(mut self) -> String {
+        self.blocks.sort_keys();
+        let mut lines = vec!["flowchart TD".to_string()];
+        for (&block_id, block) in &self.blocks {
+            let title = mermaid_escape(&self.block_title(block_id, block));
+            lines.push(format!("  subgraph block{block_id} [\"{title}\"]"));
+            for (index, operator) in block.operators.iter().enumerate() {
+                let id = Self::operator_id(block_id, index);
+                let mut label = mermaid_escape(&operator.title);
+                if !operator.subtitle.is_empty() {
+                    label += &format!("<br/>{}", mermaid_escape(&operator.subtitle));
+                }
+                let (open, close) = match operator.kind {
+                    OperatorKind::Operator => ("[", "]"),
+                    OperatorKind::Sink => ("[/", "\\]"),
+                    OperatorKind::Source => ("[\\", "/]"),
+                };
+                lines.push(format!("    {id}{open}\"{label}\"{close}"));
+            }
+            for index in 1..block.operators.len() {
+                let prev = Self::operator_id(block_id, index - 1);
+                let id = Self::operator_id(block_id, index);
+                let typ = mermaid_escape(&block.operators[index - 1].out_type.to_string());
+                lines.push(format!("    {prev} -->|\"{typ}\"| {id}"));
+            }
+            lines.push("  end".to_string());
+        }
+        for connection in self.block_connections() {
+            let arrow = match connection.strategy {
+                ConnectionStrategy::OnlyOne => "-.->",
+                ConnectionStrategy::All => "==>",
+                _ => "-->",
+            };
+            let data_type = mermaid_escape(&connection.data_type.to_string());
+            lines.push(format!(
+                "  {} {arrow}|\"{data_type}<br/>{} ({})\"| {}",
+                Self::operator_id(connection.from_block, connection.from_index),
+                strategy_name(connection.strategy),
+                mermaid_escape(&self.parallelism(connection.from_block, connection.to_block)),
+                Self::operator_id(connection.to_block, connection.to_index),
+            ));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// A hash of the structure of the job graph, which does not depend on the ids of the blocks.
+    ///
+    /// The hash is computed on a canonical form of the graph, built as follows:
+    /// - each block is labelled with the title, the kind and the output type of its operators, in
+    ///   order;
+    /// - the label of each block is refined, once for each block of the graph, with the sorted
+    ///   labels of the blocks it receives from and sends to, together with the type and the
+    ///   strategy of each connection;
+    /// - the canonical form is the sorted list of the final labels of the blocks, followed by the
+    ///   sorted list of the connections, each described by the labels of its ends, its type and
+    ///   its strategy.
+    ///
+    /// The subtitles of the operators, the number of replicas and the batch modes are not part of
+    /// the structure, since they depend on the configuration of the execution.
+    pub fn structural_hash(&self) -> u64 {
+        fn hash(s: &str) -> u64 {
+            wyhash::wyhash(s.as_bytes(), 0)
+        }
+
+        let mut connections = vec![];
+        for (&from, block) in &self.blocks {
+            for operator in &block.operators {
+                for connection in &operator.connections {
+                    let label = format!("{}:{:?}", connection.data_type, connection.strategy);
+                    connections.push((from, connection.to_block_id, label));
+                }
+            }
+        }
+
+        let mut labels: HashMap<BlockId, u64> = self
+            .blocks
+            .iter()
+            .map(|(&block_id, block)| {
+                let operators = block
+                    .operators
+                    .iter()
+                    .map(|op| format!("{}:{:?}:{}", op.title, op.kind, op.out_type))
+                    .collect::<Vec<_>>();
+                (block_id, hash(&operators.join("|")))
+            })
+            .collect();
+        // in a remote execution the blocks of the other hosts may be missing
+        let label_of = |labels: &HashMap<BlockId, u64>, block_id| {
+            labels.get(&block_id).copied().unwrap_or_default()
+        };
+        for _ in 0..self.blocks.len() {
+            labels = labels
+                .iter()
+                .map(|(&block_id, &label)| {
+                    let mut inputs = vec![];
+                    let mut outputs = vec![];
+                    for (from, to, connection) in &connections {
+                        if *to == block_id {
+                            inputs.push(format!("{:016x}>{connection}", label_of(&labels, *from)));
+                        }
+                        if *from == block_id {
+                            outputs.push(format!("{connection}>{:016x}", label_of(&labels, *to)));
+                        }
+                    }
+                    inputs.sort();
+                    outputs.sort();
+                    let refined =
+                        format!("{label:016x}/{}/{}", inputs.join(","), outputs.join(","));
+                    (block_id, hash(&refined))
+                })
+                .collect();
+        }
+
+        let mut blocks = labels
+            .values()
+            .map(|label| format!("{label:016x}"))
+            .collect::<Vec<_>>();
+        blocks.sort();
+        let mut connections = connections
+            .iter()
+            .map(|(from, to, connection)| {
+                format!(
+                    "{:016x}>{connection}>{:016x}",
+                    label_of(&labels, *from),
+                    label_of(&labels, *to)
+                )
+            })
+            .collect::<Vec<_>>();
+        connections.sort();
+        hash(&format!("{}\n{}", blocks.join(","), connections.join(",")))
+    }
+
     /// Each block will have its own `subgraph`, this function will generate the `subgraph`s for all
     /// the blocks in the network.
     fn gen_subgraphs(&self) -> String {
@@ -66,21 +263,36 @@ impl JobGraphGenerator {
             "color=lightgrey".to_string(),
             "labeljust=l".to_string(),
             "edge[fontname=\"monospace\"]".to_string(),
-            format!("label=\"Block {block_id}\""),
+            format!("label=\"{}\"", escape(&self.block_title(block_id, block))),
         ];
         let mut nodes = vec![];
         let mut connections = vec![];
 
         for (index, operator) in block.operators.iter().enumerate() {
             let id = Self::operator_id(block_id, index);
-            let label = format!("{}\\l{}", operator.title, operator.subtitle); // TODO: escape
+            let label = format!(
+                "{}\\l{}",
+                escape(&operator.title),
+                escape(&operator.subtitle)
+            );
             let shape = match operator.kind {
                 OperatorKind::Operator => "box",
                 OperatorKind::Sink => "house",
                 OperatorKind::Source => "invhouse",
             };
             let typ = &operator.out_type;
-            nodes.push(format!("{id} [label=\"{label}\",shape={shape}]"));
+            let mut tooltip = operator.title.clone();
+            if !operator.subtitle.is_empty() {
+                tooltip += &format!(" {}", operator.subtitle);
+            }
+            tooltip += &format!("\nblock {block_id}, output: {typ}");
+            if let Some(description) = self.descriptions.get(&block_id) {
+                tooltip += &format!("\n{description}");
+            }
+            let tooltip = escape(&tooltip);
+            nodes.push(format!(
+                "{id} [label=\"{label}\",shape={shape},tooltip=\"{tooltip}\"]"
+            ));
             if index < block.operators.len() - 1 {
                 let next = Self::operator_id(block_id, index + 1);
                 connections.push(format!(
@@ -108,11 +320,75 @@ impl JobGraphGenerator {
         format!("  subgraph {cluster_id} {{\n{attributes}\n{nodes}\n{connections}\n  }}\n",)
     }
 
-    /// Generate the connections between the operators in different blocks,
-    fn gen_connections(&self) -> String {
+    /// The title of a block: its id, its number of replicas, its batch mode and its labels.
+    fn block_title(&self, block_id: BlockId, block: &BlockStructure) -> String {
+        let batch_mode = block
+            .batch_mode
+            .map(|mode| format!(", batch: {mode}"))
+            .unwrap_or_default();
+        let labels = if block.labels.is_empty() {
+            String::new()
+        } else {
+            let labels = block
+                .labels
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(" [{labels}]")
+        };
+        format!(
+            "Block {block_id} (replicas: {}{batch_mode}){labels}",
+            self.replicas[&block_id]
+        )
+    }
+
+    /// Check that the type of the elements sent by each connection matches the one expected by
+    /// its receiver, returning the connections that do not.
+    ///
+    /// The types are checked by the compiler when the operators are composed, so a mismatch
+    /// points to an operator that reports the wrong types in its structure, and makes the job
+    /// graph misleading. The connections whose receiver is not registered, like the ones to
+    /// another host, are not checked.
+    pub fn validate(&self) -> Vec<TypeMismatch> {
+        let mut receivers: HashMap<(BlockId, BlockId), Vec<DataType>> = HashMap::new();
+        for (&block_id, block) in &self.blocks {
+            for receiver in block.operators.iter().flat_map(|op| &op.receivers) {
+                receivers
+                    .entry((receiver.previous_block_id, block_id))
+                    .or_default()
+                    .push(receiver.data_type.clone());
+            }
+        }
+
+        let mut mismatches = vec![];
+        for (&from_block, block) in &self.blocks {
+            for connection in block.operators.iter().flat_map(|op| &op.connections) {
+                let to_block = connection.to_block_id;
+                let Some(received) = receivers.get(&(from_block, to_block)) else {
+                    continue;
+                };
+                if !received.contains(&connection.data_type) {
+                    mismatches.push(TypeMismatch {
+                        from_block,
+                        to_block,
+                        sent: connection.data_type.clone(),
+                        received: received.clone(),
+                    });
+                }
+            }
+        }
+        mismatches
+    }
+
+    /// Find the connections between the operators in different blocks.
+    ///
+    /// Each connection ends in the operator that registered the receiver for it, or in the first
+    /// operator of the block if there is none.
+    fn block_connections(&self) -> Vec<BlockConnection<'_>> {
         let mut receivers: IndexMap<
             (BlockId, BlockId),
-            (usize, DataType),
+            (usize, &DataType),
             crate::block::CoordHasherBuilder,
         > = Default::default();
         for (&block_id, block) in &self.blocks {
@@ -120,7 +396,7 @@ impl JobGraphGenerator {
                 for receiver in &operator.receivers {
                     receivers.insert(
                         (receiver.previous_block_id, block_id),
-                        (index, receiver.data_type.clone()),
+                        (index, &receiver.data_type),
                     );
                 }
             }
@@ -131,34 +407,62 @@ impl JobGraphGenerator {
             for (from_index, operator) in block.operators.iter().enumerate() {
                 for connection in &operator.connections {
                     let to_block = connection.to_block_id;
-                    let (to_index, data_type) = if let Some((to_index, data_type)) =
-                        receivers.get(&(from_block, to_block))
-                    {
-                        (*to_index, data_type)
-                    } else {
-                        (0, &connection.data_type)
-                    };
-                    let style = match connection.strategy {
-                        ConnectionStrategy::OnlyOne => "dotted",
-                        ConnectionStrategy::Random => "solid",
-                        ConnectionStrategy::GroupBy => "dashed",
-                        ConnectionStrategy::All => "bold",
-                    };
-                    let sublabel = match connection.strategy {
-                        ConnectionStrategy::OnlyOne => "only-one",
-                        ConnectionStrategy::Random => "shuffle",
-                        ConnectionStrategy::GroupBy => "group-by",
-                        ConnectionStrategy::All => "broadcast",
-                    };
-
-                    let from_id = Self::operator_id(from_block, from_index);
-                    let to_id = Self::operator_id(to_block, to_index);
-                    result.push(format!(
-                        "{from_id} -> {to_id} [label=\"{data_type}\\n{sublabel}\",labelfloat=true,style={style}]",
-                    ));
+                    let (to_index, data_type) = receivers
+                        .get(&(from_block, to_block))
+                        .copied()
+                        .unwrap_or((0, &connection.data_type));
+                    result.push(BlockConnection {
+                        from_block,
+                        from_index,
+                        to_block,
+                        to_index,
+                        data_type,
+                        strategy: &connection.strategy,
+                    });
                 }
             }
         }
+        result
+    }
+
+    /// The replicas at the two ends of a connection.
+    fn parallelism(&self, from_block: BlockId, to_block: BlockId) -> String {
+        format!(
+            "{} -> {}",
+            self.replicas[&from_block],
+            self.replicas.get(&to_block).copied().unwrap_or_default()
+        )
+    }
+
+    /// Generate the connections between the operators in different blocks,
+    fn gen_connections(&self) -> String {
+        let mut result = vec![];
+        for connection in self.block_connections() {
+            let BlockConnection {
+                from_block,
+                to_block,
+                data_type,
+                ..
+            } = connection;
+            let style = match connection.strategy {
+                ConnectionStrategy::OnlyOne => "dotted",
+                ConnectionStrategy::Random => "solid",
+                ConnectionStrategy::GroupBy => "dashed",
+                ConnectionStrategy::All => "bold",
+                ConnectionStrategy::Partition => "dashed",
+            };
+            let sublabel = strategy_name(connection.strategy);
+            let parallelism = self.parallelism(from_block, to_block);
+
+            let from_id = Self::operator_id(from_block, connection.from_index);
+            let to_id = Self::operator_id(to_block, connection.to_index);
+            let tooltip = escape(&format!(
+                "block {from_block} -> block {to_block}: {data_type}, {sublabel} ({parallelism})"
+            ));
+            result.push(format!(
+                "{from_id} -> {to_id} [label=\"{data_type}\\n{sublabel} ({parallelism})\",labelfloat=true,style={style},tooltip=\"{tooltip}\"]",
+            ));
+        }
         result
             .into_iter()
             .map(|s| format!("  {s};"))
@@ -171,3 +475,203 @@ impl JobGraphGenerator {
         format!("block{block_id}_operator{index}")
     }
 }
+
+/// A connection between the operators of two blocks, see
+/// [`JobGraphGenerator::block_connections`].
+struct BlockConnection<'a> {
+    from_block: BlockId,
+    from_index: usize,
+    to_block: BlockId,
+    to_index: usize,
+    data_type: &'a DataType,
+    strategy: &'a ConnectionStrategy,
+}
+
+/// The name of a strategy in the labels of the connections.
+fn strategy_name(strategy: &ConnectionStrategy) -> &'static str {
+    match strategy {
+        ConnectionStrategy::OnlyOne => "only-one",
+        ConnectionStrategy::Random => "shuffle",
+        ConnectionStrategy::GroupBy => "group-by",
+        ConnectionStrategy::All => "broadcast",
+        ConnectionStrategy::Partition => "partition",
+    }
+}
+
+/// Escape a string to be used inside a quoted attribute of the dot format.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape a string to be used inside a quoted label of a Mermaid diagram.
+fn mermaid_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("#quot;"),
+            '<' => escaped.push_str("#lt;"),
+            '>' => escaped.push_str("#gt;"),
+            '\n' => escaped.push_str("<br/>"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block::{
+        BatchMode, BlockStructure, Connection, ConnectionStrategy, DataType, JobGraphGenerator,
+        OperatorReceiver, OperatorStructure, TypeMismatch,
+    };
+    use crate::scheduler::BlockId;
+
+    #[test]
+    fn batch_mode_in_block_label() {
+        let mut structure =
+            BlockStructure::default().add_operator(OperatorStructure::new::<u32, _>("End"));
+        structure.batch_mode = Some(BatchMode::fixed(42));
+        let mut generator = JobGraphGenerator::new();
+        generator.add_block(0, structure);
+        generator.add_block(1, BlockStructure::default());
+
+        let graph = generator.finalize();
+        assert!(
+            graph.contains("label=\"Block 0 (replicas: 1, batch: fixed(42))\""),
+            "{graph}"
+        );
+        assert!(graph.contains("label=\"Block 1 (replicas: 1)\""), "{graph}");
+    }
+
+    #[test]
+    fn labels_in_block_label() {
+        let mut structure =
+            BlockStructure::default().add_operator(OperatorStructure::new::<u32, _>("Map"));
+        structure.labels.insert("team".into(), "ads".into());
+        structure
+            .labels
+            .insert("stage".into(), "enrich \"geo\"".into());
+        let mut generator = JobGraphGenerator::new();
+        generator.add_block(0, structure);
+
+        let graph = generator.finalize();
+        assert!(
+            graph.contains("label=\"Block 0 (replicas: 1) [stage=enrich \\\"geo\\\", team=ads]\""),
+            "{graph}"
+        );
+    }
+
+    /// A source block `from` sending to a block `to` that maps and collects the elements.
+    fn two_blocks(from: BlockId, to: BlockId, map: &str) -> JobGraphGenerator {
+        let mut end = OperatorStructure::new::<u32, _>("End");
+        end.connections.push(Connection {
+            to_block_id: to,
+            data_type: DataType::of::<u32>(),
+            strategy: ConnectionStrategy::Random,
+        });
+        let source = BlockStructure::default()
+            .add_operator(OperatorStructure::new::<u32, _>("Source"))
+            .add_operator(end);
+        let mut start = OperatorStructure::new::<u32, _>("Start");
+        start.receivers.push(OperatorReceiver::new::<u32>(from));
+        let sink = BlockStructure::default()
+            .add_operator(start)
+            .add_operator(OperatorStructure::new::<u64, _>(map))
+            .add_operator(OperatorStructure::new::<u64, _>("Collect"));
+        let mut generator = JobGraphGenerator::new();
+        generator.add_block(to, sink);
+        generator.add_block(from, source);
+        generator
+    }
+
+    #[test]
+    fn tooltips_with_escaped_description() {
+        let mut generator = two_blocks(0, 1, "Map");
+        generator.describe_block(1, "Start -> Map<\"a\\b\"> -> Collect".into());
+
+        let graph = generator.finalize();
+        assert!(
+            graph.contains(
+                "block1_operator1 [label=\"Map\\l\",shape=box,tooltip=\"Map\\nblock 1, output: u64\\nStart -> Map<\\\"a\\\\b\\\"> -> Collect\"]"
+            ),
+            "{graph}"
+        );
+        // the blocks without a description have a tooltip too
+        assert!(
+            graph.contains("tooltip=\"Source\\nblock 0, output: u32\""),
+            "{graph}"
+        );
+        assert!(
+            graph.contains("tooltip=\"block 0 -> block 1: u32"),
+            "{graph}"
+        );
+    }
+
+    #[test]
+    fn mermaid_flowchart() {
+        let graph = two_blocks(0, 1, "Map<\"x\">").finalize_mermaid();
+
+        assert!(graph.starts_with("flowchart TD\n"), "{graph}");
+        assert!(
+            graph.contains("  subgraph block0 [\"Block 0 (replicas: 1)\"]"),
+            "{graph}"
+        );
+        assert!(
+            graph.contains("    block1_operator1[\"Map#lt;#quot;x#quot;#gt;\"]"),
+            "{graph}"
+        );
+        assert!(
+            graph.contains("    block1_operator0 -->|\"u32\"| block1_operator1"),
+            "{graph}"
+        );
+        assert!(
+            graph.contains(
+                "  block0_operator1 -->|\"u32<br/>shuffle (1 -#gt; 1)\"| block1_operator0"
+            ),
+            "{graph}"
+        );
+    }
+
+    #[test]
+    fn validate_type_mismatch() {
+        assert_eq!(two_blocks(0, 1, "Map").validate(), vec![]);
+
+        // a start that reports the wrong type for the elements it receives
+        let mut start = OperatorStructure::new::<u64, _>("Start");
+        start.receivers.push(OperatorReceiver::new::<u64>(0));
+        let mut generator = two_blocks(0, 1, "Map");
+        generator.add_block(1, BlockStructure::default().add_operator(start));
+
+        let mismatches = generator.validate();
+        assert_eq!(
+            mismatches,
+            vec![TypeMismatch {
+                from_block: 0,
+                to_block: 1,
+                sent: DataType::of::<u32>(),
+                received: vec![DataType::of::<u64>()],
+            }]
+        );
+        assert_eq!(
+            mismatches[0].to_string(),
+            "block 0 sends u32 to block 1, which expects u64"
+        );
+    }
+
+    #[test]
+    fn structural_hash_ignores_ids() {
+        let hash = two_blocks(0, 1, "Map").structural_hash();
+        assert_eq!(two_blocks(0, 1, "Map").structural_hash(), hash);
+        assert_eq!(two_blocks(7, 3, "Map").structural_hash(), hash);
+        assert_ne!(two_blocks(0, 1, "FlatMap").structural_hash(), hash);
+    }
+}
@@ -1,7 +1,11 @@
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Display, Formatter};
-use std::hash::{Hash, Hasher};
+use std::hash::{BuildHasher, Hash, Hasher};
 use std::sync::Arc;
+use std::time::Duration;
 
+pub use autoscale::Autoscale;
+pub(crate) use autoscale::AutoscaleController;
 pub use batcher::BatchMode;
 pub(crate) use batcher::*;
 pub(crate) use graph_generator::*;
@@ -13,6 +17,7 @@ use crate::operator::Operator;
 use crate::scheduler::BlockId;
 use crate::CoordUInt;
 
+mod autoscale;
 mod batcher;
 mod graph_generator;
 mod next_strategy;
@@ -36,6 +41,20 @@ where
     pub(crate) operators: OperatorChain,
     /// The batch mode of this block.
     pub(crate) batch_mode: BatchMode,
+    /// After how long without messages an input of this block is ignored by the watermarks.
+    pub(crate) watermark_idleness: Option<Duration>,
+    /// How often the start of this block wakes up the operators while its inputs are silent.
+    pub(crate) tick: Option<Duration>,
+    /// The imbalance among the replicas of a group-by after this block that triggers a warning.
+    pub(crate) skew_warning: Option<f64>,
+    /// How long a call of the code of the user in this block may take before a warning.
+    pub(crate) stuck_timeout: Option<Duration>,
+    /// The hash function assigning the keys of a group-by after this block to the replicas.
+    pub(crate) group_by_hasher: GroupByHasher,
+    /// The labels attached to this block by the user.
+    pub(crate) labels: BTreeMap<String, String>,
+    /// Whether the stream of this block may never end, because it comes from an unbounded source.
+    pub(crate) unbounded: bool,
     /// This block may be inside a number of iteration loops, this stack keeps track of the state
     /// lock for each of them.
     pub(crate) iteration_ctx: Vec<Arc<IterationStateLock>>,
@@ -54,6 +73,13 @@ where
             id: self.id,
             operators: self.operators.clone(),
             batch_mode: self.batch_mode,
+            watermark_idleness: self.watermark_idleness,
+            tick: self.tick,
+            skew_warning: self.skew_warning,
+            stuck_timeout: self.stuck_timeout,
+            group_by_hasher: self.group_by_hasher.clone(),
+            labels: self.labels.clone(),
+            unbounded: self.unbounded,
             iteration_ctx: self.iteration_ctx.clone(),
             is_only_one_strategy: self.is_only_one_strategy,
             scheduling: self.scheduling.clone(),
@@ -75,6 +101,13 @@ where
             id: self.id,
             operators: get_operator(self.operators),
             batch_mode: self.batch_mode,
+            watermark_idleness: self.watermark_idleness,
+            tick: self.tick,
+            skew_warning: self.skew_warning,
+            stuck_timeout: self.stuck_timeout,
+            group_by_hasher: self.group_by_hasher,
+            labels: self.labels,
+            unbounded: self.unbounded,
             iteration_ctx: self.iteration_ctx,
             is_only_one_strategy: false,
             scheduling: self.scheduling,
@@ -162,6 +195,13 @@ where
             id,
             operators,
             batch_mode,
+            watermark_idleness: None,
+            tick: None,
+            skew_warning: None,
+            stuck_timeout: None,
+            group_by_hasher: Default::default(),
+            labels: Default::default(),
+            unbounded: false,
             iteration_ctx,
             is_only_one_strategy: false,
             scheduling,
@@ -197,12 +237,82 @@ impl Scheduling {
 }
 
 /// Hashing function for group by operations
-pub fn group_by_hash<T: Hash>(item: &T) -> u64 {
+pub fn group_by_hash<T: Hash + ?Sized>(item: &T) -> u64 {
     let mut hasher = wyhash::WyHash::with_seed(0x0123456789abcdef);
     item.hash(&mut hasher);
     hasher.finish()
 }
 
+/// The hash function used to assign the keys of a group-by to the replicas of the next block.
+///
+/// Set it with [`Stream::group_by_hasher`](crate::Stream::group_by_hasher).
+#[derive(Clone, Default)]
+pub enum GroupByHasher {
+    /// A fast hash with a fixed seed, the same as [`group_by_hash`].
+    #[default]
+    Default,
+    /// SipHash-2-4 with the given keys, which makes the collisions of adversarial keys hard to
+    /// find as long as the keys are secret.
+    SipHash(u64, u64),
+    /// A user-provided hash function, see [`GroupByHasher::custom`].
+    Custom(Arc<dyn Fn() -> Box<dyn Hasher> + Send + Sync>),
+}
+
+impl GroupByHasher {
+    /// Hash the keys with the hashers built by `build_hasher`.
+    ///
+    /// The hash must be the same in all the hosts, so `build_hasher` must not be randomly seeded
+    /// like [`RandomState`](std::collections::hash_map::RandomState).
+    pub fn custom<B>(build_hasher: B) -> Self
+    where
+        B: BuildHasher + Send + Sync + 'static,
+        B::Hasher: 'static,
+    {
+        Self::Custom(Arc::new(move || Box::new(build_hasher.build_hasher())))
+    }
+
+    /// Hash a key.
+    pub fn hash<T: Hash + ?Sized>(&self, item: &T) -> u64 {
+        match self {
+            GroupByHasher::Default => group_by_hash(item),
+            GroupByHasher::SipHash(k0, k1) => {
+                #[allow(deprecated)]
+                let mut hasher = std::hash::SipHasher::new_with_keys(*k0, *k1);
+                item.hash(&mut hasher);
+                hasher.finish()
+            }
+            GroupByHasher::Custom(build_hasher) => {
+                let mut hasher = build_hasher();
+                item.hash(&mut hasher);
+                hasher.finish()
+            }
+        }
+    }
+
+    /// Whether the two hashers assign the keys to the same replicas. Two custom hashers are the
+    /// same only if they come from the same [`GroupByHasher::custom`].
+    pub(crate) fn same_as(&self, other: &GroupByHasher) -> bool {
+        match (self, other) {
+            (GroupByHasher::Default, GroupByHasher::Default) => true,
+            (GroupByHasher::SipHash(a0, a1), GroupByHasher::SipHash(b0, b1)) => {
+                (a0, a1) == (b0, b1)
+            }
+            (GroupByHasher::Custom(a), GroupByHasher::Custom(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Debug for GroupByHasher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GroupByHasher::Default => write!(f, "Default"),
+            GroupByHasher::SipHash(_, _) => write!(f, "SipHash"),
+            GroupByHasher::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
 /// Hasher used for internal hashmaps that have coordinates as keys
 /// (optimized for small keys)
 pub type CoordHasherBuilder = fxhash::FxBuildHasher;
@@ -1,11 +1,15 @@
 use std::hash::Hash;
 use std::marker::PhantomData;
+use std::sync::Arc;
 
 use nanorand::{tls_rng, Rng};
 
 use crate::operator::{ExchangeData, KeyerFn};
 
-use super::group_by_hash;
+use super::{Autoscale, GroupByHasher};
+
+/// Chooses the replica of the next block receiving an element, given the number of replicas.
+pub(crate) type PartitionFn<Out> = Arc<dyn Fn(&Out, usize) -> usize + Send + Sync>;
 
 /// The next strategy used at the end of a block.
 ///
@@ -28,6 +32,12 @@ where
     GroupBy(IndexFn, PhantomData<Out>),
     /// Every following replica will receive every message.
     All,
+    /// A random replica among the active ones will receive the message, the number of active
+    /// replicas is adjusted at runtime based on the observed backpressure.
+    Adaptive(Autoscale),
+    /// The replica is chosen by a user-provided function, given the message and the number of
+    /// replicas of the next block.
+    Partition(PartitionFn<Out>),
 }
 
 impl<Out, IndexFn> std::fmt::Debug for NextStrategy<Out, IndexFn>
@@ -40,6 +50,10 @@ where
             Self::Random => write!(f, "Random"),
             Self::GroupBy(_, _) => write!(f, "GroupBy"),
             Self::All => write!(f, "All"),
+            Self::Adaptive(autoscale) => {
+                write!(f, "Adaptive({}..={})", autoscale.min, autoscale.max)
+            }
+            Self::Partition(_) => write!(f, "Partition"),
         }
     }
 }
@@ -54,20 +68,23 @@ where
             Self::Random => Self::Random,
             Self::GroupBy(idx, _) => Self::GroupBy(idx.clone(), PhantomData),
             Self::All => Self::All,
+            Self::Adaptive(autoscale) => Self::Adaptive(*autoscale),
+            Self::Partition(partitioner) => Self::Partition(partitioner.clone()),
         }
     }
 }
 
 impl<Out: ExchangeData> NextStrategy<Out> {
-    /// Build a `NextStrategy` from a keyer function.
+    /// Build a `NextStrategy` from a keyer function, hashing the keys with `hasher`.
     pub(crate) fn group_by<Key: Hash, Keyer>(
         keyer: Keyer,
+        hasher: GroupByHasher,
     ) -> NextStrategy<Out, impl KeyerFn<u64, Out>>
     where
         Keyer: KeyerFn<Key, Out>,
     {
         NextStrategy::GroupBy(
-            move |item: &Out| group_by_hash(&keyer(item)),
+            move |item: &Out| hasher.hash(&keyer(item)),
             Default::default(),
         )
     }
@@ -86,6 +103,19 @@ impl<Out: ExchangeData> NextStrategy<Out> {
     pub(crate) fn random() -> NextStrategy<Out> {
         NextStrategy::Random
     }
+
+    /// Returns `NextStrategy::Adaptive` with default `IndexFn`.
+    pub(crate) fn adaptive(autoscale: Autoscale) -> NextStrategy<Out> {
+        NextStrategy::Adaptive(autoscale)
+    }
+
+    /// Returns `NextStrategy::Partition` with default `IndexFn`.
+    pub(crate) fn partition<F>(partitioner: F) -> NextStrategy<Out>
+    where
+        F: Fn(&Out, usize) -> usize + Send + Sync + 'static,
+    {
+        NextStrategy::Partition(Arc::new(partitioner))
+    }
 }
 
 impl<Out: ExchangeData, IndexFn> NextStrategy<Out, IndexFn>
@@ -93,10 +123,13 @@ where
     IndexFn: KeyerFn<u64, Out>,
 {
     /// Compute the index of the replica which this message should be forwarded to.
+    ///
+    /// With `Partition` the index depends on the number of replicas of the next block, so it is
+    /// computed by the sender of each block instead.
     pub fn index(&self, message: &Out) -> usize {
         match self {
-            NextStrategy::OnlyOne | NextStrategy::All => 0,
-            NextStrategy::Random => tls_rng().generate(),
+            NextStrategy::OnlyOne | NextStrategy::All | NextStrategy::Partition(_) => 0,
+            NextStrategy::Random | NextStrategy::Adaptive(_) => tls_rng().generate(),
             NextStrategy::GroupBy(keyer, _) => keyer(message) as usize,
         }
     }
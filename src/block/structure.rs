@@ -1,10 +1,11 @@
 //! Types that describe the structure of an execution graph. For debugging purposes
 
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 
 use serde::{Deserialize, Serialize};
 
-use crate::block::NextStrategy;
+use crate::block::{BatchMode, NextStrategy};
 use crate::operator::{ExchangeData, KeyerFn};
 use crate::scheduler::BlockId;
 
@@ -25,6 +26,12 @@ pub struct BlockStructure {
     /// The first in the list is the start of the block, while the last is the operator that ends
     /// the block.
     pub operators: Vec<OperatorStructure>,
+    /// The batch mode used for sending the elements to the next blocks, if the block sends any.
+    #[serde(default)]
+    pub batch_mode: Option<BatchMode>,
+    /// The labels attached to the block by the user, see [`Stream::label`](crate::Stream::label).
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
 }
 
 /// The structural information about an operator.
@@ -45,6 +52,12 @@ pub struct OperatorStructure {
     /// This does not count the connection to the next operator in the block: that connection is
     /// added automatically.
     pub connections: Vec<Connection>,
+    /// The strategy used to send the elements to the next blocks, if this operator ends the
+    /// block.
+    ///
+    /// Unlike the `connections`, this is known before the execution starts.
+    #[serde(default)]
+    pub next_strategy: Option<ConnectionStrategy>,
     /// The type of the data that comes out of this operator.
     pub out_type: DataType,
 }
@@ -101,6 +114,8 @@ pub enum ConnectionStrategy {
     GroupBy,
     /// All the replicas receive all the elements of the stream.
     All,
+    /// A user-provided function chooses the replica.
+    Partition,
 }
 
 impl DataType {
@@ -163,6 +178,7 @@ impl OperatorStructure {
             kind: OperatorKind::Operator,
             receivers: Default::default(),
             connections: Default::default(),
+            next_strategy: None,
             out_type: DataType::of::<Out>(),
         }
     }
@@ -201,9 +217,10 @@ where
     fn from(strategy: &NextStrategy<Out, IndexFn>) -> Self {
         match strategy {
             NextStrategy::OnlyOne => ConnectionStrategy::OnlyOne,
-            NextStrategy::Random => ConnectionStrategy::Random,
+            NextStrategy::Random | NextStrategy::Adaptive(_) => ConnectionStrategy::Random,
             NextStrategy::GroupBy(_, _) => ConnectionStrategy::GroupBy,
             NextStrategy::All => ConnectionStrategy::All,
+            NextStrategy::Partition(_) => ConnectionStrategy::Partition,
         }
     }
 }
@@ -1,16 +1,33 @@
 //! Wrapper to in-memory channels.
 //!
-//! This module exists to ease the transition between channel libraries.
+//! This module exists to ease the transition between channel libraries. The channels are
+//! implemented with `flume` by default, or with `crossbeam-channel` when the `channel-crossbeam`
+//! feature is enabled.
 #![allow(dead_code)]
 
 use std::time::Duration;
 
+#[cfg(feature = "channel-crossbeam")]
+use crossbeam_channel::{
+    bounded as bounded_ext, unbounded as unbounded_ext, Receiver as ReceiverExt,
+    RecvError as ExtRecvError, RecvTimeoutError as ExtRecvTimeoutError, SendError as SendErrorExt,
+    Sender as SenderExt, TryRecvError as ExtTryRecvError,
+};
+#[cfg(not(feature = "channel-crossbeam"))]
 use flume::{
     bounded as bounded_ext, unbounded as unbounded_ext, Receiver as ReceiverExt,
     RecvError as ExtRecvError, RecvTimeoutError as ExtRecvTimeoutError, SendError as SendErrorExt,
     Sender as SenderExt, TryRecvError as ExtTryRecvError,
 };
 
+/// How many times [`Receiver::recv_async`] yields to the other tasks before sleeping, with
+/// `crossbeam-channel`.
+#[cfg(all(feature = "channel-crossbeam", feature = "tokio"))]
+const ASYNC_SPINS: usize = 16;
+/// How long [`Receiver::recv_async`] sleeps between the attempts, with `crossbeam-channel`.
+#[cfg(all(feature = "channel-crossbeam", feature = "tokio"))]
+const ASYNC_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
 pub trait ChannelItem: Send + 'static {}
 impl<T: Send + 'static> ChannelItem for T {}
 
@@ -78,6 +95,25 @@ pub enum SelectResult<In1, In2> {
     B(Result<In2, RecvError>),
 }
 
+/// Bound on the starvation of the other receiver of
+/// [`Receiver::select_priority`](Receiver::select_priority).
+///
+/// After `max` consecutive messages taken from the preferred receiver, the next select takes a
+/// message from the other receiver if it has one ready.
+#[derive(Debug, Clone)]
+pub struct PriorityBudget {
+    max: usize,
+    used: usize,
+}
+
+impl PriorityBudget {
+    /// Allow at most `max` consecutive messages from the preferred receiver.
+    pub fn new(max: usize) -> Self {
+        Self { max, used: 0 }
+    }
+}
+
+#[cfg(not(feature = "channel-crossbeam"))]
 #[macro_use]
 mod select_impl {
     macro_rules! select_impl {
@@ -100,6 +136,29 @@ mod select_impl {
     }
 }
 
+#[cfg(feature = "channel-crossbeam")]
+#[macro_use]
+mod select_impl {
+    macro_rules! select_impl {
+        ($self:expr, $other:expr) => {{
+            crossbeam_channel::select! {
+                recv($self.0) -> el => SelectResult::A(el.map_err(RecvError::from)),
+                recv($other.0) -> el => SelectResult::B(el.map_err(RecvError::from)),
+            }
+        }};
+    }
+
+    macro_rules! select_timeout_impl {
+        ($self:expr, $other:expr, $timeout:expr) => {
+            crossbeam_channel::select! {
+                recv($self.0) -> el => Ok(SelectResult::A(el.map_err(RecvError::from))),
+                recv($other.0) -> el => Ok(SelectResult::B(el.map_err(RecvError::from))),
+                default($timeout) => Err(RecvTimeoutError::Timeout),
+            }
+        };
+    }
+}
+
 /// A wrapper on a bounded channel sender.
 #[derive(Debug, Clone)]
 pub struct Sender<T: ChannelItem>(SenderExt<T>);
@@ -113,6 +172,12 @@ impl<T: ChannelItem> Sender<T> {
     pub fn send(&self, item: T) -> Result<(), SendError<T>> {
         self.0.send(item)
     }
+
+    /// The number of messages the channel can hold, `None` if it is unbounded.
+    #[inline]
+    pub fn capacity(&self) -> Option<usize> {
+        self.0.capacity()
+    }
 }
 
 impl<T: ChannelItem> Receiver<T> {
@@ -128,12 +193,35 @@ impl<T: ChannelItem> Receiver<T> {
         self.0.try_recv().map_err(TryRecvError::from)
     }
 
+    /// Wait asynchronously until a message is present in the channel and return it when ready.
+    #[cfg(not(feature = "channel-crossbeam"))]
     #[inline]
-
     pub async fn recv_async(&self) -> Result<T, RecvError> {
         self.0.recv_async().await.map_err(RecvError::from)
     }
 
+    /// Wait asynchronously until a message is present in the channel and return it when ready.
+    ///
+    /// `crossbeam-channel` cannot be awaited, so this polls the channel without blocking the
+    /// thread: it yields to the other tasks a few times, then sleeps for a millisecond between the
+    /// attempts. It works on any runtime, at the cost of up to a millisecond of latency when the
+    /// channel stays empty.
+    #[cfg(all(feature = "channel-crossbeam", feature = "tokio"))]
+    pub async fn recv_async(&self) -> Result<T, RecvError> {
+        let mut attempts = 0;
+        loop {
+            match self.0.try_recv() {
+                Ok(item) => return Ok(item),
+                Err(ExtTryRecvError::Disconnected) => return Err(RecvError::Disconnected),
+                Err(ExtTryRecvError::Empty) if attempts < ASYNC_SPINS => {
+                    attempts += 1;
+                    tokio::task::yield_now().await;
+                }
+                Err(ExtTryRecvError::Empty) => tokio::time::sleep(ASYNC_POLL_INTERVAL).await,
+            }
+        }
+    }
+
     /// Block until a message is present in the channel and return it when ready.
     ///
     /// If the timeout expires an error is returned.
@@ -161,6 +249,31 @@ impl<T: ChannelItem> Receiver<T> {
     ) -> Result<SelectResult<T, T2>, RecvTimeoutError> {
         select_timeout_impl!(self, other, timeout)
     }
+
+    /// Like `select`, but preferring this receiver: a message from `other` is returned only when
+    /// this receiver has none ready, or when the priority `budget` is exhausted.
+    pub fn select_priority<T2: ChannelItem>(
+        &self,
+        other: &Receiver<T2>,
+        budget: &mut PriorityBudget,
+    ) -> SelectResult<T, T2> {
+        if budget.used >= budget.max {
+            if let Ok(item) = other.0.try_recv() {
+                budget.used = 0;
+                return SelectResult::B(Ok(item));
+            }
+        }
+        let res = match self.0.try_recv() {
+            Ok(item) => SelectResult::A(Ok(item)),
+            Err(ExtTryRecvError::Disconnected) => SelectResult::A(Err(RecvError::Disconnected)),
+            Err(ExtTryRecvError::Empty) => self.select(other),
+        };
+        match res {
+            SelectResult::A(_) => budget.used += 1,
+            SelectResult::B(_) => budget.used = 0,
+        }
+        res
+    }
 }
 
 /// A wrapper on an unbounded channel sender.
@@ -220,7 +333,9 @@ mod tests {
 
     use itertools::Itertools;
 
-    use crate::channel::{bounded, SelectResult};
+    use crate::channel::{bounded, PriorityBudget, SelectResult};
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
 
     const TEST_CAPACITY: usize = 10;
 
@@ -345,4 +460,104 @@ mod tests {
         // if more than 5% of the tries failed, this test fails
         assert!(100 * failures < 5 * tries);
     }
+
+    #[test]
+    fn test_select_priority() {
+        let (sender1, receiver1) = bounded(1000);
+        let (sender2, receiver2) = bounded(1000);
+        let mut budget = PriorityBudget::new(3);
+
+        for _ in 0..20 {
+            sender1.send(1).unwrap();
+            sender2.send(2).unwrap();
+        }
+
+        // both are always ready: the preferred receiver is served 3 times for each message of the
+        // other one
+        let order = (0..8)
+            .map(
+                |_| match receiver1.select_priority(&receiver2, &mut budget) {
+                    SelectResult::A(Ok(n)) | SelectResult::B(Ok(n)) => n,
+                    _ => panic!("unexpected disconnection"),
+                },
+            )
+            .collect_vec();
+        assert_eq!(order, [1, 1, 1, 2, 1, 1, 1, 2]);
+
+        // without messages on the preferred receiver the other one is served
+        let (_sender3, receiver3) = bounded::<i32>(1);
+        let mut budget = PriorityBudget::new(3);
+        assert_eq!(
+            receiver3.select_priority(&receiver2, &mut budget),
+            SelectResult::B(Ok(2))
+        );
+    }
+
+    /// A pipeline exchanging elements between blocks, through the channels of the backend
+    /// selected by the features: the result must not depend on it.
+    #[test]
+    fn pipeline_same_on_every_backend() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env
+            .stream_par_iter(0..10_000u64)
+            .shuffle()
+            .group_by_sum(|n| n % 7, |n| n)
+            .unkey()
+            .collect_vec();
+        let (state, items) = env.stream_par_iter(0..100u64).iterate(
+            5,
+            0u64,
+            |s, state| s.map(move |n| n + *state.get()),
+            |delta: &mut u64, n| *delta += n,
+            |state, delta| *state += delta,
+            |_| true,
+        );
+        let state = state.collect_vec();
+        let items = items.collect_vec();
+        env.execute_blocking();
+
+        let expected = (0..7)
+            .map(|k| (k, (0..10_000).filter(|n| n % 7 == k).sum()))
+            .collect_vec();
+        assert_eq!(
+            res.get().unwrap().into_iter().sorted().collect_vec(),
+            expected
+        );
+        // each iteration adds the sum of the items of the previous one to the state
+        let (mut state_expected, mut items_expected) = (0, (0..100u64).collect_vec());
+        for _ in 0..5 {
+            items_expected = items_expected.iter().map(|n| n + state_expected).collect();
+            state_expected += items_expected.iter().sum::<u64>();
+        }
+        assert_eq!(state.get().unwrap(), vec![state_expected]);
+        assert_eq!(
+            items.get().unwrap().into_iter().sorted().collect_vec(),
+            items_expected
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn recv_async_current_thread() {
+        let (sender, receiver) = bounded(TEST_CAPACITY);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        runtime.block_on(async move {
+            // the message is sent after the receiver started waiting
+            let send = async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                sender.send(123).unwrap();
+                drop(sender);
+            };
+            let recv = async {
+                let first = receiver.recv_async().await;
+                (first, receiver.recv_async().await)
+            };
+            let ((), (first, second)) = tokio::join!(send, recv);
+            assert_eq!(first, Ok(123));
+            assert!(second.is_err());
+        });
+    }
 }
@@ -4,6 +4,7 @@
 
 use std::env;
 use std::fmt::{Display, Formatter};
+use std::net::ToSocketAddrs;
 use std::path::Path;
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -12,6 +13,7 @@ use std::str::FromStr;
 use clap::Parser;
 use serde::{Deserialize, Serialize};
 
+use crate::network::{MessageLog, ReplayLog};
 use crate::runner::spawn_remote_workers;
 use crate::scheduler::HostId;
 use crate::CoordUInt;
@@ -75,7 +77,7 @@ pub const CONFIG_ENV_VAR: &str = "NOIR_CONFIG";
 /// let (config, args) = RuntimeConfig::from_args();
 /// let env = StreamContext::new(config);
 /// ```
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum RuntimeConfig {
     /// Use only local threads.
     Local(LocalConfig),
@@ -108,10 +110,158 @@ pub struct LocalConfig {
     ///
     /// A thread will be spawned for each core, for each block in the job graph.
     pub parallelism: CoordUInt,
+    /// Pin the threads of each replica to a CPU core.
+    ///
+    /// The threads of the `i`-th replica of every block are pinned to the `i`-th core (modulo the
+    /// number of cores). If the platform does not support setting the thread affinity, the threads
+    /// are not pinned.
+    pub pin_threads: bool,
+    /// The identifiers of the CPU cores to pin the threads to, by default all the cores available
+    /// to the process are used.
+    pub core_ids: Option<Vec<usize>>,
+    /// If set, all the messages sent between the replicas are recorded in this log.
+    pub message_log: Option<MessageLog>,
+    /// If set, the order of the messages received by the replicas is recorded, or replayed from
+    /// a previous execution, see [`ReplayLog`].
+    pub replay_log: Option<ReplayLog>,
+    /// The size in bytes of the stack of the threads running the replicas, by default the
+    /// platform default is used.
+    ///
+    /// Increase it for operators with deep recursion or large buffers allocated on the stack,
+    /// which would overflow the default stack.
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// let mut config = RuntimeConfig::local(2).unwrap();
+    /// if let RuntimeConfig::Local(local) = &mut config {
+    ///     local.thread_stack_size = Some(64 << 20);
+    /// }
+    /// let env = StreamContext::new(config);
+    /// ```
+    pub thread_stack_size: Option<usize>,
+    /// The number of batches the channel between two replicas can hold before the sender blocks,
+    /// by default 16.
+    ///
+    /// A larger capacity absorbs the bursts of the senders at the cost of memory.
+    pub channel_capacity: Option<usize>,
+}
+
+impl LocalConfig {
+    /// The core the threads of the replica with the given index should be pinned to, if any.
+    pub(crate) fn core_for_replica(&self, replica_id: CoordUInt) -> Option<core_affinity::CoreId> {
+        if !self.pin_threads {
+            return None;
+        }
+        let cores = match &self.core_ids {
+            Some(ids) => ids.iter().map(|&id| core_affinity::CoreId { id }).collect(),
+            None => core_affinity::get_core_ids().unwrap_or_default(),
+        };
+        if cores.is_empty() {
+            log::warn!("cannot pin the worker threads: the cpu cores cannot be listed");
+            return None;
+        }
+        Some(cores[replica_id as usize % cores.len()])
+    }
+}
+
+/// Builder of the configuration of a local environment, see [`LocalConfig`].
+///
+/// All the options default to the behavior of [`RuntimeConfig::local`].
+///
+/// ```
+/// # use renoir::{StreamContext, RuntimeConfig};
+/// # use renoir::config::LocalConfigBuilder;
+/// let config = LocalConfigBuilder::new(4)
+///     .thread_stack_size(64 << 20)
+///     .channel_capacity(64)
+///     .build()
+///     .unwrap();
+/// let env = StreamContext::new(config);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LocalConfigBuilder {
+    config: LocalConfig,
+}
+
+impl LocalConfigBuilder {
+    /// Start the configuration of a local environment with `parallelism` cores.
+    pub fn new(parallelism: CoordUInt) -> Self {
+        Self {
+            config: LocalConfig {
+                parallelism,
+                pin_threads: false,
+                core_ids: None,
+                message_log: None,
+                replay_log: None,
+                thread_stack_size: None,
+                channel_capacity: None,
+            },
+        }
+    }
+
+    /// Pin the threads of each replica to a CPU core, see [`LocalConfig::pin_threads`].
+    ///
+    /// The threads are pinned to the cores in `core_ids`, or to all the cores available to the
+    /// process if `None`.
+    pub fn pin_threads(&mut self, core_ids: Option<Vec<usize>>) -> &mut Self {
+        self.config.pin_threads = true;
+        self.config.core_ids = core_ids;
+        self
+    }
+
+    /// Record all the messages sent between the replicas, see [`LocalConfig::message_log`].
+    pub fn message_log(&mut self, log: MessageLog) -> &mut Self {
+        self.config.message_log = Some(log);
+        self
+    }
+
+    /// Record or replay the order of the messages received, see [`LocalConfig::replay_log`].
+    pub fn replay_log(&mut self, log: ReplayLog) -> &mut Self {
+        self.config.replay_log = Some(log);
+        self
+    }
+
+    /// Set the size of the stack of the threads, see [`LocalConfig::thread_stack_size`].
+    pub fn thread_stack_size(&mut self, thread_stack_size: usize) -> &mut Self {
+        self.config.thread_stack_size = Some(thread_stack_size);
+        self
+    }
+
+    /// Set the capacity of the channels, see [`LocalConfig::channel_capacity`].
+    pub fn channel_capacity(&mut self, channel_capacity: usize) -> &mut Self {
+        self.config.channel_capacity = Some(channel_capacity);
+        self
+    }
+
+    /// Build the configuration, checking that the options are valid.
+    pub fn build(&self) -> Result<RuntimeConfig, ConfigError> {
+        let config = &self.config;
+        if config.parallelism == 0 {
+            return Err(ConfigError::Invalid(
+                "The number of cores should be positive".into(),
+            ));
+        }
+        if matches!(&config.core_ids, Some(ids) if ids.is_empty()) {
+            return Err(ConfigError::Invalid(
+                "The list of cores to pin the threads to should not be empty".into(),
+            ));
+        }
+        if config.thread_stack_size == Some(0) {
+            return Err(ConfigError::Invalid(
+                "The stack size of the threads should be positive".into(),
+            ));
+        }
+        if config.channel_capacity == Some(0) {
+            return Err(ConfigError::Invalid(
+                "The capacity of the channels should be positive".into(),
+            ));
+        }
+        Ok(RuntimeConfig::Local(config.clone()))
+    }
 }
 
 /// This environment uses local threads and remote hosts.
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct RemoteConfig {
     /// The identifier for this host.
     #[serde(skip)]
@@ -124,15 +274,88 @@ pub struct RemoteConfig {
     /// Remove remote binaries after execution
     #[serde(default)]
     pub cleanup_executable: bool,
+    /// Write the logs of each worker to `renoir-worker-<host_id>.log` inside `tracing_dir`, one
+    /// JSON object per line tagged with `host_id` and, for the worker threads, with the `coord`,
+    /// `block_id` and `replica_id` of their replica.
+    #[serde(default)]
+    pub worker_logs: bool,
+    /// What to do with the messages received from the other hosts that cannot be decoded.
+    #[serde(default)]
+    pub on_malformed_message: MalformedMessagePolicy,
+    /// The maximum number of batches queued for each connection to another host while waiting to
+    /// be written to the socket.
+    ///
+    /// The senders block when the queue is full. On links with a high latency a larger queue keeps
+    /// more data in flight and improves the throughput, while a smaller one bounds the memory used
+    /// by the batches waiting to be sent.
+    #[serde(default = "default_max_inflight_batches")]
+    pub max_inflight_batches: usize,
+    /// The command used to start the workers on the remote hosts, replacing the default one that
+    /// just executes the binary. This allows running the workers inside a container or under a
+    /// wrapper script, e.g. `docker run -e NOIR_HOST_ID -e NOIR_CONFIG -v {bin}:{bin} image {bin}`.
+    ///
+    /// The template can contain these placeholders:
+    /// - `{bin}`: the path of the executable copied to the remote host;
+    /// - `{args}`: the command line arguments of the current process, escaped for the shell;
+    /// - `{host_id}`: the id of the remote host;
+    /// - `{config}`: this configuration serialized as TOML, escaped for the shell.
+    ///
+    /// The worker reads its host id and configuration from the environment variables
+    /// [`HOST_ID_ENV_VAR`] and [`CONFIG_ENV_VAR`], which are still exported before running the
+    /// command. When the template uses the `{host_id}` or `{config}` placeholder the
+    /// corresponding variable is not exported, and the template has to set it for the worker
+    /// (e.g. `docker run -e NOIR_HOST_ID={host_id} ...`). The `perf_path` of the hosts is
+    /// ignored when the template is set.
+    #[serde(default)]
+    pub worker_command_template: Option<String>,
+    /// How long, in seconds, a host keeps trying to connect to another host before giving up.
+    ///
+    /// The hosts of a cluster may start at different times, so a connection refused because the
+    /// other host is not listening yet is retried with an exponential backoff until this deadline
+    /// expires. Then the job fails with an error naming the unreachable host.
+    #[serde(default = "default_connect_deadline_secs")]
+    pub connect_deadline_secs: u64,
+    /// The maximum number of bytes of a message written in a single frame to the connection to
+    /// another host, not counting the header of the frame.
+    ///
+    /// The messages larger than this are split into many frames, and reassembled by the receiving
+    /// host. The frames of the messages for different replicas are interleaved, so a batch with a
+    /// huge element does not hold back the batches for the other replicas sharing the same
+    /// connection until it is fully written. By default the messages are never split.
+    #[serde(default)]
+    pub max_frame_size: Option<usize>,
+}
+
+/// What a host does with a message received from another host that cannot be decoded, because it
+/// is corrupted or it was encoded by a different version of the job.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MalformedMessagePolicy {
+    /// Panic, stopping the job.
+    #[default]
+    Fail,
+    /// Log and drop the message, and keep reading the following messages of the connection.
+    SkipMessage,
+    /// Log and close the connection, as if the other host disconnected: the messages it would
+    /// have sent after the malformed one are lost.
+    SkipConnection,
 }
 
 /// The configuration of a single remote host.
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct HostConfig {
     /// The IP address or domain name to use for connecting to this remote host.
     ///
     /// This must be reachable from all the hosts in the cluster.
     pub address: String,
+    /// The IP address of the network interface this host listens to for inter-host
+    /// communication, defaulted to `address`.
+    ///
+    /// On hosts with multiple networks this selects the one used for exchanging the data (e.g. a
+    /// high-speed interconnect), while the other hosts still connect to `address`. Use `0.0.0.0`
+    /// to listen on all the interfaces.
+    #[serde(default)]
+    pub bind_address: Option<String>,
     /// The first port to use for inter-host communication.
     ///
     /// This port and the following ones will be bound by the host, one for each connection between
@@ -142,6 +365,13 @@ pub struct HostConfig {
     ///
     /// This is the same as `LocalRuntimeConfig::num_cores`.
     pub num_cores: CoordUInt,
+    /// The relative computing power of the host, defaulted to `num_cores`.
+    ///
+    /// The elements shuffled randomly between the hosts are distributed proportionally to the
+    /// weights of the hosts, so that the more powerful hosts of a heterogeneous cluster receive
+    /// more work.
+    #[serde(default)]
+    pub weight: Option<f64>,
     /// The configuration to use to connect via SSH to the remote host.
     #[serde(default)]
     pub ssh: SSHConfig,
@@ -167,6 +397,29 @@ pub struct SSHConfig {
     pub key_file: Option<PathBuf>,
     /// The passphrase for decrypting the private SSH key.
     pub key_passphrase: Option<String>,
+    /// How the key of the remote host is verified.
+    #[serde(default)]
+    pub host_key_check: HostKeyCheck,
+    /// The known hosts file used to verify the key of the remote host, defaulted to
+    /// `~/.ssh/known_hosts`.
+    pub known_hosts: Option<PathBuf>,
+}
+
+/// How the key of a remote host is verified when connecting via SSH.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HostKeyCheck {
+    /// The key of the host must be in the known hosts file, the connection fails otherwise.
+    Strict,
+    /// The key of a host not in the known hosts file is added to it, trusting it on the first
+    /// connection. The connection fails if the host is known with a different key.
+    #[default]
+    AcceptNew,
+    /// Any key is accepted without checking the known hosts file.
+    ///
+    /// **Warning**: this is insecure, anyone who can intercept the connection can impersonate the
+    /// host and read the credentials and the configuration sent to it.
+    Accept,
 }
 
 impl std::fmt::Debug for SSHConfig {
@@ -187,11 +440,40 @@ impl std::fmt::Debug for SSHConfig {
         if self.key_passphrase.is_some() {
             d.field("key_passphrase", &"REDACTED");
         }
+        if self.host_key_check != HostKeyCheck::AcceptNew {
+            d.field("host_key_check", &self.host_key_check);
+        }
+        if let Some(known_hosts) = &self.known_hosts {
+            d.field("known_hosts", &known_hosts);
+        }
 
         d.finish()
     }
 }
 
+/// The credentials of a remote host, read from a secrets file separate from the configuration.
+#[derive(Clone, Deserialize, Derivative)]
+#[derivative(Debug)]
+#[serde(deny_unknown_fields)]
+struct HostSecrets {
+    /// The address of the host these credentials belong to.
+    address: String,
+    /// The base port of the host, to tell apart the hosts with the same address.
+    base_port: Option<u16>,
+    #[derivative(Debug = "ignore")]
+    password: Option<String>,
+    #[derivative(Debug = "ignore")]
+    key_passphrase: Option<String>,
+}
+
+/// The content of a secrets file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Secrets {
+    #[serde(rename = "host", default)]
+    hosts: Vec<HostSecrets>,
+}
+
 #[cfg(feature = "clap")]
 #[derive(Debug, Parser)]
 #[clap(
@@ -212,6 +494,10 @@ pub struct CommandLineOptions {
     #[clap(short, long)]
     local: Option<CoordUInt>,
 
+    /// Pin the threads of the local execution to the CPU cores.
+    #[clap(long, requires = "local")]
+    pin_threads: bool,
+
     /// The rest of the arguments.
     args: Vec<String>,
 }
@@ -227,7 +513,12 @@ impl RuntimeConfig {
         args.insert(0, env::args().next().unwrap());
 
         if let Some(parallelism) = opt.local {
-            (Self::local(parallelism).expect("Configuration error"), args)
+            let config = if opt.pin_threads {
+                Self::local_pinned(parallelism, None)
+            } else {
+                Self::local(parallelism)
+            };
+            (config.expect("Configuration error"), args)
         } else if let Some(remote) = opt.remote {
             (Self::remote(remote).expect("Configuration error"), args)
         } else {
@@ -240,6 +531,50 @@ impl RuntimeConfig {
         ConfigBuilder::new_local(parallelism)
     }
 
+    /// Local environment like [`RuntimeConfig::local`], with the threads of each replica pinned
+    /// to a CPU core.
+    ///
+    /// The threads are pinned to the cores in `core_ids`, or to all the cores available to the
+    /// process if `None`. See [`LocalConfig::pin_threads`].
+    pub fn local_pinned(
+        parallelism: CoordUInt,
+        core_ids: Option<Vec<usize>>,
+    ) -> Result<RuntimeConfig, ConfigError> {
+        ConfigBuilder::new_local_pinned(parallelism, core_ids)
+    }
+
+    /// Local environment like [`RuntimeConfig::local`], recording all the messages sent between
+    /// the replicas in the returned [`MessageLog`].
+    ///
+    /// This is meant for testing: the log tells exactly which elements crossed each connection,
+    /// and how they were batched. See [`LocalConfig::message_log`].
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// let (config, log) = RuntimeConfig::local_recorded(2).unwrap();
+    /// let env = StreamContext::new(config);
+    /// let res = env.stream_iter(0..10u32).shuffle().collect_vec();
+    /// env.execute_blocking();
+    ///
+    /// let items = log
+    ///     .messages::<u32>()
+    ///     .iter()
+    ///     .flat_map(|m| m.elements.iter().filter_map(|el| el.value()))
+    ///     .count();
+    /// // the items are sent once by the source and once more to the collecting replica
+    /// assert_eq!(items, 20);
+    /// ```
+    pub fn local_recorded(
+        parallelism: CoordUInt,
+    ) -> Result<(RuntimeConfig, MessageLog), ConfigError> {
+        let mut config = ConfigBuilder::new_local(parallelism)?;
+        let log = MessageLog::default();
+        if let RuntimeConfig::Local(local) = &mut config {
+            local.message_log = Some(log.clone());
+        }
+        Ok((config, log))
+    }
+
     /// Remote environment based on the provided configuration file.
     ///
     /// The behaviour of this changes if this process is the "runner" process (ie the one that will
@@ -247,14 +582,92 @@ impl RuntimeConfig {
     /// If it's the runner, the configuration file is read. If it's a worker, the configuration is
     /// read directly from the environment variable and not from the file (remote hosts may not have
     /// the configuration file).
+    ///
+    /// If a secrets file is found next to the configuration file, with the same name and the
+    /// `.secrets.toml` extension (e.g. `config.secrets.toml` for `config.toml`), the credentials
+    /// of the hosts are read from it, see [`RuntimeConfig::remote_with_secrets`].
     pub fn remote<P: AsRef<Path>>(toml_path: P) -> Result<RuntimeConfig, ConfigError> {
+        let secrets_path = toml_path.as_ref().with_extension("secrets.toml");
+        let secrets_path = secrets_path.is_file().then_some(secrets_path);
+        Self::remote_inner(toml_path.as_ref(), secrets_path.as_deref())
+    }
+
+    /// Remote environment based on the provided configuration file, with the credentials of the
+    /// hosts read from a separate secrets file.
+    ///
+    /// The secrets file contains only the sensitive fields of the [`SSHConfig`] of the hosts, so
+    /// that the configuration file can be committed without them. Each `[[host]]` of the secrets
+    /// file is merged into the hosts of the configuration with the same `address` (and
+    /// `base_port`, if specified), see [`ConfigBuilder::parse_secrets_str`].
+    ///
+    /// ```
+    /// # use renoir::RuntimeConfig;
+    /// # let dir = std::env::temp_dir().join("renoir-remote-with-secrets-doc");
+    /// # std::fs::create_dir_all(&dir).unwrap();
+    /// let config = "[[host]]\naddress = \"host1\"\nbase_port = 9500\nnum_cores = 16\n";
+    /// std::fs::write(dir.join("config.toml"), config).unwrap();
+    /// let secrets = "[[host]]\naddress = \"host1\"\npassword = \"hunter2\"\n";
+    /// std::fs::write(dir.join("credentials.toml"), secrets).unwrap();
+    ///
+    /// let config =
+    ///     RuntimeConfig::remote_with_secrets(dir.join("config.toml"), dir.join("credentials.toml"))
+    ///         .expect("cannot read config file");
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn remote_with_secrets<P: AsRef<Path>, S: AsRef<Path>>(
+        toml_path: P,
+        secrets_path: S,
+    ) -> Result<RuntimeConfig, ConfigError> {
+        Self::remote_inner(toml_path.as_ref(), Some(secrets_path.as_ref()))
+    }
+
+    fn remote_inner(
+        toml_path: &Path,
+        secrets_path: Option<&Path>,
+    ) -> Result<RuntimeConfig, ConfigError> {
         let mut builder = ConfigBuilder::new_remote();
 
         if env::var(CONFIG_ENV_VAR).is_ok() {
+            // the configuration sent by the runner already contains the secrets
             builder.parse_env()?;
             builder.host_id_from_env()?;
         } else {
             builder.parse_file(toml_path)?;
+            if let Some(secrets_path) = secrets_path {
+                builder.parse_secrets_file(secrets_path)?;
+            }
+        }
+
+        builder.build()
+    }
+
+    /// Remote environment based on a directory with one configuration file for each host.
+    ///
+    /// Each `*.toml` file inside `dir` describes a single [`HostConfig`], and the `host_id` of each
+    /// host is given by the order of the file names, see [`ConfigBuilder::parse_dir`]. Like
+    /// [`RuntimeConfig::remote`], a worker process reads the configuration from the environment
+    /// variable instead.
+    ///
+    /// ```
+    /// # use renoir::RuntimeConfig;
+    /// # let dir = std::env::temp_dir().join("renoir-remote-from-dir-doc");
+    /// # std::fs::create_dir_all(&dir).unwrap();
+    /// for (i, address) in ["host1", "host2"].into_iter().enumerate() {
+    ///     let host = format!("address = \"{address}\"\nbase_port = 9500\nnum_cores = 16\n");
+    ///     std::fs::write(dir.join(format!("host-{i}.toml")), host).unwrap();
+    /// }
+    ///
+    /// let config = RuntimeConfig::remote_from_dir(&dir).expect("cannot read config directory");
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn remote_from_dir<P: AsRef<Path>>(dir: P) -> Result<RuntimeConfig, ConfigError> {
+        let mut builder = ConfigBuilder::new_remote();
+
+        if env::var(CONFIG_ENV_VAR).is_ok() {
+            builder.parse_env()?;
+            builder.host_id_from_env()?;
+        } else {
+            builder.parse_dir(dir)?;
         }
 
         builder.build()
@@ -284,6 +697,36 @@ impl RuntimeConfig {
     }
 }
 
+impl RemoteConfig {
+    /// Resolve the addresses of all the hosts, failing with the first host that cannot be
+    /// resolved.
+    ///
+    /// This is done by the runner before spawning the workers, and by each worker before building
+    /// the job, so that an invalid address is reported right away instead of stalling the first
+    /// connection to the host.
+    pub fn resolve_hosts(&self) -> Result<(), ConfigError> {
+        for host in &self.hosts {
+            let mut addrs = (host.address.as_str(), host.base_port)
+                .to_socket_addrs()
+                .map_err(|e| ConfigError::Unresolvable(host.address.clone(), e.to_string()))?;
+            if addrs.next().is_none() {
+                return Err(ConfigError::Unresolvable(
+                    host.address.clone(),
+                    "no address found".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HostConfig {
+    /// The weight of the host used for distributing the elements, see [`HostConfig::weight`].
+    pub fn weight(&self) -> f64 {
+        self.weight.unwrap_or(self.num_cores as f64)
+    }
+}
+
 impl Display for HostConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "[{}:{}-]", self.address, self.base_port)
@@ -305,17 +748,27 @@ pub struct ConfigBuilder {
     hosts: Vec<HostConfig>,
     tracing_dir: Option<PathBuf>,
     cleanup_executable: bool,
+    worker_logs: bool,
+    on_malformed_message: Option<MalformedMessagePolicy>,
+    max_inflight_batches: Option<usize>,
+    worker_command_template: Option<String>,
+    connect_deadline_secs: Option<u64>,
+    max_frame_size: Option<usize>,
+    secrets: Vec<HostSecrets>,
 }
 
 impl ConfigBuilder {
     pub fn new_local(parallelism: CoordUInt) -> Result<RuntimeConfig, ConfigError> {
-        if parallelism == 0 {
-            Err(ConfigError::Invalid(
-                "The number of cores should be positive".into(),
-            ))
-        } else {
-            Ok(RuntimeConfig::Local(LocalConfig { parallelism }))
-        }
+        LocalConfigBuilder::new(parallelism).build()
+    }
+
+    pub fn new_local_pinned(
+        parallelism: CoordUInt,
+        core_ids: Option<Vec<usize>>,
+    ) -> Result<RuntimeConfig, ConfigError> {
+        LocalConfigBuilder::new(parallelism)
+            .pin_threads(core_ids)
+            .build()
     }
 
     pub fn new_remote() -> Self {
@@ -324,6 +777,13 @@ impl ConfigBuilder {
             hosts: Vec::new(),
             tracing_dir: None,
             cleanup_executable: false,
+            worker_logs: false,
+            on_malformed_message: None,
+            max_inflight_batches: None,
+            worker_command_template: None,
+            connect_deadline_secs: None,
+            max_frame_size: None,
+            secrets: Vec::new(),
         }
     }
     /// Parse toml and integrate it in the builder.
@@ -335,20 +795,37 @@ impl ConfigBuilder {
             hosts,
             tracing_dir,
             cleanup_executable,
+            worker_logs,
+            on_malformed_message,
+            max_inflight_batches,
+            worker_command_template,
+            connect_deadline_secs,
+            max_frame_size,
         } = toml::from_str(config_str)?;
 
-        // validate the configuration
         for host in hosts.into_iter() {
-            if host.ssh.password.is_some() && host.ssh.key_file.is_some() {
-                return Err(ConfigError::Invalid(format!(
-                    "Malformed configuration: cannot specify both password and key file on host {}",
-                    host.address
-                )));
-            }
-            self.hosts.push(host);
+            self.push_host(host)?;
         }
         self.tracing_dir = self.tracing_dir.take().or(tracing_dir);
         self.cleanup_executable |= cleanup_executable;
+        self.worker_logs |= worker_logs;
+        if on_malformed_message != MalformedMessagePolicy::default() {
+            self.on_malformed_message
+                .get_or_insert(on_malformed_message);
+        }
+        if max_inflight_batches != default_max_inflight_batches() {
+            self.max_inflight_batches
+                .get_or_insert(max_inflight_batches);
+        }
+        self.worker_command_template = self
+            .worker_command_template
+            .take()
+            .or(worker_command_template);
+        if connect_deadline_secs != default_connect_deadline_secs() {
+            self.connect_deadline_secs
+                .get_or_insert(connect_deadline_secs);
+        }
+        self.max_frame_size = self.max_frame_size.or(max_frame_size);
 
         Ok(self)
     }
@@ -360,6 +837,140 @@ impl ConfigBuilder {
         self.parse_toml_str(&content)
     }
 
+    /// Parse the credentials of the hosts from toml and integrate them in the builder.
+    ///
+    /// The secrets contain a `[[host]]` table for each host, with its `address`, optionally its
+    /// `base_port`, and the `password` or the `key_passphrase` to use for connecting via SSH.
+    /// When the configuration is built, they are merged into the [`SSHConfig`] of the hosts with
+    /// the same address (and base port, if specified), replacing the credentials already there. It
+    /// is an error if some secrets do not match any host.
+    ///
+    /// ```toml
+    /// [[host]]
+    /// address = "host1"
+    /// password = "hunter2"
+    ///
+    /// [[host]]
+    /// address = "host2"
+    /// key_passphrase = "correct horse battery staple"
+    /// ```
+    pub fn parse_secrets_str(&mut self, secrets_str: &str) -> Result<&mut Self, ConfigError> {
+        let Secrets { hosts } = toml::from_str(secrets_str)?;
+        self.secrets.extend(hosts);
+        Ok(self)
+    }
+
+    /// Read the credentials of the hosts from a toml file and integrate them in the builder, see
+    /// [`ConfigBuilder::parse_secrets_str`].
+    pub fn parse_secrets_file(
+        &mut self,
+        secrets_path: impl AsRef<Path>,
+    ) -> Result<&mut Self, ConfigError> {
+        let content = std::fs::read_to_string(secrets_path.as_ref())?;
+        self.parse_secrets_str(&content).map_err(|e| {
+            ConfigError::Invalid(format!(
+                "cannot parse {}: {e}",
+                secrets_path.as_ref().display()
+            ))
+        })
+    }
+
+    /// Read a directory with one toml file for each host and integrate them in the builder.
+    ///
+    /// Each `*.toml` file inside `dir` describes a single [`HostConfig`], the other files are
+    /// ignored. The hosts are appended in the order of the file names, which determines their
+    /// `host_id`. If the names end with a number (e.g. `node-1.toml`, `node-2.toml`, ...,
+    /// `node-10.toml`) the hosts are ordered by it instead, and the numbers must be distinct and
+    /// contiguous so that a missing or duplicated host file is detected.
+    pub fn parse_dir(&mut self, dir: impl AsRef<Path>) -> Result<&mut Self, ConfigError> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir.as_ref())? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "toml") {
+                files.push((host_file_index(&path), path));
+            }
+        }
+        if files.is_empty() {
+            return Err(ConfigError::Invalid(format!(
+                "no host configuration file (*.toml) found in {}",
+                dir.as_ref().display()
+            )));
+        }
+        files.sort();
+
+        let indexed = files.iter().filter(|(index, _)| index.is_some()).count();
+        if indexed == files.len() {
+            let pairs = || files.windows(2).map(|p| (&p[0], &p[1]));
+            if let Some(((index, a), (_, b))) = pairs().find(|(a, b)| a.0 == b.0) {
+                return Err(ConfigError::Invalid(format!(
+                    "duplicate host index {}: {} and {}",
+                    index.unwrap(),
+                    a.display(),
+                    b.display()
+                )));
+            }
+            if let Some(((_, a), (_, b))) = pairs().find(|(a, b)| a.0.unwrap() + 1 != b.0.unwrap())
+            {
+                return Err(ConfigError::Invalid(format!(
+                    "missing host index between {} and {}",
+                    a.display(),
+                    b.display()
+                )));
+            }
+        } else if indexed > 0 {
+            return Err(ConfigError::Invalid(format!(
+                "either all or none of the host files in {} must end with a host index",
+                dir.as_ref().display()
+            )));
+        }
+
+        for (_, path) in files {
+            let content = std::fs::read_to_string(&path)?;
+            let host = toml::from_str(&content).map_err(|e| {
+                ConfigError::Invalid(format!("cannot parse {}: {e}", path.display()))
+            })?;
+            self.push_host(host)?;
+        }
+        Ok(self)
+    }
+
+    /// Validate a host and append it to the list.
+    fn push_host(&mut self, host: HostConfig) -> Result<(), ConfigError> {
+        check_credentials(&host)?;
+        self.hosts.push(host);
+        Ok(())
+    }
+
+    /// The hosts with the secrets merged into their SSH configuration.
+    fn hosts_with_secrets(&self) -> Result<Vec<HostConfig>, ConfigError> {
+        let mut hosts = self.hosts.clone();
+        for secrets in &self.secrets {
+            let mut matching = hosts
+                .iter_mut()
+                .filter(|host| {
+                    host.address == secrets.address
+                        && secrets.base_port.is_none_or(|port| port == host.base_port)
+                })
+                .peekable();
+            if matching.peek().is_none() {
+                return Err(ConfigError::Invalid(format!(
+                    "the secrets of host {} do not match any host",
+                    secrets.address
+                )));
+            }
+            for host in matching {
+                if let Some(password) = &secrets.password {
+                    host.ssh.password = Some(password.clone());
+                }
+                if let Some(key_passphrase) = &secrets.key_passphrase {
+                    host.ssh.key_passphrase = Some(key_passphrase.clone());
+                }
+                check_credentials(host)?;
+            }
+        }
+        Ok(hosts)
+    }
+
     pub fn add_hosts(&mut self, hosts: &[HostConfig]) -> &mut Self {
         self.hosts.extend_from_slice(hosts);
         self
@@ -373,6 +984,48 @@ impl ConfigBuilder {
         self.parse_toml_str(&config_str)
     }
 
+    /// Write the logs of each worker to a JSON file inside the tracing directory, see
+    /// [`RemoteConfig::worker_logs`].
+    pub fn worker_logs(&mut self, worker_logs: bool) -> &mut Self {
+        self.worker_logs = worker_logs;
+        self
+    }
+
+    /// Choose what to do with the messages that cannot be decoded, see
+    /// [`RemoteConfig::on_malformed_message`].
+    pub fn on_malformed_message(&mut self, policy: MalformedMessagePolicy) -> &mut Self {
+        self.on_malformed_message = Some(policy);
+        self
+    }
+
+    /// Set the maximum number of batches queued for each connection to another host, see
+    /// [`RemoteConfig::max_inflight_batches`].
+    pub fn max_inflight_batches(&mut self, max_inflight_batches: usize) -> &mut Self {
+        self.max_inflight_batches = Some(max_inflight_batches);
+        self
+    }
+
+    /// Set the command used to start the remote workers, see
+    /// [`RemoteConfig::worker_command_template`].
+    pub fn worker_command_template(&mut self, template: impl Into<String>) -> &mut Self {
+        self.worker_command_template = Some(template.into());
+        self
+    }
+
+    /// Set how long, in seconds, a host keeps trying to connect to another host, see
+    /// [`RemoteConfig::connect_deadline_secs`].
+    pub fn connect_deadline_secs(&mut self, connect_deadline_secs: u64) -> &mut Self {
+        self.connect_deadline_secs = Some(connect_deadline_secs);
+        self
+    }
+
+    /// Set the maximum size of the frames sent to the other hosts, see
+    /// [`RemoteConfig::max_frame_size`].
+    pub fn max_frame_size(&mut self, max_frame_size: usize) -> &mut Self {
+        self.max_frame_size = Some(max_frame_size);
+        self
+    }
+
     pub fn host_id(&mut self, host_id: HostId) -> &mut Self {
         self.host_id = Some(host_id);
         self
@@ -398,21 +1051,90 @@ impl ConfigBuilder {
             }
         };
 
+        if let Some(host) = self
+            .hosts
+            .iter()
+            .find(|host| !(host.weight().is_finite() && host.weight() > 0.0))
+        {
+            return Err(ConfigError::Invalid(format!(
+                "the weight of host {host} must be positive, got {}",
+                host.weight()
+            )));
+        }
+
+        if self.max_inflight_batches == Some(0) {
+            return Err(ConfigError::Invalid(
+                "max_inflight_batches must be positive".into(),
+            ));
+        }
+
+        if self.connect_deadline_secs == Some(0) {
+            return Err(ConfigError::Invalid(
+                "connect_deadline_secs must be positive".into(),
+            ));
+        }
+
+        if self.max_frame_size == Some(0) {
+            return Err(ConfigError::Invalid(
+                "max_frame_size must be positive".into(),
+            ));
+        }
+
         let conf = RuntimeConfig::Remote(RemoteConfig {
             host_id: self.host_id,
-            hosts: self.hosts.clone(),
+            hosts: self.hosts_with_secrets()?,
             tracing_dir: self.tracing_dir.clone(),
             cleanup_executable: self.cleanup_executable,
+            worker_logs: self.worker_logs,
+            on_malformed_message: self.on_malformed_message.unwrap_or_default(),
+            max_inflight_batches: self
+                .max_inflight_batches
+                .unwrap_or_else(default_max_inflight_batches),
+            worker_command_template: self.worker_command_template.clone(),
+            connect_deadline_secs: self
+                .connect_deadline_secs
+                .unwrap_or_else(default_connect_deadline_secs),
+            max_frame_size: self.max_frame_size,
         });
         Ok(conf)
     }
 }
 
+/// Check that a host is not configured with both a password and a key file.
+fn check_credentials(host: &HostConfig) -> Result<(), ConfigError> {
+    if host.ssh.password.is_some() && host.ssh.key_file.is_some() {
+        return Err(ConfigError::Invalid(format!(
+            "Malformed configuration: cannot specify both password and key file on host {}",
+            host.address
+        )));
+    }
+    Ok(())
+}
+
 /// Default port for ssh, used by the serde default value.
 fn ssh_default_port() -> u16 {
     22
 }
 
+/// The number at the end of the name of a host file (e.g. 3 for `node-3.toml`), if any.
+fn host_file_index(path: &Path) -> Option<u64> {
+    let stem = path.file_stem()?.to_str()?;
+    let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    stem[stem.len() - digits..].parse().ok()
+}
+
+/// Default number of batches queued for each connection to another host, used by the serde
+/// default value.
+pub(crate) fn default_max_inflight_batches() -> usize {
+    10
+}
+
+/// Default number of seconds spent trying to connect to another host, used by the serde default
+/// value.
+pub(crate) fn default_connect_deadline_secs() -> u64 {
+    60
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
     #[error("Serialization error: {0}")]
@@ -426,4 +1148,7 @@ pub enum ConfigError {
 
     #[error("Missing environment variable {0}: {1}")]
     Environment(String, env::VarError),
+
+    #[error("Cannot resolve the address of host {0}: {1}")]
+    Unresolvable(String, String),
 }
@@ -1,14 +1,21 @@
 use parking_lot::Mutex;
 use std::any::TypeId;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::block::{Block, Scheduling};
-use crate::config::RuntimeConfig;
+use crate::config::{ConfigError, RuntimeConfig};
+use crate::logging;
 use crate::operator::iteration::IterationStateLock;
-use crate::operator::source::Source;
+use crate::operator::sink::{StreamOutput, StreamOutputRef};
+use crate::operator::source::{Source, StopHandle};
 use crate::operator::{Data, Operator};
+#[cfg(feature = "statsd")]
+use crate::profiler::StatsdReporter;
+use crate::savepoint::{Savepoint, SavepointError};
 #[cfg(feature = "ssh")]
-use crate::scheduler::{BlockId, Scheduler};
+use crate::scheduler::{BlockId, ExecutionError, Scheduler, ValidationError};
+use crate::shutdown::{ShutdownRecorder, ShutdownReport};
 use crate::stream::Stream;
 use crate::{BatchMode, CoordUInt};
 
@@ -24,6 +31,21 @@ pub(crate) struct StreamContextInner {
     /// The scheduler that will start the computation. It's an option because it will be moved out
     /// of this struct when the computation starts.
     scheduler: Option<Scheduler>,
+    /// Show the stages of the fused operators as separate operators in the job graph.
+    pub(crate) expand_fused: bool,
+    /// The savepoint to restore the state from.
+    restore_savepoint: Option<PathBuf>,
+    /// The directory where to write a savepoint at the end of the execution.
+    write_savepoint: Option<PathBuf>,
+    /// The handle that stops all the sources that support it.
+    stop: StopHandle,
+    /// The shutdown events of the replicas, reported at the end of the execution.
+    shutdown: ShutdownRecorder,
+    /// The structural hash of the job graph, computed when the execution starts.
+    job_graph_hash: StreamOutputRef<u64>,
+    /// The reporter pushing the metrics to StatsD during the execution.
+    #[cfg(feature = "statsd")]
+    statsd: Option<StatsdReporter>,
 }
 
 /// Streaming environment from which it's possible to register new streams and start the
@@ -47,12 +69,55 @@ pub struct StreamContext {
 impl StreamContext {
     /// Construct a new environment from the config.
     pub fn new(config: impl Into<Arc<RuntimeConfig>>) -> Self {
+        let config = config.into();
+        if let (RuntimeConfig::Remote(remote), Some(host_id)) = (&*config, config.host_id()) {
+            logging::init_worker_logs(remote, host_id);
+            if let Err(e) = remote.resolve_hosts() {
+                panic!("{e}");
+            }
+        }
         debug!("new environment");
         StreamContext {
-            inner: Arc::new(Mutex::new(StreamContextInner::new(config.into()))),
+            inner: Arc::new(Mutex::new(StreamContextInner::new(config))),
         }
     }
 
+    /// Construct a new environment from the config, restoring the state of the operators from
+    /// the savepoint at `path`.
+    ///
+    /// The states are matched by the identifiers given to the operators, and by the index of
+    /// the replica: the job should be built with the same identifiers and the same parallelism
+    /// as the one that wrote the savepoint. See [`StreamContext::savepoint`].
+    pub fn from_savepoint<P: AsRef<Path>>(
+        path: P,
+        config: impl Into<Arc<RuntimeConfig>>,
+    ) -> Result<Self, SavepointError> {
+        let path = Savepoint::open(path.as_ref())?;
+        let env = Self::new(config);
+        env.inner.lock().restore_savepoint = Some(path);
+        Ok(env)
+    }
+
+    /// Construct a new local environment with `parallelism` threads for each block.
+    ///
+    /// This is a shortcut for `StreamContext::new(RuntimeConfig::local(parallelism).unwrap())`.
+    ///
+    /// ## Panics
+    ///
+    /// If `parallelism` is zero.
+    pub fn local(parallelism: CoordUInt) -> Self {
+        let config = RuntimeConfig::local(parallelism).unwrap_or_else(|e| panic!("{e}"));
+        Self::new(config)
+    }
+
+    /// Construct a new remote environment from the configuration file at `path`.
+    ///
+    /// This is a shortcut for `StreamContext::new(RuntimeConfig::remote(path)?)`, see
+    /// [`RuntimeConfig::remote`] for the format of the file.
+    pub fn from_config_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Ok(Self::new(RuntimeConfig::remote(path)?))
+    }
+
     pub fn new_local() -> Self {
         let parallelism = std::thread::available_parallelism()
             .map(|q| q.get())
@@ -73,32 +138,337 @@ impl StreamContext {
         let mut inner = self.inner.lock();
         assert!(inner.config.host_id().is_some(), "remote config must be started using RuntimeConfig::spawn_remote_workers(). (Or initialize `host_id` correctly)");
 
-        let block = inner.new_block(source, Default::default(), Default::default());
+        if let Some(stop) = source.stop_handle() {
+            stop.link(&inner.stop);
+        }
+        let unbounded = !source.is_bounded();
+        let mut block = inner.new_block(source, Default::default(), Default::default());
+        block.unbounded = unbounded;
         Stream::new(self.inner.clone(), block)
     }
 
     /// Start the computation. Await on the returned future to actually start the computation.
+    ///
+    /// Panics if a replica panics, see [`StreamContext::try_execute`].
     #[cfg(feature = "tokio")]
     pub async fn execute(self) {
+        if let Err(e) = self.try_execute().await {
+            panic!("{e}");
+        }
+    }
+
+    /// Like [`StreamContext::execute`], returning an error if a replica panics instead of
+    /// panicking.
+    ///
+    /// See [`StreamContext::try_execute_blocking`].
+    #[cfg(feature = "tokio")]
+    pub async fn try_execute(self) -> Result<(), ExecutionError> {
         let mut env = self.inner.lock();
         info!("starting execution ({} blocks)", env.block_count);
-        let scheduler = env.scheduler.take().unwrap();
+        let (scheduler, savepoint) = env.take_scheduler();
         let block_count = env.block_count;
+        #[cfg(feature = "statsd")]
+        let statsd = env.statsd.take().map(StatsdReporter::start);
         drop(env);
-        scheduler.start(block_count).await;
+        let res = scheduler.start(block_count).await;
+        #[cfg(feature = "statsd")]
+        if let Some(statsd) = statsd {
+            statsd.stop();
+        }
+        res?;
+        finish_savepoint(&savepoint);
         info!("finished execution");
+        Ok(())
     }
 
     /// Start the computation. Blocks until the computation is complete.
     ///
     /// Execute on a thread or use the async version [`execute`]
     /// for non-blocking alternatives
+    ///
+    /// Panics if a replica panics, see [`StreamContext::try_execute_blocking`].
     pub fn execute_blocking(self) {
+        if let Err(e) = self.try_execute_blocking() {
+            panic!("{e}");
+        }
+    }
+
+    /// Like [`StreamContext::execute_blocking`], returning an error if a replica panics instead
+    /// of panicking.
+    ///
+    /// The panic of a replica is caught and logged with its coordinates, and the replica is torn
+    /// down: the replicas connected to it see it disconnecting and crash as well, instead of
+    /// waiting for it forever. When all the replicas have exited the error reports the first
+    /// replica that panicked. The savepoint is not written if the execution fails.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// let env = StreamContext::new_local();
+    /// env.stream_iter(0..10)
+    ///     .map(|n| 100 / (5 - n))
+    ///     .for_each(|_| {});
+    ///
+    /// assert!(env.try_execute_blocking().is_err());
+    /// ```
+    pub fn try_execute_blocking(self) -> Result<(), ExecutionError> {
         let mut env = self.inner.lock();
         info!("starting execution ({} blocks)", env.block_count);
-        let scheduler = env.scheduler.take().unwrap();
-        scheduler.start_blocking(env.block_count);
+        let (scheduler, savepoint) = env.take_scheduler();
+        #[cfg(feature = "statsd")]
+        let statsd = env.statsd.take().map(StatsdReporter::start);
+        let res = scheduler.start_blocking(env.block_count);
+        #[cfg(feature = "statsd")]
+        if let Some(statsd) = statsd {
+            statsd.stop();
+        }
+        res?;
+        finish_savepoint(&savepoint);
         info!("finished execution");
+        Ok(())
+    }
+
+    /// Show each stage of the operators built with [`Stream::fused`] as a separate operator in the
+    /// job graph, instead of a single `Fused` operator.
+    ///
+    /// This only changes how the job graph is displayed, which is useful when debugging it: the
+    /// stages are still executed by a single operator. It affects the streams built after calling
+    /// this method.
+    pub fn expand_fused_operators(&self, expand: bool) {
+        self.inner.lock().expand_fused = expand;
+    }
+
+    /// Choose the host of the replicas of the blocks in a remote execution.
+    ///
+    /// `placement` receives the id of a block and the global id of one of its replicas (the index
+    /// of the replica among all the replicas of the block), and returns the id of the host where
+    /// the replica should run, or `None` to leave the choice to the scheduler. The number of
+    /// replicas of each block does not change: a host takes at most a replica for each of its
+    /// cores, or a single replica if the block has [`Replication::Host`] or [`Replication::One`].
+    ///
+    /// When the preferred host does not exist or has no room left, a warning is logged and the
+    /// replica is placed like the replicas without a preference: these fill the room left on the
+    /// hosts in order, starting from the first host.
+    ///
+    /// The blocks are placed as they are built, so call this before building the streams. Every
+    /// host computes the placement on its own, so `placement` must return the same hosts in all of
+    /// them. In a local execution it is ignored.
+    ///
+    /// [`Replication::Host`]: crate::Replication::Host
+    /// [`Replication::One`]: crate::Replication::One
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// let env = StreamContext::new_local();
+    /// // run the first replica of every block on the second host
+    /// env.replica_placement(|_block_id, replica| (replica == 0).then_some(1));
+    /// ```
+    pub fn replica_placement<F>(&self, placement: F)
+    where
+        F: Fn(CoordUInt, CoordUInt) -> Option<CoordUInt> + Send + Sync + 'static,
+    {
+        self.inner.lock().scheduler_mut().placement = Some(Arc::new(placement));
+    }
+
+    /// Push the metrics of the profiler to a StatsD server while the job runs, see
+    /// [`StatsdReporter`].
+    ///
+    /// The reporter starts with the execution, and sends the last metrics when it ends. In a
+    /// remote execution each host sends the metrics of its replicas.
+    #[cfg(feature = "statsd")]
+    pub fn statsd_reporter(&self, reporter: StatsdReporter) {
+        self.inner.lock().statsd = Some(reporter);
+    }
+
+    /// Write a savepoint to the directory at `path` when the execution stops cleanly.
+    ///
+    /// When the stream ends, each stateful operator with an identifier (like
+    /// [`Stream::stateful_map`]) writes its state, and each resumable source (like
+    /// [`IteratorSource::savepoint_id`](crate::operator::source::IteratorSource::savepoint_id))
+    /// writes its offset. The savepoint can be restored with [`StreamContext::from_savepoint`].
+    ///
+    /// The savepoint is marked as complete only after all the states have been written, an
+    /// interrupted execution does not leave a usable savepoint. In a remote execution each host
+    /// writes the state of its replicas to its own file system.
+    pub fn savepoint<P: Into<PathBuf>>(&self, path: P) {
+        self.inner.lock().write_savepoint = Some(path.into());
+    }
+
+    /// Get the handle that stops all the sources of this environment that can be stopped from
+    /// outside (the ones with a [`StopHandle`], like
+    /// [`WatchDirSource`](crate::operator::source::WatchDirSource)).
+    ///
+    /// Stopping it drains the job: the stoppable sources end their stream, the rest of the
+    /// pipeline processes the elements already read and the sinks are flushed, then the execution
+    /// ends cleanly, writing the savepoint if requested. The bounded sources without a handle are
+    /// not interrupted, they end when they are exhausted.
+    pub fn stop_handle(&self) -> StopHandle {
+        self.inner.lock().stop.clone()
+    }
+
+    /// Drain the job when the process receives `SIGINT` (Ctrl-C) or `SIGTERM`, as
+    /// [`StreamContext::stop_handle`] does.
+    ///
+    /// This is opt-in since it replaces the default behavior of the signals for the whole
+    /// process. If a second signal is received while the job is draining, the process exits
+    /// immediately.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// let env = StreamContext::new_local();
+    /// env.stop_on_signals().expect("cannot install the signal handlers");
+    /// // build a job with unbounded sources...
+    /// env.execute_blocking();
+    /// ```
+    #[cfg(feature = "signals")]
+    pub fn stop_on_signals(&self) -> std::io::Result<()> {
+        use signal_hook::consts::{SIGINT, SIGTERM};
+        use signal_hook::flag;
+
+        let stopped = self.inner.lock().stop.flag();
+        for signal in [SIGINT, SIGTERM] {
+            // the first signal sets the flag, the second one finds it set and exits
+            flag::register_conditional_shutdown(signal, 130, stopped.clone())?;
+            flag::register(signal, stopped.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Get the report of how the end of the stream propagated through the blocks, available after
+    /// the execution.
+    ///
+    /// For each block the report tells when its replicas received the end of the stream from all
+    /// their inputs, and how long they waited between the first and the last of them, so that a
+    /// block lagging in terminating can be spotted. The report is also logged at the end of the
+    /// execution. See [`ShutdownReport`].
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// let env = StreamContext::new_local();
+    /// env.stream_iter(0..10).shuffle().for_each(|_| {});
+    /// let report = env.shutdown_report();
+    /// env.execute_blocking();
+    ///
+    /// let report = report.get().unwrap();
+    /// assert_eq!(report.blocks.len(), 2);
+    /// ```
+    pub fn shutdown_report(&self) -> StreamOutput<ShutdownReport> {
+        self.inner.lock().shutdown.output().into()
+    }
+
+    /// Get a hash of the structure of the job graph, available when the execution starts.
+    ///
+    /// The hash is the same for two jobs with the same blocks, operators and connections, even if
+    /// the ids of their blocks differ, and changes when an operator is added, removed or replaced.
+    /// It can be used to detect whether the structure of a job changed between two deployments.
+    /// The subtitles of the operators, the number of replicas and the batch modes are ignored. In
+    /// a remote execution each host hashes only the blocks it runs.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// let hash = |double: bool| {
+    ///     let env = StreamContext::new_local();
+    ///     let s = env.stream_iter(0..10).shuffle();
+    ///     if double {
+    ///         s.map(|n| n * 2).for_each(|_| {});
+    ///     } else {
+    ///         s.for_each(|_| {});
+    ///     }
+    ///     let hash = env.job_graph_hash();
+    ///     env.execute_blocking();
+    ///     hash.get().unwrap()
+    /// };
+    ///
+    /// assert_eq!(hash(true), hash(true));
+    /// assert_ne!(hash(true), hash(false));
+    /// ```
+    pub fn job_graph_hash(&self) -> StreamOutput<u64> {
+        self.inner.lock().job_graph_hash.clone().into()
+    }
+
+    /// Get the job graph in dot format, without executing the job.
+    ///
+    /// Each block is drawn as a cluster with its operators and its number of replicas, and the
+    /// connections between the blocks are labelled with the type of the elements and the strategy
+    /// used to send them. Only the streams that already have a sink are part of the graph, so call
+    /// this after building the whole job, before the execution.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// let env = StreamContext::new_local();
+    /// env.stream_iter(0..10).shuffle().map(|n| n * 2).for_each(|_| {});
+    ///
+    /// let graph = env.job_graph();
+    /// assert!(graph.starts_with("digraph renoir"));
+    /// assert_eq!(graph.matches("subgraph cluster_block").count(), 2);
+    /// ```
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the execution has already started.
+    pub fn job_graph(&self) -> String {
+        self.inner.lock().scheduler_mut().job_graph()
+    }
+
+    /// Get the job graph as a Mermaid flowchart, without executing the job.
+    ///
+    /// This is the same diagram as [`StreamContext::job_graph`], in a format that can be embedded
+    /// in Markdown documents.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the execution has already started.
+    pub fn job_graph_mermaid(&self) -> String {
+        self.inner.lock().scheduler_mut().job_graph_mermaid()
+    }
+
+    /// Check the job graph built so far for the problems that would make the execution fail or
+    /// hang, without executing the job.
+    ///
+    /// All the problems are returned at once, see [`ValidationError`]: the streams without a sink,
+    /// the blocks without replicas, the blocks connected in a cycle outside of an iteration, and
+    /// the blocks that send each element to the replica of the next block with the same
+    /// coordinates when the next block has other replicas. The types of the elements sent between
+    /// the blocks and the operators that need a key are already checked by the compiler.
+    ///
+    /// This is a cheap pre-flight check, for example to run in CI before deploying a remote job.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// let env = StreamContext::new_local();
+    /// env.stream_iter(0..10).map(|n| n * 2).for_each(|_| {});
+    /// assert_eq!(env.validate(), Ok(()));
+    ///
+    /// // a stream without a sink
+    /// env.stream_iter(0..10).map(|n| n * 2);
+    /// assert_eq!(env.validate().unwrap_err().len(), 1);
+    /// ```
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the execution has already started.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut env = self.inner.lock();
+        let block_count = env.block_count;
+        let errors = env.scheduler_mut().validate(block_count);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 
     /// Get the total number of processing cores in the cluster.
@@ -110,15 +480,44 @@ impl StreamContext {
     }
 }
 
+/// Mark the savepoint written by the execution as complete.
+fn finish_savepoint(savepoint: &Savepoint) {
+    if let Err(e) = savepoint.finish() {
+        log::error!("cannot complete the savepoint: {e}");
+    }
+}
+
 impl StreamContextInner {
     fn new(config: Arc<RuntimeConfig>) -> Self {
         Self {
             config: config.clone(),
             block_count: 0,
             scheduler: Some(Scheduler::new(config)),
+            expand_fused: false,
+            restore_savepoint: None,
+            write_savepoint: None,
+            stop: Default::default(),
+            shutdown: Default::default(),
+            job_graph_hash: Default::default(),
+            #[cfg(feature = "statsd")]
+            statsd: None,
         }
     }
 
+    /// Take the scheduler out of the environment to start the execution, together with the
+    /// savepoints it uses.
+    fn take_scheduler(&mut self) -> (Scheduler, Arc<Savepoint>) {
+        let mut scheduler = self.scheduler.take().unwrap();
+        let savepoint = Arc::new(Savepoint::new(
+            self.restore_savepoint.clone(),
+            self.write_savepoint.clone(),
+        ));
+        scheduler.savepoint = savepoint.clone();
+        scheduler.shutdown = self.shutdown.clone();
+        scheduler.job_graph_hash = self.job_graph_hash.clone();
+        (scheduler, savepoint)
+    }
+
     pub(crate) fn new_block<S: Source>(
         &mut self,
         source: S,
@@ -176,3 +575,91 @@ impl StreamContextInner {
             .expect("The environment has already been started, cannot access the scheduler")
     }
 }
+
+#[cfg(all(test, unix, feature = "signals"))]
+mod tests {
+    use std::fmt::Display;
+    use std::time::{Duration, Instant};
+
+    use itertools::Itertools;
+
+    use crate::block::{BlockStructure, OperatorKind, OperatorStructure, Replication};
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::{Source, StopHandle};
+    use crate::operator::{Operator, StreamElement};
+    use crate::scheduler::ExecutionMetadata;
+
+    /// Unbounded source emitting an item every few milliseconds until it is stopped.
+    #[derive(Clone, Default)]
+    struct SlowSource {
+        next: u64,
+        stop: StopHandle,
+        terminated: bool,
+    }
+
+    impl Display for SlowSource {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "SlowSource")
+        }
+    }
+
+    impl Operator for SlowSource {
+        type Out = u64;
+
+        fn setup(&mut self, _metadata: &mut ExecutionMetadata) {}
+
+        fn next(&mut self) -> StreamElement<u64> {
+            if self.terminated {
+                return StreamElement::Terminate;
+            }
+            if self.stop.is_stopped() {
+                self.terminated = true;
+                return StreamElement::FlushAndRestart;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+            self.next += 1;
+            StreamElement::Item(self.next - 1)
+        }
+
+        fn structure(&self) -> BlockStructure {
+            let mut operator = OperatorStructure::new::<u64, _>("SlowSource");
+            operator.kind = OperatorKind::Source;
+            BlockStructure::default().add_operator(operator)
+        }
+    }
+
+    impl Source for SlowSource {
+        fn replication(&self) -> Replication {
+            Replication::One
+        }
+
+        fn stop_handle(&self) -> Option<StopHandle> {
+            Some(self.stop.clone())
+        }
+    }
+
+    #[test]
+    fn sigterm_drains_the_job() {
+        let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+        env.stop_on_signals().unwrap();
+        let res = env
+            .stream(SlowSource::default())
+            .shuffle()
+            .map(|n| n * 2)
+            .collect_vec();
+
+        std::thread::spawn(|| {
+            std::thread::sleep(Duration::from_millis(200));
+            signal_hook::low_level::raise(signal_hook::consts::SIGTERM).unwrap();
+        });
+        let start = Instant::now();
+        env.execute_blocking();
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        // everything read before the signal reached the sink
+        let res = res.get().unwrap().into_iter().sorted().collect_vec();
+        assert!(!res.is_empty());
+        assert_eq!(res, (0..res.len() as u64).map(|n| n * 2).collect_vec());
+    }
+}
@@ -118,28 +118,34 @@ extern crate derivative;
 extern crate tracing;
 
 pub use block::structure;
+pub use block::Autoscale;
 pub use block::BatchMode;
 pub use block::Replication;
-pub use block::{group_by_hash, GroupHasherBuilder};
+pub use block::{group_by_hash, GroupByHasher, GroupHasherBuilder};
 pub use config::RuntimeConfig;
 pub use environment::StreamContext;
+pub use network::{MessageLog, RecordedMessage, ReplayLog};
 pub use operator::iteration::IterationStateHandle;
-pub use scheduler::ExecutionMetadata;
+#[cfg(feature = "statsd")]
+pub use profiler::StatsdReporter;
+pub use scheduler::{ExecutionError, ExecutionMetadata, OperatorError, ValidationError};
 pub use stream::{KeyedStream, Stream, WindowedStream};
 
 pub(crate) mod block;
 pub(crate) mod channel;
 pub mod config;
 pub(crate) mod environment;
+mod logging;
 pub(crate) mod network;
 pub mod operator;
 mod profiler;
 #[cfg(feature = "ssh")]
 pub(crate) mod runner;
+pub mod savepoint;
 pub(crate) mod scheduler;
+pub mod shutdown;
 pub(crate) mod stream;
-#[cfg(test)]
-pub(crate) mod test;
+pub mod test;
 pub(crate) mod worker;
 
 pub type CoordUInt = u64;
@@ -148,9 +154,11 @@ pub type CoordUInt = u64;
 pub mod prelude {
     pub use super::operator::sink::StreamOutput;
     pub use super::operator::source::*;
-    pub use super::operator::window::{CountWindow, ProcessingTimeWindow, SessionWindow};
+    pub use super::operator::window::{
+        CountWindow, DynamicSessionWindow, ProcessingTimeWindow, SessionWindow,
+    };
     #[cfg(feature = "timestamp")]
     pub use super::operator::window::{EventTimeWindow, TransactionWindow};
     pub use super::Replication;
-    pub use super::{BatchMode, RuntimeConfig, StreamContext};
+    pub use super::{Autoscale, BatchMode, RuntimeConfig, StreamContext};
 }
@@ -0,0 +1,106 @@
+//! Structured logs of the remote workers.
+//!
+//! When [`RemoteConfig::worker_logs`] is enabled, each worker process writes its logs to a file of
+//! its own inside [`RemoteConfig::tracing_dir`], one JSON object per line, instead of mixing them
+//! on the standard error of the runner.
+
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::PathBuf;
+use std::sync::Once;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{LevelFilter, Log, Metadata, Record};
+use parking_lot::Mutex;
+
+use crate::config::RemoteConfig;
+use crate::scheduler::HostId;
+use crate::worker::replica_coord;
+
+/// Logger writing the records as JSON lines, tagged with the host and the replica that emitted
+/// them.
+struct JsonLogger {
+    host_id: HostId,
+    level: LevelFilter,
+    file: Mutex<LineWriter<File>>,
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let mut line = serde_json::json!({
+            "timestamp": timestamp,
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "host_id": self.host_id,
+            "message": record.args().to_string(),
+        });
+        // the records emitted by the worker threads are tagged with their replica
+        if let Some(coord) = replica_coord() {
+            line["coord"] = coord.to_string().into();
+            line["block_id"] = coord.block_id.into();
+            line["replica_id"] = coord.replica_id.into();
+        }
+        // a log that cannot be written has nowhere to be reported
+        let _ = writeln!(self.file.lock(), "{line}");
+    }
+
+    fn flush(&self) {
+        let _ = self.file.lock().flush();
+    }
+}
+
+/// The path of the log file of the host inside the tracing directory.
+fn worker_log_path(config: &RemoteConfig, host_id: HostId) -> Option<PathBuf> {
+    let dir = config.tracing_dir.as_ref()?;
+    Some(dir.join(format!("renoir-worker-{host_id}.log")))
+}
+
+/// Send the logs of this process to its worker log file, if enabled in the configuration.
+///
+/// The logger is installed once per process. The level is read from `RUST_LOG` (e.g. `debug`),
+/// and defaults to `info`.
+pub(crate) fn init_worker_logs(config: &RemoteConfig, host_id: HostId) {
+    static INIT: Once = Once::new();
+    if !config.worker_logs {
+        return;
+    }
+    INIT.call_once(|| {
+        let Some(path) = worker_log_path(config, host_id) else {
+            warn!("worker_logs requires tracing_dir, the logs are not redirected");
+            return;
+        };
+        let file = std::fs::create_dir_all(config.tracing_dir.as_ref().unwrap())
+            .and_then(|_| File::create(&path));
+        let file = match file {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("cannot create the worker log file {}: {e}", path.display());
+                return;
+            }
+        };
+        let level = std::env::var("RUST_LOG")
+            .ok()
+            .and_then(|level| level.parse().ok())
+            .unwrap_or(LevelFilter::Info);
+        let logger = JsonLogger {
+            host_id,
+            level,
+            file: Mutex::new(LineWriter::new(file)),
+        };
+        match log::set_logger(Box::leak(Box::new(logger))) {
+            Ok(()) => log::set_max_level(level),
+            Err(_) => warn!("a logger is already installed, the worker logs are not redirected"),
+        }
+    });
+}
@@ -0,0 +1,153 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+
+use crate::network::{Coord, ReceiverEndpoint};
+use crate::scheduler::{BlockId, ReplicaId};
+
+/// A serialized message waiting to be written to a remote connection, in one or more frames.
+pub(crate) struct OutgoingMessage {
+    /// The receiver of the message.
+    pub dest: ReceiverEndpoint,
+    /// The replica that sent the message.
+    pub sender: Coord,
+    /// The serialized message.
+    pub body: Vec<u8>,
+    /// The number of bytes of `body` already written.
+    pub written: usize,
+}
+
+/// A frame of an [`OutgoingMessage`], to be written after its header.
+pub(crate) struct Frame<'a> {
+    pub dest: ReceiverEndpoint,
+    pub sender: Coord,
+    pub bytes: &'a [u8],
+    /// Whether more frames of the same message follow this one.
+    pub more: bool,
+    /// The size of the whole message, when this is its last frame.
+    pub message_size: Option<usize>,
+}
+
+/// The messages waiting to be written to a remote connection, split into frames.
+///
+/// The frames of the messages for different receivers are interleaved round-robin, so a huge
+/// message does not hold back the messages for the other receivers sharing the same connection.
+/// The messages for the same receiver are written one after the other, in order.
+#[derive(Default)]
+pub(crate) struct FrameQueue {
+    /// The messages waiting for each receiver.
+    pending: HashMap<ReceiverEndpoint, VecDeque<OutgoingMessage>>,
+    /// The receivers with pending messages, in the order their next frame is written.
+    order: VecDeque<ReceiverEndpoint>,
+    len: usize,
+    /// The buffers of the messages already written, reused for the next ones.
+    spare: Vec<Vec<u8>>,
+}
+
+impl FrameQueue {
+    /// Queue a message after the ones for the same receiver.
+    pub(crate) fn push(&mut self, message: OutgoingMessage) {
+        let dest = message.dest;
+        match self.pending.entry(dest) {
+            Entry::Occupied(mut e) => e.get_mut().push_back(message),
+            Entry::Vacant(e) => {
+                e.insert(VecDeque::from([message]));
+                self.order.push_back(dest);
+            }
+        }
+        self.len += 1;
+    }
+
+    /// The number of messages not fully written.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// An empty buffer to serialize the next message into.
+    pub(crate) fn buffer(&mut self) -> Vec<u8> {
+        self.spare.pop().unwrap_or_default()
+    }
+
+    /// The next frame to write, of at most `max_frame_size` bytes, or the whole message if `None`.
+    pub(crate) fn next_frame(&self, max_frame_size: Option<usize>) -> Option<Frame<'_>> {
+        let dest = self.order.front()?;
+        let message = self.pending[dest].front().unwrap();
+        let end = frame_end(message, max_frame_size);
+        let more = end < message.body.len();
+        Some(Frame {
+            dest: message.dest,
+            sender: message.sender,
+            bytes: &message.body[message.written..end],
+            more,
+            message_size: (!more).then_some(message.body.len()),
+        })
+    }
+
+    /// Mark the frame returned by [`FrameQueue::next_frame`] as written, moving to the next
+    /// receiver.
+    pub(crate) fn advance(&mut self, max_frame_size: Option<usize>) {
+        let dest = self.order.pop_front().expect("no frame to advance");
+        let queue = self.pending.get_mut(&dest).unwrap();
+        let message = queue.front_mut().unwrap();
+        message.written = frame_end(message, max_frame_size);
+        if message.written == message.body.len() {
+            let mut body = queue.pop_front().unwrap().body;
+            body.clear();
+            self.spare.push(body);
+            self.len -= 1;
+        }
+        if queue.is_empty() {
+            self.pending.remove(&dest);
+        } else {
+            self.order.push_back(dest);
+        }
+    }
+}
+
+fn frame_end(message: &OutgoingMessage, max_frame_size: Option<usize>) -> usize {
+    let remaining = message.body.len() - message.written;
+    message.written + max_frame_size.map_or(remaining, |max| remaining.min(max))
+}
+
+/// The buffers used to read the frames of a remote connection and to reassemble the messages.
+#[derive(Default)]
+pub(crate) struct FrameBuffers {
+    /// The body of the last frame read.
+    pub frame: Vec<u8>,
+    /// The frames received so far of the incomplete messages, for each receiver.
+    partial: HashMap<(ReplicaId, BlockId), Vec<u8>>,
+    /// The last message reassembled from many frames.
+    message: Vec<u8>,
+}
+
+impl FrameBuffers {
+    /// Add the last frame read to the message for the receiver `key`, returning the whole message
+    /// if it was the last frame.
+    pub(crate) fn push_frame(&mut self, key: (ReplicaId, BlockId), more: bool) -> Option<&[u8]> {
+        match (self.partial.entry(key), more) {
+            // the message fits in a single frame
+            (Entry::Vacant(_), false) => Some(&self.frame),
+            (Entry::Vacant(e), true) => {
+                e.insert(std::mem::take(&mut self.frame));
+                None
+            }
+            (Entry::Occupied(mut e), true) => {
+                e.get_mut().extend_from_slice(&self.frame);
+                None
+            }
+            (Entry::Occupied(e), false) => {
+                self.message = e.remove();
+                self.message.extend_from_slice(&self.frame);
+                Some(&self.message)
+            }
+        }
+    }
+
+    /// Drop the frames received so far of the message for the receiver `key`.
+    pub(crate) fn discard(&mut self, key: (ReplicaId, BlockId)) {
+        self.partial.remove(&key);
+    }
+}
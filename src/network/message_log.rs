@@ -0,0 +1,100 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::network::{Coord, NetworkMessage, ReceiverEndpoint};
+use crate::operator::{ExchangeData, StreamElement};
+
+/// A message sent from a replica to another, as recorded by a [`MessageLog`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedMessage<T> {
+    /// The replica that sent the message.
+    pub sender: Coord,
+    /// The replica that received the message.
+    pub receiver: Coord,
+    /// The elements of the batch, in the order they were sent.
+    pub elements: Vec<StreamElement<T>>,
+}
+
+/// A message of unknown type, the elements are a `Vec<StreamElement<T>>`.
+struct Entry {
+    sender: Coord,
+    receiver: Coord,
+    elements: Box<dyn Any + Send>,
+}
+
+/// The log of all the messages sent between the replicas of a local job.
+///
+/// Build a configuration that records the messages with
+/// [`RuntimeConfig::local_recorded`](crate::RuntimeConfig::local_recorded). Every batch sent
+/// through a connection between two replicas is recorded with its sender and receiver, in the
+/// order it was sent, so that the tests can check exactly what crossed each edge of the job
+/// graph.
+///
+/// Recording clones all the elements sent, so it should be used only for testing.
+#[derive(Clone, Default)]
+pub struct MessageLog {
+    entries: Arc<Mutex<Vec<Entry>>>,
+}
+
+impl MessageLog {
+    pub(crate) fn record<T: ExchangeData>(
+        &self,
+        receiver: ReceiverEndpoint,
+        message: &NetworkMessage<T>,
+    ) {
+        let elements = message.iter().cloned().collect::<Vec<_>>();
+        self.entries.lock().push(Entry {
+            sender: message.sender(),
+            receiver: receiver.coord,
+            elements: Box::new(elements),
+        });
+    }
+
+    /// The messages with elements of type `T` recorded so far, in the order they were sent.
+    ///
+    /// The messages of the connections carrying other types are skipped, so the type selects the
+    /// edges of the job graph to look at.
+    pub fn messages<T: ExchangeData>(&self) -> Vec<RecordedMessage<T>> {
+        self.entries
+            .lock()
+            .iter()
+            .filter_map(|entry| {
+                let elements = entry.elements.downcast_ref::<Vec<StreamElement<T>>>()?;
+                Some(RecordedMessage {
+                    sender: entry.sender,
+                    receiver: entry.receiver,
+                    elements: elements.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// The total number of messages recorded so far, of any type.
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    /// Whether no message has been recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl std::fmt::Debug for MessageLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MessageLog")
+            .field("messages", &self.len())
+            .finish()
+    }
+}
+
+/// Two logs are equal if they are the same log.
+impl PartialEq for MessageLog {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.entries, &other.entries)
+    }
+}
+
+impl Eq for MessageLog {}
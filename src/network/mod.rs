@@ -1,10 +1,15 @@
 use std::fmt::{Debug, Display, Formatter};
 
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
+pub use message_log::{MessageLog, RecordedMessage};
 pub(crate) use network_channel::*;
+pub use replay_log::ReplayLog;
+pub(crate) use replay_log::{ReplicaReplay, Selected};
 pub(crate) use topology::*;
 
+use crate::config::MalformedMessagePolicy;
 use crate::operator::StreamElement;
 use crate::scheduler::{BlockId, HostId, ReplicaId};
 
@@ -18,9 +23,98 @@ mod sync;
 #[cfg(not(feature = "tokio"))]
 use sync::*;
 
+mod frames;
+mod message_log;
 mod network_channel;
+mod replay_log;
 mod topology;
 
+/// The version of the encoding of the messages exchanged between the hosts.
+///
+/// It is sent with each message and checked by the receiver, it must be increased whenever the
+/// encoding of the messages (the header, the bincode configuration or the layout of
+/// [`NetworkMessage`]) changes.
+pub(crate) const PROTOCOL_VERSION: u8 = 2;
+
+/// The reason why a message received from a remote host cannot be decoded.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub(crate) enum ProtocolError {
+    #[error("received a message with protocol version {received} from {address}, but this host uses version {expected}: all the hosts must run the same version of renoir")]
+    Version {
+        received: u8,
+        expected: u8,
+        address: String,
+    },
+    #[error("received a message for {dest} from {address} with items of a different type than `{expected}`: all the hosts must run the same version of the job")]
+    Type {
+        dest: DemuxCoord,
+        address: String,
+        expected: &'static str,
+    },
+    #[error("received a malformed message for {dest} from {address}: {error}")]
+    Malformed {
+        dest: DemuxCoord,
+        address: String,
+        error: String,
+    },
+}
+
+/// Handle a message received from another host that cannot be decoded, according to `policy`.
+///
+/// Returns whether the following messages of the connection should still be read.
+pub(crate) fn on_malformed_message(policy: MalformedMessagePolicy, error: ProtocolError) -> bool {
+    match policy {
+        MalformedMessagePolicy::Fail => panic!("{error}"),
+        MalformedMessagePolicy::SkipMessage => {
+            log::error!("dropping a message: {error}");
+            true
+        }
+        MalformedMessagePolicy::SkipConnection => {
+            log::error!("closing the connection: {error}");
+            false
+        }
+    }
+}
+
+/// A hash of the type `T` that is stable across processes, used to detect the hosts that send a
+/// different type on a channel.
+///
+/// It is based on the name of the type: it tells apart different types, not different versions
+/// of the same type.
+pub(crate) fn type_hash<T>() -> u32 {
+    // FNV-1a, since the std hashers are not guaranteed to be stable
+    std::any::type_name::<T>()
+        .bytes()
+        .fold(0x811c9dc5u32, |hash, byte| {
+            (hash ^ byte as u32).wrapping_mul(0x01000193)
+        })
+}
+
+/// Check that a message received for `dest` from `address` has been encoded with the same
+/// protocol version and the same item type `T` used by this host.
+pub(crate) fn check_protocol<T>(
+    version: u8,
+    hash: u32,
+    dest: DemuxCoord,
+    address: &str,
+) -> Result<(), ProtocolError> {
+    if version != PROTOCOL_VERSION {
+        return Err(ProtocolError::Version {
+            received: version,
+            expected: PROTOCOL_VERSION,
+            address: address.to_string(),
+        });
+    }
+    if hash != type_hash::<T>() {
+        return Err(ProtocolError::Type {
+            dest,
+            address: address.to_string(),
+            expected: std::any::type_name::<T>(),
+        });
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub enum NetworkDataIterator<T> {
     Batch(std::vec::IntoIter<T>),
@@ -98,6 +192,7 @@ pub struct DemuxCoord {
 }
 
 impl<T> NetworkMessage<T> {
+    /// Build a message containing a single element, sent by `sender`.
     pub fn new_single(data: StreamElement<T>, sender: Coord) -> Self {
         Self {
             data: NetworkData::Batch(vec![data]),
@@ -105,6 +200,7 @@ impl<T> NetworkMessage<T> {
         }
     }
 
+    /// Build a message containing a batch of elements, sent by `sender`.
     pub fn new_batch(data: Vec<StreamElement<T>>, sender: Coord) -> Self {
         Self {
             data: NetworkData::Batch(data),
@@ -123,6 +219,13 @@ impl<T> NetworkMessage<T> {
             NetworkData::Batch(v) => v.len(),
         }
     }
+
+    /// Iterate over the elements of the batch without consuming the message.
+    pub fn iter(&self) -> impl Iterator<Item = &StreamElement<T>> {
+        match &self.data {
+            NetworkData::Batch(v) => v.iter(),
+        }
+    }
 }
 
 impl<T> IntoIterator for NetworkMessage<T> {
@@ -219,3 +322,36 @@ impl From<ReceiverEndpoint> for DemuxCoord {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::network::{Coord, NetworkMessage};
+    use crate::operator::StreamElement;
+
+    #[test]
+    fn network_message_introspection() {
+        let sender = Coord::new(1, 2, 3);
+        let message = NetworkMessage::new_batch(
+            vec![
+                StreamElement::Item(1),
+                StreamElement::Item(2),
+                StreamElement::FlushBatch,
+            ],
+            sender,
+        );
+
+        assert_eq!(message.sender(), sender);
+        assert_eq!(message.num_items(), 3);
+        let items = message.iter().cloned().collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            vec![
+                StreamElement::Item(1),
+                StreamElement::Item(2),
+                StreamElement::FlushBatch
+            ]
+        );
+        // iterating does not consume the message
+        assert_eq!(message.into_iter().collect::<Vec<_>>(), items);
+    }
+}
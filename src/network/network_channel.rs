@@ -1,30 +1,38 @@
+use std::collections::VecDeque;
 use std::time::Duration;
 
+use parking_lot::Mutex;
+
 use thiserror::Error;
 
 use crate::channel::{
-    self, Receiver, RecvError, RecvTimeoutError, SelectResult, Sender, TryRecvError,
+    self, PriorityBudget, Receiver, RecvError, RecvTimeoutError, SelectResult, Sender, TryRecvError,
 };
 
-use crate::network::{NetworkMessage, ReceiverEndpoint};
+use crate::network::{
+    Coord, MessageLog, NetworkMessage, ReceiverEndpoint, ReplayLog, ReplicaReplay, Selected,
+};
 use crate::operator::ExchangeData;
 use crate::profiler::{get_profiler, Profiler};
 
-/// The capacity of the in-buffer.
-const CHANNEL_CAPACITY: usize = 16;
+/// The default capacity of the in-buffer, in batches.
+pub(crate) const CHANNEL_CAPACITY: usize = 16;
 
 pub(crate) fn local_channel<T: ExchangeData>(
     receiver_endpoint: ReceiverEndpoint,
+    capacity: usize,
 ) -> (NetworkSender<T>, NetworkReceiver<T>) {
-    let (sender, receiver) = channel::bounded(CHANNEL_CAPACITY);
+    let (sender, receiver) = channel::bounded(capacity);
     (
         NetworkSender {
             receiver_endpoint,
             sender: SenderInner::Local(sender),
+            log: None,
         },
         NetworkReceiver {
             receiver_endpoint,
             receiver,
+            replay: None,
         },
     )
 }
@@ -36,6 +44,7 @@ pub(crate) fn mux_sender<T: ExchangeData>(
     NetworkSender {
         receiver_endpoint,
         sender: SenderInner::Mux(tx),
+        log: None,
     }
 }
 
@@ -56,6 +65,16 @@ pub(crate) struct NetworkReceiver<In: Send + 'static> {
     /// The actual receiver where the users of this struct will wait upon.
     #[derivative(Debug = "ignore")]
     receiver: Receiver<NetworkMessage<In>>,
+    /// If set, the order of the messages received is recorded or replayed.
+    #[derivative(Debug = "ignore")]
+    replay: Option<ReceiverReplay<In>>,
+}
+
+/// The state of a receiver recording or replaying the order of its messages with a [`ReplayLog`].
+struct ReceiverReplay<In> {
+    replica: ReplicaReplay,
+    /// When replaying, the messages received before their turn.
+    stash: Mutex<VecDeque<NetworkMessage<In>>>,
 }
 
 impl<In: Send + 'static> NetworkReceiver<In> {
@@ -74,19 +93,152 @@ impl<In: Send + 'static> NetworkReceiver<In> {
         })
     }
 
+    /// Record or replay the order of the messages received with `log`.
+    pub fn replayed(mut self, log: &ReplayLog) -> Self {
+        self.replay = Some(ReceiverReplay {
+            replica: log.replica(self.receiver_endpoint.coord),
+            stash: Default::default(),
+        });
+        self
+    }
+
+    /// When replaying, the next recorded outcome of a receive, if any is left.
+    fn expected(&self) -> Option<Option<Coord>> {
+        let replay = self.replay.as_ref()?;
+        replay
+            .replica
+            .expected_receive(self.receiver_endpoint.prev_block_id)
+    }
+
+    /// When recording, append the outcome of a receive.
+    fn record<E>(&self, message: &Result<NetworkMessage<In>, E>) {
+        if let Some(replay) = &self.replay {
+            let sender = message.as_ref().ok().map(|m| m.sender);
+            replay
+                .replica
+                .record_receive(self.receiver_endpoint.prev_block_id, sender);
+        }
+    }
+
+    /// A message received before its turn, if any.
+    fn take_stashed(&self) -> Option<NetworkMessage<In>> {
+        self.replay.as_ref()?.stash.lock().pop_front()
+    }
+
+    /// Receive the next message of `sender`, holding back the messages of the other senders.
+    fn recv_from(&self, sender: Coord) -> Result<NetworkMessage<In>, RecvError> {
+        let replay = self.replay.as_ref().unwrap();
+        let mut stash = replay.stash.lock();
+        if let Some(pos) = stash.iter().position(|m| m.sender == sender) {
+            return Ok(stash.remove(pos).unwrap());
+        }
+        loop {
+            let message = self.receiver.recv()?;
+            if message.sender == sender {
+                return Ok(message);
+            }
+            stash.push_back(message);
+        }
+    }
+
+    /// Receive a message from any sender, in the recorded order when replaying.
+    fn next_message(&self) -> Result<NetworkMessage<In>, RecvError> {
+        let message = match self.expected() {
+            Some(Some(sender)) => self.recv_from(sender),
+            _ => match self.take_stashed() {
+                Some(message) => Ok(message),
+                None => self.receiver.recv(),
+            },
+        };
+        self.record(&message);
+        message
+    }
+
     /// Receive a message from any sender.
     pub fn recv(&self) -> Result<NetworkMessage<In>, RecvError> {
-        self.profile_message(self.receiver.recv())
+        self.profile_message(self.next_message())
     }
 
     /// Receive a message from any sender without blocking.
+    ///
+    /// When replaying, this blocks until the message recorded is received.
     pub fn try_recv(&self) -> Result<NetworkMessage<In>, TryRecvError> {
-        self.profile_message(self.receiver.try_recv())
+        let message = match self.expected() {
+            Some(Some(sender)) => self
+                .recv_from(sender)
+                .map_err(|_| TryRecvError::Disconnected),
+            Some(None) => Err(TryRecvError::Empty),
+            None => match self.take_stashed() {
+                Some(message) => Ok(message),
+                None => self.receiver.try_recv(),
+            },
+        };
+        self.record(&message);
+        self.profile_message(message)
     }
 
     /// Receive a message from any sender with a timeout.
+    ///
+    /// When replaying, this waits for the message recorded ignoring the timeout, or times out
+    /// immediately if it timed out when recorded.
     pub fn recv_timeout(&self, timeout: Duration) -> Result<NetworkMessage<In>, RecvTimeoutError> {
-        self.profile_message(self.receiver.recv_timeout(timeout))
+        let message = match self.expected() {
+            Some(Some(sender)) => self
+                .recv_from(sender)
+                .map_err(|_| RecvTimeoutError::Disconnected),
+            Some(None) => Err(RecvTimeoutError::Timeout),
+            None => match self.take_stashed() {
+                Some(message) => Ok(message),
+                None => self.receiver.recv_timeout(timeout),
+            },
+        };
+        self.record(&message);
+        self.profile_message(message)
+    }
+
+    /// When replaying, the next recorded outcome of a select, if any is left.
+    fn expected_select(&self) -> Option<Selected> {
+        self.replay.as_ref()?.replica.expected_select()
+    }
+
+    /// When replaying, the recorded outcome of the select between this receiver and `other`.
+    /// When recording, or when nothing is left to replay, `select` is used to pick one.
+    fn replay_select<In2: ExchangeData>(
+        &self,
+        other: &NetworkReceiver<In2>,
+        select: impl FnOnce() -> Result<
+            SelectResult<NetworkMessage<In>, NetworkMessage<In2>>,
+            RecvTimeoutError,
+        >,
+    ) -> Result<SelectResult<NetworkMessage<In>, NetworkMessage<In2>>, RecvTimeoutError> {
+        match self.expected_select() {
+            Some(Selected::A) => return Ok(SelectResult::A(self.next_message())),
+            Some(Selected::B) => return Ok(SelectResult::B(other.next_message())),
+            Some(Selected::Nothing) => return Err(RecvTimeoutError::Timeout),
+            None => {}
+        }
+        if let Some(message) = self.take_stashed() {
+            return Ok(SelectResult::A(Ok(message)));
+        }
+        if let Some(message) = other.take_stashed() {
+            return Ok(SelectResult::B(Ok(message)));
+        }
+        let result = select();
+        if let Some(replay) = &self.replay {
+            let selected = match &result {
+                Ok(SelectResult::A(message)) => {
+                    self.record(message);
+                    Selected::A
+                }
+                Ok(SelectResult::B(message)) => {
+                    other.record(message);
+                    Selected::B
+                }
+                Err(_) => Selected::Nothing,
+            };
+            replay.replica.record_select(selected);
+        }
+        result
     }
 
     /// Receive a message from any sender of this receiver of the other provided receiver.
@@ -98,7 +250,22 @@ impl<In: Send + 'static> NetworkReceiver<In> {
         &self,
         other: &NetworkReceiver<In2>,
     ) -> SelectResult<NetworkMessage<In>, NetworkMessage<In2>> {
-        self.receiver.select(&other.receiver)
+        self.replay_select(other, || Ok(self.receiver.select(&other.receiver)))
+            .expect("select without a timeout timed out")
+    }
+
+    /// Like `select`, but preferring the messages of this receiver over the ones of `other`,
+    /// while bounding the starvation of `other` with the priority `budget`.
+    #[allow(dead_code)]
+    pub fn select_priority<In2: ExchangeData>(
+        &self,
+        other: &NetworkReceiver<In2>,
+        budget: &mut PriorityBudget,
+    ) -> SelectResult<NetworkMessage<In>, NetworkMessage<In2>> {
+        self.replay_select(other, || {
+            Ok(self.receiver.select_priority(&other.receiver, budget))
+        })
+        .expect("select without a timeout timed out")
     }
 
     /// Same as `select`, with a timeout.
@@ -107,7 +274,9 @@ impl<In: Send + 'static> NetworkReceiver<In> {
         other: &NetworkReceiver<In2>,
         timeout: Duration,
     ) -> Result<SelectResult<NetworkMessage<In>, NetworkMessage<In2>>, RecvTimeoutError> {
-        self.receiver.select_timeout(&other.receiver, timeout)
+        self.replay_select(other, || {
+            self.receiver.select_timeout(&other.receiver, timeout)
+        })
     }
 }
 
@@ -124,6 +293,8 @@ pub(crate) struct NetworkSender<Out: Send + 'static> {
     /// The generic sender that will send the message either locally or remotely.
     #[derivative(Debug = "ignore")]
     sender: SenderInner<Out>,
+    /// If set, all the messages sent are recorded in this log.
+    log: Option<MessageLog>,
 }
 
 #[derive(Clone)]
@@ -133,20 +304,31 @@ enum SenderInner<Out: Send + 'static> {
 }
 
 impl<Out: ExchangeData> NetworkSender<Out> {
+    /// Record all the messages sent by this sender in `log`.
+    pub fn recorded(mut self, log: MessageLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
     pub fn send(&self, message: NetworkMessage<Out>) -> Result<(), NetworkSendError> {
         get_profiler().items_out(
             message.sender,
             self.receiver_endpoint.coord,
             message.num_items(),
         );
+        if let Some(log) = &self.log {
+            log.record(self.receiver_endpoint, &message);
+        }
 
+        let disconnected = NetworkSendError::Disconnected {
+            sender: message.sender,
+            receiver: self.receiver_endpoint,
+        };
         match &self.sender {
             SenderInner::Mux(tx) => tx
                 .send((self.receiver_endpoint, message))
-                .map_err(|_| NetworkSendError::Disconnected(self.receiver_endpoint)),
-            SenderInner::Local(tx) => tx
-                .send(message)
-                .map_err(|_| NetworkSendError::Disconnected(self.receiver_endpoint)),
+                .map_err(|_| disconnected),
+            SenderInner::Local(tx) => tx.send(message).map_err(|_| disconnected),
         }
     }
 
@@ -160,6 +342,42 @@ impl<Out: ExchangeData> NetworkSender<Out> {
 
 #[derive(Debug, Error)]
 pub enum NetworkSendError {
-    #[error("channel disconnected")]
-    Disconnected(ReceiverEndpoint),
+    /// The receiver of the channel is gone, the error names the replicas at both ends.
+    #[error(
+        "block {} replica {} (host {}) → block {} replica {} (host {}) disconnected",
+        sender.block_id,
+        sender.replica_id,
+        sender.host_id,
+        receiver.coord.block_id,
+        receiver.coord.replica_id,
+        receiver.coord.host_id
+    )]
+    Disconnected {
+        /// The replica sending the message.
+        sender: Coord,
+        /// The endpoint of the receiving replica.
+        receiver: ReceiverEndpoint,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::network::{
+        local_channel, Coord, NetworkMessage, ReceiverEndpoint, CHANNEL_CAPACITY,
+    };
+    use crate::operator::StreamElement;
+
+    #[test]
+    fn disconnected_names_both_endpoints() {
+        let receiver = ReceiverEndpoint::new(Coord::new(5, 1, 0), 3);
+        let (tx, rx) = local_channel::<u32>(receiver, CHANNEL_CAPACITY);
+        drop(rx);
+
+        let message = NetworkMessage::new_single(StreamElement::Item(42), Coord::new(3, 0, 2));
+        let err = tx.send(message).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "block 3 replica 2 (host 0) → block 5 replica 0 (host 1) disconnected"
+        );
+    }
 }
@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::network::Coord;
+use crate::scheduler::BlockId;
+
+/// Which of the two receivers of a `select` returned, as recorded by a [`ReplayLog`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Selected {
+    /// The first receiver.
+    A,
+    /// The second receiver.
+    B,
+    /// Neither, the select timed out.
+    Nothing,
+}
+
+/// The choices made by the receivers of a replica, in order.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ReplicaLog {
+    coord: Coord,
+    /// For each previous block, the sender of each message received, or `None` when a receive
+    /// returned no message.
+    receives: BTreeMap<BlockId, VecDeque<Option<Coord>>>,
+    /// The outcome of each select between two receivers.
+    selects: VecDeque<Selected>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Record,
+    Replay,
+}
+
+struct Inner {
+    mode: Mode,
+    dir: PathBuf,
+    replicas: Mutex<HashMap<Coord, Arc<Mutex<ReplicaLog>>>>,
+}
+
+/// The order in which the replicas of a local job received their messages, to run the job again
+/// in the same order.
+///
+/// With many senders connected to the same replica, or a replica selecting between two inputs,
+/// the order of the messages received depends on the scheduling of the threads, so a bug may
+/// show up only occasionally. A log built with [`ReplayLog::record`] records, for each replica,
+/// the sender of each message it received and the outcome of each select, and it is saved to a
+/// file for each replica inside its directory when the execution ends. A log loaded with
+/// [`ReplayLog::replay`] makes a new execution of the same job receive the messages in the
+/// recorded order, holding back the messages arriving earlier.
+///
+/// Set it with [`LocalConfigBuilder::replay_log`](crate::config::LocalConfigBuilder::replay_log).
+/// The replay is deterministic as long as the sources and the operators are: the log does not
+/// record the elements emitted by the sources, nor the choices of the random routing (like
+/// [`Stream::shuffle`](crate::Stream::shuffle)).
+///
+/// ```no_run
+/// # use renoir::{StreamContext, ReplayLog};
+/// # use renoir::config::LocalConfigBuilder;
+/// let log = ReplayLog::record("/tmp/replay");
+/// let config = LocalConfigBuilder::new(4).replay_log(log).build().unwrap();
+/// // ... run the job, and then run it again in the same order
+/// let log = ReplayLog::replay("/tmp/replay").unwrap();
+/// let config = LocalConfigBuilder::new(4).replay_log(log).build().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct ReplayLog {
+    inner: Arc<Inner>,
+}
+
+impl ReplayLog {
+    /// Record the order of the messages received, saving it inside `dir` when the execution ends.
+    pub fn record(dir: impl Into<PathBuf>) -> Self {
+        Self::new(Mode::Record, dir.into(), HashMap::new())
+    }
+
+    /// Load the order of the messages recorded inside `dir`, to receive them in the same order.
+    pub fn replay(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        let mut replicas = HashMap::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if !is_replica_log(&path) {
+                continue;
+            }
+            let file = BufReader::new(std::fs::File::open(&path)?);
+            let log: ReplicaLog = serde_json::from_reader(file)?;
+            replicas.insert(log.coord, Arc::new(Mutex::new(log)));
+        }
+        Ok(Self::new(Mode::Replay, dir, replicas))
+    }
+
+    fn new(mode: Mode, dir: PathBuf, replicas: HashMap<Coord, Arc<Mutex<ReplicaLog>>>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                mode,
+                dir,
+                replicas: Mutex::new(replicas),
+            }),
+        }
+    }
+
+    /// The directory of the log.
+    pub fn dir(&self) -> &Path {
+        &self.inner.dir
+    }
+
+    /// Whether the log replays a recorded execution.
+    pub fn is_replay(&self) -> bool {
+        self.inner.mode == Mode::Replay
+    }
+
+    /// The log of the replica with the given coordinates.
+    pub(crate) fn replica(&self, coord: Coord) -> ReplicaReplay {
+        let log = self
+            .inner
+            .replicas
+            .lock()
+            .entry(coord)
+            .or_insert_with(|| {
+                Arc::new(Mutex::new(ReplicaLog {
+                    coord,
+                    ..Default::default()
+                }))
+            })
+            .clone();
+        ReplicaReplay {
+            mode: self.inner.mode,
+            log,
+        }
+    }
+
+    /// Save the recorded order to a file for each replica, doing nothing when replaying.
+    pub(crate) fn save(&self) -> std::io::Result<()> {
+        if self.is_replay() {
+            return Ok(());
+        }
+        std::fs::create_dir_all(&self.inner.dir)?;
+        for (coord, log) in self.inner.replicas.lock().iter() {
+            let name = format!(
+                "replay-{}-{}-{}.json",
+                coord.block_id, coord.host_id, coord.replica_id
+            );
+            let file = BufWriter::new(std::fs::File::create(self.inner.dir.join(name))?);
+            serde_json::to_writer(file, &*log.lock())?;
+        }
+        Ok(())
+    }
+}
+
+fn is_replica_log(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    name.starts_with("replay-") && name.ends_with(".json")
+}
+
+impl std::fmt::Debug for ReplayLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplayLog")
+            .field("mode", &self.inner.mode)
+            .field("dir", &self.inner.dir)
+            .finish()
+    }
+}
+
+/// Two logs are equal if they are the same log.
+impl PartialEq for ReplayLog {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for ReplayLog {}
+
+/// The part of a [`ReplayLog`] of a single replica, shared by its receivers.
+#[derive(Clone)]
+pub(crate) struct ReplicaReplay {
+    mode: Mode,
+    log: Arc<Mutex<ReplicaLog>>,
+}
+
+impl ReplicaReplay {
+    /// When replaying, the next recorded outcome of a receive from `prev_block`, if any is left.
+    pub(crate) fn expected_receive(&self, prev_block: BlockId) -> Option<Option<Coord>> {
+        if self.mode == Mode::Record {
+            return None;
+        }
+        self.log.lock().receives.get_mut(&prev_block)?.pop_front()
+    }
+
+    /// When recording, append the outcome of a receive from `prev_block`.
+    pub(crate) fn record_receive(&self, prev_block: BlockId, sender: Option<Coord>) {
+        if self.mode == Mode::Record {
+            let mut log = self.log.lock();
+            log.receives
+                .entry(prev_block)
+                .or_default()
+                .push_back(sender);
+        }
+    }
+
+    /// When replaying, the next recorded outcome of a select, if any is left.
+    pub(crate) fn expected_select(&self) -> Option<Selected> {
+        if self.mode == Mode::Record {
+            return None;
+        }
+        self.log.lock().selects.pop_front()
+    }
+
+    /// When recording, append the outcome of a select.
+    pub(crate) fn record_select(&self, selected: Selected) {
+        if self.mode == Mode::Record {
+            self.log.lock().selects.push_back(selected);
+        }
+    }
+}
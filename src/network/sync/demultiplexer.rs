@@ -5,6 +5,8 @@ use std::collections::HashMap;
 use std::net::ToSocketAddrs;
 
 use crate::channel::{self, Sender, UnboundedReceiver, UnboundedSender};
+use crate::config::MalformedMessagePolicy;
+use crate::network::frames::FrameBuffers;
 use crate::network::remote::remote_recv;
 use crate::network::{DemuxCoord, NetworkMessage, ReceiverEndpoint};
 use crate::operator::ExchangeData;
@@ -33,6 +35,7 @@ impl<In: ExchangeData> DemuxHandle<In> {
         coord: DemuxCoord,
         address: (String, u16),
         num_clients: usize,
+        policy: MalformedMessagePolicy,
     ) -> (Self, JoinHandle<()>) {
         let (tx_senders, rx_senders) = channel::unbounded();
         let join_handle = std::thread::Builder::new()
@@ -40,7 +43,7 @@ impl<In: ExchangeData> DemuxHandle<In> {
                 "reg-{}:{}-{}",
                 coord.coord.host_id, coord.prev_block_id, coord.coord.block_id
             ))
-            .spawn(move || bind_remotes(coord, address, num_clients, rx_senders))
+            .spawn(move || bind_remotes(coord, address, num_clients, policy, rx_senders))
             .unwrap();
         (Self { coord, tx_senders }, join_handle)
     }
@@ -63,6 +66,7 @@ fn bind_remotes<In: ExchangeData>(
     coord: DemuxCoord,
     address: (String, u16),
     num_clients: usize,
+    policy: MalformedMessagePolicy,
     rx_senders: UnboundedReceiver<(ReceiverEndpoint, Sender<NetworkMessage<In>>)>,
 ) {
     let address = (address.0.as_ref(), address.1);
@@ -124,7 +128,7 @@ fn bind_remotes<In: ExchangeData>(
                     senders.insert(endpoint, sender);
                 }
                 log::debug!("{coord} got senders");
-                demux_thread::<In>(coord, senders, stream);
+                demux_thread::<In>(coord, senders, stream, policy);
             })
             .unwrap();
         join_handles.push(join_handle);
@@ -165,6 +169,7 @@ fn demux_thread<In: ExchangeData>(
     coord: DemuxCoord,
     senders: HashMap<ReceiverEndpoint, Sender<NetworkMessage<In>>>,
     mut stream: TcpStream,
+    policy: MalformedMessagePolicy,
 ) {
     let address = stream
         .peer_addr()
@@ -175,7 +180,9 @@ fn demux_thread<In: ExchangeData>(
     // let mut r = std::io::BufReader::new(&mut stream);
     let mut r = &mut stream;
 
-    while let Some((dest, message)) = remote_recv(coord, &mut r, &address) {
+    // the read buffers are reused for all the messages of this connection
+    let mut buf = FrameBuffers::default();
+    while let Some((dest, message)) = remote_recv(coord, &mut r, &mut buf, &address, policy) {
         if let Err(e) = senders[&dest].send(message) {
             warn!("demux failed to send message to {}: {:?}", dest, e);
         }
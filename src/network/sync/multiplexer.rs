@@ -1,11 +1,12 @@
 use std::io::ErrorKind;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use std::net::{Shutdown, TcpStream, ToSocketAddrs};
 use std::thread::{sleep, JoinHandle};
 
 use crate::channel::{self, Receiver, Sender};
-use crate::network::remote::remote_send;
+use crate::network::frames::FrameQueue;
+use crate::network::remote::{encode_message, write_frame};
 use crate::network::{DemuxCoord, NetworkMessage, ReceiverEndpoint};
 use crate::operator::ExchangeData;
 
@@ -14,8 +15,6 @@ use crate::operator::ExchangeData;
 
 use crate::network::NetworkSender;
 
-/// Maximum number of attempts to make for connecting to a remote host.
-const CONNECT_ATTEMPTS: usize = 32;
 /// Timeout for connecting to a remote host.
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 /// To avoid spamming the connections, wait this timeout before trying again. If the connection
@@ -24,7 +23,6 @@ const RETRY_INITIAL_TIMEOUT: Duration = Duration::from_millis(8);
 /// Maximum timeout between connection attempts.
 const RETRY_MAX_TIMEOUT: Duration = Duration::from_secs(1);
 
-const MUX_CHANNEL_CAPACITY: usize = 10;
 /// Like `NetworkSender`, but this should be used in a multiplexed channel (i.e. a remote one).
 ///
 /// The `ReceiverEndpoint` is sent alongside the actual message in order to demultiplex it.
@@ -34,8 +32,14 @@ pub struct MultiplexingSender<Out: ExchangeData> {
 }
 
 impl<Out: ExchangeData> MultiplexingSender<Out> {
-    pub fn new(coord: DemuxCoord, address: (String, u16)) -> (Self, JoinHandle<()>) {
-        let (tx, rx) = channel::bounded(MUX_CHANNEL_CAPACITY);
+    pub fn new(
+        coord: DemuxCoord,
+        address: (String, u16),
+        max_inflight_batches: usize,
+        max_frame_size: Option<usize>,
+        connect_deadline: Duration,
+    ) -> (Self, JoinHandle<()>) {
+        let (tx, rx) = channel::bounded(max_inflight_batches);
 
         let join_handle = std::thread::Builder::new()
             .name(format!(
@@ -47,9 +51,9 @@ impl<Out: ExchangeData> MultiplexingSender<Out> {
                     "mux {coord} connecting to {}",
                     address.to_socket_addrs().unwrap().next().unwrap()
                 );
-                let stream = connect_remote(coord, address);
+                let stream = connect_remote(coord, address, connect_deadline);
 
-                mux_thread::<Out>(coord, rx, stream);
+                mux_thread::<Out>(coord, rx, stream, max_inflight_batches, max_frame_size);
             })
             .unwrap();
         (Self { tx: Some(tx) }, join_handle)
@@ -63,17 +67,20 @@ impl<Out: ExchangeData> MultiplexingSender<Out> {
 /// Connect the sender to a remote channel located at the specified address.
 ///
 /// - At first the address is resolved to an actual address (DNS resolution)
-/// - Then at most `CONNECT_ATTEMPTS` are performed, and an exponential backoff is used in case
-///   of errors.
+/// - Then the connection is attempted until `deadline` expires, and an exponential backoff is
+///   used in case of errors.
 /// - If the connection cannot be established this function will panic.
-fn connect_remote(coord: DemuxCoord, address: (String, u16)) -> TcpStream {
+fn connect_remote(coord: DemuxCoord, address: (String, u16), deadline: Duration) -> TcpStream {
     let socket_addrs: Vec<_> = address
         .to_socket_addrs()
         .map_err(|e| format!("Failed to get the address for {coord}: {e:?}",))
         .unwrap()
         .collect();
+    let start = Instant::now();
     let mut retry_delay = RETRY_INITIAL_TIMEOUT;
-    for attempt in 1..=CONNECT_ATTEMPTS {
+    let mut attempt = 0;
+    while start.elapsed() < deadline {
+        attempt += 1;
         log::debug!(
             "{} connecting to {:?} ({} attempt)",
             coord,
@@ -82,7 +89,11 @@ fn connect_remote(coord: DemuxCoord, address: (String, u16)) -> TcpStream {
         );
 
         for address in socket_addrs.iter() {
-            match TcpStream::connect_timeout(address, CONNECT_TIMEOUT) {
+            let timeout = CONNECT_TIMEOUT.min(deadline.saturating_sub(start.elapsed()));
+            if timeout.is_zero() {
+                break;
+            }
+            match TcpStream::connect_timeout(address, timeout) {
                 Ok(stream) => {
                     return stream;
                 }
@@ -112,16 +123,21 @@ fn connect_remote(coord: DemuxCoord, address: (String, u16)) -> TcpStream {
             retry_delay.as_secs_f32(),
         );
 
-        sleep(retry_delay);
+        sleep(retry_delay.min(deadline.saturating_sub(start.elapsed())));
         retry_delay = (2 * retry_delay).min(RETRY_MAX_TIMEOUT);
     }
-    panic!("Failed to connect to remote {coord} at {address:?} after {CONNECT_ATTEMPTS} attempts",);
+    panic!(
+        "Failed to connect to remote {coord} at {}:{} within {deadline:?} ({attempt} attempts)",
+        address.0, address.1
+    );
 }
 
 fn mux_thread<Out: ExchangeData>(
     coord: DemuxCoord,
     rx: Receiver<(ReceiverEndpoint, NetworkMessage<Out>)>,
     mut stream: TcpStream,
+    max_inflight_batches: usize,
+    max_frame_size: Option<usize>,
 ) {
     use std::io::Write;
 
@@ -134,11 +150,150 @@ fn mux_thread<Out: ExchangeData>(
     // let mut w = std::io::BufWriter::new(&mut stream);
     let mut w = &mut stream;
 
-    while let Ok((dest, message)) = rx.recv() {
-        remote_send(message, dest, &mut w, &address);
+    // the serialization buffers are reused for all the messages of this connection
+    let (mut frames, mut buf) = (FrameQueue::default(), Vec::new());
+    loop {
+        if frames.is_empty() {
+            let Ok((dest, message)) = rx.recv() else {
+                break;
+            };
+            let body = frames.buffer();
+            frames.push(encode_message(&message, dest, body));
+        }
+        // take the other messages ready, to interleave their frames with the ones in progress
+        while max_frame_size.is_some() && frames.len() < max_inflight_batches {
+            let Ok((dest, message)) = rx.try_recv() else {
+                break;
+            };
+            let body = frames.buffer();
+            frames.push(encode_message(&message, dest, body));
+        }
+        write_frame::<Out, _>(&mut frames, max_frame_size, &mut w, &mut buf, &address);
     }
 
     w.flush().unwrap();
     let _ = stream.shutdown(Shutdown::Both);
     log::debug!("{} finished", coord);
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::network::{Coord, DemuxCoord, NetworkMessage, ReceiverEndpoint};
+    use crate::operator::StreamElement;
+
+    use super::MultiplexingSender;
+
+    /// Send large batches to a host that does not read them, and count how many are accepted
+    /// before the sender blocks.
+    fn accepted_batches(max_inflight_batches: usize) -> usize {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let from = Coord::new(0, 0, 0);
+        let to = Coord::new(1, 1, 0);
+        let (mut mux, join_handle) = MultiplexingSender::<Vec<u8>>::new(
+            DemuxCoord::new(from, to),
+            ("127.0.0.1".to_string(), port),
+            max_inflight_batches,
+            None,
+            Duration::from_secs(10),
+        );
+        let sender = mux.get_sender(ReceiverEndpoint::new(to, from.block_id));
+        drop(mux);
+        let (mut stream, _) = listener.accept().unwrap();
+
+        let sent = Arc::new(AtomicUsize::new(0));
+        let producer = {
+            let sent = sent.clone();
+            std::thread::spawn(move || {
+                for _ in 0..40 {
+                    let batch = vec![StreamElement::Item(vec![0u8; 4 << 20])];
+                    sender.send(NetworkMessage::new_batch(batch, from)).unwrap();
+                    sent.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        };
+        std::thread::sleep(Duration::from_millis(500));
+        let accepted = sent.load(Ordering::SeqCst);
+
+        // read everything to let the sender and the multiplexer finish
+        std::io::copy(&mut stream, &mut std::io::sink()).unwrap();
+        producer.join().unwrap();
+        join_handle.join().unwrap();
+        accepted
+    }
+
+    #[test]
+    fn max_inflight_batches() {
+        // the socket buffers some batches in both cases, the others wait in the queue
+        let small = accepted_batches(1);
+        let large = accepted_batches(16);
+        assert!(small < 40, "{small} batches accepted with 1 in flight");
+        assert!(
+            large >= small + 10,
+            "{large} batches accepted with 16 in flight, {small} with 1"
+        );
+    }
+
+    /// A port with nothing listening on it.
+    fn free_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    #[test]
+    fn connect_to_late_receiver() {
+        let port = free_port();
+        let from = Coord::new(0, 0, 0);
+        let to = Coord::new(1, 1, 0);
+        let (mut mux, join_handle) = MultiplexingSender::<u32>::new(
+            DemuxCoord::new(from, to),
+            ("127.0.0.1".to_string(), port),
+            1,
+            None,
+            Duration::from_secs(10),
+        );
+        let sender = mux.get_sender(ReceiverEndpoint::new(to, from.block_id));
+        drop(mux);
+
+        // the receiver starts listening after the first attempts were refused
+        std::thread::sleep(Duration::from_millis(500));
+        let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+
+        sender
+            .send(NetworkMessage::new_single(StreamElement::Item(42), from))
+            .unwrap();
+        drop(sender);
+        let mut received = Vec::new();
+        stream.read_to_end(&mut received).unwrap();
+        assert!(!received.is_empty());
+        join_handle.join().unwrap();
+    }
+
+    #[test]
+    fn connect_gives_up_after_deadline() {
+        let port = free_port();
+        let from = Coord::new(0, 0, 0);
+        let to = Coord::new(1, 1, 0);
+        let (_mux, join_handle) = MultiplexingSender::<u32>::new(
+            DemuxCoord::new(from, to),
+            ("127.0.0.1".to_string(), port),
+            1,
+            None,
+            Duration::from_millis(300),
+        );
+
+        let err = join_handle.join().unwrap_err();
+        let message = err.downcast_ref::<String>().unwrap();
+        assert!(
+            message.contains(&format!("127.0.0.1:{port}")),
+            "the error does not name the endpoint: {message}"
+        );
+    }
+}
@@ -4,11 +4,19 @@ use std::io::Read;
 #[cfg(not(feature = "tokio"))]
 use std::io::Write;
 
-use bincode::config::{FixintEncoding, RejectTrailing, WithOtherIntEncoding, WithOtherTrailing};
+use bincode::config::{
+    FixintEncoding, LittleEndian, RejectTrailing, VarintEncoding, WithOtherEndian,
+    WithOtherIntEncoding, WithOtherTrailing,
+};
 use bincode::{DefaultOptions, Options};
 use serde::{Deserialize, Serialize};
 
-use crate::network::{Coord, DemuxCoord, NetworkMessage, ReceiverEndpoint};
+use crate::config::MalformedMessagePolicy;
+use crate::network::frames::{FrameBuffers, FrameQueue, OutgoingMessage};
+use crate::network::{
+    check_protocol, on_malformed_message, type_hash, Coord, DemuxCoord, NetworkMessage,
+    ProtocolError, ReceiverEndpoint, PROTOCOL_VERSION,
+};
 use crate::operator::ExchangeData;
 use crate::profiler::{get_profiler, Profiler};
 use crate::scheduler::BlockId;
@@ -23,129 +31,419 @@ static BINCODE_HEADER_CONFIG: Lazy<
         .reject_trailing_bytes()
 });
 
-static BINCODE_MSG_CONFIG: Lazy<DefaultOptions> = Lazy::new(bincode::DefaultOptions::new);
+type MsgConfig = WithOtherTrailing<
+    WithOtherEndian<WithOtherIntEncoding<DefaultOptions, VarintEncoding>, LittleEndian>,
+    RejectTrailing,
+>;
 
-pub(crate) const HEADER_SIZE: usize = 20; // std::mem::size_of::<MessageHeader>();
+/// Configuration of the message serializer: variable length integers, little endian, no size
+/// limit (the size is checked by the header) and no trailing bytes.
+///
+/// Every setting is explicit so that the encoding does not depend on the defaults of bincode: any
+/// change must increase [`PROTOCOL_VERSION`].
+static BINCODE_MSG_CONFIG: Lazy<MsgConfig> = Lazy::new(|| {
+    bincode::DefaultOptions::new()
+        .with_varint_encoding()
+        .with_little_endian()
+        .reject_trailing_bytes()
+});
+
+pub(crate) const HEADER_SIZE: usize = 26; // std::mem::size_of::<MessageHeader>();
 
-/// Header of a message sent before the actual message.
+/// Header of a frame sent before its bytes.
 #[derive(Serialize, Deserialize, Default)]
 struct MessageHeader {
-    /// The size of the actual message
+    /// The version of the protocol used by the sender.
+    version: u8,
+    /// The hash of the type of the items in the message.
+    type_hash: u32,
+    /// The size of the frame
     size: u32,
+    /// Whether more frames of the same message follow.
+    more: bool,
     /// The id of the replica this message is for.
     replica_id: ReplicaId,
     /// The id of the block that is sending the message.
     sender_block_id: BlockId,
 }
 
-/// Serialize and send a message to a remote socket.
+/// Serialize a message for a remote receiver, into `body`, which is cleared first.
 ///
-/// The network protocol works as follow:
-/// - send a `MessageHeader` serialized with bincode with `FixintEncoding`
-/// - send the message
+/// Reusing the buffers of the messages already written (see [`FrameQueue::buffer`]) avoids an
+/// allocation per message.
 #[cfg(not(feature = "tokio"))]
-pub(crate) fn remote_send<T: ExchangeData, W: Write>(
-    msg: NetworkMessage<T>,
+pub(crate) fn encode_message<T: ExchangeData>(
+    msg: &NetworkMessage<T>,
     dest: ReceiverEndpoint,
-    writer: &mut W,
-    address: &str,
-) {
-    let serialized_len = BINCODE_MSG_CONFIG
-        .serialized_size(&msg)
+    mut body: Vec<u8>,
+) -> OutgoingMessage {
+    let serialized_len = BINCODE_MSG_CONFIG.serialized_size(msg).unwrap_or_else(|e| {
+        panic!("Failed to compute serialized length of outgoing message to {dest}: {e:?}",)
+    });
+
+    body.clear();
+    body.reserve(serialized_len as usize);
+    BINCODE_MSG_CONFIG
+        .serialize_into(&mut body, msg)
         .unwrap_or_else(|e| {
-            panic!("Failed to compute serialized length of outgoing message to {dest}: {e:?}",)
+            panic!("Failed to serialize message, {serialized_len} bytes to {dest}: {e:?}",)
         });
+    assert_eq!(body.len(), serialized_len as usize);
 
+    OutgoingMessage {
+        dest,
+        sender: msg.sender,
+        body,
+        written: 0,
+    }
+}
+
+/// Send the next frame of the messages in `frames` to a remote socket.
+///
+/// The frame is built into `buf`, which is reused for all the frames of a connection.
+///
+/// The network protocol works as follow:
+/// - send a `MessageHeader` serialized with bincode with `FixintEncoding`, it contains the
+///   protocol version and the hash of the item type, checked by the receiver
+/// - send at most `max_frame_size` bytes of the serialized message, the header tells whether more
+///   frames of the same message follow
+#[cfg(not(feature = "tokio"))]
+pub(crate) fn write_frame<T: ExchangeData, W: Write>(
+    frames: &mut FrameQueue,
+    max_frame_size: Option<usize>,
+    writer: &mut W,
+    buf: &mut Vec<u8>,
+    address: &str,
+) {
+    let Some(frame) = frames.next_frame(max_frame_size) else {
+        return;
+    };
+    let dest = frame.dest;
     let header = MessageHeader {
-        size: serialized_len.try_into().unwrap(),
+        version: PROTOCOL_VERSION,
+        type_hash: type_hash::<T>(),
+        size: frame.bytes.len().try_into().unwrap(),
+        more: frame.more,
         replica_id: dest.coord.replica_id,
         sender_block_id: dest.prev_block_id,
     };
 
-    let mut buf = Vec::with_capacity(HEADER_SIZE + serialized_len as usize);
-
+    buf.clear();
+    buf.reserve(HEADER_SIZE + frame.bytes.len());
     BINCODE_HEADER_CONFIG
-        .serialize_into(&mut buf, &header)
+        .serialize_into(&mut *buf, &header)
         .unwrap_or_else(|e| {
-            panic!(
-                "Failed to serialize header of message (was {serialized_len} bytes) to {dest} at {address}: {e:?}",
-            )
-        });
-
-    BINCODE_MSG_CONFIG
-        .serialize_into(&mut buf, &msg)
-        .unwrap_or_else(|e| {
-            panic!(
-                "Failed to serialize message, {serialized_len} bytes to {dest} at {address}: {e:?}",
-            )
+            panic!("Failed to serialize header of frame to {dest} at {address}: {e:?}")
         });
-
-    assert_eq!(buf.len(), HEADER_SIZE + serialized_len as usize);
+    buf.extend_from_slice(frame.bytes);
 
     writer.write_all(buf.as_ref()).unwrap_or_else(|e| {
-        panic!("Failed to send message {serialized_len} bytes to {dest} at {address}: {e:?}",)
+        panic!(
+            "Failed to send frame of {} bytes to {dest} at {address}: {e:?}",
+            frame.bytes.len()
+        )
     });
 
-    get_profiler().net_bytes_out(
-        msg.sender,
-        dest.coord,
-        HEADER_SIZE + serialized_len as usize,
-    );
+    if let Some(size) = frame.message_size {
+        get_profiler().net_bytes_out(frame.sender, dest.coord, HEADER_SIZE + size);
+    }
+    frames.advance(max_frame_size);
 }
 
 /// Receive a message from the remote channel. Returns `None` if there was a failure receiving the
 /// last message.
 ///
+/// The messages that cannot be decoded are handled according to `policy`: they are skipped, or
+/// they close the connection returning `None`.
+///
 /// The message won't be deserialized, use `deserialize()`.
+///
+/// The frames are read into `frames`, which are reused across the messages of a connection, until
+/// a message is complete.
 #[cfg(not(feature = "tokio"))]
 pub(crate) fn remote_recv<T: ExchangeData, R: Read>(
     coord: DemuxCoord,
     reader: &mut R,
+    frames: &mut FrameBuffers,
     address: &str,
+    policy: MalformedMessagePolicy,
 ) -> Option<(ReceiverEndpoint, NetworkMessage<T>)> {
-    let mut header = [0u8; HEADER_SIZE];
-    match reader.read_exact(&mut header) {
-        Ok(_) => {}
-        Err(e) => {
-            log::trace!(
-                "Failed to receive {} bytes of header to {} from {}: {:?}",
-                HEADER_SIZE,
-                coord,
-                address,
-                e
-            );
+    loop {
+        let mut header = [0u8; HEADER_SIZE];
+        match reader.read_exact(&mut header) {
+            Ok(_) => {}
+            Err(e) => {
+                log::trace!(
+                    "Failed to receive {} bytes of header to {} from {}: {:?}",
+                    HEADER_SIZE,
+                    coord,
+                    address,
+                    e
+                );
+                return None;
+            }
+        }
+        let header: MessageHeader = BINCODE_HEADER_CONFIG
+            .deserialize(&header)
+            .expect("Malformed header");
+        let checked = check_protocol::<T>(header.version, header.type_hash, coord, address);
+        // the frame is read even if it cannot be decoded, to skip to the next one
+        frames.frame.clear();
+        frames.frame.resize(header.size as usize, 0);
+        reader.read_exact(&mut frames.frame).unwrap_or_else(|e| {
+            panic!(
+                "Failed to receive {} bytes to {} from {}: {:?}",
+                header.size, coord, address, e
+            )
+        });
+        let key = (header.replica_id, header.sender_block_id);
+        if let Err(e) = checked {
+            frames.discard(key);
+            if on_malformed_message(policy, e) {
+                continue;
+            }
             return None;
         }
+        let Some(message) = frames.push_frame(key, header.more) else {
+            continue;
+        };
+        let size = message.len();
+        let msg = BINCODE_MSG_CONFIG
+            .deserialize::<NetworkMessage<T>>(message)
+            .map_err(|e| ProtocolError::Malformed {
+                dest: coord,
+                address: address.to_string(),
+                error: e.to_string(),
+            });
+        let msg = match msg {
+            Ok(msg) => msg,
+            Err(e) => {
+                if on_malformed_message(policy, e) {
+                    continue;
+                }
+                return None;
+            }
+        };
+
+        let dest = ReceiverEndpoint::new(
+            Coord::new(coord.coord.block_id, coord.coord.host_id, header.replica_id),
+            header.sender_block_id,
+        );
+        get_profiler().net_bytes_in(msg.sender, dest.coord, HEADER_SIZE + size);
+        return Some((dest, msg));
     }
-    let header: MessageHeader = BINCODE_HEADER_CONFIG
-        .deserialize(&header)
-        .expect("Malformed header");
-    let mut buf = vec![0u8; header.size as usize];
-    reader.read_exact(&mut buf).unwrap_or_else(|e| {
-        panic!(
-            "Failed to receive {} bytes to {} from {}: {:?}",
-            header.size, coord, address, e
-        )
-    });
-    let msg: NetworkMessage<T> = BINCODE_MSG_CONFIG
-        .deserialize(buf.as_ref())
-        .expect("Malformed message");
-
-    let dest = ReceiverEndpoint::new(
-        Coord::new(coord.coord.block_id, coord.coord.host_id, header.replica_id),
-        header.sender_block_id,
-    );
-    get_profiler().net_bytes_in(msg.sender, dest.coord, HEADER_SIZE + header.size as usize);
-    Some((dest, msg))
 }
 
 #[cfg(test)]
 mod tests {
     use bincode::Options;
 
+    use crate::config::MalformedMessagePolicy::{self, Fail, SkipConnection, SkipMessage};
+    use crate::network::frames::{FrameBuffers, FrameQueue};
     use crate::network::remote::HEADER_SIZE;
+    use crate::network::{Coord, DemuxCoord, NetworkMessage, ReceiverEndpoint};
+    use crate::operator::{ExchangeData, StreamElement};
 
-    use super::{MessageHeader, BINCODE_HEADER_CONFIG};
+    use super::{encode_message, remote_recv, write_frame, MessageHeader, BINCODE_HEADER_CONFIG};
+
+    /// Write all the frames of the messages queued in `frames` to `wire`.
+    fn write_all<T: ExchangeData>(
+        frames: &mut FrameQueue,
+        wire: &mut Vec<u8>,
+        max_frame_size: Option<usize>,
+    ) {
+        let mut buf = Vec::new();
+        while !frames.is_empty() {
+            write_frame::<T, _>(frames, max_frame_size, wire, &mut buf, "test");
+        }
+    }
+
+    /// Write a message to `wire`, in frames of at most `max_frame_size` bytes.
+    fn send<T: ExchangeData>(
+        msg: NetworkMessage<T>,
+        dest: ReceiverEndpoint,
+        wire: &mut Vec<u8>,
+        max_frame_size: Option<usize>,
+    ) {
+        let mut frames = FrameQueue::default();
+        frames.push(encode_message(&msg, dest, Vec::new()));
+        write_all::<T>(&mut frames, wire, max_frame_size);
+    }
+
+    /// Encode a message with the items `0..10`, as sent to the replica of block 1 from block 0.
+    fn encoded() -> (Vec<u8>, DemuxCoord) {
+        let from = Coord::new(0, 0, 0);
+        let to = Coord::new(1, 0, 0);
+        let items = (0..10u32).map(StreamElement::Item).collect();
+        let mut buf = Vec::new();
+        send(
+            NetworkMessage::new_batch(items, from),
+            ReceiverEndpoint::new(to, from.block_id),
+            &mut buf,
+            None,
+        );
+        (buf, DemuxCoord::new(from, to))
+    }
+
+    #[test]
+    fn remote_round_trip() {
+        let (buf, coord) = encoded();
+        let mut frames = FrameBuffers::default();
+        let (_, msg) =
+            remote_recv::<u32, _>(coord, &mut buf.as_slice(), &mut frames, "test", Fail).unwrap();
+        let items = msg.into_iter().collect::<Vec<_>>();
+        assert_eq!(items, (0..10).map(StreamElement::Item).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn remote_reuse_buffers() {
+        let from = Coord::new(0, 0, 0);
+        let to = Coord::new(1, 0, 0);
+        let dest = ReceiverEndpoint::new(to, from.block_id);
+        let (mut send_frames, mut recv_buf) = (FrameQueue::default(), FrameBuffers::default());
+        let mut wire = Vec::new();
+        // a big message followed by a small one: the leftovers of the first must not leak
+        for n in [100u32, 3] {
+            let items = (0..n).map(StreamElement::Item).collect();
+            let msg = NetworkMessage::new_batch(items, from);
+            let body = send_frames.buffer();
+            send_frames.push(encode_message(&msg, dest, body));
+            write_all::<u32>(&mut send_frames, &mut wire, None);
+        }
+
+        let coord = DemuxCoord::new(from, to);
+        let mut reader = wire.as_slice();
+        for n in [100u32, 3] {
+            let (_, msg) =
+                remote_recv::<u32, _>(coord, &mut reader, &mut recv_buf, "test", Fail).unwrap();
+            assert_eq!(
+                msg.into_iter().collect::<Vec<_>>(),
+                (0..n).map(StreamElement::Item).collect::<Vec<_>>()
+            );
+        }
+        assert!(remote_recv::<u32, _>(coord, &mut reader, &mut recv_buf, "test", Fail).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "with items of a different type than `u64`")]
+    fn remote_type_mismatch() {
+        let (buf, coord) = encoded();
+        let mut frames = FrameBuffers::default();
+        remote_recv::<u64, _>(coord, &mut buf.as_slice(), &mut frames, "test", Fail);
+    }
+
+    #[test]
+    #[should_panic(expected = "protocol version 42")]
+    fn remote_version_mismatch() {
+        let (mut buf, coord) = encoded();
+        // the version is the first byte of the header
+        buf[0] = 42;
+        let mut frames = FrameBuffers::default();
+        remote_recv::<u32, _>(coord, &mut buf.as_slice(), &mut frames, "test", Fail);
+    }
+
+    /// Receive all the messages of `wire`, where the body of the second one is corrupted.
+    fn recv_corrupted(policy: MalformedMessagePolicy) -> Vec<Vec<StreamElement<u32>>> {
+        let from = Coord::new(0, 0, 0);
+        let to = Coord::new(1, 0, 0);
+        let dest = ReceiverEndpoint::new(to, from.block_id);
+        let mut wire = Vec::new();
+        for n in 1..=3u32 {
+            let mut frame = Vec::new();
+            let items = (0..n).map(StreamElement::Item).collect();
+            let msg = NetworkMessage::new_batch(items, from);
+            send(msg, dest, &mut frame, None);
+            if n == 2 {
+                frame[HEADER_SIZE..].fill(0xff);
+            }
+            wire.extend(frame);
+        }
+
+        let coord = DemuxCoord::new(from, to);
+        let (mut reader, mut buf) = (wire.as_slice(), FrameBuffers::default());
+        let mut received = vec![];
+        while let Some((_, msg)) = remote_recv(coord, &mut reader, &mut buf, "test", policy) {
+            received.push(msg.into_iter().collect());
+        }
+        received
+    }
+
+    #[test]
+    fn remote_skip_malformed_message() {
+        let received = recv_corrupted(SkipMessage);
+        assert_eq!(
+            received,
+            vec![
+                vec![StreamElement::Item(0)],
+                (0..3).map(StreamElement::Item).collect()
+            ]
+        );
+    }
+
+    #[test]
+    fn remote_skip_malformed_connection() {
+        let received = recv_corrupted(SkipConnection);
+        assert_eq!(received, vec![vec![StreamElement::Item(0)]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "received a malformed message")]
+    fn remote_fail_malformed_message() {
+        recv_corrupted(Fail);
+    }
+
+    #[test]
+    fn remote_message_in_frames() {
+        let from = Coord::new(0, 0, 0);
+        let to = Coord::new(1, 0, 0);
+        let dest = ReceiverEndpoint::new(to, from.block_id);
+        // a single element much larger than a frame
+        let element = (0..1000u64).collect::<Vec<_>>();
+        let msg = NetworkMessage::new_single(StreamElement::Item(element.clone()), from);
+        let mut wire = Vec::new();
+        send(msg, dest, &mut wire, Some(100));
+        assert!(wire.len() > 10 * (HEADER_SIZE + 100));
+
+        let coord = DemuxCoord::new(from, to);
+        let (mut reader, mut frames) = (wire.as_slice(), FrameBuffers::default());
+        let (received, msg) =
+            remote_recv::<Vec<u64>, _>(coord, &mut reader, &mut frames, "test", Fail).unwrap();
+        assert_eq!(received, dest);
+        assert_eq!(
+            msg.into_iter().collect::<Vec<_>>(),
+            vec![StreamElement::Item(element)]
+        );
+        assert!(
+            remote_recv::<Vec<u64>, _>(coord, &mut reader, &mut frames, "test", Fail).is_none()
+        );
+    }
+
+    #[test]
+    fn remote_interleaved_frames() {
+        let from = Coord::new(0, 0, 0);
+        let large = ReceiverEndpoint::new(Coord::new(1, 0, 0), from.block_id);
+        let small = ReceiverEndpoint::new(Coord::new(1, 0, 1), from.block_id);
+        let batch =
+            |n: u32| NetworkMessage::new_batch((0..n).map(StreamElement::Item).collect(), from);
+        let mut frames = FrameQueue::default();
+        frames.push(encode_message(&batch(1000), large, Vec::new()));
+        frames.push(encode_message(&batch(3), small, Vec::new()));
+        frames.push(encode_message(&batch(500), large, Vec::new()));
+        let mut wire = Vec::new();
+        write_all::<u32>(&mut frames, &mut wire, Some(64));
+
+        let coord = DemuxCoord::new(from, Coord::new(1, 0, 0));
+        let (mut reader, mut buf) = (wire.as_slice(), FrameBuffers::default());
+        let mut received = vec![];
+        while let Some((dest, msg)) =
+            remote_recv::<u32, _>(coord, &mut reader, &mut buf, "test", Fail)
+        {
+            received.push((dest, msg.into_iter().count()));
+        }
+        // the small message is not held back by the large one sent before it, and the messages
+        // for the same receiver keep their order
+        assert_eq!(received, vec![(small, 3), (large, 1000), (large, 500)]);
+    }
 
     #[test]
     fn header_size() {
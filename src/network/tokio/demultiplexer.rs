@@ -9,6 +9,8 @@ use std::collections::HashMap;
 use std::net::ToSocketAddrs;
 
 use crate::channel::{self, Sender, UnboundedReceiver, UnboundedSender};
+use crate::config::MalformedMessagePolicy;
+use crate::network::frames::FrameBuffers;
 use crate::network::remote::remote_recv;
 use crate::network::{DemuxCoord, NetworkMessage, ReceiverEndpoint};
 use crate::operator::ExchangeData;
@@ -38,10 +40,17 @@ impl<In: ExchangeData> DemuxHandle<In> {
         coord: DemuxCoord,
         address: (String, u16),
         num_clients: usize,
+        policy: MalformedMessagePolicy,
     ) -> (Self, JoinHandle<()>) {
         let (tx_senders, rx_senders) = channel::unbounded();
 
-        let join_handle = tokio::spawn(bind_remotes(coord, address, num_clients, rx_senders));
+        let join_handle = tokio::spawn(bind_remotes(
+            coord,
+            address,
+            num_clients,
+            policy,
+            rx_senders,
+        ));
         (Self { coord, tx_senders }, join_handle)
     }
 
@@ -68,6 +77,7 @@ async fn bind_remotes<In: ExchangeData>(
     coord: DemuxCoord,
     address: (String, u16),
     num_clients: usize,
+    policy: MalformedMessagePolicy,
     rx_senders: UnboundedReceiver<(ReceiverEndpoint, Sender<NetworkMessage<In>>)>,
 ) {
     let address = (address.0.as_ref(), address.1);
@@ -125,7 +135,7 @@ async fn bind_remotes<In: ExchangeData>(
                 senders.insert(endpoint, sender);
             }
             log::debug!("demux got senders");
-            demux_thread::<In>(coord, senders, stream).await;
+            demux_thread::<In>(coord, senders, stream, policy).await;
         });
         join_handles.push(join_handle);
         tx_broadcast.push(demux_tx);
@@ -165,6 +175,7 @@ async fn demux_thread<In: ExchangeData>(
     coord: DemuxCoord,
     senders: HashMap<ReceiverEndpoint, Sender<NetworkMessage<In>>>,
     mut stream: TcpStream,
+    policy: MalformedMessagePolicy,
 ) {
     let address = stream
         .peer_addr()
@@ -172,7 +183,11 @@ async fn demux_thread<In: ExchangeData>(
         .unwrap_or_else(|_| "unknown".to_string());
     log::debug!("{} started", coord);
 
-    while let Some((dest, message)) = remote_recv(coord, &mut stream, &address).await {
+    // the read buffers are reused for all the messages of this connection
+    let mut buf = FrameBuffers::default();
+    while let Some((dest, message)) =
+        remote_recv(coord, &mut stream, &mut buf, &address, policy).await
+    {
         if let Err(e) = senders[&dest].send(message) {
             warn!("demux failed to send message to {}: {:?}", dest, e);
         }
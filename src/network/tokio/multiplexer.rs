@@ -4,6 +4,8 @@ use std::time::Duration;
 #[cfg(feature = "tokio")]
 use std::net::ToSocketAddrs;
 #[cfg(feature = "tokio")]
+use std::time::Instant;
+#[cfg(feature = "tokio")]
 use tokio::net::TcpStream;
 #[cfg(feature = "tokio")]
 use tokio::task::JoinHandle;
@@ -11,7 +13,8 @@ use tokio::task::JoinHandle;
 use tokio::time::sleep;
 
 use crate::channel::{self, Receiver, Sender};
-use crate::network::remote::remote_send;
+use crate::network::frames::FrameQueue;
+use crate::network::remote::{encode_message, write_frame};
 use crate::network::{DemuxCoord, NetworkMessage, ReceiverEndpoint};
 use crate::operator::ExchangeData;
 
@@ -20,8 +23,6 @@ use crate::operator::ExchangeData;
 
 use crate::network::NetworkSender;
 
-/// Maximum number of attempts to make for connecting to a remote host.
-const CONNECT_ATTEMPTS: usize = 32;
 /// Timeout for connecting to a remote host.
 #[cfg(not(feature = "tokio"))]
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
@@ -31,7 +32,6 @@ const RETRY_INITIAL_TIMEOUT: Duration = Duration::from_millis(8);
 /// Maximum timeout between connection attempts.
 const RETRY_MAX_TIMEOUT: Duration = Duration::from_secs(1);
 
-const MUX_CHANNEL_CAPACITY: usize = 10;
 /// Like `NetworkSender`, but this should be used in a multiplexed channel (i.e. a remote one).
 ///
 /// The `ReceiverEndpoint` is sent alongside the actual message in order to demultiplex it.
@@ -44,16 +44,24 @@ pub struct MultiplexingSender<Out: Send + 'static> {
 impl<Out: ExchangeData> MultiplexingSender<Out> {
     /// Construct a new `MultiplexingSender` for a block.
     ///
-    /// All the replicas of this block should point to this multiplexer (or one of its clones).
-    pub fn new(coord: DemuxCoord, address: (String, u16)) -> (Self, JoinHandle<()>) {
-        let (tx, rx) = channel::bounded(MUX_CHANNEL_CAPACITY);
+    /// All the replicas of this block should point to this multiplexer (or one of its clones). At
+    /// most `max_inflight_batches` messages are queued before the senders block, and the messages
+    /// are written in frames of at most `max_frame_size` bytes.
+    pub fn new(
+        coord: DemuxCoord,
+        address: (String, u16),
+        max_inflight_batches: usize,
+        max_frame_size: Option<usize>,
+        connect_deadline: Duration,
+    ) -> (Self, JoinHandle<()>) {
+        let (tx, rx) = channel::bounded(max_inflight_batches);
         let join_handle = tokio::spawn(async move {
             log::debug!(
                 "mux connecting to {}",
                 address.to_socket_addrs().unwrap().next().unwrap()
             );
-            let stream = connect_remote(coord, address).await;
-            mux_thread::<Out>(coord, rx, stream).await;
+            let stream = connect_remote(coord, address, connect_deadline).await;
+            mux_thread::<Out>(coord, rx, stream, max_inflight_batches, max_frame_size).await;
         });
         (Self { tx: Some(tx) }, join_handle)
     }
@@ -78,18 +86,25 @@ impl<Out: ExchangeData> MultiplexingSender<Out> {
 /// Connect the sender to a remote channel located at the specified address.
 ///
 /// - At first the address is resolved to an actual address (DNS resolution)
-/// - Then at most `CONNECT_ATTEMPTS` are performed, and an exponential backoff is used in case
-///   of errors.
+/// - Then the connection is attempted until `deadline` expires, and an exponential backoff is
+///   used in case of errors.
 /// - If the connection cannot be established this function will panic.
 #[cfg(feature = "tokio")]
-async fn connect_remote(coord: DemuxCoord, address: (String, u16)) -> TcpStream {
+async fn connect_remote(
+    coord: DemuxCoord,
+    address: (String, u16),
+    deadline: Duration,
+) -> TcpStream {
     let socket_addrs: Vec<_> = address
         .to_socket_addrs()
         .map_err(|e| format!("Failed to get the address for {}: {:?}", coord, e))
         .unwrap()
         .collect();
+    let start = Instant::now();
     let mut retry_delay = RETRY_INITIAL_TIMEOUT;
-    for attempt in 1..=CONNECT_ATTEMPTS {
+    let mut attempt = 0;
+    while start.elapsed() < deadline {
+        attempt += 1;
         log::debug!(
             "{} connecting to {:?} ({} attempt)",
             coord,
@@ -98,7 +113,13 @@ async fn connect_remote(coord: DemuxCoord, address: (String, u16)) -> TcpStream
         );
 
         for address in socket_addrs.iter() {
-            match TcpStream::connect(address).await {
+            let timeout = deadline.saturating_sub(start.elapsed());
+            let Ok(result) = tokio::time::timeout(timeout, TcpStream::connect(address)).await
+            else {
+                log::debug!("{coord} timeout connecting to {address:?}");
+                break;
+            };
+            match result {
                 Ok(stream) => {
                     return stream;
                 }
@@ -128,12 +149,12 @@ async fn connect_remote(coord: DemuxCoord, address: (String, u16)) -> TcpStream
             retry_delay.as_secs_f32(),
         );
 
-        sleep(retry_delay).await;
+        sleep(retry_delay.min(deadline.saturating_sub(start.elapsed()))).await;
         retry_delay = (2 * retry_delay).min(RETRY_MAX_TIMEOUT);
     }
     panic!(
-        "Failed to connect to remote {} at {:?} after {} attempts",
-        coord, address, CONNECT_ATTEMPTS
+        "Failed to connect to remote {coord} at {}:{} within {deadline:?} ({attempt} attempts)",
+        address.0, address.1
     );
 }
 
@@ -142,6 +163,8 @@ async fn mux_thread<Out: ExchangeData>(
     coord: DemuxCoord,
     rx: Receiver<(ReceiverEndpoint, NetworkMessage<Out>)>,
     mut stream: TcpStream,
+    max_inflight_batches: usize,
+    max_frame_size: Option<usize>,
 ) {
     use tokio::io::AsyncWriteExt;
 
@@ -151,8 +174,25 @@ async fn mux_thread<Out: ExchangeData>(
         .unwrap_or_else(|_| "unknown".to_string());
     log::debug!("{} connected to {:?}", coord, address);
 
-    while let Ok((dest, message)) = rx.recv_async().await {
-        remote_send(message, dest, &mut stream, &address).await;
+    // the serialization buffers are reused for all the messages of this connection
+    let (mut frames, mut buf) = (FrameQueue::default(), Vec::new());
+    loop {
+        if frames.is_empty() {
+            let Ok((dest, message)) = rx.recv_async().await else {
+                break;
+            };
+            let body = frames.buffer();
+            frames.push(encode_message(&message, dest, body));
+        }
+        // take the other messages ready, to interleave their frames with the ones in progress
+        while max_frame_size.is_some() && frames.len() < max_inflight_batches {
+            let Ok((dest, message)) = rx.try_recv() else {
+                break;
+            };
+            let body = frames.buffer();
+            frames.push(encode_message(&message, dest, body));
+        }
+        write_frame::<Out, _>(&mut frames, max_frame_size, &mut stream, &mut buf, &address).await;
     }
 
     stream.shutdown().await.unwrap();
@@ -2,11 +2,19 @@ use once_cell::sync::Lazy;
 #[cfg(feature = "tokio")]
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
-use bincode::config::{FixintEncoding, RejectTrailing, WithOtherIntEncoding, WithOtherTrailing};
+use bincode::config::{
+    FixintEncoding, LittleEndian, RejectTrailing, VarintEncoding, WithOtherEndian,
+    WithOtherIntEncoding, WithOtherTrailing,
+};
 use bincode::{DefaultOptions, Options};
 use serde::{Deserialize, Serialize};
 
-use crate::network::{Coord, DemuxCoord, NetworkMessage, ReceiverEndpoint};
+use crate::config::MalformedMessagePolicy;
+use crate::network::frames::{FrameBuffers, FrameQueue, OutgoingMessage};
+use crate::network::{
+    check_protocol, on_malformed_message, type_hash, Coord, DemuxCoord, NetworkMessage,
+    ProtocolError, ReceiverEndpoint, PROTOCOL_VERSION,
+};
 use crate::operator::ExchangeData;
 use crate::profiler::{get_profiler, Profiler};
 use crate::scheduler::BlockId;
@@ -21,123 +29,215 @@ static BINCODE_HEADER_CONFIG: Lazy<
         .reject_trailing_bytes()
 });
 
-static BINCODE_MSG_CONFIG: Lazy<DefaultOptions> = Lazy::new(bincode::DefaultOptions::new);
+type MsgConfig = WithOtherTrailing<
+    WithOtherEndian<WithOtherIntEncoding<DefaultOptions, VarintEncoding>, LittleEndian>,
+    RejectTrailing,
+>;
 
-pub(crate) const HEADER_SIZE: usize = 20; // std::mem::size_of::<MessageHeader>();
+/// Configuration of the message serializer: variable length integers, little endian, no size
+/// limit (the size is checked by the header) and no trailing bytes.
+///
+/// Every setting is explicit so that the encoding does not depend on the defaults of bincode: any
+/// change must increase [`PROTOCOL_VERSION`].
+static BINCODE_MSG_CONFIG: Lazy<MsgConfig> = Lazy::new(|| {
+    bincode::DefaultOptions::new()
+        .with_varint_encoding()
+        .with_little_endian()
+        .reject_trailing_bytes()
+});
+
+pub(crate) const HEADER_SIZE: usize = 26; // std::mem::size_of::<MessageHeader>();
 
-/// Header of a message sent before the actual message.
+/// Header of a frame sent before its bytes.
 #[derive(Serialize, Deserialize, Default)]
 struct MessageHeader {
-    /// The size of the actual message
+    /// The version of the protocol used by the sender.
+    version: u8,
+    /// The hash of the type of the items in the message.
+    type_hash: u32,
+    /// The size of the frame
     size: u32,
+    /// Whether more frames of the same message follow.
+    more: bool,
     /// The id of the replica this message is for.
     replica_id: ReplicaId,
     /// The id of the block that is sending the message.
     sender_block_id: BlockId,
 }
 
-/// Serialize and send a message to a remote socket.
+/// Serialize a message for a remote receiver, into `body`, which is cleared first.
 ///
-/// The network protocol works as follow:
-/// - send a `MessageHeader` serialized with bincode with `FixintEncoding`
-/// - send the message
+/// Reusing the buffers of the messages already written (see [`FrameQueue::buffer`]) avoids an
+/// allocation per message.
 #[cfg(feature = "tokio")]
-pub(crate) async fn remote_send<T: ExchangeData, W: AsyncWrite + Unpin>(
-    msg: NetworkMessage<T>,
+pub(crate) fn encode_message<T: ExchangeData>(
+    msg: &NetworkMessage<T>,
     dest: ReceiverEndpoint,
-    writer: &mut W,
-    address: &str,
-) {
-    let serialized_len = BINCODE_MSG_CONFIG
-        .serialized_size(&msg)
+    mut body: Vec<u8>,
+) -> OutgoingMessage {
+    let serialized_len = BINCODE_MSG_CONFIG.serialized_size(msg).unwrap_or_else(|e| {
+        panic!(
+            "Failed to compute serialized length of outgoing message to {}: {:?}",
+            dest, e
+        )
+    });
+
+    body.clear();
+    body.reserve(serialized_len as usize);
+    BINCODE_MSG_CONFIG
+        .serialize_into(&mut body, msg)
         .unwrap_or_else(|e| {
             panic!(
-                "Failed to compute serialized length of outgoing message to {}: {:?}",
-                dest, e
+                "Failed to serialize message, {} bytes to {}: {:?}",
+                serialized_len, dest, e
             )
         });
+    assert_eq!(body.len(), serialized_len as usize);
 
+    OutgoingMessage {
+        dest,
+        sender: msg.sender,
+        body,
+        written: 0,
+    }
+}
+
+/// Send the next frame of the messages in `frames` to a remote socket.
+///
+/// The frame is built into `buf`, which is reused for all the frames of a connection.
+///
+/// The network protocol works as follow:
+/// - send a `MessageHeader` serialized with bincode with `FixintEncoding`, it contains the
+///   protocol version and the hash of the item type, checked by the receiver
+/// - send at most `max_frame_size` bytes of the serialized message, the header tells whether more
+///   frames of the same message follow
+#[cfg(feature = "tokio")]
+pub(crate) async fn write_frame<T: ExchangeData, W: AsyncWrite + Unpin>(
+    frames: &mut FrameQueue,
+    max_frame_size: Option<usize>,
+    writer: &mut W,
+    buf: &mut Vec<u8>,
+    address: &str,
+) {
+    let Some(frame) = frames.next_frame(max_frame_size) else {
+        return;
+    };
+    let dest = frame.dest;
     let header = MessageHeader {
-        size: serialized_len.try_into().unwrap(),
+        version: PROTOCOL_VERSION,
+        type_hash: type_hash::<T>(),
+        size: frame.bytes.len().try_into().unwrap(),
+        more: frame.more,
         replica_id: dest.coord.replica_id,
         sender_block_id: dest.prev_block_id,
     };
+    let (sender, message_size) = (frame.sender, frame.message_size);
 
-    let mut buf = Vec::with_capacity(HEADER_SIZE + serialized_len as usize);
-
+    buf.clear();
+    buf.reserve(HEADER_SIZE + frame.bytes.len());
     BINCODE_HEADER_CONFIG
-        .serialize_into(&mut buf, &header)
+        .serialize_into(&mut *buf, &header)
         .unwrap_or_else(|e| {
             panic!(
-                "Failed to serialize header of message (was {} bytes) to {} at {}: {:?}",
-                serialized_len, dest, address, e
+                "Failed to serialize header of frame to {} at {}: {:?}",
+                dest, address, e
             )
         });
-
-    BINCODE_MSG_CONFIG
-        .serialize_into(&mut buf, &msg)
-        .unwrap_or_else(|e| {
-            panic!(
-                "Failed to serialize message, {} bytes to {} at {}: {:?}",
-                serialized_len, dest, address, e
-            )
-        });
-    assert_eq!(buf.len(), HEADER_SIZE + serialized_len as usize);
+    buf.extend_from_slice(frame.bytes);
 
     writer.write_all(buf.as_ref()).await.unwrap_or_else(|e| {
         panic!(
-            "Failed to send message {} bytes to {} at {}: {:?}",
-            serialized_len, dest, address, e
+            "Failed to send frame of {} bytes to {} at {}: {:?}",
+            buf.len() - HEADER_SIZE,
+            dest,
+            address,
+            e
         )
     });
 
-    get_profiler().net_bytes_out(
-        msg.sender,
-        dest.coord,
-        HEADER_SIZE + serialized_len as usize,
-    );
+    if let Some(size) = message_size {
+        get_profiler().net_bytes_out(sender, dest.coord, HEADER_SIZE + size);
+    }
+    frames.advance(max_frame_size);
 }
 
+/// Receive a message from the remote channel, reading its frames into `frames` until it is
+/// complete. Returns `None` if there was a failure receiving the last message.
 #[cfg(feature = "tokio")]
 pub(crate) async fn remote_recv<T: ExchangeData, R: AsyncRead + Unpin>(
     coord: DemuxCoord,
     reader: &mut R,
+    frames: &mut FrameBuffers,
     address: &str,
+    policy: MalformedMessagePolicy,
 ) -> Option<(ReceiverEndpoint, NetworkMessage<T>)> {
-    let mut header = [0u8; HEADER_SIZE];
-    match reader.read_exact(&mut header).await {
-        Ok(_) => {}
-        Err(e) => {
-            log::trace!(
-                "Failed to receive {} bytes of header to {} from {}: {:?}",
-                HEADER_SIZE,
-                coord,
-                address,
-                e
-            );
+    loop {
+        let mut header = [0u8; HEADER_SIZE];
+        match reader.read_exact(&mut header).await {
+            Ok(_) => {}
+            Err(e) => {
+                log::trace!(
+                    "Failed to receive {} bytes of header to {} from {}: {:?}",
+                    HEADER_SIZE,
+                    coord,
+                    address,
+                    e
+                );
+                return None;
+            }
+        }
+        let header: MessageHeader = BINCODE_HEADER_CONFIG
+            .deserialize(&header)
+            .expect("Malformed header");
+        let checked = check_protocol::<T>(header.version, header.type_hash, coord, address);
+        // the frame is read even if it cannot be decoded, to skip to the next one
+        frames.frame.clear();
+        frames.frame.resize(header.size as usize, 0);
+        reader
+            .read_exact(&mut frames.frame)
+            .await
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Failed to receive {} bytes to {} from {}: {:?}",
+                    header.size, coord, address, e
+                )
+            });
+        let key = (header.replica_id, header.sender_block_id);
+        if let Err(e) = checked {
+            frames.discard(key);
+            if on_malformed_message(policy, e) {
+                continue;
+            }
             return None;
         }
+        let Some(message) = frames.push_frame(key, header.more) else {
+            continue;
+        };
+        let size = message.len();
+        let msg = BINCODE_MSG_CONFIG
+            .deserialize::<NetworkMessage<T>>(message)
+            .map_err(|e| ProtocolError::Malformed {
+                dest: coord,
+                address: address.to_string(),
+                error: e.to_string(),
+            });
+        let msg = match msg {
+            Ok(msg) => msg,
+            Err(e) => {
+                if on_malformed_message(policy, e) {
+                    continue;
+                }
+                return None;
+            }
+        };
+
+        let dest = ReceiverEndpoint::new(
+            Coord::new(coord.coord.block_id, coord.coord.host_id, header.replica_id),
+            header.sender_block_id,
+        );
+        get_profiler().net_bytes_in(msg.sender, dest.coord, HEADER_SIZE + size);
+        return Some((dest, msg));
     }
-    let header: MessageHeader = BINCODE_HEADER_CONFIG
-        .deserialize(&header)
-        .expect("Malformed header");
-    let mut buf = vec![0u8; header.size as usize];
-    reader.read_exact(&mut buf).await.unwrap_or_else(|e| {
-        panic!(
-            "Failed to receive {} bytes to {} from {}: {:?}",
-            header.size, coord, address, e
-        )
-    });
-    let msg: NetworkMessage<T> = BINCODE_MSG_CONFIG
-        .deserialize(buf.as_ref())
-        .expect("Malformed message");
-
-    let dest = ReceiverEndpoint::new(
-        Coord::new(coord.coord.block_id, coord.coord.host_id, header.replica_id),
-        header.sender_block_id,
-    );
-    get_profiler().net_bytes_in(msg.sender, dest.coord, HEADER_SIZE + header.size as usize);
-    Some((dest, msg))
 }
 
 #[cfg(test)]
@@ -6,6 +6,7 @@ use std::marker::PhantomData;
 use std::sync::Arc;
 #[cfg(not(feature = "tokio"))]
 use std::thread::JoinHandle;
+use std::time::Duration;
 
 #[cfg(feature = "tokio")]
 use futures::StreamExt;
@@ -13,11 +14,12 @@ use indexmap::IndexSet;
 use typemap_rev::{TypeMap, TypeMapKey};
 
 use crate::channel::Sender;
-use crate::config::RuntimeConfig;
+use crate::config::{default_connect_deadline_secs, default_max_inflight_batches, RuntimeConfig};
 use crate::network::demultiplexer::DemuxHandle;
 use crate::network::multiplexer::MultiplexingSender;
 use crate::network::{
     local_channel, BlockCoord, Coord, DemuxCoord, NetworkReceiver, NetworkSender, ReceiverEndpoint,
+    CHANNEL_CAPACITY,
 };
 use crate::operator::ExchangeData;
 use crate::scheduler::{BlockId, HostId};
@@ -313,8 +315,14 @@ impl NetworkTopology {
                 }
             }
             if !prev.is_empty() {
-                let address = self.demultiplexer_addresses[&demux_coord].clone();
-                let (demux, join_handle) = DemuxHandle::new(demux_coord, address, prev.len());
+                let address =
+                    demux_bind_address(&self.config, &self.demultiplexer_addresses, demux_coord);
+                let policy = match self.config.as_ref() {
+                    RuntimeConfig::Remote(config) => config.on_malformed_message,
+                    RuntimeConfig::Local(_) => Default::default(),
+                };
+                let (demux, join_handle) =
+                    DemuxHandle::new(demux_coord, address, prev.len(), policy);
                 #[cfg(not(feature = "tokio"))]
                 self.join_handles.push(join_handle);
                 #[cfg(feature = "tokio")]
@@ -343,7 +351,26 @@ impl NetworkTopology {
 
         if let Entry::Vacant(e) = muxers.entry(demux_coord) {
             let address = self.demultiplexer_addresses[&demux_coord].clone();
-            let (mux, join_handle) = MultiplexingSender::new(demux_coord, address);
+            let (max_inflight_batches, max_frame_size, connect_deadline_secs) =
+                match self.config.as_ref() {
+                    RuntimeConfig::Remote(config) => (
+                        config.max_inflight_batches,
+                        config.max_frame_size,
+                        config.connect_deadline_secs,
+                    ),
+                    RuntimeConfig::Local(_) => (
+                        default_max_inflight_batches(),
+                        None,
+                        default_connect_deadline_secs(),
+                    ),
+                };
+            let (mux, join_handle) = MultiplexingSender::new(
+                demux_coord,
+                address,
+                max_inflight_batches,
+                max_frame_size,
+                Duration::from_secs(connect_deadline_secs),
+            );
             #[cfg(not(feature = "tokio"))]
             self.join_handles.push(join_handle);
             #[cfg(feature = "tokio")]
@@ -385,7 +412,7 @@ impl NetworkTopology {
                         .or_default()
                         .insert(receiver_endpoint, sender);
                 } else {
-                    let (sender, receiver) = local_channel(receiver_endpoint);
+                    let (sender, receiver) = local_channel(receiver_endpoint, CHANNEL_CAPACITY);
 
                     if receiver_endpoint.coord.host_id == self.config.host_id().unwrap() {
                         self.register_demux(receiver_endpoint, sender.clone_inner());
@@ -405,8 +432,15 @@ impl NetworkTopology {
                         .insert(receiver_endpoint, sender);
                 };
             }
-            RuntimeConfig::Local(_) => {
-                let (sender, receiver) = local_channel(receiver_endpoint);
+            RuntimeConfig::Local(config) => {
+                let capacity = config.channel_capacity.unwrap_or(CHANNEL_CAPACITY);
+                let (mut sender, mut receiver) = local_channel(receiver_endpoint, capacity);
+                if let Some(log) = &config.message_log {
+                    sender = sender.recorded(log.clone());
+                }
+                if let Some(log) = &config.replay_log {
+                    receiver = receiver.replayed(log);
+                }
 
                 self.receivers
                     .as_mut()
@@ -565,8 +599,26 @@ impl NetworkTopology {
     }
 }
 
+/// The address the demultiplexer at `coord` listens to: the bind address of its host if set,
+/// otherwise the address the other hosts connect to.
+fn demux_bind_address(
+    config: &RuntimeConfig,
+    addresses: &HashMap<DemuxCoord, (String, u16), crate::block::CoordHasherBuilder>,
+    coord: DemuxCoord,
+) -> (String, u16) {
+    let (address, port) = addresses[&coord].clone();
+    match config {
+        RuntimeConfig::Remote(config) => {
+            let host = &config.hosts[coord.coord.host_id as usize];
+            (host.bind_address.clone().unwrap_or(address), port)
+        }
+        RuntimeConfig::Local(_) => (address, port),
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::config::LocalConfigBuilder;
     use crate::network::NetworkMessage;
     use crate::operator::StreamElement;
     use itertools::Itertools;
@@ -634,6 +686,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn local_channel_capacity_from_config() {
+        let config = LocalConfigBuilder::new(1)
+            .channel_capacity(3)
+            .build()
+            .unwrap();
+        let mut topology = NetworkTopology::new(Arc::new(config));
+
+        let sender = Coord::new(0, 0, 0);
+        let receiver = Coord::new(1, 0, 0);
+        topology.connect(sender, receiver, TypeId::of::<i32>(), false);
+        topology.build();
+
+        let tx = topology.get_sender::<i32>(ReceiverEndpoint::new(receiver, 0));
+        assert_eq!(tx.clone_inner().capacity(), Some(3));
+    }
+
     #[cfg(not(feature = "tokio"))]
     #[test]
     fn test_remote_topology() {
@@ -790,6 +859,48 @@ num_cores = 1
         join1.join().unwrap();
     }
 
+    #[test]
+    fn test_demux_bind_address() {
+        use crate::config::ConfigBuilder;
+
+        let config = ConfigBuilder::new_remote()
+            .parse_toml_str(
+                r#"[[host]]
+address = "127.0.0.1"
+base_port = 21900
+num_cores = 1
+[[host]]
+address = "127.0.0.1"
+bind_address = "0.0.0.0"
+base_port = 21910
+num_cores = 1
+"#,
+            )
+            .unwrap()
+            .host_id(0)
+            .build()
+            .unwrap();
+        let mut topology = NetworkTopology::new(Arc::new(config));
+        let (from, to) = (Coord::new(0, 0, 0), Coord::new(1, 1, 0));
+        let back = Coord::new(2, 0, 0);
+        topology.connect(from, to, TypeId::of::<u8>(), false);
+        topology.connect(to, back, TypeId::of::<u8>(), false);
+        topology.build();
+
+        // the peers connect to `address`, while the demultiplexer listens to `bind_address`
+        let coord = DemuxCoord::new(from, to);
+        let connect = ("127.0.0.1".to_string(), 21910);
+        assert_eq!(topology.demultiplexer_addresses[&coord], connect);
+        let bind_address =
+            |coord| demux_bind_address(&topology.config, &topology.demultiplexer_addresses, coord);
+        assert_eq!(bind_address(coord), ("0.0.0.0".to_string(), 21910));
+        // without a bind address the demultiplexer listens to `address`
+        let coord = DemuxCoord::new(to, back);
+        let address = topology.demultiplexer_addresses[&coord].clone();
+        assert_eq!(address, ("127.0.0.1".to_string(), 21900));
+        assert_eq!(bind_address(coord), address);
+    }
+
     #[cfg(not(feature = "tokio"))]
     fn receiver<T: ExchangeData + Ord + std::fmt::Debug>(
         receiver: NetworkReceiver<T>,
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crate::block::{BlockStructure, CoordHasherBuilder, OperatorStructure};
+use crate::network::Coord;
+use crate::operator::{fmt_stage, Operator, StreamElement, Timestamp};
+use crate::scheduler::{BlockId, ExecutionMetadata};
+use crate::Stream;
+
+/// How long a replica ahead of the group waits before checking again, in case a notification
+/// was missed.
+const RECHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Default)]
+struct Members {
+    /// The blocks of the streams aligned in the group.
+    streams: Vec<BlockId>,
+    /// For each aligned block with replicas in this process, the number of its replicas, known
+    /// after the first replica joined the group.
+    blocks: Option<HashMap<BlockId, usize>>,
+    /// The last watermark of each replica, `None` if it has not sent one yet.
+    watermarks: HashMap<Coord, Option<Timestamp>>,
+}
+
+impl Members {
+    /// The lowest watermark of the group, `None` if some replicas have not sent a watermark yet
+    /// or have not joined the group.
+    fn frontier(&self) -> Option<Timestamp> {
+        let blocks = self.blocks.as_ref()?;
+        let joined = blocks.iter().all(|(block, &replicas)| {
+            self.watermarks
+                .keys()
+                .filter(|c| c.block_id == *block)
+                .count()
+                == replicas
+        });
+        if !joined {
+            return None;
+        }
+        self.watermarks
+            .values()
+            .try_fold(Timestamp::MAX, |min, w| w.map(|w| min.min(w)))
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    max_drift: Timestamp,
+    members: Mutex<Members>,
+    changed: Condvar,
+}
+
+/// A group of streams whose watermarks advance together, see [`Stream::align_watermarks`].
+///
+/// Create a group with the maximum drift allowed and pass it to each of the streams to align.
+#[derive(Debug, Clone)]
+pub struct WatermarkAlignment {
+    inner: Arc<Inner>,
+}
+
+impl WatermarkAlignment {
+    /// A new group where no replica can emit a watermark more than `max_drift` ahead of the
+    /// lowest watermark of the group.
+    pub fn new(max_drift: Timestamp) -> Self {
+        assert!(max_drift >= 0, "the maximum drift must not be negative");
+        Self {
+            inner: Arc::new(Inner {
+                max_drift,
+                members: Default::default(),
+                changed: Condvar::new(),
+            }),
+        }
+    }
+
+    /// The maximum drift allowed between the watermarks of the group.
+    pub fn max_drift(&self) -> Timestamp {
+        self.inner.max_drift
+    }
+
+    fn add_stream(&self, block_id: BlockId) {
+        self.inner.members.lock().unwrap().streams.push(block_id);
+    }
+
+    /// Add a replica to the group, given the number of replicas of each block in this process.
+    fn join(&self, coord: Coord, local_replicas: &HashMap<BlockId, usize, CoordHasherBuilder>) {
+        let mut members = self.inner.members.lock().unwrap();
+        if members.blocks.is_none() {
+            // the streams without replicas here do not hold back the others
+            let blocks = members
+                .streams
+                .iter()
+                .map(|block| (*block, local_replicas.get(block).copied().unwrap_or(0)))
+                .filter(|&(_, replicas)| replicas > 0)
+                .collect();
+            members.blocks = Some(blocks);
+        }
+        members.watermarks.insert(coord, None);
+        self.inner.changed.notify_all();
+    }
+
+    fn update(&self, coord: Coord, watermark: Timestamp) {
+        let mut members = self.inner.members.lock().unwrap();
+        members.watermarks.insert(coord, Some(watermark));
+        self.inner.changed.notify_all();
+    }
+
+    /// Block until `watermark` is within the maximum drift from the lowest watermark of the group.
+    fn wait(&self, watermark: Timestamp) {
+        let mut members = self.inner.members.lock().unwrap();
+        while members
+            .frontier()
+            .is_none_or(|min| watermark > min.saturating_add(self.inner.max_drift))
+        {
+            members = self
+                .inner
+                .changed
+                .wait_timeout(members, RECHECK_INTERVAL)
+                .unwrap()
+                .0;
+        }
+    }
+}
+
+/// The membership of a replica in a [`WatermarkAlignment`], which stops holding back the group
+/// when dropped, even if the replica panicked.
+#[derive(Debug)]
+struct Member {
+    group: WatermarkAlignment,
+    coord: Coord,
+}
+
+impl Drop for Member {
+    fn drop(&mut self) {
+        // a replica that ended does not hold back the others
+        self.group.update(self.coord, Timestamp::MAX);
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct AlignWatermarks<Op> {
+    prev: Op,
+    group: WatermarkAlignment,
+    member: Option<Member>,
+    /// The last watermark forwarded by this replica.
+    watermark: Option<Timestamp>,
+}
+
+impl<Op: Clone> Clone for AlignWatermarks<Op> {
+    fn clone(&self) -> Self {
+        Self {
+            prev: self.prev.clone(),
+            group: self.group.clone(),
+            member: None,
+            watermark: None,
+        }
+    }
+}
+
+impl<Op: Operator> Display for AlignWatermarks<Op> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, Op::Out>(f, &self.prev, "AlignWatermarks")
+    }
+}
+
+impl<Op: Operator> Operator for AlignWatermarks<Op> {
+    type Out = Op::Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        let coord = metadata.coord;
+        self.group.join(coord, &metadata.local_replicas);
+        self.member = Some(Member {
+            group: self.group.clone(),
+            coord,
+        });
+    }
+
+    fn next(&mut self) -> StreamElement<Op::Out> {
+        // do not read further while ahead of the group
+        if let Some(watermark) = self.watermark {
+            self.group.wait(watermark);
+        }
+        let el = self.prev.next();
+        match &el {
+            StreamElement::Watermark(ts) => {
+                self.watermark = Some(*ts);
+                self.group.update(self.member.as_ref().unwrap().coord, *ts);
+            }
+            StreamElement::Terminate => {
+                self.watermark = None;
+                self.member = None;
+            }
+            _ => {}
+        }
+        el
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<Op::Out, _>("AlignWatermarks"))
+    }
+}
+
+impl<Op> Stream<Op>
+where
+    Op: Operator + 'static,
+{
+    /// Hold back the replicas of this stream whose watermark runs ahead of the lowest watermark
+    /// of the `group` by more than its maximum drift.
+    ///
+    /// This should be called right after the watermarks are generated (e.g. after
+    /// [`Stream::add_timestamps`]), in the same block as the source: a replica that forwarded a
+    /// watermark too far ahead stops reading from its source until the slowest replicas of the
+    /// group catch up. Aligning the streams joined on event time keeps a fast stream from
+    /// filling the memory of the join with the elements waiting for the slow one.
+    ///
+    /// The replicas hold back the others until they send their first watermark, and stop doing it
+    /// when they end. The watermarks are aligned among the replicas running on the same host: the
+    /// streams without replicas on a host (e.g. with [`Replication::One`]) do not hold back the
+    /// others there.
+    ///
+    /// [`Replication::One`]: crate::Replication::One
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::WatermarkAlignment;
+    /// # let mut env = StreamContext::new_local();
+    /// let group = WatermarkAlignment::new(100);
+    /// let fast = env
+    ///     .stream_iter(0..1000i64)
+    ///     .add_timestamps(|&n| n, |_, &ts| Some(ts))
+    ///     .align_watermarks(&group);
+    /// let slow = env
+    ///     .stream_iter(0..1000i64)
+    ///     .add_timestamps(|&n| n, |_, &ts| Some(ts))
+    ///     .align_watermarks(&group);
+    /// let res = fast.merge(slow).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap().len(), 2000);
+    /// ```
+    pub fn align_watermarks(
+        self,
+        group: &WatermarkAlignment,
+    ) -> Stream<impl Operator<Out = Op::Out>> {
+        group.add_stream(self.block.id);
+        let group = group.clone();
+        self.add_operator(|prev| AlignWatermarks {
+            prev,
+            group,
+            member: None,
+            watermark: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicI64, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::WatermarkAlignment;
+
+    #[test]
+    fn align_watermarks_throttles_fast_source() {
+        const DRIFT: i64 = 10;
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        let group = WatermarkAlignment::new(DRIFT);
+        // the largest timestamp emitted by the slow source
+        let slow_progress = Arc::new(AtomicI64::new(-1));
+
+        let progress = slow_progress.clone();
+        let slow = env
+            .stream_iter(0..200i64)
+            .map(|n| {
+                std::thread::sleep(Duration::from_millis(2));
+                n
+            })
+            .add_timestamps(|&n| n, |_, &ts| Some(ts))
+            .align_watermarks(&group)
+            .inspect(move |&n| {
+                progress.fetch_max(n, Ordering::SeqCst);
+            })
+            .collect_count();
+
+        let progress = slow_progress.clone();
+        let fast = env
+            .stream_iter(0..200i64)
+            .add_timestamps(|&n| n, |_, &ts| Some(ts))
+            .align_watermarks(&group)
+            .map(move |n| (n, progress.load(Ordering::SeqCst)))
+            .collect_vec();
+
+        env.execute_blocking();
+
+        assert_eq!(slow.get(), Some(200));
+        let fast = fast.get().unwrap();
+        assert_eq!(fast.len(), 200);
+        for (n, slow) in fast {
+            // the fast source stops right after a watermark more than the drift ahead
+            assert!(
+                n <= slow + DRIFT + 1,
+                "{n} emitted when the slow source was at {slow}"
+            );
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+
+use crate::block::{BlockStructure, OperatorStructure};
+use crate::operator::{fmt_stage, Data, DataKey, KeyerFn, Operator, StreamElement, Timestamp};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct BatchByKey<Key, Keyer, Op>
+where
+    Key: DataKey,
+    Keyer: KeyerFn<Key, Op::Out>,
+    Op: Operator,
+{
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    keyer: Keyer,
+    /// The key and the elements of the current run.
+    #[derivative(Debug = "ignore")]
+    run: Option<(Key, Vec<Op::Out>)>,
+    /// The largest timestamp of the elements of the current run.
+    ts: Option<Timestamp>,
+    /// The last watermark received during the current run, forwarded after the run.
+    watermark: Option<Timestamp>,
+    /// The elements to return before pulling the next ones.
+    #[derivative(Debug = "ignore")]
+    pending: VecDeque<StreamElement<(Key, Vec<Op::Out>)>>,
+}
+
+impl<Key, Keyer, Op> Clone for BatchByKey<Key, Keyer, Op>
+where
+    Key: DataKey,
+    Keyer: KeyerFn<Key, Op::Out>,
+    Op: Operator,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.prev.clone(), self.keyer.clone())
+    }
+}
+
+impl<Key, Keyer, Op> BatchByKey<Key, Keyer, Op>
+where
+    Key: DataKey,
+    Keyer: KeyerFn<Key, Op::Out>,
+    Op: Operator,
+{
+    fn new(prev: Op, keyer: Keyer) -> Self {
+        Self {
+            prev,
+            keyer,
+            run: None,
+            ts: None,
+            watermark: None,
+            pending: Default::default(),
+        }
+    }
+
+    /// End the current run, queueing it followed by the watermarks it held back.
+    fn end_run(&mut self) {
+        if let Some(run) = self.run.take() {
+            let el = match self.ts.take() {
+                Some(ts) => StreamElement::Timestamped(run, ts),
+                None => StreamElement::Item(run),
+            };
+            self.pending.push_back(el);
+        }
+        if let Some(w) = self.watermark.take() {
+            self.pending.push_back(StreamElement::Watermark(w));
+        }
+    }
+}
+
+impl<Key, Keyer, Op> Display for BatchByKey<Key, Keyer, Op>
+where
+    Key: DataKey,
+    Keyer: KeyerFn<Key, Op::Out>,
+    Op: Operator,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, (Key, Vec<Op::Out>)>(f, &self.prev, "BatchByKey")
+    }
+}
+
+impl<Key, Keyer, Op> Operator for BatchByKey<Key, Keyer, Op>
+where
+    Key: DataKey,
+    Keyer: KeyerFn<Key, Op::Out>,
+    Op: Operator,
+{
+    type Out = (Key, Vec<Op::Out>);
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        loop {
+            if let Some(el) = self.pending.pop_front() {
+                return el;
+            }
+            let (item, ts) = match self.prev.next() {
+                StreamElement::Item(item) => (item, None),
+                StreamElement::Timestamped(item, ts) => (item, Some(ts)),
+                // the elements of the current run may precede the watermark
+                StreamElement::Watermark(w) if self.run.is_some() => {
+                    self.watermark = Some(w);
+                    continue;
+                }
+                StreamElement::Watermark(w) => return StreamElement::Watermark(w),
+                StreamElement::FlushBatch => return StreamElement::FlushBatch,
+                StreamElement::FlushAndRestart => {
+                    self.end_run();
+                    self.pending.push_back(StreamElement::FlushAndRestart);
+                    continue;
+                }
+                StreamElement::Terminate => return StreamElement::Terminate,
+            };
+            let key = (self.keyer)(&item);
+            match &mut self.run {
+                Some((k, run)) if *k == key => run.push(item),
+                _ => {
+                    self.end_run();
+                    self.run = Some((key, vec![item]));
+                }
+            }
+            self.ts = self.ts.max(ts);
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<Self::Out, _>("BatchByKey"))
+    }
+}
+
+impl<I, Op> Stream<Op>
+where
+    I: Data,
+    Op: Operator<Out = I> + 'static,
+{
+    /// Group the consecutive elements of the stream with the same key, emitting each run with
+    /// its key when an element with a different key arrives, or when the stream ends.
+    ///
+    /// **Note**: this groups only the consecutive runs, not all the elements with the same key:
+    /// a key appearing again after a different one starts a new run. It is meant for the streams
+    /// already grouped or sorted by key, where it avoids the shuffle of
+    /// [`group_by`](Stream::group_by). Each replica groups its own elements.
+    ///
+    /// A run carries the largest timestamp of its elements, and the watermarks received during
+    /// a run are forwarded after it.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter([1, 3, 2, 4, 5].into_iter());
+    /// let res = s.batch_by_key(|&n| n % 2).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(
+    ///     res.get().unwrap(),
+    ///     vec![(1, vec![1, 3]), (0, vec![2, 4]), (1, vec![5])]
+    /// );
+    /// ```
+    pub fn batch_by_key<Key, Keyer>(
+        self,
+        keyer: Keyer,
+    ) -> Stream<impl Operator<Out = (Key, Vec<I>)>>
+    where
+        Key: DataKey,
+        Keyer: KeyerFn<Key, I>,
+    {
+        self.add_operator(|prev| BatchByKey::new(prev, keyer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::operator::batch_by_key::BatchByKey;
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+
+    #[test]
+    fn batch_by_key_consecutive_runs() {
+        let mut prev = FakeOperator::new([("a", 1), ("a", 2), ("b", 3), ("a", 4)].into_iter());
+        prev.push(StreamElement::FlushAndRestart);
+        let mut op = BatchByKey::new(prev, |&(k, _): &(&'static str, i32)| k);
+        op.setup(&mut FakeNetworkTopology::<u8>::new(0, 0).metadata());
+
+        let run = |k, v: &[i32]| {
+            let items = v.iter().map(|&v| (k, v)).collect();
+            StreamElement::Item((k, items))
+        };
+        assert_eq!(op.next(), run("a", &[1, 2]));
+        assert_eq!(op.next(), run("b", &[3]));
+        assert_eq!(op.next(), run("a", &[4]));
+        assert_eq!(op.next(), StreamElement::FlushAndRestart);
+        assert_eq!(op.next(), StreamElement::Terminate);
+    }
+
+    #[cfg(feature = "timestamp")]
+    #[test]
+    fn batch_by_key_holds_watermarks() {
+        let mut prev = FakeOperator::empty();
+        prev.push(StreamElement::Timestamped(1, 10));
+        prev.push(StreamElement::Watermark(11));
+        prev.push(StreamElement::Timestamped(3, 12));
+        prev.push(StreamElement::Timestamped(2, 13));
+        prev.push(StreamElement::FlushAndRestart);
+        let mut op = BatchByKey::new(prev, |&n: &i32| n % 2);
+        op.setup(&mut FakeNetworkTopology::<u8>::new(0, 0).metadata());
+
+        assert_eq!(op.next(), StreamElement::Timestamped((1, vec![1, 3]), 12));
+        assert_eq!(op.next(), StreamElement::Watermark(11));
+        assert_eq!(op.next(), StreamElement::Timestamped((0, vec![2]), 13));
+        assert_eq!(op.next(), StreamElement::FlushAndRestart);
+        assert_eq!(op.next(), StreamElement::Terminate);
+    }
+}
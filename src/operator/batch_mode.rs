@@ -25,6 +25,17 @@ mod tests {
         assert_eq!(stream.block.batch_mode, batch_mode);
     }
 
+    #[test]
+    fn batch_mode_periodic() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let source = FakeOperator::<u8>::empty();
+        let batch_mode = BatchMode::periodic(42, Duration::from_millis(42));
+        let stream = env.stream(source).batch_mode(batch_mode);
+        assert_eq!(stream.block.batch_mode, batch_mode);
+        assert_eq!(batch_mode.flush_interval(), Some(Duration::from_millis(42)));
+        assert_eq!(batch_mode.max_delay(), None);
+    }
+
     #[test]
     fn batch_inherit_from_previous() {
         let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
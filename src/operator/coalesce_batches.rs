@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use crate::block::{BlockStructure, OperatorStructure};
+use crate::operator::{fmt_stage, Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct CoalesceBatches<Op>
+where
+    Op: Operator,
+{
+    prev: Op,
+    min_size: usize,
+    max_delay: Duration,
+    /// The items and watermarks held back, in the order they were received.
+    #[derivative(Debug = "ignore")]
+    buffer: VecDeque<StreamElement<Op::Out>>,
+    /// The number of items in `buffer`.
+    items: usize,
+    /// When the oldest element of `buffer` was received.
+    since: Option<Instant>,
+    /// The elements to return before pulling from `prev` again.
+    #[derivative(Debug = "ignore")]
+    ready: VecDeque<StreamElement<Op::Out>>,
+}
+
+impl<Op> Clone for CoalesceBatches<Op>
+where
+    Op: Operator,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.prev.clone(), self.min_size, self.max_delay)
+    }
+}
+
+impl<Op> CoalesceBatches<Op>
+where
+    Op: Operator,
+{
+    fn new(prev: Op, min_size: usize, max_delay: Duration) -> Self {
+        assert!(min_size > 0, "the batch size must be positive");
+        Self {
+            prev,
+            min_size,
+            max_delay,
+            buffer: Default::default(),
+            items: 0,
+            since: None,
+            ready: Default::default(),
+        }
+    }
+
+    /// Whether the buffered elements have to be released.
+    fn is_full(&self) -> bool {
+        self.items >= self.min_size
+            || self
+                .since
+                .is_some_and(|since| since.elapsed() >= self.max_delay)
+    }
+
+    /// Move the buffered elements to the ready ones, followed by `el`.
+    fn release(&mut self, el: StreamElement<Op::Out>) {
+        self.ready.append(&mut self.buffer);
+        self.ready.push_back(el);
+        self.items = 0;
+        self.since = None;
+    }
+}
+
+impl<Op> Display for CoalesceBatches<Op>
+where
+    Op: Operator,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, Op::Out>(f, &self.prev, "CoalesceBatches")
+    }
+}
+
+impl<Op> Operator for CoalesceBatches<Op>
+where
+    Op: Operator,
+{
+    type Out = Op::Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        loop {
+            if let Some(el) = self.ready.pop_front() {
+                return el;
+            }
+            let el = self.prev.next();
+            match el {
+                StreamElement::Item(_)
+                | StreamElement::Timestamped(_, _)
+                | StreamElement::Watermark(_) => {
+                    if !matches!(el, StreamElement::Watermark(_)) {
+                        self.items += 1;
+                    }
+                    self.since.get_or_insert_with(Instant::now);
+                    self.buffer.push_back(el);
+                    if self.is_full() {
+                        self.release(StreamElement::FlushBatch);
+                    }
+                }
+                // hold back the flush of a small batch, it will be merged with the next ones
+                StreamElement::FlushBatch => {
+                    if self.since.is_none() {
+                        return el;
+                    }
+                    if self.is_full() {
+                        self.release(el);
+                    }
+                }
+                StreamElement::FlushAndRestart | StreamElement::Terminate => self.release(el),
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<Self::Out, _>("CoalesceBatches");
+        operator.subtitle = format!("{} items or {:?}", self.min_size, self.max_delay);
+        self.prev.structure().add_operator(operator)
+    }
+}
+
+impl<Op> Stream<Op>
+where
+    Op: Operator + 'static,
+{
+    /// Repack the elements of the stream into batches of at least `min_size` items before they
+    /// are sent to the next block.
+    ///
+    /// After a selective operator, like a [`filter`](Stream::filter) that drops most of the
+    /// elements, the items reach the end of the block one at a time and the batches sent over the
+    /// network are almost empty, paying the overhead of a message for each item. This operator
+    /// holds back the items and the flushes of the batches until `min_size` items are buffered,
+    /// or the oldest of them waited for `max_delay`, and then releases them all together.
+    ///
+    /// The order of the items and the watermarks is preserved, the watermarks are just delayed
+    /// together with the items. The buffered elements are always released before the end of the
+    /// stream or of an iteration. This works best with a [`BatchMode::fixed`] of at least
+    /// `min_size`, since an adaptive batch mode may still split a batch when its timeout expires.
+    ///
+    /// [`BatchMode::fixed`]: crate::BatchMode::fixed
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..1000);
+    /// let res = s
+    ///     .filter(|n| n % 100 == 0)
+    ///     .coalesce_batches(1024, Duration::from_millis(10))
+    ///     .shuffle()
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, (0..10).map(|n| n * 100).collect::<Vec<_>>());
+    /// ```
+    pub fn coalesce_batches(
+        mut self,
+        min_size: usize,
+        max_delay: Duration,
+    ) -> Stream<impl Operator<Out = Op::Out>> {
+        // wake up the block often enough to release the items that waited for `max_delay`
+        let tick = max_delay / 2;
+        self.block.tick = Some(self.block.tick.map_or(tick, |t| t.min(tick)));
+        self.add_operator(|prev| CoalesceBatches::new(prev, min_size, max_delay))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::operator::coalesce_batches::CoalesceBatches;
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+
+    #[test]
+    fn coalesce_small_batches() {
+        let mut prev = FakeOperator::empty();
+        for i in 0..6 {
+            prev.push(StreamElement::Item(i));
+            prev.push(StreamElement::FlushBatch);
+        }
+        let mut coalesce = CoalesceBatches::new(prev, 3, Duration::from_secs(10));
+        coalesce.setup(&mut FakeNetworkTopology::<u8>::new(0, 0).metadata());
+
+        for batch in [[0, 1, 2], [3, 4, 5]] {
+            for i in batch {
+                assert_eq!(coalesce.next(), StreamElement::Item(i));
+            }
+            assert_eq!(coalesce.next(), StreamElement::FlushBatch);
+            // the flush after the last item of the batch has nothing to hold back
+            assert_eq!(coalesce.next(), StreamElement::FlushBatch);
+        }
+        assert_eq!(coalesce.next(), StreamElement::Terminate);
+    }
+
+    #[test]
+    fn coalesce_keeps_order() {
+        let mut prev = FakeOperator::empty();
+        prev.push(StreamElement::Timestamped(0, 1));
+        prev.push(StreamElement::FlushBatch);
+        prev.push(StreamElement::Watermark(1));
+        prev.push(StreamElement::Timestamped(1, 2));
+        prev.push(StreamElement::FlushAndRestart);
+        let mut coalesce = CoalesceBatches::new(prev, 10, Duration::from_secs(10));
+        coalesce.setup(&mut FakeNetworkTopology::<u8>::new(0, 0).metadata());
+
+        assert_eq!(coalesce.next(), StreamElement::Timestamped(0, 1));
+        assert_eq!(coalesce.next(), StreamElement::Watermark(1));
+        assert_eq!(coalesce.next(), StreamElement::Timestamped(1, 2));
+        assert_eq!(coalesce.next(), StreamElement::FlushAndRestart);
+        assert_eq!(coalesce.next(), StreamElement::Terminate);
+    }
+}
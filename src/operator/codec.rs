@@ -0,0 +1,357 @@
+//! The framing of the messages exchanged with external systems over a byte stream.
+//!
+//! A byte stream like a TCP connection has no message boundaries: a [`FrameCodec`] encodes each
+//! message in a frame, and finds the frames back in the bytes read from the stream. The built-in
+//! codecs cover the most common wire protocols:
+//!
+//! - [`LengthDelimited`]: each frame starts with the length of the message;
+//! - [`NewlineDelimited`]: each message is a line of text;
+//! - [`FixedSize`]: all the messages have the same size.
+//!
+//! The bytes read from the stream can split a frame anywhere: a [`FrameReader`] accumulates them
+//! and returns the frames as soon as they are complete.
+
+/// An error framing or deframing a message.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum FrameError {
+    /// The message is larger than the maximum size allowed by the codec.
+    #[error("frame of {size} bytes exceeds the maximum of {max} bytes")]
+    TooLarge {
+        /// The size of the message.
+        size: usize,
+        /// The maximum size allowed.
+        max: usize,
+    },
+    /// The message does not have the size of the frames of a [`FixedSize`] codec.
+    #[error("message of {size} bytes does not fit a frame of {frame_size} bytes")]
+    WrongSize {
+        /// The size of the message.
+        size: usize,
+        /// The size of the frames.
+        frame_size: usize,
+    },
+    /// The message contains the delimiter of the frames, so it cannot be encoded by a
+    /// [`NewlineDelimited`] codec.
+    #[error("message contains the frame delimiter")]
+    ContainsDelimiter,
+}
+
+/// The framing of the messages exchanged over a byte stream.
+///
+/// Implement this trait to speak the wire protocol of an external system.
+pub trait FrameCodec: Clone + Send + 'static {
+    /// Append the frame of `message` to `out`.
+    fn encode(&mut self, message: &[u8], out: &mut Vec<u8>) -> Result<(), FrameError>;
+
+    /// Find the first frame at the start of `buf`, returning its message and the number of bytes
+    /// of the frame, or `None` if `buf` does not contain a whole frame yet.
+    fn decode(&mut self, buf: &[u8]) -> Result<Option<(Vec<u8>, usize)>, FrameError>;
+}
+
+/// The default maximum size of a frame of [`LengthDelimited`] and [`NewlineDelimited`].
+const DEFAULT_MAX_FRAME_SIZE: usize = 8 << 20;
+
+/// Frames made of the length of the message, as a 4 bytes big-endian integer, followed by the
+/// message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthDelimited {
+    max_frame_size: usize,
+}
+
+impl LengthDelimited {
+    /// A codec accepting the messages up to 8 MiB.
+    pub fn new() -> Self {
+        Self {
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Set the maximum size of a message, to fail on a corrupted length instead of waiting for a
+    /// huge frame.
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size.min(u32::MAX as usize);
+        self
+    }
+}
+
+impl Default for LengthDelimited {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameCodec for LengthDelimited {
+    fn encode(&mut self, message: &[u8], out: &mut Vec<u8>) -> Result<(), FrameError> {
+        if message.len() > self.max_frame_size {
+            return Err(FrameError::TooLarge {
+                size: message.len(),
+                max: self.max_frame_size,
+            });
+        }
+        out.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        out.extend_from_slice(message);
+        Ok(())
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<Option<(Vec<u8>, usize)>, FrameError> {
+        let Some(header) = buf.get(..4) else {
+            return Ok(None);
+        };
+        let size = u32::from_be_bytes(header.try_into().unwrap()) as usize;
+        if size > self.max_frame_size {
+            return Err(FrameError::TooLarge {
+                size,
+                max: self.max_frame_size,
+            });
+        }
+        Ok(buf
+            .get(4..4 + size)
+            .map(|message| (message.to_vec(), 4 + size)))
+    }
+}
+
+/// Frames made of a line of text: the message followed by `\n`.
+///
+/// A `\r` before the `\n` is not part of the message, so the lines ended by `\r\n` are read
+/// correctly too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NewlineDelimited {
+    max_frame_size: usize,
+}
+
+impl NewlineDelimited {
+    /// A codec accepting the lines up to 8 MiB.
+    pub fn new() -> Self {
+        Self {
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Set the maximum size of a line, to fail on a stream without newlines instead of buffering
+    /// it forever.
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+}
+
+impl Default for NewlineDelimited {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameCodec for NewlineDelimited {
+    fn encode(&mut self, message: &[u8], out: &mut Vec<u8>) -> Result<(), FrameError> {
+        if message.len() > self.max_frame_size {
+            return Err(FrameError::TooLarge {
+                size: message.len(),
+                max: self.max_frame_size,
+            });
+        }
+        if message.contains(&b'\n') {
+            return Err(FrameError::ContainsDelimiter);
+        }
+        out.extend_from_slice(message);
+        out.push(b'\n');
+        Ok(())
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<Option<(Vec<u8>, usize)>, FrameError> {
+        let Some(end) = buf.iter().position(|&b| b == b'\n') else {
+            if buf.len() > self.max_frame_size {
+                return Err(FrameError::TooLarge {
+                    size: buf.len(),
+                    max: self.max_frame_size,
+                });
+            }
+            return Ok(None);
+        };
+        let line = &buf[..end];
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.len() > self.max_frame_size {
+            return Err(FrameError::TooLarge {
+                size: line.len(),
+                max: self.max_frame_size,
+            });
+        }
+        Ok(Some((line.to_vec(), end + 1)))
+    }
+}
+
+/// Frames without any header or delimiter, for messages that all have the same size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedSize {
+    frame_size: usize,
+}
+
+impl FixedSize {
+    /// A codec for messages of exactly `frame_size` bytes.
+    pub fn new(frame_size: usize) -> Self {
+        assert!(frame_size > 0, "the size of the frames must be positive");
+        Self { frame_size }
+    }
+}
+
+impl FrameCodec for FixedSize {
+    fn encode(&mut self, message: &[u8], out: &mut Vec<u8>) -> Result<(), FrameError> {
+        if message.len() != self.frame_size {
+            return Err(FrameError::WrongSize {
+                size: message.len(),
+                frame_size: self.frame_size,
+            });
+        }
+        out.extend_from_slice(message);
+        Ok(())
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<Option<(Vec<u8>, usize)>, FrameError> {
+        Ok(buf
+            .get(..self.frame_size)
+            .map(|message| (message.to_vec(), self.frame_size)))
+    }
+}
+
+/// Accumulate the bytes read from a stream and split them in frames with a [`FrameCodec`].
+///
+/// ## Example
+///
+/// ```
+/// use renoir::operator::codec::{FrameReader, NewlineDelimited};
+///
+/// let mut reader = FrameReader::new(NewlineDelimited::new());
+/// reader.extend(b"hello\nwor");
+/// assert_eq!(reader.next_frame().unwrap(), Some(b"hello".to_vec()));
+/// assert_eq!(reader.next_frame().unwrap(), None);
+/// reader.extend(b"ld\n");
+/// assert_eq!(reader.next_frame().unwrap(), Some(b"world".to_vec()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameReader<C> {
+    codec: C,
+    buf: Vec<u8>,
+    /// The start of the bytes not yet returned in a frame.
+    start: usize,
+}
+
+impl<C: FrameCodec> FrameReader<C> {
+    /// A reader with an empty buffer.
+    pub fn new(codec: C) -> Self {
+        Self {
+            codec,
+            buf: Vec::new(),
+            start: 0,
+        }
+    }
+
+    /// Add the bytes read from the stream.
+    pub fn extend(&mut self, bytes: &[u8]) {
+        // drop the bytes already returned when they are most of the buffer
+        if self.start > self.buf.len() / 2 {
+            self.buf.drain(..self.start);
+            self.start = 0;
+        }
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// The next complete frame, or `None` if the bytes of the next frame are not all available
+    /// yet.
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, FrameError> {
+        match self.codec.decode(&self.buf[self.start..])? {
+            Some((message, len)) => {
+                self.start += len;
+                Ok(Some(message))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The number of bytes received that are not part of a complete frame yet.
+    pub fn pending(&self) -> usize {
+        self.buf.len() - self.start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode the messages, and decode them back reading the bytes in chunks of `chunk` bytes.
+    fn roundtrip<C: FrameCodec>(codec: C, messages: &[&[u8]], chunk: usize) -> Vec<Vec<u8>> {
+        let mut encoder = codec.clone();
+        let mut bytes = Vec::new();
+        for message in messages {
+            encoder.encode(message, &mut bytes).unwrap();
+        }
+
+        let mut reader = FrameReader::new(codec);
+        let mut decoded = Vec::new();
+        for read in bytes.chunks(chunk) {
+            reader.extend(read);
+            while let Some(message) = reader.next_frame().unwrap() {
+                decoded.push(message);
+            }
+        }
+        assert_eq!(reader.pending(), 0);
+        decoded
+    }
+
+    fn assert_roundtrip<C: FrameCodec>(codec: C, messages: &[&[u8]]) {
+        // the frames are split across the reads in every possible way
+        for chunk in [1, 2, 3, 7, 64, usize::MAX] {
+            let decoded = roundtrip(codec.clone(), messages, chunk);
+            assert_eq!(decoded, messages, "reading chunks of {chunk} bytes");
+        }
+    }
+
+    #[test]
+    fn length_delimited() {
+        let long = vec![42; 1000];
+        assert_roundtrip(
+            LengthDelimited::new(),
+            &[b"hello", b"", b"with\nnewlines\n", &long],
+        );
+
+        let mut codec = LengthDelimited::new().max_frame_size(4);
+        let mut out = Vec::new();
+        let err = codec.encode(b"hello", &mut out).unwrap_err();
+        assert_eq!(err, FrameError::TooLarge { size: 5, max: 4 });
+        let err = codec.decode(&[0, 0, 0, 5]).unwrap_err();
+        assert_eq!(err, FrameError::TooLarge { size: 5, max: 4 });
+    }
+
+    #[test]
+    fn newline_delimited() {
+        assert_roundtrip(
+            NewlineDelimited::new(),
+            &[b"hello", b"", b"world", b"with\ttabs"],
+        );
+
+        let mut codec = NewlineDelimited::new().max_frame_size(4);
+        assert_eq!(
+            codec.decode(b"ab\r\ncd\n").unwrap(),
+            Some((b"ab".to_vec(), 4))
+        );
+        let mut out = Vec::new();
+        let err = codec.encode(b"a\nb", &mut out).unwrap_err();
+        assert_eq!(err, FrameError::ContainsDelimiter);
+        // a line without a newline cannot grow forever
+        let err = codec.decode(b"hello").unwrap_err();
+        assert_eq!(err, FrameError::TooLarge { size: 5, max: 4 });
+    }
+
+    #[test]
+    fn fixed_size() {
+        assert_roundtrip(FixedSize::new(3), &[b"abc", b"def", b"\n\0\n"]);
+
+        let mut out = Vec::new();
+        let err = FixedSize::new(3).encode(b"ab", &mut out).unwrap_err();
+        assert_eq!(
+            err,
+            FrameError::WrongSize {
+                size: 2,
+                frame_size: 3
+            }
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Transparent compression of the files read and written by the operators.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// The compression of the files written by a sink.
+///
+/// This is independent of the compression of the messages sent between the hosts. The compressed
+/// formats require the `compression` feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputCompression {
+    /// Write the content as is.
+    #[default]
+    None,
+    /// Gzip, usually with the `.gz` extension.
+    Gzip,
+    /// Zstandard, usually with the `.zst` extension.
+    Zstd,
+    /// The framing format of Snappy, usually with the `.sz` extension.
+    Snappy,
+}
+
+/// The compression of a file, detected from its extension.
+///
+/// The compressed files (`.gz`, `.zst` and `.sz`) require the `compression` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Snappy,
+}
+
+impl From<OutputCompression> for Compression {
+    fn from(compression: OutputCompression) -> Self {
+        match compression {
+            OutputCompression::None => Compression::None,
+            OutputCompression::Gzip => Compression::Gzip,
+            OutputCompression::Zstd => Compression::Zstd,
+            OutputCompression::Snappy => Compression::Snappy,
+        }
+    }
+}
+
+impl Compression {
+    pub(crate) fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            Some("sz") => Compression::Snappy,
+            _ => Compression::None,
+        }
+    }
+
+    pub(crate) fn is_compressed(self) -> bool {
+        self != Compression::None
+    }
+
+    /// Read the content of `file`, decompressing it while it is read.
+    pub(crate) fn reader(self, file: File) -> Box<dyn BufRead + Send> {
+        match self {
+            Compression::None => Box::new(BufReader::new(file)),
+            #[cfg(feature = "compression")]
+            Compression::Gzip => Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(file))),
+            #[cfg(feature = "compression")]
+            Compression::Zstd => Box::new(BufReader::new(
+                zstd::Decoder::new(file).expect("Cannot create the zstd decoder"),
+            )),
+            #[cfg(feature = "compression")]
+            Compression::Snappy => Box::new(BufReader::new(snap::read::FrameDecoder::new(file))),
+            #[cfg(not(feature = "compression"))]
+            _ => panic!("Reading {self:?} files requires the `compression` feature"),
+        }
+    }
+
+    /// Write to `file`, compressing the content. The compressed stream is completed when the
+    /// writer is dropped.
+    pub(crate) fn writer(self, file: File) -> Box<dyn Write + Send> {
+        match self {
+            Compression::None => Box::new(BufWriter::new(file)),
+            #[cfg(feature = "compression")]
+            Compression::Gzip => Box::new(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::default(),
+            )),
+            #[cfg(feature = "compression")]
+            Compression::Zstd => Box::new(
+                zstd::Encoder::new(file, zstd::DEFAULT_COMPRESSION_LEVEL)
+                    .expect("Cannot create the zstd encoder")
+                    .auto_finish(),
+            ),
+            #[cfg(feature = "compression")]
+            Compression::Snappy => Box::new(snap::write::FrameEncoder::new(file)),
+            #[cfg(not(feature = "compression"))]
+            _ => panic!("Writing {self:?} files requires the `compression` feature"),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use std::fs::File;
+    use std::io::{Read, Write};
+
+    use itertools::Itertools;
+    use serde::{Deserialize, Serialize};
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::FileSource;
+    use crate::operator::OutputCompression;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Event {
+        id: u32,
+        name: String,
+    }
+
+    #[test]
+    fn gzip_source_zstd_sink_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let events = (0..1000)
+            .map(|id| Event {
+                id,
+                name: format!("event-{id}"),
+            })
+            .collect_vec();
+
+        let input = dir.path().join("events.jsonl.gz");
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&input).unwrap(), Default::default());
+        for event in &events {
+            writeln!(encoder, "{}", serde_json::to_string(event).unwrap()).unwrap();
+        }
+        encoder.finish().unwrap();
+
+        // the compressed file is read whole by a single replica
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let output = dir.path().join("events.csv.zst");
+        env.stream(FileSource::new(&input))
+            .map(|line| serde_json::from_str::<Event>(&line).unwrap())
+            .write_csv_one(&output, false);
+        env.execute_blocking();
+
+        let mut content = String::new();
+        zstd::Decoder::new(File::open(&output).unwrap())
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        let res: Vec<Event> = csv::Reader::from_reader(content.as_bytes())
+            .deserialize()
+            .map(Result::unwrap)
+            .sorted_by_key(|e: &Event| e.id)
+            .collect();
+        assert_eq!(res, events);
+    }
+
+    #[test]
+    fn gzip_csv_sink_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("squares.csv.gz");
+
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        env.stream_par_iter(0..1000u32)
+            .map(|n| (n, n * n))
+            .write_csv_one_compressed(&output, false, OutputCompression::Gzip);
+        env.execute_blocking();
+
+        let mut magic = [0; 2];
+        File::open(&output).unwrap().read_exact(&mut magic).unwrap();
+        assert_eq!(magic, [0x1f, 0x8b]);
+
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env
+            .stream(FileSource::new(&output))
+            .map(|line| {
+                let (n, square) = line.trim_end().split_once(',').unwrap();
+                (n.parse::<u32>().unwrap(), square.parse::<u32>().unwrap())
+            })
+            .collect_vec();
+        env.execute_blocking();
+
+        let res = res.get().unwrap().into_iter().sorted().collect_vec();
+        assert_eq!(res, (0..1000).map(|n| (n, n * n)).collect_vec());
+    }
+}
@@ -0,0 +1,188 @@
+use std::sync::Arc;
+
+use flume::Receiver;
+use parking_lot::{RwLock, RwLockReadGuard};
+
+use crate::operator::{Data, Operator};
+use crate::Stream;
+
+/// A [`Stream`] whose next operator reads a state updated by out-of-band control messages.
+///
+/// Build it with [`Stream::with_control`].
+pub struct ControlledStream<Op, S, C, F>
+where
+    Op: Operator,
+{
+    inner: Stream<Op>,
+    control: Control<S, C, F>,
+}
+
+/// The state shared by the replicas of an operator, with the channel of its control messages.
+struct Control<S, C, F> {
+    state: Arc<RwLock<S>>,
+    receiver: Receiver<C>,
+    apply: Arc<F>,
+}
+
+impl<S, C, F> Clone for Control<S, C, F> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            receiver: self.receiver.clone(),
+            apply: self.apply.clone(),
+        }
+    }
+}
+
+impl<S, C, F> Control<S, C, F>
+where
+    F: Fn(&mut S, C),
+{
+    /// Apply the pending control messages, then return the current state.
+    fn state(&self) -> RwLockReadGuard<'_, S> {
+        if !self.receiver.is_empty() {
+            let mut state = self.state.write();
+            while let Ok(msg) = self.receiver.try_recv() {
+                (self.apply)(&mut state, msg);
+            }
+        }
+        self.state.read()
+    }
+}
+
+impl<Op> Stream<Op>
+where
+    Op: Operator + 'static,
+{
+    /// Feed out-of-band control messages to the next operator, to reconfigure it while the job
+    /// is running (e.g. changing the threshold of a filter) without restarting it.
+    ///
+    /// The next operator reads `state`, and each message received from `receiver` is applied to
+    /// the state with `apply`. The pending messages are applied before the operator processes
+    /// each element, so an element is always processed with all the messages sent before it was
+    /// pulled from the previous operator.
+    ///
+    /// The state is shared by all the replicas of the operator in the process, and each message
+    /// is applied once. When the job is distributed, each host must send the messages to its own
+    /// receiver.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let (threshold, receiver) = flume::unbounded();
+    /// let res = env
+    ///     .stream_iter(0..10)
+    ///     .with_control(receiver, 5, |t, new| *t = new)
+    ///     .filter(|t, &n| n >= *t)
+    ///     .collect_vec();
+    ///
+    /// // the items are filtered with the last threshold received
+    /// threshold.send(8).unwrap();
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![8, 9]);
+    /// ```
+    pub fn with_control<S, C, F>(
+        self,
+        receiver: Receiver<C>,
+        state: S,
+        apply: F,
+    ) -> ControlledStream<Op, S, C, F>
+    where
+        S: Send + Sync + 'static,
+        C: Send + 'static,
+        F: Fn(&mut S, C) + Send + Sync + 'static,
+    {
+        let control = Control {
+            state: Arc::new(RwLock::new(state)),
+            receiver,
+            apply: Arc::new(apply),
+        };
+        ControlledStream {
+            inner: self,
+            control,
+        }
+    }
+}
+
+impl<Op, S, C, F> ControlledStream<Op, S, C, F>
+where
+    Op: Operator + 'static,
+    S: Send + Sync + 'static,
+    C: Send + 'static,
+    F: Fn(&mut S, C) + Send + Sync + 'static,
+{
+    /// Like [`Stream::filter`], with the predicate reading the current state.
+    pub fn filter<P>(self, predicate: P) -> Stream<impl Operator<Out = Op::Out>>
+    where
+        P: Fn(&S, &Op::Out) -> bool + Clone + Send + 'static,
+    {
+        let control = self.control;
+        self.inner
+            .filter(move |item| predicate(&control.state(), item))
+    }
+
+    /// Like [`Stream::map`], with the function reading the current state.
+    pub fn map<O, G>(self, f: G) -> Stream<impl Operator<Out = O>>
+    where
+        G: Fn(&S, Op::Out) -> O + Clone + Send + 'static,
+        O: Send,
+    {
+        let control = self.control;
+        self.inner.map(move |item| f(&control.state(), item))
+    }
+
+    /// Like [`Stream::filter_map`], with the function reading the current state.
+    pub fn filter_map<O, G>(self, f: G) -> Stream<impl Operator<Out = O>>
+    where
+        G: Fn(&S, Op::Out) -> Option<O> + Clone + Send + 'static,
+        O: Data,
+    {
+        let control = self.control;
+        self.inner.filter_map(move |item| f(&control.state(), item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+
+    #[test]
+    fn control_changes_filter_threshold() {
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        let (threshold, receiver) = flume::unbounded();
+        // the threshold is raised while the stream is running, before the item 5 is filtered
+        let items = (0..10).inspect(move |&n| {
+            if n == 5 {
+                threshold.send(7).unwrap();
+            }
+        });
+        let res = env
+            .stream_iter(items)
+            .with_control(receiver, 0, |t, new| *t = new)
+            .filter(|t, &n| n >= *t)
+            .collect_vec();
+        env.execute_blocking();
+        assert_eq!(res.get().unwrap(), vec![0, 1, 2, 3, 4, 7, 8, 9]);
+    }
+
+    #[test]
+    fn control_shared_by_replicas() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let (offset, receiver) = flume::unbounded();
+        offset.send(10).unwrap();
+        offset.send(90).unwrap();
+        let res = env
+            .stream_par_iter(0..100u64)
+            .with_control(receiver, 0, |o, add| *o += add)
+            .map(|o, n| n + o)
+            .collect_vec();
+        env.execute_blocking();
+        let mut res = res.get().unwrap();
+        res.sort();
+        assert_eq!(res, (100..200).collect::<Vec<_>>());
+    }
+}
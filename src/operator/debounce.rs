@@ -0,0 +1,237 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use crate::block::{BlockStructure, GroupHasherBuilder, NextStrategy, OperatorStructure};
+use crate::operator::end::End;
+use crate::operator::{fmt_stage, DataKey, ExchangeData, ExchangeDataKey, Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::stream::KeyedStream;
+
+/// The latest element of each key, with when it was received.
+type Pending<K, V> = HashMap<K, (Instant, StreamElement<(K, V)>), GroupHasherBuilder>;
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct Debounce<K, V, Op>
+where
+    K: DataKey,
+    V: Send,
+    Op: Operator<Out = (K, V)>,
+{
+    prev: Op,
+    duration: Duration,
+    /// The latest element of each key waiting to be emitted, with when it was received.
+    #[derivative(Debug = "ignore")]
+    pending: Pending<K, V>,
+    /// The updates of the keys in the order they were received. An update is stale if its key
+    /// received a newer one.
+    #[derivative(Debug = "ignore")]
+    updates: VecDeque<(Instant, K)>,
+    /// The elements ready to be returned.
+    #[derivative(Debug = "ignore")]
+    ready: VecDeque<StreamElement<(K, V)>>,
+}
+
+impl<K, V, Op> Clone for Debounce<K, V, Op>
+where
+    K: DataKey,
+    V: Send,
+    Op: Operator<Out = (K, V)>,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.prev.clone(), self.duration)
+    }
+}
+
+impl<K, V, Op> Debounce<K, V, Op>
+where
+    K: DataKey,
+    V: Send,
+    Op: Operator<Out = (K, V)>,
+{
+    fn new(prev: Op, duration: Duration) -> Self {
+        Self {
+            prev,
+            duration,
+            pending: Default::default(),
+            updates: Default::default(),
+            ready: Default::default(),
+        }
+    }
+
+    /// Emit the latest element of the keys that received no updates for `duration` before `now`.
+    fn emit_quiet(&mut self, now: Instant) {
+        while let Some((at, _)) = self.updates.front() {
+            if now.duration_since(*at) < self.duration {
+                break;
+            }
+            let (at, key) = self.updates.pop_front().unwrap();
+            self.emit_update(at, key);
+        }
+    }
+
+    /// Emit the latest element of all the keys.
+    fn emit_all(&mut self) {
+        while let Some((at, key)) = self.updates.pop_front() {
+            self.emit_update(at, key);
+        }
+    }
+
+    /// Emit the latest element of `key` if the update received `at` is not stale.
+    fn emit_update(&mut self, at: Instant, key: K) {
+        if self.pending.get(&key).is_some_and(|(last, _)| *last == at) {
+            let (_, el) = self.pending.remove(&key).unwrap();
+            self.ready.push_back(el);
+        }
+    }
+}
+
+impl<K, V, Op> Display for Debounce<K, V, Op>
+where
+    K: DataKey,
+    V: Send,
+    Op: Operator<Out = (K, V)>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, Op::Out>(f, &self.prev, "Debounce")
+    }
+}
+
+impl<K, V, Op> Operator for Debounce<K, V, Op>
+where
+    K: DataKey,
+    V: Send,
+    Op: Operator<Out = (K, V)>,
+{
+    type Out = (K, V);
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        loop {
+            if let Some(el) = self.ready.pop_front() {
+                return el;
+            }
+            let el = self.prev.next();
+            match &el {
+                StreamElement::Item((key, _)) | StreamElement::Timestamped((key, _), _) => {
+                    let now = Instant::now();
+                    let key = key.clone();
+                    self.updates.push_back((now, key.clone()));
+                    self.pending.insert(key, (now, el));
+                    self.emit_quiet(now);
+                }
+                StreamElement::Watermark(_) | StreamElement::FlushBatch => {
+                    self.emit_quiet(Instant::now());
+                    self.ready.push_back(el);
+                }
+                StreamElement::FlushAndRestart | StreamElement::Terminate => {
+                    self.emit_all();
+                    self.ready.push_back(el);
+                }
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<Self::Out, _>("Debounce");
+        operator.subtitle = format!("{:?}", self.duration);
+        self.prev.structure().add_operator(operator)
+    }
+}
+
+impl<K, V, Op> KeyedStream<Op>
+where
+    K: ExchangeDataKey,
+    V: ExchangeData,
+    Op: Operator<Out = (K, V)> + 'static,
+{
+    /// Emit the latest value of each key only once the key received no updates for `duration`.
+    ///
+    /// The updates of a key that arrive closer than `duration` to each other are collapsed into
+    /// the last one, which is emitted `duration` after it was received. This reduces the churn
+    /// caused by the keys that update very often, like the price of a stock shown to a user.
+    /// Unlike a rate limit, which applies to the whole stream, the quiet period is tracked for
+    /// each key separately. When the stream ends the pending values are emitted immediately.
+    ///
+    /// The time is measured when the elements are received, and the timestamps of the emitted
+    /// elements are kept. The watermarks are not held back by the pending values, which may be
+    /// late for the event time operators that follow.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter([(0, 1), (1, 10), (0, 2), (0, 3)].into_iter());
+    /// let res = s
+    ///     .group_by(|&(key, _)| key)
+    ///     .map(|(_, (_, price))| price)
+    ///     .debounce(Duration::from_secs(1))
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, vec![(0, 3), (1, 10)]);
+    /// ```
+    pub fn debounce(self, duration: Duration) -> KeyedStream<impl Operator<Out = (K, V)>> {
+        // keep the same parallelism of the previous block, and so the same keys in each replica
+        let scheduler_requirements = self.0.block.scheduling.clone();
+        let mut new_stream = self.0.split_block(End::new, NextStrategy::only_one());
+        new_stream.block.scheduling = scheduler_requirements;
+        // wake up the new block often enough to emit the keys that became quiet
+        new_stream.block.tick = Some(duration / 4);
+        KeyedStream(new_stream.add_operator(|prev| Debounce::new(prev, duration)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::operator::debounce::Debounce;
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+
+    #[test]
+    fn debounce_burst() {
+        let prev = FakeOperator::new([(0, 1), (0, 2), (0, 3)].into_iter());
+        let mut debounce = Debounce::new(prev, Duration::from_secs(10));
+        debounce.setup(&mut FakeNetworkTopology::<(u8, u8)>::new(0, 0).metadata());
+
+        assert_eq!(debounce.next(), StreamElement::Item((0, 3)));
+        assert_eq!(debounce.next(), StreamElement::Terminate);
+    }
+
+    #[test]
+    fn debounce_after_quiet() {
+        let mut prev = FakeOperator::new([(0, 1), (1, 10), (0, 2), (0, 3)].into_iter());
+        prev.push(StreamElement::FlushBatch);
+        let mut debounce = Debounce::new(prev, Duration::from_millis(50));
+        debounce.setup(&mut FakeNetworkTopology::<(u8, u8)>::new(0, 0).metadata());
+
+        // the keys are still updating
+        assert_eq!(debounce.next(), StreamElement::FlushBatch);
+
+        std::thread::sleep(Duration::from_millis(60));
+        debounce.prev.push(StreamElement::FlushBatch);
+        let mut emitted = (0..2)
+            .map(|_| match debounce.next() {
+                StreamElement::Item(item) => item,
+                el => panic!("expected an item, got {el:?}"),
+            })
+            .collect::<Vec<_>>();
+        emitted.sort();
+        assert_eq!(emitted, vec![(0, 3), (1, 10)]);
+        assert_eq!(debounce.next(), StreamElement::FlushBatch);
+        assert_eq!(debounce.next(), StreamElement::Terminate);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::operator::{DataKey, ExchangeData, Operator};
+use crate::stream::Stream;
+
+/// Which element of each key [`Stream::dedup_by_key`] keeps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DedupPolicy {
+    /// Keep the first element of each key: without windows it is forwarded as soon as it
+    /// arrives.
+    #[default]
+    KeepFirst,
+    /// Keep the last element of each key, which is known only at the end of the stream (or of
+    /// the window).
+    KeepLast,
+}
+
+impl<I, Op> Stream<Op>
+where
+    I: ExchangeData,
+    Op: Operator<Out = I> + 'static,
+{
+    /// Remove the elements with the same key, keeping only one element for each key according to
+    /// `policy`.
+    ///
+    /// With [`DedupPolicy::KeepFirst`] the first element of each key is forwarded immediately and
+    /// the following ones are dropped. With [`DedupPolicy::KeepLast`] the last element of each
+    /// key is kept until the end of the stream, so it is emitted only once the stream ends.
+    ///
+    /// The elements are partitioned by key like [`Stream::group_by`], and the keys seen are kept
+    /// forever: to deduplicate an unbounded stream use the windowed version,
+    /// [`WindowedStream::dedup_by_key`](crate::WindowedStream::dedup_by_key).
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// use renoir::operator::DedupPolicy;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter([('a', 1), ('b', 2), ('a', 3)].into_iter());
+    /// let res = s.dedup_by_key(|&(k, _)| k, DedupPolicy::KeepLast).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, vec![('a', 3), ('b', 2)]);
+    /// ```
+    pub fn dedup_by_key<K, F>(self, key: F, policy: DedupPolicy) -> Stream<impl Operator<Out = I>>
+    where
+        K: DataKey,
+        F: Fn(&I) -> K + Send + Clone + 'static,
+    {
+        let keyed = self.group_by(key);
+        // the two policies are different chains, the box gives them the same type
+        match policy {
+            DedupPolicy::KeepFirst => {
+                let mut seen = false;
+                keyed
+                    .rich_filter_map(move |(_, x)| {
+                        (!std::mem::replace(&mut seen, true)).then_some(x)
+                    })
+                    .drop_key()
+                    .into_boxed()
+            }
+            DedupPolicy::KeepLast => keyed.reduce(|last, x| *last = x).drop_key().into_boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DedupPolicy;
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+
+    fn dedup(policy: DedupPolicy) -> Vec<(char, u32)> {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env
+            .stream_iter([('a', 1), ('b', 2), ('a', 3)])
+            .dedup_by_key(|&(k, _)| k, policy)
+            .collect_vec();
+        env.execute_blocking();
+
+        let mut res = res.get().unwrap();
+        res.sort_unstable();
+        res
+    }
+
+    #[test]
+    fn dedup_by_key_keep_first() {
+        assert_eq!(dedup(DedupPolicy::KeepFirst), vec![('a', 1), ('b', 2)]);
+    }
+
+    #[test]
+    fn dedup_by_key_keep_last() {
+        assert_eq!(dedup(DedupPolicy::KeepLast), vec![('a', 3), ('b', 2)]);
+    }
+}
@@ -1,23 +1,104 @@
 use std::collections::HashMap;
 use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Instant;
 
 use crate::block::{
-    BatchMode, Batcher, BlockStructure, Connection, NextStrategy, OperatorStructure,
+    AutoscaleController, BatchMode, Batcher, BlockStructure, Connection, NextStrategy,
+    OperatorStructure,
 };
 use crate::network::{Coord, ReceiverEndpoint};
 use crate::operator::{ExchangeData, KeyerFn, Operator, StreamElement};
-use crate::scheduler::{BlockId, ExecutionMetadata};
+use crate::scheduler::{BlockId, ExecutionMetadata, HostId};
+
+/// The default imbalance among the replicas of a group-by that triggers a warning, see
+/// [`Stream::skew_warning`](crate::Stream::skew_warning).
+const DEFAULT_SKEW_WARNING: f64 = 4.0;
+
+/// The minimum number of items sent to a block before checking if they are skewed.
+const MIN_SKEW_ITEMS: usize = 1000;
+
+/// Find the replica that received too many items, if any.
+///
+/// Returns the position of the replica in `counts` and how many times more items than the average
+/// it received, when that is more than `threshold`.
+fn hot_replica(counts: &[usize], threshold: f64) -> Option<(usize, f64)> {
+    let total: usize = counts.iter().sum();
+    if counts.len() < 2 || total < MIN_SKEW_ITEMS {
+        return None;
+    }
+    let (hot, &max) = counts.iter().enumerate().max_by_key(|&(_, &count)| count)?;
+    let ratio = max as f64 * counts.len() as f64 / total as f64;
+    (ratio > threshold).then_some((hot, ratio))
+}
 
 /// The list with the interesting senders of a single block.
 #[derive(Debug, Clone)]
 pub(crate) struct BlockSenders {
     /// Indexes of the senders for all the replicas of this box
     pub indexes: Vec<usize>,
+    /// The cumulative weights of the replicas, for picking them proportionally to the weight of
+    /// their host. Empty if all the replicas weigh the same.
+    pub cumulative_weights: Vec<f64>,
 }
 
 impl BlockSenders {
     pub(crate) fn new(indexes: Vec<usize>) -> Self {
-        Self { indexes }
+        Self {
+            indexes,
+            cumulative_weights: Vec::new(),
+        }
+    }
+
+    /// Weigh the replicas, given the host of each of them and the weights of the hosts.
+    ///
+    /// The weight of a host is split evenly among its replicas.
+    fn set_weights(&mut self, hosts: &[HostId], host_weights: &[f64]) {
+        let mut replicas_per_host = HashMap::<HostId, usize>::new();
+        for host in hosts {
+            *replicas_per_host.entry(*host).or_default() += 1;
+        }
+        let weights = hosts
+            .iter()
+            .map(|host| host_weights[*host as usize] / replicas_per_host[host] as f64)
+            .collect::<Vec<_>>();
+        self.cumulative_weights.clear();
+        if weights.iter().all(|w| *w == weights[0]) {
+            return;
+        }
+        let mut total = 0.0;
+        for w in weights {
+            total += w;
+            self.cumulative_weights.push(total);
+        }
+    }
+
+    /// The index of the sender of the replica chosen by a partitioner, given the message.
+    #[inline]
+    pub(crate) fn partition<Out>(
+        &self,
+        partitioner: &(dyn Fn(&Out, usize) -> usize + Send + Sync),
+        message: &Out,
+    ) -> usize {
+        let num_replicas = self.indexes.len();
+        let index = partitioner(message, num_replicas);
+        assert!(
+            index < num_replicas,
+            "the partitioner chose replica {index}, but there are only {num_replicas} replicas"
+        );
+        self.indexes[index]
+    }
+
+    /// Pick the index of the sender of a replica, given a random `index`.
+    #[inline]
+    fn pick(&self, index: usize) -> usize {
+        let Some(total) = self.cumulative_weights.last() else {
+            return self.indexes[index % self.indexes.len()];
+        };
+        // the 53 most significant bits of the index are a uniform float in [0, 1)
+        let r = ((index as u64) >> 11) as f64 / (1u64 << 53) as f64 * total;
+        let i = self.cumulative_weights.partition_point(|w| *w <= r);
+        self.indexes[i.min(self.indexes.len() - 1)]
     }
 }
 
@@ -35,6 +116,11 @@ where
     senders: Vec<(ReceiverEndpoint, Batcher<OperatorChain::Out>)>,
     feedback_id: Option<BlockId>,
     ignore_block_ids: Vec<BlockId>,
+    autoscale: Option<AutoscaleController>,
+    host_weights: Arc<Vec<f64>>,
+    /// The number of items sent to each sender.
+    sent: Vec<usize>,
+    skew_warning: f64,
 }
 
 impl<OperatorChain: std::fmt::Debug, IndexFn: std::fmt::Debug> std::fmt::Debug
@@ -73,6 +159,10 @@ where
             senders: Default::default(),
             feedback_id: self.feedback_id,
             ignore_block_ids: self.ignore_block_ids.clone(),
+            autoscale: None,
+            host_weights: self.host_weights.clone(),
+            sent: Default::default(),
+            skew_warning: self.skew_warning,
         }
     }
 }
@@ -87,6 +177,7 @@ where
         match self.next_strategy {
             NextStrategy::Random => write!(f, "{} -> Shuffle", self.prev),
             NextStrategy::OnlyOne => write!(f, "{} -> OnlyOne", self.prev),
+            NextStrategy::Adaptive(_) => write!(f, "{} -> AdaptiveShuffle", self.prev),
             _ => self.prev.fmt(f),
         }
     }
@@ -112,6 +203,10 @@ where
             senders: Default::default(),
             feedback_id: None,
             ignore_block_ids: Default::default(),
+            autoscale: None,
+            host_weights: Default::default(),
+            sent: Default::default(),
+            skew_warning: DEFAULT_SKEW_WARNING,
         }
     }
 
@@ -142,6 +237,42 @@ where
                 .iter()
                 .for_each(|s| assert_eq!(s.indexes.len(), 1));
         }
+
+        // the random shuffle favours the replicas on the hosts with more weight
+        if matches!(self.next_strategy, NextStrategy::Random) && !self.host_weights.is_empty() {
+            for block in self.block_senders.iter_mut() {
+                let hosts = block
+                    .indexes
+                    .iter()
+                    .map(|&i| self.senders[i].0.coord.host_id)
+                    .collect::<Vec<_>>();
+                block.set_weights(&hosts, &self.host_weights);
+            }
+        }
+    }
+
+    /// Warn about the blocks whose replicas received a very unbalanced share of the items.
+    fn warn_skew(&self) {
+        for block in &self.block_senders {
+            let counts = block
+                .indexes
+                .iter()
+                .map(|&i| self.sent[i])
+                .collect::<Vec<_>>();
+            if let Some((hot, ratio)) = hot_replica(&counts, self.skew_warning) {
+                let total: usize = counts.iter().sum();
+                log::warn!(
+                    "{}: key skew, replica {} received {} of the {} items of the group by ({:.1}x \
+                    the average), a few keys are probably very frequent: consider splitting them \
+                    with Stream::partition_custom",
+                    self.coord.unwrap(),
+                    self.senders[block.indexes[hot]].0.coord,
+                    counts[hot],
+                    total,
+                    ratio
+                );
+            }
+        }
     }
 
     /// Mark this `End` as the end of a feedback loop.
@@ -176,8 +307,14 @@ where
             .filter(|(endpoint, _)| !self.ignore_block_ids.contains(&endpoint.coord.block_id))
             .map(|(coord, sender)| (coord, Batcher::new(sender, self.batch_mode, metadata.coord)))
             .collect();
+        self.host_weights = metadata.host_weights.clone();
+        self.sent = vec![0; self.senders.len()];
+        self.skew_warning = metadata.skew_warning.unwrap_or(DEFAULT_SKEW_WARNING);
 
         self.setup_senders();
+        if let NextStrategy::Adaptive(autoscale) = self.next_strategy {
+            self.autoscale = Some(AutoscaleController::new(autoscale));
+        }
 
         self.coord = Some(metadata.coord);
     }
@@ -207,13 +344,32 @@ where
                 }
             }
             // Direct messages
-            StreamElement::Item(item) | StreamElement::Timestamped(item, _) => {
+            StreamElement::Item(item) | StreamElement::Timestamped(item, _)
+                if self.autoscale.is_some() =>
+            {
+                let autoscale = self.autoscale.as_mut().unwrap();
                 let index = self.next_strategy.index(item);
+                let start = Instant::now();
                 for block in self.block_senders.iter() {
-                    let index = index % block.indexes.len();
+                    let index = index % autoscale.active().min(block.indexes.len());
                     let sender_idx = block.indexes[index];
                     self.senders[sender_idx].1.enqueue(message.clone());
                 }
+                // the time spent sending is the time spent waiting for the next replicas
+                autoscale.record(start.elapsed());
+            }
+            StreamElement::Item(item) | StreamElement::Timestamped(item, _) => {
+                let index = self.next_strategy.index(item);
+                for block in self.block_senders.iter() {
+                    let sender_idx = match &self.next_strategy {
+                        NextStrategy::Partition(partitioner) => {
+                            block.partition(partitioner.as_ref(), item)
+                        }
+                        _ => block.pick(index),
+                    };
+                    self.sent[sender_idx] += 1;
+                    self.senders[sender_idx].1.enqueue(message.clone());
+                }
             }
             StreamElement::FlushBatch => {}
         };
@@ -231,6 +387,9 @@ where
                     self.coord.unwrap(),
                     self.senders.len()
                 );
+                if matches!(self.next_strategy, NextStrategy::GroupBy(_, _)) {
+                    self.warn_skew();
+                }
                 for (_, batcher) in self.senders.drain(..) {
                     batcher.end();
                 }
@@ -243,6 +402,7 @@ where
 
     fn structure(&self) -> BlockStructure {
         let mut operator = OperatorStructure::new::<OperatorChain::Out, _>("End");
+        operator.next_strategy = Some((&self.next_strategy).into());
         for sender_group in &self.block_senders {
             if !sender_group.indexes.is_empty() {
                 let block_id = self.senders[sender_group.indexes[0]].0.coord.block_id;
@@ -254,6 +414,86 @@ where
                     ));
             }
         }
-        self.prev.structure().add_operator(operator)
+        let mut structure = self.prev.structure().add_operator(operator);
+        structure.batch_mode = Some(self.batch_mode);
+        structure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nanorand::{tls_rng, Rng};
+
+    use crate::block::NextStrategy;
+
+    use super::{hot_replica, BlockSenders};
+
+    #[test]
+    fn weighted_random_distribution() {
+        // two replicas on each of two hosts weighing 1:3
+        let mut senders = BlockSenders::new(vec![0, 1, 2, 3]);
+        senders.set_weights(&[0, 0, 1, 1], &[1.0, 3.0]);
+
+        let n = 100_000;
+        let mut counts = [0usize; 4];
+        for _ in 0..n {
+            counts[senders.pick(tls_rng().generate())] += 1;
+        }
+        let host1 = (counts[2] + counts[3]) as f64 / n as f64;
+        assert!((host1 - 0.75).abs() < 0.01, "{counts:?}");
+        // the replicas of the same host share its weight
+        assert!(counts[0].abs_diff(counts[1]) < n / 50, "{counts:?}");
+    }
+
+    #[test]
+    fn same_weights_are_uniform() {
+        let mut senders = BlockSenders::new(vec![0, 1, 2]);
+        senders.set_weights(&[0, 1, 1], &[2.0, 4.0]);
+        assert!(senders.cumulative_weights.is_empty());
+        assert_eq!(
+            (0..6).map(|i| senders.pick(i)).collect::<Vec<_>>(),
+            [0, 1, 2, 0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn partition_picks_the_chosen_replica() {
+        let senders = BlockSenders::new(vec![4, 5, 6]);
+        let partitioner = |n: &i32, num_replicas: usize| n.rem_euclid(num_replicas as i32) as usize;
+        assert_eq!(
+            [-1, 0, 1, 2].map(|n| senders.partition(&partitioner, &n)),
+            [6, 4, 5, 6]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "only 3 replicas")]
+    fn partition_out_of_range() {
+        let senders = BlockSenders::new(vec![0, 1, 2]);
+        senders.partition(&|_: &i32, _| 3, &0);
+    }
+
+    #[test]
+    fn skewed_keys() {
+        // most of the items have the same key
+        let keys = (0..2000).map(|i| if i % 10 == 0 { i } else { 42 });
+        let strategy = NextStrategy::<u64>::group_by(|&k: &u64| k, Default::default());
+        let senders = BlockSenders::new(vec![0, 1, 2, 3]);
+        let mut counts = [0; 4];
+        for key in keys {
+            counts[senders.pick(strategy.index(&key))] += 1;
+        }
+
+        let hot = senders.pick(strategy.index(&42));
+        let (replica, ratio) = hot_replica(&counts, 2.0).unwrap();
+        assert_eq!(replica, hot);
+        assert!(ratio > 3.0, "{ratio}");
+    }
+
+    #[test]
+    fn balanced_keys() {
+        assert_eq!(hot_replica(&[260, 240, 250, 250], 2.0), None);
+        // too few items to tell
+        assert_eq!(hot_replica(&[10, 0, 0, 0], 2.0), None);
     }
 }
@@ -0,0 +1,184 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use futures::Future;
+use quick_cache::sync::Cache;
+use quick_cache::UnitWeighter;
+
+use crate::block::GroupHasherBuilder;
+use crate::operator::map_async::MapAsync;
+use crate::operator::{Data, DataKey, Operator};
+use crate::Stream;
+
+/// The maximum number of lookups evaluated concurrently by each replica.
+const CONCURRENCY: usize = 4;
+
+/// The cache of the lookups of a replica.
+///
+/// Cloning it creates a new empty cache with the same capacity, so each replica of the operator
+/// gets its own cache.
+struct ReplicaCache<K, V> {
+    capacity: usize,
+    cache: Arc<Cache<K, V, UnitWeighter, GroupHasherBuilder>>,
+}
+
+impl<K: DataKey + Sync, V: Clone> ReplicaCache<K, V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            cache: Arc::new(Cache::with(
+                capacity,
+                capacity as u64,
+                UnitWeighter,
+                Default::default(),
+                Default::default(),
+            )),
+        }
+    }
+}
+
+impl<K: DataKey + Sync, V: Clone> Clone for ReplicaCache<K, V> {
+    fn clone(&self) -> Self {
+        Self::new(self.capacity)
+    }
+}
+
+impl<Op> Stream<Op>
+where
+    Op: Operator + 'static,
+{
+    /// Enrich each element of the stream with the value obtained by evaluating an asynchronous
+    /// lookup of its key, extracted with `key`.
+    ///
+    /// Each replica keeps the values of at most `cache_size` keys in a cache, and `lookup` is
+    /// evaluated only on a miss, so a key repeated by the elements of a replica is looked up
+    /// once as long as its value stays in the cache. The elements whose key is already being
+    /// looked up wait for the same lookup instead of starting a new one. At most 4 lookups are
+    /// in flight at the same time in each replica, and the elements keep their order.
+    ///
+    /// Unlike [`Stream::map_async_memo_by`], whose cache is shared by the replicas of a
+    /// process, the cache is local to each replica and holds only the results of the lookups.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # tokio::runtime::Runtime::new()
+    /// #    .unwrap()
+    /// #    .block_on(base());
+    /// # async fn base() {
+    /// #    let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(vec![(1, "a"), (2, "b"), (1, "c")].into_iter());
+    /// let res = s
+    ///     .enrich_async(|(user, _)| *user, 100, |user| async move { format!("user{user}") })
+    ///     .collect_vec();
+    /// env.execute().await;
+    /// assert_eq!(
+    ///     res.get().unwrap(),
+    ///     vec![
+    ///         ((1, "a"), "user1".to_string()),
+    ///         ((2, "b"), "user2".to_string()),
+    ///         ((1, "c"), "user1".to_string()),
+    ///     ]
+    /// );
+    /// # }
+    /// ```
+    pub fn enrich_async<K, V, Fk, F, Fut>(
+        self,
+        key: Fk,
+        cache_size: usize,
+        lookup: F,
+    ) -> Stream<impl Operator<Out = (Op::Out, V)>>
+    where
+        Op::Out: Data,
+        K: DataKey + Sync,
+        V: Data + Sync,
+        Fk: Fn(&Op::Out) -> K + Send + Sync + Clone + 'static,
+        F: Fn(K) -> Fut + Send + Sync + Clone + 'static,
+        Fut: Future<Output = V> + Send + 'static,
+    {
+        assert!(cache_size > 0, "the cache size must be positive");
+        let cache = ReplicaCache::new(cache_size);
+        self.add_operator(|prev| {
+            MapAsync::new(
+                prev,
+                move |el| {
+                    let k = key(&el);
+                    let lookup = lookup.clone();
+                    let cache = cache.cache.clone();
+                    async move {
+                        // the lookup starts only on a miss, and the concurrent misses of the same
+                        // key wait for the first one
+                        let miss = async { Ok::<_, Infallible>(lookup(k.clone()).await) };
+                        let value = cache.get_or_insert_async(&k, miss).await.unwrap();
+                        (el, value)
+                    }
+                },
+                CONCURRENCY,
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use itertools::Itertools;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn enrich_async_looks_up_unique_keys() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let lookups2 = lookups.clone();
+        let res = env
+            .stream_iter((0..1000u64).map(|n| n % 10))
+            .enrich_async(
+                |n| *n,
+                100,
+                move |n| {
+                    lookups2.fetch_add(1, Ordering::Relaxed);
+                    async move {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        n * 100
+                    }
+                },
+            )
+            .collect_vec();
+        env.execute().await;
+
+        let expected = (0..1000).map(|n| (n % 10, n % 10 * 100)).collect_vec();
+        assert_eq!(res.get().unwrap(), expected);
+        // the repeated keys hit the cache, or wait for the lookup in flight
+        assert_eq!(lookups.load(Ordering::Relaxed), 10);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn enrich_async_evicts() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let lookups2 = lookups.clone();
+        let res = env
+            .stream_iter((0..1000u64).map(|n| n % 100))
+            .enrich_async(
+                |n| *n,
+                10,
+                move |n| {
+                    lookups2.fetch_add(1, Ordering::Relaxed);
+                    async move { n + 1 }
+                },
+            )
+            .collect_vec();
+        env.execute().await;
+
+        let expected = (0..1000).map(|n| (n % 100, n % 100 + 1)).collect_vec();
+        assert_eq!(res.get().unwrap(), expected);
+        // the cache is too small for the keys, which are looked up again after an eviction
+        assert!(lookups.load(Ordering::Relaxed) > 100);
+    }
+}
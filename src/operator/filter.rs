@@ -1,7 +1,8 @@
 use std::fmt::Display;
 
 use crate::block::{BlockStructure, OperatorStructure};
-use crate::operator::{Operator, StreamElement};
+use crate::operator::stuck::StuckWatch;
+use crate::operator::{fmt_stage, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 
 #[derive(Clone)]
@@ -12,6 +13,7 @@ where
 {
     prev: Op,
     predicate: Predicate,
+    stuck: StuckWatch,
 }
 
 impl<Op, Predicate> Display for Filter<Op, Predicate>
@@ -20,12 +22,7 @@ where
     Op: Operator,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> Filter<{}>",
-            self.prev,
-            std::any::type_name::<Op::Out>()
-        )
+        fmt_stage::<Op::Out, Op::Out>(f, &self.prev, "Filter")
     }
 }
 
@@ -35,7 +32,11 @@ where
     Op: Operator,
 {
     pub(super) fn new(prev: Op, predicate: Predicate) -> Self {
-        Self { prev, predicate }
+        Self {
+            prev,
+            predicate,
+            stuck: Default::default(),
+        }
     }
 }
 
@@ -48,6 +49,7 @@ where
 
     fn setup(&mut self, metadata: &mut ExecutionMetadata) {
         self.prev.setup(metadata);
+        self.stuck.setup(metadata, "Filter");
     }
 
     #[inline]
@@ -55,7 +57,7 @@ where
         loop {
             match self.prev.next() {
                 StreamElement::Item(ref item) | StreamElement::Timestamped(ref item, _)
-                    if !(self.predicate)(item) => {}
+                    if !self.stuck.run(|| (self.predicate)(item)) => {}
                 element => return element,
             }
         }
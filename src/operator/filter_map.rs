@@ -2,7 +2,8 @@ use std::fmt::Display;
 use std::marker::PhantomData;
 
 use crate::block::{BlockStructure, OperatorStructure};
-use crate::operator::{Data, Operator};
+use crate::operator::stuck::StuckWatch;
+use crate::operator::{fmt_stage, Data, Operator};
 
 use crate::ExecutionMetadata;
 
@@ -16,6 +17,7 @@ where
 {
     prev: PreviousOperator,
     predicate: Predicate,
+    stuck: StuckWatch,
     _out: PhantomData<Out>,
 }
 
@@ -25,12 +27,7 @@ where
     PreviousOperator: Operator + 'static,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> FilterMap<{}>",
-            self.prev,
-            std::any::type_name::<Out>()
-        )
+        fmt_stage::<PreviousOperator::Out, Out>(f, &self.prev, "FilterMap")
     }
 }
 
@@ -43,6 +40,7 @@ where
         Self {
             prev,
             predicate,
+            stuck: Default::default(),
             _out: Default::default(),
         }
     }
@@ -58,6 +56,7 @@ where
 
     fn setup(&mut self, metadata: &mut ExecutionMetadata) {
         self.prev.setup(metadata);
+        self.stuck.setup(metadata, "FilterMap");
     }
 
     #[inline]
@@ -65,12 +64,12 @@ where
         loop {
             match self.prev.next() {
                 StreamElement::Item(item) => {
-                    if let Some(el) = (self.predicate)(item) {
+                    if let Some(el) = self.stuck.run(|| (self.predicate)(item)) {
                         return StreamElement::Item(el);
                     }
                 }
                 StreamElement::Timestamped(item, ts) => {
-                    if let Some(el) = (self.predicate)(item) {
+                    if let Some(el) = self.stuck.run(|| (self.predicate)(item)) {
                         return StreamElement::Timestamped(el, ts);
                     }
                 }
@@ -1,5 +1,6 @@
 use crate::block::{BlockStructure, OperatorStructure};
-use crate::operator::{Operator, StreamElement, Timestamp};
+use crate::operator::stuck::StuckWatch;
+use crate::operator::{fmt_stage, Operator, StreamElement, Timestamp};
 use crate::scheduler::ExecutionMetadata;
 use crate::stream::KeyedItem;
 use core::iter::{IntoIterator, Iterator};
@@ -26,6 +27,7 @@ where
     frontiter: Option<<It as IntoIterator>::IntoIter>,
     #[cfg(feature = "timestamp")]
     timestamp: Option<Timestamp>,
+    stuck: StuckWatch,
 }
 
 impl<It, F, Op> Clone for FlatMap<It, F, Op>
@@ -43,6 +45,7 @@ where
             frontiter: None,
             #[cfg(feature = "timestamp")]
             timestamp: self.timestamp,
+            stuck: self.stuck.clone(),
         }
     }
 }
@@ -56,13 +59,7 @@ where
     F: Fn(Op::Out) -> It + Clone + Send,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> FlatMap<{} -> {}>",
-            self.prev,
-            std::any::type_name::<Op::Out>(),
-            std::any::type_name::<It::Item>()
-        )
+        fmt_stage::<Op::Out, It::Item>(f, &self.prev, "FlatMap")
     }
 }
 
@@ -81,6 +78,7 @@ where
             frontiter: None,
             #[cfg(feature = "timestamp")]
             timestamp: None,
+            stuck: Default::default(),
         }
     }
 }
@@ -97,6 +95,7 @@ where
 
     fn setup(&mut self, metadata: &mut ExecutionMetadata) {
         self.prev.setup(metadata);
+        self.stuck.setup(metadata, "FlatMap");
     }
 
     fn next(&mut self) -> StreamElement<Self::Out> {
@@ -116,17 +115,17 @@ where
             match self.prev.next() {
                 #[cfg(not(feature = "timestamp"))]
                 StreamElement::Item(inner) | StreamElement::Timestamped(inner, _) => {
-                    self.frontiter = Some((self.f)(inner).into_iter());
+                    self.frontiter = Some(self.stuck.run(|| (self.f)(inner)).into_iter());
                 }
 
                 #[cfg(feature = "timestamp")]
                 StreamElement::Item(inner) => {
-                    self.frontiter = Some((self.f)(inner).into_iter());
+                    self.frontiter = Some(self.stuck.run(|| (self.f)(inner)).into_iter());
                     self.timestamp = None;
                 }
                 #[cfg(feature = "timestamp")]
                 StreamElement::Timestamped(inner, ts) => {
-                    self.frontiter = Some((self.f)(inner).into_iter());
+                    self.frontiter = Some(self.stuck.run(|| (self.f)(inner)).into_iter());
                     self.timestamp = Some(ts);
                 }
                 StreamElement::Watermark(ts) => return StreamElement::Watermark(ts),
@@ -196,13 +195,7 @@ where
     F: Fn(Op::Out) -> It + Clone + Send,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> KeyedFlatMap<{} -> {}>",
-            self.prev,
-            std::any::type_name::<Op::Out>(),
-            std::any::type_name::<It::Item>()
-        )
+        fmt_stage::<Op::Out, (<Op::Out as KeyedItem>::Key, It::Item)>(f, &self.prev, "KeyedFlatMap")
     }
 }
 
@@ -290,9 +283,115 @@ where
     }
 }
 
+/// Like [`FlatMap`], but the function pushes the outputs of each element into a buffer that is
+/// reused for all the elements.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct FlatMapIterOwned<O, F, Op>
+where
+    Op: Operator,
+    O: Send,
+    F: Fn(Op::Out, &mut Vec<O>) + Clone + Send,
+{
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    f: F,
+    /// The outputs of the last element still to be returned, in reverse order.
+    #[derivative(Debug = "ignore")]
+    buffer: Vec<O>,
+    timestamp: Option<Timestamp>,
+}
+
+impl<O, F, Op> Clone for FlatMapIterOwned<O, F, Op>
+where
+    Op: Operator,
+    O: Send,
+    F: Fn(Op::Out, &mut Vec<O>) + Clone + Send,
+{
+    fn clone(&self) -> Self {
+        Self {
+            prev: self.prev.clone(),
+            f: self.f.clone(),
+            buffer: Vec::new(),
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+impl<O, F, Op> Display for FlatMapIterOwned<O, F, Op>
+where
+    Op: Operator,
+    O: Send,
+    F: Fn(Op::Out, &mut Vec<O>) + Clone + Send,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, O>(f, &self.prev, "FlatMapIterOwned")
+    }
+}
+
+impl<O, F, Op> FlatMapIterOwned<O, F, Op>
+where
+    Op: Operator,
+    O: Send,
+    F: Fn(Op::Out, &mut Vec<O>) + Clone + Send,
+{
+    pub(super) fn new(prev: Op, f: F) -> Self {
+        Self {
+            prev,
+            f,
+            buffer: Vec::new(),
+            timestamp: None,
+        }
+    }
+
+    fn expand(&mut self, item: Op::Out, timestamp: Option<Timestamp>) {
+        (self.f)(item, &mut self.buffer);
+        self.buffer.reverse();
+        self.timestamp = timestamp;
+    }
+}
+
+impl<O, F, Op> Operator for FlatMapIterOwned<O, F, Op>
+where
+    Op: Operator,
+    O: Send,
+    F: Fn(Op::Out, &mut Vec<O>) + Clone + Send,
+{
+    type Out = O;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        loop {
+            if let Some(item) = self.buffer.pop() {
+                return match self.timestamp {
+                    None => StreamElement::Item(item),
+                    Some(ts) => StreamElement::Timestamped(item, ts),
+                };
+            }
+            match self.prev.next() {
+                StreamElement::Item(item) => self.expand(item, None),
+                StreamElement::Timestamped(item, ts) => self.expand(item, Some(ts)),
+                StreamElement::Watermark(ts) => return StreamElement::Watermark(ts),
+                StreamElement::FlushBatch => return StreamElement::FlushBatch,
+                StreamElement::Terminate => return StreamElement::Terminate,
+                StreamElement::FlushAndRestart => return StreamElement::FlushAndRestart,
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<O, _>("FlatMapIterOwned"))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::operator::flat_map::FlatMap;
+    use crate::operator::flat_map::{FlatMap, FlatMapIterOwned};
     use crate::operator::{Operator, StreamElement};
     use crate::test::FakeOperator;
 
@@ -334,4 +433,27 @@ mod tests {
         assert_eq!(flat_map.next(), StreamElement::Watermark(4));
         assert_eq!(flat_map.next(), StreamElement::Terminate);
     }
+
+    #[test]
+    #[cfg(feature = "timestamp")]
+    fn test_flat_map_iter_owned() {
+        let mut fake_operator = FakeOperator::empty();
+        fake_operator.push(StreamElement::Item("a bb".to_string()));
+        fake_operator.push(StreamElement::Timestamped("".to_string(), 1));
+        fake_operator.push(StreamElement::Timestamped("ccc d".to_string(), 2));
+        fake_operator.push(StreamElement::Watermark(3));
+
+        let mut flat_map = FlatMapIterOwned::new(fake_operator, |line: String, out| {
+            out.extend(line.split(' ').filter(|w| !w.is_empty()).map(String::from))
+        });
+
+        let item = |w: &str| StreamElement::Item(w.to_string());
+        let timestamped = |w: &str, ts| StreamElement::Timestamped(w.to_string(), ts);
+        assert_eq!(flat_map.next(), item("a"));
+        assert_eq!(flat_map.next(), item("bb"));
+        assert_eq!(flat_map.next(), timestamped("ccc", 2));
+        assert_eq!(flat_map.next(), timestamped("d", 2));
+        assert_eq!(flat_map.next(), StreamElement::Watermark(3));
+        assert_eq!(flat_map.next(), StreamElement::Terminate);
+    }
 }
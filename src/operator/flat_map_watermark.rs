@@ -0,0 +1,248 @@
+use std::fmt::Display;
+
+use crate::block::{BlockStructure, OperatorStructure};
+use crate::network::Coord;
+use crate::operator::{fmt_stage, Operator, StreamElement, Timestamp};
+use crate::profiler::{get_profiler, DropReason, Profiler};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct FlatMapWatermark<It, F, Op>
+where
+    Op: Operator,
+    It: IntoIterator,
+    It::IntoIter: Send,
+    F: Fn(Op::Out, Timestamp) -> It + Clone + Send,
+{
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    f: F,
+    /// The derived elements of the last input not emitted yet.
+    #[derivative(Debug = "ignore")]
+    frontiter: Option<It::IntoIter>,
+    /// The last watermark forwarded: the derived elements not after it are late.
+    last_watermark: Option<Timestamp>,
+    coord: Option<Coord>,
+    /// The number of late elements dropped since the last report to the profiler.
+    dropped: usize,
+}
+
+impl<It, F, Op> Clone for FlatMapWatermark<It, F, Op>
+where
+    Op: Operator,
+    It: IntoIterator,
+    It::IntoIter: Send,
+    F: Fn(Op::Out, Timestamp) -> It + Clone + Send,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.prev.clone(), self.f.clone())
+    }
+}
+
+impl<It, F, Op> Display for FlatMapWatermark<It, F, Op>
+where
+    Op: Operator,
+    It: IntoIterator,
+    It::IntoIter: Send,
+    F: Fn(Op::Out, Timestamp) -> It + Clone + Send,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, It::Item>(f, &self.prev, "FlatMapWatermark")
+    }
+}
+
+impl<It, F, Op> FlatMapWatermark<It, F, Op>
+where
+    Op: Operator,
+    It: IntoIterator,
+    It::IntoIter: Send,
+    F: Fn(Op::Out, Timestamp) -> It + Clone + Send,
+{
+    fn new(prev: Op, f: F) -> Self {
+        Self {
+            prev,
+            f,
+            frontiter: None,
+            last_watermark: None,
+            coord: None,
+            dropped: 0,
+        }
+    }
+
+    /// Report the late elements dropped since the last report to the profiler.
+    fn report_dropped(&mut self) {
+        if self.dropped > 0 {
+            let dropped = std::mem::take(&mut self.dropped);
+            get_profiler().dropped(
+                self.coord.unwrap(),
+                "FlatMapWatermark",
+                DropReason::TooLate,
+                dropped,
+            );
+        }
+    }
+}
+
+impl<O, It, F, Op> Operator for FlatMapWatermark<It, F, Op>
+where
+    Op: Operator,
+    It: IntoIterator<Item = (O, Timestamp)>,
+    It::IntoIter: Send,
+    O: Send,
+    F: Fn(Op::Out, Timestamp) -> It + Clone + Send,
+{
+    type Out = O;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        self.coord = Some(metadata.coord);
+    }
+
+    #[inline]
+    fn next(&mut self) -> StreamElement<O> {
+        loop {
+            // all the derived elements of an input are emitted before reading the next element,
+            // so they precede the following watermark
+            if let Some(inner) = &mut self.frontiter {
+                match inner.next() {
+                    Some((_, ts)) if self.last_watermark.is_some_and(|w| ts <= w) => {
+                        log::trace!("Dropping derived element with timestamp {ts}, too late");
+                        self.dropped += 1;
+                    }
+                    Some((item, ts)) => return StreamElement::Timestamped(item, ts),
+                    None => self.frontiter = None,
+                }
+                continue;
+            }
+            match self.prev.next() {
+                StreamElement::Timestamped(item, ts) => {
+                    self.frontiter = Some((self.f)(item, ts).into_iter());
+                }
+                StreamElement::Item(_) => {
+                    panic!("FlatMapWatermark only supports timestamped streams")
+                }
+                StreamElement::Watermark(ts) => {
+                    self.last_watermark = Some(ts);
+                    return StreamElement::Watermark(ts);
+                }
+                StreamElement::FlushBatch => return StreamElement::FlushBatch,
+                StreamElement::FlushAndRestart => {
+                    // the timestamps restart with the next iteration
+                    self.last_watermark = None;
+                    self.report_dropped();
+                    return StreamElement::FlushAndRestart;
+                }
+                StreamElement::Terminate => {
+                    self.report_dropped();
+                    return StreamElement::Terminate;
+                }
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<O, _>("FlatMapWatermark"))
+    }
+}
+
+impl<I, Op> Stream<Op>
+where
+    I: Send + 'static,
+    Op: Operator<Out = I> + 'static,
+{
+    /// Apply a mapping operation to each element of the stream, producing zero or more elements
+    /// with their own timestamp.
+    ///
+    /// `f` receives each element with its timestamp, and returns the derived elements, each with
+    /// the timestamp it should have, like the sub-events of an event spanning an interval. Unlike
+    /// [`Stream::flat_map`], where all the elements inherit the timestamp of their input, the
+    /// event time operators that follow see each derived element at its own time.
+    ///
+    /// All the derived elements of an input are emitted before the following watermark, so the
+    /// watermarks never advance past an element not emitted yet. A derived element with a
+    /// timestamp not after the last watermark would be late for the following operators: it is
+    /// dropped, and counted in the profiler as `too late`. To avoid this, give each input the
+    /// timestamp of its first derived element, like the start of its interval.
+    ///
+    /// **Note**: the stream must be timestamped, see [`Stream::add_timestamps`].
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::window::EventTimeWindow;
+    /// # let mut env = StreamContext::new_local();
+    /// // (start, minutes) of the sessions, with the timestamps in minutes
+    /// let s = env.stream_iter(vec![(0, 3), (1, 2), (10, 2)].into_iter());
+    /// let res = s
+    ///     .add_timestamps(|&(start, _)| start, |_, &ts| Some(ts))
+    ///     // an element for each minute of each session
+    ///     .flat_map_with_watermark_awareness(|(start, minutes), _| {
+    ///         (start..start + minutes).map(|minute| ((), minute))
+    ///     })
+    ///     .window_all(EventTimeWindow::tumbling(5))
+    ///     .count()
+    ///     .drop_key()
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// // the sessions active in each interval of 5 minutes
+    /// assert_eq!(res.get().unwrap(), vec![5, 2]);
+    /// ```
+    pub fn flat_map_with_watermark_awareness<O, It, F>(self, f: F) -> Stream<impl Operator<Out = O>>
+    where
+        O: Send + 'static,
+        It: IntoIterator<Item = (O, Timestamp)> + 'static,
+        It::IntoIter: Send + 'static,
+        F: Fn(I, Timestamp) -> It + Clone + Send + 'static,
+    {
+        self.add_operator(|prev| FlatMapWatermark::new(prev, f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlatMapWatermark;
+    use crate::operator::StreamElement;
+    use crate::test::{run_operator, FakeOperator};
+
+    #[test]
+    fn flat_map_watermark() {
+        // (start, length) of the intervals, with a sub-event for each unit of time
+        let fake = FakeOperator::from_elements(vec![
+            StreamElement::Timestamped((0, 3), 0),
+            StreamElement::Watermark(1),
+            // the first unit of this interval is not after the watermark, so it is late
+            StreamElement::Timestamped((1, 3), 2),
+            StreamElement::Watermark(2),
+            StreamElement::Timestamped((5, 2), 5),
+            StreamElement::FlushAndRestart,
+            StreamElement::Terminate,
+        ]);
+        let op = FlatMapWatermark::new(fake, |(start, length), _| {
+            (start..start + length).map(|t| (t * 10, t))
+        });
+
+        assert_eq!(
+            run_operator(op),
+            vec![
+                StreamElement::Timestamped(0, 0),
+                StreamElement::Timestamped(10, 1),
+                StreamElement::Timestamped(20, 2),
+                StreamElement::Watermark(1),
+                StreamElement::Timestamped(20, 2),
+                StreamElement::Timestamped(30, 3),
+                StreamElement::Watermark(2),
+                StreamElement::Timestamped(50, 5),
+                StreamElement::Timestamped(60, 6),
+                StreamElement::FlushAndRestart,
+                StreamElement::Terminate,
+            ]
+        );
+    }
+}
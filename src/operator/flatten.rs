@@ -2,7 +2,7 @@ use core::iter::{IntoIterator, Iterator};
 use std::fmt::Display;
 
 use crate::block::{BlockStructure, OperatorStructure};
-use crate::operator::{Operator, StreamElement, Timestamp};
+use crate::operator::{fmt_stage, Operator, StreamElement, Timestamp};
 use crate::scheduler::ExecutionMetadata;
 use crate::stream::KeyedItem;
 
@@ -54,13 +54,7 @@ where
     <Op::Out as IntoIterator>::IntoIter: Send,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> Flatten<{} -> {}>",
-            self.prev,
-            std::any::type_name::<Op::Out>(),
-            std::any::type_name::<<Op::Out as IntoIterator>::Item>()
-        )
+        fmt_stage::<Op::Out, <Op::Out as IntoIterator>::Item>(f, &self.prev, "Flatten")
     }
 }
 
@@ -187,12 +181,10 @@ where
     KeyedFlattenIter<Op>: Send,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
+        fmt_stage::<Op::Out, (<Op::Out as KeyedItem>::Key, KeyedFlattenIterItem<Op>)>(
             f,
-            "{} -> KeyedFlatten<{} -> {}>",
-            self.prev,
-            std::any::type_name::<Op::Out>(),
-            std::any::type_name::<(<Op::Out as KeyedItem>::Key, KeyedFlattenIterItem<Op>)>()
+            &self.prev,
+            "KeyedFlatten",
         )
     }
 }
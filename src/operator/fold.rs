@@ -1,7 +1,7 @@
 use std::fmt::Display;
 
 use crate::block::{BlockStructure, OperatorStructure};
-use crate::operator::{Operator, StreamElement, Timestamp};
+use crate::operator::{fmt_stage, Operator, StreamElement, Timestamp};
 use crate::scheduler::ExecutionMetadata;
 
 #[derive(Clone, Derivative)]
@@ -28,13 +28,7 @@ where
     Op: Operator,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> Fold<{} -> {}>",
-            self.prev,
-            std::any::type_name::<Op::Out>(),
-            std::any::type_name::<O>()
-        )
+        fmt_stage::<Op::Out, O>(f, &self.prev, "Fold")
     }
 }
 
@@ -0,0 +1,259 @@
+use std::fmt::Display;
+use std::marker::PhantomData;
+
+use crate::block::{BlockStructure, DataType, OperatorStructure};
+use crate::operator::{Data, Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+
+/// A chain of stateless stages that are applied to each item by a single operator.
+///
+/// The stages are composed into a single function, so an item goes through all of them with a
+/// single dispatch instead of being passed from one operator of the chain to the next. Build it
+/// with [`Stream::fused`](crate::Stream::fused).
+pub struct Fused<I, O, F>
+where
+    F: Fn(I) -> Option<O> + Send + Clone,
+{
+    f: F,
+    stages: Vec<(&'static str, DataType)>,
+    _types: PhantomData<fn(I) -> O>,
+}
+
+impl<I> Fused<I, I, fn(I) -> Option<I>> {
+    pub(crate) fn new() -> Self {
+        Self {
+            f: Some,
+            stages: Default::default(),
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<I, O, F> Fused<I, O, F>
+where
+    F: Fn(I) -> Option<O> + Send + Clone,
+{
+    fn then<O2, G>(mut self, stage: &'static str, g: G) -> Fused<I, O2, G>
+    where
+        G: Fn(I) -> Option<O2> + Send + Clone,
+    {
+        self.stages.push((stage, DataType::of::<O2>()));
+        Fused {
+            f: g,
+            stages: self.stages,
+            _types: PhantomData,
+        }
+    }
+
+    /// Map each item into a new item, like [`Stream::map`](crate::Stream::map).
+    pub fn map<O2, G>(self, g: G) -> Fused<I, O2, impl Fn(I) -> Option<O2> + Send + Clone>
+    where
+        G: Fn(O) -> O2 + Send + Clone,
+    {
+        let f = self.f.clone();
+        self.then("Map", move |x| f(x).map(&g))
+    }
+
+    /// Keep only the items that satisfy the predicate, like [`Stream::filter`](crate::Stream::filter).
+    pub fn filter<P>(self, predicate: P) -> Fused<I, O, impl Fn(I) -> Option<O> + Send + Clone>
+    where
+        P: Fn(&O) -> bool + Send + Clone,
+    {
+        let f = self.f.clone();
+        self.then("Filter", move |x| f(x).filter(&predicate))
+    }
+
+    /// Map each item into an optional item, dropping the `None`s, like
+    /// [`Stream::filter_map`](crate::Stream::filter_map).
+    pub fn filter_map<O2, G>(self, g: G) -> Fused<I, O2, impl Fn(I) -> Option<O2> + Send + Clone>
+    where
+        G: Fn(O) -> Option<O2> + Send + Clone,
+    {
+        let f = self.f.clone();
+        self.then("FilterMap", move |x| f(x).and_then(&g))
+    }
+
+    /// Call a function on a reference to each item, like
+    /// [`Stream::inspect`](crate::Stream::inspect).
+    pub fn inspect<G>(self, g: G) -> Fused<I, O, impl Fn(I) -> Option<O> + Send + Clone>
+    where
+        G: Fn(&O) + Send + Clone,
+    {
+        let f = self.f.clone();
+        self.then("Inspect", move |x| f(x).inspect(&g))
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct FusedOperator<O, F, Op>
+where
+    F: Fn(Op::Out) -> Option<O> + Send + Clone,
+    Op: Operator,
+{
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    f: F,
+    stages: Vec<(&'static str, DataType)>,
+    /// Show each stage as a separate operator in the structure.
+    expand: bool,
+    _out: PhantomData<O>,
+}
+
+impl<O, F, Op: Clone> Clone for FusedOperator<O, F, Op>
+where
+    F: Fn(Op::Out) -> Option<O> + Send + Clone,
+    Op: Operator,
+{
+    fn clone(&self) -> Self {
+        Self {
+            prev: self.prev.clone(),
+            f: self.f.clone(),
+            stages: self.stages.clone(),
+            expand: self.expand,
+            _out: PhantomData,
+        }
+    }
+}
+
+impl<O, F, Op> Display for FusedOperator<O, F, Op>
+where
+    F: Fn(Op::Out) -> Option<O> + Send + Clone,
+    Op: Operator,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stages = self
+            .stages
+            .iter()
+            .map(|(stage, _)| *stage)
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(
+            f,
+            "{} -> Fused[{}]<{} -> {}>",
+            self.prev,
+            stages,
+            std::any::type_name::<Op::Out>(),
+            std::any::type_name::<O>()
+        )
+    }
+}
+
+impl<O, F, Op> FusedOperator<O, F, Op>
+where
+    F: Fn(Op::Out) -> Option<O> + Send + Clone,
+    Op: Operator,
+{
+    pub(super) fn new(prev: Op, fused: Fused<Op::Out, O, F>, expand: bool) -> Self {
+        Self {
+            prev,
+            f: fused.f,
+            stages: fused.stages,
+            expand,
+            _out: PhantomData,
+        }
+    }
+}
+
+impl<O: Data, F, Op> Operator for FusedOperator<O, F, Op>
+where
+    F: Fn(Op::Out) -> Option<O> + Send + Clone,
+    Op: Operator,
+{
+    type Out = O;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+    }
+
+    #[inline]
+    fn next(&mut self) -> StreamElement<O> {
+        loop {
+            match self.prev.next() {
+                StreamElement::Item(item) => {
+                    if let Some(el) = (self.f)(item) {
+                        return StreamElement::Item(el);
+                    }
+                }
+                StreamElement::Timestamped(item, ts) => {
+                    if let Some(el) = (self.f)(item) {
+                        return StreamElement::Timestamped(el, ts);
+                    }
+                }
+                StreamElement::Watermark(w) => return StreamElement::Watermark(w),
+                StreamElement::Terminate => return StreamElement::Terminate,
+                StreamElement::FlushAndRestart => return StreamElement::FlushAndRestart,
+                StreamElement::FlushBatch => return StreamElement::FlushBatch,
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut structure = self.prev.structure();
+        if self.expand {
+            for (stage, out_type) in &self.stages {
+                let mut operator = OperatorStructure::new::<O, _>(*stage);
+                operator.subtitle = "(fused)".into();
+                operator.out_type = out_type.clone();
+                structure = structure.add_operator(operator);
+            }
+        } else {
+            let mut operator = OperatorStructure::new::<O, _>("Fused");
+            operator.subtitle = self
+                .stages
+                .iter()
+                .map(|(stage, _)| *stage)
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            structure = structure.add_operator(operator);
+        }
+        structure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::operator::fused::{Fused, FusedOperator};
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::FakeOperator;
+
+    #[test]
+    fn fused_stages() {
+        let mut fake_operator = FakeOperator::new(0..10u32);
+        fake_operator.push(StreamElement::Watermark(100));
+
+        let fused = Fused::new()
+            .map(|x: u32| x * 3)
+            .filter(|x| x % 2 == 0)
+            .filter_map(|x| x.checked_sub(6))
+            .map(|x| x.to_string());
+        let mut fused = FusedOperator::new(fake_operator, fused, false);
+
+        for i in [0, 6, 12, 18] {
+            assert_eq!(fused.next(), StreamElement::Item(i.to_string()));
+        }
+        assert_eq!(fused.next(), StreamElement::Watermark(100));
+        assert_eq!(fused.next(), StreamElement::Terminate);
+
+        let structure = fused.structure();
+        let operator = structure.operators.last().unwrap();
+        assert_eq!(operator.title, "Fused");
+        assert_eq!(operator.subtitle, "Map -> Filter -> FilterMap -> Map");
+    }
+
+    #[test]
+    fn fused_stages_expanded() {
+        let fake_operator = FakeOperator::new(0..10u32);
+        let fused = Fused::new().map(|x: u32| x as u64).filter(|x| *x > 3);
+        let fused = FusedOperator::new(fake_operator, fused, true);
+
+        let structure = fused.structure();
+        let titles = structure
+            .operators
+            .iter()
+            .skip(1)
+            .map(|op| op.title.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, ["Map", "Filter"]);
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::{BlockStructure, NextStrategy, OperatorStructure};
+use crate::operator::end::End;
+use crate::operator::{fmt_stage, Data, ExchangeData, Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// An element of a stream with heartbeats: either a real element or a synthetic marker.
+///
+/// See [`Stream::with_heartbeat`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Heartbeat<T, M> {
+    /// An element of the original stream.
+    Item(T),
+    /// A marker emitted because no element passed for a while.
+    Marker(M),
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct HeartbeatOperator<M, F, Op>
+where
+    F: FnMut() -> M + Clone + Send,
+    Op: Operator,
+{
+    prev: Op,
+    interval: Duration,
+    #[derivative(Debug = "ignore")]
+    make_marker: F,
+    /// When the last element, real or marker, was emitted.
+    last: Option<Instant>,
+    /// Whether a `FlushBatch` has to be forwarded after the marker just emitted.
+    pending_flush: bool,
+}
+
+impl<M, F, Op> Clone for HeartbeatOperator<M, F, Op>
+where
+    F: FnMut() -> M + Clone + Send,
+    Op: Operator,
+{
+    fn clone(&self) -> Self {
+        Self {
+            prev: self.prev.clone(),
+            interval: self.interval,
+            make_marker: self.make_marker.clone(),
+            last: self.last,
+            pending_flush: self.pending_flush,
+        }
+    }
+}
+
+impl<M, F, Op> Display for HeartbeatOperator<M, F, Op>
+where
+    F: FnMut() -> M + Clone + Send,
+    Op: Operator,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, Heartbeat<Op::Out, M>>(f, &self.prev, "Heartbeat")
+    }
+}
+
+impl<M, F, Op> Operator for HeartbeatOperator<M, F, Op>
+where
+    M: Send,
+    F: FnMut() -> M + Clone + Send,
+    Op: Operator,
+{
+    type Out = Heartbeat<Op::Out, M>;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        self.last = Some(Instant::now());
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        if self.pending_flush {
+            self.pending_flush = false;
+            return StreamElement::FlushBatch;
+        }
+        let el = self.prev.next();
+        match el {
+            StreamElement::Item(_) | StreamElement::Timestamped(_, _) => {
+                self.last = Some(Instant::now());
+            }
+            StreamElement::FlushBatch => {
+                let last = self.last.get_or_insert_with(Instant::now);
+                if last.elapsed() >= self.interval {
+                    *last = Instant::now();
+                    self.pending_flush = true;
+                    return StreamElement::Item(Heartbeat::Marker((self.make_marker)()));
+                }
+            }
+            _ => {}
+        }
+        el.map(Heartbeat::Item)
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let operator = OperatorStructure::new::<Self::Out, _>("Heartbeat");
+        self.prev.structure().add_operator(operator)
+    }
+}
+
+impl<Op> Stream<Op>
+where
+    Op: Operator + 'static,
+    Op::Out: ExchangeData,
+{
+    /// Emit a marker into the stream each time no element passed for `interval`.
+    ///
+    /// The elements of the stream are wrapped in [`Heartbeat::Item`], while the markers built by
+    /// `make_marker` are emitted as [`Heartbeat::Marker`]. The downstream operators can use the
+    /// markers to tell a pipeline that is alive but idle from a stalled one. As long as the
+    /// elements keep flowing more often than `interval` no marker is emitted.
+    ///
+    /// The markers are emitted by each replica independently, and they have no timestamp.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::Heartbeat;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..5);
+    /// let res = s
+    ///     .with_heartbeat(Duration::from_secs(1), || "alive")
+    ///     .filter_map(|el| match el {
+    ///         Heartbeat::Item(n) => Some(n),
+    ///         Heartbeat::Marker(_) => None,
+    ///     })
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![0, 1, 2, 3, 4]);
+    /// ```
+    pub fn with_heartbeat<M, F>(
+        self,
+        interval: Duration,
+        make_marker: F,
+    ) -> Stream<impl Operator<Out = Heartbeat<Op::Out, M>>>
+    where
+        M: Data,
+        F: FnMut() -> M + Clone + Send + 'static,
+    {
+        // keep the same parallelism of the previous block
+        let scheduler_requirements = self.block.scheduling.clone();
+        let mut new_stream = self.split_block(End::new, NextStrategy::only_one());
+        new_stream.block.scheduling = scheduler_requirements;
+        // wake up the new block often enough to notice when `interval` has passed
+        new_stream.block.tick = Some(interval / 4);
+        new_stream.add_operator(|prev| HeartbeatOperator {
+            prev,
+            interval,
+            make_marker,
+            last: None,
+            pending_flush: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::ChannelSource;
+    use crate::operator::Heartbeat;
+    use crate::Replication;
+
+    #[test]
+    fn heartbeat_on_idle_source() {
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        let (tx, source) = ChannelSource::new(64, Replication::One);
+        let res = env
+            .stream(source)
+            .with_heartbeat(Duration::from_millis(100), || ())
+            .collect_vec();
+
+        let producer = std::thread::spawn(move || {
+            // silent for a while: only markers
+            std::thread::sleep(Duration::from_millis(550));
+            // then the data flows faster than the interval: no markers
+            for i in 0..20 {
+                tx.send(i).unwrap();
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        });
+        env.execute_blocking();
+        producer.join().unwrap();
+
+        let res = res.get().unwrap();
+        let first_item = res
+            .iter()
+            .position(|el| matches!(el, Heartbeat::Item(_)))
+            .unwrap();
+        let markers = res[..first_item].len();
+        assert!((3..=6).contains(&markers), "{markers} markers while idle");
+        let items = res[first_item..]
+            .iter()
+            .take_while(|el| matches!(el, Heartbeat::Item(_)))
+            .count();
+        assert_eq!(items, 20);
+    }
+}
@@ -1,7 +1,8 @@
 use std::fmt::Display;
 
 use crate::block::{BlockStructure, OperatorStructure};
-use crate::operator::{Operator, StreamElement};
+use crate::operator::stuck::StuckWatch;
+use crate::operator::{fmt_stage, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 
 #[derive(Clone, Derivative)]
@@ -14,6 +15,7 @@ where
     prev: Op,
     #[derivative(Debug = "ignore")]
     f: F,
+    stuck: StuckWatch,
 }
 
 impl<F, Op> Inspect<F, Op>
@@ -22,7 +24,11 @@ where
     Op: Operator,
 {
     pub fn new(prev: Op, f: F) -> Self {
-        Self { prev, f }
+        Self {
+            prev,
+            f,
+            stuck: Default::default(),
+        }
     }
 }
 
@@ -32,7 +38,7 @@ where
     Op: Operator,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} -> Inspect", self.prev)
+        fmt_stage::<Op::Out, Op::Out>(f, &self.prev, "Inspect")
     }
 }
 
@@ -45,6 +51,7 @@ where
 
     fn setup(&mut self, metadata: &mut ExecutionMetadata) {
         self.prev.setup(metadata);
+        self.stuck.setup(metadata, "Inspect");
     }
 
     #[inline]
@@ -52,7 +59,7 @@ where
         let el = self.prev.next();
         match &el {
             StreamElement::Item(t) | StreamElement::Timestamped(t, _) => {
-                (self.f)(t);
+                self.stuck.run(|| (self.f)(t));
             }
             _ => {}
         }
@@ -5,7 +5,8 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use crate::block::{
-    BlockStructure, Connection, NextStrategy, OperatorReceiver, OperatorStructure, Replication,
+    BlockStructure, Connection, ConnectionStrategy, NextStrategy, OperatorReceiver,
+    OperatorStructure, Replication,
 };
 use crate::channel::RecvError::Disconnected;
 use crate::channel::SelectResult;
@@ -277,6 +278,7 @@ impl<Out: ExchangeData, State: ExchangeData + Sync> Operator for Iterate<Out, St
 
     fn structure(&self) -> BlockStructure {
         let mut operator = OperatorStructure::new::<Out, _>("Iterate");
+        operator.next_strategy = Some(ConnectionStrategy::OnlyOne);
         operator
             .receivers
             .push(OperatorReceiver::new::<StateFeedback<State>>(
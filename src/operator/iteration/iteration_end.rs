@@ -1,4 +1,6 @@
-use crate::block::{BlockStructure, Connection, NextStrategy, OperatorStructure};
+use crate::block::{
+    BlockStructure, Connection, ConnectionStrategy, NextStrategy, OperatorStructure,
+};
 use crate::network::{Coord, NetworkMessage, NetworkSender, ReceiverEndpoint};
 use crate::operator::{ExchangeData, Operator, StreamElement};
 use crate::scheduler::{BlockId, ExecutionMetadata};
@@ -123,6 +125,7 @@ where
 
     fn structure(&self) -> BlockStructure {
         let mut operator = OperatorStructure::new::<DeltaUpdate, _>("IterationEnd");
+        operator.next_strategy = Some(ConnectionStrategy::OnlyOne);
         operator.connections.push(Connection::new::<DeltaUpdate, _>(
             self.leader_block_id,
             &NextStrategy::only_one(),
@@ -2,7 +2,9 @@ use std::fmt::Display;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use crate::block::{BlockStructure, Connection, NextStrategy, OperatorStructure, Replication};
+use crate::block::{
+    BlockStructure, Connection, ConnectionStrategy, NextStrategy, OperatorStructure, Replication,
+};
 use crate::network::{Coord, NetworkMessage, NetworkSender};
 use crate::operator::iteration::{IterationResult, StateFeedback};
 use crate::operator::source::Source;
@@ -235,17 +237,26 @@ where
 
     fn structure(&self) -> BlockStructure {
         let mut operator = OperatorStructure::new::<State, _>("IterationLeader");
-        operator
-            .connections
-            .push(Connection::new::<StateFeedback<State>, _>(
-                self.feedback_senders[0].receiver_endpoint.coord.block_id,
-                &NextStrategy::only_one(),
-            ));
-        self.state_update_receiver
-            .as_ref()
-            .unwrap()
-            .structure()
-            .add_operator(operator)
+        operator.next_strategy = Some(ConnectionStrategy::OnlyOne);
+        // before the setup the senders and the receiver are not there yet
+        if let Some(sender) = self.feedback_senders.first() {
+            operator
+                .connections
+                .push(Connection::new::<StateFeedback<State>, _>(
+                    sender.receiver_endpoint.coord.block_id,
+                    &NextStrategy::only_one(),
+                ));
+        }
+        let start = match &self.state_update_receiver {
+            Some(receiver) => receiver.structure(),
+            None => {
+                let feedback_block_id = self.feedback_block_id.load(Ordering::Acquire) as BlockId;
+                let receiver: SimpleStartOperator<DeltaUpdate> =
+                    Start::single(feedback_block_id, None);
+                receiver.structure()
+            }
+        };
+        start.add_operator(operator)
     }
 }
 
@@ -73,8 +73,10 @@ where
     {
         let keyer1 = prev.keyer1;
         let keyer2 = prev.keyer2;
-        let next_strategy1 = NextStrategy::group_by(keyer1.clone());
-        let next_strategy2 = NextStrategy::group_by(keyer2.clone());
+        // both sides must assign the same key to the same replica
+        let hasher = prev.lhs.block.group_by_hasher.clone();
+        let next_strategy1 = NextStrategy::group_by(keyer1.clone(), hasher.clone());
+        let next_strategy2 = NextStrategy::group_by(keyer2.clone(), hasher);
         let inner =
             prev.lhs
                 .binary_connection(prev.rhs, Start::multiple, next_strategy1, next_strategy2);
@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use crate::block::{BlockStructure, OperatorStructure};
 use crate::operator::DataKey;
-use crate::operator::{Operator, StreamElement};
+use crate::operator::{fmt_stage, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 
 #[derive(Derivative)]
@@ -36,12 +36,7 @@ where
     Op: Operator,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> KeyBy<{}>",
-            self.prev,
-            std::any::type_name::<Key>(),
-        )
+        fmt_stage::<Op::Out, (Key, Op::Out)>(f, &self.prev, "KeyBy")
     }
 }
 
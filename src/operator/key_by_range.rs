@@ -0,0 +1,94 @@
+use crate::operator::{ExchangeData, Operator};
+use crate::{KeyedStream, Stream};
+
+/// The index of the bucket of `value`: the number of `boundaries` not greater than it.
+fn bucket(boundaries: &[f64], value: f64) -> usize {
+    boundaries.partition_point(|&b| b <= value)
+}
+
+impl<I, Op> Stream<Op>
+where
+    I: ExchangeData + Copy + Into<f64>,
+    Op: Operator<Out = I> + 'static,
+{
+    /// Partition the numeric elements of the stream into the buckets delimited by `boundaries`,
+    /// keying each element with the index of its bucket.
+    ///
+    /// With `n` increasing boundaries there are `n + 1` buckets: bucket `0` holds the values
+    /// below `boundaries[0]` (underflow), bucket `i` the values in
+    /// `boundaries[i - 1]..boundaries[i]`, and bucket `n` the values greater than or equal to the
+    /// last boundary (overflow). `NaN` falls in bucket `0`.
+    ///
+    /// Unlike [`Stream::key_by`] the elements are partitioned like [`Stream::group_by`], so the
+    /// keyed operators that follow (e.g. folds and windows) aggregate each bucket, for example to
+    /// build a histogram.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(vec![0.5, 3.0, 1.5, 1.0, -2.0].into_iter());
+    /// let res = s
+    ///     .key_by_range(vec![1.0, 2.0])
+    ///     .fold(0, |count, _| *count += 1)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, vec![(0, 2), (1, 2), (2, 1)]);
+    /// ```
+    pub fn key_by_range(
+        self,
+        boundaries: Vec<f64>,
+    ) -> KeyedStream<impl Operator<Out = (usize, I)>> {
+        assert!(
+            boundaries.windows(2).all(|w| w[0] < w[1]),
+            "the boundaries of the buckets must be strictly increasing: {boundaries:?}"
+        );
+        self.group_by(move |&value| bucket(&boundaries, value.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+
+    use super::bucket;
+
+    #[test]
+    fn bucket_bounds() {
+        let boundaries = [1.0, 2.0];
+        assert_eq!(bucket(&boundaries, f64::NEG_INFINITY), 0);
+        assert_eq!(bucket(&boundaries, 1.0), 1);
+        assert_eq!(bucket(&boundaries, 2.0), 2);
+        assert_eq!(bucket(&boundaries, f64::INFINITY), 2);
+        assert_eq!(bucket(&boundaries, f64::NAN), 0);
+        assert_eq!(bucket(&[], 42.0), 0);
+    }
+
+    #[test]
+    fn key_by_range_buckets() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env
+            .stream_iter(vec![0.5, 1.5, 2.5])
+            .key_by_range(vec![1.0, 2.0])
+            .collect_vec();
+        env.execute_blocking();
+
+        let mut res = res.get().unwrap();
+        res.sort_by_key(|(bucket, _)| *bucket);
+        assert_eq!(res, vec![(0, 0.5), (1, 1.5), (2, 2.5)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "strictly increasing")]
+    fn key_by_range_unsorted_boundaries() {
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        let _ = env.stream_iter(vec![0.5f64]).key_by_range(vec![2.0, 1.0]);
+    }
+}
@@ -5,7 +5,7 @@ use std::fmt::Display;
 
 use crate::block::{BlockStructure, OperatorStructure};
 
-use crate::operator::{Operator, StreamElement, Timestamp};
+use crate::operator::{fmt_stage, Operator, StreamElement, Timestamp};
 use crate::scheduler::ExecutionMetadata;
 use crate::stream::KeyedItem;
 
@@ -54,13 +54,7 @@ where
     Op::Out: KeyedItem,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> KeyedFold<{} -> {}>",
-            self.prev,
-            std::any::type_name::<Op::Out>(),
-            std::any::type_name::<(<Op::Out as KeyedItem>::Key, O)>()
-        )
+        fmt_stage::<Op::Out, (<Op::Out as KeyedItem>::Key, O)>(f, &self.prev, "KeyedFold")
     }
 }
 
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::block::{BlockStructure, GroupHasherBuilder, OperatorStructure};
+use crate::operator::{Data, ExchangeData, ExchangeDataKey, Operator, StreamElement};
+use crate::savepoint::{key_group, key_group_replica, replica_key_groups, Savepoint};
+use crate::scheduler::ExecutionMetadata;
+use crate::{KeyedStream, Stream};
+
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+struct KeyedStatefulMap<K, S, O, Fk, F, Op>
+where
+    Op: Operator,
+{
+    prev: Op,
+    id: String,
+    #[derivative(Debug = "ignore")]
+    init: S,
+    /// The state of each key seen by this replica.
+    #[derivative(Debug = "ignore")]
+    states: HashMap<K, S, GroupHasherBuilder>,
+    #[derivative(Debug = "ignore")]
+    keyer: Fk,
+    #[derivative(Debug = "ignore")]
+    f: F,
+    #[derivative(Debug = "ignore")]
+    savepoint: Arc<Savepoint>,
+    _out: PhantomData<O>,
+}
+
+impl<K, S, O, Fk, F, Op> Display for KeyedStatefulMap<K, S, O, Fk, F, Op>
+where
+    Op: Operator,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} -> KeyedStatefulMap[{}]<{} -> {}>",
+            self.prev,
+            self.id,
+            std::any::type_name::<Op::Out>(),
+            std::any::type_name::<(K, O)>()
+        )
+    }
+}
+
+impl<K, S, O, Fk, F, Op> KeyedStatefulMap<K, S, O, Fk, F, Op>
+where
+    K: ExchangeDataKey,
+    S: Serialize + DeserializeOwned + Clone,
+    Fk: Fn(&Op::Out) -> K,
+    F: Fn(&mut S, Op::Out) -> O,
+    Op: Operator,
+{
+    fn new(prev: Op, id: String, keyer: Fk, init: S, f: F) -> Self {
+        Self {
+            prev,
+            id,
+            init,
+            states: Default::default(),
+            keyer,
+            f,
+            savepoint: Default::default(),
+            _out: PhantomData,
+        }
+    }
+
+    fn apply(&mut self, item: Op::Out) -> (K, O) {
+        let key = (self.keyer)(&item);
+        let state = self
+            .states
+            .entry(key.clone())
+            .or_insert_with(|| self.init.clone());
+        let out = (self.f)(state, item);
+        (key, out)
+    }
+
+    /// Store the states in the savepoint, grouped by key group.
+    fn store(&self) {
+        let mut groups: HashMap<usize, Vec<(&K, &S)>> = HashMap::new();
+        for (key, state) in &self.states {
+            groups.entry(key_group(key)).or_default().push((key, state));
+        }
+        for (group, states) in groups {
+            self.savepoint.store_key_group(&self.id, group, &states);
+        }
+    }
+}
+
+impl<K, S, O, Fk, F, Op> Operator for KeyedStatefulMap<K, S, O, Fk, F, Op>
+where
+    K: ExchangeDataKey,
+    S: Serialize + DeserializeOwned + Clone + Send,
+    O: Data,
+    Fk: Fn(&Op::Out) -> K + Send + Clone,
+    F: Fn(&mut S, Op::Out) -> O + Send + Clone,
+    Op: Operator,
+{
+    type Out = (K, O);
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        self.savepoint = metadata.savepoint.clone();
+        // the key groups owned by this replica are the ones routed to it
+        let groups = replica_key_groups(metadata.global_id as usize, metadata.replicas.len());
+        for group in groups {
+            if let Some(states) = self
+                .savepoint
+                .load_key_group::<Vec<(K, S)>>(&self.id, group)
+            {
+                self.states.extend(states);
+            }
+        }
+        if !self.states.is_empty() {
+            log::debug!(
+                "savepoint: restored {} keys of {} (replica {})",
+                self.states.len(),
+                self.id,
+                metadata.global_id
+            );
+        }
+    }
+
+    #[inline]
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        match self.prev.next() {
+            StreamElement::Item(item) => StreamElement::Item(self.apply(item)),
+            StreamElement::Timestamped(item, ts) => {
+                StreamElement::Timestamped(self.apply(item), ts)
+            }
+            StreamElement::Watermark(ts) => StreamElement::Watermark(ts),
+            StreamElement::FlushBatch => StreamElement::FlushBatch,
+            StreamElement::FlushAndRestart => StreamElement::FlushAndRestart,
+            StreamElement::Terminate => {
+                self.store();
+                StreamElement::Terminate
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<Self::Out, _>("KeyedStatefulMap");
+        operator.subtitle = format!("id: {}", self.id);
+        self.prev.structure().add_operator(operator)
+    }
+}
+
+impl<I, Op> Stream<Op>
+where
+    I: ExchangeData,
+    Op: Operator<Out = I> + 'static,
+{
+    /// Map the elements of the stream into new elements, updating a state kept for each key,
+    /// extracted with `keyer`.
+    ///
+    /// The state of each key starts from a clone of `init`, and the function receives a mutable
+    /// reference to the state of the key together with each element. The elements are
+    /// partitioned by key, like [`Stream::group_by`], and the result is keyed.
+    ///
+    /// The states are part of the savepoints under the identifier `id`, like the state of
+    /// [`Stream::stateful_map`], but they are stored by key group (see
+    /// [`KEY_GROUPS`](crate::savepoint::KEY_GROUPS)) instead of by replica: the keys are hashed
+    /// into a fixed number of key groups, and each replica owns a contiguous range of them. A
+    /// savepoint can be restored into an execution with a different number of replicas, and the
+    /// state of each key is restored in the replica that receives the key.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..6);
+    /// let res = s
+    ///     .keyed_stateful_map("counts", |n| n % 2, 0, |count, _| {
+    ///         *count += 1;
+    ///         *count
+    ///     })
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, vec![(0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3)]);
+    /// ```
+    pub fn keyed_stateful_map<K, S, O, Fk, F>(
+        self,
+        id: impl Into<String>,
+        keyer: Fk,
+        init: S,
+        f: F,
+    ) -> KeyedStream<impl Operator<Out = (K, O)>>
+    where
+        K: ExchangeDataKey,
+        S: Serialize + DeserializeOwned + Clone + Send + 'static,
+        O: Data,
+        Fk: Fn(&I) -> K + Send + Sync + Clone + 'static,
+        F: Fn(&mut S, I) -> O + Send + Clone + 'static,
+    {
+        let id = id.into();
+        let route = keyer.clone();
+        let stream = self
+            .partition_custom(move |item, replicas| {
+                key_group_replica(key_group(&route(item)), replicas)
+            })
+            .add_operator(|prev| KeyedStatefulMap::new(prev, id, keyer, init, f));
+        KeyedStream(stream)
+    }
+}
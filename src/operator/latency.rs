@@ -0,0 +1,209 @@
+use std::fmt::Display;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::{BlockStructure, OperatorStructure};
+use crate::network::Coord;
+use crate::operator::{fmt_stage, Data, Operator, StreamElement};
+use crate::profiler::{get_profiler, Profiler};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// An element tagged with the wall-clock time it entered the pipeline.
+///
+/// Build it with [`Stream::tag_ingestion`], and measure the end-to-end latency of the elements
+/// with [`Stream::record_latency`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ingested<T> {
+    /// The element.
+    pub value: T,
+    /// When the element was ingested, in microseconds since the UNIX epoch.
+    pub ingested_at: u64,
+}
+
+/// The current wall-clock time, in microseconds since the UNIX epoch.
+fn now_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the system clock is before the UNIX epoch")
+        .as_micros() as u64
+}
+
+impl<T> Ingested<T> {
+    /// Tag `value` with the current time.
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            ingested_at: now_micros(),
+        }
+    }
+
+    /// The time elapsed since the element was ingested.
+    ///
+    /// The clocks of the hosts are assumed to be synchronized, a clock behind the one of the
+    /// ingesting host gives a zero latency.
+    pub fn latency(&self) -> Duration {
+        Duration::from_micros(now_micros().saturating_sub(self.ingested_at))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RecordLatency<Op> {
+    prev: Op,
+    coord: Option<Coord>,
+}
+
+impl<Op> RecordLatency<Op> {
+    fn new(prev: Op) -> Self {
+        Self { prev, coord: None }
+    }
+}
+
+impl<T: Data, Op> Display for RecordLatency<Op>
+where
+    Op: Operator<Out = Ingested<T>>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, T>(f, &self.prev, "RecordLatency")
+    }
+}
+
+impl<T: Data, Op> Operator for RecordLatency<Op>
+where
+    Op: Operator<Out = Ingested<T>>,
+{
+    type Out = T;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        self.coord = Some(metadata.coord);
+    }
+
+    #[inline]
+    fn next(&mut self) -> StreamElement<T> {
+        let el = self.prev.next();
+        if let StreamElement::Item(item) | StreamElement::Timestamped(item, _) = &el {
+            get_profiler().latency(self.coord.unwrap(), item.latency());
+        }
+        el.map(|item| item.value)
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<T, _>("RecordLatency"))
+    }
+}
+
+impl<Op> Stream<Op>
+where
+    Op: Operator + 'static,
+{
+    /// Tag each element of the stream with the current wall-clock time, to measure its
+    /// end-to-end latency later with [`Stream::record_latency`].
+    ///
+    /// This should be called right after the source. Unlike the event time, the ingestion time
+    /// measures how long the element took to go through the pipeline.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..5);
+    /// let res = s
+    ///     .tag_ingestion()
+    ///     .map(|mut e| {
+    ///         e.value *= 2;
+    ///         e
+    ///     })
+    ///     .record_latency()
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![0, 2, 4, 6, 8]);
+    /// ```
+    pub fn tag_ingestion(self) -> Stream<impl Operator<Out = Ingested<Op::Out>>> {
+        self.map(Ingested::new)
+    }
+}
+
+impl<T: Data, Op> Stream<Op>
+where
+    Op: Operator<Out = Ingested<T>> + 'static,
+{
+    /// Record the end-to-end latency of each element, from when it was tagged with
+    /// [`Stream::tag_ingestion`], and remove the tag.
+    ///
+    /// The latencies are reported to the profiler as a histogram for each block replica. This
+    /// should be called right before the sink. The ingestion time travels with the element, so
+    /// across hosts the latency is accurate only if their clocks are synchronized.
+    pub fn record_latency(self) -> Stream<impl Operator<Out = T>> {
+        self.add_operator(RecordLatency::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::operator::latency::{Ingested, RecordLatency};
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+
+    #[test]
+    fn record_latency_unwraps() {
+        let mut prev = FakeOperator::new((0..3u32).map(Ingested::new));
+        prev.push(StreamElement::FlushBatch);
+        let mut op = RecordLatency::new(prev);
+        op.setup(&mut FakeNetworkTopology::<u32>::new(0, 0).metadata());
+
+        for i in 0..3 {
+            assert_eq!(op.next(), StreamElement::Item(i));
+        }
+        assert_eq!(op.next(), StreamElement::FlushBatch);
+        assert_eq!(op.next(), StreamElement::Terminate);
+    }
+
+    #[cfg(feature = "profiler")]
+    #[test]
+    fn record_latency_in_profiler_report() {
+        use std::time::Duration;
+
+        use crate::operator::map::Map;
+        use crate::profiler::{wait_profiler, LatencyHistogram};
+
+        const DELAY: Duration = Duration::from_millis(20);
+
+        let thread = std::thread::Builder::new()
+            .name("latency-test".into())
+            .spawn(|| {
+                let prev = FakeOperator::new(0..10u32);
+                let tagged = Map::new(prev, Ingested::new);
+                let delayed = Map::new(tagged, |e| {
+                    std::thread::sleep(DELAY);
+                    e
+                });
+                let mut op = RecordLatency::new(delayed);
+                op.setup(&mut FakeNetworkTopology::<u32>::new(0, 0).metadata());
+                while op.next() != StreamElement::Terminate {}
+            })
+            .unwrap();
+        thread.join().unwrap();
+
+        let mut histogram = LatencyHistogram::default();
+        for metrics in wait_profiler()
+            .into_iter()
+            .filter(|r| r.thread_name == "latency-test")
+            .flat_map(|r| r.buckets)
+            .flat_map(|b| b.latency_metrics)
+        {
+            histogram.merge(&metrics.histogram);
+        }
+        assert_eq!(histogram.len(), 10);
+        // the buckets of the histogram are at most 12.5% wide
+        let p50 = histogram.quantile(0.5).unwrap();
+        assert!(p50 >= DELAY * 7 / 8, "p50 {p50:?} below the delay");
+        assert!(p50 <= DELAY * 3 / 2, "p50 {p50:?} too far from the delay");
+    }
+}
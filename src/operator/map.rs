@@ -1,7 +1,8 @@
 use std::fmt::Display;
 
 use crate::block::{BlockStructure, OperatorStructure};
-use crate::operator::{Operator, StreamElement};
+use crate::operator::stuck::StuckWatch;
+use crate::operator::{fmt_stage, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 
 #[derive(Derivative)]
@@ -14,6 +15,7 @@ where
     prev: Op,
     #[derivative(Debug = "ignore")]
     f: F,
+    stuck: StuckWatch,
 }
 
 impl<O: Send, F: Clone, Op: Clone> Clone for Map<O, F, Op>
@@ -25,6 +27,7 @@ where
         Self {
             prev: self.prev.clone(),
             f: self.f.clone(),
+            stuck: self.stuck.clone(),
         }
     }
 }
@@ -35,13 +38,7 @@ where
     Op: Operator,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> Map<{} -> {}>",
-            self.prev,
-            std::any::type_name::<Op::Out>(),
-            std::any::type_name::<O>()
-        )
+        fmt_stage::<Op::Out, O>(f, &self.prev, "Map")
     }
 }
 
@@ -51,7 +48,11 @@ where
     Op: Operator,
 {
     pub(super) fn new(prev: Op, f: F) -> Self {
-        Self { prev, f }
+        Self {
+            prev,
+            f,
+            stuck: Default::default(),
+        }
     }
 }
 
@@ -64,11 +65,13 @@ where
 
     fn setup(&mut self, metadata: &mut ExecutionMetadata) {
         self.prev.setup(metadata);
+        self.stuck.setup(metadata, "Map");
     }
 
     #[inline]
     fn next(&mut self) -> StreamElement<O> {
-        self.prev.next().map(&self.f)
+        let (f, stuck) = (&self.f, &self.stuck);
+        self.prev.next().map(|t| stuck.run(|| f(t)))
     }
 
     fn structure(&self) -> BlockStructure {
@@ -82,6 +85,7 @@ where
 mod tests {
     use std::str::FromStr;
 
+    use crate::operator::filter::Filter;
     use crate::operator::map::Map;
     use crate::operator::{Operator, StreamElement};
     use crate::test::FakeOperator;
@@ -110,4 +114,14 @@ mod tests {
         assert_eq!(map.next(), StreamElement::Watermark(100));
         assert_eq!(map.next(), StreamElement::Terminate);
     }
+
+    #[test]
+    fn map_filter_to_string() {
+        let map = Map::new(FakeOperator::new(0..10i32), |x| x.to_string());
+        let filter = Filter::new(map, |x: &String| !x.is_empty());
+        assert_eq!(
+            filter.to_string(),
+            "FakeOperator<i32> -> Map<i32 -> alloc::string::String> -> Filter<alloc::string::String>"
+        );
+    }
 }
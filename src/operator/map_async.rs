@@ -7,7 +7,7 @@ use flume::{Receiver, Sender};
 use futures::{Future, StreamExt};
 
 use crate::block::{BlockStructure, OperatorStructure};
-use crate::operator::{Data, Operator, StreamElement};
+use crate::operator::{fmt_stage, Data, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 use crate::BatchMode;
 
@@ -32,7 +32,7 @@ impl<T> Batcher<T> {
     /// Put a message in the batch queue, it won't be sent immediately.
     pub(crate) fn enqueue(&mut self, message: StreamElement<T>) -> Option<Vec<StreamElement<T>>> {
         match self.mode {
-            BatchMode::Adaptive(n, max_delay) => {
+            BatchMode::Adaptive(n, max_delay) | BatchMode::Periodic(n, max_delay) => {
                 self.buffer.push(message);
                 let timeout_elapsed = self.last_send.elapsed() > max_delay.into();
                 if self.buffer.len() >= n.get() || timeout_elapsed {
@@ -83,6 +83,8 @@ where
     buffer: Option<VecIter<StreamElement<O>>>,
     flushing: bool,
     pending: usize,
+    /// The maximum number of futures evaluated concurrently.
+    concurrency: usize,
     f: F,
     i_tx: Sender<Vec<StreamElement<Op::Out>>>,
     o_rx: Receiver<Vec<StreamElement<O>>>,
@@ -97,7 +99,7 @@ where
     F: Clone,
 {
     fn clone(&self) -> Self {
-        Self::new(self.prev.clone(), self.f.clone(), 4)
+        Self::new(self.prev.clone(), self.f.clone(), self.concurrency)
     }
 }
 
@@ -108,13 +110,7 @@ where
     Op: Operator,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> MapAsync<{} -> {}>",
-            self.prev,
-            std::any::type_name::<Op::Out>(),
-            std::any::type_name::<O>()
-        )
+        fmt_stage::<Op::Out, O>(f, &self.prev, "MapAsync")
     }
 }
 
@@ -125,7 +121,7 @@ where
     Op: Operator,
     Op::Out: 'static,
 {
-    pub(super) fn new(prev: Op, f: F, buffer: usize) -> Self {
+    pub(super) fn new(prev: Op, f: F, concurrency: usize) -> Self {
         const CH: usize = 2;
         let (i_tx, i_rx) = flume::bounded::<Vec<StreamElement<Op::Out>>>(CH);
         let (o_tx, o_rx) = flume::bounded::<Vec<StreamElement<O>>>(CH);
@@ -138,7 +134,7 @@ where
                         let ff = ff.clone();
                         tokio::spawn(async move { el.map_async(ff.as_ref()).await })
                     })
-                    .buffered(buffer)
+                    .buffered(concurrency)
                     .map(Result::unwrap)
                     .collect()
                     .await;
@@ -153,6 +149,7 @@ where
             f,
             flushing: false,
             pending: 0,
+            concurrency,
             buffer: Default::default(),
             i_tx,
             o_rx,
@@ -278,3 +275,33 @@ where
 //         assert_eq!(map.next(), StreamElement::Terminate);
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use itertools::Itertools;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn flat_map_async_concurrent_expansions() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env
+            .stream_par_iter(0..100u64)
+            .flat_map_async(8, |n| async move {
+                // the later inputs complete first
+                tokio::time::sleep(Duration::from_millis(100 - n)).await;
+                futures::stream::iter((0..n % 5).map(move |i| (n, i)))
+            })
+            .collect_vec();
+        env.execute().await;
+
+        let res = res.get().unwrap().into_iter().sorted().collect_vec();
+        let expected = (0..100)
+            .flat_map(|n| (0..n % 5).map(move |i| (n, i)))
+            .collect_vec();
+        assert_eq!(res, expected);
+    }
+}
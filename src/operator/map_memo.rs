@@ -7,7 +7,7 @@ use quick_cache::sync::Cache;
 use quick_cache::UnitWeighter;
 
 use crate::block::{BlockStructure, GroupHasherBuilder, OperatorStructure};
-use crate::operator::{Data, Operator, StreamElement};
+use crate::operator::{fmt_stage, Data, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 
 use super::DataKey;
@@ -45,13 +45,7 @@ where
     K: DataKey + Sync,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> MapMemo<{} -> {}>",
-            self.prev,
-            std::any::type_name::<Op::Out>(),
-            std::any::type_name::<O>()
-        )
+        fmt_stage::<Op::Out, O>(f, &self.prev, "MapMemo")
     }
 }
 
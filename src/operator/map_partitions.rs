@@ -0,0 +1,250 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+
+use crate::block::{BlockStructure, OperatorStructure};
+use crate::operator::{fmt_stage, Data, Operator, StreamElement, Timestamp};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct MapPartitions<O, F, Op>
+where
+    Op: Operator,
+{
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    f: F,
+    /// The maximum number of items in a chunk, the size of the batches of the block.
+    chunk_size: usize,
+    /// The items of the current chunk.
+    #[derivative(Debug = "ignore")]
+    chunk: Vec<Op::Out>,
+    /// The largest timestamp of the items of the current chunk.
+    timestamp: Option<Timestamp>,
+    /// The elements to return before pulling from `prev` again.
+    #[derivative(Debug = "ignore")]
+    ready: VecDeque<StreamElement<O>>,
+}
+
+impl<O, F: Clone, Op> Clone for MapPartitions<O, F, Op>
+where
+    Op: Operator,
+{
+    fn clone(&self) -> Self {
+        Self {
+            prev: self.prev.clone(),
+            f: self.f.clone(),
+            chunk_size: self.chunk_size,
+            chunk: Vec::new(),
+            timestamp: None,
+            ready: Default::default(),
+        }
+    }
+}
+
+impl<O, F, Op> Display for MapPartitions<O, F, Op>
+where
+    Op: Operator,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, O>(f, &self.prev, "MapPartitions")
+    }
+}
+
+impl<O, F, It, Op> MapPartitions<O, F, Op>
+where
+    Op: Operator,
+    F: Fn(Vec<Op::Out>) -> It + Send + Clone,
+    It: IntoIterator<Item = O>,
+{
+    fn new(prev: Op, f: F) -> Self {
+        Self {
+            prev,
+            f,
+            chunk_size: 1,
+            chunk: Vec::new(),
+            timestamp: None,
+            ready: Default::default(),
+        }
+    }
+
+    /// Transform the current chunk, if any, and append the results to the ready elements.
+    fn flush_chunk(&mut self) {
+        if self.chunk.is_empty() {
+            return;
+        }
+        let chunk = std::mem::replace(&mut self.chunk, Vec::with_capacity(self.chunk_size));
+        let timestamp = self.timestamp.take();
+        self.ready
+            .extend((self.f)(chunk).into_iter().map(|item| match timestamp {
+                Some(ts) => StreamElement::Timestamped(item, ts),
+                None => StreamElement::Item(item),
+            }));
+    }
+}
+
+impl<O, F, It, Op> Operator for MapPartitions<O, F, Op>
+where
+    O: Data,
+    Op: Operator,
+    F: Fn(Vec<Op::Out>) -> It + Send + Clone,
+    It: IntoIterator<Item = O>,
+{
+    type Out = O;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        self.chunk_size = metadata.batch_mode.max_size();
+        self.chunk.reserve(self.chunk_size);
+    }
+
+    fn next(&mut self) -> StreamElement<O> {
+        loop {
+            if let Some(el) = self.ready.pop_front() {
+                return el;
+            }
+            match self.prev.next() {
+                StreamElement::Item(item) => self.chunk.push(item),
+                StreamElement::Timestamped(item, ts) => {
+                    self.chunk.push(item);
+                    self.timestamp = Some(self.timestamp.map_or(ts, |t| t.max(ts)));
+                }
+                // the items before a watermark or a flush are transformed without waiting for a
+                // full chunk
+                StreamElement::Watermark(ts) => {
+                    self.flush_chunk();
+                    self.ready.push_back(StreamElement::Watermark(ts));
+                }
+                StreamElement::FlushBatch => {
+                    self.flush_chunk();
+                    self.ready.push_back(StreamElement::FlushBatch);
+                }
+                StreamElement::FlushAndRestart => {
+                    self.flush_chunk();
+                    self.ready.push_back(StreamElement::FlushAndRestart);
+                }
+                StreamElement::Terminate => {
+                    self.flush_chunk();
+                    self.ready.push_back(StreamElement::Terminate);
+                }
+            }
+            if self.chunk.len() >= self.chunk_size {
+                self.flush_chunk();
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<O, _>("MapPartitions"))
+    }
+}
+
+impl<Op> Stream<Op>
+where
+    Op: Operator + 'static,
+{
+    /// Map the elements of the stream in chunks, calling `f` once for each chunk instead of once
+    /// for each element.
+    ///
+    /// This allows amortizing a costly setup over many elements, for example a single bulk lookup
+    /// in a database, or a vectorized computation. Each replica cuts its elements into chunks of
+    /// at most the batch size of the block (see [`BatchMode`](crate::BatchMode)), and a chunk
+    /// also ends at each watermark, flush of a batch and end of the stream, so the elements are
+    /// never held back waiting for a full chunk. `f` can return any number of elements for each
+    /// chunk.
+    ///
+    /// The elements produced from a timestamped chunk get the largest timestamp of the chunk.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..5);
+    /// let res = s
+    ///     .map_partitions(|chunk| chunk.into_iter().map(|n| n * 10))
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![0, 10, 20, 30, 40]);
+    /// ```
+    pub fn map_partitions<O, F, It>(self, f: F) -> Stream<impl Operator<Out = O>>
+    where
+        O: Data,
+        F: Fn(Vec<Op::Out>) -> It + Send + Clone + 'static,
+        It: IntoIterator<Item = O> + 'static,
+    {
+        self.add_operator(|prev| MapPartitions::new(prev, f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::map_partitions::MapPartitions;
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+    use crate::BatchMode;
+
+    #[cfg(feature = "timestamp")]
+    #[test]
+    fn map_partitions_chunks() {
+        let mut prev = FakeOperator::empty();
+        for i in 0..5 {
+            prev.push(StreamElement::Timestamped(i, i as i64));
+        }
+        prev.push(StreamElement::Watermark(4));
+        prev.push(StreamElement::Timestamped(5, 5));
+        prev.push(StreamElement::FlushAndRestart);
+        let mut op = MapPartitions::new(prev, |chunk: Vec<u8>| vec![chunk]);
+        let mut topology = FakeNetworkTopology::<u8>::new(0, 0);
+        let mut metadata = topology.metadata();
+        metadata.batch_mode = BatchMode::fixed(3);
+        op.setup(&mut metadata);
+
+        assert_eq!(op.next(), StreamElement::Timestamped(vec![0, 1, 2], 2));
+        assert_eq!(op.next(), StreamElement::Timestamped(vec![3, 4], 4));
+        assert_eq!(op.next(), StreamElement::Watermark(4));
+        assert_eq!(op.next(), StreamElement::Timestamped(vec![5], 5));
+        assert_eq!(op.next(), StreamElement::FlushAndRestart);
+        assert_eq!(op.next(), StreamElement::Terminate);
+    }
+
+    #[test]
+    fn map_partitions_same_as_map() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let source = env
+            .stream_par_iter(0..10_000u64)
+            .batch_mode(BatchMode::fixed(100));
+        let mut split = source.split(2).into_iter();
+        let calls2 = calls.clone();
+        let chunked = split
+            .next()
+            .unwrap()
+            .map_partitions(move |mut chunk: Vec<u64>| {
+                calls2.fetch_add(1, Ordering::Relaxed);
+                // a single pass over the whole chunk
+                chunk.iter_mut().for_each(|n| *n *= 2);
+                chunk
+            })
+            .collect_vec();
+        let mapped = split.next().unwrap().map(|n| n * 2).collect_vec();
+        env.execute_blocking();
+
+        let (mut chunked, mut mapped) = (chunked.get().unwrap(), mapped.get().unwrap());
+        chunked.sort_unstable();
+        mapped.sort_unstable();
+        assert_eq!(chunked, mapped);
+        // far fewer calls than elements
+        assert!(calls.load(Ordering::Relaxed) < 10_000 / 10);
+    }
+}
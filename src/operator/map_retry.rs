@@ -0,0 +1,222 @@
+use std::fmt::Debug;
+use std::time::Duration;
+
+use crate::operator::{Data, ExchangeData, Operator};
+use crate::Stream;
+
+/// What [`Stream::map_retry`] does with an element whose transform failed all the attempts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RetryFailure {
+    /// Drop the element, logging the last error.
+    #[default]
+    Drop,
+    /// Panic, stopping the job.
+    Fail,
+}
+
+/// Apply `f` to `item` up to `max_attempts` times, sleeping between the attempts with an
+/// exponential backoff starting from `backoff`. On the final failure the item is returned with
+/// the last error.
+fn retry<I, O, E, F>(max_attempts: usize, backoff: Duration, f: &F, item: I) -> Result<O, (I, E)>
+where
+    I: Clone,
+    E: Debug,
+    F: Fn(I) -> Result<O, E>,
+{
+    let mut delay = backoff;
+    for attempt in 1.. {
+        match f(item.clone()) {
+            Ok(out) => return Ok(out),
+            Err(e) if attempt >= max_attempts => return Err((item, e)),
+            Err(e) => {
+                log::debug!("map_retry: attempt {attempt}/{max_attempts} failed: {e:?}");
+                std::thread::sleep(delay);
+                delay = delay.saturating_mul(2);
+            }
+        }
+    }
+    unreachable!()
+}
+
+impl<I, Op> Stream<Op>
+where
+    I: Data,
+    Op: Operator<Out = I> + 'static,
+{
+    /// Map the elements of the stream with a fallible transform, retrying it when it fails.
+    ///
+    /// Each element is passed to `f` up to `max_attempts` times, until it succeeds. After a
+    /// failure the replica waits `backoff` before the next attempt, doubling the wait each time.
+    /// When all the attempts fail the element is dropped or the job is stopped, depending on
+    /// `on_failure`. To keep the failed elements use [`Stream::map_retry_dead_letter`].
+    ///
+    /// The waits block the replica, so this is meant for the calls to external services that fail
+    /// seldom and transiently.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::RetryFailure;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(["1", "x", "3"].into_iter());
+    /// let res = s
+    ///     .map_retry(3, Duration::from_millis(1), RetryFailure::Drop, |s| s.parse::<u32>())
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![1, 3]);
+    /// ```
+    pub fn map_retry<O, E, F>(
+        self,
+        max_attempts: usize,
+        backoff: Duration,
+        on_failure: RetryFailure,
+        f: F,
+    ) -> Stream<impl Operator<Out = O>>
+    where
+        O: Data,
+        E: Debug,
+        F: Fn(I) -> Result<O, E> + Clone + Send + 'static,
+    {
+        assert!(max_attempts > 0, "map_retry needs at least one attempt");
+        self.filter_map(move |item| match retry(max_attempts, backoff, &f, item) {
+            Ok(out) => Some(out),
+            Err((_, e)) => match on_failure {
+                RetryFailure::Drop => {
+                    log::warn!("map_retry: dropping an element, last error: {e:?}");
+                    None
+                }
+                RetryFailure::Fail => {
+                    panic!("map_retry: failed after {max_attempts} attempts: {e:?}")
+                }
+            },
+        })
+    }
+
+    /// Like [`Stream::map_retry`], sending the elements whose transform failed all the attempts
+    /// to a dead-letter stream, together with the last error.
+    ///
+    /// Returns the stream of the transformed elements and the dead-letter stream.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(["1", "x", "3"].map(String::from).into_iter());
+    /// let (ok, failed) = s.map_retry_dead_letter(3, Duration::from_millis(1), |s| {
+    ///     s.parse::<u32>().map_err(|e| e.to_string())
+    /// });
+    /// let ok = ok.collect_vec();
+    /// let failed = failed.collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(ok.get().unwrap(), vec![1, 3]);
+    /// assert_eq!(failed.get().unwrap()[0].0, "x");
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn map_retry_dead_letter<O, E, F>(
+        self,
+        max_attempts: usize,
+        backoff: Duration,
+        f: F,
+    ) -> (
+        Stream<impl Operator<Out = O>>,
+        Stream<impl Operator<Out = (I, E)>>,
+    )
+    where
+        I: ExchangeData,
+        O: ExchangeData,
+        E: ExchangeData + Debug,
+        F: Fn(I) -> Result<O, E> + Clone + Send + 'static,
+    {
+        assert!(max_attempts > 0, "map_retry needs at least one attempt");
+        let mut routes = self
+            .map(move |item| retry(max_attempts, backoff, &f, item))
+            .route()
+            .add_route(Result::is_ok)
+            .add_route(Result::is_err)
+            .build_inner()
+            .into_iter();
+        let ok = routes.next().unwrap().filter_map(Result::ok);
+        let failed = routes.next().unwrap().filter_map(Result::err);
+        (ok, failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::RetryFailure;
+
+    #[test]
+    fn map_retry_succeeds_after_failures() {
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let res = env
+            .stream_iter(0..1u32)
+            .map_retry(3, Duration::from_millis(1), RetryFailure::Fail, move |n| {
+                // fail twice, then succeed
+                match counter.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err("unavailable"),
+                    _ => Ok(n + 10),
+                }
+            })
+            .collect_vec();
+        env.execute_blocking();
+        assert_eq!(res.get().unwrap(), vec![10]);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn map_retry_dead_letter() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let (ok, failed) = env.stream_par_iter(0..100u32).map_retry_dead_letter(
+            2,
+            Duration::from_millis(1),
+            |n| {
+                if n == 42 {
+                    Err(format!("{n} always fails"))
+                } else {
+                    Ok(n)
+                }
+            },
+        );
+        let ok = ok.collect_vec();
+        let failed = failed.collect_vec();
+        env.execute_blocking();
+
+        let mut ok = ok.get().unwrap();
+        ok.sort();
+        assert_eq!(ok, (0..100).filter(|&n| n != 42).collect::<Vec<_>>());
+        assert_eq!(
+            failed.get().unwrap(),
+            vec![(42, "42 always fails".to_string())]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn map_retry_fail() {
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        env.stream_iter(0..1u32)
+            .map_retry(2, Duration::ZERO, RetryFailure::Fail, |_| {
+                Err::<u32, _>("down")
+            })
+            .for_each(|_| {});
+        env.execute_blocking();
+    }
+}
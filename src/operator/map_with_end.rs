@@ -0,0 +1,218 @@
+use std::fmt::Display;
+
+use crate::block::{BlockStructure, OperatorStructure};
+use crate::operator::{fmt_stage, Data, Operator, StreamElement, Timestamp};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+struct MapWithEnd<S, O, F, E, Op>
+where
+    Op: Operator,
+{
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    init: S,
+    #[derivative(Debug = "ignore")]
+    state: S,
+    #[derivative(Debug = "ignore")]
+    f: F,
+    #[derivative(Debug = "ignore")]
+    on_end: E,
+    /// The largest timestamp received since the start of the stream.
+    timestamp: Option<Timestamp>,
+    /// The end of the stream received, returned after the final element.
+    #[derivative(Debug = "ignore")]
+    end: Option<StreamElement<O>>,
+}
+
+impl<S, O, F, E, Op> Display for MapWithEnd<S, O, F, E, Op>
+where
+    Op: Operator,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, O>(f, &self.prev, "MapWithEnd")
+    }
+}
+
+impl<S, O, F, E, Op> MapWithEnd<S, O, F, E, Op>
+where
+    S: Clone,
+    Op: Operator,
+{
+    fn new(prev: Op, init: S, f: F, on_end: E) -> Self {
+        Self {
+            prev,
+            state: init.clone(),
+            init,
+            f,
+            on_end,
+            timestamp: None,
+            end: None,
+        }
+    }
+}
+
+impl<S, O, F, E, Op> Operator for MapWithEnd<S, O, F, E, Op>
+where
+    S: Clone + Send,
+    O: Data,
+    F: Fn(&mut S, Op::Out) -> O + Send + Clone,
+    E: Fn(&S) -> Option<O> + Send + Clone,
+    Op: Operator,
+{
+    type Out = O;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<O> {
+        if let Some(end) = self.end.take() {
+            return end;
+        }
+        match self.prev.next() {
+            StreamElement::Item(item) => StreamElement::Item((self.f)(&mut self.state, item)),
+            StreamElement::Timestamped(item, ts) => {
+                self.timestamp = Some(self.timestamp.map_or(ts, |t| t.max(ts)));
+                StreamElement::Timestamped((self.f)(&mut self.state, item), ts)
+            }
+            StreamElement::Watermark(ts) => StreamElement::Watermark(ts),
+            StreamElement::FlushBatch => StreamElement::FlushBatch,
+            // the state starts again from `init` in the next iteration
+            StreamElement::FlushAndRestart => {
+                let state = std::mem::replace(&mut self.state, self.init.clone());
+                let timestamp = self.timestamp.take();
+                match (self.on_end)(&state) {
+                    Some(last) => {
+                        self.end = Some(StreamElement::FlushAndRestart);
+                        match timestamp {
+                            Some(ts) => StreamElement::Timestamped(last, ts),
+                            None => StreamElement::Item(last),
+                        }
+                    }
+                    None => StreamElement::FlushAndRestart,
+                }
+            }
+            StreamElement::Terminate => StreamElement::Terminate,
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<O, _>("MapWithEnd"))
+    }
+}
+
+impl<Op> Stream<Op>
+where
+    Op: Operator + 'static,
+{
+    /// Map the elements of the stream with a stateful function, and emit a final element derived
+    /// from the state when the stream ends.
+    ///
+    /// Each replica starts from a clone of `init`, and `f` receives a mutable reference to the
+    /// state of its replica together with each element. At the end of the stream `on_end`
+    /// receives the final state, and the element it returns, if any, is emitted right before the
+    /// end. This is handy for emitting a total after the partial results. Inside an iteration the
+    /// final element is emitted at the end of each iteration, and the state starts again from
+    /// `init`.
+    ///
+    /// The final element has the largest timestamp of the elements received, if any.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(1..5);
+    /// let res = s
+    ///     .map_with_end(
+    ///         0,
+    ///         |sum, n| {
+    ///             *sum += n;
+    ///             format!("+{n}")
+    ///         },
+    ///         |sum| Some(format!("={sum}")),
+    ///     )
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec!["+1", "+2", "+3", "+4", "=10"]);
+    /// ```
+    pub fn map_with_end<S, O, F, E>(
+        self,
+        init: S,
+        f: F,
+        on_end: E,
+    ) -> Stream<impl Operator<Out = O>>
+    where
+        S: Clone + Send + 'static,
+        O: Data,
+        F: Fn(&mut S, Op::Out) -> O + Send + Clone + 'static,
+        E: Fn(&S) -> Option<O> + Send + Clone + 'static,
+    {
+        self.add_operator(|prev| MapWithEnd::new(prev, init, f, on_end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::map_with_end::MapWithEnd;
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+
+    #[test]
+    fn final_count_before_end() {
+        let mut prev = FakeOperator::new(0..3u32);
+        prev.push(StreamElement::FlushBatch);
+        prev.push(StreamElement::FlushAndRestart);
+        prev.push(StreamElement::Item(7));
+        prev.push(StreamElement::FlushAndRestart);
+        let count = |c: &mut u32, _: u32| -> Option<u32> {
+            *c += 1;
+            None
+        };
+        let mut op = MapWithEnd::new(prev, 0, count, |c: &u32| Some(Some(*c)));
+        op.setup(&mut FakeNetworkTopology::<u32>::new(0, 0).metadata());
+
+        for _ in 0..3 {
+            assert_eq!(op.next(), StreamElement::Item(None));
+        }
+        assert_eq!(op.next(), StreamElement::FlushBatch);
+        assert_eq!(op.next(), StreamElement::Item(Some(3)));
+        assert_eq!(op.next(), StreamElement::FlushAndRestart);
+        // the count starts again after the end of an iteration
+        assert_eq!(op.next(), StreamElement::Item(None));
+        assert_eq!(op.next(), StreamElement::Item(Some(1)));
+        assert_eq!(op.next(), StreamElement::FlushAndRestart);
+        assert_eq!(op.next(), StreamElement::Terminate);
+    }
+
+    #[test]
+    fn final_count_at_sink() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env
+            .stream_iter(0..100u32)
+            .map_with_end(
+                0,
+                |count, n| {
+                    *count += 1;
+                    n
+                },
+                |count| Some(1000 + *count),
+            )
+            .collect_vec();
+        env.execute_blocking();
+
+        let res = res.get().unwrap();
+        let (last, items) = res.split_last().unwrap();
+        assert_eq!(*last, 1100);
+        assert_eq!(items, (0..100).collect::<Vec<_>>());
+    }
+}
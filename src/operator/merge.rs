@@ -5,9 +5,14 @@ use crate::operator::start::{BinaryElement, Start};
 use crate::operator::{ExchangeData, Operator};
 use crate::stream::Stream;
 
-#[derive(Clone, Serialize, Deserialize)]
+/// An element coming from one of two streams with different types.
+///
+/// See [`Stream::co_process`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MergeElement<A, B> {
+    /// An element of the first stream.
     Left(A),
+    /// An element of the second stream.
     Right(B),
 }
 
@@ -56,6 +61,58 @@ where
         })
     }
 
+    /// Process together the items of this stream and of another stream with a different type.
+    ///
+    /// The items of the two streams are interleaved as they arrive, and each one is passed to `f`
+    /// wrapped in a [`MergeElement`] telling which stream it comes from. This is useful, for
+    /// example, to process a stream of data together with a stream of rules or commands.
+    ///
+    /// **Note**: the order of the items of the two streams is not specified.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::MergeElement;
+    /// # let mut env = StreamContext::new_local();
+    /// let numbers = env.stream_iter(0..3);
+    /// let words = env.stream_iter(["a", "bb"].into_iter().map(String::from));
+    /// let res = numbers
+    ///     .co_process(words, |e| match e {
+    ///         MergeElement::Left(n) => n,
+    ///         MergeElement::Right(w) => w.len() as i32 * 10,
+    ///     })
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, vec![0, 1, 2, 10, 20]);
+    /// ```
+    pub fn co_process<Op2, O, F>(self, oth: Stream<Op2>, f: F) -> Stream<impl Operator<Out = O>>
+    where
+        Op2: Operator + 'static,
+        Op2::Out: ExchangeData,
+        O: Send,
+        F: Fn(MergeElement<Op::Out, Op2::Out>) -> O + Send + Clone + 'static,
+    {
+        self.binary_connection(
+            oth,
+            Start::multiple,
+            NextStrategy::only_one(),
+            NextStrategy::only_one(),
+        )
+        .filter_map(|e| match e {
+            BinaryElement::Left(item) => Some(MergeElement::Left(item)),
+            BinaryElement::Right(item) => Some(MergeElement::Right(item)),
+            _ => None,
+        })
+        .map(f)
+    }
+
     pub(crate) fn merge_distinct<Op2>(
         self,
         right: Stream<Op2>,
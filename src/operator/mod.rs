@@ -11,13 +11,37 @@ use std::ops::{AddAssign, Div};
 use flume::{unbounded, Receiver};
 #[cfg(feature = "tokio")]
 use futures::Future;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 pub(crate) use start::*;
 
+#[cfg(feature = "timestamp")]
+pub use align_watermarks::WatermarkAlignment;
+pub use compression::OutputCompression;
+pub use control::ControlledStream;
+pub use dedup_by_key::DedupPolicy;
+pub use fused::Fused;
+pub use heartbeat::Heartbeat;
+pub use latency::Ingested;
+pub use map_retry::RetryFailure;
+pub use merge::MergeElement;
+pub use process::{ProcessContext, ProcessFunction, Timer};
 pub use rich_map_custom::ElementGenerator;
-
-use crate::block::{group_by_hash, BlockStructure, GroupHasherBuilder, NextStrategy, Replication};
+pub use running_count_distinct::EstimateCadence;
+pub use sliding_aggregate::{
+    SlidingAccumulator, SlidingCount, SlidingMax, SlidingMean, SlidingMin, SlidingSum,
+};
+pub use spill::MemoryBudget;
+pub use state_ttl::KeyedStateTtl;
+pub use tap_metrics::{MetricCounter, MetricHistogram, MetricsHandle};
+pub use validate::ValidationFailure;
+
+use crate::block::structure::ConnectionStrategy;
+use crate::block::{
+    group_by_hash, Autoscale, BlockStructure, GroupByHasher, GroupHasherBuilder, NextStrategy,
+    Replication,
+};
 use crate::scheduler::ExecutionMetadata;
 
 use crate::stream::KeyedItem;
@@ -42,52 +66,87 @@ use self::{
     end::End,
     filter::Filter,
     filter_map::FilterMap,
-    flat_map::{FlatMap, KeyedFlatMap},
+    flat_map::{FlatMap, FlatMapIterOwned, KeyedFlatMap},
     flatten::{Flatten, KeyedFlatten},
     fold::Fold,
+    fused::FusedOperator,
     inspect::Inspect,
     key_by::KeyBy,
     keyed_fold::KeyedFold,
     map::Map,
-    merge::MergeElement,
     reorder::Reorder,
     rich_map::RichMap,
     rich_map_custom::RichMapCustom,
     route::RouterBuilder,
+    stateful_map::StatefulMap,
     zip::Zip,
 };
 
 #[cfg(feature = "timestamp")]
 mod add_timestamps;
+#[cfg(feature = "timestamp")]
+mod align_watermarks;
+mod batch_by_key;
 mod batch_mode;
 mod boxed;
 pub mod cache;
+mod coalesce_batches;
+pub mod codec;
+mod compression;
+mod control;
+mod debounce;
+mod dedup_by_key;
 pub(crate) mod end;
+#[cfg(feature = "tokio")]
+mod enrich_async;
 mod filter;
 mod filter_map;
 mod flat_map;
+#[cfg(feature = "timestamp")]
+mod flat_map_watermark;
 mod flatten;
 mod fold;
+mod fused;
+mod heartbeat;
 mod inspect;
 #[cfg(feature = "timestamp")]
 mod interval_join;
 pub mod iteration;
 pub mod join;
 mod key_by;
+mod key_by_range;
 mod keyed_fold;
+mod keyed_stateful_map;
+mod latency;
 mod map;
 #[cfg(feature = "tokio")]
 mod map_async;
 mod map_memo;
+mod map_partitions;
+mod map_retry;
+mod map_with_end;
 mod merge;
+mod process;
 mod reorder;
 mod replication;
 mod rich_map;
 mod rich_map_custom;
 mod route;
+mod running_count_distinct;
 pub mod sink;
+mod sliding_aggregate;
+mod sort;
 pub mod source;
+mod spill;
 mod start;
+mod state_ttl;
+mod stateful_map;
+mod stuck;
+mod take_while;
+mod tap_metrics;
+mod top_k;
+mod try_map;
+mod validate;
 pub mod window;
 mod zip;
 
@@ -96,6 +155,10 @@ pub trait Data: Clone + Send + 'static {}
 impl<T: Clone + Send + 'static> Data for T {}
 
 /// Marker trait for data types that are used to communicate between different blocks.
+///
+/// It is implemented for all the serializable types, so it carries no per-type settings. There is
+/// no need for a hint of the serialized size: before sending a batch to a remote host, its exact
+/// size is computed and the serialization buffer, reused across the batches, is reserved up front.
 pub trait ExchangeData: Serialize + for<'a> Deserialize<'a> + Clone + Send + 'static {}
 impl<T: Serialize + for<'a> Deserialize<'a> + Clone + Send + 'static> ExchangeData for T {}
 
@@ -177,6 +240,27 @@ pub trait Operator: Clone + Send + Display {
     fn structure(&self) -> BlockStructure;
 }
 
+/// Format a stage of a chain of operators, appending it to the stages of `prev`.
+///
+/// The operators described by their name and the types of their elements use this in their
+/// `Display` implementation, so that the chains look the same in the logs and in the dot output:
+/// a stage that changes the type of the elements is shown as `Name<In -> Out>`, a stage that keeps
+/// it as `Name<Out>`. The types have their full path. The operators showing more than that (e.g.
+/// the strategy of an `End`, the id of a `StatefulMap` or the writer of a sink) format their stage
+/// by hand, in the same `{prev} -> Name` form.
+pub(crate) fn fmt_stage<In: ?Sized, Out: ?Sized>(
+    f: &mut std::fmt::Formatter<'_>,
+    prev: &dyn Display,
+    name: &str,
+) -> std::fmt::Result {
+    let (input, output) = (std::any::type_name::<In>(), std::any::type_name::<Out>());
+    if input == output {
+        write!(f, "{prev} -> {name}<{output}>")
+    } else {
+        write!(f, "{prev} -> {name}<{input} -> {output}>")
+    }
+}
+
 impl<Out> StreamElement<Out> {
     /// Create a new `StreamElement` with an `Item(())` if `self` contains an item, otherwise it
     /// returns the same variant of `self`.
@@ -359,6 +443,145 @@ where
         self
     }
 
+    /// Let the watermarks of this block advance without the inputs that stay idle.
+    ///
+    /// The watermark received by the operators of this block is the minimum of the watermarks of
+    /// its inputs (e.g. the two sides of a [`Stream::merge`]), so an input that stops sending
+    /// stalls the watermark, and the event time windows, forever. With this option an input
+    /// that sends no data and no watermarks for longer than `idleness` is excluded from the
+    /// minimum until it sends something again. The watermark never goes back: the elements an
+    /// idle input sends behind it when it resumes are late.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s1 = env.stream_iter(0..10).add_timestamps(|&n| n, |_, &ts| Some(ts));
+    /// let s2 = env.stream_iter(0..10).add_timestamps(|&n| n, |_, &ts| Some(ts));
+    /// s1.merge(s2).with_idleness(Duration::from_secs(1));
+    /// ```
+    #[cfg(feature = "timestamp")]
+    pub fn with_idleness(mut self, idleness: std::time::Duration) -> Self {
+        self.block.watermark_idleness = Some(idleness);
+        self
+    }
+
+    /// Set how unbalanced a group by following this block must be to log a warning.
+    ///
+    /// When the stream ends, each replica of this block checks how many items it sent to each
+    /// replica of a following [`Stream::group_by`]: if a replica received more than `ratio` times
+    /// the average, a warning names it, since a few very frequent keys are probably overloading
+    /// it. The default ratio is 4, use `f64::INFINITY` to disable the warning.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..100);
+    /// let res = s.skew_warning(2.0).group_by(|&n| n % 2).collect_vec();
+    ///
+    /// env.execute_blocking();
+    /// ```
+    pub fn skew_warning(mut self, ratio: f64) -> Self {
+        self.block.skew_warning = Some(ratio);
+        self
+    }
+
+    /// Log a warning when the code of the user in this block runs for longer than `timeout` on a
+    /// single element.
+    ///
+    /// A closure that blocks (a deadlock, an endless loop, a call to a service that does not
+    /// answer) stalls the whole pipeline without any error. With this option the closures of the
+    /// [`Stream::map`], [`Stream::filter`], [`Stream::filter_map`], [`Stream::flat_map`],
+    /// [`Stream::inspect`] and [`Stream::for_each`] of this block are timed: when a call takes
+    /// longer than `timeout`, a warning names the operator, its replica and the thread running it,
+    /// and another one reports how long the call took when it finishes. The job is not stopped.
+    ///
+    /// The option applies to the operators of the current block, up to the next operator that
+    /// splits it (e.g. a [`Stream::group_by`]). Timing a call costs a read of the clock, so this
+    /// is meant for debugging a job that hangs.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let res = env
+    ///     .stream_iter(0..10)
+    ///     .stuck_timeout(Duration::from_secs(10))
+    ///     .map(|n| n * 2)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    /// ```
+    pub fn stuck_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.block.stuck_timeout = Some(timeout);
+        self
+    }
+
+    /// Attach the label `key` with `value` to the current block, for observability.
+    ///
+    /// The labels name the stages of the pipeline with meaningful terms (e.g. `stage=enrichment`
+    /// or `team=ads`), to correlate them with external dashboards: they are shown in the job
+    /// graph (see [`StreamContext::job_graph`](crate::StreamContext::job_graph)), and reported in
+    /// the profiler results of the replicas of the block. Unlike the batch mode the labels are
+    /// not propagated to the next blocks. Setting a key again replaces its value.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..10);
+    /// let res = s
+    ///     .map(|n| n * 2)
+    ///     .label("stage", "enrichment")
+    ///     .label("team", "ads")
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    /// ```
+    pub fn label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.block.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Change the hash function that assigns the keys of the group-bys to the replicas.
+    ///
+    /// The default is a fast hash with a fixed seed. A [`GroupByHasher::SipHash`] with secret
+    /// keys avoids the collisions of adversarial keys overloading a replica, and
+    /// [`GroupByHasher::custom`] uses any other hasher. The same key is always assigned to the
+    /// same replica, in every execution with the same hasher and parallelism.
+    ///
+    /// This change will be propagated to all the operators following, even of the next blocks,
+    /// until it's changed again. In a join both sides use the hasher of the left one.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// use renoir::GroupByHasher;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..100);
+    /// let res = s
+    ///     .group_by_hasher(GroupByHasher::SipHash(0x1234, 0x5678))
+    ///     .group_by(|&n| n % 10)
+    ///     .fold(0, |count, _| *count += 1)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap().len(), 10);
+    /// ```
+    pub fn group_by_hasher(mut self, hasher: GroupByHasher) -> Self {
+        self.block.group_by_hasher = hasher;
+        self
+    }
+
     /// Remove from the stream all the elements for which the provided function returns `None` and
     /// keep the elements that returned `Some(_)`.
     ///
@@ -385,6 +608,43 @@ where
         self.add_operator(|prev| FilterMap::new(prev, f))
     }
 
+    /// Apply a chain of stateless stages (`map`, `filter`, `filter_map`, `inspect`) with a single
+    /// operator.
+    ///
+    /// The stages are built from the [`Fused`] passed to `build` and are composed into a single
+    /// function, so each element is matched only once instead of once for every operator of the
+    /// chain. The operators are already inlined by the compiler in most cases, measure the
+    /// pipeline (e.g. with the `fusion` benchmark) before relying on a speedup.
+    ///
+    /// In the job graph the chain is shown as a single `Fused` operator, use
+    /// [`StreamContext::expand_fused_operators`](crate::StreamContext::expand_fused_operators) to
+    /// show each stage instead.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..10);
+    /// let res = s
+    ///     .fused(|f| f.map(|n| n * 3).filter(|n| n % 2 == 0).map(|n| n + 1))
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![1, 7, 13, 19, 25])
+    /// ```
+    pub fn fused<O, F, B>(self, build: B) -> Stream<impl Operator<Out = O>>
+    where
+        B: FnOnce(Fused<Op::Out, Op::Out, fn(Op::Out) -> Option<Op::Out>>) -> Fused<Op::Out, O, F>,
+        F: Fn(Op::Out) -> Option<O> + Send + Clone + 'static,
+        O: Data,
+    {
+        let fused = build(Fused::new());
+        let expand = self.ctx.lock().expand_fused;
+        self.add_operator(|prev| FusedOperator::new(prev, fused, expand))
+    }
+
     /// Remove from the stream all the elements for which the provided predicate returns `false`.
     ///
     /// **Note**: this is very similar to [`Iteartor::filter`](std::iter::Iterator::filter)
@@ -462,6 +722,41 @@ where
         self.rich_map(f).filter(|x| x.is_some()).map(|x| x.unwrap())
     }
 
+    /// Remove the elements equal to the one immediately before them, collapsing each run of equal
+    /// elements into its first one.
+    ///
+    /// Unlike [`Stream::unique_assoc`], an element is dropped only if it repeats the previous one,
+    /// so an element can appear again after a different one. Only the last element is kept, so
+    /// the state is constant. Each replica compares the elements it receives, so this should
+    /// follow a single replica or a stream with the order to preserve.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter([1, 1, 2, 2, 2, 1].into_iter());
+    /// let res = s.distinct_until_changed().collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![1, 2, 1]);
+    /// ```
+    pub fn distinct_until_changed(self) -> Stream<impl Operator<Out = Op::Out>>
+    where
+        Op::Out: Data + PartialEq,
+    {
+        let mut last = None;
+        self.rich_filter_map(move |el| {
+            if last.as_ref() == Some(&el) {
+                None
+            } else {
+                last = Some(el.clone());
+                Some(el)
+            }
+        })
+    }
+
     /// Map the elements of the stream into new elements. The mapping function can be stateful.
     ///
     /// This is equivalent to [`Stream::map`] but with a stateful function.
@@ -551,6 +846,88 @@ where
         self.add_operator(|prev| Map::new(prev, f))
     }
 
+    /// Map the elements of the stream into new elements, updating a state kept by each replica.
+    ///
+    /// Each replica starts from a clone of `init`, and the function receives a mutable reference
+    /// to the state of its replica together with each element.
+    ///
+    /// The state is part of the savepoints under the identifier `id`: when a savepoint is written
+    /// (see [`StreamContext::savepoint`](crate::StreamContext::savepoint)) the state of each
+    /// replica is stored at the end of the stream, and it is restored instead of `init` when the
+    /// environment is built with
+    /// [`StreamContext::from_savepoint`](crate::StreamContext::from_savepoint). The identifier
+    /// must be unique in the job.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..5);
+    /// let res = s
+    ///     .stateful_map("running-sum", 0, |sum, n| {
+    ///         *sum += n;
+    ///         *sum
+    ///     })
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![0, 1, 3, 6, 10]);
+    /// ```
+    pub fn stateful_map<S, O, F>(
+        self,
+        id: impl Into<String>,
+        init: S,
+        f: F,
+    ) -> Stream<impl Operator<Out = O>>
+    where
+        S: Serialize + DeserializeOwned + Clone + Send + 'static,
+        F: Fn(&mut S, Op::Out) -> O + Send + Clone + 'static,
+        O: Data,
+    {
+        let id = id.into();
+        self.add_operator(|prev| StatefulMap::new(prev, id, init, f))
+    }
+
+    /// Map the elements of the stream into new elements, updating a state kept by each replica.
+    ///
+    /// Each replica starts from a clone of `init`, and the function receives a mutable reference
+    /// to the state of its replica together with each element. This is a shorthand for a
+    /// [`Stream::rich_map`] that captures the state, and it is useful for keeping a counter or a
+    /// small cache. Unlike [`Stream::stateful_map`] the state is not part of the savepoints.
+    ///
+    /// Like a scan, an element is emitted for each update of the state: to emit only the final
+    /// state use [`Stream::fold`], to combine the states of all the replicas use
+    /// [`Stream::fold_scan`].
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(["a", "b", "c"].into_iter());
+    /// let res = s
+    ///     .map_with_state(0, |count, x| {
+    ///         *count += 1;
+    ///         format!("{x}{count}")
+    ///     })
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec!["a1", "b2", "c3"]);
+    /// ```
+    pub fn map_with_state<S, O, F>(self, init: S, f: F) -> Stream<impl Operator<Out = O>>
+    where
+        S: Clone + Send + 'static,
+        F: Fn(&mut S, Op::Out) -> O + Send + Clone + 'static,
+        O: Send + 'static,
+    {
+        let mut state = init;
+        self.rich_map(move |x| f(&mut state, x))
+    }
+
     /// Map the elements of the stream into new elements by evaluating a future for each one.
     /// Use memoization to cache outputs for previously seen inputs.
     ///
@@ -649,6 +1026,50 @@ where
         self.add_operator(|prev| MapAsync::new(prev, f, 4))
     }
 
+    /// Map each element of the stream into a [`futures::Stream`] of new elements by evaluating a
+    /// future, and flatten the resulting streams.
+    ///
+    /// At most `concurrency` futures are evaluated at the same time by each replica. The streams
+    /// are collected when their future completes and the outputs keep the order of the inputs,
+    /// each with the timestamp of its input, so the watermarks are not overtaken by the outputs
+    /// of the previous elements.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # tokio::runtime::Runtime::new()
+    /// #    .unwrap()
+    /// #    .block_on(base());
+    /// # async fn base() {
+    /// #    let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..4);
+    /// let res = s
+    ///     .flat_map_async(2, |n| async move { futures::stream::iter(0..n) })
+    ///     .collect_vec();
+    /// env.execute().await;
+    /// assert_eq!(res.get().unwrap(), vec![0, 0, 1, 0, 1, 2]);
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn flat_map_async<O: Data, F, Fut, S>(
+        self,
+        concurrency: usize,
+        f: F,
+    ) -> Stream<impl Operator<Out = O>>
+    where
+        F: Fn(Op::Out) -> Fut + Send + Sync + 'static + Clone,
+        Fut: futures::Future<Output = S> + Send + 'static,
+        S: futures::Stream<Item = O> + Send + 'static,
+    {
+        use futures::{FutureExt, StreamExt};
+
+        assert!(concurrency > 0, "the concurrency must be positive");
+        let f = move |x| f(x).then(|s| s.collect::<Vec<_>>());
+        self.add_operator(|prev| MapAsync::new(prev, f, concurrency))
+            .flatten()
+    }
+
     /// Map the elements of the stream into new elements. Use memoization
     /// to cache outputs for previously seen inputs.
     ///
@@ -831,8 +1252,9 @@ where
         Op::Out: Clone,
     {
         // GroupBy based on key
+        let hasher = self.block.group_by_hasher.clone();
         let next_strategy = NextStrategy::GroupBy(
-            move |(key, _): &(K, O)| group_by_hash(&key),
+            move |(key, _): &(K, O)| hasher.hash(key),
             Default::default(),
         );
 
@@ -1123,6 +1545,36 @@ where
         self.add_operator(|prev| FlatMap::new(prev, f))
     }
 
+    /// Like [`flat_map`](Stream::flat_map), but the function takes each element by value and
+    /// pushes its outputs into a buffer instead of returning a new collection.
+    ///
+    /// The buffer is reused for all the elements, so no collection is allocated for each of them,
+    /// and since the element is owned by the function its parts can be moved into the outputs
+    /// instead of being cloned. This is faster than [`flat_map`](Stream::flat_map) when each
+    /// element produces many outputs, like splitting large lines into words.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(vec!["a b".to_string(), "c".to_string()].into_iter());
+    /// let res = s
+    ///     .flat_map_iter_owned(|line, out| out.extend(line.split(' ').map(String::from)))
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec!["a", "b", "c"]);
+    /// ```
+    pub fn flat_map_iter_owned<O, F>(self, f: F) -> Stream<impl Operator<Out = O>>
+    where
+        O: Send,
+        F: Fn(Op::Out, &mut Vec<O>) + Send + Clone,
+    {
+        self.add_operator(|prev| FlatMapIterOwned::new(prev, f))
+    }
+
     /// Apply the given function to all the elements of the stream, consuming the stream.
     ///
     /// ## Example
@@ -1144,6 +1596,48 @@ where
             .finalize_block();
     }
 
+    /// Apply the given async function to all the elements of the stream, consuming the stream.
+    ///
+    /// This is meant for the side effects that are async, like writing to an async database
+    /// client or sending HTTP requests. At most `concurrency` futures are evaluated at the same
+    /// time by each replica: when the limit is reached the replica stops pulling new elements
+    /// until some complete. All the futures complete before the execution ends.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::sync::atomic::{AtomicU32, Ordering};
+    /// # use std::sync::Arc;
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # tokio::runtime::Runtime::new()
+    /// #    .unwrap()
+    /// #    .block_on(base());
+    /// # async fn base() {
+    /// #    let mut env = StreamContext::new_local();
+    /// let sum = Arc::new(AtomicU32::new(0));
+    /// let s = env.stream_iter(0..5);
+    /// let sum2 = sum.clone();
+    /// s.for_each_async(2, move |n| {
+    ///     let sum = sum2.clone();
+    ///     async move {
+    ///         sum.fetch_add(n, Ordering::Relaxed);
+    ///     }
+    /// });
+    /// env.execute().await;
+    /// assert_eq!(sum.load(Ordering::Relaxed), 10);
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn for_each_async<F, Fut>(self, concurrency: usize, f: F)
+    where
+        F: Fn(Op::Out) -> Fut + Send + Sync + 'static + Clone,
+        Fut: futures::Future<Output = ()> + Send + 'static,
+    {
+        assert!(concurrency > 0, "the concurrency must be positive");
+        self.add_operator(|prev| MapAsync::new(prev, f, concurrency))
+            .for_each(|()| {});
+    }
+
     /// Transform this stream of containers into a stream of all the contained values.
     ///
     /// **Note**: this is very similar to [`Iteartor::flatten`](std::iter::Iterator::flatten)
@@ -1200,6 +1694,54 @@ where
         self.split_block(End::new, NextStrategy::all())
     }
 
+    /// Start a new block, choosing explicitly how the elements are sent to the replicas of the
+    /// new block.
+    ///
+    /// This is an escape hatch for advanced control of the topology: the operators usually pick
+    /// the connection strategy themselves (e.g. [`Stream::shuffle`] uses
+    /// [`ConnectionStrategy::Random`] and [`Stream::broadcast`] uses [`ConnectionStrategy::All`]).
+    /// With [`ConnectionStrategy::OnlyOne`] each replica sends to the corresponding replica of the
+    /// next block, or to its only replica.
+    ///
+    /// Panics with [`ConnectionStrategy::GroupBy`], since the stream has no key (see
+    /// [`Stream::group_by`]), and with [`ConnectionStrategy::Partition`], since there is no
+    /// partitioning function (see [`Stream::partition_custom`]).
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::structure::ConnectionStrategy;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..10);
+    /// let res = s.connect_with(ConnectionStrategy::Random).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, (0..10).collect::<Vec<_>>());
+    /// ```
+    pub fn connect_with(
+        self,
+        strategy: ConnectionStrategy,
+    ) -> Stream<impl Operator<Out = Op::Out>> {
+        let next_strategy = match strategy {
+            ConnectionStrategy::OnlyOne => NextStrategy::only_one(),
+            ConnectionStrategy::Random => NextStrategy::random(),
+            ConnectionStrategy::All => NextStrategy::all(),
+            ConnectionStrategy::GroupBy => {
+                panic!("the GroupBy connection needs a key, use Stream::group_by instead")
+            }
+            ConnectionStrategy::Partition => panic!(
+                "the Partition connection needs a function, use Stream::partition_custom instead"
+            ),
+        };
+        self.split_block(End::new, next_strategy)
+    }
+
     /// Given a stream, make a [`KeyedStream`] partitioning the values according to a key generated
     /// by the `keyer` function provided.
     ///
@@ -1227,13 +1769,65 @@ where
         Fk: Fn(&Op::Out) -> K + Send + Clone + 'static,
         K: DataKey,
     {
-        let next_strategy = NextStrategy::group_by(keyer.clone());
+        let next_strategy =
+            NextStrategy::group_by(keyer.clone(), self.block.group_by_hasher.clone());
         let new_stream = self
             .split_block(End::new, next_strategy)
             .add_operator(|prev| KeyBy::new(prev, keyer));
         KeyedStream(new_stream)
     }
 
+    /// Like [`Stream::group_by`], but each item can belong to any number of partitions: a copy of
+    /// the item is sent to the partition of each key returned by `keyer`.
+    ///
+    /// The keyed operators added to the returned [`KeyedStream`] see the item once for each of its
+    /// keys, e.g. a post with several hashtags is counted under each hashtag. An item without keys
+    /// is dropped.
+    ///
+    /// **Note**: unlike [`Stream::group_by`] the keys are sent to the network together with the
+    /// items, since they cannot be rebuilt from the item on the receiving side.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let posts = vec!["a b", "b", "a c"];
+    /// let s = env.stream_iter(posts.into_iter().map(String::from));
+    /// let res = s
+    ///     .group_by_multi(|post| post.split(' ').map(str::to_string).collect::<Vec<_>>())
+    ///     .fold(0, |count, _| *count += 1)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, vec![("a".into(), 2), ("b".into(), 2), ("c".into(), 1)]);
+    /// ```
+    pub fn group_by_multi<K, Fk, It>(self, keyer: Fk) -> KeyedStream<impl Operator<Out = (K, I)>>
+    where
+        Fk: Fn(&Op::Out) -> It + Send + Clone + 'static,
+        It: IntoIterator<Item = K>,
+        K: ExchangeDataKey,
+    {
+        let hasher = self.block.group_by_hasher.clone();
+        let next_strategy = NextStrategy::GroupBy(
+            move |(key, _): &(K, I)| hasher.hash(key),
+            Default::default(),
+        );
+        let new_stream = self
+            .flat_map(move |item| {
+                keyer(&item)
+                    .into_iter()
+                    .map(|key| (key, item.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .split_block(End::new, next_strategy);
+        KeyedStream(new_stream)
+    }
+
     /// Find, for each partition of the stream, the item with the largest value.
     ///
     /// The stream is partitioned using the `keyer` function and the value to compare is obtained
@@ -1613,6 +2207,83 @@ where
         new_stream
     }
 
+    /// Run the following operators with `parallelism` replicas, instead of one for each core.
+    ///
+    /// The elements are redistributed randomly to the new replicas, so the parallelism can be
+    /// both lower and higher than the one of the previous operators. This is useful to limit the
+    /// replicas of an expensive or rate-limited operator (e.g. a call to an external service)
+    /// without limiting the rest of the job. The parallelism is the total across all the hosts.
+    ///
+    /// The parallelism is kept until the block is split again: the operators that redistribute
+    /// the elements (e.g. [`Stream::shuffle`], [`Stream::group_by`]) go back to the default.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let res = env
+    ///     .stream_par_iter(0..100)
+    ///     .set_parallelism(2)
+    ///     .map(|n| n * 2) // only 2 replicas run this
+    ///     .shuffle()
+    ///     .map(|n| n + 1)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap().len(), 100);
+    /// ```
+    pub fn set_parallelism(
+        self,
+        parallelism: crate::CoordUInt,
+    ) -> Stream<impl Operator<Out = Op::Out>> {
+        let mut new_stream = self.split_block(End::new, NextStrategy::random());
+        new_stream
+            .block
+            .scheduling
+            .replication(Replication::new_limited(parallelism));
+        new_stream
+    }
+
+    /// Run the following operators in a new block, connected to the current one.
+    ///
+    /// The operators of a block are chained in the same thread, and a new block is started only
+    /// where the elements need to be redistributed (e.g. by [`Stream::shuffle`] or
+    /// [`Stream::group_by`]). Splitting the block explicitly runs the following operators in
+    /// their own threads, pipelining an expensive chain of operators with the rest of the job.
+    /// Each replica sends its elements to the replica with the same index, so the order and the
+    /// partitioning of the elements are preserved, and the new block keeps the parallelism of the
+    /// current one.
+    ///
+    /// Chaining is the default, so there is no need to ask for it: the operators are always
+    /// fused into the current block until a split.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let res = env
+    ///     .stream_iter(0..5)
+    ///     .map(|n| n * 2)
+    ///     .start_new_block()
+    ///     .map(|n| n + 1) // runs in a separate block
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![1, 3, 5, 7, 9]);
+    /// ```
+    pub fn start_new_block(self) -> Stream<impl Operator<Out = Op::Out>> {
+        let scheduler_requirements = self.block.scheduling.clone();
+        let mut new_stream = self.split_block(End::new, NextStrategy::only_one());
+        new_stream.block.scheduling = scheduler_requirements;
+        new_stream
+    }
+
     /// Advanced operator that allows changing the replication and forwarding strategy
     ///
     /// **Note**: this operator is advanced and is only intended to add functionality
@@ -1636,11 +2307,91 @@ where
         replication: Replication,
         partition_fn: Fk,
     ) -> Stream<impl Operator<Out = Op::Out>> {
-        let mut new_stream = self.split_block(End::new, NextStrategy::group_by(partition_fn));
+        let hasher = self.block.group_by_hasher.clone();
+        let mut new_stream =
+            self.split_block(End::new, NextStrategy::group_by(partition_fn, hasher));
         new_stream.block.scheduling.replication(replication);
         new_stream
     }
 
+    /// Send each element to the replica of the next block chosen by `partitioner`.
+    ///
+    /// The partitioner receives the element and the number of replicas of the next block, and
+    /// returns the index of the replica that will receive the element, which must be lower than
+    /// the number of replicas. This allows custom routing, like range partitioning or splitting
+    /// a hot key among more replicas.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..100);
+    /// // range partitioning
+    /// let res = s
+    ///     .partition_custom(|&n, num_partitions| n * num_partitions / 100)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    /// ```
+    pub fn partition_custom<F>(self, partitioner: F) -> Stream<impl Operator<Out = Op::Out>>
+    where
+        F: Fn(&Op::Out, usize) -> usize + Send + Sync + 'static,
+    {
+        self.split_block(End::new, NextStrategy::partition(partitioner))
+    }
+
+    /// Send each element to the replica of the next block that handles the range of its key.
+    ///
+    /// The sorted `boundaries` split the keys into contiguous ranges: the keys lower than the
+    /// first boundary go to the first replica, the keys between the first and the second boundary
+    /// (included the first) go to the second replica and so on. If the next block has fewer
+    /// replicas than ranges, the exceeding ranges all go to the last replica. Unlike
+    /// [`Stream::repartition_by`], each replica receives a contiguous range of the keys, so
+    /// sorting each replica separately gives a global sort.
+    ///
+    /// The boundaries are not computed from the data, to balance the replicas they can be picked
+    /// from a sample of the keys.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new(RuntimeConfig::local(3).unwrap());
+    /// let s = env.stream_iter(0..30);
+    /// // the first replica receives 0..10, the second 10..20 and the third 20..30
+    /// let res = s.repartition_range(|&n| n, vec![10, 20]).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, (0..30).collect::<Vec<_>>());
+    /// ```
+    pub fn repartition_range<K, F>(
+        self,
+        key_fn: F,
+        boundaries: Vec<K>,
+    ) -> Stream<impl Operator<Out = Op::Out>>
+    where
+        K: Ord + Send + Sync + 'static,
+        F: Fn(&Op::Out) -> K + Send + Sync + 'static,
+    {
+        assert!(
+            boundaries.windows(2).all(|w| w[0] <= w[1]),
+            "the boundaries of repartition_range must be sorted"
+        );
+        self.partition_custom(move |item, num_replicas| {
+            let key = key_fn(item);
+            let range = boundaries.partition_point(|b| *b <= key);
+            range.min(num_replicas - 1)
+        })
+    }
+
     /// Reduce the stream into a stream that emits a single value.
     ///
     /// The reducing operator consists in adding to the current accumulation value  the value of the
@@ -1792,6 +2543,40 @@ where
         self.split_block(End::new, NextStrategy::random())
     }
 
+    /// Perform a network shuffle sending the messages to a random replica among the active ones,
+    /// scaling the number of active replicas of the following block based on the observed
+    /// backpressure.
+    ///
+    /// The following block is started with `autoscale.max` replicas, but only some of them receive
+    /// the items: when the replicas of this block are often blocked waiting for the next block,
+    /// another replica is activated, when they are idle a replica is retired. See [`Autoscale`]
+    /// for the details.
+    ///
+    /// **Note**: only stateless operators should follow this operator. The items are not
+    /// partitioned by key and the state of a retired replica is not moved to the others, so a
+    /// keyed operator (e.g. [`Stream::group_by`]) must be placed after another repartitioning.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig, Autoscale};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..5);
+    /// let res = s.shuffle_autoscale(Autoscale::new(1, 4)).map(|x| x * 2).collect_vec();
+    ///
+    /// env.execute_blocking();
+    /// ```
+    pub fn shuffle_autoscale(self, autoscale: Autoscale) -> Stream<impl Operator<Out = Op::Out>> {
+        let mut new_stream = self.split_block(End::new, NextStrategy::adaptive(autoscale));
+        new_stream
+            .block
+            .scheduling
+            .replication(Replication::new_limited(autoscale.max as crate::CoordUInt));
+        new_stream
+    }
+
     /// Split the stream into `splits` streams, each with all the elements of the first one.
     ///
     /// This will effectively duplicate every item in the stream into the newly created streams.
@@ -1825,6 +2610,39 @@ where
         streams
     }
 
+    /// Duplicate the stream into two streams, each with all the elements of the first one.
+    ///
+    /// This is a [`split`](Stream::split) in two, to send the same elements to different sinks of
+    /// the same job: a single execution fills all their outputs.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let (all, summary) = env.stream_iter(0..5).tee();
+    /// let all = all.collect_vec();
+    /// let sum = summary.fold(0, |acc, n| *acc += n).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(all.get().unwrap(), vec![0, 1, 2, 3, 4]);
+    /// assert_eq!(sum.get().unwrap(), vec![10]);
+    /// ```
+    pub fn tee(
+        self,
+    ) -> (
+        Stream<impl Operator<Out = Op::Out>>,
+        Stream<impl Operator<Out = Op::Out>>,
+    ) {
+        let mut streams = self.split(2);
+        let second = streams.pop().unwrap();
+        let first = streams.pop().unwrap();
+        (first, second)
+    }
+
     /// Given two [`Stream`]s, zip their elements together: the resulting stream will be a stream of
     /// pairs, each of which is an element from both streams respectively.
     ///
@@ -1869,6 +2687,10 @@ where
     /// If the stream is distributed among multiple replicas, parallelism will
     /// be set to 1 to gather all results
     ///
+    /// Each item is sent to the channel as soon as it reaches the end of the stream, so the
+    /// channel can be read while the job is running (e.g. running `execute_blocking` in another
+    /// thread) to process the results incrementally. The channel is closed when the stream ends.
+    ///
     /// **Note**: the order of items and keys is unspecified.
     ///
     /// **Note**: this operator will split the current block.
@@ -1882,12 +2704,11 @@ where
     /// let s = env.stream_iter(0..10u32);
     /// let rx = s.collect_channel();
     ///
-    /// env.execute_blocking();
-    /// let mut v = Vec::new();
-    /// while let Ok(x) = rx.recv() {
-    ///     v.push(x)
-    /// }
+    /// let job = std::thread::spawn(move || env.execute_blocking());
+    /// // the items are received while the job is running
+    /// let v: Vec<_> = rx.iter().collect();
     /// assert_eq!(v, (0..10u32).collect::<Vec<_>>());
+    /// job.join().unwrap();
     /// ```
     pub fn collect_channel(self) -> Receiver<I> {
         let (tx, rx) = unbounded();
@@ -1988,6 +2809,68 @@ where
         StreamOutput::from(output)
     }
 
+    /// Close the stream and store all the resulting items into a [`Vec`] on a single host, sorted
+    /// with the comparator function `cmp`.
+    ///
+    /// Unlike [`Stream::collect_vec`], the result does not depend on how the items of the
+    /// replicas are interleaved, which makes it handy for checking the output of a job in the
+    /// tests. The items are sorted as in [`Stream::sort_by`], so the stream must be bounded.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_par_iter(0..10).shuffle();
+    /// let res = s.collect_vec_sorted(|a: &i32, b| a.cmp(b));
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), (0..10).collect::<Vec<_>>());
+    /// ```
+    pub fn collect_vec_sorted<F>(self, cmp: F) -> StreamOutput<Vec<I>>
+    where
+        F: Fn(&I, &I) -> std::cmp::Ordering + Clone + Send + 'static,
+    {
+        self.sort_by(cmp).collect_vec()
+    }
+
+    /// Close the stream and store all the resulting items into a [`Vec`] on a single host, in the
+    /// order they are produced.
+    ///
+    /// The order is deterministic only if the items come from a single replica, so this panics
+    /// if the current block may have more than one (e.g. after a parallel source or a
+    /// [`Stream::shuffle`]). Use [`Stream::replication`] with [`Replication::One`] to gather the
+    /// items before, or [`Stream::collect_vec_sorted`] to sort them.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..10);
+    /// let res = s.map(|n| n * 2).collect_vec_ordered();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), (0..10).map(|n| n * 2).collect::<Vec<_>>());
+    /// ```
+    pub fn collect_vec_ordered(self) -> StreamOutput<Vec<I>> {
+        assert!(
+            matches!(
+                self.block.scheduling.replication,
+                Replication::One | Replication::Limited(1)
+            ),
+            "collect_vec_ordered requires a single replica, but the block has replication {:?}",
+            self.block.scheduling.replication
+        );
+        self.collect_vec()
+    }
+
     /// Close the stream and store all the resulting items into a [`Vec`] on a single host.
     ///
     /// If the stream is distributed among multiple replicas, a bottleneck is placed where all the
@@ -2595,6 +3478,26 @@ where
             .map(|(_, x)| x.unwrap())
     }
 
+    /// Remove the elements equal to the one immediately before them with the same key, collapsing
+    /// each run of equal values of a key into its first one.
+    ///
+    /// This is exactly like [`Stream::distinct_until_changed`], but the last value is kept for
+    /// each key.
+    pub fn distinct_until_changed(self) -> KeyedStream<impl Operator<Out = (K, I)>>
+    where
+        I: Data + PartialEq,
+    {
+        let mut last = None;
+        self.rich_filter_map(move |(_, el)| {
+            if last.as_ref() == Some(&el) {
+                None
+            } else {
+                last = Some(el.clone());
+                Some(el)
+            }
+        })
+    }
+
     /// Map the elements of the stream into new elements. The mapping function can be stateful.
     ///
     /// This is exactly like [`Stream::rich_map`], but the function is cloned for each key. This
@@ -2708,10 +3611,20 @@ where
 
     /// Merge the items of this stream with the items of another stream with the same type.
     ///
+    /// The two streams must be partitioned the same way: each replica receives the items of both
+    /// streams with its keys, without shuffling them again, so the keyed operators that follow see
+    /// the items of each key of both streams. The watermark of the merged stream is the minimum of
+    /// the watermarks of the two streams.
+    ///
     /// **Note**: the order of the resulting items is not specified.
     ///
     /// **Note**: this operator will split the current block.
     ///
+    /// ## Panics
+    ///
+    /// If the two streams have a different replication, or were grouped with a different
+    /// [`GroupByHasher`](crate::GroupByHasher).
+    ///
     /// ## Example
     ///
     /// ```
@@ -2732,6 +3645,16 @@ where
     where
         Op2: Operator<Out = (K, I)> + 'static,
     {
+        let (left, right) = (&self.0.block.group_by_hasher, &oth.0.block.group_by_hasher);
+        assert!(
+            left.same_as(right),
+            "The keyed streams to merge must be partitioned the same way. On the left ({}) the \
+            group-by hasher is {:?}, on the right ({}) is {:?}",
+            self.0.block,
+            left,
+            oth.0.block,
+            right
+        );
         KeyedStream(self.0.merge(oth.0))
     }
 
@@ -0,0 +1,556 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use crate::block::{BlockStructure, GroupHasherBuilder, NextStrategy, OperatorStructure};
+use crate::operator::end::End;
+use crate::operator::{
+    fmt_stage, Data, DataKey, ExchangeData, ExchangeDataKey, Operator, StreamElement, Timestamp,
+};
+use crate::scheduler::ExecutionMetadata;
+use crate::stream::KeyedStream;
+
+/// How often the block of a [`KeyedStream::process`] wakes up to fire the processing time
+/// timers.
+const PROCESSING_TIMER_TICK: Duration = Duration::from_millis(50);
+
+/// A timer registered by a [`ProcessFunction`] for a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Timer {
+    /// Fires when the watermark reaches the timestamp.
+    EventTime(Timestamp),
+    /// Fires when the wall-clock time reaches the instant.
+    ProcessingTime(Instant),
+}
+
+impl Timer {
+    /// A processing time timer that fires after `delay` from now.
+    pub fn after(delay: Duration) -> Self {
+        Timer::ProcessingTime(Instant::now() + delay)
+    }
+}
+
+/// A function called by [`KeyedStream::process`] for each element of a key, with a mutable state
+/// of the key and the ability to register timers that call it back later.
+///
+/// This is the general-purpose primitive to implement custom temporal logic, like detecting the
+/// events that are not followed by another one in time.
+pub trait ProcessFunction<K, I>: Clone + Send + 'static {
+    /// The state kept for each key, created with [`Default`] when the key is first seen.
+    type State: Default + Clone + Send + 'static;
+    /// The type of the elements emitted.
+    type Out: Data;
+
+    /// Process an element of the key of `ctx`.
+    fn process_element(
+        &mut self,
+        item: I,
+        state: &mut Self::State,
+        ctx: &mut ProcessContext<K, Self::Out>,
+    );
+
+    /// Called when a timer registered for the key of `ctx` fires.
+    ///
+    /// By default it does nothing.
+    fn on_timer(
+        &mut self,
+        timer: Timer,
+        state: &mut Self::State,
+        ctx: &mut ProcessContext<K, Self::Out>,
+    ) {
+        let _ = (timer, state, ctx);
+    }
+}
+
+/// The pending timers of a [`Process`] operator, with the keys that registered them.
+#[derive(Clone, Debug)]
+struct Timers<K> {
+    event_time: BTreeMap<Timestamp, Vec<K>>,
+    processing_time: BTreeMap<Instant, Vec<K>>,
+}
+
+impl<K> Default for Timers<K> {
+    fn default() -> Self {
+        Self {
+            event_time: Default::default(),
+            processing_time: Default::default(),
+        }
+    }
+}
+
+impl<K: DataKey> Timers<K> {
+    fn register(&mut self, key: &K, timer: Timer) {
+        let keys = match timer {
+            Timer::EventTime(ts) => self.event_time.entry(ts).or_default(),
+            Timer::ProcessingTime(at) => self.processing_time.entry(at).or_default(),
+        };
+        // a key has at most one timer for each time
+        if !keys.contains(key) {
+            keys.push(key.clone());
+        }
+    }
+
+    fn delete(&mut self, key: &K, timer: Timer) {
+        fn remove<T: Ord, K: Eq>(timers: &mut BTreeMap<T, Vec<K>>, time: T, key: &K) {
+            if let Some(keys) = timers.get_mut(&time) {
+                keys.retain(|k| k != key);
+                if keys.is_empty() {
+                    timers.remove(&time);
+                }
+            }
+        }
+        match timer {
+            Timer::EventTime(ts) => remove(&mut self.event_time, ts, key),
+            Timer::ProcessingTime(at) => remove(&mut self.processing_time, at, key),
+        }
+    }
+
+    /// Remove the earliest event time timer up to `watermark`, or any if `None`.
+    fn pop_event_time(&mut self, watermark: Option<Timestamp>) -> Option<(Timestamp, K)> {
+        pop_first(&mut self.event_time, watermark)
+    }
+
+    /// Remove the earliest processing time timer up to `now`.
+    fn pop_processing_time(&mut self, now: Instant) -> Option<(Instant, K)> {
+        pop_first(&mut self.processing_time, Some(now))
+    }
+}
+
+/// Remove the key of the earliest timer, if it is not after `up_to`.
+fn pop_first<T: Ord + Copy, K>(
+    timers: &mut BTreeMap<T, Vec<K>>,
+    up_to: Option<T>,
+) -> Option<(T, K)> {
+    let mut entry = timers.first_entry()?;
+    let time = *entry.key();
+    if up_to.is_some_and(|up_to| time > up_to) {
+        return None;
+    }
+    let key = entry.get_mut().pop().unwrap();
+    if entry.get().is_empty() {
+        entry.remove();
+    }
+    Some((time, key))
+}
+
+/// The context of a call of a [`ProcessFunction`], to emit elements and manage the timers of the
+/// current key.
+pub struct ProcessContext<'a, K, O> {
+    key: &'a K,
+    timestamp: Option<Timestamp>,
+    watermark: Option<Timestamp>,
+    timers: &'a mut Timers<K>,
+    output: &'a mut VecDeque<StreamElement<(K, O)>>,
+    clear_state: bool,
+}
+
+impl<K: DataKey, O> ProcessContext<'_, K, O> {
+    /// The key of the element or of the timer.
+    pub fn key(&self) -> &K {
+        self.key
+    }
+
+    /// The timestamp of the element, or of the event time timer that fired.
+    ///
+    /// `None` for the elements without a timestamp and for the processing time timers.
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        self.timestamp
+    }
+
+    /// The last watermark received by the operator, if any.
+    pub fn watermark(&self) -> Option<Timestamp> {
+        self.watermark
+    }
+
+    /// Emit an element for the current key, with the timestamp of the context.
+    pub fn emit(&mut self, value: O) {
+        let item = (self.key.clone(), value);
+        self.output.push_back(match self.timestamp {
+            Some(ts) => StreamElement::Timestamped(item, ts),
+            None => StreamElement::Item(item),
+        });
+    }
+
+    /// Register a timer for the current key. Registering the same timer twice has no effect.
+    pub fn register_timer(&mut self, timer: Timer) {
+        self.timers.register(self.key, timer);
+    }
+
+    /// Delete a timer of the current key, if it has not fired yet.
+    pub fn delete_timer(&mut self, timer: Timer) {
+        self.timers.delete(self.key, timer);
+    }
+
+    /// Drop the state of the current key after this call, freeing its memory. The timers of the
+    /// key are kept.
+    pub fn clear_state(&mut self) {
+        self.clear_state = true;
+    }
+}
+
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+struct Process<K, V, F, Op>
+where
+    K: DataKey,
+    F: ProcessFunction<K, V>,
+    Op: Operator<Out = (K, V)>,
+{
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    f: F,
+    #[derivative(Debug = "ignore")]
+    states: HashMap<K, F::State, GroupHasherBuilder>,
+    #[derivative(Debug = "ignore")]
+    timers: Timers<K>,
+    watermark: Option<Timestamp>,
+    /// The elements emitted by the function, to return before pulling from `prev` again.
+    #[derivative(Debug = "ignore")]
+    ready: VecDeque<StreamElement<(K, F::Out)>>,
+}
+
+impl<K, V, F, Op> Process<K, V, F, Op>
+where
+    K: DataKey,
+    F: ProcessFunction<K, V>,
+    Op: Operator<Out = (K, V)>,
+{
+    fn new(prev: Op, f: F) -> Self {
+        Self {
+            prev,
+            f,
+            states: Default::default(),
+            timers: Default::default(),
+            watermark: None,
+            ready: Default::default(),
+        }
+    }
+
+    /// Call the function with the state of `key`, dropping the state if the function asked to.
+    fn call(
+        &mut self,
+        key: K,
+        timestamp: Option<Timestamp>,
+        call: impl FnOnce(&mut F, &mut F::State, &mut ProcessContext<K, F::Out>),
+    ) {
+        let state = self.states.entry(key.clone()).or_default();
+        let mut ctx = ProcessContext {
+            key: &key,
+            timestamp,
+            watermark: self.watermark,
+            timers: &mut self.timers,
+            output: &mut self.ready,
+            clear_state: false,
+        };
+        call(&mut self.f, state, &mut ctx);
+        if ctx.clear_state {
+            self.states.remove(&key);
+        }
+    }
+
+    /// Fire the event time timers up to `watermark`, or all of them if `None`.
+    fn fire_event_time(&mut self, watermark: Option<Timestamp>) {
+        while let Some((ts, key)) = self.timers.pop_event_time(watermark) {
+            self.call(key, Some(ts), |f, state, ctx| {
+                f.on_timer(Timer::EventTime(ts), state, ctx)
+            });
+        }
+    }
+
+    /// Fire the processing time timers that expired.
+    fn fire_processing_time(&mut self) {
+        if self.timers.processing_time.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        while let Some((at, key)) = self.timers.pop_processing_time(now) {
+            self.call(key, None, |f, state, ctx| {
+                f.on_timer(Timer::ProcessingTime(at), state, ctx)
+            });
+        }
+    }
+}
+
+impl<K, V, F, Op> Display for Process<K, V, F, Op>
+where
+    K: DataKey,
+    F: ProcessFunction<K, V>,
+    Op: Operator<Out = (K, V)>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, (K, F::Out)>(f, &self.prev, "Process")
+    }
+}
+
+impl<K, V, F, Op> Operator for Process<K, V, F, Op>
+where
+    K: DataKey,
+    V: Data,
+    F: ProcessFunction<K, V>,
+    Op: Operator<Out = (K, V)>,
+{
+    type Out = (K, F::Out);
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        loop {
+            if let Some(el) = self.ready.pop_front() {
+                return el;
+            }
+            match self.prev.next() {
+                StreamElement::Item((key, value)) => {
+                    self.call(key, None, |f, state, ctx| {
+                        f.process_element(value, state, ctx)
+                    });
+                }
+                StreamElement::Timestamped((key, value), ts) => {
+                    self.call(key, Some(ts), |f, state, ctx| {
+                        f.process_element(value, state, ctx)
+                    });
+                }
+                StreamElement::Watermark(ts) => {
+                    self.watermark = Some(ts);
+                    self.fire_event_time(Some(ts));
+                    self.ready.push_back(StreamElement::Watermark(ts));
+                }
+                StreamElement::FlushBatch => {
+                    self.fire_processing_time();
+                    self.ready.push_back(StreamElement::FlushBatch);
+                }
+                // the end of the stream moves the event time to the end, the processing time
+                // timers are dropped
+                StreamElement::Terminate => {
+                    self.fire_event_time(None);
+                    self.ready.push_back(StreamElement::Terminate);
+                }
+                StreamElement::FlushAndRestart => {
+                    self.fire_event_time(None);
+                    self.states.clear();
+                    self.timers = Default::default();
+                    self.watermark = None;
+                    self.ready.push_back(StreamElement::FlushAndRestart);
+                }
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<Self::Out, _>("Process"))
+    }
+}
+
+impl<K, V, Op> KeyedStream<Op>
+where
+    K: ExchangeDataKey,
+    V: ExchangeData,
+    Op: Operator<Out = (K, V)> + 'static,
+{
+    /// Process the elements of each key with a [`ProcessFunction`], which keeps a mutable state for
+    /// each key and can register timers that call it back later.
+    ///
+    /// An event time timer fires when the watermark reaches its timestamp, and the elements
+    /// emitted by the timer have that timestamp. When the stream ends all the pending event time
+    /// timers fire, as if the watermark reached the end of time. A processing time timer fires
+    /// when the block is woken up after its instant, at most every 50 milliseconds if the stream is
+    /// idle, and its elements have no timestamp. The processing time timers still pending at the
+    /// end of the stream are dropped.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// Emit the deadline of each `"open"` not followed by a `"close"` of the same key within 5
+    /// time units.
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// use renoir::operator::{ProcessContext, ProcessFunction, Timer};
+    /// # let mut env = StreamContext::new_local();
+    ///
+    /// #[derive(Clone)]
+    /// struct Timeout;
+    ///
+    /// impl ProcessFunction<u32, String> for Timeout {
+    ///     type State = Option<i64>;
+    ///     type Out = i64;
+    ///
+    ///     fn process_element(
+    ///         &mut self,
+    ///         event: String,
+    ///         deadline: &mut Option<i64>,
+    ///         ctx: &mut ProcessContext<u32, i64>,
+    ///     ) {
+    ///         match event.as_str() {
+    ///             "open" => {
+    ///                 let ts = ctx.timestamp().unwrap() + 5;
+    ///                 ctx.register_timer(Timer::EventTime(ts));
+    ///                 *deadline = Some(ts);
+    ///             }
+    ///             _ => {
+    ///                 if let Some(ts) = deadline.take() {
+    ///                     ctx.delete_timer(Timer::EventTime(ts));
+    ///                 }
+    ///                 ctx.clear_state();
+    ///             }
+    ///         }
+    ///     }
+    ///
+    ///     fn on_timer(
+    ///         &mut self,
+    ///         _timer: Timer,
+    ///         deadline: &mut Option<i64>,
+    ///         ctx: &mut ProcessContext<u32, i64>,
+    ///     ) {
+    ///         ctx.emit(deadline.unwrap());
+    ///         ctx.clear_state();
+    ///     }
+    /// }
+    ///
+    /// let events: [(u32, &str, i64); 4] =
+    ///     [(1, "open", 0), (2, "open", 1), (1, "close", 3), (3, "open", 4)];
+    /// let res = env
+    ///     .stream_iter(events.map(|(key, event, ts)| (key, event.to_string(), ts)))
+    ///     .add_timestamps(|&(_, _, ts)| ts, |_, _| None)
+    ///     .group_by(|&(key, _, _)| key)
+    ///     .map(|(_, (_, event, _))| event)
+    ///     .process(Timeout)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, vec![(2, 6), (3, 9)]);
+    /// ```
+    pub fn process<F>(self, f: F) -> KeyedStream<impl Operator<Out = (K, F::Out)>>
+    where
+        F: ProcessFunction<K, V>,
+    {
+        // keep the same parallelism of the previous block, and so the same keys in each replica
+        let scheduler_requirements = self.0.block.scheduling.clone();
+        let mut new_stream = self.0.split_block(End::new, NextStrategy::only_one());
+        new_stream.block.scheduling = scheduler_requirements;
+        // wake up the new block to fire the processing time timers
+        new_stream.block.tick = Some(PROCESSING_TIMER_TICK);
+        KeyedStream(new_stream.add_operator(|prev| Process::new(prev, f)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::operator::process::{Process, ProcessContext, ProcessFunction, Timer};
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+
+    /// Alert if an `A` of a key is not followed by a `B` within `timeout`.
+    #[cfg(feature = "timestamp")]
+    #[derive(Clone)]
+    struct MissingFollowUp {
+        timeout: i64,
+    }
+
+    #[cfg(feature = "timestamp")]
+    impl ProcessFunction<u8, char> for MissingFollowUp {
+        type State = Option<i64>;
+        type Out = String;
+
+        fn process_element(
+            &mut self,
+            event: char,
+            deadline: &mut Option<i64>,
+            ctx: &mut ProcessContext<u8, String>,
+        ) {
+            match event {
+                'A' => {
+                    let ts = ctx.timestamp().unwrap() + self.timeout;
+                    ctx.register_timer(Timer::EventTime(ts));
+                    *deadline = Some(ts);
+                }
+                'B' => {
+                    if let Some(ts) = deadline.take() {
+                        ctx.delete_timer(Timer::EventTime(ts));
+                    }
+                    ctx.clear_state();
+                }
+                _ => {}
+            }
+        }
+
+        fn on_timer(
+            &mut self,
+            timer: Timer,
+            deadline: &mut Option<i64>,
+            ctx: &mut ProcessContext<u8, String>,
+        ) {
+            assert_eq!(Some(timer), deadline.map(Timer::EventTime));
+            let key = *ctx.key();
+            ctx.emit(format!("no B after A for key {key}"));
+            ctx.clear_state();
+        }
+    }
+
+    #[cfg(feature = "timestamp")]
+    #[test]
+    fn process_timeout_detection() {
+        let mut prev = FakeOperator::empty();
+        prev.push(StreamElement::Timestamped((1, 'A'), 0));
+        prev.push(StreamElement::Timestamped((2, 'A'), 2));
+        prev.push(StreamElement::Timestamped((1, 'B'), 3));
+        prev.push(StreamElement::Watermark(6));
+        prev.push(StreamElement::Timestamped((3, 'A'), 8));
+        prev.push(StreamElement::Watermark(10));
+        let mut process = Process::new(prev, MissingFollowUp { timeout: 5 });
+        process.setup(&mut FakeNetworkTopology::<(u8, char)>::new(0, 0).metadata());
+
+        // the deadline of key 1 was deleted by its B
+        assert_eq!(process.next(), StreamElement::Watermark(6));
+        assert_eq!(
+            process.next(),
+            StreamElement::Timestamped((2, "no B after A for key 2".into()), 7)
+        );
+        assert_eq!(process.next(), StreamElement::Watermark(10));
+        // the end of the stream fires the timers still pending
+        assert_eq!(
+            process.next(),
+            StreamElement::Timestamped((3, "no B after A for key 3".into()), 13)
+        );
+        assert_eq!(process.next(), StreamElement::Terminate);
+        assert!(process.states.is_empty());
+    }
+
+    #[derive(Clone)]
+    struct Echo;
+
+    impl ProcessFunction<u8, u8> for Echo {
+        type State = u8;
+        type Out = u8;
+
+        fn process_element(&mut self, item: u8, state: &mut u8, ctx: &mut ProcessContext<u8, u8>) {
+            *state = item;
+            ctx.register_timer(Timer::after(Duration::ZERO));
+        }
+
+        fn on_timer(&mut self, _timer: Timer, state: &mut u8, ctx: &mut ProcessContext<u8, u8>) {
+            ctx.emit(*state);
+        }
+    }
+
+    #[test]
+    fn process_processing_time_timer() {
+        let mut prev = FakeOperator::new([(0, 7)].into_iter());
+        prev.push(StreamElement::FlushBatch);
+        let mut process = Process::new(prev, Echo);
+        process.setup(&mut FakeNetworkTopology::<(u8, u8)>::new(0, 0).metadata());
+
+        assert_eq!(process.next(), StreamElement::Item((0, 7)));
+        assert_eq!(process.next(), StreamElement::FlushBatch);
+        assert_eq!(process.next(), StreamElement::Terminate);
+    }
+}
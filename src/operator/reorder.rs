@@ -3,7 +3,7 @@ use std::collections::VecDeque;
 use std::fmt::Display;
 
 use crate::block::{BlockStructure, OperatorStructure};
-use crate::operator::{Operator, StreamElement, Timestamp};
+use crate::operator::{fmt_stage, Operator, StreamElement, Timestamp};
 use crate::scheduler::ExecutionMetadata;
 
 #[derive(Clone)]
@@ -67,12 +67,7 @@ where
     Op::Out: Send,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> Reorder<{}>",
-            self.prev,
-            std::any::type_name::<Op::Out>(),
-        )
+        fmt_stage::<Op::Out, Op::Out>(f, &self.prev, "Reorder")
     }
 }
 
@@ -1,9 +1,14 @@
 #[cfg(test)]
 mod tests {
-    use crate::block::Replication;
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+
+    use crate::block::{GroupByHasher, Replication};
     use crate::config::RuntimeConfig;
     use crate::environment::StreamContext;
+    use crate::network::Coord;
     use crate::test::FakeOperator;
+    use crate::worker::replica_coord;
 
     #[test]
     fn test_replication() {
@@ -19,5 +24,71 @@ mod tests {
         );
         assert_ne!(old_block_id, new_block_id);
     }
+
+    #[test]
+    fn test_set_parallelism() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let seen = Arc::new(Mutex::new(HashSet::<Coord>::new()));
+        let record = |seen: &Arc<Mutex<HashSet<Coord>>>| {
+            let seen = seen.clone();
+            move |_: &u32| {
+                seen.lock().unwrap().insert(replica_coord().unwrap());
+            }
+        };
+        let before = env.stream_par_iter(0..1000u32).inspect(record(&seen));
+        let before_id = before.block.id;
+        let limited = before.set_parallelism(1).inspect(record(&seen));
+        let limited_id = limited.block.id;
+        let after = limited.shuffle().inspect(record(&seen));
+        let after_id = after.block.id;
+        let res = after.collect_vec();
+        env.execute_blocking();
+
+        assert_eq!(res.get().unwrap().len(), 1000);
+        let seen = seen.lock().unwrap();
+        let replicas = |block_id| seen.iter().filter(|c| c.block_id == block_id).count();
+        assert_eq!(replicas(before_id), 4);
+        assert_eq!(replicas(limited_id), 1);
+        assert_eq!(replicas(after_id), 4);
+    }
+
+    #[test]
+    fn test_repartition_range() {
+        let env = StreamContext::new(RuntimeConfig::local(3).unwrap());
+        let res = env
+            .stream_par_iter(0..30u32)
+            .repartition_range(|&n| n, vec![10, 20])
+            .map(|n| (n, replica_coord().unwrap().replica_id))
+            .collect_vec();
+        env.execute_blocking();
+
+        let mut res = res.get().unwrap();
+        res.sort();
+        let expected = (0..30).map(|n| (n, n as u64 / 10)).collect::<Vec<_>>();
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn test_group_by_hasher() {
+        let assignment = |hasher: GroupByHasher| {
+            let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+            let res = env
+                .stream_iter(0..100u32)
+                .group_by_hasher(hasher)
+                .group_by(|&n| n)
+                .map(|_| replica_coord().unwrap().replica_id)
+                .unkey()
+                .collect_vec();
+            env.execute_blocking();
+            let mut res = res.get().unwrap();
+            res.sort();
+            res
+        };
+
+        let seeded = assignment(GroupByHasher::SipHash(1, 2));
+        assert_eq!(seeded, assignment(GroupByHasher::SipHash(1, 2)));
+        assert_ne!(seeded, assignment(GroupByHasher::SipHash(3, 4)));
+        assert_ne!(seeded, assignment(GroupByHasher::Default));
+    }
 }
 // TODO: Actual meaningful tests
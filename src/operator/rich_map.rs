@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
 use crate::block::{BlockStructure, OperatorStructure};
-use crate::operator::{DataKey, Operator, StreamElement};
+use crate::operator::{fmt_stage, DataKey, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 
 #[derive(Debug)]
@@ -15,6 +16,11 @@ where
     prev: OperatorChain,
     maps_fn: HashMap<K, F, crate::block::GroupHasherBuilder>,
     init_map: F,
+    /// Time to live of the state of each key, if the state expires.
+    ttl: Option<Duration>,
+    /// When each key was last seen, only tracked if the state expires.
+    last_access: HashMap<K, Instant, crate::block::GroupHasherBuilder>,
+    last_scan: Instant,
     _i: PhantomData<I>,
     _o: PhantomData<O>,
 }
@@ -29,6 +35,9 @@ where
             prev: self.prev.clone(),
             maps_fn: self.maps_fn.clone(),
             init_map: self.init_map.clone(),
+            ttl: self.ttl,
+            last_access: self.last_access.clone(),
+            last_scan: self.last_scan,
             _i: self._i,
             _o: self._o,
         }
@@ -41,13 +50,7 @@ where
     OperatorChain: Operator<Out = (K, I)>,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> RichMap<{} -> {}>",
-            self.prev,
-            std::any::type_name::<I>(),
-            std::any::type_name::<O>()
-        )
+        fmt_stage::<(K, I), (K, O)>(f, &self.prev, "RichMap")
     }
 }
 
@@ -61,10 +64,38 @@ where
             prev,
             maps_fn: Default::default(),
             init_map: f,
+            ttl: None,
+            last_access: Default::default(),
+            last_scan: Instant::now(),
             _i: Default::default(),
             _o: Default::default(),
         }
     }
+
+    /// Drop the state of the keys that have not been seen for longer than `ttl`.
+    pub(super) fn with_ttl(prev: OperatorChain, f: F, ttl: Duration) -> Self {
+        Self {
+            ttl: Some(ttl),
+            ..Self::new(prev, f)
+        }
+    }
+
+    /// Evict the expired keys. The whole state is scanned at most twice per TTL, so a key is
+    /// evicted between `ttl` and `1.5 * ttl` after it was last seen.
+    fn evict_expired(&mut self, now: Instant, ttl: Duration) {
+        if now.duration_since(self.last_scan) < ttl / 2 {
+            return;
+        }
+        self.last_scan = now;
+        let maps_fn = &mut self.maps_fn;
+        self.last_access.retain(|key, last| {
+            let alive = now.duration_since(*last) < ttl;
+            if !alive {
+                maps_fn.remove(key);
+            }
+            alive
+        });
+    }
 }
 
 impl<K: DataKey, I: Send, O: Send, F, OperatorChain> Operator for RichMap<K, I, O, F, OperatorChain>
@@ -87,7 +118,15 @@ where
         if matches!(element, StreamElement::FlushAndRestart) {
             // self.maps_fn.clear();
         }
+        let now = self.ttl.map(|ttl| {
+            let now = Instant::now();
+            self.evict_expired(now, ttl);
+            now
+        });
         element.map(|(key, value)| {
+            if let Some(now) = now {
+                self.last_access.insert(key.clone(), now);
+            }
             let map_fn = if let Some(map_fn) = self.maps_fn.get_mut(&key) {
                 map_fn
             } else {
@@ -107,3 +146,34 @@ where
             .add_operator(OperatorStructure::new::<O, _>("RichMap"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::operator::rich_map::RichMap;
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::FakeOperator;
+
+    #[test]
+    fn rich_map_state_ttl() {
+        let fake_operator = FakeOperator::new([(0, ()), (0, ()), (1, ()), (0, ())].into_iter());
+        let mut count = 0;
+        let mut map = RichMap::with_ttl(
+            fake_operator,
+            move |(_, ())| {
+                count += 1;
+                count
+            },
+            Duration::from_millis(50),
+        );
+
+        assert_eq!(map.next(), StreamElement::Item((0, 1)));
+        assert_eq!(map.next(), StreamElement::Item((0, 2)));
+        std::thread::sleep(Duration::from_millis(100));
+        // the state of key 0 expired, while key 1 has never been seen
+        assert_eq!(map.next(), StreamElement::Item((1, 1)));
+        assert_eq!(map.next(), StreamElement::Item((0, 1)));
+        assert_eq!(map.next(), StreamElement::Terminate);
+    }
+}
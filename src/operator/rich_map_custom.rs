@@ -2,7 +2,7 @@ use std::fmt::Display;
 use std::marker::PhantomData;
 
 use crate::block::{BlockStructure, OperatorStructure};
-use crate::operator::{Operator, StreamElement};
+use crate::operator::{fmt_stage, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 
 pub struct ElementGenerator<'a, Op> {
@@ -51,13 +51,7 @@ where
     Op: Operator,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} -> RichMapCustom<{} -> {}>",
-            self.prev,
-            std::any::type_name::<Op::Out>(),
-            std::any::type_name::<O>()
-        )
+        fmt_stage::<Op::Out, O>(f, &self.prev, "RichMapCustom")
     }
 }
 
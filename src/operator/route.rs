@@ -60,6 +60,7 @@ impl<Out: ExchangeData, OperatorChain: Operator<Out = Out> + 'static>
         let mut ctx_lock = ctx.lock();
         let scheduler_requirements = self.stream.block.scheduling.clone();
         let batch_mode = self.stream.block.batch_mode;
+        let unbounded = self.stream.block.unbounded;
         let block_id = self.stream.block.id;
         let iteration_context = self.stream.block.iteration_ctx.clone();
 
@@ -83,6 +84,7 @@ impl<Out: ExchangeData, OperatorChain: Operator<Out = Out> + 'static>
         for new_block in &mut new_blocks {
             ctx_lock.connect_blocks::<Out>(block_id, new_block.id);
             new_block.scheduling = scheduler_requirements.clone();
+            new_block.unbounded = unbounded;
         }
 
         drop(ctx_lock);
@@ -162,7 +164,7 @@ where
             let indexes = block_map
                 .remove(&block_id)
                 .expect("scheduler connection missing for RoutingEnd");
-            let block_senders = BlockSenders { indexes };
+            let block_senders = BlockSenders::new(indexes);
             self.endpoints.push(Endpoint {
                 block_id,
                 filter,
@@ -279,6 +281,7 @@ where
 
     fn structure(&self) -> BlockStructure {
         let mut operator = OperatorStructure::new::<Out, _>("RoutingEnd");
+        operator.next_strategy = Some((&self.next_strategy).into());
         for e in &self.endpoints {
             if !e.block_senders.indexes.is_empty() {
                 let block_id = self.senders[e.block_senders.indexes[0]].0.coord.block_id;
@@ -0,0 +1,192 @@
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use crate::operator::window::{HyperLogLog, DEFAULT_PRECISION};
+use crate::operator::{DataKey, KeyedStateTtl, Operator};
+use crate::stream::KeyedStream;
+
+/// When [`KeyedStream::running_count_distinct_approx`] emits the estimate of a key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EstimateCadence {
+    /// After every element of the key, even if its estimate did not change.
+    EveryElement,
+    /// After the elements of the key that change its estimate.
+    #[default]
+    OnChange,
+    /// After the elements of the key that change its estimate, at most once every interval for
+    /// each key.
+    ///
+    /// The estimate is emitted only when an element of the key arrives, so the last changes of a
+    /// key that stops receiving elements within the interval are not reported.
+    Throttled(Duration),
+}
+
+/// The running estimate of the distinct elements of a key.
+#[derive(Clone, Debug)]
+struct RunningEstimate {
+    sketch: HyperLogLog,
+    cadence: EstimateCadence,
+    /// The current estimate, never lower than the previous ones.
+    estimate: u64,
+    /// The last estimate emitted, and when.
+    emitted: Option<(u64, Instant)>,
+}
+
+impl RunningEstimate {
+    fn new(cadence: EstimateCadence) -> Self {
+        Self {
+            sketch: HyperLogLog::new(DEFAULT_PRECISION),
+            cadence,
+            estimate: 0,
+            emitted: None,
+        }
+    }
+
+    /// Add an element to the sketch, returning the estimate if it has to be emitted.
+    fn add<T: Hash>(&mut self, item: &T) -> Option<u64> {
+        if self.sketch.insert(item) {
+            // switching from linear counting to the HyperLogLog estimate may lower it a bit
+            self.estimate = self.estimate.max(self.sketch.estimate());
+        }
+        let changed = self.emitted.is_none_or(|(e, _)| e != self.estimate);
+        let emit = match self.cadence {
+            EstimateCadence::EveryElement => true,
+            EstimateCadence::OnChange => changed,
+            EstimateCadence::Throttled(interval) => {
+                changed && self.emitted.is_none_or(|(_, t)| t.elapsed() >= interval)
+            }
+        };
+        if emit {
+            self.emitted = Some((self.estimate, Instant::now()));
+            Some(self.estimate)
+        } else {
+            None
+        }
+    }
+}
+
+impl<K, I, Op> KeyedStream<Op>
+where
+    K: DataKey,
+    I: Hash + Send + 'static,
+    Op: Operator<Out = (K, I)> + 'static,
+{
+    /// Estimate the number of distinct elements of each key since the start of the stream,
+    /// emitting the updated estimate as the elements arrive.
+    ///
+    /// Unlike [`WindowedStream::approx_count_distinct`] there are no windows: each key keeps a
+    /// HyperLogLog sketch of 4 KiB for the whole stream, and the estimates have a standard error of
+    /// about 1.6%. The estimates of a key never decrease. The `cadence` chooses which elements
+    /// emit the estimate of their key, see [`EstimateCadence`].
+    ///
+    /// The sketches of the keys are kept forever: for an unbounded key space use
+    /// [`KeyedStream::with_state_ttl`] to drop the keys that stop appearing.
+    ///
+    /// [`WindowedStream::approx_count_distinct`]: crate::WindowedStream::approx_count_distinct
+    ///
+    /// ## Example
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// use renoir::operator::EstimateCadence;
+    /// # let mut env = StreamContext::new_local();
+    /// // (page, visitor)
+    /// let s = env.stream_iter((0..2000u32).map(|n| (n % 2, n % 200)));
+    /// let res = s
+    ///     .group_by(|&(page, _)| page)
+    ///     .map(|(_, (_, visitor))| visitor)
+    ///     .running_count_distinct_approx(EstimateCadence::OnChange)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let last = res.get().unwrap().into_iter().filter(|&(page, _)| page == 0).last();
+    /// let (_, count) = last.unwrap();
+    /// assert!(count.abs_diff(100) <= 5);
+    /// ```
+    pub fn running_count_distinct_approx(
+        self,
+        cadence: EstimateCadence,
+    ) -> KeyedStream<impl Operator<Out = (K, u64)>> {
+        let mut state = RunningEstimate::new(cadence);
+        self.rich_filter_map(move |(_, x)| state.add(&x))
+    }
+}
+
+impl<K, I, Op> KeyedStateTtl<Op>
+where
+    K: DataKey,
+    I: Hash + Send + 'static,
+    Op: Operator<Out = (K, I)> + 'static,
+{
+    /// Like [`KeyedStream::running_count_distinct_approx`], with the sketch of each key expiring
+    /// after the TTL.
+    pub fn running_count_distinct_approx(
+        self,
+        cadence: EstimateCadence,
+    ) -> KeyedStream<impl Operator<Out = (K, u64)>> {
+        let mut state = RunningEstimate::new(cadence);
+        self.rich_filter_map(move |(_, x)| state.add(&x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use super::{EstimateCadence, RunningEstimate};
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+
+    #[test]
+    fn running_estimate_cadence() {
+        let mut every = RunningEstimate::new(EstimateCadence::EveryElement);
+        let mut on_change = RunningEstimate::new(EstimateCadence::OnChange);
+        let mut throttled =
+            RunningEstimate::new(EstimateCadence::Throttled(Duration::from_secs(3600)));
+
+        assert_eq!(every.add(&1), Some(1));
+        assert_eq!(every.add(&1), Some(1));
+        assert_eq!(on_change.add(&1), Some(1));
+        assert_eq!(on_change.add(&1), None);
+        assert_eq!(on_change.add(&2), Some(2));
+        assert_eq!(throttled.add(&1), Some(1));
+        // the estimate changed, but the interval did not elapse yet
+        assert_eq!(throttled.add(&2), None);
+    }
+
+    #[test]
+    fn running_count_distinct_approx() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let distinct = [10u32, 1_000, 20_000];
+        let res = env
+            .stream_iter(
+                // every element is repeated twice, the keys are interleaved
+                (0..2 * distinct[2]).flat_map(move |i| (0..3).map(move |k| (k, i % distinct[k]))),
+            )
+            .group_by(|&(k, _)| k)
+            .map(|(_, (_, x))| x)
+            .running_count_distinct_approx(EstimateCadence::OnChange)
+            .collect_vec();
+        env.execute_blocking();
+
+        let mut estimates: HashMap<usize, Vec<u64>> = HashMap::new();
+        for (k, estimate) in res.get().unwrap() {
+            estimates.entry(k).or_default().push(estimate);
+        }
+        for (k, exact) in distinct.into_iter().enumerate() {
+            let estimates = &estimates[&k];
+            assert!(
+                estimates.windows(2).all(|w| w[0] < w[1]),
+                "the estimates of key {k} decreased"
+            );
+            // within 3 standard errors
+            let last = *estimates.last().unwrap() as f64;
+            let relative = (last - exact as f64).abs() / exact as f64;
+            assert!(
+                relative <= 3.0 * 1.04 / 64.0,
+                "estimated {last}, exact {exact}"
+            );
+        }
+    }
+}
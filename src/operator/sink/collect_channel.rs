@@ -1,7 +1,7 @@
 use std::fmt::Display;
 
 use crate::block::{BlockStructure, OperatorKind, OperatorStructure};
-use crate::operator::{ExchangeData, Operator, StreamElement};
+use crate::operator::{fmt_stage, ExchangeData, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 
 use flume::Sender;
@@ -29,7 +29,7 @@ where
     PreviousOperators: Operator<Out = Out>,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} -> CollectChannelSink", self.prev)
+        fmt_stage::<Out, Out>(f, &self.prev, "CollectChannelSink")
     }
 }
 
@@ -46,7 +46,10 @@ where
     fn next(&mut self) -> StreamElement<()> {
         match self.prev.next() {
             StreamElement::Item(t) | StreamElement::Timestamped(t, _) => {
-                let _ = self.tx.as_ref().map(|tx| tx.send(t));
+                // stop sending when nobody is reading the channel anymore
+                if matches!(self.tx.as_ref().map(|tx| tx.send(t)), Some(Err(_))) {
+                    self.tx = None;
+                }
                 StreamElement::Item(())
             }
             StreamElement::Watermark(w) => StreamElement::Watermark(w),
@@ -70,6 +73,7 @@ where
 mod tests {
     use itertools::Itertools;
 
+    use crate::block::BatchMode;
     use crate::config::RuntimeConfig;
     use crate::environment::StreamContext;
     use crate::operator::source;
@@ -86,4 +90,31 @@ mod tests {
         }
         assert_eq!(v, (0..10).collect_vec());
     }
+
+    #[test]
+    fn collect_channel_while_running() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let (ack_tx, ack_rx) = flume::unbounded();
+        // the source does not end until the first item has been read from the channel
+        let items = (0..10u8).inspect(move |&i| {
+            if i == 1 {
+                ack_rx.recv().unwrap();
+            }
+        });
+        // each item is sent as soon as it is produced
+        let rx = env
+            .stream_iter(items)
+            .batch_mode(BatchMode::single())
+            .collect_channel();
+        let job = std::thread::spawn(move || env.execute_blocking());
+
+        assert_eq!(rx.recv().unwrap(), 0);
+        assert!(!job.is_finished());
+        ack_tx.send(()).unwrap();
+        let rest = rx.iter().collect_vec();
+        assert_eq!(rest, (1..10).collect_vec());
+        // the channel is closed when the stream ends
+        assert!(rx.recv().is_err());
+        job.join().unwrap();
+    }
 }
@@ -3,7 +3,7 @@ use std::fmt::Display;
 use crate::block::{BlockStructure, OperatorKind, OperatorStructure};
 
 use crate::operator::sink::StreamOutputRef;
-use crate::operator::{Operator, StreamElement};
+use crate::operator::{fmt_stage, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 
 #[derive(Debug)]
@@ -34,7 +34,7 @@ where
     PreviousOperators: Operator<Out = usize>,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} -> CollectCountSink", self.prev)
+        fmt_stage::<usize, usize>(f, &self.prev, "CollectCountSink")
     }
 }
 
@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use crate::block::{BlockStructure, OperatorKind, OperatorStructure};
 use crate::operator::sink::StreamOutputRef;
-use crate::operator::{ExchangeData, Operator, StreamElement};
+use crate::operator::{fmt_stage, ExchangeData, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 
 #[derive(Debug)]
@@ -33,7 +33,7 @@ where
     PreviousOperators: Operator<Out = Out>,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} -> CollectVecSink", self.prev)
+        fmt_stage::<Out, Out>(f, &self.prev, "CollectVecSink")
     }
 }
 
@@ -100,4 +100,37 @@ mod tests {
         env.execute_blocking();
         assert_eq!(res.get().unwrap(), (0..10).collect_vec());
     }
+
+    #[test]
+    fn collect_vec_sorted_shuffled() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env
+            .stream_par_iter(0..10_000u32)
+            .shuffle()
+            .map(|n| n * 3 % 10_000)
+            .collect_vec_sorted(|a, b| a.cmp(b));
+        env.execute_blocking();
+        assert_eq!(res.get().unwrap(), (0..10_000).collect_vec());
+    }
+
+    #[test]
+    fn collect_vec_ordered() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env
+            .stream_iter((0..1000u32).rev())
+            .map(|n| n * 2)
+            .collect_vec_ordered();
+        env.execute_blocking();
+        assert_eq!(
+            res.get().unwrap(),
+            (0..1000).rev().map(|n| n * 2).collect_vec()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "collect_vec_ordered requires a single replica")]
+    fn collect_vec_ordered_parallel() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        env.stream_par_iter(0..10u32).collect_vec_ordered();
+    }
 }
@@ -0,0 +1,184 @@
+use std::fmt::Display;
+use std::io::Write;
+
+use crate::block::{BlockStructure, OperatorKind, OperatorStructure, Replication};
+use crate::operator::sink::{StreamOutput, StreamOutputRef};
+use crate::operator::{fmt_stage, ExchangeData, Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// Sink that encodes every item to a writer as soon as it arrives, giving back the writer when
+/// the stream ends.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct CollectWriterSink<Op, W, F>
+where
+    Op: Operator,
+    W: Write + Send,
+    F: Fn(&Op::Out, &mut W) + Send,
+{
+    prev: Op,
+    /// The writer, until the stream ends.
+    #[derivative(Debug = "ignore")]
+    writer: Option<W>,
+    #[derivative(Debug = "ignore")]
+    encode: F,
+    output: StreamOutputRef<W>,
+}
+
+impl<Op, W, F> CollectWriterSink<Op, W, F>
+where
+    Op: Operator,
+    W: Write + Send,
+    F: Fn(&Op::Out, &mut W) + Send,
+{
+    pub(crate) fn new(prev: Op, writer: W, encode: F, output: StreamOutputRef<W>) -> Self {
+        Self {
+            prev,
+            writer: Some(writer),
+            encode,
+            output,
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(writer) = self.writer.as_mut() {
+            if let Err(e) = writer.flush() {
+                panic!("CollectWriterSink cannot flush the writer: {e}");
+            }
+        }
+    }
+}
+
+impl<Op, W, F> Display for CollectWriterSink<Op, W, F>
+where
+    Op: Operator,
+    W: Write + Send,
+    F: Fn(&Op::Out, &mut W) + Send,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, ()>(f, &self.prev, "CollectWriterSink")
+    }
+}
+
+impl<Op, W, F> Operator for CollectWriterSink<Op, W, F>
+where
+    Op: Operator,
+    W: Write + Send,
+    F: Fn(&Op::Out, &mut W) + Send,
+{
+    type Out = ();
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<()> {
+        match self.prev.next() {
+            StreamElement::Item(t) | StreamElement::Timestamped(t, _) => {
+                if let Some(writer) = self.writer.as_mut() {
+                    (self.encode)(&t, writer);
+                }
+                StreamElement::Item(())
+            }
+            StreamElement::Watermark(w) => StreamElement::Watermark(w),
+            StreamElement::FlushBatch => {
+                self.flush();
+                StreamElement::FlushBatch
+            }
+            StreamElement::FlushAndRestart => {
+                self.flush();
+                StreamElement::FlushAndRestart
+            }
+            StreamElement::Terminate => {
+                self.flush();
+                if let Some(writer) = self.writer.take() {
+                    *self.output.lock().unwrap() = Some(writer);
+                }
+                StreamElement::Terminate
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<Op::Out, _>("CollectWriterSink");
+        operator.kind = OperatorKind::Sink;
+        self.prev.structure().add_operator(operator)
+    }
+}
+
+impl<Op, W, F> Clone for CollectWriterSink<Op, W, F>
+where
+    Op: Operator,
+    W: Write + Send,
+    F: Fn(&Op::Out, &mut W) + Send,
+{
+    fn clone(&self) -> Self {
+        panic!("CollectWriterSink cannot be cloned, replication should be 1");
+    }
+}
+
+impl<Op: Operator> Stream<Op>
+where
+    Op: 'static,
+    Op::Out: ExchangeData,
+{
+    /// Write the items of the stream to `writer` as they arrive, encoding each one with `encode`.
+    ///
+    /// A single replica receives all the items and owns the writer, so any [`Write`] can be used
+    /// (a socket, a compressor, the standard output) without synchronization. The writer is
+    /// flushed at the end of each batch and when the stream ends, then it is given back in the
+    /// returned [`StreamOutput`]. `encode` writes an item to the writer, and should panic if the
+    /// writer fails.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let res = env
+    ///     .stream_iter(0..3)
+    ///     .collect_to_writer(Vec::new(), |n, w| writeln!(w, "{n}").unwrap());
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), b"0\n1\n2\n");
+    /// ```
+    pub fn collect_to_writer<W, F>(self, writer: W, encode: F) -> StreamOutput<W>
+    where
+        W: Write + Send + 'static,
+        F: Fn(&Op::Out, &mut W) + Send + 'static,
+    {
+        let output = StreamOutputRef::default();
+        self.replication(Replication::One)
+            .add_operator(|prev| CollectWriterSink::new(prev, writer, encode, output.clone()))
+            .finalize_block();
+        StreamOutput::from(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+
+    #[test]
+    fn collect_to_writer() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env
+            .stream_iter(0..1000u32)
+            .map(|n| n * 2)
+            .collect_to_writer(Vec::new(), |n, w| w.write_all(&n.to_le_bytes()).unwrap());
+        env.execute_blocking();
+
+        let expected = (0..1000u32)
+            .flat_map(|n| (n * 2).to_le_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(res.get().unwrap(), expected);
+    }
+}
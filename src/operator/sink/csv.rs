@@ -1,10 +1,11 @@
 use serde::Serialize;
 use std::fs::File;
-use std::io::BufWriter;
+use std::io::Write;
 use std::marker::PhantomData;
 use std::path::PathBuf;
 
 use crate::block::NextStrategy;
+use crate::operator::compression::{Compression, OutputCompression};
 use crate::operator::{ExchangeData, Operator};
 use crate::scheduler::ExecutionMetadata;
 use crate::{CoordUInt, Replication, Stream};
@@ -16,8 +17,10 @@ pub struct CsvWriteOp<T> {
     _t: PhantomData<T>,
     append: bool,
     path: Option<PathBuf>,
+    /// The compression of the files, detected from their extension if `None`.
+    compression: Option<OutputCompression>,
     /// Reader used to parse the CSV file.
-    writer: Option<csv::Writer<BufWriter<File>>>,
+    writer: Option<csv::Writer<Box<dyn Write + Send>>>,
 }
 
 impl<T> CsvWriteOp<T>
@@ -29,9 +32,16 @@ where
             _t: PhantomData,
             append,
             path: None,
+            compression: None,
             writer: None,
         }
     }
+
+    /// Compress the files with `compression`, whatever their extension.
+    pub fn with_compression(mut self, compression: OutputCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
 }
 
 impl<T> WriteOperator<T> for CsvWriteOp<T>
@@ -59,10 +69,14 @@ where
             });
         let file_len = file.metadata().unwrap().len();
 
-        let buf_writer = BufWriter::new(file);
+        let compression = match self.compression {
+            Some(compression) => Compression::from(compression),
+            None => Compression::from_path(self.path.as_ref().unwrap()),
+        };
+        let writer = compression.writer(file);
         let csv_writer = csv::WriterBuilder::default()
             .has_headers(file_len == 0)
-            .from_writer(buf_writer);
+            .from_writer(writer);
 
         self.writer = Some(csv_writer);
     }
@@ -88,6 +102,7 @@ impl<T> Clone for CsvWriteOp<T> {
             _t: PhantomData,
             append: self.append,
             path: None,
+            compression: self.compression,
             writer: None,
         }
     }
@@ -113,6 +128,9 @@ where
     }
 
     /// Write output to CSV files. A CSV is created for each replica of the current block.
+    ///
+    /// The files with the `.gz`, `.zst` and `.sz` extensions are compressed, with the
+    /// `compression` feature. See [`Stream::write_csv_seq_compressed`] to choose the compression.
     /// A file with a numerical suffix is created according to the path passed as parameter.
     ///
     /// + If the input is a directory numbered files will be created as output.
@@ -130,6 +148,21 @@ where
         })
         .finalize_block();
     }
+
+    /// Write output to CSV files like [`Stream::write_csv_seq`], compressed with `compression`
+    /// whatever their extension.
+    pub fn write_csv_seq_compressed(
+        self,
+        template_path: PathBuf,
+        append: bool,
+        compression: OutputCompression,
+    ) {
+        self.add_operator(|prev| {
+            let writer = CsvWriteOp::new(append).with_compression(compression);
+            WriterOperator::new(prev, writer, |m| sequential_path(template_path, m))
+        })
+        .finalize_block();
+    }
 }
 
 impl<Op: Operator> Stream<Op>
@@ -146,4 +179,34 @@ where
             })
             .finalize_block();
     }
+
+    /// Write output to a single CSV file like [`Stream::write_csv_one`], compressed with
+    /// `compression` whatever its extension.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::OutputCompression;
+    /// # let mut env = StreamContext::new_local();
+    /// env.stream_iter(0..10u32)
+    ///     .map(|n| (n, n * n))
+    ///     .write_csv_one_compressed("squares.csv.gz", false, OutputCompression::Gzip);
+    ///
+    /// env.execute_blocking();
+    /// ```
+    pub fn write_csv_one_compressed<P: Into<PathBuf>>(
+        self,
+        path: P,
+        append: bool,
+        compression: OutputCompression,
+    ) {
+        let path = path.into();
+        self.repartition(Replication::One, NextStrategy::only_one())
+            .add_operator(|prev| {
+                let writer = CsvWriteOp::new(append).with_compression(compression);
+                WriterOperator::new(prev, writer, move |_| path)
+            })
+            .finalize_block();
+    }
 }
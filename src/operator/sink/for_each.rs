@@ -2,7 +2,8 @@ use std::fmt::Display;
 
 use crate::block::{BlockStructure, OperatorKind, OperatorStructure};
 
-use crate::operator::{Operator, StreamElement};
+use crate::operator::stuck::StuckWatch;
+use crate::operator::{fmt_stage, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 
 #[derive(Clone, Derivative)]
@@ -15,6 +16,7 @@ where
     prev: Op,
     #[derivative(Debug = "ignore")]
     f: F,
+    stuck: StuckWatch,
 }
 
 impl<F, Op> ForEach<F, Op>
@@ -23,7 +25,11 @@ where
     Op: Operator,
 {
     pub(crate) fn new(prev: Op, f: F) -> Self {
-        Self { prev, f }
+        Self {
+            prev,
+            f,
+            stuck: Default::default(),
+        }
     }
 }
 
@@ -33,7 +39,7 @@ where
     Op: Operator,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} -> ForEach", self.prev)
+        fmt_stage::<Op::Out, Op::Out>(f, &self.prev, "ForEach")
     }
 }
 
@@ -46,13 +52,14 @@ where
 
     fn setup(&mut self, metadata: &mut ExecutionMetadata) {
         self.prev.setup(metadata);
+        self.stuck.setup(metadata, "ForEach");
     }
 
     fn next(&mut self) -> StreamElement<()> {
         loop {
             match self.prev.next() {
                 StreamElement::Item(t) | StreamElement::Timestamped(t, _) => {
-                    (self.f)(t);
+                    self.stuck.run(|| (self.f)(t));
                 }
                 StreamElement::Watermark(w) => return StreamElement::Watermark(w),
                 StreamElement::Terminate => return StreamElement::Terminate,
@@ -91,6 +98,41 @@ mod tests {
         assert_eq!(sum2.load(Ordering::Acquire), (0..10).sum::<u8>());
     }
 
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn for_each_async_completes_before_end() {
+        use std::sync::atomic::AtomicUsize;
+        use std::time::Duration;
+
+        const CONCURRENCY: usize = 3;
+
+        let done = Arc::new(AtomicUsize::new(0));
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let (done2, in_flight2, max_in_flight2) =
+            (done.clone(), in_flight.clone(), max_in_flight.clone());
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(async move {
+                let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+                env.stream_iter(0..50u32)
+                    .for_each_async(CONCURRENCY, move |_| {
+                        let (done, in_flight, max_in_flight) =
+                            (done2.clone(), in_flight2.clone(), max_in_flight2.clone());
+                        async move {
+                            let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                            max_in_flight.fetch_max(now, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(2)).await;
+                            in_flight.fetch_sub(1, Ordering::SeqCst);
+                            done.fetch_add(1, Ordering::SeqCst);
+                        }
+                    });
+                env.execute().await;
+            });
+        assert_eq!(done.load(Ordering::SeqCst), 50);
+        assert!(max_in_flight.load(Ordering::SeqCst) <= CONCURRENCY);
+    }
+
     #[test]
     fn for_each_keyed() {
         let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
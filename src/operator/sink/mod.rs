@@ -11,12 +11,28 @@ pub(super) mod collect;
 pub(super) mod collect_channel;
 pub(super) mod collect_count;
 pub(super) mod collect_vec;
+pub(super) mod collect_writer;
 pub(super) mod csv;
 pub(super) mod for_each;
 #[cfg(feature = "parquet")]
 pub(super) mod parquet;
+pub(super) mod partitioned;
+#[cfg(feature = "redis")]
+pub(super) mod redis;
+#[cfg(feature = "sql")]
+pub(super) mod sql;
+pub(super) mod two_phase;
+#[cfg(feature = "websocket")]
+pub(super) mod websocket;
 pub(super) mod writer;
 
+#[cfg(feature = "redis")]
+pub use self::redis::RedisTarget;
+pub use partitioned::PartitionedFileSink;
+#[cfg(feature = "sql")]
+pub use sql::{SqlSinkConfig, SqlSinkError, SqlValue};
+pub use two_phase::{FileTransaction, PendingFile, TransactionalFileSink, TwoPhaseCommitSink};
+
 pub(crate) type StreamOutputRef<Out> = Arc<Mutex<Option<Out>>>;
 
 /// The result of a stream after the execution.
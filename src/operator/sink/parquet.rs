@@ -1,5 +1,6 @@
 use arrow::datatypes::Schema;
-use arrow::json::reader::Decoder;
+use arrow::error::ArrowError;
+use arrow::json::reader::{infer_json_schema_from_iterator, Decoder};
 use arrow::json::ReaderBuilder;
 use parquet::arrow::ArrowWriter;
 use parquet::basic::Compression;
@@ -13,7 +14,7 @@ use std::sync::Arc;
 
 use crate::block::NextStrategy;
 use crate::operator::sink::writer::WriteOperator;
-use crate::operator::{ExchangeData, Operator};
+use crate::operator::{ExchangeData, Operator, OutputCompression};
 use crate::{Replication, Stream};
 
 use super::writer::{sequential_path, WriterOperator};
@@ -24,18 +25,61 @@ pub struct ParquetSink<T> {
     /// Reader used to parse the CSV file.
     writer: Option<ArrowWriter<BufWriter<File>>>,
     decoder: Option<Decoder>,
-    schema: Arc<Schema>,
+    /// The schema of the file, inferred from the first items if not given.
+    schema: Option<Arc<Schema>>,
+    destination: Option<PathBuf>,
+    /// The compression of the columns of the file.
+    compression: Compression,
     _t: PhantomData<T>,
 }
 
+impl<T> ParquetSink<T> {
+    fn new(schema: Option<Schema>) -> Self {
+        Self {
+            writer: None,
+            decoder: None,
+            schema: schema.map(Arc::new),
+            destination: None,
+            compression: Compression::SNAPPY,
+            _t: PhantomData,
+        }
+    }
+
+    /// Compress the columns with `compression`: parquet compresses the pages of each column
+    /// inside the file, not the whole file.
+    fn with_compression(mut self, compression: OutputCompression) -> Self {
+        self.compression = match compression {
+            OutputCompression::None => Compression::UNCOMPRESSED,
+            OutputCompression::Gzip => Compression::GZIP(Default::default()),
+            OutputCompression::Zstd => Compression::ZSTD(Default::default()),
+            OutputCompression::Snappy => Compression::SNAPPY,
+        };
+        self
+    }
+
+    /// Open the file, once the schema is known.
+    fn open(&mut self, schema: Arc<Schema>) {
+        let destination = self.destination.as_ref().unwrap();
+        let file = BufWriter::new(File::create(destination).unwrap());
+        let props = WriterProperties::builder()
+            .set_compression(self.compression)
+            .build();
+
+        let writer = ArrowWriter::try_new(file, schema.clone(), Some(props)).unwrap();
+        self.writer = Some(writer);
+        self.decoder = Some(ReaderBuilder::new(schema).build_decoder().unwrap());
+    }
+}
+
 impl<T> Clone for ParquetSink<T> {
     fn clone(&self) -> Self {
         Self {
             _t: PhantomData,
             schema: self.schema.clone(),
+            destination: None,
+            compression: self.compression,
             writer: None,
             decoder: None,
-            // schema: self.schema.clone(),
         }
     }
 }
@@ -47,33 +91,38 @@ where
     type Destination = PathBuf;
 
     fn setup(&mut self, destination: Self::Destination) {
-        let file = BufWriter::new(File::create(destination).unwrap());
-        let props = WriterProperties::builder()
-            .set_compression(Compression::SNAPPY)
-            .build();
-
-        let writer = ArrowWriter::try_new(file, self.schema.clone(), Some(props)).unwrap();
-        self.writer = Some(writer);
-        self.decoder = Some(
-            ReaderBuilder::new(self.schema.clone())
-                .build_decoder()
-                .unwrap(),
-        );
+        self.destination = Some(destination);
+        if let Some(schema) = self.schema.clone() {
+            self.open(schema);
+        }
     }
 
     fn write(&mut self, items: &mut impl Iterator<Item = T>) {
+        let items = items.collect::<Vec<_>>();
+        if self.writer.is_none() {
+            if items.is_empty() {
+                return;
+            }
+            let values = items.iter().map(|item| {
+                serde_json::to_value(item).map_err(|e| ArrowError::JsonError(e.to_string()))
+            });
+            let schema = infer_json_schema_from_iterator(values)
+                .expect("failed to infer the parquet schema from the items");
+            self.schema = Some(Arc::new(schema));
+            self.open(self.schema.clone().unwrap());
+        }
         self.decoder
             .as_mut()
             .unwrap()
-            .serialize(&items.collect::<Vec<_>>())
+            .serialize(&items)
             .expect("failed to serialize struct to arrow RecordBatch");
     }
 
     fn flush(&mut self) {
-        if let Some(batch) = self
-            .decoder
-            .as_mut()
-            .unwrap()
+        let Some(decoder) = self.decoder.as_mut() else {
+            return;
+        };
+        if let Some(batch) = decoder
             .flush()
             .expect("failed to decode struct to arrow RecordBatch")
         {
@@ -83,7 +132,15 @@ where
     }
 
     fn finalize(&mut self) {
-        self.writer.take().unwrap().close().unwrap();
+        match self.writer.take() {
+            Some(writer) => {
+                writer.close().unwrap();
+            }
+            None => log::warn!(
+                "no items to infer the parquet schema from, {} not written",
+                self.destination.as_ref().unwrap().display()
+            ),
+        }
     }
 }
 
@@ -92,13 +149,60 @@ where
     Op: 'static,
     Op::Out: Serialize,
 {
+    /// Write the items of the stream to a parquet file for each replica, with the schema inferred
+    /// from the serialized fields of the first items.
+    ///
+    /// The path of each file is built from `path` as in [`Stream::write_parquet_seq`], e.g.
+    /// `out/part.parquet` becomes `out/part0000.parquet`, `out/part0001.parquet`, ... The schema
+    /// is inferred from the first batch of items of each replica: if some fields are `None` in all
+    /// of them, or the replicas see different variants of the items, give the schema explicitly
+    /// with [`Stream::write_parquet_seq`]. A replica that receives no items writes no file.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use serde::{Deserialize, Serialize};
+    /// # let env = StreamContext::new_local();
+    /// #[derive(Clone, Serialize, Deserialize)]
+    /// struct Row {
+    ///     id: u32,
+    ///     name: String,
+    /// }
+    ///
+    /// env.stream_par_iter(0..100u32)
+    ///     .map(|id| Row { id, name: format!("row {id}") })
+    ///     .write_parquet("out/part.parquet");
+    ///
+    /// env.execute_blocking();
+    /// ```
+    pub fn write_parquet<P: Into<PathBuf>>(self, path: P) {
+        let writer = ParquetSink::new(None);
+        let path = path.into();
+        self.add_operator(|prev| {
+            WriterOperator::new(prev, writer, |meta| sequential_path(path, meta))
+        })
+        .finalize_block();
+    }
+
+    /// Write the items of the stream to a parquet file for each replica like
+    /// [`Stream::write_parquet`], with the columns compressed with `compression` instead of
+    /// Snappy.
+    pub fn write_parquet_compressed<P: Into<PathBuf>>(
+        self,
+        path: P,
+        compression: OutputCompression,
+    ) {
+        let writer = ParquetSink::new(None).with_compression(compression);
+        let path = path.into();
+        self.add_operator(|prev| {
+            WriterOperator::new(prev, writer, |meta| sequential_path(path, meta))
+        })
+        .finalize_block();
+    }
+
     pub fn write_parquet_seq<P: Into<PathBuf>>(self, path: P, schema: Schema) {
-        let writer = ParquetSink {
-            writer: None,
-            decoder: None,
-            schema: Arc::new(schema),
-            _t: PhantomData,
-        };
+        let writer = ParquetSink::new(Some(schema));
         let path = path.into();
         self.add_operator(|prev| {
             WriterOperator::new(prev, writer, |meta| sequential_path(path, meta))
@@ -112,12 +216,7 @@ where
     Op: Operator<Out: ExchangeData> + 'static,
 {
     pub fn write_parquet_one<P: Into<PathBuf>>(self, path: P, schema: Schema) {
-        let writer = ParquetSink {
-            writer: None,
-            decoder: None,
-            schema: Arc::new(schema),
-            _t: PhantomData,
-        };
+        let writer = ParquetSink::new(Some(schema));
         let path = path.into();
 
         if matches!(self.block.scheduling.replication, Replication::One) {
@@ -130,3 +229,87 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use itertools::Itertools;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use serde::{Deserialize, Serialize};
+
+    use super::ParquetSink;
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::sink::writer::WriteOperator;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Row {
+        id: u32,
+        value: f64,
+        name: String,
+    }
+
+    fn row(id: u32) -> Row {
+        Row {
+            id,
+            value: id as f64 / 2.0,
+            name: format!("row {id}"),
+        }
+    }
+
+    #[test]
+    fn parquet_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        env.stream_par_iter(0..5000u32)
+            .map(row)
+            .write_parquet(dir.path().join("rows.parquet"));
+        env.execute_blocking();
+
+        let env = StreamContext::new(RuntimeConfig::local(3).unwrap());
+        let res = env
+            .stream_parquet::<Row>(dir.path().join("rows0000.parquet"))
+            .collect_vec();
+        env.execute_blocking();
+
+        let res = res
+            .get()
+            .unwrap()
+            .into_iter()
+            .sorted_by_key(|r| r.id)
+            .collect_vec();
+        assert_eq!(res, (0..5000).map(row).collect_vec());
+    }
+
+    #[test]
+    fn parquet_row_groups_split_among_replicas() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rows.parquet");
+
+        // each flush of the sink closes a row group
+        let mut sink = ParquetSink::<Row>::new(None);
+        sink.setup(path.clone());
+        for mut chunk in &(0..5000).map(row).chunks(1000) {
+            sink.write(&mut chunk);
+            sink.flush();
+        }
+        sink.finalize();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(builder.metadata().num_row_groups(), 5);
+
+        let env = StreamContext::new(RuntimeConfig::local(3).unwrap());
+        let res = env.stream_parquet::<Row>(path).collect_vec();
+        env.execute_blocking();
+
+        // every row is read by exactly one replica
+        let res = res
+            .get()
+            .unwrap()
+            .into_iter()
+            .sorted_by_key(|r| r.id)
+            .collect_vec();
+        assert_eq!(res, (0..5000).map(row).collect_vec());
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::operator::Operator;
+use crate::scheduler::ExecutionMetadata;
+use crate::{CoordUInt, Stream};
+
+use super::writer::{WriteOperator, WriterOperator};
+
+type PartitionKey<T> = Arc<dyn Fn(&T) -> String + Send + Sync>;
+
+/// A sink writing the items in a directory tree partitioned by some of their fields, with the
+/// layout used by Hive, Spark and the query engines reading their output.
+///
+/// Each item is written as a line of JSON in `base/key1=value1/key2=value2/part-{replica}.jsonl`,
+/// where the keys are the names of the partitions, in the order they are added, and the values are
+/// extracted from the item. The directories are created as needed.
+///
+/// Each replica keeps a file open for each partition it is writing to, up to
+/// [`PartitionedFileSink::max_open_files`]: when the limit is reached the least recently used file
+/// is closed, and reopened in append mode if more items of its partition arrive.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use renoir::{StreamContext, RuntimeConfig};
+/// # use renoir::operator::sink::PartitionedFileSink;
+/// # let mut env = StreamContext::new_local();
+/// let sink = PartitionedFileSink::new("/data/events")
+///     .partition("year", |(year, _): &(u32, String)| year.to_string())
+///     .partition("country", |(_, country)| country.clone());
+/// env.stream_iter(vec![(2024, "it".to_string())].into_iter())
+///     .write_partitioned(sink);
+///
+/// env.execute_blocking();
+/// // writes /data/events/year=2024/country=it/part-0.jsonl
+/// ```
+pub struct PartitionedFileSink<T> {
+    base: PathBuf,
+    partitions: Vec<(String, PartitionKey<T>)>,
+    max_open_files: usize,
+}
+
+impl<T> Clone for PartitionedFileSink<T> {
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone(),
+            partitions: self.partitions.clone(),
+            max_open_files: self.max_open_files,
+        }
+    }
+}
+
+impl<T> PartitionedFileSink<T> {
+    /// Write the partitions inside the `base` directory.
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self {
+            base: base.into(),
+            partitions: Vec::new(),
+            max_open_files: 64,
+        }
+    }
+
+    /// Add a level of partitioning, named `name`, with the value extracted from each item by `key`.
+    pub fn partition<F>(mut self, name: impl Into<String>, key: F) -> Self
+    where
+        F: Fn(&T) -> String + Send + Sync + 'static,
+    {
+        self.partitions.push((name.into(), Arc::new(key)));
+        self
+    }
+
+    /// The maximum number of files each replica keeps open at the same time (default: 64).
+    pub fn max_open_files(mut self, max_open_files: usize) -> Self {
+        assert!(
+            max_open_files > 0,
+            "max_open_files must be greater than zero"
+        );
+        self.max_open_files = max_open_files;
+        self
+    }
+
+    /// The directory of the partition of `item`.
+    fn directory(&self, item: &T) -> PathBuf {
+        let mut dir = self.base.clone();
+        for (name, key) in &self.partitions {
+            dir.push(format!("{}={}", escape(name), escape(&key(item))));
+        }
+        dir
+    }
+}
+
+/// Escape the characters that cannot appear in a partition directory, like Hive does.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '/' | '\\' | '=' | '%' | ':' | '\n' => {
+                escaped.push_str(&format!("%{:02X}", c as u32));
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub struct PartitionedWriteOp<T> {
+    sink: PartitionedFileSink<T>,
+    file_name: String,
+    /// The open files, from the least to the most recently used.
+    writers: IndexMap<PathBuf, BufWriter<File>>,
+    /// The files created by this replica, reopened in append mode after being closed.
+    created: HashSet<PathBuf>,
+}
+
+impl<T> Clone for PartitionedWriteOp<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sink: self.sink.clone(),
+            file_name: self.file_name.clone(),
+            writers: Default::default(),
+            created: Default::default(),
+        }
+    }
+}
+
+impl<T> PartitionedWriteOp<T> {
+    fn new(sink: PartitionedFileSink<T>) -> Self {
+        Self {
+            sink,
+            file_name: String::new(),
+            writers: Default::default(),
+            created: Default::default(),
+        }
+    }
+
+    /// The writer of the partition directory `dir`, opening its file if necessary.
+    fn writer(&mut self, dir: PathBuf) -> &mut BufWriter<File> {
+        if let Some(index) = self.writers.get_index_of(&dir) {
+            self.writers.move_index(index, self.writers.len() - 1);
+        } else {
+            if self.writers.len() >= self.sink.max_open_files {
+                let (path, mut writer) = self.writers.shift_remove_index(0).unwrap();
+                writer.flush().unwrap_or_else(|err| {
+                    panic!("PartitionedFileSink: error while writing {path:?}: {err:?}")
+                });
+            }
+            let path = dir.join(&self.file_name);
+            let append = !self.created.insert(path.clone());
+            let file = std::fs::create_dir_all(&dir).and_then(|_| {
+                File::options()
+                    .write(true)
+                    .create(true)
+                    .truncate(!append)
+                    .append(append)
+                    .open(&path)
+            });
+            let file = file.unwrap_or_else(|err| {
+                panic!("PartitionedFileSink: error while opening file {path:?}: {err:?}")
+            });
+            self.writers.insert(dir, BufWriter::new(file));
+        }
+        self.writers.last_mut().unwrap().1
+    }
+}
+
+impl<T> WriteOperator<T> for PartitionedWriteOp<T>
+where
+    T: Serialize + Send,
+{
+    type Destination = CoordUInt;
+
+    fn setup(&mut self, replica: CoordUInt) {
+        self.file_name = format!("part-{replica}.jsonl");
+    }
+
+    fn write(&mut self, items: &mut impl Iterator<Item = T>) {
+        for item in items {
+            let dir = self.sink.directory(&item);
+            let writer = self.writer(dir);
+            serde_json::to_writer(&mut *writer, &item)
+                .map_err(std::io::Error::from)
+                .and_then(|_| writer.write_all(b"\n"))
+                .unwrap_or_else(|err| {
+                    panic!("PartitionedFileSink: error while writing an item: {err:?}")
+                });
+        }
+    }
+
+    fn flush(&mut self) {
+        for (path, writer) in self.writers.iter_mut() {
+            writer.flush().unwrap_or_else(|err| {
+                panic!("PartitionedFileSink: error while writing {path:?}: {err:?}")
+            });
+        }
+    }
+
+    fn finalize(&mut self) {
+        self.flush();
+        self.writers.clear();
+    }
+}
+
+impl<Op: Operator> Stream<Op>
+where
+    Op: 'static,
+    Op::Out: Serialize,
+{
+    /// Write the items in a directory tree partitioned by some of their fields.
+    ///
+    /// See [`PartitionedFileSink`] for the layout of the output.
+    pub fn write_partitioned(self, sink: PartitionedFileSink<Op::Out>) {
+        self.add_operator(|prev| {
+            let writer = PartitionedWriteOp::new(sink);
+            WriterOperator::new(prev, writer, |m: &ExecutionMetadata| m.global_id)
+        })
+        .finalize_block();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::sink::PartitionedFileSink;
+
+    #[test]
+    fn partitioned_file_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        // a single open file forces the partitions to be closed and reopened
+        let sink = PartitionedFileSink::new(dir.path())
+            .partition("parity", |n: &u32| ["even", "odd"][*n as usize % 2].into())
+            .max_open_files(1);
+        env.stream_iter(0..10u32).write_partitioned(sink);
+        env.execute_blocking();
+
+        let dirs = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .sorted()
+            .collect_vec();
+        assert_eq!(dirs, ["parity=even", "parity=odd"]);
+        for (dir_name, rem) in [("parity=even", 0), ("parity=odd", 1)] {
+            let path = dir.path().join(dir_name).join("part-0.jsonl");
+            let content = std::fs::read_to_string(path).unwrap();
+            let expected = (0..10).filter(|n| n % 2 == rem).map(|n| format!("{n}\n"));
+            assert_eq!(content, expected.collect::<String>());
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::marker::PhantomData;
+
+use redis::Connection;
+use serde::Serialize;
+
+use crate::operator::sink::writer::{WriteOperator, WriterOperator};
+use crate::operator::source::REDIS_DATA_FIELD;
+use crate::operator::Operator;
+use crate::Stream;
+
+/// Where [`Stream::write_redis`] sends the items.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedisTarget {
+    /// Append the items to a Redis Stream with `XADD`, in the `data` field of each entry.
+    Stream(String),
+    /// Publish the items on a Pub/Sub channel with `PUBLISH`.
+    Channel(String),
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct RedisSink<T> {
+    url: String,
+    target: RedisTarget,
+    #[derivative(Debug = "ignore")]
+    connection: Option<Connection>,
+    _t: PhantomData<fn(T)>,
+}
+
+impl<T> Clone for RedisSink<T> {
+    fn clone(&self) -> Self {
+        Self {
+            url: self.url.clone(),
+            target: self.target.clone(),
+            connection: None,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T> WriteOperator<T> for RedisSink<T>
+where
+    T: Serialize + Send,
+{
+    type Destination = ();
+
+    fn setup(&mut self, _destination: ()) {
+        let connection = redis::Client::open(self.url.as_str())
+            .and_then(|client| client.get_connection())
+            .unwrap_or_else(|e| panic!("RedisSink: cannot connect to {}: {e}", self.url));
+        self.connection = Some(connection);
+    }
+
+    fn write(&mut self, items: &mut impl Iterator<Item = T>) {
+        // send the whole batch with a single round trip
+        let mut pipe = redis::pipe();
+        for item in items {
+            let data = serde_json::to_vec(&item).expect("failed to serialize item to JSON");
+            match &self.target {
+                RedisTarget::Stream(key) => pipe.xadd(key, "*", &[(REDIS_DATA_FIELD, data)]),
+                RedisTarget::Channel(channel) => pipe.publish(channel, data),
+            }
+            .ignore();
+        }
+        let connection = self.connection.as_mut().unwrap();
+        if let Err(e) = pipe.query::<()>(connection) {
+            panic!("RedisSink: failed to write to {:?}: {e}", self.target);
+        }
+    }
+
+    fn flush(&mut self) {}
+
+    fn finalize(&mut self) {
+        self.connection.take();
+    }
+}
+
+impl<Op: Operator> Stream<Op>
+where
+    Op: 'static,
+    Op::Out: Serialize,
+{
+    /// Write the items of the stream to the Redis server at `url` (e.g. `redis://localhost:6379`),
+    /// serialized as JSON.
+    ///
+    /// Each replica opens its own connection. The items written to a [`RedisTarget::Stream`] can
+    /// be read back with [`RedisStreamSource`](crate::operator::source::RedisStreamSource).
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::sink::RedisTarget;
+    /// # let mut env = StreamContext::new_local();
+    /// env.stream_iter(0..100)
+    ///     .write_redis("redis://localhost:6379", RedisTarget::Stream("numbers".into()));
+    ///
+    /// env.execute_blocking();
+    /// ```
+    pub fn write_redis<U: Into<String>>(self, url: U, target: RedisTarget) {
+        let writer = RedisSink {
+            url: url.into(),
+            target,
+            connection: None,
+            _t: PhantomData,
+        };
+        self.add_operator(|prev| WriterOperator::new(prev, writer, |_| ()))
+            .finalize_block();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use itertools::Itertools;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::sink::RedisTarget;
+    use crate::operator::source::RedisStreamSource;
+
+    type Commands = Arc<Mutex<Vec<Vec<Vec<u8>>>>>;
+
+    /// Start a fake Redis server that records the commands it receives and replies `OK` to all
+    /// of them, returns its url.
+    fn fake_redis() -> (String, Commands) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+        let commands = Commands::default();
+        let recorded = commands.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let commands = recorded.clone();
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    // each command is an array of bulk strings: *<n> then $<len> <data> n times
+                    loop {
+                        let header = read_line(&mut reader);
+                        let Some(n) = header.trim_end().strip_prefix('*') else {
+                            return;
+                        };
+                        let mut command = vec![];
+                        for _ in 0..n.parse::<usize>().unwrap() {
+                            let len = read_line(&mut reader);
+                            let len: usize = len.trim_end()[1..].parse().unwrap();
+                            let mut arg = vec![0; len + 2];
+                            reader.read_exact(&mut arg).unwrap();
+                            arg.truncate(len);
+                            command.push(arg);
+                        }
+                        commands.lock().unwrap().push(command);
+                        stream.write_all(b"+OK\r\n").unwrap();
+                    }
+                });
+            }
+        });
+        (url, commands)
+    }
+
+    fn read_line(reader: &mut impl BufRead) -> String {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        line
+    }
+
+    /// The items sent with the commands named `name` to `key`, the item being the last argument.
+    fn sent_items(commands: &Commands, name: &str, key: &str) -> Vec<u32> {
+        commands
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|c| c[0].eq_ignore_ascii_case(name.as_bytes()) && c[1] == key.as_bytes())
+            .map(|c| serde_json::from_slice::<u32>(c.last().unwrap()).unwrap())
+            .sorted()
+            .collect()
+    }
+
+    #[test]
+    fn redis_sink_fake_server() {
+        let (url, commands) = fake_redis();
+
+        let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+        let mut streams = env.stream_par_iter(0..100u32).split(2).into_iter();
+        streams
+            .next()
+            .unwrap()
+            .write_redis(url.clone(), RedisTarget::Stream("numbers".into()));
+        streams
+            .next()
+            .unwrap()
+            .write_redis(url, RedisTarget::Channel("updates".into()));
+        env.execute_blocking();
+
+        let expected = (0..100).collect_vec();
+        assert_eq!(sent_items(&commands, "XADD", "numbers"), expected);
+        assert_eq!(sent_items(&commands, "PUBLISH", "updates"), expected);
+        // the entries of the stream hold the item in the data field, with an id chosen by Redis
+        let commands = commands.lock().unwrap();
+        let xadd = commands
+            .iter()
+            .find(|c| c[0].eq_ignore_ascii_case(b"XADD"))
+            .unwrap();
+        assert_eq!(&xadd[2..4], [b"*".to_vec(), b"data".to_vec()]);
+    }
+
+    #[test]
+    #[ignore = "requires a Redis server, set REDIS_URL (e.g. redis://localhost:6379)"]
+    fn redis_stream_round_trip() {
+        let url = std::env::var("REDIS_URL").unwrap();
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let key = format!("renoir-test-{nanos}");
+
+        let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+        env.stream_par_iter(0..1000u32)
+            .map(|i| (i, i.to_string()))
+            .write_redis(url.clone(), RedisTarget::Stream(key.clone()));
+        env.execute_blocking();
+
+        let env = StreamContext::new(RuntimeConfig::local(3).unwrap());
+        let source = RedisStreamSource::<(u32, String)>::new(url, key, "test").stop_when_empty();
+        let res = env.stream(source).collect_vec();
+        env.execute_blocking();
+
+        let res = res.get().unwrap().into_iter().sorted().collect_vec();
+        assert_eq!(res, (0..1000).map(|i| (i, i.to_string())).collect_vec());
+    }
+}
@@ -0,0 +1,400 @@
+use serde::{Deserialize, Serialize};
+use sqlx::any::{AnyArguments, AnyPoolOptions};
+use sqlx::query::Query;
+use sqlx::{Any, AnyPool};
+use thiserror::Error;
+use tokio::runtime::Runtime;
+
+use crate::operator::sink::{StreamOutput, StreamOutputRef};
+use crate::operator::Operator;
+use crate::Stream;
+
+use super::writer::{WriteOperator, WriterOperator};
+
+/// A single value bound to a placeholder of the `INSERT` statement generated by the sql sink.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SqlValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl SqlValue {
+    fn bind<'q>(self, query: Query<'q, Any, AnyArguments<'q>>) -> Query<'q, Any, AnyArguments<'q>> {
+        match self {
+            SqlValue::Null => query.bind(Option::<i64>::None),
+            SqlValue::Bool(v) => query.bind(v),
+            SqlValue::Int(v) => query.bind(v),
+            SqlValue::Float(v) => query.bind(v),
+            SqlValue::Text(v) => query.bind(v),
+            SqlValue::Blob(v) => query.bind(v),
+        }
+    }
+}
+
+macro_rules! impl_sql_value_from {
+    ($t:ty, $variant:ident) => {
+        impl From<$t> for SqlValue {
+            fn from(value: $t) -> Self {
+                SqlValue::$variant(value.into())
+            }
+        }
+    };
+}
+
+impl_sql_value_from!(bool, Bool);
+impl_sql_value_from!(i8, Int);
+impl_sql_value_from!(i16, Int);
+impl_sql_value_from!(i32, Int);
+impl_sql_value_from!(i64, Int);
+impl_sql_value_from!(u8, Int);
+impl_sql_value_from!(u16, Int);
+impl_sql_value_from!(u32, Int);
+impl_sql_value_from!(f32, Float);
+impl_sql_value_from!(f64, Float);
+impl_sql_value_from!(String, Text);
+impl_sql_value_from!(&str, Text);
+impl_sql_value_from!(Vec<u8>, Blob);
+
+impl<T: Into<SqlValue>> From<Option<T>> for SqlValue {
+    fn from(value: Option<T>) -> Self {
+        value.map(Into::into).unwrap_or(SqlValue::Null)
+    }
+}
+
+/// Error raised while writing to the database from the sql sink.
+#[derive(Debug, Error)]
+pub enum SqlSinkError {
+    #[error("failed to connect to the database: {0}")]
+    Connect(sqlx::Error),
+    #[error("failed to commit a batch of {rows} rows: {source}")]
+    Batch { rows: usize, source: sqlx::Error },
+}
+
+/// Configuration of the sink created by [`Stream::write_sql`].
+///
+/// Each item of the stream is mapped to a row that is inserted in `table`, binding the values to
+/// the `columns` in order. Rows are written in transactions of at most `batch_size` rows, the
+/// pending rows are also committed when the stream flushes and when it ends.
+#[derive(Debug, Clone)]
+pub struct SqlSinkConfig {
+    url: String,
+    table: String,
+    columns: Vec<String>,
+    conflict_columns: Option<Vec<String>>,
+    batch_size: usize,
+    max_connections: u32,
+}
+
+impl SqlSinkConfig {
+    /// Insert the rows in `table` of the database at `url` (e.g. `postgres://localhost/db` or
+    /// `sqlite://data.db`).
+    pub fn new<S: Into<String>>(
+        url: impl Into<String>,
+        table: impl Into<String>,
+        columns: impl IntoIterator<Item = S>,
+    ) -> Self {
+        Self {
+            url: url.into(),
+            table: table.into(),
+            columns: columns.into_iter().map(Into::into).collect(),
+            conflict_columns: None,
+            batch_size: 1024,
+            max_connections: 1,
+        }
+    }
+
+    /// Maximum number of rows committed in a single transaction.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "the batch size must be positive");
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Update the existing rows instead of failing when a row conflicts on `conflict_columns`
+    /// (`ON CONFLICT ... DO UPDATE`).
+    pub fn upsert<S: Into<String>>(
+        mut self,
+        conflict_columns: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.conflict_columns = Some(conflict_columns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Maximum number of connections opened by each replica of the sink.
+    pub fn max_connections(mut self, max_connections: u32) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    fn statement(&self) -> String {
+        let postgres = self.url.starts_with("postgres");
+        let placeholders = (1..=self.columns.len())
+            .map(|i| {
+                if postgres {
+                    format!("${i}")
+                } else {
+                    "?".to_string()
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut statement = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            self.table,
+            self.columns.join(", "),
+            placeholders.join(", ")
+        );
+        if let Some(conflict) = &self.conflict_columns {
+            let updates = self
+                .columns
+                .iter()
+                .filter(|c| !conflict.contains(c))
+                .map(|c| format!("{c} = excluded.{c}"))
+                .collect::<Vec<_>>();
+            statement.push_str(&format!(" ON CONFLICT ({})", conflict.join(", ")));
+            if updates.is_empty() {
+                statement.push_str(" DO NOTHING");
+            } else {
+                statement.push_str(&format!(" DO UPDATE SET {}", updates.join(", ")));
+            }
+        }
+        statement
+    }
+}
+
+/// A row of values waiting to be inserted.
+#[derive(Clone, Serialize)]
+struct SqlRow(Vec<SqlValue>);
+
+struct SqlWriteOp {
+    config: SqlSinkConfig,
+    statement: String,
+    runtime: Option<Runtime>,
+    pool: Option<AnyPool>,
+    pending: Vec<Vec<SqlValue>>,
+    errors: Vec<SqlSinkError>,
+    output: StreamOutputRef<Vec<SqlSinkError>>,
+}
+
+impl SqlWriteOp {
+    fn new(config: SqlSinkConfig, output: StreamOutputRef<Vec<SqlSinkError>>) -> Self {
+        Self {
+            statement: config.statement(),
+            config,
+            runtime: None,
+            pool: None,
+            pending: Default::default(),
+            errors: Default::default(),
+            output,
+        }
+    }
+
+    /// Insert all the pending rows in a single transaction.
+    fn commit(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let rows = std::mem::take(&mut self.pending);
+        let (Some(runtime), Some(pool)) = (self.runtime.as_ref(), self.pool.as_ref()) else {
+            // the connection failed, the error has already been reported
+            return;
+        };
+
+        let num_rows = rows.len();
+        let statement = self.statement.as_str();
+        let result = runtime.block_on(async {
+            let mut tx = pool.begin().await?;
+            for row in rows {
+                let query = row
+                    .into_iter()
+                    .fold(sqlx::query(statement), |query, value| value.bind(query));
+                query.execute(&mut *tx).await?;
+            }
+            tx.commit().await
+        });
+
+        if let Err(source) = result {
+            tracing::error!("SqlSink: failed to commit {num_rows} rows: {source}");
+            self.errors.push(SqlSinkError::Batch {
+                rows: num_rows,
+                source,
+            });
+        }
+    }
+}
+
+impl Clone for SqlWriteOp {
+    fn clone(&self) -> Self {
+        Self::new(self.config.clone(), self.output.clone())
+    }
+}
+
+impl WriteOperator<SqlRow> for SqlWriteOp {
+    type Destination = ();
+
+    fn setup(&mut self, _destination: ()) {
+        sqlx::any::install_default_drivers();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to build the runtime of the sql sink");
+
+        let options = AnyPoolOptions::new().max_connections(self.config.max_connections);
+        match runtime.block_on(options.connect(&self.config.url)) {
+            Ok(pool) => self.pool = Some(pool),
+            Err(e) => {
+                tracing::error!("SqlSink: failed to connect to the database: {e}");
+                self.errors.push(SqlSinkError::Connect(e));
+            }
+        }
+        self.runtime = Some(runtime);
+    }
+
+    fn write(&mut self, items: &mut impl Iterator<Item = SqlRow>) {
+        for row in items {
+            self.pending.push(row.0);
+            if self.pending.len() >= self.config.batch_size {
+                self.commit();
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        self.commit();
+    }
+
+    fn finalize(&mut self) {
+        self.commit();
+        if let (Some(runtime), Some(pool)) = (self.runtime.take(), self.pool.take()) {
+            runtime.block_on(pool.close());
+        }
+        self.output
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Vec::new)
+            .append(&mut self.errors);
+    }
+}
+
+impl<Op: Operator> Stream<Op>
+where
+    Op: 'static,
+{
+    /// Write the items of the stream into a table of a relational database.
+    ///
+    /// Each item is converted to a row using `to_row`, the values are bound in order to the columns
+    /// listed in the `config`. The rows are inserted in batched transactions, each replica of the
+    /// sink opens its own connection pool.
+    ///
+    /// The returned output holds the errors encountered while writing, a failed batch is rolled
+    /// back and the execution continues with the next one.
+    ///
+    /// **Note**: this is available only with the `sql` feature.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::sink::{SqlSinkConfig, SqlValue};
+    /// # let mut env = StreamContext::new_local();
+    /// let config = SqlSinkConfig::new("postgres://localhost/db", "squares", ["n", "square"])
+    ///     .batch_size(512)
+    ///     .upsert(["n"]);
+    /// let errors = env
+    ///     .stream_iter(0..100i64)
+    ///     .write_sql(config, |n| vec![SqlValue::from(n), SqlValue::from(n * n)]);
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert!(errors.get().unwrap().is_empty());
+    /// ```
+    pub fn write_sql<F>(self, config: SqlSinkConfig, to_row: F) -> StreamOutput<Vec<SqlSinkError>>
+    where
+        F: Fn(Op::Out) -> Vec<SqlValue> + Send + Clone + 'static,
+    {
+        let output = StreamOutputRef::default();
+        let writer = SqlWriteOp::new(config, output.clone());
+        self.map(move |item| SqlRow(to_row(item)))
+            .add_operator(|prev| WriterOperator::new(prev, writer, |_| ()))
+            .finalize_block();
+        StreamOutput::from(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::any::AnyPoolOptions;
+    use sqlx::Row;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::sink::{SqlSinkConfig, SqlValue};
+
+    const URL: &str = "sqlite:file:renoir_sql_sink?mode=memory&cache=shared";
+
+    #[test]
+    fn write_sql_batches() {
+        sqlx::any::install_default_drivers();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        // keep a connection open for the whole test, otherwise the in-memory database is dropped
+        let pool = runtime.block_on(async {
+            let pool = AnyPoolOptions::new()
+                .max_connections(1)
+                .connect(URL)
+                .await
+                .unwrap();
+            sqlx::query("CREATE TABLE squares (n INTEGER PRIMARY KEY, square INTEGER)")
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO squares (n, square) VALUES (3, 0)")
+                .execute(&pool)
+                .await
+                .unwrap();
+            pool
+        });
+
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        // 10 rows with batches of 3: the last row is committed only at the end
+        let config = SqlSinkConfig::new(URL, "squares", ["n", "square"])
+            .batch_size(3)
+            .upsert(["n"]);
+        let errors = env
+            .stream_iter(0..10i64)
+            .write_sql(config, |n| vec![SqlValue::from(n), SqlValue::from(n * n)]);
+        env.execute_blocking();
+
+        assert!(errors.get().unwrap().is_empty());
+        let rows = runtime.block_on(async {
+            sqlx::query("SELECT n, square FROM squares ORDER BY n")
+                .fetch_all(&pool)
+                .await
+                .unwrap()
+        });
+        let rows = rows
+            .iter()
+            .map(|r| (r.get::<i64, _>(0), r.get::<i64, _>(1)))
+            .collect::<Vec<_>>();
+        assert_eq!(rows, (0..10).map(|n| (n, n * n)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn upsert_statement() {
+        let config = SqlSinkConfig::new("postgres://localhost/db", "t", ["a", "b", "c"]);
+        assert_eq!(
+            config.statement(),
+            "INSERT INTO t (a, b, c) VALUES ($1, $2, $3)"
+        );
+        let config = config.upsert(["a"]);
+        assert_eq!(
+            config.statement(),
+            "INSERT INTO t (a, b, c) VALUES ($1, $2, $3) ON CONFLICT (a) DO UPDATE SET b = excluded.b, c = excluded.c"
+        );
+    }
+}
@@ -0,0 +1,511 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::block::{BlockStructure, OperatorKind, OperatorStructure};
+use crate::operator::{Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::{CoordUInt, Stream};
+
+/// A sink that writes to a transactional system, committing its output in two phases.
+///
+/// The output of each replica is written inside a transaction. When the replica reaches a
+/// barrier (the end of the stream, or the end of an iteration round) the open transaction is
+/// pre-committed: after that it must be possible to commit it, but its output must not be
+/// visible yet. The pre-committed transactions are committed only when the stream terminates
+/// cleanly. If the replica fails in the meantime the open transaction is aborted; at the next
+/// start [`TwoPhaseCommitSink::recover`] commits the transactions that a crashed execution had
+/// already pre-committed and discards the ones it had not.
+///
+/// Combined with a replayable source this gives exactly-once output: either all the output of a
+/// replica becomes visible or none of it does.
+pub trait TwoPhaseCommitSink<T>: Clone + Send {
+    /// An open transaction, receiving the items.
+    type Transaction: Send;
+    /// A pre-committed transaction, waiting to be committed.
+    type Pending: Send;
+    /// The error returned by the operations of the sink.
+    type Error: std::error::Error;
+
+    /// Prepare the sink for a replica, completing the transactions left by a previous execution
+    /// that did not complete: the pre-committed ones are committed, the others are discarded.
+    fn recover(&mut self, metadata: &ExecutionMetadata) -> Result<(), Self::Error>;
+    /// Open a new transaction.
+    fn begin(&mut self) -> Result<Self::Transaction, Self::Error>;
+    /// Write an item inside the transaction.
+    fn write(&mut self, transaction: &mut Self::Transaction, item: T) -> Result<(), Self::Error>;
+    /// Make the transaction ready to be committed, without making its output visible.
+    fn pre_commit(&mut self, transaction: Self::Transaction) -> Result<Self::Pending, Self::Error>;
+    /// Make the output of a pre-committed transaction visible.
+    fn commit(&mut self, pending: Self::Pending) -> Result<(), Self::Error>;
+    /// Discard a transaction.
+    fn abort(&mut self, transaction: Self::Transaction);
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct TwoPhaseCommitOperator<Op, S>
+where
+    Op: Operator,
+    S: TwoPhaseCommitSink<Op::Out>,
+{
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    sink: S,
+    #[derivative(Debug = "ignore")]
+    transaction: Option<S::Transaction>,
+    #[derivative(Debug = "ignore")]
+    pending: Vec<S::Pending>,
+}
+
+impl<Op, S> TwoPhaseCommitOperator<Op, S>
+where
+    Op: Operator,
+    S: TwoPhaseCommitSink<Op::Out>,
+{
+    pub(crate) fn new(prev: Op, sink: S) -> Self {
+        Self {
+            prev,
+            sink,
+            transaction: None,
+            pending: Default::default(),
+        }
+    }
+
+    /// Abort the open transaction and panic with the error.
+    fn fail(&mut self, operation: &str, error: S::Error) -> ! {
+        if let Some(transaction) = self.transaction.take() {
+            self.sink.abort(transaction);
+        }
+        panic!("TwoPhaseCommitSink: {operation} failed: {error}");
+    }
+}
+
+impl<Op, S> Clone for TwoPhaseCommitOperator<Op, S>
+where
+    Op: Operator,
+    S: TwoPhaseCommitSink<Op::Out>,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.prev.clone(), self.sink.clone())
+    }
+}
+
+impl<Op, S> Display for TwoPhaseCommitOperator<Op, S>
+where
+    Op: Operator,
+    S: TwoPhaseCommitSink<Op::Out>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} -> TwoPhaseCommitSink<{}>",
+            self.prev,
+            std::any::type_name::<S>()
+        )
+    }
+}
+
+impl<Op, S> Operator for TwoPhaseCommitOperator<Op, S>
+where
+    Op: Operator,
+    S: TwoPhaseCommitSink<Op::Out>,
+{
+    type Out = ();
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        if let Err(e) = self.sink.recover(metadata) {
+            panic!("TwoPhaseCommitSink: recovery failed: {e}");
+        }
+    }
+
+    fn next(&mut self) -> StreamElement<()> {
+        let element = self.prev.next();
+        match element {
+            StreamElement::Item(item) | StreamElement::Timestamped(item, _) => {
+                let transaction = match self.transaction.take() {
+                    Some(transaction) => transaction,
+                    None => match self.sink.begin() {
+                        Ok(transaction) => transaction,
+                        Err(e) => self.fail("begin", e),
+                    },
+                };
+                let transaction = self.transaction.insert(transaction);
+                if let Err(e) = self.sink.write(transaction, item) {
+                    self.fail("write", e);
+                }
+                StreamElement::Item(())
+            }
+            StreamElement::FlushAndRestart => {
+                if let Some(transaction) = self.transaction.take() {
+                    match self.sink.pre_commit(transaction) {
+                        Ok(pending) => self.pending.push(pending),
+                        Err(e) => self.fail("pre-commit", e),
+                    }
+                }
+                StreamElement::FlushAndRestart
+            }
+            StreamElement::Terminate => {
+                for pending in std::mem::take(&mut self.pending) {
+                    if let Err(e) = self.sink.commit(pending) {
+                        self.fail("commit", e);
+                    }
+                }
+                StreamElement::Terminate
+            }
+            other => other.variant(),
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<Op::Out, _>("TwoPhaseCommitSink");
+        operator.kind = OperatorKind::Sink;
+        self.prev.structure().add_operator(operator)
+    }
+}
+
+impl<Op, S> Drop for TwoPhaseCommitOperator<Op, S>
+where
+    Op: Operator,
+    S: TwoPhaseCommitSink<Op::Out>,
+{
+    fn drop(&mut self) {
+        // the stream did not terminate cleanly: the pending transactions are left to the recovery
+        if let Some(transaction) = self.transaction.take() {
+            self.sink.abort(transaction);
+        }
+    }
+}
+
+/// Reference [`TwoPhaseCommitSink`] writing the items to a file, one JSON value per line.
+///
+/// Each transaction is written to a hidden temporary file next to the destination (`.<name>.tmp`).
+/// When pre-committed the file is synced to disk and renamed to `.<name>.pending`, when committed
+/// it is renamed to the destination. After a crash the recovery renames the pending files to
+/// their destination and removes the temporary ones. The first
+/// transaction of a replica is committed to the destination path, the following ones (one for
+/// each iteration round after the first) to the destination path with the suffix `.<n>`.
+pub struct TransactionalFileSink<T, F> {
+    make_path: F,
+    path: Option<PathBuf>,
+    transactions: usize,
+    _t: PhantomData<fn(T)>,
+}
+
+impl<T, F: Clone> Clone for TransactionalFileSink<T, F> {
+    fn clone(&self) -> Self {
+        Self {
+            make_path: self.make_path.clone(),
+            path: None,
+            transactions: 0,
+            _t: PhantomData,
+        }
+    }
+}
+
+/// An open transaction of a [`TransactionalFileSink`].
+pub struct FileTransaction {
+    writer: BufWriter<File>,
+    temp: PathBuf,
+    destination: PathBuf,
+}
+
+/// A pre-committed transaction of a [`TransactionalFileSink`].
+pub struct PendingFile {
+    pending: PathBuf,
+    destination: PathBuf,
+}
+
+impl<T, F> TransactionalFileSink<T, F>
+where
+    F: FnOnce(CoordUInt) -> PathBuf + Clone + Send,
+{
+    /// Create the sink, the destination of each replica is built by `make_path` from the index
+    /// of the replica.
+    pub fn new(make_path: F) -> Self {
+        Self {
+            make_path,
+            path: None,
+            transactions: 0,
+            _t: PhantomData,
+        }
+    }
+
+    /// The path of the file of a transaction writing to `destination`, with the `extension`
+    /// of its state (`tmp` while open, `pending` once pre-committed).
+    fn transaction_path(destination: &std::path::Path, extension: &str) -> PathBuf {
+        let name = destination
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        destination.with_file_name(format!(".{name}.{extension}"))
+    }
+
+    /// If `file_name` is the file with the `extension` of a transaction writing to the
+    /// destination `name`, or to one of the following destinations (`name.1`, `name.2`, ...),
+    /// the name of its destination.
+    fn destination_of(file_name: &str, name: &str, extension: &str) -> Option<String> {
+        let rest = file_name
+            .strip_prefix('.')?
+            .strip_prefix(name)?
+            .strip_suffix(extension)?
+            .strip_suffix('.')?;
+        match rest.strip_prefix('.') {
+            None if rest.is_empty() => Some(name.to_string()),
+            Some(n) if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => {
+                Some(format!("{name}.{n}"))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<T, F> TwoPhaseCommitSink<T> for TransactionalFileSink<T, F>
+where
+    T: Serialize,
+    F: FnOnce(CoordUInt) -> PathBuf + Clone + Send,
+{
+    type Transaction = FileTransaction;
+    type Pending = PendingFile;
+    type Error = std::io::Error;
+
+    fn recover(&mut self, metadata: &ExecutionMetadata) -> std::io::Result<()> {
+        let path = (self.make_path.clone())(metadata.global_id);
+        let dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if dir.as_os_str().is_empty() || dir.is_dir() {
+            let entries = std::fs::read_dir(if dir.as_os_str().is_empty() {
+                std::path::Path::new(".")
+            } else {
+                &dir
+            })?;
+            for entry in entries {
+                let entry = entry?;
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                if let Some(destination) = Self::destination_of(&file_name, &name, "pending") {
+                    log::warn!("TransactionalFileSink: committing pre-committed {file_name}");
+                    std::fs::rename(entry.path(), entry.path().with_file_name(destination))?;
+                } else if Self::destination_of(&file_name, &name, "tmp").is_some() {
+                    log::warn!("TransactionalFileSink: removing incomplete {file_name}");
+                    std::fs::remove_file(entry.path())?;
+                }
+            }
+        }
+        self.path = Some(path);
+        Ok(())
+    }
+
+    fn begin(&mut self) -> std::io::Result<FileTransaction> {
+        let path = self
+            .path
+            .as_ref()
+            .expect("TransactionalFileSink was not set up");
+        let destination = match self.transactions {
+            0 => path.clone(),
+            n => {
+                let mut name = path.file_name().unwrap_or_default().to_os_string();
+                name.push(format!(".{n}"));
+                path.with_file_name(name)
+            }
+        };
+        self.transactions += 1;
+        let temp = Self::transaction_path(&destination, "tmp");
+        if let Some(dir) = temp.parent() {
+            if !dir.as_os_str().is_empty() {
+                std::fs::create_dir_all(dir)?;
+            }
+        }
+        let writer = BufWriter::new(File::create(&temp)?);
+        Ok(FileTransaction {
+            writer,
+            temp,
+            destination,
+        })
+    }
+
+    fn write(&mut self, transaction: &mut FileTransaction, item: T) -> std::io::Result<()> {
+        serde_json::to_writer(&mut transaction.writer, &item)?;
+        transaction.writer.write_all(b"\n")
+    }
+
+    fn pre_commit(&mut self, transaction: FileTransaction) -> std::io::Result<PendingFile> {
+        let file = transaction
+            .writer
+            .into_inner()
+            .map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        // from now on the recovery commits the transaction instead of discarding it
+        let pending = Self::transaction_path(&transaction.destination, "pending");
+        std::fs::rename(&transaction.temp, &pending)?;
+        Ok(PendingFile {
+            pending,
+            destination: transaction.destination,
+        })
+    }
+
+    fn commit(&mut self, pending: PendingFile) -> std::io::Result<()> {
+        std::fs::rename(&pending.pending, &pending.destination)
+    }
+
+    fn abort(&mut self, transaction: FileTransaction) {
+        drop(transaction.writer);
+        if let Err(e) = std::fs::remove_file(&transaction.temp) {
+            log::warn!(
+                "TransactionalFileSink: cannot remove {}: {e}",
+                transaction.temp.display()
+            );
+        }
+    }
+}
+
+impl<Op: Operator> Stream<Op>
+where
+    Op: 'static,
+{
+    /// Write the items of the stream with a [`TwoPhaseCommitSink`]: the output of each replica
+    /// becomes visible only when the stream terminates cleanly.
+    pub fn write_two_phase<S>(self, sink: S)
+    where
+        S: TwoPhaseCommitSink<Op::Out> + 'static,
+    {
+        self.add_operator(|prev| TwoPhaseCommitOperator::new(prev, sink))
+            .finalize_block();
+    }
+
+    /// Write the items of the stream to a file for each replica, one JSON value per line, using
+    /// the [`TransactionalFileSink`]: a file appears only when all the output of the replica has
+    /// been written.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// env.stream_iter(0..100)
+    ///     .write_transactional_file(|id| format!("/data/output/part-{id:04}.jsonl").into());
+    ///
+    /// env.execute_blocking();
+    /// ```
+    pub fn write_transactional_file<F>(self, make_path: F)
+    where
+        Op::Out: Serialize,
+        F: FnOnce(CoordUInt) -> PathBuf + Clone + Send + 'static,
+    {
+        self.write_two_phase(TransactionalFileSink::new(make_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::sink::{TransactionalFileSink, TwoPhaseCommitSink};
+    use crate::test::FakeNetworkTopology;
+
+    #[test]
+    fn transactional_file_crash_between_pre_commit_and_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("out.jsonl");
+        let make_path = {
+            let destination = destination.clone();
+            move |_| destination
+        };
+
+        let mut topology = FakeNetworkTopology::<u32>::new(0, 0);
+        let metadata = topology.metadata();
+        let mut sink = TransactionalFileSink::new(make_path.clone());
+        sink.recover(&metadata).unwrap();
+        let mut transaction = sink.begin().unwrap();
+        for i in 0..10u32 {
+            sink.write(&mut transaction, i).unwrap();
+        }
+        let pending = sink.pre_commit(transaction).unwrap();
+        // simulated failure: the pending transaction is never committed, and the transaction of
+        // the next round is never pre-committed
+        drop(pending);
+        let mut transaction = sink.begin().unwrap();
+        sink.write(&mut transaction, 10).unwrap();
+        std::mem::forget(transaction);
+        assert!(!destination.exists());
+
+        // the next execution commits the pre-committed transaction and discards the other one
+        let mut sink = TransactionalFileSink::<u32, _>::new(make_path);
+        sink.recover(&metadata).unwrap();
+        let files = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect_vec();
+        assert_eq!(files, ["out.jsonl"]);
+        let content = std::fs::read_to_string(&destination).unwrap();
+        assert_eq!(content, (0..10).map(|i| format!("{i}\n")).join(""));
+    }
+
+    #[test]
+    fn transactional_file_recover_keeps_other_replicas() {
+        let dir = tempfile::tempdir().unwrap();
+        let make_path = {
+            let base = dir.path().to_path_buf();
+            move |id| base.join(format!("part-{id}.jsonl"))
+        };
+        // the transaction files of the replica 1 and of the replica 10
+        let own = [".part-1.jsonl.tmp", ".part-1.jsonl.3.tmp"];
+        let own_pending = [".part-1.jsonl.pending", ".part-1.jsonl.2.pending"];
+        let others = [
+            ".part-10.jsonl.tmp",
+            ".part-10.jsonl.2.tmp",
+            ".part-10.jsonl.pending",
+            ".part-1.jsonl.x.tmp",
+            ".part-1.jsonl.x.pending",
+        ];
+        for file in own.iter().chain(&own_pending).chain(&others) {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+
+        let mut topology = FakeNetworkTopology::<u32>::new(0, 0);
+        let mut metadata = topology.metadata();
+        metadata.global_id = 1;
+        let mut sink = TransactionalFileSink::<u32, _>::new(make_path);
+        sink.recover(&metadata).unwrap();
+
+        let files = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .sorted()
+            .collect_vec();
+        let expected = others
+            .into_iter()
+            .chain(["part-1.jsonl", "part-1.jsonl.2"])
+            .sorted()
+            .collect_vec();
+        assert_eq!(files, expected);
+    }
+
+    #[test]
+    fn transactional_file_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+        let base = dir.path().to_path_buf();
+        env.stream_par_iter(|i, _| (i * 10)..(i * 10 + 10))
+            .write_transactional_file(move |id| base.join(format!("part-{id}.jsonl")));
+        env.execute_blocking();
+
+        let files = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .sorted()
+            .collect_vec();
+        assert_eq!(files, ["part-0.jsonl", "part-1.jsonl"]);
+        for (id, file) in files.iter().enumerate() {
+            let content = std::fs::read_to_string(dir.path().join(file)).unwrap();
+            let expected = (id * 10..id * 10 + 10).map(|i| format!("{i}\n")).join("");
+            assert_eq!(content, expected);
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use flume::{Receiver, Sender, TrySendError};
+use futures::{SinkExt, StreamExt};
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::block::{BlockStructure, NextStrategy, OperatorKind, OperatorStructure, Replication};
+use crate::operator::{ExchangeData, Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// The number of messages buffered for each client before they start being dropped.
+const CLIENT_CAPACITY: usize = 1024;
+/// How long to wait for the pending messages to be delivered when the stream ends.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A connected client of the sink.
+struct Client {
+    /// The messages to send to the client.
+    tx: Sender<Message>,
+    /// Disconnected when the connection with the client is closed.
+    closed: Receiver<()>,
+}
+
+/// Sink that listens for WebSocket clients and pushes every item to all the connected clients,
+/// serialized as JSON text messages.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct WebSocketSink<Op: Operator> {
+    prev: Op,
+    address: String,
+    min_clients: usize,
+    #[derivative(Debug = "ignore")]
+    new_clients: Option<Receiver<Client>>,
+    #[derivative(Debug = "ignore")]
+    clients: Vec<Client>,
+    #[derivative(Debug = "ignore")]
+    listener: Option<JoinHandle<()>>,
+}
+
+impl<Op: Operator> WebSocketSink<Op> {
+    pub(crate) fn new(prev: Op, address: String, min_clients: usize) -> Self {
+        Self {
+            prev,
+            address,
+            min_clients,
+            new_clients: None,
+            clients: Default::default(),
+            listener: None,
+        }
+    }
+
+    /// Register the clients that connected since the last item.
+    fn accept_clients(&mut self) {
+        let new_clients = self.new_clients.as_ref().unwrap();
+        while self.clients.len() < self.min_clients {
+            match new_clients.recv() {
+                Ok(client) => self.clients.push(client),
+                Err(_) => break,
+            }
+        }
+        self.min_clients = 0;
+        self.clients.extend(new_clients.try_iter());
+    }
+
+    fn broadcast(&mut self, message: Message) {
+        self.accept_clients();
+        self.clients
+            .retain(|client| match client.tx.try_send(message.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    log::warn!("WebSocketSink: client is too slow, dropping a message");
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+    }
+
+    /// Close the connections with all the clients, waiting for the pending messages to be sent.
+    fn close(&mut self) {
+        let deadline = Instant::now() + CLOSE_TIMEOUT;
+        let mut clients = std::mem::take(&mut self.clients);
+        if let Some(new_clients) = self.new_clients.as_ref() {
+            clients.extend(new_clients.try_iter());
+        }
+        let closed = clients
+            .into_iter()
+            .map(|client| client.closed)
+            .collect::<Vec<_>>();
+        for closed in closed {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let _ = closed.recv_timeout(timeout);
+        }
+        if let Some(listener) = self.listener.take() {
+            listener.abort();
+        }
+    }
+}
+
+/// Accept the connections on the listener, forwarding the messages of the sink to each client.
+async fn listen(listener: tokio::net::TcpListener, new_clients: Sender<Client>) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::warn!("WebSocketSink: failed to accept a connection: {e}");
+                continue;
+            }
+        };
+        let (tx, rx) = flume::bounded::<Message>(CLIENT_CAPACITY);
+        let (closed_tx, closed) = flume::bounded::<()>(1);
+        if new_clients.send_async(Client { tx, closed }).await.is_err() {
+            return;
+        }
+        tokio::spawn(async move {
+            let _closed = closed_tx;
+            let ws = match tokio_tungstenite::accept_async(stream).await {
+                Ok(ws) => ws,
+                Err(e) => {
+                    log::warn!("WebSocketSink: handshake with {peer} failed: {e}");
+                    return;
+                }
+            };
+            log::debug!("WebSocketSink: client {peer} connected");
+            let (mut write, read) = ws.split();
+            // drive the reading half so that control frames are answered
+            tokio::spawn(read.for_each(|_| async {}));
+            while let Ok(message) = rx.recv_async().await {
+                if write.send(message).await.is_err() {
+                    log::debug!("WebSocketSink: client {peer} disconnected");
+                    return;
+                }
+            }
+            let _ = write.send(Message::Close(None)).await;
+        });
+    }
+}
+
+impl<Op: Operator> Display for WebSocketSink<Op> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> WebSocketSink({})", self.prev, self.address)
+    }
+}
+
+impl<Op> Operator for WebSocketSink<Op>
+where
+    Op: Operator,
+    Op::Out: Serialize,
+{
+    type Out = ();
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+
+        let listener = std::net::TcpListener::bind(&self.address).unwrap_or_else(|err| {
+            panic!(
+                "WebSocketSink: failed to bind address {}: {:?}",
+                self.address, err
+            )
+        });
+        listener.set_nonblocking(true).unwrap();
+        let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+
+        let (tx, rx) = flume::unbounded();
+        self.listener = Some(tokio::runtime::Handle::current().spawn(listen(listener, tx)));
+        self.new_clients = Some(rx);
+    }
+
+    fn next(&mut self) -> StreamElement<()> {
+        match self.prev.next() {
+            StreamElement::Item(t) | StreamElement::Timestamped(t, _) => {
+                let message = serde_json::to_string(&t).expect("failed to serialize item");
+                self.broadcast(Message::Text(message));
+                StreamElement::Item(())
+            }
+            StreamElement::Watermark(w) => StreamElement::Watermark(w),
+            StreamElement::Terminate => {
+                self.close();
+                StreamElement::Terminate
+            }
+            StreamElement::FlushBatch => StreamElement::FlushBatch,
+            StreamElement::FlushAndRestart => StreamElement::FlushAndRestart,
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<Op::Out, _>("WebSocketSink");
+        operator.kind = OperatorKind::Sink;
+        self.prev.structure().add_operator(operator)
+    }
+}
+
+impl<Op: Operator> Clone for WebSocketSink<Op> {
+    fn clone(&self) -> Self {
+        panic!("WebSocketSink cannot be cloned, replication should be 1");
+    }
+}
+
+impl<Op: Operator> Stream<Op>
+where
+    Op: 'static,
+    Op::Out: ExchangeData,
+{
+    /// Serve the items of the stream to the WebSocket clients connected to `address`.
+    ///
+    /// A single replica listens on `address`, every item is serialized to JSON and sent to all the
+    /// clients connected at that time. The first item is sent only after `min_clients` clients are
+    /// connected, the items of a client that does not keep up are dropped. The connections are
+    /// closed when the stream ends.
+    ///
+    /// **Note**: this is available only with the `websocket` feature.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// env.stream_iter(0..100).write_websocket("0.0.0.0:8080", 1);
+    ///
+    /// env.execute_blocking();
+    /// ```
+    pub fn write_websocket<A: Into<String>>(self, address: A, min_clients: usize) {
+        let address = address.into();
+        self.repartition(Replication::One, NextStrategy::only_one())
+            .add_operator(|prev| WebSocketSink::new(prev, address, min_clients))
+            .finalize_block();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::StreamExt;
+    use itertools::Itertools;
+    use tokio_tungstenite::tungstenite::Message;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+
+    #[test]
+    fn websocket_sink_fan_out() {
+        // find a free port
+        let address = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let clients = (0..2)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    let runtime = tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .unwrap();
+                    runtime.block_on(async move {
+                        let mut ws = loop {
+                            match tokio_tungstenite::connect_async(format!("ws://{address}")).await
+                            {
+                                Ok((ws, _)) => break ws,
+                                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+                            }
+                        };
+                        let mut received = vec![];
+                        while let Some(Ok(message)) = ws.next().await {
+                            match message {
+                                Message::Text(text) => received.push(text),
+                                Message::Close(_) => break,
+                                _ => {}
+                            }
+                        }
+                        received
+                    })
+                })
+            })
+            .collect_vec();
+
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        env.stream_iter(0..5)
+            .map(|n| (n, n * n))
+            .write_websocket(address.to_string(), 2);
+        env.execute_blocking();
+
+        let expected = (0..5).map(|n| format!("[{},{}]", n, n * n)).collect_vec();
+        for client in clients {
+            assert_eq!(client.join().unwrap(), expected);
+        }
+    }
+}
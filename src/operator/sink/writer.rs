@@ -1,7 +1,8 @@
 use serde::Serialize;
-use std::{ffi::OsString, fmt::Display, path::PathBuf};
+use std::{ffi::OsString, fmt::Display, panic::AssertUnwindSafe, path::PathBuf};
 
 use crate::{
+    network::Coord,
     operator::{Operator, StreamElement},
     structure::{OperatorKind, OperatorStructure},
     ExecutionMetadata,
@@ -16,10 +17,18 @@ pub trait WriteOperator<T: Serialize>: Clone + Send {
 }
 
 #[derive(Debug, Clone)]
-pub struct WriterOperator<Op, W, D> {
+pub struct WriterOperator<Op, W, D>
+where
+    Op: Operator,
+    Op::Out: Serialize,
+    W: WriteOperator<Op::Out>,
+{
     prev: Op,
     writer: W,
     make_destination: Option<D>,
+    coord: Option<Coord>,
+    /// The number of records written since the last flush.
+    unflushed: usize,
 }
 
 impl<Op, W, D> WriterOperator<Op, W, D>
@@ -34,11 +43,18 @@ where
             prev,
             writer,
             make_destination: Some(make_destination),
+            coord: None,
+            unflushed: 0,
         }
     }
 }
 
-impl<W, Op: Operator, D> Display for WriterOperator<Op, W, D> {
+impl<Op, W, D> Display for WriterOperator<Op, W, D>
+where
+    Op: Operator,
+    Op::Out: Serialize,
+    W: WriteOperator<Op::Out>,
+{
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
@@ -61,6 +77,7 @@ where
 
     fn setup(&mut self, metadata: &mut ExecutionMetadata) {
         self.prev.setup(metadata);
+        self.coord = Some(metadata.coord);
         self.writer
             .setup((self.make_destination.take().unwrap())(metadata));
     }
@@ -69,6 +86,8 @@ where
         struct SequenceIterator<'a, Op: Operator> {
             prev: &'a mut Op,
             last: Option<StreamElement<Op::Out>>,
+            /// Counted as the items are read, so that it is right even if the writer panics.
+            count: &'a mut usize,
         }
 
         impl<Op: Operator> Iterator for SequenceIterator<'_, Op> {
@@ -76,7 +95,10 @@ where
 
             fn next(&mut self) -> Option<Self::Item> {
                 match self.prev.next() {
-                    StreamElement::Item(item) | StreamElement::Timestamped(item, _) => Some(item),
+                    StreamElement::Item(item) | StreamElement::Timestamped(item, _) => {
+                        *self.count += 1;
+                        Some(item)
+                    }
                     other => {
                         self.last = Some(other);
                         None
@@ -88,12 +110,19 @@ where
         let mut s = SequenceIterator {
             prev: &mut self.prev,
             last: None,
+            count: &mut self.unflushed,
         };
         self.writer.write(&mut s);
         let result = s.last.take().unwrap().variant();
         match &result {
-            StreamElement::FlushBatch | StreamElement::FlushAndRestart => self.writer.flush(),
-            StreamElement::Terminate => self.writer.finalize(),
+            StreamElement::FlushBatch | StreamElement::FlushAndRestart => {
+                self.writer.flush();
+                self.unflushed = 0;
+            }
+            StreamElement::Terminate => {
+                self.writer.finalize();
+                self.unflushed = 0;
+            }
             _ => {}
         }
         result
@@ -109,6 +138,32 @@ where
     }
 }
 
+impl<Op, W, D> Drop for WriterOperator<Op, W, D>
+where
+    Op: Operator,
+    Op::Out: Serialize,
+    W: WriteOperator<Op::Out>,
+{
+    fn drop(&mut self) {
+        // the replica crashed: try to save the records still buffered by the writer
+        if !std::thread::panicking() || self.unflushed == 0 {
+            return;
+        }
+        let records = std::mem::take(&mut self.unflushed);
+        let coord = self.coord.map(|c| c.to_string()).unwrap_or_default();
+        match std::panic::catch_unwind(AssertUnwindSafe(|| self.writer.flush())) {
+            Ok(()) => log::error!(
+                "worker {coord} crashed: flushed the last {records} records of {}",
+                std::any::type_name::<W>()
+            ),
+            Err(_) => log::error!(
+                "worker {coord} crashed: the last {records} records of {} may be lost",
+                std::any::type_name::<W>()
+            ),
+        }
+    }
+}
+
 pub fn sequential_path(base: PathBuf, metadata: &ExecutionMetadata) -> PathBuf {
     let mut path = base;
     let id = metadata.global_id;
@@ -129,3 +184,54 @@ pub fn sequential_path(base: PathBuf, metadata: &ExecutionMetadata) -> PathBuf {
     }
     path
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::sink::writer::{WriteOperator, WriterOperator};
+
+    /// Keep the records in memory until they are flushed.
+    #[derive(Clone, Default)]
+    struct BufferedWrite {
+        buffer: Vec<u32>,
+        flushed: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl WriteOperator<u32> for BufferedWrite {
+        type Destination = ();
+
+        fn setup(&mut self, _destination: ()) {}
+
+        fn write(&mut self, items: &mut impl Iterator<Item = u32>) {
+            self.buffer.extend(items);
+        }
+
+        fn flush(&mut self) {
+            self.flushed.lock().unwrap().append(&mut self.buffer);
+        }
+
+        fn finalize(&mut self) {
+            self.flush();
+        }
+    }
+
+    #[test]
+    fn flush_on_panic() {
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        let writer = BufferedWrite::default();
+        let flushed = writer.flushed.clone();
+        env.stream_iter(0..10u32)
+            .map(|n| {
+                assert!(n < 5, "crash");
+                n
+            })
+            .add_operator(|prev| WriterOperator::new(prev, writer, |_| ()))
+            .finalize_block();
+
+        assert!(env.try_execute_blocking().is_err());
+        assert_eq!(*flushed.lock().unwrap(), vec![0, 1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,450 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::ops::{AddAssign, SubAssign};
+
+use crate::block::{BlockStructure, GroupHasherBuilder, OperatorStructure};
+use crate::operator::{fmt_stage, Data, DataKey, Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::stream::KeyedStream;
+
+/// An aggregate of a sliding window, updated as the elements enter and leave the window.
+///
+/// The elements leave the window in the same order they entered it, so an aggregate that cannot
+/// be inverted, like the minimum, can still be maintained by keeping only the elements that may
+/// become the result. See [`KeyedStream::sliding_aggregate`].
+pub trait SlidingAccumulator<T>: Clone + Send + 'static {
+    /// The result of the aggregate.
+    type Out: Data;
+
+    /// Add the element that entered the window.
+    fn push(&mut self, item: &T);
+
+    /// Remove the oldest element of the window.
+    fn pop(&mut self, item: &T);
+
+    /// The result of the aggregate of the elements in the window, which is not empty.
+    fn output(&self) -> Self::Out;
+}
+
+/// The sum of the elements of a sliding window.
+///
+/// The sum is updated by subtracting the elements that leave the window, so with floating point
+/// numbers the rounding errors accumulate over time.
+#[derive(Clone, Debug, Default)]
+pub struct SlidingSum<T>(T);
+
+impl<T: Default> SlidingSum<T> {
+    pub fn new() -> Self {
+        Self(T::default())
+    }
+}
+
+impl<T> SlidingAccumulator<T> for SlidingSum<T>
+where
+    T: Data + AddAssign + SubAssign,
+{
+    type Out = T;
+
+    fn push(&mut self, item: &T) {
+        self.0 += item.clone();
+    }
+
+    fn pop(&mut self, item: &T) {
+        self.0 -= item.clone();
+    }
+
+    fn output(&self) -> T {
+        self.0.clone()
+    }
+}
+
+/// The number of elements of a sliding window.
+#[derive(Clone, Debug, Default)]
+pub struct SlidingCount(usize);
+
+impl SlidingCount {
+    pub fn new() -> Self {
+        Self(0)
+    }
+}
+
+impl<T> SlidingAccumulator<T> for SlidingCount {
+    type Out = usize;
+
+    fn push(&mut self, _item: &T) {
+        self.0 += 1;
+    }
+
+    fn pop(&mut self, _item: &T) {
+        self.0 -= 1;
+    }
+
+    fn output(&self) -> usize {
+        self.0
+    }
+}
+
+/// The mean of the elements of a sliding window.
+#[derive(Clone, Debug, Default)]
+pub struct SlidingMean {
+    sum: f64,
+    count: usize,
+}
+
+impl SlidingMean {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T> SlidingAccumulator<T> for SlidingMean
+where
+    T: Clone + Into<f64>,
+{
+    type Out = f64;
+
+    fn push(&mut self, item: &T) {
+        self.sum += item.clone().into();
+        self.count += 1;
+    }
+
+    fn pop(&mut self, item: &T) {
+        self.sum -= item.clone().into();
+        self.count -= 1;
+    }
+
+    fn output(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+}
+
+/// The minimum of the elements of a sliding window.
+///
+/// Only the elements smaller than all the ones that entered the window after them are kept, in
+/// increasing order, so each element is added and removed at most once.
+#[derive(Clone, Debug)]
+pub struct SlidingMin<T>(VecDeque<T>);
+
+impl<T> SlidingMin<T> {
+    pub fn new() -> Self {
+        Self(VecDeque::new())
+    }
+}
+
+impl<T> Default for SlidingMin<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Data + Ord> SlidingAccumulator<T> for SlidingMin<T> {
+    type Out = T;
+
+    fn push(&mut self, item: &T) {
+        while self.0.back().is_some_and(|last| last > item) {
+            self.0.pop_back();
+        }
+        self.0.push_back(item.clone());
+    }
+
+    fn pop(&mut self, item: &T) {
+        if self.0.front() == Some(item) {
+            self.0.pop_front();
+        }
+    }
+
+    fn output(&self) -> T {
+        self.0.front().expect("the window is empty").clone()
+    }
+}
+
+/// The maximum of the elements of a sliding window.
+///
+/// Only the elements greater than all the ones that entered the window after them are kept, in
+/// decreasing order, so each element is added and removed at most once.
+#[derive(Clone, Debug)]
+pub struct SlidingMax<T>(VecDeque<T>);
+
+impl<T> SlidingMax<T> {
+    pub fn new() -> Self {
+        Self(VecDeque::new())
+    }
+}
+
+impl<T> Default for SlidingMax<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Data + Ord> SlidingAccumulator<T> for SlidingMax<T> {
+    type Out = T;
+
+    fn push(&mut self, item: &T) {
+        while self.0.back().is_some_and(|last| last < item) {
+            self.0.pop_back();
+        }
+        self.0.push_back(item.clone());
+    }
+
+    fn pop(&mut self, item: &T) {
+        if self.0.front() == Some(item) {
+            self.0.pop_front();
+        }
+    }
+
+    fn output(&self) -> T {
+        self.0.front().expect("the window is empty").clone()
+    }
+}
+
+/// The sliding window of a key.
+#[derive(Clone)]
+struct Window<V, A> {
+    /// The elements in the window, from the oldest.
+    elements: VecDeque<V>,
+    acc: A,
+    /// The number of elements received by the key.
+    seen: usize,
+}
+
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+struct SlidingAggregate<K, V, A, Op>
+where
+    K: DataKey,
+    Op: Operator<Out = (K, V)>,
+{
+    prev: Op,
+    size: usize,
+    slide: usize,
+    #[derivative(Debug = "ignore")]
+    init: A,
+    #[derivative(Debug = "ignore")]
+    windows: HashMap<K, Window<V, A>, GroupHasherBuilder>,
+}
+
+impl<K, V, A, Op> SlidingAggregate<K, V, A, Op>
+where
+    K: DataKey,
+    Op: Operator<Out = (K, V)>,
+{
+    fn new(prev: Op, size: usize, slide: usize, init: A) -> Self {
+        assert!(size > 0, "window size must be > 0");
+        assert!(slide > 0, "window slide must be > 0");
+        Self {
+            prev,
+            size,
+            slide,
+            init,
+            windows: Default::default(),
+        }
+    }
+}
+
+impl<K, V, A, Op> Display for SlidingAggregate<K, V, A, Op>
+where
+    K: DataKey,
+    V: Data,
+    A: SlidingAccumulator<V>,
+    Op: Operator<Out = (K, V)>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, (K, A::Out)>(f, &self.prev, "SlidingAggregate")
+    }
+}
+
+impl<K, V, A, Op> Operator for SlidingAggregate<K, V, A, Op>
+where
+    K: DataKey,
+    V: Data,
+    A: SlidingAccumulator<V>,
+    Op: Operator<Out = (K, V)>,
+{
+    type Out = (K, A::Out);
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        loop {
+            let el = self.prev.next();
+            let ts = el.timestamp().cloned();
+            match el {
+                StreamElement::Item((key, value)) | StreamElement::Timestamped((key, value), _) => {
+                    let window = self.windows.entry(key.clone()).or_insert_with(|| Window {
+                        elements: VecDeque::with_capacity(self.size),
+                        acc: self.init.clone(),
+                        seen: 0,
+                    });
+                    window.acc.push(&value);
+                    window.elements.push_back(value);
+                    if window.elements.len() > self.size {
+                        let oldest = window.elements.pop_front().unwrap();
+                        window.acc.pop(&oldest);
+                    }
+                    window.seen += 1;
+
+                    // the i-th window ends with the (i * slide + size)-th element
+                    if window.seen >= self.size
+                        && (window.seen - self.size).is_multiple_of(self.slide)
+                    {
+                        let out = (key, window.acc.output());
+                        return match ts {
+                            Some(ts) => StreamElement::Timestamped(out, ts),
+                            None => StreamElement::Item(out),
+                        };
+                    }
+                }
+                StreamElement::Watermark(ts) => return StreamElement::Watermark(ts),
+                StreamElement::FlushBatch => return StreamElement::FlushBatch,
+                StreamElement::FlushAndRestart => {
+                    self.windows.clear();
+                    return StreamElement::FlushAndRestart;
+                }
+                StreamElement::Terminate => return StreamElement::Terminate,
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<Self::Out, _>("SlidingAggregate");
+        operator.subtitle = format!("size {}, slide {}", self.size, self.slide);
+        self.prev.structure().add_operator(operator)
+    }
+}
+
+impl<K, V, Op> KeyedStream<Op>
+where
+    K: DataKey,
+    V: Data,
+    Op: Operator<Out = (K, V)> + 'static,
+{
+    /// Aggregate the sliding windows of `size` elements of each key, emitting the result of a
+    /// window every `slide` elements.
+    ///
+    /// Instead of aggregating each window from scratch, the accumulator is updated as the
+    /// elements enter and leave the window (see [`SlidingAccumulator`]), so the cost for each
+    /// element does not depend on the size of the window. The elements in the window are still
+    /// buffered, to remove them when they leave it. This produces the same results of
+    /// [`CountWindow::sliding`](crate::operator::window::CountWindow::sliding): only the complete
+    /// windows are emitted, and the windows are discarded at the end of the stream.
+    ///
+    /// The accumulators available are [`SlidingSum`], [`SlidingCount`], [`SlidingMean`],
+    /// [`SlidingMin`] and [`SlidingMax`].
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::SlidingSum;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..6u32);
+    /// let res = s
+    ///     .group_by(|&n| n % 2)
+    ///     .sliding_aggregate(2, 1, SlidingSum::new())
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, vec![(0, 2), (0, 6), (1, 4), (1, 8)]);
+    /// ```
+    pub fn sliding_aggregate<A>(
+        self,
+        size: usize,
+        slide: usize,
+        accumulator: A,
+    ) -> KeyedStream<impl Operator<Out = (K, A::Out)>>
+    where
+        A: SlidingAccumulator<V>,
+    {
+        self.add_operator(|prev| SlidingAggregate::new(prev, size, slide, accumulator))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use itertools::Itertools;
+
+    use super::{SlidingAccumulator, SlidingMax, SlidingMin, SlidingSum};
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::window::CountWindow;
+
+    /// A sum that counts the updates of the accumulators.
+    #[derive(Clone)]
+    struct CountingSum(SlidingSum<u64>, Arc<AtomicUsize>);
+
+    impl SlidingAccumulator<u64> for CountingSum {
+        type Out = u64;
+
+        fn push(&mut self, item: &u64) {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            self.0.push(item);
+        }
+
+        fn pop(&mut self, item: &u64) {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            self.0.pop(item);
+        }
+
+        fn output(&self) -> u64 {
+            self.0.output()
+        }
+    }
+
+    #[test]
+    fn sliding_sum_same_as_window() {
+        const N: u64 = 10_000;
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let updates = Arc::new(AtomicUsize::new(0));
+        let mut split = env.stream_iter(0..N).split(2).into_iter();
+        let sliding = split
+            .next()
+            .unwrap()
+            .group_by(|n| n % 7)
+            .sliding_aggregate(100, 3, CountingSum(SlidingSum::new(), updates.clone()))
+            .collect_vec();
+        let windowed = split
+            .next()
+            .unwrap()
+            .group_by(|n| n % 7)
+            .window(CountWindow::sliding(100, 3))
+            .sum::<u64>()
+            .collect_vec();
+        env.execute_blocking();
+
+        let sliding = sliding.get().unwrap().into_iter().sorted().collect_vec();
+        let windowed = windowed.get().unwrap().into_iter().sorted().collect_vec();
+        assert!(!sliding.is_empty());
+        assert_eq!(sliding, windowed);
+        // each element enters and leaves the window once, instead of being added to 100 / 3
+        // windows
+        assert!(updates.load(Ordering::Relaxed) <= 2 * N as usize);
+    }
+
+    #[test]
+    fn sliding_min_max() {
+        let items = [5, 3, 8, 3, 1, 9, 9, 2, 7, 4, 6, 6, 0];
+        let mut min = SlidingMin::new();
+        let mut max = SlidingMax::new();
+        for (i, item) in items.iter().enumerate() {
+            if i >= 3 {
+                min.pop(&items[i - 3]);
+                max.pop(&items[i - 3]);
+            }
+            min.push(item);
+            max.push(item);
+
+            let window = &items[i.saturating_sub(2)..=i];
+            assert_eq!(min.output(), *window.iter().min().unwrap());
+            assert_eq!(max.output(), *window.iter().max().unwrap());
+        }
+    }
+}
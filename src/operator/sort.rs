@@ -0,0 +1,358 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+
+use crate::block::{BlockStructure, OperatorStructure, Replication};
+use crate::operator::spill::{estimated_size, MemoryBudget, SpilledRun};
+use crate::operator::{fmt_stage, ExchangeData, Operator, StreamElement, Timestamp};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// Buffer all the elements until the end of the stream, then emit them sorted by `cmp`.
+///
+/// With a memory budget, the buffered elements exceeding it are sorted and spilled to disk in
+/// runs, which are merged back with the elements in memory at the end of the stream.
+pub(crate) struct Sort<Op, F>
+where
+    Op: Operator,
+{
+    prev: Op,
+    cmp: F,
+    budget: Option<MemoryBudget>,
+    /// The elements received, in reverse order once the end of the stream has been received.
+    buffer: Vec<(Op::Out, Option<Timestamp>)>,
+    /// The estimated size of the elements in `buffer`.
+    buffered_bytes: usize,
+    /// The sorted runs spilled to disk, in the order they were received.
+    runs: Vec<SpilledRun<(Op::Out, Option<Timestamp>)>>,
+    /// While merging, the next element of each run, followed by the next element of `buffer`.
+    heads: Vec<Option<(Op::Out, Option<Timestamp>)>>,
+    /// The last watermark received, forwarded after the sorted elements.
+    watermark: Option<Timestamp>,
+    /// The end of the stream received, forwarded after the sorted elements.
+    end: Option<StreamElement<Op::Out>>,
+}
+
+impl<Op: Clone, F: Clone> Clone for Sort<Op, F>
+where
+    Op: Operator,
+{
+    fn clone(&self) -> Self {
+        Self {
+            prev: self.prev.clone(),
+            cmp: self.cmp.clone(),
+            budget: self.budget.clone(),
+            buffer: Default::default(),
+            buffered_bytes: 0,
+            runs: Default::default(),
+            heads: Default::default(),
+            watermark: None,
+            end: None,
+        }
+    }
+}
+
+impl<Op, F> Display for Sort<Op, F>
+where
+    Op: Operator,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, Op::Out>(f, &self.prev, "Sort")
+    }
+}
+
+impl<Op, F> Sort<Op, F>
+where
+    Op: Operator,
+    Op::Out: ExchangeData,
+    F: Fn(&Op::Out, &Op::Out) -> Ordering + Clone + Send,
+{
+    pub(crate) fn new(prev: Op, cmp: F, budget: Option<MemoryBudget>) -> Self {
+        Self {
+            prev,
+            cmp,
+            budget,
+            buffer: Default::default(),
+            buffered_bytes: 0,
+            runs: Default::default(),
+            heads: Default::default(),
+            watermark: None,
+            end: None,
+        }
+    }
+
+    /// Buffer an element, spilling the buffer if it exceeds the budget.
+    fn push(&mut self, item: Op::Out, ts: Option<Timestamp>) {
+        let element = (item, ts);
+        let Some(budget) = &self.budget else {
+            self.buffer.push(element);
+            return;
+        };
+        self.buffered_bytes += estimated_size(&element);
+        self.buffer.push(element);
+        if self.buffered_bytes > budget.max_bytes() {
+            log::debug!(
+                "sort: spilling {} elements ({} bytes)",
+                self.buffer.len(),
+                self.buffered_bytes
+            );
+            let cmp = &self.cmp;
+            glidesort::sort_by(&mut self.buffer, |a, b| cmp(&a.0, &b.0));
+            self.runs
+                .push(SpilledRun::write(&budget.dir(), &self.buffer));
+            self.buffer.clear();
+            self.buffered_bytes = 0;
+        }
+    }
+
+    /// The next element in order, once the end of the stream has been received.
+    fn next_sorted(&mut self) -> Option<(Op::Out, Option<Timestamp>)> {
+        if self.runs.is_empty() {
+            return self.buffer.pop();
+        }
+        // the runs are in the order the elements were received, so on a tie the first run wins
+        let cmp = &self.cmp;
+        let mut min: Option<(usize, &Op::Out)> = None;
+        for (i, head) in self.heads.iter().enumerate() {
+            if let Some((item, _)) = head {
+                let smaller = match min {
+                    Some((_, m)) => cmp(item, m) == Ordering::Less,
+                    None => true,
+                };
+                if smaller {
+                    min = Some((i, item));
+                }
+            }
+        }
+        let (i, _) = min?;
+        let refill = match self.runs.get_mut(i) {
+            Some(run) => run.next(),
+            None => self.buffer.pop(),
+        };
+        std::mem::replace(&mut self.heads[i], refill)
+    }
+}
+
+impl<Op, F> Operator for Sort<Op, F>
+where
+    Op: Operator,
+    Op::Out: ExchangeData,
+    F: Fn(&Op::Out, &Op::Out) -> Ordering + Clone + Send,
+{
+    type Out = Op::Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<Op::Out> {
+        loop {
+            if self.end.is_some() {
+                if let Some((item, ts)) = self.next_sorted() {
+                    return match ts {
+                        Some(ts) => StreamElement::Timestamped(item, ts),
+                        None => StreamElement::Item(item),
+                    };
+                }
+                if let Some(w) = self.watermark.take() {
+                    return StreamElement::Watermark(w);
+                }
+                // the spilled runs are all read, this removes their files
+                self.runs.clear();
+                self.heads.clear();
+                return self.end.take().unwrap();
+            }
+
+            match self.prev.next() {
+                StreamElement::Item(item) => self.push(item, None),
+                StreamElement::Timestamped(item, ts) => self.push(item, Some(ts)),
+                StreamElement::Watermark(w) => self.watermark = Some(w),
+                StreamElement::FlushBatch => return StreamElement::FlushBatch,
+                end @ (StreamElement::FlushAndRestart | StreamElement::Terminate) => {
+                    // the sort is stable, and the elements are popped from the back
+                    let cmp = &self.cmp;
+                    glidesort::sort_by(&mut self.buffer, |a, b| cmp(&a.0, &b.0));
+                    self.buffer.reverse();
+                    self.buffered_bytes = 0;
+                    if !self.runs.is_empty() {
+                        self.heads = self.runs.iter_mut().map(|run| run.next()).collect();
+                        self.heads.push(self.buffer.pop());
+                    }
+                    self.end = Some(end);
+                }
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<Op::Out, _>("Sort"))
+    }
+}
+
+impl<I, Op> Stream<Op>
+where
+    I: ExchangeData,
+    Op: Operator<Out = I> + 'static,
+{
+    /// Sort the elements of the stream with the comparator function `cmp`.
+    ///
+    /// Each replica sorts its elements, then the sorted runs are merged by a single replica that
+    /// emits all the elements in order.
+    ///
+    /// The elements are emitted only when the stream ends, so this can only be used on bounded
+    /// streams: it panics if the stream comes from an unbounded source. Inside an iteration, the
+    /// elements are sorted at the end of each iteration.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_par_iter(0..10u32).shuffle();
+    /// let res = s.sort_by(|a, b| b.cmp(a)).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), (0..10).rev().collect::<Vec<_>>());
+    /// ```
+    pub fn sort_by<F>(self, cmp: F) -> Stream<impl Operator<Out = I>>
+    where
+        F: Fn(&I, &I) -> Ordering + Clone + Send + 'static,
+    {
+        self.sort_by_budget(cmp, None)
+    }
+
+    /// Sort the elements of the stream like [`Stream::sort_by`], buffering at most `budget`
+    /// bytes of elements in memory in each replica.
+    ///
+    /// When the elements buffered exceed the budget, they are sorted and spilled to a file in
+    /// the directory of the budget. At the end of the stream the spilled runs are read back and
+    /// merged with the elements still in memory, so the output is the same as
+    /// [`Stream::sort_by`], and the files are removed.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// use renoir::operator::MemoryBudget;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_par_iter(0..10_000u32).shuffle();
+    /// let res = s
+    ///     .sort_by_with_memory_budget(|a, b| a.cmp(b), MemoryBudget::new(16 << 10))
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), (0..10_000).collect::<Vec<_>>());
+    /// ```
+    pub fn sort_by_with_memory_budget<F>(
+        self,
+        cmp: F,
+        budget: MemoryBudget,
+    ) -> Stream<impl Operator<Out = I>>
+    where
+        F: Fn(&I, &I) -> Ordering + Clone + Send + 'static,
+    {
+        self.sort_by_budget(cmp, Some(budget))
+    }
+
+    fn sort_by_budget<F>(
+        self,
+        cmp: F,
+        budget: Option<MemoryBudget>,
+    ) -> Stream<impl Operator<Out = I>>
+    where
+        F: Fn(&I, &I) -> Ordering + Clone + Send + 'static,
+    {
+        assert!(
+            !self.block.unbounded,
+            "sort_by requires a bounded stream, but the stream comes from an unbounded source"
+        );
+        let merge = cmp.clone();
+        let merge_budget = budget.clone();
+        self.add_operator(|prev| Sort::new(prev, cmp, budget))
+            .replication(Replication::One)
+            // the batches received are already sorted runs, that glidesort merges efficiently
+            .add_operator(|prev| Sort::new(prev, merge, merge_budget))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::MemoryBudget;
+
+    #[test]
+    fn sort_shuffled_input() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env
+            .stream_par_iter(0..10_000u32)
+            .shuffle()
+            .sort_by(|a, b| a.cmp(b))
+            .collect_vec();
+        env.execute_blocking();
+        assert_eq!(res.get().unwrap(), (0..10_000).collect_vec());
+    }
+
+    #[test]
+    fn sort_by_field() {
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        let res = env
+            .stream_iter((0..100u32).rev().map(|n| (n % 3, n)))
+            .sort_by(|a, b| a.0.cmp(&b.0))
+            .collect_vec();
+        env.execute_blocking();
+        let expected = (0..3)
+            .flat_map(|k| {
+                (0..100u32)
+                    .rev()
+                    .filter(move |n| n % 3 == k)
+                    .map(move |n| (k, n))
+            })
+            .collect_vec();
+        assert_eq!(res.get().unwrap(), expected);
+    }
+
+    #[test]
+    fn sort_spilling_to_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        // about 30 elements fit in the budget, so the elements are spilled in many runs
+        let budget = MemoryBudget::new(512).spill_dir(dir.path());
+        let res = env
+            .stream_iter((0..1000u32).rev().map(|n| (n % 3, n)))
+            .sort_by_with_memory_budget(|a, b| a.0.cmp(&b.0), budget)
+            .collect_vec();
+        env.execute_blocking();
+        // the merge of the runs keeps the sort stable
+        let expected = (0..3)
+            .flat_map(|k| {
+                (0..1000u32)
+                    .rev()
+                    .filter(move |n| n % 3 == k)
+                    .map(move |n| (k, n))
+            })
+            .collect_vec();
+        assert_eq!(res.get().unwrap(), expected);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[cfg(feature = "notify")]
+    #[test]
+    #[should_panic(expected = "sort_by requires a bounded stream")]
+    fn sort_unbounded_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        env.stream(crate::operator::source::WatchDirSource::new(dir.path()))
+            .sort_by(|a, b| a.cmp(b))
+            .for_each(|_| {});
+    }
+}
@@ -0,0 +1,391 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+use crate::block::{BlockStructure, OperatorStructure, Replication};
+use crate::network::Coord;
+use crate::operator::source::{Source, StopHandle};
+use crate::operator::{Operator, StreamElement};
+use crate::profiler::{get_profiler, DropReason, Profiler};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// What a source does with its items when the rest of the pipeline cannot keep up.
+///
+/// See [`Stream::backpressure`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum BackpressureStrategy {
+    /// Stop reading from the source until there is room for the new items.
+    #[default]
+    Block,
+    /// Drop the new items.
+    DropNewest,
+    /// Drop the oldest items waiting to be processed to make room for the new ones.
+    DropOldest,
+    /// Keep only the given fraction (between 0 and 1) of the new items, making room for them by
+    /// dropping the oldest ones, and drop the others.
+    Sample(f64),
+}
+
+/// The items read from the source and not yet taken by the rest of the block.
+struct Queue<Out> {
+    elements: VecDeque<StreamElement<Out>>,
+    /// The source stopped producing, either because it terminated or because it panicked.
+    finished: bool,
+    /// The operator has been dropped, the source should stop.
+    closed: bool,
+    /// The number of items dropped.
+    dropped: u64,
+    /// The fraction of item accumulated by `Sample`: an item is admitted when it reaches 1.
+    credit: f64,
+}
+
+struct Shared<Out> {
+    queue: Mutex<Queue<Out>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+/// Marks the queue as finished when the thread of the source exits, even if it panics.
+struct FinishGuard<Out>(Arc<Shared<Out>>);
+
+impl<Out> Drop for FinishGuard<Out> {
+    fn drop(&mut self) {
+        let mut queue = self.0.queue.lock().unwrap_or_else(|e| e.into_inner());
+        queue.finished = true;
+        self.0.not_empty.notify_all();
+    }
+}
+
+/// Source that reads another source in a separate thread, applying a [`BackpressureStrategy`]
+/// when the rest of the pipeline cannot keep up with it.
+///
+/// Build it with [`Stream::backpressure`].
+pub struct BackpressureSource<S: Source> {
+    /// The wrapped source, moved to its own thread at setup.
+    inner: Option<S>,
+    strategy: BackpressureStrategy,
+    capacity: usize,
+    shared: Arc<Shared<S::Out>>,
+    thread: Option<JoinHandle<()>>,
+    /// The structure of the wrapped source, kept after it has been moved.
+    structure: Option<BlockStructure>,
+}
+
+impl<S: Source> BackpressureSource<S> {
+    fn new(inner: S, strategy: BackpressureStrategy, capacity: usize) -> Self {
+        assert!(capacity > 0, "the backpressure capacity must be positive");
+        if let BackpressureStrategy::Sample(rate) = strategy {
+            assert!(
+                rate > 0.0 && rate <= 1.0,
+                "the sample rate must be between 0 and 1"
+            );
+        }
+        Self {
+            inner: Some(inner),
+            strategy,
+            capacity,
+            shared: Arc::new(Shared {
+                queue: Mutex::new(Queue {
+                    elements: Default::default(),
+                    finished: false,
+                    closed: false,
+                    dropped: 0,
+                    credit: 0.0,
+                }),
+                not_empty: Condvar::new(),
+                not_full: Condvar::new(),
+            }),
+            thread: None,
+            structure: None,
+        }
+    }
+}
+
+/// Read the elements of the source, putting them into the queue.
+fn pump<S: Source>(
+    mut source: S,
+    shared: Arc<Shared<S::Out>>,
+    strategy: BackpressureStrategy,
+    capacity: usize,
+    coord: Coord,
+) {
+    let _guard = FinishGuard(shared.clone());
+    loop {
+        let element = source.next();
+        let terminate = matches!(element, StreamElement::Terminate);
+        let is_item = matches!(
+            element,
+            StreamElement::Item(_) | StreamElement::Timestamped(_, _)
+        );
+
+        let mut queue = shared.queue.lock().unwrap();
+        if queue.elements.len() >= capacity && is_item {
+            let admit = match strategy {
+                BackpressureStrategy::Block => true,
+                BackpressureStrategy::DropNewest => false,
+                BackpressureStrategy::DropOldest => drop_oldest(&mut queue, coord),
+                BackpressureStrategy::Sample(rate) => {
+                    queue.credit += rate;
+                    if queue.credit >= 1.0 {
+                        queue.credit -= 1.0;
+                        drop_oldest(&mut queue, coord)
+                    } else {
+                        false
+                    }
+                }
+            };
+            if !admit {
+                queue.dropped += 1;
+                get_profiler().dropped(coord, "Backpressure", DropReason::Backpressure, 1);
+                continue;
+            }
+        }
+        // the control messages are never dropped
+        while queue.elements.len() >= capacity && !queue.closed {
+            queue = shared.not_full.wait(queue).unwrap();
+        }
+        if queue.closed {
+            return;
+        }
+        queue.elements.push_back(element);
+        shared.not_empty.notify_one();
+        if terminate {
+            if queue.dropped > 0 {
+                log::warn!(
+                    "{}: dropped {} items due to backpressure",
+                    std::any::type_name::<S>(),
+                    queue.dropped
+                );
+            }
+            return;
+        }
+    }
+}
+
+/// Drop the oldest item in the queue, returns whether there is now room for a new one.
+fn drop_oldest<Out>(queue: &mut Queue<Out>, coord: Coord) -> bool {
+    let oldest = queue.elements.iter().position(|element| {
+        matches!(
+            element,
+            StreamElement::Item(_) | StreamElement::Timestamped(_, _)
+        )
+    });
+    match oldest {
+        Some(index) => {
+            queue.elements.remove(index);
+            queue.dropped += 1;
+            get_profiler().dropped(coord, "Backpressure", DropReason::Backpressure, 1);
+            true
+        }
+        // only control messages are waiting, the new item waits for them to be taken
+        None => true,
+    }
+}
+
+impl<S> Operator for BackpressureSource<S>
+where
+    S: Source + 'static,
+    S::Out: 'static,
+{
+    type Out = S::Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        let mut source = self.inner.take().expect("BackpressureSource set up twice");
+        source.setup(metadata);
+        self.structure = Some(source.structure());
+        let shared = self.shared.clone();
+        let strategy = self.strategy;
+        let capacity = self.capacity;
+        let coord = metadata.coord;
+        let thread = std::thread::Builder::new()
+            .name(format!("backpressure-{}", metadata.coord))
+            .spawn(move || pump(source, shared, strategy, capacity, coord))
+            .expect("failed to spawn the backpressure thread");
+        self.thread = Some(thread);
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        let mut queue = self.shared.queue.lock().unwrap();
+        loop {
+            if let Some(element) = queue.elements.pop_front() {
+                self.shared.not_full.notify_one();
+                return element;
+            }
+            if queue.finished {
+                drop(queue);
+                // the source stopped without sending Terminate: it panicked
+                if let Some(thread) = self.thread.take() {
+                    if let Err(e) = thread.join() {
+                        std::panic::resume_unwind(e);
+                    }
+                }
+                return StreamElement::Terminate;
+            }
+            queue = self.shared.not_empty.wait(queue).unwrap();
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let structure = match (&self.inner, &self.structure) {
+            (Some(inner), _) => inner.structure(),
+            (None, Some(structure)) => structure.clone(),
+            (None, None) => BlockStructure::default(),
+        };
+        let mut operator = OperatorStructure::new::<S::Out, _>("Backpressure");
+        operator.subtitle = format!("{:?}, capacity: {}", self.strategy, self.capacity);
+        structure.add_operator(operator)
+    }
+}
+
+impl<S> Source for BackpressureSource<S>
+where
+    S: Source + 'static,
+    S::Out: 'static,
+{
+    fn replication(&self) -> Replication {
+        self.inner
+            .as_ref()
+            .map(|inner| inner.replication())
+            .unwrap_or_default()
+    }
+
+    fn stop_handle(&self) -> Option<StopHandle> {
+        self.inner.as_ref().and_then(|inner| inner.stop_handle())
+    }
+
+    fn is_bounded(&self) -> bool {
+        self.inner.as_ref().is_none_or(|inner| inner.is_bounded())
+    }
+}
+
+impl<S: Source> Clone for BackpressureSource<S> {
+    fn clone(&self) -> Self {
+        let inner = self
+            .inner
+            .clone()
+            .expect("BackpressureSource cloned after setup");
+        Self::new(inner, self.strategy, self.capacity)
+    }
+}
+
+impl<S: Source> Display for BackpressureSource<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.inner {
+            Some(inner) => write!(f, "{inner} -> Backpressure[{:?}]", self.strategy),
+            None => write!(f, "Backpressure[{:?}]", self.strategy),
+        }
+    }
+}
+
+impl<S: Source> std::fmt::Debug for BackpressureSource<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackpressureSource")
+            .field("strategy", &self.strategy)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl<S: Source> Drop for BackpressureSource<S> {
+    fn drop(&mut self) {
+        let mut queue = self.shared.queue.lock().unwrap_or_else(|e| e.into_inner());
+        queue.closed = true;
+        self.shared.not_full.notify_all();
+    }
+}
+
+impl<S> Stream<S>
+where
+    S: Source + 'static,
+    S::Out: 'static,
+{
+    /// Decouple the source from the rest of the pipeline, applying `strategy` when the pipeline
+    /// cannot keep up with it.
+    ///
+    /// The source is read in a separate thread, and up to `capacity` items read from it wait to
+    /// be processed. When they are all waiting, the new items are handled by the strategy: with
+    /// [`BackpressureStrategy::Block`] the source waits, like it does without this operator,
+    /// while the other strategies drop some items so that the source never stalls. This is meant
+    /// for best-effort pipelines that prefer fresh data over complete data, like real-time
+    /// dashboards. The watermarks and the other control messages are never dropped, and the
+    /// number of dropped items is logged when the stream ends and reported to the profiler as
+    /// `backpressure`.
+    ///
+    /// This must be called right after creating the stream from the source.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::source::BackpressureStrategy;
+    /// # let mut env = StreamContext::new_local();
+    /// let res = env
+    ///     .stream_iter(0..1000)
+    ///     .backpressure(BackpressureStrategy::DropOldest, 128)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    /// # assert!(res.get().unwrap().contains(&999));
+    /// ```
+    pub fn backpressure(
+        self,
+        strategy: BackpressureStrategy,
+        capacity: usize,
+    ) -> Stream<BackpressureSource<S>> {
+        self.add_operator(|source| BackpressureSource::new(source, strategy, capacity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use itertools::Itertools;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::{BackpressureStrategy, IteratorSource};
+
+    fn saturated(strategy: BackpressureStrategy) -> Vec<u32> {
+        let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+        let res = env
+            .stream(IteratorSource::new(0..5000u32))
+            .backpressure(strategy, 16)
+            .map(|x| {
+                std::thread::sleep(Duration::from_micros(200));
+                x
+            })
+            .collect_vec();
+        env.execute_blocking();
+        res.get().unwrap()
+    }
+
+    #[test]
+    fn backpressure_drop_oldest() {
+        let res = saturated(BackpressureStrategy::DropOldest);
+        assert!(res.len() < 5000, "nothing was dropped");
+        // the newest items keep flowing
+        assert_eq!(res.last(), Some(&4999));
+        assert!(res.iter().tuple_windows().all(|(a, b)| a < b));
+    }
+
+    #[test]
+    fn backpressure_drop_newest() {
+        let res = saturated(BackpressureStrategy::DropNewest);
+        assert!(res.len() < 5000, "nothing was dropped");
+        assert_eq!(res[..16], (0..16).collect_vec());
+    }
+
+    #[test]
+    fn backpressure_block() {
+        let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+        let res = env
+            .stream(IteratorSource::new(0..1000u32))
+            .backpressure(BackpressureStrategy::Block, 4)
+            .collect_vec();
+        env.execute_blocking();
+        assert_eq!(res.get().unwrap(), (0..1000).collect_vec());
+    }
+}
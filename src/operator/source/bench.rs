@@ -0,0 +1,158 @@
+use std::fmt::Display;
+
+use nanorand::{Rng, WyRand};
+
+use crate::block::{BlockStructure, OperatorKind, OperatorStructure, Replication};
+use crate::operator::source::Source;
+use crate::operator::{Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+
+/// Source for throughput benchmarks, emitting a fixed number of byte payloads of a fixed size as
+/// fast as possible.
+///
+/// The payloads are filled with pseudo-random bytes generated from a fixed seed, so every run
+/// emits the same elements. Each payload is a `Vec<u8>` of exactly `size` bytes: when sent over
+/// the network it is serialized with its length, a few bytes more. The elements are split among
+/// all the replicas of the source.
+///
+/// The source does not measure anything itself: pair it with a sink that drops the elements
+/// (e.g. [`Stream::for_each`](crate::Stream::for_each)) and measure the execution.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct BenchSource {
+    count: u64,
+    size: usize,
+    seed: u64,
+    /// The number of elements this replica has still to emit.
+    remaining: u64,
+    #[derivative(Debug = "ignore")]
+    rng: WyRand,
+    terminated: bool,
+}
+
+impl Display for BenchSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BenchSource<{} bytes>", self.size)
+    }
+}
+
+impl BenchSource {
+    /// Create a new source emitting `count` elements of `size` bytes in total.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::source::BenchSource;
+    /// # let mut env = StreamContext::new_local();
+    /// let source = BenchSource::new(1000, 128).seed(42);
+    /// env.stream(source).shuffle().for_each(|_payload| {});
+    ///
+    /// env.execute_blocking();
+    /// ```
+    pub fn new(count: u64, size: usize) -> Self {
+        Self {
+            count,
+            size,
+            seed: 0,
+            remaining: 0,
+            rng: WyRand::new_seed(0),
+            terminated: false,
+        }
+    }
+
+    /// Set the seed of the generated payloads, `0` by default.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    fn payload(&mut self) -> Vec<u8> {
+        let mut payload = vec![0; self.size];
+        for chunk in payload.chunks_mut(8) {
+            let bytes = self.rng.generate::<u64>().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+        payload
+    }
+}
+
+impl Source for BenchSource {
+    fn replication(&self) -> Replication {
+        Replication::Unlimited
+    }
+}
+
+impl Operator for BenchSource {
+    type Out = Vec<u8>;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        let replicas = metadata.replicas.len() as u64;
+        let id = metadata.global_id;
+        // the first replicas emit one more element when the count is not a multiple
+        self.remaining = self.count / replicas + u64::from(id < self.count % replicas);
+        self.rng = WyRand::new_seed(self.seed.wrapping_add(id));
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        if self.terminated {
+            return StreamElement::Terminate;
+        }
+        if self.remaining == 0 {
+            self.terminated = true;
+            return StreamElement::FlushAndRestart;
+        }
+        self.remaining -= 1;
+        StreamElement::Item(self.payload())
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<Self::Out, _>("BenchSource");
+        operator.kind = OperatorKind::Source;
+        BlockStructure::default().add_operator(operator)
+    }
+}
+
+impl Clone for BenchSource {
+    fn clone(&self) -> Self {
+        Self::new(self.count, self.size).seed(self.seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::BenchSource;
+
+    fn run(parallelism: u64, count: u64, size: usize) -> Vec<Vec<u8>> {
+        let env = StreamContext::new(RuntimeConfig::local(parallelism).unwrap());
+        let res = env
+            .stream(BenchSource::new(count, size).seed(7))
+            .collect_vec();
+        env.execute_blocking();
+        res.get().unwrap()
+    }
+
+    #[test]
+    fn bench_source_count_and_size() {
+        let res = run(4, 1001, 100);
+        assert_eq!(res.len(), 1001);
+        assert!(res.iter().all(|payload| payload.len() == 100));
+
+        assert_eq!(run(3, 0, 100).len(), 0);
+        assert_eq!(run(1, 10, 0), vec![Vec::<u8>::new(); 10]);
+    }
+
+    #[test]
+    fn bench_source_deterministic() {
+        let mut first = run(4, 100, 13);
+        let mut second = run(4, 100, 13);
+        first.sort_unstable();
+        second.sort_unstable();
+        assert_eq!(first, second);
+        // the payloads are not all the same
+        first.dedup();
+        assert_eq!(first.len(), 100);
+    }
+}
@@ -1,6 +1,8 @@
 use std::fmt::Display;
 
-use flume::{bounded, Receiver, RecvError, Sender, TryRecvError};
+use std::time::Duration;
+
+use flume::{bounded, Receiver, RecvTimeoutError, Sender, TryRecvError};
 
 use crate::block::{BlockStructure, OperatorKind, OperatorStructure, Replication};
 use crate::operator::source::Source;
@@ -8,6 +10,8 @@ use crate::operator::{replication, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 
 const MAX_RETRY: u8 = 16;
+/// How long the source waits for an item before flushing again, while the channel is empty.
+const IDLE_FLUSH_INTERVAL: Duration = Duration::from_millis(100);
 
 /// Source that consumes an iterator and emits all its elements into the stream.
 ///
@@ -98,10 +102,14 @@ impl<Out: Send + core::fmt::Debug> Operator for ChannelSource<Out> {
                 }
                 Err(TryRecvError::Empty) => {
                     log::debug!("flushed and no values ready, blocking");
-                    self.retry_count = 0;
-                    match self.rx.recv() {
-                        Ok(t) => return StreamElement::Item(t),
-                        Err(RecvError::Disconnected) => {
+                    // flush periodically while waiting, letting the operators notice the idleness
+                    match self.rx.recv_timeout(IDLE_FLUSH_INTERVAL) {
+                        Ok(t) => {
+                            self.retry_count = 0;
+                            return StreamElement::Item(t);
+                        }
+                        Err(RecvTimeoutError::Timeout) => return StreamElement::FlushBatch,
+                        Err(RecvTimeoutError::Disconnected) => {
                             self.terminated = true;
                             log::info!("Stream disconnected");
                             return StreamElement::FlushAndRestart;
@@ -4,12 +4,16 @@ use std::io;
 use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::marker::PhantomData;
 use std::path::PathBuf;
+#[cfg(feature = "timestamp")]
+use std::sync::Arc;
 
 use csv::{ByteRecord, Reader, ReaderBuilder, Terminator, Trim};
 use serde::Deserialize;
 
 use crate::block::{BlockStructure, OperatorKind, OperatorStructure, Replication};
 use crate::operator::source::Source;
+#[cfg(feature = "timestamp")]
+use crate::operator::Timestamp;
 use crate::operator::{Data, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 use crate::Stream;
@@ -64,6 +68,8 @@ struct CsvOptions {
     trim: Trim,
     /// Whether the CSV file has headers.
     has_headers: bool,
+    /// Whether to match the columns to the fields by the names in the headers.
+    match_headers: bool,
 }
 
 impl Default for CsvOptions {
@@ -79,10 +85,51 @@ impl Default for CsvOptions {
             terminator: Terminator::CRLF,
             trim: Trim::None,
             has_headers: true,
+            match_headers: false,
         }
     }
 }
 
+/// The number of records between two watermarks of a source with event time.
+#[cfg(feature = "timestamp")]
+const WATERMARK_INTERVAL: usize = 256;
+
+/// Assigns the event time to the records of a source, see [`CsvSource::event_time`].
+#[cfg(feature = "timestamp")]
+#[derive(Clone)]
+struct EventTime<Out> {
+    extractor: Arc<dyn Fn(&Out) -> Timestamp + Send + Sync>,
+    max_delay: Timestamp,
+    /// The largest timestamp assigned.
+    max_timestamp: Option<Timestamp>,
+    /// The last watermark emitted.
+    watermark: Option<Timestamp>,
+    /// The watermark to emit after the last record.
+    pending: Option<Timestamp>,
+    /// The number of records since the last watermark.
+    since_watermark: usize,
+}
+
+#[cfg(feature = "timestamp")]
+impl<Out> EventTime<Out> {
+    /// The timestamp of `item`, scheduling a watermark every `WATERMARK_INTERVAL` records.
+    fn assign(&mut self, item: &Out) -> Timestamp {
+        let ts = (self.extractor)(item);
+        let max = self.max_timestamp.map_or(ts, |max| max.max(ts));
+        self.max_timestamp = Some(max);
+        self.since_watermark += 1;
+        if self.since_watermark >= WATERMARK_INTERVAL {
+            self.since_watermark = 0;
+            let watermark = max.saturating_sub(self.max_delay);
+            if self.watermark < Some(watermark) {
+                self.watermark = Some(watermark);
+                self.pending = Some(watermark);
+            }
+        }
+        ts
+    }
+}
+
 /// Source that reads and parses a CSV file.
 ///
 /// The file is divided in chunks and is read concurrently by multiple replicas.
@@ -91,12 +138,17 @@ pub struct CsvSource<Out: Data + for<'a> Deserialize<'a>> {
     path: PathBuf,
     /// Reader used to parse the CSV file.
     csv_reader: Option<Reader<LimitedReader<BufReader<File>>>>,
+    /// The headers used to match the columns to the fields, if enabled.
+    headers: Option<ByteRecord>,
     /// Options to customize the CSV parser.
     options: CsvOptions,
     /// Whether the reader has terminated its job.
     terminated: bool,
     _out: PhantomData<Out>,
     buf: ByteRecord,
+    /// The event time of the records, if they are timestamped.
+    #[cfg(feature = "timestamp")]
+    event_time: Option<EventTime<Out>>,
 }
 
 impl<Out: Data + for<'a> Deserialize<'a>> Display for CsvSource<Out> {
@@ -142,13 +194,62 @@ impl<Out: Data + for<'a> Deserialize<'a>> CsvSource<Out> {
         Self {
             path: path.into(),
             csv_reader: None,
+            headers: None,
             options: Default::default(),
             terminated: false,
             _out: PhantomData,
             buf: ByteRecord::new(),
+            #[cfg(feature = "timestamp")]
+            event_time: None,
         }
     }
 
+    /// Use the timestamp extracted from each record by `extractor` as its event time.
+    ///
+    /// The source emits timestamped records, ready for the event time windows without an
+    /// [`add_timestamps`](crate::Stream::add_timestamps) stage. The records may be out of order
+    /// by at most `max_delay`: every few records each replica emits a watermark `max_delay`
+    /// before the largest timestamp it read, so a record more than `max_delay` older than a
+    /// previous one may be late.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::source::CsvSource;
+    /// # use renoir::operator::window::EventTimeWindow;
+    /// # use serde::{Deserialize, Serialize};
+    /// # let mut env = StreamContext::new_local();
+    /// #[derive(Clone, Deserialize, Serialize)]
+    /// struct Reading {
+    ///     time: i64,
+    ///     sensor: u32,
+    /// }
+    /// let source = CsvSource::<Reading>::new("/datasets/readings.csv").event_time(|r| r.time, 10);
+    /// let res = env
+    ///     .stream(source)
+    ///     .group_by(|r| r.sensor)
+    ///     .window(EventTimeWindow::tumbling(1000))
+    ///     .count()
+    ///     .collect_vec();
+    /// ```
+    #[cfg(feature = "timestamp")]
+    pub fn event_time<F>(mut self, extractor: F, max_delay: Timestamp) -> Self
+    where
+        F: Fn(&Out) -> Timestamp + Send + Sync + 'static,
+    {
+        assert!(max_delay >= 0, "the maximum delay must not be negative");
+        self.event_time = Some(EventTime {
+            extractor: Arc::new(extractor),
+            max_delay,
+            max_timestamp: None,
+            watermark: None,
+            pending: None,
+            since_watermark: 0,
+        });
+        self
+    }
+
     /// The comment character to use when parsing CSV.
     ///
     /// If the start of a record begins with the byte given here, then that line is ignored by the
@@ -258,6 +359,19 @@ impl<Out: Data + for<'a> Deserialize<'a>> CsvSource<Out> {
         self.options.has_headers = has_headers;
         self
     }
+
+    /// Whether to match the columns of the CSV to the fields of `Out` by the names in the headers.
+    ///
+    /// By default the columns are matched to the fields by their position. When this is enabled
+    /// each field of a struct is read from the column with the same name, so the columns can be in
+    /// any order and the extra columns are ignored. The replica reading a record without the column
+    /// of a required field panics with the name of the missing field.
+    ///
+    /// The CSV file must have headers.
+    pub fn match_headers(mut self, match_headers: bool) -> Self {
+        self.options.match_headers = match_headers;
+        self
+    }
 }
 
 impl<Out: Data + for<'a> Deserialize<'a>> Source for CsvSource<Out> {
@@ -362,14 +476,20 @@ impl<Out: Data + for<'a> Deserialize<'a>> Operator for CsvSource<Out> {
             .has_headers(self.options.has_headers)
             .from_reader(limited_reader);
 
+        assert!(
+            self.options.has_headers || !self.options.match_headers,
+            "CsvSource: matching the columns by the headers requires a CSV file with headers"
+        );
         if self.options.has_headers {
             // set the headers of the CSV file
-            csv_reader.set_byte_headers(
-                Reader::from_reader(header.as_slice())
-                    .byte_headers()
-                    .unwrap()
-                    .to_owned(),
-            );
+            let headers = Reader::from_reader(header.as_slice())
+                .byte_headers()
+                .unwrap()
+                .to_owned();
+            if self.options.match_headers {
+                self.headers = Some(headers.clone());
+            }
+            csv_reader.set_byte_headers(headers);
         }
 
         self.csv_reader = Some(csv_reader);
@@ -379,6 +499,10 @@ impl<Out: Data + for<'a> Deserialize<'a>> Operator for CsvSource<Out> {
         if self.terminated {
             return StreamElement::Terminate;
         }
+        #[cfg(feature = "timestamp")]
+        if let Some(watermark) = self.event_time.as_mut().and_then(|e| e.pending.take()) {
+            return StreamElement::Watermark(watermark);
+        }
         let csv_reader = self
             .csv_reader
             .as_mut()
@@ -388,8 +512,19 @@ impl<Out: Data + for<'a> Deserialize<'a>> Operator for CsvSource<Out> {
             Ok(true) => {
                 let item = self
                     .buf
-                    .deserialize::<Out>(None)
-                    .expect("csv does not match type");
+                    .deserialize::<Out>(self.headers.as_ref())
+                    .unwrap_or_else(|e| {
+                        panic!(
+                            "CsvSource: the record of {:?} does not match {}: {e}",
+                            self.path,
+                            std::any::type_name::<Out>()
+                        )
+                    });
+                #[cfg(feature = "timestamp")]
+                if let Some(event_time) = &mut self.event_time {
+                    let ts = event_time.assign(&item);
+                    return StreamElement::Timestamped(item, ts);
+                }
                 StreamElement::Item(item)
             }
             Ok(false) => {
@@ -416,10 +551,13 @@ impl<Out: Data + for<'a> Deserialize<'a>> Clone for CsvSource<Out> {
         Self {
             path: self.path.clone(),
             csv_reader: None,
+            headers: None,
             options: self.options.clone(),
             terminated: false,
             _out: PhantomData,
             buf: ByteRecord::new(),
+            #[cfg(feature = "timestamp")]
+            event_time: self.event_time.clone(),
         }
     }
 }
@@ -433,6 +571,35 @@ impl crate::StreamContext {
         let source = CsvSource::new(path);
         self.stream(source)
     }
+
+    /// Like [`StreamContext::stream_csv`](crate::StreamContext::stream_csv), matching the columns
+    /// to the fields of `T` by the names in the headers of the CSV file.
+    ///
+    /// The columns can be in any order and the extra columns are ignored, so the stream keeps
+    /// working when the columns of the file are reordered or new ones are added. See
+    /// [`CsvSource::match_headers`].
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use serde::{Deserialize, Serialize};
+    /// # let mut env = StreamContext::new_local();
+    /// #[derive(Clone, Deserialize, Serialize)]
+    /// struct Thing {
+    ///     what: String,
+    ///     count: u64,
+    /// }
+    /// // the file may have the columns `count,id,what`
+    /// let s = env.stream_csv_typed::<Thing>("/datasets/huge.csv");
+    /// ```
+    pub fn stream_csv_typed<T: Data + for<'a> Deserialize<'a>>(
+        &self,
+        path: impl Into<PathBuf>,
+    ) -> Stream<CsvSource<T>> {
+        let source = CsvSource::new(path).match_headers(true);
+        self.stream(source)
+    }
 }
 
 #[cfg(test)]
@@ -446,6 +613,8 @@ mod tests {
     use crate::config::RuntimeConfig;
     use crate::environment::StreamContext;
     use crate::operator::source::CsvSource;
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::FakeNetworkTopology;
 
     #[test]
     fn csv_without_headers() {
@@ -500,4 +669,83 @@ mod tests {
             }
         }
     }
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Row {
+        a: i32,
+        b: String,
+        c: i32,
+    }
+
+    #[test]
+    fn csv_match_headers() {
+        let file = NamedTempFile::new().unwrap();
+        writeln!(file.as_file(), "c,extra,b,a").unwrap();
+        for i in 0..100 {
+            writeln!(file.as_file(), "{},x,b{},{}", i + 1, i, i).unwrap();
+        }
+
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env.stream_csv_typed::<Row>(file.path()).collect_vec();
+        env.execute_blocking();
+
+        let mut res = res.get().unwrap();
+        res.sort_unstable_by_key(|r| r.a);
+        let expected = (0..100)
+            .map(|i| Row {
+                a: i,
+                b: format!("b{i}"),
+                c: i + 1,
+            })
+            .collect_vec();
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "missing field `c`")]
+    fn csv_match_headers_missing_column() {
+        let file = NamedTempFile::new().unwrap();
+        write!(file.as_file(), "b,a\nb0,0\n").unwrap();
+
+        let mut source = CsvSource::<Row>::new(file.path()).match_headers(true);
+        source.setup(&mut FakeNetworkTopology::<Row>::new(0, 0).metadata());
+        assert_eq!(source.next(), StreamElement::Terminate);
+    }
+
+    #[cfg(feature = "timestamp")]
+    #[test]
+    fn csv_event_time_window() {
+        use crate::operator::window::EventTimeWindow;
+
+        #[derive(Clone, Serialize, Deserialize)]
+        struct Reading {
+            time: i64,
+            sensor: u32,
+        }
+
+        let file = NamedTempFile::new().unwrap();
+        writeln!(file.as_file(), "time,sensor").unwrap();
+        for i in 0..1000i64 {
+            // swap the consecutive pairs, so the records are out of order by one
+            let time = i ^ 1;
+            writeln!(file.as_file(), "{},{}", time, time % 2).unwrap();
+        }
+
+        // the windows of a sensor start at its first record, so it must be read first
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        let source = CsvSource::<Reading>::new(file.path()).event_time(|r| r.time, 1);
+        let res = env
+            .stream(source)
+            .group_by(|r| r.sensor)
+            .window(EventTimeWindow::tumbling(100))
+            .count()
+            .collect_vec();
+        env.execute_blocking();
+
+        let res = res.get().unwrap().into_iter().sorted().collect_vec();
+        let expected = (0..2)
+            .flat_map(|sensor| std::iter::repeat_n((sensor, 50), 10))
+            .collect_vec();
+        assert_eq!(res, expected);
+    }
 }
@@ -8,6 +8,7 @@ use std::path::PathBuf;
 use crate::block::Replication;
 use crate::block::{BlockStructure, OperatorKind, OperatorStructure};
 use crate::network::Coord;
+use crate::operator::compression::Compression;
 use crate::operator::source::Source;
 use crate::operator::{Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
@@ -16,11 +17,13 @@ use crate::Stream;
 /// Source that reads a text file line-by-line.
 ///
 /// The file is divided in chunks and is read concurrently by multiple replicas.
-#[derive(Debug)]
+#[derive(Derivative)]
+#[derivative(Debug)]
 pub struct FileSource {
     path: PathBuf,
     // reader is initialized in `setup`, before it is None
-    reader: Option<BufReader<File>>,
+    #[derivative(Debug = "ignore")]
+    reader: Option<Box<dyn BufRead + Send>>,
     current: usize,
     end: usize,
     terminated: bool,
@@ -43,6 +46,10 @@ impl FileSource {
     /// **Note**: the file must be readable and its size must be available. This means that only
     /// regular files can be read.
     ///
+    /// Files with the `.gz`, `.zst` and `.sz` extensions are decompressed while they are read,
+    /// with the `compression` feature. A compressed file cannot be split in chunks, so it is read whole by
+    /// a single replica.
+    ///
     /// ## Example
     ///
     /// ```
@@ -80,12 +87,25 @@ impl Operator for FileSource {
         let global_id = metadata.global_id;
         let instances = metadata.replicas.len();
 
-        let file = File::open(&self.path).unwrap_or_else(|err| {
+        let mut file = File::open(&self.path).unwrap_or_else(|err| {
             panic!(
                 "FileSource: error while opening file {:?}: {:?}",
                 self.path, err
             )
         });
+        let compression = Compression::from_path(&self.path);
+        if compression.is_compressed() {
+            // a compressed file cannot be split by byte ranges: the first replica reads it whole
+            self.current = 0;
+            self.end = usize::MAX;
+            self.coord = Some(metadata.coord);
+            self.reader = Some(if global_id == 0 {
+                compression.reader(file)
+            } else {
+                Box::new(std::io::empty())
+            });
+            return;
+        }
         let file_size = file.metadata().unwrap().len() as usize;
 
         let range_size = file_size / instances;
@@ -97,11 +117,10 @@ impl Operator for FileSource {
             start + range_size
         };
 
-        let mut reader = BufReader::new(file);
-        // Seek reader to the first byte to be read
-        reader
-            .seek(SeekFrom::Current(start as i64))
+        // Seek file to the first byte to be read
+        file.seek(SeekFrom::Current(start as i64))
             .expect("seek file");
+        let mut reader = BufReader::new(file);
         if global_id != 0 {
             // discard first line
             let mut v = Vec::new();
@@ -111,7 +130,7 @@ impl Operator for FileSource {
                 .expect("Cannot read line from file");
         }
         self.coord = Some(metadata.coord);
-        self.reader = Some(reader);
+        self.reader = Some(Box::new(reader));
     }
 
     fn next(&mut self) -> StreamElement<String> {
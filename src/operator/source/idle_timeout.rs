@@ -0,0 +1,181 @@
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use crate::block::{BlockStructure, OperatorStructure, Replication};
+use crate::operator::source::{Source, StopHandle};
+use crate::operator::{Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// Source that ends the stream of another source when it produces no items for a while.
+///
+/// Build it with [`Stream::with_idle_timeout`].
+#[derive(Clone, Debug)]
+pub struct IdleTimeoutSource<S: Source> {
+    inner: S,
+    timeout: Duration,
+    /// When the last item was emitted, or when the first element was requested.
+    last_item: Option<Instant>,
+    /// Whether the stream has been ended because of the timeout.
+    ended: bool,
+}
+
+impl<S: Source> IdleTimeoutSource<S> {
+    fn new(inner: S, timeout: Duration) -> Self {
+        Self {
+            inner,
+            timeout,
+            last_item: None,
+            ended: false,
+        }
+    }
+}
+
+impl<S: Source> Operator for IdleTimeoutSource<S> {
+    type Out = S::Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.inner.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        if self.ended {
+            return StreamElement::Terminate;
+        }
+        let last_item = *self.last_item.get_or_insert_with(Instant::now);
+        let el = self.inner.next();
+        match el {
+            StreamElement::Item(_) | StreamElement::Timestamped(_, _) => {
+                self.last_item = Some(Instant::now());
+            }
+            // the idle sources flush periodically while waiting for new items
+            StreamElement::FlushBatch | StreamElement::Watermark(_)
+                if last_item.elapsed() >= self.timeout =>
+            {
+                log::info!(
+                    "no items for {:?}, ending the stream of {}",
+                    self.timeout,
+                    self.inner
+                );
+                if let Some(stop) = self.inner.stop_handle() {
+                    stop.stop();
+                }
+                self.ended = true;
+                return StreamElement::FlushAndRestart;
+            }
+            _ => {}
+        }
+        el
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<S::Out, _>("IdleTimeout");
+        operator.subtitle = format!("{:?}", self.timeout);
+        self.inner.structure().add_operator(operator)
+    }
+}
+
+impl<S: Source> Source for IdleTimeoutSource<S> {
+    fn replication(&self) -> Replication {
+        self.inner.replication()
+    }
+
+    fn stop_handle(&self) -> Option<StopHandle> {
+        self.inner.stop_handle()
+    }
+
+    fn is_bounded(&self) -> bool {
+        self.inner.is_bounded()
+    }
+}
+
+impl<S: Source> Display for IdleTimeoutSource<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> IdleTimeout[{:?}]", self.inner, self.timeout)
+    }
+}
+
+impl<S> Stream<S>
+where
+    S: Source + 'static,
+{
+    /// End the stream of the source when it produces no items for `timeout`.
+    ///
+    /// This turns a source that may wait forever, like a
+    /// [`ChannelSource`](crate::operator::source::ChannelSource) that has gone quiet, into one
+    /// that ends by itself. Unlike a processing time window, which closes a window and goes on,
+    /// this ends the whole stream. Each replica of the source ends on its own.
+    ///
+    /// The inactivity is checked when the source flushes, so the source must return from `next`
+    /// periodically while it waits for new items. This must be called right after creating the
+    /// stream from the source.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use renoir::{StreamContext, RuntimeConfig, Replication};
+    /// # use renoir::operator::source::ChannelSource;
+    /// # let mut env = StreamContext::new_local();
+    /// let (tx, source) = ChannelSource::new(4, Replication::One);
+    /// let res = env
+    ///     .stream(source)
+    ///     .with_idle_timeout(Duration::from_millis(100))
+    ///     .collect_vec();
+    /// tx.send(1).unwrap();
+    /// tx.send(2).unwrap();
+    ///
+    /// // the sender is still open, but the stream ends when it goes quiet
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![1, 2]);
+    /// ```
+    pub fn with_idle_timeout(self, timeout: Duration) -> Stream<IdleTimeoutSource<S>> {
+        self.add_operator(|inner| IdleTimeoutSource::new(inner, timeout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::ChannelSource;
+    use crate::Replication;
+
+    #[test]
+    fn idle_timeout_ends_quiet_source() {
+        const TIMEOUT: Duration = Duration::from_millis(300);
+
+        let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+        let (tx, source) = ChannelSource::new(16, Replication::One);
+        let res = env
+            .stream(source)
+            .with_idle_timeout(TIMEOUT)
+            .shuffle()
+            .collect_vec();
+
+        let start = Instant::now();
+        let producer = std::thread::spawn(move || {
+            for i in 0..5u32 {
+                tx.send(i).unwrap();
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            // the sender stays open: the stream is ended by the timeout
+            tx
+        });
+        env.execute_blocking();
+        let elapsed = start.elapsed();
+        let _tx = producer.join().unwrap();
+
+        let mut res = res.get().unwrap();
+        res.sort();
+        assert_eq!(res, vec![0, 1, 2, 3, 4]);
+        // the last item is sent after 400ms, the items before it do not trigger the timeout
+        assert!(
+            elapsed >= Duration::from_millis(400) + TIMEOUT,
+            "{elapsed:?}"
+        );
+    }
+}
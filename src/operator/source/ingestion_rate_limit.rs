@@ -0,0 +1,201 @@
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use crate::block::{BlockStructure, OperatorStructure, Replication};
+use crate::operator::source::{Source, StopHandle};
+use crate::operator::{Operator, StreamElement, Timestamp};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// Source that paces another source following the event time of its elements, replaying them at
+/// a multiple of real time.
+///
+/// Build it with [`Stream::with_ingestion_rate_limit`].
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct IngestionRateLimitSource<S: Source, F> {
+    inner: S,
+    #[derivative(Debug = "ignore")]
+    timestamp: F,
+    /// How many times faster than real time the elements are emitted.
+    speedup: f64,
+    /// The event time of the first element, and when it was emitted.
+    origin: Option<(Timestamp, Instant)>,
+}
+
+impl<S, F> IngestionRateLimitSource<S, F>
+where
+    S: Source,
+    F: Fn(&S::Out) -> Timestamp + Clone + Send,
+{
+    fn new(inner: S, speedup: f64, timestamp: F) -> Self {
+        assert!(
+            speedup > 0.0 && speedup.is_finite(),
+            "the speedup of the replay must be positive"
+        );
+        Self {
+            inner,
+            timestamp,
+            speedup,
+            origin: None,
+        }
+    }
+
+    /// Wait until the element with event time `ts` is due.
+    fn pace(&mut self, ts: Timestamp) {
+        let (first, start) = *self.origin.get_or_insert_with(|| (ts, Instant::now()));
+        // the elements before the first one are emitted as soon as they are read
+        let millis = ts.saturating_sub(first).max(0) as f64 / self.speedup;
+        let at = start + Duration::from_secs_f64(millis / 1000.0);
+        let now = Instant::now();
+        if at > now {
+            std::thread::sleep(at - now);
+        }
+    }
+}
+
+impl<S, F> Operator for IngestionRateLimitSource<S, F>
+where
+    S: Source,
+    F: Fn(&S::Out) -> Timestamp + Clone + Send,
+{
+    type Out = S::Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.inner.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        let el = self.inner.next();
+        if let StreamElement::Item(item) | StreamElement::Timestamped(item, _) = &el {
+            let ts = (self.timestamp)(item);
+            self.pace(ts);
+        }
+        el
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<S::Out, _>("IngestionRateLimit");
+        operator.subtitle = format!("{}x", self.speedup);
+        self.inner.structure().add_operator(operator)
+    }
+}
+
+impl<S, F> Source for IngestionRateLimitSource<S, F>
+where
+    S: Source,
+    F: Fn(&S::Out) -> Timestamp + Clone + Send,
+{
+    fn replication(&self) -> Replication {
+        self.inner.replication()
+    }
+
+    fn stop_handle(&self) -> Option<StopHandle> {
+        self.inner.stop_handle()
+    }
+
+    fn is_bounded(&self) -> bool {
+        self.inner.is_bounded()
+    }
+}
+
+impl<S: Source, F> Display for IngestionRateLimitSource<S, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> IngestionRateLimit[{}x]", self.inner, self.speedup)
+    }
+}
+
+impl<S> Stream<S>
+where
+    S: Source + 'static,
+{
+    /// Replay the elements of the source at `speedup` times real time, following their event
+    /// time.
+    ///
+    /// `timestamp` extracts the event time of each element, in milliseconds, usually the same
+    /// function given to [`Stream::add_timestamps`]. The first element is emitted immediately,
+    /// and each of the following ones when the time elapsed since then, multiplied by `speedup`,
+    /// reaches the gap between their event times: with a `speedup` of 10, an hour of data is
+    /// replayed in 6 minutes. The elements out of order, with an event time before the previous
+    /// ones, are emitted as soon as they are read.
+    ///
+    /// This simulates the arrival of the elements of a historical dataset when testing the latency
+    /// of a pipeline, or the behavior of processing time windows. Each replica of the source is
+    /// paced separately.
+    ///
+    /// This must be called right after creating the stream from the source.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// // (event time in milliseconds, value): a second of data, replayed in 10 milliseconds
+    /// let res = env
+    ///     .stream_iter((0..=10).map(|i| (i * 100, i)))
+    ///     .with_ingestion_rate_limit(100.0, |&(ts, _)| ts)
+    ///     .add_timestamps(|&(ts, _)| ts, |_, &ts| Some(ts))
+    ///     .map(|(_, value)| value)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), (0..=10).collect::<Vec<_>>());
+    /// ```
+    pub fn with_ingestion_rate_limit<F>(
+        self,
+        speedup: f64,
+        timestamp: F,
+    ) -> Stream<IngestionRateLimitSource<S, F>>
+    where
+        F: Fn(&S::Out) -> Timestamp + Clone + Send + 'static,
+    {
+        self.add_operator(|inner| IngestionRateLimitSource::new(inner, speedup, timestamp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::operator::source::IngestionRateLimitSource;
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+
+    fn replay(timestamps: &[i64], speedup: f64) -> Vec<Duration> {
+        let mut source = IngestionRateLimitSource::new(
+            FakeOperator::new(timestamps.iter().copied()),
+            speedup,
+            |&ts| ts,
+        );
+        source.setup(&mut FakeNetworkTopology::<i64>::new(0, 0).metadata());
+
+        let start = Instant::now();
+        let mut times = vec![];
+        for _ in timestamps {
+            assert!(matches!(source.next(), StreamElement::Item(_)));
+            times.push(start.elapsed());
+        }
+        times
+    }
+
+    #[test]
+    fn replay_real_time() {
+        let timestamps = [1000, 1050, 1050, 1130, 1300];
+        let times = replay(&timestamps, 1.0);
+        for (ts, time) in timestamps.iter().zip(times) {
+            let expected = Duration::from_millis((ts - timestamps[0]) as u64);
+            assert!(
+                time >= expected && time < expected + Duration::from_millis(30),
+                "element at {ts} emitted after {time:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn replay_faster_than_real_time() {
+        let times = replay(&[0, 500, 1000], 10.0);
+        assert!(times[2] >= Duration::from_millis(100), "{times:?}");
+        assert!(times[2] < Duration::from_millis(150), "{times:?}");
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use crate::block::{BlockStructure, OperatorKind, OperatorStructure, Replication};
+use crate::operator::source::{Source, StopHandle};
+use crate::operator::{Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// How often the source checks the stop handle while waiting for the next tick.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Source that emits a tick every interval, numbered from 0.
+///
+/// The first tick is emitted as soon as the source starts, and the following ones at a fixed rate
+/// from it, so a slow pipeline does not make the ticks drift. The source has a single replica.
+/// The stream ends after the maximum number of ticks, if set, or when the [`StopHandle`] of the
+/// source is stopped.
+///
+/// This is handy for driving periodic polls and processing time windows, and for testing.
+#[derive(Clone, Debug)]
+pub struct IntervalSource {
+    interval: Duration,
+    max_ticks: Option<u64>,
+    stop: StopHandle,
+    /// When the next tick is due, set when the first tick is requested.
+    next_tick: Option<Instant>,
+    /// The number of ticks emitted so far.
+    ticks: u64,
+    /// Whether a tick was emitted since the last `FlushBatch`.
+    need_flush: bool,
+    terminated: bool,
+}
+
+impl Display for IntervalSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "IntervalSource<{}>", std::any::type_name::<u64>())
+    }
+}
+
+impl IntervalSource {
+    /// Create a new source that emits a tick every `interval`, until it is stopped.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::source::IntervalSource;
+    /// # let mut env = StreamContext::new_local();
+    /// let source = IntervalSource::new(Duration::from_millis(1)).max_ticks(5);
+    /// let res = env.stream(source).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![0, 1, 2, 3, 4]);
+    /// ```
+    pub fn new(interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "the interval must be positive");
+        Self {
+            interval,
+            max_ticks: None,
+            stop: Default::default(),
+            next_tick: None,
+            ticks: 0,
+            need_flush: false,
+            terminated: false,
+        }
+    }
+
+    /// End the stream after `max_ticks` ticks.
+    pub fn max_ticks(mut self, max_ticks: u64) -> Self {
+        self.max_ticks = Some(max_ticks);
+        self
+    }
+
+    /// Get the handle that ends the stream produced by this source.
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+}
+
+impl Source for IntervalSource {
+    fn replication(&self) -> Replication {
+        Replication::One
+    }
+
+    fn stop_handle(&self) -> Option<StopHandle> {
+        Some(self.stop.clone())
+    }
+
+    fn is_bounded(&self) -> bool {
+        self.max_ticks.is_some()
+    }
+}
+
+impl Operator for IntervalSource {
+    type Out = u64;
+
+    fn setup(&mut self, _metadata: &mut ExecutionMetadata) {}
+
+    fn next(&mut self) -> StreamElement<u64> {
+        loop {
+            if self.terminated {
+                return StreamElement::Terminate;
+            }
+            // the ticks are rare, send each one as soon as it is emitted
+            if self.need_flush {
+                self.need_flush = false;
+                return StreamElement::FlushBatch;
+            }
+            if self.stop.is_stopped() || Some(self.ticks) == self.max_ticks {
+                self.terminated = true;
+                return StreamElement::FlushAndRestart;
+            }
+
+            let due = *self.next_tick.get_or_insert_with(Instant::now);
+            let now = Instant::now();
+            if due > now {
+                std::thread::sleep((due - now).min(POLL_INTERVAL));
+                continue;
+            }
+            self.next_tick = Some(due + self.interval);
+            self.ticks += 1;
+            self.need_flush = true;
+            return StreamElement::Item(self.ticks - 1);
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<u64, _>("IntervalSource");
+        operator.kind = OperatorKind::Source;
+        operator.subtitle = format!("{:?}", self.interval);
+        BlockStructure::default().add_operator(operator)
+    }
+}
+
+impl crate::StreamContext {
+    /// Convenience method, creates an `IntervalSource` and makes a stream using
+    /// `StreamContext::stream`, returning the handle that stops the stream.
+    pub fn stream_interval(&self, interval: Duration) -> (StopHandle, Stream<IntervalSource>) {
+        let source = IntervalSource::new(interval);
+        let stop = source.stop_handle();
+        (stop, self.stream(source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+
+    #[test]
+    fn interval_ticks_until_stopped() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let (stop, stream) = env.stream_interval(Duration::from_millis(100));
+        let res = stream.collect_vec();
+
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(1000));
+            stop.stop();
+        });
+        env.execute_blocking();
+        stopper.join().unwrap();
+
+        let res = res.get().unwrap();
+        // a tick at the start and one every 100ms, the last ones may race with the stop
+        assert!((9..=12).contains(&res.len()), "{} ticks", res.len());
+        assert_eq!(res, (0..res.len() as u64).collect::<Vec<_>>());
+    }
+}
@@ -1,8 +1,11 @@
 use std::fmt::Display;
+use std::iter::{Flatten, RepeatN};
+use std::sync::Arc;
 
 use crate::block::{BlockStructure, OperatorKind, OperatorStructure, Replication};
 use crate::operator::source::Source;
 use crate::operator::{Operator, StreamElement};
+use crate::savepoint::Savepoint;
 use crate::scheduler::ExecutionMetadata;
 use crate::Stream;
 
@@ -19,6 +22,12 @@ where
     #[derivative(Debug = "ignore")]
     inner: It,
     terminated: bool,
+    /// The identifier of the offset of this source in the savepoints.
+    savepoint_id: Option<String>,
+    /// The number of items consumed from the iterator.
+    offset: u64,
+    #[derivative(Debug = "ignore")]
+    savepoint: Arc<Savepoint>,
 }
 
 impl<It> Display for IteratorSource<It>
@@ -56,8 +65,22 @@ where
         Self {
             inner,
             terminated: false,
+            savepoint_id: None,
+            offset: 0,
+            savepoint: Default::default(),
         }
     }
+
+    /// Store the number of items consumed from the iterator in the savepoints, under the
+    /// identifier `id`.
+    ///
+    /// When the environment is built from a savepoint, the items already emitted by the previous
+    /// execution are skipped, so the iterator should produce the same items in the same order.
+    /// See [`StreamContext::savepoint`](crate::StreamContext::savepoint).
+    pub fn savepoint_id(mut self, id: impl Into<String>) -> Self {
+        self.savepoint_id = Some(id.into());
+        self
+    }
 }
 
 impl<It> Source for IteratorSource<It>
@@ -77,15 +100,30 @@ where
 {
     type Out = It::Item;
 
-    fn setup(&mut self, _metadata: &mut ExecutionMetadata) {}
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.savepoint = metadata.savepoint.clone();
+        // the source is not parallel: the offset is stored as the one of the first replica
+        if let Some(id) = &self.savepoint_id {
+            if let Some(offset) = self.savepoint.load::<u64>(id, 0) {
+                self.inner.by_ref().take(offset as usize).for_each(drop);
+                self.offset = offset;
+            }
+        }
+    }
 
     fn next(&mut self) -> StreamElement<Self::Out> {
         if self.terminated {
+            if let Some(id) = &self.savepoint_id {
+                self.savepoint.store(id, 0, &self.offset);
+            }
             return StreamElement::Terminate;
         }
         // TODO: with adaptive batching this does not work since it never emits FlushBatch messages
         match self.inner.next() {
-            Some(t) => StreamElement::Item(t),
+            Some(t) => {
+                self.offset += 1;
+                StreamElement::Item(t)
+            }
             None => {
                 self.terminated = true;
                 StreamElement::FlushAndRestart
@@ -113,12 +151,133 @@ where
 
 impl crate::StreamContext {
     /// Convenience method, creates a `IteratorSource` and makes a stream using `StreamContext::stream`
-    pub fn stream_iter<It>(&self, iterator: It) -> Stream<IteratorSource<It>>
+    ///
+    /// Any [`IntoIterator`] can be used, like a range or a `Vec`. The items are read by a single
+    /// replica, and the stream ends when the iterator is exhausted. For a parallel source see
+    /// [`StreamContext::stream_par_iter`](crate::StreamContext::stream_par_iter).
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let res = env.stream_iter(vec!['a', 'b', 'c']).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec!['a', 'b', 'c']);
+    /// ```
+    pub fn stream_iter<It>(&self, iterator: It) -> Stream<IteratorSource<It::IntoIter>>
     where
-        It: Iterator + Send + 'static,
+        It: IntoIterator,
+        It::IntoIter: Send + 'static,
         It::Item: Send,
     {
-        let source = IteratorSource::new(iterator);
+        let source = IteratorSource::new(iterator.into_iter());
         self.stream(source)
     }
+
+    /// Convenience method, creates a stream emitting `n` clones of `value`, like
+    /// [`std::iter::repeat_n`].
+    ///
+    /// The values are emitted by a single replica, see
+    /// [`StreamContext::stream_iter`](crate::StreamContext::stream_iter). This is useful to
+    /// generate a controlled load for tests and benchmarks.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let res = env.stream_repeat('a', 3).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec!['a', 'a', 'a']);
+    /// ```
+    pub fn stream_repeat<T>(&self, value: T, n: usize) -> Stream<IteratorSource<RepeatN<T>>>
+    where
+        T: Clone + Send + 'static,
+    {
+        self.stream_iter(std::iter::repeat_n(value, n))
+    }
+
+    /// Convenience method, creates a stream emitting all the items of a finite iterator, replayed
+    /// `times` times.
+    ///
+    /// The iterator is cloned at each round, so it must be finite, otherwise the following rounds
+    /// never start. The items are emitted by a single replica, see
+    /// [`StreamContext::stream_iter`](crate::StreamContext::stream_iter).
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let res = env.stream_cycle(0..3, 2).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![0, 1, 2, 0, 1, 2]);
+    /// ```
+    pub fn stream_cycle<It>(
+        &self,
+        iterator: It,
+        times: usize,
+    ) -> Stream<IteratorSource<Flatten<RepeatN<It::IntoIter>>>>
+    where
+        It: IntoIterator,
+        It::IntoIter: Clone + Send + 'static,
+        It::Item: Send,
+    {
+        self.stream_iter(std::iter::repeat_n(iterator.into_iter(), times).flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::IteratorSource;
+    use crate::operator::StreamElement;
+    use crate::test::run_operator;
+
+    #[test]
+    fn iterator_source_ends() {
+        let elements = run_operator(IteratorSource::new(0..3));
+        let expected = vec![
+            StreamElement::Item(0),
+            StreamElement::Item(1),
+            StreamElement::Item(2),
+            StreamElement::FlushAndRestart,
+            StreamElement::Terminate,
+        ];
+        assert_eq!(elements, expected);
+    }
+
+    #[test]
+    fn stream_iter() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env.stream_iter(0..10).collect_vec();
+        env.execute_blocking();
+        assert_eq!(res.get().unwrap(), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stream_repeat() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env.stream_repeat(7, 100).collect_vec();
+        let empty = env.stream_repeat(7, 0).collect_vec();
+        env.execute_blocking();
+        assert_eq!(res.get().unwrap(), vec![7; 100]);
+        assert_eq!(empty.get().unwrap(), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn stream_cycle() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env.stream_cycle(vec!['a', 'b'], 3).collect_vec();
+        env.execute_blocking();
+        assert_eq!(res.get().unwrap(), ['a', 'b'].repeat(3));
+    }
 }
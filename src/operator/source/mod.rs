@@ -1,16 +1,34 @@
 //! Utility traits and structures related to the source operators.
 
 pub use self::csv::*;
+#[cfg(feature = "redis")]
+pub use self::redis::*;
 #[cfg(feature = "tokio")]
 pub use async_stream::*;
 #[cfg(feature = "avro")]
 pub use avro::*;
+pub use backpressure::*;
+pub use bench::*;
 pub use channel::*;
 pub use file::*;
+pub use idle_timeout::*;
+#[cfg(feature = "timestamp")]
+pub use ingestion_rate_limit::*;
+pub use interval::*;
 pub use iterator::*;
+pub use panic_policy::*;
 pub use parallel_iterator::*;
 #[cfg(feature = "parquet")]
 pub use parquet::*;
+pub use ramp_up::*;
+pub use stdin::*;
+#[cfg(feature = "notify")]
+pub use watch_dir::*;
+#[cfg(feature = "websocket")]
+pub use websocket::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 
 use crate::{block::Replication, operator::Operator};
 
@@ -18,16 +36,87 @@ use crate::{block::Replication, operator::Operator};
 mod async_stream;
 #[cfg(feature = "avro")]
 mod avro;
+mod backpressure;
+mod bench;
 mod channel;
 mod csv;
 mod file;
+mod idle_timeout;
+#[cfg(feature = "timestamp")]
+mod ingestion_rate_limit;
+mod interval;
 mod iterator;
+mod panic_policy;
 mod parallel_iterator;
 #[cfg(feature = "parquet")]
 mod parquet;
+mod ramp_up;
+#[cfg(feature = "redis")]
+mod redis;
+mod stdin;
+#[cfg(feature = "notify")]
+mod watch_dir;
+#[cfg(feature = "websocket")]
+mod websocket;
 
 /// This trait marks all the operators that can be used as sinks.
 pub trait Source: Operator {
     /// The maximum parallelism offered by this operator.
     fn replication(&self) -> Replication;
+
+    /// The handle that ends the stream of this source, if it can be stopped from outside.
+    ///
+    /// The handle is linked to the one of the [`StreamContext`](crate::StreamContext) when the
+    /// stream is created, so that [`StreamContext::stop_handle`](crate::StreamContext::stop_handle)
+    /// stops this source too.
+    fn stop_handle(&self) -> Option<StopHandle> {
+        None
+    }
+
+    /// Whether the stream of this source eventually ends.
+    ///
+    /// The operators that need the whole stream before producing their output (e.g.
+    /// [`Stream::sort_by`](crate::Stream::sort_by)) refuse the streams of unbounded sources.
+    fn is_bounded(&self) -> bool {
+        true
+    }
+}
+
+/// Handle used to stop an unbounded source from outside of the stream.
+///
+/// All the clones of the handle refer to the same flag: once [`StopHandle::stop`] is called, the
+/// sources holding the handle end their stream (in all their replicas) the next time they are
+/// polled.
+#[derive(Debug, Clone, Default)]
+pub struct StopHandle {
+    stopped: Arc<AtomicBool>,
+    /// The handle of the context of the source, stopping it stops this handle too.
+    parent: Arc<OnceLock<StopHandle>>,
+}
+
+impl StopHandle {
+    /// Ask the sources holding this handle to end their stream.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Release);
+    }
+
+    /// Whether [`StopHandle::stop`] has been called, on this handle or on the one of the context.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+            || self.parent.get().is_some_and(|parent| parent.is_stopped())
+    }
+
+    /// Stop this handle when `parent` is stopped. A handle can be linked only once, the other
+    /// calls are ignored.
+    pub(crate) fn link(&self, parent: &StopHandle) {
+        if !Arc::ptr_eq(&self.stopped, &parent.stopped) {
+            let _ = self.parent.set(parent.clone());
+        }
+    }
+
+    /// The flag set by [`StopHandle::stop`].
+    #[cfg(feature = "signals")]
+    pub(crate) fn flag(&self) -> Arc<AtomicBool> {
+        self.stopped.clone()
+    }
 }
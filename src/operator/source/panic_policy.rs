@@ -0,0 +1,170 @@
+use std::any::Any;
+use std::fmt::Display;
+use std::panic::AssertUnwindSafe;
+
+use crate::block::{BlockStructure, OperatorStructure, Replication};
+use crate::network::Coord;
+use crate::operator::source::{Source, StopHandle};
+use crate::operator::{Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// What happens to the job when a replica of a source panics.
+///
+/// See [`Stream::on_source_panic`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SourcePanicPolicy {
+    /// The panic stops the whole job.
+    #[default]
+    FailFast,
+    /// The panic is logged and only the replica that panicked ends its stream, the other replicas
+    /// and the rest of the job keep going.
+    Isolate,
+}
+
+/// Source that applies a [`SourcePanicPolicy`] to the panics of another source.
+///
+/// Build it with [`Stream::on_source_panic`].
+#[derive(Clone, Debug)]
+pub struct PanicPolicySource<S: Source> {
+    inner: S,
+    policy: SourcePanicPolicy,
+    coord: Option<Coord>,
+    /// Whether the inner source panicked, and must not be polled anymore.
+    panicked: bool,
+}
+
+/// The message of a panic, if it has one.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg
+    } else {
+        "<unknown panic payload>"
+    }
+}
+
+impl<S: Source> Operator for PanicPolicySource<S> {
+    type Out = S::Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.inner.setup(metadata);
+        self.coord = Some(metadata.coord);
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        if self.panicked {
+            return StreamElement::Terminate;
+        }
+        match self.policy {
+            SourcePanicPolicy::FailFast => self.inner.next(),
+            SourcePanicPolicy::Isolate => {
+                match std::panic::catch_unwind(AssertUnwindSafe(|| self.inner.next())) {
+                    Ok(el) => el,
+                    Err(payload) => {
+                        log::error!(
+                            "{}: source panicked, ending the stream of this replica: {}",
+                            self.coord.unwrap(),
+                            panic_message(payload.as_ref())
+                        );
+                        self.panicked = true;
+                        StreamElement::FlushAndRestart
+                    }
+                }
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<S::Out, _>("PanicPolicy");
+        operator.subtitle = format!("{:?}", self.policy);
+        self.inner.structure().add_operator(operator)
+    }
+}
+
+impl<S: Source> Source for PanicPolicySource<S> {
+    fn replication(&self) -> Replication {
+        self.inner.replication()
+    }
+
+    fn stop_handle(&self) -> Option<StopHandle> {
+        self.inner.stop_handle()
+    }
+
+    fn is_bounded(&self) -> bool {
+        self.inner.is_bounded()
+    }
+}
+
+impl<S: Source> Display for PanicPolicySource<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> PanicPolicy[{:?}]", self.inner, self.policy)
+    }
+}
+
+impl<S> Stream<S>
+where
+    S: Source + 'static,
+{
+    /// Choose what happens when a replica of the source panics.
+    ///
+    /// By default a panic of the source stops the whole job. With
+    /// [`SourcePanicPolicy::Isolate`] the panic is caught and logged together with the replica
+    /// that panicked, which ends its stream there: the items it would have produced after the
+    /// panic are lost, while the other replicas and the rest of the job keep going. This is
+    /// meant for the jobs that prefer partial results to no results when some of their input is
+    /// malformed.
+    ///
+    /// The panic message is still printed by the panic hook.
+    ///
+    /// This must be called right after creating the stream from the source.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::source::SourcePanicPolicy;
+    /// # let mut env = StreamContext::new_local();
+    /// let res = env
+    ///     .stream_iter((0..10).map(|n| if n < 5 { n } else { panic!("bad input") }))
+    ///     .on_source_panic(SourcePanicPolicy::Isolate)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![0, 1, 2, 3, 4]);
+    /// ```
+    pub fn on_source_panic(self, policy: SourcePanicPolicy) -> Stream<PanicPolicySource<S>> {
+        self.add_operator(|inner| PanicPolicySource {
+            inner,
+            policy,
+            coord: None,
+            panicked: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::SourcePanicPolicy;
+
+    #[test]
+    fn isolate_panicking_replica() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env
+            .stream_par_iter(|id, _| {
+                // the replica reading 0..25 panics on 10, the others complete
+                (id * 25..(id + 1) * 25).inspect(|&n| assert!(n != 10, "bad element"))
+            })
+            .on_source_panic(SourcePanicPolicy::Isolate)
+            .collect_vec();
+        env.execute_blocking();
+
+        let mut res = res.get().unwrap();
+        res.sort();
+        assert_eq!(res, (0..10).chain(25..100).collect::<Vec<_>>());
+    }
+}
@@ -1,10 +1,14 @@
 use std::{fs::File, path::PathBuf};
 
+use arrow::json::ArrayWriter;
 use arrow::{
     array::{cast::AsArray, types::ArrowPrimitiveType, Array, RecordBatch},
     datatypes::*,
 };
-use parquet::arrow::arrow_reader::{ArrowReaderBuilder, ParquetRecordBatchReader};
+use parquet::arrow::arrow_reader::{
+    ArrowReaderBuilder, ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder,
+};
+use serde::de::DeserializeOwned;
 
 use crate::{
     operator::{Operator, StreamElement},
@@ -89,6 +93,151 @@ impl crate::StreamContext {
     }
 }
 
+/// Source that reads a parquet file and deserializes each row into a `T`.
+///
+/// The row groups of the file are distributed across the replicas: each replica has to have the
+/// **same** file in the same path, and reads the row groups whose index modulo the number of
+/// replicas is equal to its own index. The rows are converted to `T` using its
+/// [`Deserialize`](serde::Deserialize) implementation, with the columns matched to the fields by
+/// name.
+pub struct ParquetRowSource<T> {
+    path: PathBuf,
+    reader: Option<ParquetRecordBatchReader>,
+    /// The rows of the last batch that have not been emitted yet.
+    rows: std::vec::IntoIter<T>,
+    state: State,
+}
+
+impl<T> Clone for ParquetRowSource<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.path.clone())
+    }
+}
+
+impl<T> ParquetRowSource<T> {
+    /// Create a new source that reads the rows of the parquet file at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            reader: None,
+            rows: Vec::new().into_iter(),
+            state: State::Running,
+        }
+    }
+}
+
+impl<T: DeserializeOwned + Send> ParquetRowSource<T> {
+    /// Convert a batch into its rows, passing through the JSON representation of the batch.
+    fn batch_rows(batch: &RecordBatch) -> Vec<T> {
+        let mut writer = ArrayWriter::new(Vec::new());
+        writer
+            .write(batch)
+            .and_then(|_| writer.finish())
+            .expect("failed to convert RecordBatch to rows");
+        serde_json::from_slice(&writer.into_inner()).unwrap_or_else(|e| {
+            panic!(
+                "parquet rows cannot be deserialized as {}: {e}",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+}
+
+impl<T: DeserializeOwned + Send> Operator for ParquetRowSource<T> {
+    type Out = T;
+
+    fn setup(&mut self, metadata: &mut crate::ExecutionMetadata) {
+        let global_id = metadata.global_id as usize;
+        let instances = metadata.replicas.len();
+
+        let file = File::open(&self.path).unwrap_or_else(|err| {
+            panic!(
+                "ParquetRowSource: error while opening file {:?}: {:?}",
+                self.path, err
+            )
+        });
+        let builder =
+            ParquetRecordBatchReaderBuilder::try_new(file).expect("failed to create arrow reader");
+        let row_groups = (0..builder.metadata().num_row_groups())
+            .filter(|i| i % instances == global_id)
+            .collect();
+        let reader = builder
+            .with_row_groups(row_groups)
+            .with_batch_size(1024)
+            .build()
+            .expect("failed to build arrow reader");
+        self.reader = Some(reader);
+    }
+
+    fn next(&mut self) -> StreamElement<T> {
+        loop {
+            if let Some(row) = self.rows.next() {
+                return StreamElement::Item(row);
+            }
+            let r = self.reader.as_mut().unwrap();
+            match r.next() {
+                Some(batch) => {
+                    let batch = batch.expect("failed to build RecordBatch");
+                    self.rows = Self::batch_rows(&batch).into_iter();
+                }
+                None => break,
+            }
+        }
+
+        match self.state {
+            State::Running => {
+                self.state = State::Ended;
+                StreamElement::FlushAndRestart
+            }
+            State::Ended => StreamElement::Terminate,
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<T, _>("ParquetRowSource");
+        operator.kind = OperatorKind::Source;
+        BlockStructure::default().add_operator(operator)
+    }
+}
+
+impl<T> std::fmt::Display for ParquetRowSource<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ParquetRowSource<{}>", std::any::type_name::<T>())
+    }
+}
+
+impl<T: DeserializeOwned + Send> Source for ParquetRowSource<T> {
+    fn replication(&self) -> Replication {
+        Replication::Unlimited
+    }
+}
+
+impl crate::StreamContext {
+    /// Convenience method, creates a `ParquetRowSource` and makes a stream using
+    /// `StreamContext::stream`
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use serde::{Deserialize, Serialize};
+    /// # let env = StreamContext::new_local();
+    /// #[derive(Clone, Serialize, Deserialize)]
+    /// struct Row {
+    ///     id: u32,
+    ///     name: String,
+    /// }
+    ///
+    /// let rows = env.stream_parquet::<Row>("data.parquet").collect_vec();
+    /// ```
+    pub fn stream_parquet<T>(&self, path: impl Into<PathBuf>) -> Stream<ParquetRowSource<T>>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        self.stream(ParquetRowSource::new(path))
+    }
+}
+
 impl<Op> Stream<Op>
 where
     Op: Operator<Out = RecordBatch> + 'static,
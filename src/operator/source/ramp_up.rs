@@ -0,0 +1,176 @@
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use crate::block::{BlockStructure, OperatorStructure, Replication};
+use crate::operator::source::{Source, StopHandle};
+use crate::operator::{Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// Source that limits the rate of another source, increasing it linearly from zero to a target
+/// rate during a warmup.
+///
+/// Build it with [`Stream::ramp_up`].
+#[derive(Clone, Debug)]
+pub struct RampUpSource<S: Source> {
+    inner: S,
+    /// The items per second emitted after the warmup.
+    target_rate: f64,
+    warmup: Duration,
+    /// When the first element was requested.
+    start: Option<Instant>,
+    /// The number of items emitted so far.
+    emitted: u64,
+}
+
+impl<S: Source> RampUpSource<S> {
+    fn new(inner: S, target_rate: f64, warmup: Duration) -> Self {
+        assert!(
+            target_rate > 0.0 && target_rate.is_finite(),
+            "the target rate must be positive"
+        );
+        Self {
+            inner,
+            target_rate,
+            warmup,
+            start: None,
+            emitted: 0,
+        }
+    }
+
+    /// The time, since the start, at which the `n`-th item can be emitted.
+    ///
+    /// During the warmup the rate at time `t` is `target_rate * t / warmup`, so the items emitted
+    /// by then are `target_rate * t² / (2 * warmup)`. After the warmup they grow at `target_rate`.
+    fn emission_time(&self, n: u64) -> Duration {
+        let n = n as f64;
+        let warmup = self.warmup.as_secs_f64();
+        let warmup_items = self.target_rate * warmup / 2.0;
+        let secs = if n <= warmup_items {
+            (2.0 * warmup * n / self.target_rate).sqrt()
+        } else {
+            n / self.target_rate + warmup / 2.0
+        };
+        Duration::from_secs_f64(secs)
+    }
+}
+
+impl<S: Source> Operator for RampUpSource<S> {
+    type Out = S::Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.inner.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let el = self.inner.next();
+        if matches!(
+            el,
+            StreamElement::Item(_) | StreamElement::Timestamped(_, _)
+        ) {
+            self.emitted += 1;
+            let at = start + self.emission_time(self.emitted);
+            let now = Instant::now();
+            if at > now {
+                std::thread::sleep(at - now);
+            }
+        }
+        el
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<S::Out, _>("RampUp");
+        operator.subtitle = format!("{}/s after {:?}", self.target_rate, self.warmup);
+        self.inner.structure().add_operator(operator)
+    }
+}
+
+impl<S: Source> Source for RampUpSource<S> {
+    fn replication(&self) -> Replication {
+        self.inner.replication()
+    }
+
+    fn stop_handle(&self) -> Option<StopHandle> {
+        self.inner.stop_handle()
+    }
+
+    fn is_bounded(&self) -> bool {
+        self.inner.is_bounded()
+    }
+}
+
+impl<S: Source> Display for RampUpSource<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> RampUp[{}/s]", self.inner, self.target_rate)
+    }
+}
+
+impl<S> Stream<S>
+where
+    S: Source + 'static,
+{
+    /// Limit the rate of the source, increasing it linearly from zero to `target_rate` items per
+    /// second during `warmup`, and keeping it at `target_rate` after that.
+    ///
+    /// This avoids the spike of a cold start, making the throughput measured by benchmarks
+    /// cleaner and more reproducible. The rate applies to each replica of the source separately,
+    /// and if the source is slower than the rate the items are emitted as soon as they are read.
+    ///
+    /// This must be called right after creating the stream from the source.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let res = env
+    ///     .stream_iter(0..100)
+    ///     .ramp_up(10_000.0, Duration::from_millis(10))
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), (0..100).collect::<Vec<_>>());
+    /// ```
+    pub fn ramp_up(self, target_rate: f64, warmup: Duration) -> Stream<RampUpSource<S>> {
+        self.add_operator(|inner| RampUpSource::new(inner, target_rate, warmup))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::operator::source::RampUpSource;
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+
+    #[test]
+    fn ramp_up_rate() {
+        let mut source = RampUpSource::new(
+            FakeOperator::new(0..1000u32),
+            1000.0,
+            Duration::from_millis(200),
+        );
+        source.setup(&mut FakeNetworkTopology::<u32>::new(0, 0).metadata());
+
+        let start = Instant::now();
+        let mut times = vec![];
+        while start.elapsed() < Duration::from_millis(400) {
+            assert!(matches!(source.next(), StreamElement::Item(_)));
+            times.push(start.elapsed());
+        }
+        let count = |from: u64, to: u64| {
+            let range = Duration::from_millis(from)..Duration::from_millis(to);
+            times.iter().filter(|t| range.contains(t)).count()
+        };
+
+        // 100 items per 100ms at the target rate, 25 in the first 100ms of the ramp
+        let first = count(0, 100);
+        assert!(first < 50, "{first} items at the start of the warmup");
+        let last = count(300, 400);
+        assert!((80..=110).contains(&last), "{last} items after the warmup");
+    }
+}
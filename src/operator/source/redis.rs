@@ -0,0 +1,320 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use redis::streams::{StreamReadOptions, StreamReadReply};
+use redis::{Commands, Connection, RedisResult};
+use serde::de::DeserializeOwned;
+
+use crate::block::{BlockStructure, OperatorKind, OperatorStructure, Replication};
+use crate::operator::source::{Source, StopHandle};
+use crate::operator::{Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// The field of the Redis Stream entries holding the serialized item.
+pub(crate) const REDIS_DATA_FIELD: &str = "data";
+
+/// The maximum number of entries acknowledged with a single `XACK`.
+const ACK_CHUNK: usize = 1024;
+
+/// Source that consumes the entries of a Redis Stream with a consumer group.
+///
+/// Each replica joins the consumer group as the consumer `<consumer>-<replica>`, so the entries
+/// of the stream are distributed across the replicas. The item is read from the `data` field of
+/// each entry and deserialized from JSON, the entries that cannot be deserialized are logged and
+/// skipped. The group is created, starting from the beginning of the stream, if it does not
+/// exist.
+///
+/// The entries are acknowledged with `XACK` when the source reaches the end of its stream. If the
+/// job fails before, the entries stay pending for their consumer and are delivered again when the
+/// job is restarted with the same group and consumer names, giving at-least-once delivery. The
+/// source does not know when the rest of the job has processed the entries: if the job fails
+/// after the source ended, while the last entries are still being processed downstream, they are
+/// already acknowledged and are lost. Since the pending entries are tracked by Redis until they
+/// are acknowledged, an unbounded job should be stopped periodically with the [`StopHandle`] of
+/// the source.
+///
+/// The stream ends when the [`StopHandle`] is stopped or, if
+/// [`RedisStreamSource::stop_when_empty`] is set, when there are no new entries.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct RedisStreamSource<Out> {
+    url: String,
+    key: String,
+    group: String,
+    consumer: String,
+    count: usize,
+    poll: Duration,
+    stop_when_empty: bool,
+    stop: StopHandle,
+    #[derivative(Debug = "ignore")]
+    connection: Option<Connection>,
+    #[derivative(Debug = "ignore")]
+    buffer: VecDeque<Out>,
+    /// The identifiers of the entries read and not acknowledged yet.
+    to_ack: Vec<String>,
+    /// The id after which to read the entries left pending by a previous execution, `None` once
+    /// they have all been read.
+    pending_cursor: Option<String>,
+    /// Whether some items were emitted since the last `FlushBatch`.
+    need_flush: bool,
+    terminated: bool,
+    _out: PhantomData<Out>,
+}
+
+impl<Out> Display for RedisStreamSource<Out> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RedisStreamSource<{}>", std::any::type_name::<Out>())
+    }
+}
+
+impl<Out: DeserializeOwned + Send + 'static> RedisStreamSource<Out> {
+    /// Create a new source that consumes the stream `key` of the Redis server at `url` (e.g.
+    /// `redis://localhost:6379`) as part of the consumer group `group`.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::source::RedisStreamSource;
+    /// # let mut env = StreamContext::new_local();
+    /// let source = RedisStreamSource::<u64>::new("redis://localhost:6379", "events", "renoir")
+    ///     .consumer("worker");
+    /// let s = env.stream(source);
+    /// ```
+    pub fn new<U, K, G>(url: U, key: K, group: G) -> Self
+    where
+        U: Into<String>,
+        K: Into<String>,
+        G: Into<String>,
+    {
+        Self {
+            url: url.into(),
+            key: key.into(),
+            group: group.into(),
+            consumer: "renoir".into(),
+            count: 1024,
+            poll: Duration::from_millis(100),
+            stop_when_empty: false,
+            stop: Default::default(),
+            connection: None,
+            buffer: Default::default(),
+            to_ack: Default::default(),
+            pending_cursor: Some("0".into()),
+            need_flush: false,
+            terminated: false,
+            _out: PhantomData,
+        }
+    }
+
+    /// The prefix of the consumer names of the replicas (default `renoir`). A restarted job must
+    /// use the same names to receive the entries left pending by the previous execution.
+    pub fn consumer<S: Into<String>>(mut self, consumer: S) -> Self {
+        self.consumer = consumer.into();
+        self
+    }
+
+    /// The maximum number of entries read with a single `XREADGROUP` (default 1024).
+    pub fn count(mut self, count: usize) -> Self {
+        assert!(count > 0, "the count must be positive");
+        self.count = count;
+        self
+    }
+
+    /// How long a read waits for new entries (default 100ms), it is also how often the
+    /// [`StopHandle`] is checked.
+    pub fn poll_interval(mut self, poll: Duration) -> Self {
+        self.poll = poll;
+        self
+    }
+
+    /// End the stream when there are no new entries, instead of waiting for them.
+    pub fn stop_when_empty(mut self) -> Self {
+        self.stop_when_empty = true;
+        self
+    }
+
+    /// Get the handle that ends the stream produced by this source.
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    /// Read the next entries, returns the number of entries read.
+    fn read(&mut self, consumer: &str) -> RedisResult<usize> {
+        let mut options = StreamReadOptions::default()
+            .group(&self.group, consumer)
+            .count(self.count);
+        // the entries left pending for this consumer are read by id, then only the new ones
+        let id = match &self.pending_cursor {
+            Some(cursor) => cursor.clone(),
+            None => {
+                options = options.block(self.poll.as_millis() as usize);
+                ">".to_string()
+            }
+        };
+        let connection = self
+            .connection
+            .as_mut()
+            .expect("RedisStreamSource was not initialized");
+        let reply: StreamReadReply = connection.xread_options(&[&self.key], &[id], &options)?;
+        let mut read = 0;
+        for entry in reply.keys.into_iter().flat_map(|key| key.ids) {
+            read += 1;
+            let item = entry
+                .get::<Vec<u8>>(REDIS_DATA_FIELD)
+                .ok_or_else(|| format!("missing field {REDIS_DATA_FIELD}"))
+                .and_then(|data| serde_json::from_slice(&data).map_err(|e| e.to_string()));
+            match item {
+                Ok(item) => self.buffer.push_back(item),
+                Err(e) => log::warn!("RedisStreamSource: invalid entry {}: {e}", entry.id),
+            }
+            if self.pending_cursor.is_some() {
+                self.pending_cursor = Some(entry.id.clone());
+            }
+            self.to_ack.push(entry.id);
+        }
+        Ok(read)
+    }
+
+    /// Acknowledge all the entries read.
+    fn ack(&mut self) {
+        let Some(connection) = self.connection.as_mut() else {
+            return;
+        };
+        for ids in self.to_ack.chunks(ACK_CHUNK) {
+            let result: RedisResult<usize> = connection.xack(&self.key, &self.group, ids);
+            if let Err(e) = result {
+                log::error!(
+                    "RedisStreamSource: failed to acknowledge {} entries of {}: {e}",
+                    ids.len(),
+                    self.key
+                );
+            }
+        }
+        self.to_ack.clear();
+    }
+
+    fn terminate(&mut self) -> StreamElement<Out> {
+        // the entries may still be in flight downstream, see the documentation of the source
+        self.ack();
+        self.terminated = true;
+        self.connection.take();
+        StreamElement::FlushAndRestart
+    }
+}
+
+impl<Out: DeserializeOwned + Send + 'static> Source for RedisStreamSource<Out> {
+    fn replication(&self) -> Replication {
+        Replication::Unlimited
+    }
+
+    fn stop_handle(&self) -> Option<StopHandle> {
+        Some(self.stop.clone())
+    }
+
+    fn is_bounded(&self) -> bool {
+        false
+    }
+}
+
+impl<Out: DeserializeOwned + Send + 'static> Operator for RedisStreamSource<Out> {
+    type Out = Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.consumer = format!("{}-{}", self.consumer, metadata.global_id);
+        let mut connection = redis::Client::open(self.url.as_str())
+            .and_then(|client| client.get_connection())
+            .unwrap_or_else(|e| panic!("RedisStreamSource: cannot connect to {}: {e}", self.url));
+        let created: RedisResult<()> =
+            connection.xgroup_create_mkstream(&self.key, &self.group, "0");
+        match created {
+            Ok(()) => log::debug!("RedisStreamSource: created group {}", self.group),
+            Err(e) if e.code() == Some("BUSYGROUP") => {}
+            Err(e) => panic!(
+                "RedisStreamSource: cannot create group {} on {}: {e}",
+                self.group, self.key
+            ),
+        }
+        self.connection = Some(connection);
+    }
+
+    fn next(&mut self) -> StreamElement<Out> {
+        loop {
+            if self.terminated {
+                return StreamElement::Terminate;
+            }
+            if let Some(item) = self.buffer.pop_front() {
+                self.need_flush = true;
+                return StreamElement::Item(item);
+            }
+            if self.stop.is_stopped() {
+                return self.terminate();
+            }
+            let consumer = self.consumer.clone();
+            match self.read(&consumer) {
+                Ok(0) if self.pending_cursor.is_some() => self.pending_cursor = None,
+                Ok(0) if self.need_flush => {
+                    self.need_flush = false;
+                    return StreamElement::FlushBatch;
+                }
+                Ok(0) if self.stop_when_empty => return self.terminate(),
+                Ok(_) => {}
+                Err(e) => {
+                    log::warn!("RedisStreamSource: failed to read from {}: {e}", self.key);
+                    std::thread::sleep(self.poll);
+                }
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<Out, _>("RedisStreamSource");
+        operator.subtitle = format!("{} ({})", self.key, self.group);
+        operator.kind = OperatorKind::Source;
+        BlockStructure::default().add_operator(operator)
+    }
+}
+
+impl<Out> Clone for RedisStreamSource<Out> {
+    fn clone(&self) -> Self {
+        Self {
+            url: self.url.clone(),
+            key: self.key.clone(),
+            group: self.group.clone(),
+            consumer: self.consumer.clone(),
+            count: self.count,
+            poll: self.poll,
+            stop_when_empty: self.stop_when_empty,
+            stop: self.stop.clone(),
+            connection: None,
+            buffer: Default::default(),
+            to_ack: Default::default(),
+            pending_cursor: Some("0".into()),
+            need_flush: false,
+            terminated: false,
+            _out: PhantomData,
+        }
+    }
+}
+
+impl crate::StreamContext {
+    /// Convenience method, creates a `RedisStreamSource` and makes a stream using
+    /// `StreamContext::stream`
+    pub fn stream_redis<Out, U, K, G>(
+        &self,
+        url: U,
+        key: K,
+        group: G,
+    ) -> Stream<RedisStreamSource<Out>>
+    where
+        Out: DeserializeOwned + Send + 'static,
+        U: Into<String>,
+        K: Into<String>,
+        G: Into<String>,
+    {
+        let source = RedisStreamSource::new(url, key, group);
+        self.stream(source)
+    }
+}
@@ -0,0 +1,232 @@
+use std::fmt::Display;
+use std::io::{BufRead, BufReader, Stdin};
+
+use crate::block::{BlockStructure, OperatorKind, OperatorStructure, Replication};
+use crate::operator::source::Source;
+use crate::operator::{Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// What [`StdinSource`] does with the lines that are not valid UTF-8.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvalidUtf8 {
+    /// Replace the invalid sequences with `U+FFFD REPLACEMENT CHARACTER`.
+    #[default]
+    Lossy,
+    /// Drop the line, logging a warning.
+    Skip,
+    /// Panic, stopping the job.
+    Fail,
+}
+
+/// Source that reads the lines of the standard input, ending the stream at EOF.
+///
+/// The lines are emitted without the line terminator (`\n` or `\r\n`). The standard input cannot
+/// be split, therefore this source is **not parallel**: the lines are read only by one replica.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct StdinSource<R = BufReader<Stdin>>
+where
+    R: BufRead + Send + 'static,
+{
+    #[derivative(Debug = "ignore")]
+    reader: R,
+    invalid_utf8: InvalidUtf8,
+    /// The number of lines read, used in the error messages.
+    line: usize,
+    buffer: Vec<u8>,
+    terminated: bool,
+}
+
+impl StdinSource {
+    /// Create a new source that reads the lines of the standard input of the process.
+    ///
+    /// **Note**: this source is **not parallel**, the lines are read by a single replica. If you
+    /// want to achieve parallelism you need to add an operator that shuffles the data (e.g.
+    /// [`Stream::shuffle`](crate::Stream::shuffle)).
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::source::StdinSource;
+    /// # let mut env = StreamContext::new_local();
+    /// // cat data.txt | my_job
+    /// let s = env.stream(StdinSource::new());
+    /// s.for_each(|line| println!("{}", line.len()));
+    ///
+    /// env.execute_blocking();
+    /// ```
+    pub fn new() -> Self {
+        Self::from_reader(BufReader::new(std::io::stdin()))
+    }
+}
+
+impl Default for StdinSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R> StdinSource<R>
+where
+    R: BufRead + Send + 'static,
+{
+    /// Create a new source that reads the lines from `reader` instead of the standard input.
+    pub fn from_reader(reader: R) -> Self {
+        Self {
+            reader,
+            invalid_utf8: InvalidUtf8::default(),
+            line: 0,
+            buffer: Vec::new(),
+            terminated: false,
+        }
+    }
+
+    /// Set what to do with the lines that are not valid UTF-8, by default they are decoded lossily.
+    pub fn invalid_utf8(mut self, invalid_utf8: InvalidUtf8) -> Self {
+        self.invalid_utf8 = invalid_utf8;
+        self
+    }
+
+    /// Read the next line, `None` at EOF.
+    fn read_line(&mut self) -> Option<String> {
+        loop {
+            self.buffer.clear();
+            let read = self
+                .reader
+                .read_until(b'\n', &mut self.buffer)
+                .unwrap_or_else(|e| panic!("StdinSource: cannot read line {}: {e}", self.line + 1));
+            if read == 0 {
+                return None;
+            }
+            self.line += 1;
+            if self.buffer.ends_with(b"\n") {
+                self.buffer.pop();
+                if self.buffer.ends_with(b"\r") {
+                    self.buffer.pop();
+                }
+            }
+            match std::str::from_utf8(&self.buffer) {
+                Ok(line) => return Some(line.to_string()),
+                Err(e) => match self.invalid_utf8 {
+                    InvalidUtf8::Lossy => {
+                        return Some(String::from_utf8_lossy(&self.buffer).into_owned())
+                    }
+                    InvalidUtf8::Skip => {
+                        log::warn!("StdinSource: skipping line {}: {e}", self.line);
+                    }
+                    InvalidUtf8::Fail => {
+                        panic!("StdinSource: line {} is not valid UTF-8: {e}", self.line)
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<R> Display for StdinSource<R>
+where
+    R: BufRead + Send + 'static,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "StdinSource")
+    }
+}
+
+impl<R> Source for StdinSource<R>
+where
+    R: BufRead + Send + 'static,
+{
+    fn replication(&self) -> Replication {
+        Replication::One
+    }
+}
+
+impl<R> Operator for StdinSource<R>
+where
+    R: BufRead + Send + 'static,
+{
+    type Out = String;
+
+    fn setup(&mut self, _metadata: &mut ExecutionMetadata) {}
+
+    fn next(&mut self) -> StreamElement<String> {
+        if self.terminated {
+            return StreamElement::Terminate;
+        }
+        match self.read_line() {
+            Some(line) => StreamElement::Item(line),
+            None => {
+                self.terminated = true;
+                StreamElement::FlushAndRestart
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<String, _>("StdinSource");
+        operator.kind = OperatorKind::Source;
+        BlockStructure::default().add_operator(operator)
+    }
+}
+
+impl<R> Clone for StdinSource<R>
+where
+    R: BufRead + Send + 'static,
+{
+    fn clone(&self) -> Self {
+        // Since this is a non-parallel source, we don't want the other replicas to emit any value
+        panic!("StdinSource cannot be cloned, replication should be 1");
+    }
+}
+
+impl crate::StreamContext {
+    /// Convenience method, creates a `StdinSource` and makes a stream using `StreamContext::stream`
+    pub fn stream_stdin(&self) -> Stream<StdinSource> {
+        self.stream(StdinSource::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::{InvalidUtf8, StdinSource};
+    use crate::operator::{Operator, StreamElement};
+
+    const INPUT: &[u8] = b"first\r\nsecond\n\xffthird\n\nlast";
+
+    #[test]
+    fn stdin_source_lines() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let source = StdinSource::from_reader(Cursor::new(INPUT));
+        let res = env.stream(source).collect_vec();
+        env.execute_blocking();
+        assert_eq!(
+            res.get().unwrap(),
+            ["first", "second", "\u{fffd}third", "", "last"]
+        );
+    }
+
+    #[test]
+    fn stdin_source_skip_invalid() {
+        let mut source =
+            StdinSource::from_reader(Cursor::new(INPUT)).invalid_utf8(InvalidUtf8::Skip);
+        for line in ["first", "second", "", "last"] {
+            assert_eq!(source.next(), StreamElement::Item(line.to_string()));
+        }
+        assert_eq!(source.next(), StreamElement::FlushAndRestart);
+        assert_eq!(source.next(), StreamElement::Terminate);
+    }
+
+    #[test]
+    #[should_panic(expected = "line 3 is not valid UTF-8")]
+    fn stdin_source_fail_invalid() {
+        let mut source =
+            StdinSource::from_reader(Cursor::new(INPUT)).invalid_utf8(InvalidUtf8::Fail);
+        while source.next() != StreamElement::Terminate {}
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use flume::{Receiver, RecvTimeoutError};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::block::{group_by_hash, BlockStructure, OperatorKind, OperatorStructure, Replication};
+use crate::operator::source::{Source, StopHandle};
+use crate::operator::{Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::{CoordUInt, Stream};
+
+/// How often the source checks the stop handle while no event is received.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Source that watches a directory and emits the path of each file created or modified inside it.
+///
+/// The events of a file are debounced: a path is emitted only after no new event has been
+/// received for it for the whole quiet period. Hidden files (whose name starts with `.`) are
+/// ignored, so a file can be written with a temporary hidden name and then renamed into place to
+/// be emitted only when complete.
+///
+/// The discovered files are distributed among the replicas by hashing their path, each file is
+/// emitted by exactly one replica. The stream is unbounded: it ends only when the
+/// [`StopHandle`] of the source is stopped. When stopped, the files still waiting out their quiet
+/// period are emitted without waiting further before the stream ends.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct WatchDirSource {
+    path: PathBuf,
+    quiet_period: Duration,
+    stop: StopHandle,
+    #[derivative(Debug = "ignore")]
+    watcher: Option<RecommendedWatcher>,
+    #[derivative(Debug = "ignore")]
+    events: Option<Receiver<notify::Result<notify::Event>>>,
+    /// Paths with pending events, with the time of their last event.
+    pending: HashMap<PathBuf, Instant>,
+    /// Paths whose quiet period has expired and are ready to be emitted.
+    ready: VecDeque<PathBuf>,
+    global_id: CoordUInt,
+    instances: CoordUInt,
+    /// Whether some items were emitted since the last `FlushBatch`.
+    need_flush: bool,
+    terminated: bool,
+}
+
+impl Display for WatchDirSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WatchDirSource<{}>", std::any::type_name::<PathBuf>())
+    }
+}
+
+impl WatchDirSource {
+    /// Create a new source that watches the directory at `path`.
+    ///
+    /// Each replica has to watch the **same** directory: the files are partitioned among the
+    /// replicas. Only the files that appear after the source is started are emitted.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::source::WatchDirSource;
+    /// # let mut env = StreamContext::new_local();
+    /// let source = WatchDirSource::new("/datasets/incoming");
+    /// let stop = source.stop_handle();
+    /// let s = env.stream(source);
+    /// // call `stop.stop()` from another thread to end the stream
+    /// ```
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            quiet_period: Duration::from_millis(500),
+            stop: Default::default(),
+            watcher: None,
+            events: None,
+            pending: Default::default(),
+            ready: Default::default(),
+            global_id: 0,
+            instances: 1,
+            need_flush: false,
+            terminated: false,
+        }
+    }
+
+    /// Time without events after which a file is considered completely written (default 500ms).
+    pub fn quiet_period(mut self, quiet_period: Duration) -> Self {
+        self.quiet_period = quiet_period;
+        self
+    }
+
+    /// Get the handle that ends the stream produced by this source.
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    /// Whether this replica is responsible for emitting the file.
+    fn is_owned(&self, path: &Path) -> bool {
+        let hidden = path
+            .file_name()
+            .map(|name| name.to_string_lossy().starts_with('.'))
+            .unwrap_or(true);
+        !hidden && group_by_hash(&path) % self.instances == self.global_id
+    }
+
+    fn record(&mut self, event: notify::Event) {
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return;
+        }
+        let now = Instant::now();
+        for path in event.paths {
+            if self.is_owned(&path) {
+                self.pending.insert(path, now);
+            }
+        }
+    }
+
+    /// Move the paths whose quiet period has expired (or all of them, if `all`) to the ready
+    /// queue, returning how long to wait for the next one to expire.
+    fn collect_ready(&mut self, all: bool) -> Duration {
+        let now = Instant::now();
+        let mut wait = POLL_INTERVAL;
+        let ready = &mut self.ready;
+        let quiet_period = self.quiet_period;
+        self.pending.retain(|path, last| {
+            let elapsed = now.duration_since(*last);
+            if all || elapsed >= quiet_period {
+                // the file may have been removed or renamed in the meantime
+                if path.is_file() {
+                    ready.push_back(path.clone());
+                }
+                false
+            } else {
+                wait = wait.min(quiet_period - elapsed);
+                true
+            }
+        });
+        wait
+    }
+}
+
+impl Source for WatchDirSource {
+    fn replication(&self) -> Replication {
+        Replication::Unlimited
+    }
+
+    fn stop_handle(&self) -> Option<StopHandle> {
+        Some(self.stop.clone())
+    }
+
+    fn is_bounded(&self) -> bool {
+        false
+    }
+}
+
+impl Operator for WatchDirSource {
+    type Out = PathBuf;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.global_id = metadata.global_id;
+        self.instances = metadata.replicas.len() as CoordUInt;
+
+        let (tx, rx) = flume::unbounded();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+        })
+        .expect("WatchDirSource: failed to create the watcher");
+        watcher
+            .watch(&self.path, RecursiveMode::NonRecursive)
+            .unwrap_or_else(|err| {
+                panic!(
+                    "WatchDirSource: error while watching directory {:?}: {:?}",
+                    self.path, err
+                )
+            });
+        self.watcher = Some(watcher);
+        self.events = Some(rx);
+    }
+
+    fn next(&mut self) -> StreamElement<PathBuf> {
+        loop {
+            if self.terminated {
+                return StreamElement::Terminate;
+            }
+            if let Some(path) = self.ready.pop_front() {
+                self.need_flush = true;
+                return StreamElement::Item(path);
+            }
+            if self.stop.is_stopped() {
+                if let Some(events) = self.events.take() {
+                    // emit the files still waiting out the quiet period before ending the stream
+                    self.watcher.take();
+                    for event in events.try_iter() {
+                        match event {
+                            Ok(event) => self.record(event),
+                            Err(e) => log::warn!(
+                                "WatchDirSource: error while watching {:?}: {e}",
+                                self.path
+                            ),
+                        }
+                    }
+                    self.collect_ready(true);
+                    continue;
+                }
+                self.terminated = true;
+                return StreamElement::FlushAndRestart;
+            }
+
+            let wait = self.collect_ready(false);
+            if !self.ready.is_empty() {
+                continue;
+            }
+
+            let events = self
+                .events
+                .as_ref()
+                .expect("WatchDirSource was not initialized");
+            match events.recv_timeout(wait) {
+                Ok(Ok(event)) => self.record(event),
+                Ok(Err(e)) => {
+                    log::warn!("WatchDirSource: error while watching {:?}: {e}", self.path)
+                }
+                Err(RecvTimeoutError::Timeout) if self.need_flush => {
+                    self.need_flush = false;
+                    return StreamElement::FlushBatch;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    log::warn!("WatchDirSource: watcher of {:?} disconnected", self.path);
+                    self.stop.stop();
+                }
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<PathBuf, _>("WatchDirSource");
+        operator.kind = OperatorKind::Source;
+        BlockStructure::default().add_operator(operator)
+    }
+}
+
+impl Clone for WatchDirSource {
+    fn clone(&self) -> Self {
+        assert!(
+            self.watcher.is_none(),
+            "WatchDirSource must be cloned before calling setup"
+        );
+        Self {
+            path: self.path.clone(),
+            quiet_period: self.quiet_period,
+            stop: self.stop.clone(),
+            watcher: None,
+            events: None,
+            pending: Default::default(),
+            ready: Default::default(),
+            global_id: 0,
+            instances: 1,
+            need_flush: false,
+            terminated: false,
+        }
+    }
+}
+
+impl crate::StreamContext {
+    /// Convenience method, creates a `WatchDirSource` and makes a stream using
+    /// `StreamContext::stream`, returning the handle that stops the stream.
+    pub fn stream_watch_dir<P: Into<PathBuf>>(
+        &self,
+        path: P,
+    ) -> (StopHandle, Stream<WatchDirSource>) {
+        let source = WatchDirSource::new(path);
+        let stop = source.stop_handle();
+        (stop, self.stream(source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use itertools::Itertools;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::WatchDirSource;
+
+    #[test]
+    fn watch_dir_emits_new_files_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let source = WatchDirSource::new(dir.path()).quiet_period(Duration::from_millis(100));
+        let stop = source.stop_handle();
+        let res = env.stream(source).collect_vec();
+
+        let path = dir.path().to_path_buf();
+        let writer = std::thread::spawn(move || {
+            // give the watchers the time to start
+            std::thread::sleep(Duration::from_millis(300));
+            for i in 0..5 {
+                let file = path.join(format!("file{i}.txt"));
+                std::fs::write(&file, "hello").unwrap();
+                std::fs::write(&file, "hello world").unwrap();
+            }
+            // written with a hidden name and renamed into place
+            std::fs::write(path.join(".partial"), "data").unwrap();
+            std::fs::rename(path.join(".partial"), path.join("renamed.txt")).unwrap();
+            std::thread::sleep(Duration::from_millis(800));
+            stop.stop();
+        });
+
+        env.execute_blocking();
+        writer.join().unwrap();
+
+        let res = res
+            .get()
+            .unwrap()
+            .into_iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .sorted()
+            .collect_vec();
+        let mut expected = (0..5).map(|i| format!("file{i}.txt")).collect_vec();
+        expected.push("renamed.txt".to_string());
+        assert_eq!(res, expected);
+    }
+    #[test]
+    fn watch_dir_emits_pending_files_when_stopped() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+        // the quiet period never expires before the source is stopped
+        let source = WatchDirSource::new(dir.path()).quiet_period(Duration::from_secs(3600));
+        let stop = source.stop_handle();
+        let res = env.stream(source).collect_vec();
+
+        let path = dir.path().to_path_buf();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            for i in 0..5 {
+                std::fs::write(path.join(format!("file{i}.txt")), "hello").unwrap();
+            }
+            std::thread::sleep(Duration::from_millis(300));
+            stop.stop();
+        });
+
+        env.execute_blocking();
+        writer.join().unwrap();
+
+        let res = res
+            .get()
+            .unwrap()
+            .into_iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .sorted()
+            .collect_vec();
+        let expected = (0..5).map(|i| format!("file{i}.txt")).collect_vec();
+        assert_eq!(res, expected);
+    }
+}
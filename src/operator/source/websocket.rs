@@ -0,0 +1,297 @@
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use flume::{Receiver, RecvTimeoutError, Sender};
+use futures::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::block::{BlockStructure, OperatorKind, OperatorStructure, Replication};
+use crate::operator::source::{Source, StopHandle};
+use crate::operator::{Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// The capacity of the channel between the connection task and the source.
+const CHANNEL_CAPACITY: usize = 1024;
+/// How often the source checks the stop handle while no message is received.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Source that connects to a WebSocket server and emits the messages it receives.
+///
+/// Each text or binary message is deserialized from JSON, the messages that cannot be
+/// deserialized are logged and skipped. If the connection is lost the source reconnects, the
+/// stream ends when the server closes the connection, when the reconnection attempts are
+/// exhausted or when the [`StopHandle`] of the source is stopped.
+///
+/// **Note**: this source is **not parallel**, a single connection is opened by one replica.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct WebSocketSource<Out> {
+    url: String,
+    greeting: Vec<String>,
+    max_reconnects: usize,
+    reconnect_delay: Duration,
+    stop: StopHandle,
+    #[derivative(Debug = "ignore")]
+    rx: Option<Receiver<Out>>,
+    /// Whether some items were emitted since the last `FlushBatch`.
+    need_flush: bool,
+    terminated: bool,
+    _out: PhantomData<Out>,
+}
+
+impl<Out> Display for WebSocketSource<Out> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WebSocketSource<{}>", std::any::type_name::<Out>())
+    }
+}
+
+impl<Out: DeserializeOwned + Send + 'static> WebSocketSource<Out> {
+    /// Create a new source that connects to the WebSocket server at `url` (e.g.
+    /// `ws://localhost:8080/feed`).
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::source::WebSocketSource;
+    /// # let mut env = StreamContext::new_local();
+    /// let source = WebSocketSource::<u64>::new("ws://localhost:8080/feed")
+    ///     .greeting(r#"{"subscribe": "ticks"}"#);
+    /// let s = env.stream(source);
+    /// ```
+    pub fn new<S: Into<String>>(url: S) -> Self {
+        Self {
+            url: url.into(),
+            greeting: Default::default(),
+            max_reconnects: 5,
+            reconnect_delay: Duration::from_secs(1),
+            stop: Default::default(),
+            rx: None,
+            need_flush: false,
+            terminated: false,
+            _out: PhantomData,
+        }
+    }
+
+    /// Add a text message sent to the server every time the connection is (re)established, for
+    /// example to subscribe to a feed.
+    pub fn greeting<S: Into<String>>(mut self, message: S) -> Self {
+        self.greeting.push(message.into());
+        self
+    }
+
+    /// Number of consecutive failed reconnection attempts after which the stream ends, and the
+    /// delay between them (default 5 attempts, 1 second apart).
+    pub fn reconnect(mut self, max_reconnects: usize, delay: Duration) -> Self {
+        self.max_reconnects = max_reconnects;
+        self.reconnect_delay = delay;
+        self
+    }
+
+    /// Get the handle that ends the stream produced by this source.
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+}
+
+/// Keep the connection with the server open, forwarding the decoded messages to the source.
+async fn connection_loop<Out: DeserializeOwned>(
+    url: String,
+    greeting: Vec<String>,
+    max_reconnects: usize,
+    reconnect_delay: Duration,
+    stop: StopHandle,
+    tx: Sender<Out>,
+) {
+    let mut attempts = 0;
+    loop {
+        if stop.is_stopped() || tx.is_disconnected() {
+            return;
+        }
+        match tokio_tungstenite::connect_async(url.as_str()).await {
+            Ok((mut ws, _)) => {
+                log::debug!("WebSocketSource: connected to {url}");
+                attempts = 0;
+                for message in greeting.iter() {
+                    if let Err(e) = ws.send(Message::Text(message.clone())).await {
+                        log::warn!("WebSocketSource: failed to send greeting to {url}: {e}");
+                    }
+                }
+                while let Some(message) = ws.next().await {
+                    let item = match message {
+                        Ok(Message::Text(text)) => serde_json::from_str(&text),
+                        Ok(Message::Binary(data)) => serde_json::from_slice(&data),
+                        Ok(Message::Close(_)) => {
+                            log::debug!("WebSocketSource: {url} closed the connection");
+                            return;
+                        }
+                        Ok(_) => continue,
+                        Err(e) => {
+                            log::warn!("WebSocketSource: connection to {url} lost: {e}");
+                            break;
+                        }
+                    };
+                    match item {
+                        Ok(item) => {
+                            if tx.send_async(item).await.is_err() {
+                                return;
+                            }
+                        }
+                        Err(e) => log::warn!("WebSocketSource: invalid message from {url}: {e}"),
+                    }
+                }
+            }
+            Err(e) => log::warn!("WebSocketSource: failed to connect to {url}: {e}"),
+        }
+
+        attempts += 1;
+        if attempts > max_reconnects {
+            log::error!("WebSocketSource: giving up on {url} after {max_reconnects} reconnections");
+            return;
+        }
+        tokio::time::sleep(reconnect_delay).await;
+    }
+}
+
+impl<Out: DeserializeOwned + Send + 'static> Source for WebSocketSource<Out> {
+    fn replication(&self) -> Replication {
+        Replication::One
+    }
+
+    fn stop_handle(&self) -> Option<StopHandle> {
+        Some(self.stop.clone())
+    }
+
+    fn is_bounded(&self) -> bool {
+        false
+    }
+}
+
+impl<Out: DeserializeOwned + Send + 'static> Operator for WebSocketSource<Out> {
+    type Out = Out;
+
+    fn setup(&mut self, _metadata: &mut ExecutionMetadata) {
+        let (tx, rx) = flume::bounded(CHANNEL_CAPACITY);
+        tokio::runtime::Handle::current().spawn(connection_loop(
+            self.url.clone(),
+            self.greeting.clone(),
+            self.max_reconnects,
+            self.reconnect_delay,
+            self.stop.clone(),
+            tx,
+        ));
+        self.rx = Some(rx);
+    }
+
+    fn next(&mut self) -> StreamElement<Out> {
+        loop {
+            if self.terminated {
+                return StreamElement::Terminate;
+            }
+            if self.stop.is_stopped() {
+                self.terminated = true;
+                self.rx.take();
+                return StreamElement::FlushAndRestart;
+            }
+            let rx = self
+                .rx
+                .as_ref()
+                .expect("WebSocketSource was not initialized");
+            match rx.recv_timeout(POLL_INTERVAL) {
+                Ok(item) => {
+                    self.need_flush = true;
+                    return StreamElement::Item(item);
+                }
+                Err(RecvTimeoutError::Timeout) if self.need_flush => {
+                    self.need_flush = false;
+                    return StreamElement::FlushBatch;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.terminated = true;
+                    return StreamElement::FlushAndRestart;
+                }
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<Out, _>("WebSocketSource");
+        operator.kind = OperatorKind::Source;
+        BlockStructure::default().add_operator(operator)
+    }
+}
+
+impl<Out> Clone for WebSocketSource<Out> {
+    fn clone(&self) -> Self {
+        // Since this is a non-parallel source, we don't want the other replicas to emit any value
+        panic!("WebSocketSource cannot be cloned, replication should be 1");
+    }
+}
+
+impl crate::StreamContext {
+    /// Convenience method, creates a `WebSocketSource` and makes a stream using
+    /// `StreamContext::stream`
+    pub fn stream_websocket<Out, S>(&self, url: S) -> Stream<WebSocketSource<Out>>
+    where
+        Out: DeserializeOwned + Send + 'static,
+        S: Into<String>,
+    {
+        let source = WebSocketSource::new(url);
+        self.stream(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt};
+    use itertools::Itertools;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::WebSocketSource;
+
+    #[test]
+    fn websocket_source_echo() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        listener.set_nonblocking(true).unwrap();
+
+        // echo server that closes the connection after 3 messages
+        let server = std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                for _ in 0..3 {
+                    let message = ws.next().await.unwrap().unwrap();
+                    ws.send(message).await.unwrap();
+                }
+                ws.close(None).await.unwrap();
+            });
+        });
+
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let source = WebSocketSource::<(u32, String)>::new(format!("ws://{address}"))
+            .greeting(r#"[1, "a"]"#)
+            .greeting(r#"[2, "b"]"#)
+            .greeting(r#"[3, "c"]"#);
+        let res = env.stream(source).collect_vec();
+        env.execute_blocking();
+        server.join().unwrap();
+
+        let res = res.get().unwrap();
+        let expected = [(1, "a"), (2, "b"), (3, "c")]
+            .into_iter()
+            .map(|(n, s)| (n, s.to_string()))
+            .collect_vec();
+        assert_eq!(res, expected);
+    }
+}
@@ -0,0 +1,143 @@
+//! The memory budget of the operators that buffer their elements, and the files where they spill
+//! the elements exceeding it.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Used to give a different name to each spill file of the process.
+static SPILL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// The memory an operator may use to buffer its elements, before spilling them to disk.
+///
+/// The size of an element is estimated with the size of its serialization, so the memory
+/// actually used is usually a bit larger than the budget. Each replica of the operator has its
+/// own budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryBudget {
+    max_bytes: usize,
+    spill_dir: Option<PathBuf>,
+}
+
+impl MemoryBudget {
+    /// A budget of `max_bytes` for each replica, spilling to the temporary directory of the
+    /// system.
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            spill_dir: None,
+        }
+    }
+
+    /// Spill the elements to the directory at `dir` instead of the temporary directory of the
+    /// system. In a remote execution each host writes to its own file system.
+    pub fn spill_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.spill_dir = Some(dir.into());
+        self
+    }
+
+    pub(crate) fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    pub(crate) fn dir(&self) -> PathBuf {
+        self.spill_dir.clone().unwrap_or_else(std::env::temp_dir)
+    }
+}
+
+/// The estimated memory used by an element, see [`MemoryBudget`].
+pub(crate) fn estimated_size<T: Serialize>(item: &T) -> usize {
+    bincode::serialized_size(item).unwrap_or_default() as usize
+}
+
+/// A sequence of elements spilled to a file, read back in the same order.
+///
+/// The file is deleted when the run is dropped.
+pub(crate) struct SpilledRun<T> {
+    path: PathBuf,
+    reader: BufReader<File>,
+    /// The number of elements not read back yet.
+    remaining: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> SpilledRun<T> {
+    /// Write `items` to a new file in `dir`.
+    pub(crate) fn write<'a>(dir: &Path, items: impl IntoIterator<Item = &'a T>) -> Self
+    where
+        T: 'a,
+    {
+        let name = format!(
+            "renoir-spill-{}-{}",
+            std::process::id(),
+            SPILL_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = dir.join(name);
+        let file = File::create(&path)
+            .unwrap_or_else(|e| panic!("cannot create the spill file {}: {e}", path.display()));
+        let mut writer = BufWriter::new(file);
+        let mut len = 0;
+        for item in items {
+            bincode::serialize_into(&mut writer, item)
+                .unwrap_or_else(|e| panic!("cannot write the spill file {}: {e}", path.display()));
+            len += 1;
+        }
+        writer
+            .flush()
+            .unwrap_or_else(|e| panic!("cannot write the spill file {}: {e}", path.display()));
+
+        let file = File::open(&path)
+            .unwrap_or_else(|e| panic!("cannot open the spill file {}: {e}", path.display()));
+        Self {
+            path,
+            reader: BufReader::new(file),
+            remaining: len,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for SpilledRun<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let item = bincode::deserialize_from(&mut self.reader)
+            .unwrap_or_else(|e| panic!("cannot read the spill file {}: {e}", self.path.display()));
+        Some(item)
+    }
+}
+
+impl<T> Drop for SpilledRun<T> {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::warn!("cannot remove the spill file {}: {e}", self.path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpilledRun;
+
+    #[test]
+    fn spilled_run_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let items = vec![(1, "a".to_string()), (2, "b".to_string())];
+        let mut run: SpilledRun<(u32, String)> = SpilledRun::write(dir.path(), &items);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        assert_eq!(run.next(), Some(items[0].clone()));
+        assert_eq!(run.collect::<Vec<_>>(), vec![items[1].clone()]);
+        // the file is removed with the run
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
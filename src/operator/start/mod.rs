@@ -1,6 +1,6 @@
 use std::fmt::{Debug, Display};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 pub(crate) use binary::*;
 pub(crate) use simple::*;
@@ -14,7 +14,9 @@ use crate::operator::iteration::IterationStateLock;
 use crate::operator::source::Source;
 use crate::operator::start::watermark_frontier::WatermarkFrontier;
 use crate::operator::{ExchangeData, Operator, StreamElement};
+use crate::profiler::{get_profiler, Profiler};
 use crate::scheduler::{BlockId, ExecutionMetadata};
+use crate::shutdown::ShutdownRecorder;
 
 mod binary;
 mod simple;
@@ -68,6 +70,12 @@ pub(crate) type SimpleStartOperator<Out> = Start<SimpleStartReceiver<Out>>;
 pub(crate) struct Start<Receiver: StartReceiver + Send> {
     /// Execution metadata of this block.
     max_delay: Option<Duration>,
+    /// If set, emit a `FlushBatch` at least this often while the inputs are silent.
+    tick: Option<Duration>,
+    /// If set, emit a `FlushBatch` at least this often, even while the messages keep arriving.
+    flush_interval: Option<Duration>,
+    /// When the next periodic `FlushBatch` is due.
+    next_flush: Option<Instant>,
 
     coord: Option<Coord>,
 
@@ -85,6 +93,10 @@ pub(crate) struct Start<Receiver: StartReceiver + Send> {
     /// The total number of replicas in the previous blocks. This is used for resetting
     /// `missing_flush_and_restart`.
     num_previous_replicas: usize,
+    /// When the first `StreamElement::Terminate` was received.
+    first_terminate: Option<Instant>,
+    /// Where to record when all the `StreamElement::Terminate` have been received.
+    shutdown: ShutdownRecorder,
 
     /// Whether the previous blocks timed out and the last batch has been flushed.
     ///
@@ -93,6 +105,8 @@ pub(crate) struct Start<Receiver: StartReceiver + Send> {
 
     /// The current frontier of the watermarks from the previous replicas.
     watermark_frontier: WatermarkFrontier,
+    /// The previous replicas holding the watermark frontier back, the last time it was checked.
+    constraining: Vec<Coord>,
 
     /// Whether the iteration has ended and the current block has to wait for the local iteration
     /// leader to update the iteration state before letting the messages pass.
@@ -105,14 +119,20 @@ impl<Receiver: StartReceiver + Send> Clone for Start<Receiver> {
     fn clone(&self) -> Self {
         Self {
             max_delay: self.max_delay,
+            tick: self.tick,
+            flush_interval: self.flush_interval,
+            next_flush: None,
             coord: self.coord,
             receiver: self.receiver.clone(),
             batch_iter: Default::default(),
             missing_terminate: self.missing_terminate,
             missing_flush_and_restart: self.missing_flush_and_restart,
             num_previous_replicas: self.num_previous_replicas,
+            first_terminate: self.first_terminate,
+            shutdown: self.shutdown.clone(),
             already_timed_out: self.already_timed_out,
             watermark_frontier: self.watermark_frontier.clone(),
+            constraining: self.constraining.clone(),
             wait_for_state: self.wait_for_state,
             state_lock: self.state_lock.clone(),
             state_generation: self.state_generation,
@@ -162,6 +182,9 @@ impl<Receiver: StartReceiver + Send> Start<Receiver> {
         Self {
             coord: Default::default(),
             max_delay: Default::default(),
+            tick: Default::default(),
+            flush_interval: Default::default(),
+            next_flush: None,
 
             receiver,
             batch_iter: None,
@@ -169,10 +192,13 @@ impl<Receiver: StartReceiver + Send> Start<Receiver> {
             missing_terminate: Default::default(),
             missing_flush_and_restart: Default::default(),
             num_previous_replicas: 0,
+            first_terminate: None,
+            shutdown: Default::default(),
 
             already_timed_out: Default::default(),
 
             watermark_frontier: Default::default(),
+            constraining: Default::default(),
 
             wait_for_state: Default::default(),
             state_lock,
@@ -183,6 +209,23 @@ impl<Receiver: StartReceiver + Send> Start<Receiver> {
     pub(crate) fn receiver(&self) -> &Receiver {
         &self.receiver
     }
+
+    /// Report the last watermarks of the previous replicas to the profiler, logging which ones
+    /// are holding the frontier back when they change.
+    fn report_watermarks(&mut self, coord: Coord) {
+        let constraining = self.watermark_frontier.constraining();
+        if constraining != self.constraining {
+            let inputs = constraining
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>();
+            log::debug!("{coord} watermark held back by [{}]", inputs.join(", "));
+            self.constraining = constraining;
+        }
+        if cfg!(feature = "profiler") {
+            get_profiler().watermarks(coord, self.watermark_frontier.inputs());
+        }
+    }
 }
 
 impl<Receiver> Operator for Start<Receiver>
@@ -199,7 +242,8 @@ where
         self.num_previous_replicas = prev_replicas.len();
         self.missing_terminate = self.num_previous_replicas;
         self.missing_flush_and_restart = self.num_previous_replicas;
-        self.watermark_frontier = WatermarkFrontier::new(prev_replicas);
+        self.watermark_frontier =
+            WatermarkFrontier::new(prev_replicas, metadata.watermark_idleness);
 
         log::trace!(
             "{} initialized <{}>",
@@ -208,6 +252,9 @@ where
         );
         self.coord = Some(metadata.coord);
         self.max_delay = metadata.batch_mode.max_delay();
+        self.tick = metadata.tick;
+        self.flush_interval = metadata.batch_mode.flush_interval();
+        self.shutdown = metadata.shutdown.clone();
     }
 
     fn next(&mut self) -> StreamElement<Receiver::Out> {
@@ -217,6 +264,9 @@ where
             // all the previous blocks sent an end: we're done
             if self.missing_terminate == 0 {
                 log::trace!("{} ended", coord);
+                if let Some(first_terminate) = self.first_terminate.take() {
+                    self.shutdown.ends_received(coord, first_terminate);
+                }
                 return StreamElement::Terminate;
             }
             if self.missing_flush_and_restart == 0 {
@@ -230,6 +280,15 @@ where
                 return StreamElement::FlushAndRestart;
             }
 
+            // with a periodic flush the batch is flushed on time even if the messages keep arriving
+            if let (Some(interval), false) = (self.flush_interval, self.wait_for_state) {
+                let now = Instant::now();
+                if now >= *self.next_flush.get_or_insert(now + interval) {
+                    self.next_flush = Some(now + interval);
+                    return StreamElement::FlushBatch;
+                }
+            }
+
             if let Some((sender, ref mut inner)) = self.batch_iter {
                 let msg = match inner.next() {
                     None => {
@@ -241,7 +300,9 @@ where
                         match item {
                             StreamElement::Watermark(ts) => {
                                 // update the frontier and return a watermark if necessary
-                                match self.watermark_frontier.update(sender, ts) {
+                                let frontier = self.watermark_frontier.update(sender, ts);
+                                self.report_watermarks(coord);
+                                match frontier {
                                     Some(ts) => StreamElement::Watermark(ts), // ts is safe
                                     None => continue,
                                 }
@@ -256,6 +317,7 @@ where
                                 continue;
                             }
                             StreamElement::Terminate => {
+                                self.first_terminate.get_or_insert_with(Instant::now);
                                 self.missing_terminate -= 1;
                                 log::trace!(
                                     "{} received terminate, {} left",
@@ -280,31 +342,63 @@ where
                 return msg;
             }
 
+            // some inputs may have become idle since the last batch
+            if let Some(ts) = self.watermark_frontier.update_idle() {
+                self.report_watermarks(coord);
+                return StreamElement::Watermark(ts);
+            }
+
             // Receive next batch
-            let net_msg = match (self.already_timed_out, self.max_delay) {
-                // check the timeout only if there is one and the last time we didn't timed out
-                (false, Some(max_delay)) => {
-                    match self.receiver.recv_timeout(max_delay) {
-                        Ok(net_msg) => net_msg,
-                        Err(_) => {
-                            // timed out: tell the block to flush the current batch
-                            // next time we wait indefinitely without the timeout since the batch is
-                            // currently empty
-                            self.already_timed_out = true;
-                            // this is a fake batch, and its sender is meaningless and will be
-                            // forget immediately
+            // check the batch timeout only if there is one and the last time we didn't timed out
+            let batch_timeout = self.max_delay.filter(|_| !self.already_timed_out);
+            // with an idleness timeout wake up periodically to check for idle inputs
+            let timeout = batch_timeout
+                .into_iter()
+                .chain(self.watermark_frontier.idleness())
+                .chain(self.tick)
+                .chain(
+                    self.next_flush
+                        .map(|next| next.saturating_duration_since(Instant::now())),
+                )
+                .min();
+            let net_msg = match timeout {
+                Some(timeout) => match self.receiver.recv_timeout(timeout) {
+                    Ok(net_msg) => net_msg,
+                    // a previous replica crashed before sending its end
+                    Err(RecvTimeoutError::Disconnected) => {
+                        panic!("{coord}: the previous replicas disconnected")
+                    }
+                    Err(_)
+                        if batch_timeout.is_some()
+                            || self.tick.is_some()
+                            || self.flush_interval.is_some() =>
+                    {
+                        // timed out: tell the block to flush the current batch
+                        // next time we wait without the batch timeout since the batch is
+                        // currently empty, but with a tick we keep waking up the block
+                        self.already_timed_out = true;
+                        if let Some(interval) = self.flush_interval {
+                            self.next_flush = Some(Instant::now() + interval);
+                        }
+                        // this is a fake batch, and its sender is meaningless and will be
+                        // forget immediately
+                        self.batch_iter = Some((
+                            Default::default(),
                             NetworkMessage::new_single(
                                 StreamElement::FlushBatch,
                                 Default::default(),
                             )
-                        }
+                            .into_iter(),
+                        ));
+                        continue;
                     }
-                }
-                _ => {
-                    self.already_timed_out = false;
-                    self.receiver.recv()
-                }
+                    // only the idle inputs have to be checked
+                    Err(_) => continue,
+                },
+                None => self.receiver.recv(),
             };
+            self.already_timed_out = false;
+            self.watermark_frontier.activity(net_msg.sender());
 
             self.batch_iter = Some((net_msg.sender(), net_msg.into_iter()));
         }
@@ -327,6 +421,9 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
+    use crate::block::BatchMode;
     use crate::network::NetworkMessage;
     use crate::operator::{BinaryElement, Operator, Start, StreamElement, Timestamp};
     use crate::test::FakeNetworkTopology;
@@ -374,6 +471,38 @@ mod tests {
         assert_eq!(StreamElement::Terminate, start_block.next());
     }
 
+    #[test]
+    fn test_periodic_flush() {
+        let mut t = FakeNetworkTopology::new(1, 1);
+        let (from, sender) = t.senders_mut()[0].pop().unwrap();
+
+        let mut start_block = Start::single(sender.receiver_endpoint.prev_block_id, None);
+        let mut metadata = t.metadata();
+        metadata.batch_mode = BatchMode::periodic(100, Duration::from_millis(20));
+        start_block.setup(&mut metadata);
+
+        sender
+            .send(NetworkMessage::new_batch(
+                vec![StreamElement::Item(1), StreamElement::Item(2)],
+                from,
+            ))
+            .unwrap();
+
+        assert_eq!(StreamElement::Item(1), start_block.next());
+        // the interval elapsed while the messages are still arriving
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(StreamElement::FlushBatch, start_block.next());
+        assert_eq!(StreamElement::Item(2), start_block.next());
+        // the flush keeps firing while the inputs are silent
+        assert_eq!(StreamElement::FlushBatch, start_block.next());
+        assert_eq!(StreamElement::FlushBatch, start_block.next());
+
+        sender
+            .send(NetworkMessage::new_single(StreamElement::Terminate, from))
+            .unwrap();
+        assert_eq!(StreamElement::Terminate, start_block.next());
+    }
+
     #[test]
     #[cfg(feature = "timestamp")]
     fn test_single_watermark() {
@@ -422,6 +551,99 @@ mod tests {
         assert_eq!(StreamElement::Watermark(ts(110)), start_block.next());
     }
 
+    #[test]
+    #[cfg(feature = "timestamp")]
+    fn test_single_watermark_idleness() {
+        let mut t = FakeNetworkTopology::<i32>::new(1, 2);
+        let (from1, sender1) = t.senders_mut()[0].pop().unwrap();
+        let (from2, sender2) = t.senders_mut()[0].pop().unwrap();
+
+        let mut start_block = Start::single(sender1.receiver_endpoint.prev_block_id, None);
+        let mut metadata = t.metadata();
+        metadata.watermark_idleness = Some(Duration::from_millis(200));
+        start_block.setup(&mut metadata);
+
+        sender1
+            .send(NetworkMessage::new_single(
+                StreamElement::Watermark(ts(20)),
+                from1,
+            ))
+            .unwrap();
+
+        // the second replica never sent anything, and after the timeout it is not waited for
+        assert_eq!(StreamElement::<i32>::FlushBatch, start_block.next());
+        assert_eq!(StreamElement::Watermark(ts(20)), start_block.next());
+
+        // the second replica resumes behind the frontier, which waits for it to catch up
+        sender2
+            .send(NetworkMessage::new_single(
+                StreamElement::Watermark(ts(10)),
+                from2,
+            ))
+            .unwrap();
+        sender1
+            .send(NetworkMessage::new_single(
+                StreamElement::Watermark(ts(30)),
+                from1,
+            ))
+            .unwrap();
+        sender2
+            .send(NetworkMessage::new_single(
+                StreamElement::Watermark(ts(40)),
+                from2,
+            ))
+            .unwrap();
+
+        assert_eq!(StreamElement::Watermark(ts(30)), start_block.next());
+    }
+
+    #[test]
+    #[cfg(feature = "timestamp")]
+    fn test_single_watermark_constraining() {
+        let mut t = FakeNetworkTopology::<i32>::new(1, 2);
+        let (from1, sender1) = t.senders_mut()[0].pop().unwrap();
+        let (from2, sender2) = t.senders_mut()[0].pop().unwrap();
+
+        let mut start_block = Start::single(sender1.receiver_endpoint.prev_block_id, None);
+        start_block.setup(&mut t.metadata());
+
+        sender1
+            .send(NetworkMessage::new_single(
+                StreamElement::Watermark(ts(10)),
+                from1,
+            ))
+            .unwrap();
+        sender2
+            .send(NetworkMessage::new_single(
+                StreamElement::Watermark(ts(10)),
+                from2,
+            ))
+            .unwrap();
+        assert_eq!(StreamElement::Watermark(ts(10)), start_block.next());
+        // both replicas are at the frontier, in the order of the replicas
+        assert_eq!(start_block.constraining, vec![from2, from1]);
+
+        // the second replica stops advancing its watermark
+        sender1
+            .send(NetworkMessage::new_batch(
+                vec![
+                    StreamElement::Watermark(ts(20)),
+                    StreamElement::Watermark(ts(30)),
+                ],
+                from1,
+            ))
+            .unwrap();
+        assert_eq!(StreamElement::<i32>::FlushBatch, start_block.next());
+        assert_eq!(start_block.constraining, vec![from2]);
+
+        let inputs = start_block.watermark_frontier.inputs();
+        let input = |coord| inputs.iter().find(|i| i.from == coord).unwrap();
+        assert_eq!(input(from1).watermark, Some(ts(30)));
+        assert!(!input(from1).constraining);
+        assert_eq!(input(from2).watermark, Some(ts(10)));
+        assert!(input(from2).constraining);
+    }
+
     #[test]
     #[cfg(feature = "timestamp")]
     fn test_multiple_no_cache() {
@@ -1,17 +1,30 @@
+use std::time::{Duration, Instant};
+
 use indexmap::IndexMap;
 
 use crate::block::CoordHasherBuilder;
 use crate::network::Coord;
 use crate::operator::Timestamp;
+use crate::profiler::InputWatermark;
 
 /// Handle watermarks coming from multiple replicas.
 ///
 /// A watermark with timestamp `ts` is safe to be passed downstream if and only if, for every
 /// previous replica, a watermark with timestamp greater or equal to `ts` has already been received.
+///
+/// With an idleness timeout, the replicas that sent nothing for longer than the timeout are
+/// ignored until they send something again, so that an idle input does not stall the frontier.
+/// When all the replicas are idle, the frontier advances to the largest watermark received.
+/// The frontier never goes back: when an idle replica resumes behind it, the frontier waits for
+/// the replica to catch up.
 #[derive(Clone, Debug, Default)]
 pub(super) struct WatermarkFrontier {
     map: IndexMap<Coord, Option<Timestamp>, CoordHasherBuilder>,
     front: Option<Timestamp>,
+    /// After how long without messages a replica is ignored.
+    idleness: Option<Duration>,
+    /// When each replica sent its last message, tracked only with an idleness timeout.
+    last_active: IndexMap<Coord, Instant, CoordHasherBuilder>,
 }
 
 fn opt_join<T: std::cmp::Ord>(a: Option<T>, b: Option<T>, f: fn(T, T) -> T) -> Option<T> {
@@ -22,15 +35,51 @@ fn opt_join<T: std::cmp::Ord>(a: Option<T>, b: Option<T>, f: fn(T, T) -> T) -> O
 }
 
 impl WatermarkFrontier {
-    pub fn new(prev_replicas: impl IntoIterator<Item = Coord>) -> Self {
+    pub fn new(prev_replicas: impl IntoIterator<Item = Coord>, idleness: Option<Duration>) -> Self {
+        let map: IndexMap<_, _, _> = prev_replicas.into_iter().map(|c| (c, None)).collect();
+        let last_active = match idleness {
+            Some(_) => {
+                let now = Instant::now();
+                map.keys().map(|&c| (c, now)).collect()
+            }
+            None => Default::default(),
+        };
         Self {
-            map: prev_replicas.into_iter().map(|c| (c, None)).collect(),
+            map,
             front: None,
+            idleness,
+            last_active,
+        }
+    }
+
+    /// The idleness timeout of the replicas, if any.
+    pub fn idleness(&self) -> Option<Duration> {
+        self.idleness
+    }
+
+    fn is_idle(&self, coord: &Coord, now: Instant) -> bool {
+        match (self.idleness, self.last_active.get(coord)) {
+            (Some(idleness), Some(&last)) => now.duration_since(last) >= idleness,
+            _ => false,
         }
     }
 
     fn compute_frontier(&self) -> Option<Timestamp> {
-        let (complete, min) = self.map.values().fold((true, None), |(all, min), x| {
+        let now = Instant::now();
+        let mut active = self
+            .map
+            .iter()
+            .filter(|(coord, _)| !self.is_idle(coord, now))
+            .peekable();
+        if active.peek().is_none() && !self.map.is_empty() {
+            // all the replicas are idle: none of them is holding the others back
+            return self
+                .map
+                .values()
+                .fold(None, |max, x| opt_join(max, *x, std::cmp::max));
+        }
+
+        let (complete, min) = active.fold((true, None), |(all, min), (_, x)| {
             (all & x.is_some(), opt_join(min, *x, std::cmp::min))
         });
 
@@ -41,6 +90,21 @@ impl WatermarkFrontier {
         }
     }
 
+    /// Recompute the frontier, return `Some(ts)` if it advanced to `ts`.
+    fn advance(&mut self) -> Option<Timestamp> {
+        match (self.front, self.compute_frontier()) {
+            (None, Some(new)) => {
+                self.front = Some(new);
+                Some(new)
+            }
+            (Some(old), Some(new)) if new > old => {
+                self.front = Some(new);
+                Some(new)
+            }
+            _ => None,
+        }
+    }
+
     /// Update the frontier, return `Some(ts)` if timestamp `ts` is now safe
     pub fn update(&mut self, coord: Coord, ts: Timestamp) -> Option<Timestamp> {
         let t0 = &mut self.map[&coord];
@@ -50,17 +114,57 @@ impl WatermarkFrontier {
         }
         *t0 = Some(ts);
 
-        // get new frontier
-        let prev_frontier = self.front;
-        self.front = self.compute_frontier();
+        self.advance()
+    }
 
-        match (prev_frontier, self.front) {
-            (None, Some(new)) => Some(new),
-            (Some(old), Some(new)) if old != new => Some(new),
-            _ => None,
+    /// Record that a message from `coord` has been received, so it is not idle.
+    pub fn activity(&mut self, coord: Coord) {
+        if let Some(last) = self.last_active.get_mut(&coord) {
+            *last = Instant::now();
         }
     }
 
+    /// Recompute the frontier ignoring the replicas that became idle, return `Some(ts)` if
+    /// timestamp `ts` is now safe.
+    pub fn update_idle(&mut self) -> Option<Timestamp> {
+        self.idleness?;
+        self.advance()
+    }
+
+    /// The replicas holding the frontier back: the ones that are not idle and sent no watermark
+    /// yet, or whose last watermark is the lowest.
+    pub fn constraining(&self) -> Vec<Coord> {
+        let now = Instant::now();
+        let active = || self.map.iter().filter(|(c, _)| !self.is_idle(c, now));
+        let missing: Vec<_> = active()
+            .filter(|(_, x)| x.is_none())
+            .map(|(&c, _)| c)
+            .collect();
+        if !missing.is_empty() {
+            return missing;
+        }
+        let min = active().filter_map(|(_, x)| *x).min();
+        active()
+            .filter(|(_, x)| min.is_some() && **x == min)
+            .map(|(&c, _)| c)
+            .collect()
+    }
+
+    /// The last watermark of each replica, marking the ones holding the frontier back.
+    pub fn inputs(&self) -> Vec<InputWatermark> {
+        let now = Instant::now();
+        let constraining = self.constraining();
+        self.map
+            .iter()
+            .map(|(&from, &watermark)| InputWatermark {
+                from,
+                watermark,
+                idle: self.is_idle(&from, now),
+                constraining: constraining.contains(&from),
+            })
+            .collect()
+    }
+
     /// Reset all the watermarks.
     pub fn reset(&mut self) {
         self.map.values_mut().for_each(|v| *v = None);
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use crate::operator::rich_map::RichMap;
+use crate::operator::{Data, DataKey, Operator};
+use crate::stream::{KeyedItem, KeyedStream};
+
+/// A [`KeyedStream`] whose next stateful operator drops the state of the keys that are not seen
+/// for longer than a time to live.
+///
+/// Build it with [`KeyedStream::with_state_ttl`].
+pub struct KeyedStateTtl<Op>
+where
+    Op: Operator,
+    Op::Out: KeyedItem,
+{
+    inner: KeyedStream<Op>,
+    ttl: Duration,
+}
+
+impl<K, I, Op> KeyedStream<Op>
+where
+    K: DataKey,
+    I: Send + 'static,
+    Op: Operator<Out = (K, I)> + 'static,
+{
+    /// Expire the per-key state of the next stateful operator if the key is not seen for longer
+    /// than `ttl`, freeing its memory. If the key appears again later, its state starts fresh.
+    ///
+    /// The TTL is refreshed by every item of the key, since the operators that support it read
+    /// and update the state of a key together. The expired keys are evicted when the operator
+    /// receives an element of the stream, so a key is dropped between `ttl` and `1.5 * ttl` after
+    /// it was last seen, or later if the stream is idle.
+    ///
+    /// This is meant for long-running streams with an unbounded key space, where the state of the
+    /// keys that stop appearing would otherwise never be freed. Only the operators that emit while
+    /// processing the stream support a TTL: expiring the accumulators of
+    /// [`KeyedStream::fold`] or [`KeyedStream::reduce`] would lose their output.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..6).group_by(|&n| n % 2);
+    /// let res = s
+    ///     .with_state_ttl(Duration::from_secs(60))
+    ///     .rich_map(|(_key, n)| n)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    /// # assert_eq!(res.get().unwrap().len(), 6);
+    /// ```
+    pub fn with_state_ttl(self, ttl: Duration) -> KeyedStateTtl<Op> {
+        assert!(!ttl.is_zero(), "the state TTL must be positive");
+        KeyedStateTtl { inner: self, ttl }
+    }
+}
+
+impl<K, I, Op> KeyedStateTtl<Op>
+where
+    K: DataKey,
+    I: Send + 'static,
+    Op: Operator<Out = (K, I)> + 'static,
+{
+    /// Like [`KeyedStream::rich_map`], with the state of each key expiring after the TTL.
+    pub fn rich_map<O, F>(self, f: F) -> KeyedStream<impl Operator<Out = (K, O)>>
+    where
+        F: FnMut((&K, I)) -> O + Clone + Send + 'static,
+        O: Data,
+    {
+        let ttl = self.ttl;
+        self.inner
+            .add_operator(|prev| RichMap::with_ttl(prev, f, ttl))
+    }
+
+    /// Like [`KeyedStream::rich_flat_map`], with the state of each key expiring after the TTL.
+    pub fn rich_flat_map<O, It, F>(self, f: F) -> KeyedStream<impl Operator<Out = (K, O)>>
+    where
+        It: IntoIterator<Item = O>,
+        <It as IntoIterator>::IntoIter: Clone + Send + 'static,
+        F: FnMut((&K, I)) -> It + Clone + Send + 'static,
+        O: Data,
+        It: Data,
+    {
+        self.rich_map(f).flatten()
+    }
+
+    /// Like [`KeyedStream::rich_filter_map`], with the state of each key expiring after the TTL.
+    pub fn rich_filter_map<O, F>(self, f: F) -> KeyedStream<impl Operator<Out = (K, O)>>
+    where
+        F: FnMut((&K, I)) -> Option<O> + Send + Clone + 'static,
+        O: Data,
+    {
+        self.rich_map(f)
+            .filter(|(_, x)| x.is_some())
+            .map(|(_, x)| x.unwrap())
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt::Display;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::block::{BlockStructure, OperatorStructure};
+use crate::operator::{Data, Operator, StreamElement};
+use crate::savepoint::Savepoint;
+use crate::scheduler::ExecutionMetadata;
+use crate::CoordUInt;
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct StatefulMap<S, O, F, Op>
+where
+    F: Fn(&mut S, Op::Out) -> O + Send + Clone,
+    Op: Operator,
+{
+    prev: Op,
+    id: String,
+    #[derivative(Debug = "ignore")]
+    state: S,
+    #[derivative(Debug = "ignore")]
+    f: F,
+    global_id: CoordUInt,
+    #[derivative(Debug = "ignore")]
+    savepoint: Arc<Savepoint>,
+    _out: std::marker::PhantomData<O>,
+}
+
+impl<S: Clone, O, F, Op: Clone> Clone for StatefulMap<S, O, F, Op>
+where
+    F: Fn(&mut S, Op::Out) -> O + Send + Clone,
+    Op: Operator,
+{
+    fn clone(&self) -> Self {
+        Self {
+            prev: self.prev.clone(),
+            id: self.id.clone(),
+            state: self.state.clone(),
+            f: self.f.clone(),
+            global_id: self.global_id,
+            savepoint: self.savepoint.clone(),
+            _out: Default::default(),
+        }
+    }
+}
+
+impl<S, O, F, Op> Display for StatefulMap<S, O, F, Op>
+where
+    F: Fn(&mut S, Op::Out) -> O + Send + Clone,
+    Op: Operator,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} -> StatefulMap[{}]<{} -> {}>",
+            self.prev,
+            self.id,
+            std::any::type_name::<Op::Out>(),
+            std::any::type_name::<O>()
+        )
+    }
+}
+
+impl<S, O, F, Op> StatefulMap<S, O, F, Op>
+where
+    F: Fn(&mut S, Op::Out) -> O + Send + Clone,
+    Op: Operator,
+{
+    pub(super) fn new(prev: Op, id: String, init: S, f: F) -> Self {
+        Self {
+            prev,
+            id,
+            state: init,
+            f,
+            global_id: 0,
+            savepoint: Default::default(),
+            _out: Default::default(),
+        }
+    }
+}
+
+impl<S, O: Data, F, Op> Operator for StatefulMap<S, O, F, Op>
+where
+    S: Serialize + DeserializeOwned + Clone + Send,
+    F: Fn(&mut S, Op::Out) -> O + Send + Clone,
+    Op: Operator,
+{
+    type Out = O;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        self.global_id = metadata.global_id;
+        self.savepoint = metadata.savepoint.clone();
+        if let Some(state) = self.savepoint.load(&self.id, self.global_id) {
+            self.state = state;
+        }
+    }
+
+    #[inline]
+    fn next(&mut self) -> StreamElement<O> {
+        let element = self.prev.next();
+        if matches!(element, StreamElement::Terminate) {
+            self.savepoint.store(&self.id, self.global_id, &self.state);
+        }
+        element.map(|item| (self.f)(&mut self.state, item))
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<O, _>("StatefulMap");
+        operator.subtitle = format!("id: {}", self.id);
+        self.prev.structure().add_operator(operator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::operator::stateful_map::StatefulMap;
+    use crate::operator::{Operator, StreamElement};
+    use crate::savepoint::Savepoint;
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+
+    #[test]
+    fn stateful_map_restores_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sp");
+
+        let mut topology = FakeNetworkTopology::<u32>::new(0, 0);
+        let mut metadata = topology.metadata();
+        metadata.savepoint = Arc::new(Savepoint::new(None, Some(path.clone())));
+        let savepoint = metadata.savepoint.clone();
+        let mut map = StatefulMap::new(
+            FakeOperator::new(0..3u32),
+            "sum".into(),
+            0u32,
+            |sum: &mut u32, x| {
+                *sum += x;
+                *sum
+            },
+        );
+        map.setup(&mut metadata);
+        assert_eq!(map.next(), StreamElement::Item(0));
+        assert_eq!(map.next(), StreamElement::Item(1));
+        assert_eq!(map.next(), StreamElement::Item(3));
+        assert_eq!(map.next(), StreamElement::Terminate);
+        savepoint.finish().unwrap();
+
+        let mut topology = FakeNetworkTopology::<u32>::new(0, 0);
+        let mut metadata = topology.metadata();
+        metadata.savepoint = Arc::new(Savepoint::new(Some(path), None));
+        let mut map = StatefulMap::new(
+            FakeOperator::new(10..11u32),
+            "sum".into(),
+            0u32,
+            |sum: &mut u32, x| {
+                *sum += x;
+                *sum
+            },
+        );
+        map.setup(&mut metadata);
+        assert_eq!(map.next(), StreamElement::Item(13));
+    }
+}
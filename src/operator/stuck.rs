@@ -0,0 +1,229 @@
+//! The detection of the operators stuck in the code of the user, see
+//! [`Stream::stuck_timeout`](crate::Stream::stuck_timeout).
+//!
+//! Each watched operator publishes when its current call of the code of the user started, and a
+//! watchdog thread periodically logs a warning for the calls running for longer than the timeout.
+//! The watchdog runs only while there are operators to watch.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
+
+use crate::network::Coord;
+use crate::scheduler::ExecutionMetadata;
+
+/// The origin of the start times of the calls.
+static EPOCH: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// The operators watched, and whether the watchdog thread is running.
+static WATCHED: Lazy<Mutex<(Vec<Weak<Slot>>, bool)>> = Lazy::new(Default::default);
+
+/// The bounds of how often the watchdog checks the operators.
+const MIN_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The current call of a watched operator.
+#[derive(Debug)]
+struct Slot {
+    coord: Coord,
+    operator: &'static str,
+    /// The name of the thread running the operator, known from its first call.
+    thread: OnceCell<String>,
+    timeout: Duration,
+    /// When the current call started, in nanoseconds since the epoch plus one, or 0 if the
+    /// operator is not running the code of the user.
+    started: AtomicU64,
+    /// The value of `started` of the last call reported as stuck.
+    reported: AtomicU64,
+}
+
+impl Slot {
+    /// How long the current call has been running, if longer than the timeout.
+    fn overdue(&self, now: u64) -> Option<(u64, Duration)> {
+        let started = self.started.load(Ordering::Relaxed);
+        if started == 0 {
+            return None;
+        }
+        let elapsed = Duration::from_nanos(now.saturating_sub(started - 1));
+        (elapsed > self.timeout).then_some((started, elapsed))
+    }
+
+    fn message(&self, elapsed: Duration) -> String {
+        format!(
+            "{} in {} has been running the code of the user for {elapsed:?}, longer than the \
+            timeout of {:?}: it may be stuck. To see where, take a backtrace of the thread {} \
+            (e.g. with `gdb -p {}` and `thread apply all bt`)",
+            self.operator,
+            self.coord,
+            self.timeout,
+            self.thread.get().map_or("<unknown>", String::as_str),
+            std::process::id()
+        )
+    }
+}
+
+/// Times the calls of the code of the user of an operator, when its block has a
+/// [`Stream::stuck_timeout`](crate::Stream::stuck_timeout).
+///
+/// A clone is not watched until it is set up.
+#[derive(Debug, Default)]
+pub(crate) struct StuckWatch {
+    slot: Option<Arc<Slot>>,
+}
+
+impl Clone for StuckWatch {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl StuckWatch {
+    /// Start watching the operator named `operator`, if its block has a timeout.
+    pub(crate) fn setup(&mut self, metadata: &ExecutionMetadata, operator: &'static str) {
+        let Some(timeout) = metadata.stuck_timeout else {
+            return;
+        };
+        let slot = Arc::new(Slot {
+            coord: metadata.coord,
+            operator,
+            thread: OnceCell::new(),
+            timeout,
+            started: AtomicU64::new(0),
+            reported: AtomicU64::new(0),
+        });
+        let mut watched = WATCHED.lock();
+        watched.0.push(Arc::downgrade(&slot));
+        if !watched.1 {
+            watched.1 = true;
+            std::thread::Builder::new()
+                .name("stuck-watchdog".into())
+                .spawn(watchdog)
+                .unwrap();
+        }
+        self.slot = Some(slot);
+    }
+
+    /// Run `f`, the code of the user, timing it.
+    #[inline]
+    pub(crate) fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        let Some(slot) = &self.slot else {
+            return f();
+        };
+        // the operator is set up by the scheduler, but runs in the thread of its block
+        slot.thread.get_or_init(|| {
+            std::thread::current()
+                .name()
+                .unwrap_or("<unnamed>")
+                .to_string()
+        });
+        let started = now() + 1;
+        slot.started.store(started, Ordering::Relaxed);
+        let res = f();
+        slot.started.store(0, Ordering::Relaxed);
+        if slot.reported.load(Ordering::Relaxed) == started {
+            let elapsed = Duration::from_nanos(now() - (started - 1));
+            warn(format!(
+                "{} in {} finished after {elapsed:?}",
+                slot.operator, slot.coord
+            ));
+        }
+        res
+    }
+}
+
+fn now() -> u64 {
+    EPOCH.elapsed().as_nanos() as u64
+}
+
+/// Log a warning, which the tests also keep to check it.
+fn warn(message: String) {
+    log::warn!("{message}");
+    #[cfg(test)]
+    tests::WARNINGS.lock().push(message);
+}
+
+/// Log a warning for each call running for longer than its timeout, until there are no operators
+/// left to watch.
+fn watchdog() {
+    loop {
+        let slots = {
+            let mut watched = WATCHED.lock();
+            watched.0.retain(|slot| slot.strong_count() > 0);
+            if watched.0.is_empty() {
+                watched.1 = false;
+                return;
+            }
+            watched
+                .0
+                .iter()
+                .filter_map(Weak::upgrade)
+                .collect::<Vec<_>>()
+        };
+
+        let now = now();
+        for slot in &slots {
+            if let Some((started, elapsed)) = slot.overdue(now) {
+                // each call is reported once
+                if slot.reported.swap(started, Ordering::Relaxed) != started {
+                    warn(slot.message(elapsed));
+                }
+            }
+        }
+
+        let interval = slots.iter().map(|slot| slot.timeout / 4).min().unwrap();
+        drop(slots);
+        std::thread::sleep(interval.clamp(MIN_CHECK_INTERVAL, MAX_CHECK_INTERVAL));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use once_cell::sync::Lazy;
+    use parking_lot::Mutex;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+
+    /// The warnings logged so far.
+    pub(super) static WARNINGS: Lazy<Mutex<Vec<String>>> = Lazy::new(Default::default);
+
+    #[test]
+    fn stuck_map_reported() {
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        let res = env
+            .stream_iter(0..3u32)
+            .stuck_timeout(Duration::from_millis(50))
+            .map(|n| {
+                if n == 1 {
+                    std::thread::sleep(Duration::from_millis(500));
+                }
+                n
+            })
+            .collect_vec();
+        let execution = std::thread::spawn(move || env.execute_blocking());
+
+        let start = Instant::now();
+        while WARNINGS.lock().is_empty() && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        execution.join().unwrap();
+
+        let warnings = WARNINGS.lock().clone();
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+        assert!(
+            warnings[0].starts_with("Map in (b00.h00.r00)"),
+            "{warnings:?}"
+        );
+        assert!(warnings[0].contains("thread block-"), "{warnings:?}");
+        assert!(
+            warnings[1].starts_with("Map in (b00.h00.r00) finished"),
+            "{warnings:?}"
+        );
+        assert_eq!(res.get().unwrap(), vec![0, 1, 2]);
+    }
+}
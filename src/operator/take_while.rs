@@ -0,0 +1,605 @@
+use std::fmt::Display;
+
+use crate::block::{BlockStructure, OperatorKind, OperatorStructure};
+use crate::operator::{fmt_stage, Data, Operator, StreamElement};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct TakeWhile<Op, Predicate>
+where
+    Predicate: Fn(&Op::Out) -> bool + Clone + Send,
+    Op: Operator,
+{
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    predicate: Predicate,
+    /// Whether the predicate has not failed yet.
+    taking: bool,
+    /// Whether the block starts with a source, which can just stop being polled once the
+    /// predicate fails. Otherwise the previous blocks are drained until they end.
+    stop_source: bool,
+}
+
+impl<Op, Predicate> TakeWhile<Op, Predicate>
+where
+    Predicate: Fn(&Op::Out) -> bool + Clone + Send,
+    Op: Operator,
+{
+    fn new(prev: Op, predicate: Predicate) -> Self {
+        Self {
+            prev,
+            predicate,
+            taking: true,
+            stop_source: false,
+        }
+    }
+}
+
+impl<Op, Predicate> Display for TakeWhile<Op, Predicate>
+where
+    Predicate: Fn(&Op::Out) -> bool + Clone + Send,
+    Op: Operator,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, Op::Out>(f, &self.prev, "TakeWhile")
+    }
+}
+
+impl<Op, Predicate> Operator for TakeWhile<Op, Predicate>
+where
+    Predicate: Fn(&Op::Out) -> bool + Clone + Send,
+    Op: Operator,
+{
+    type Out = Op::Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        self.stop_source = self
+            .prev
+            .structure()
+            .operators
+            .first()
+            .is_some_and(|op| matches!(op.kind, OperatorKind::Source));
+    }
+
+    fn next(&mut self) -> StreamElement<Op::Out> {
+        loop {
+            if !self.taking && self.stop_source {
+                return StreamElement::Terminate;
+            }
+            let el = self.prev.next();
+            match &el {
+                StreamElement::Item(item) | StreamElement::Timestamped(item, _) => {
+                    if !self.taking {
+                        continue;
+                    }
+                    if (self.predicate)(item) {
+                        return el;
+                    }
+                    self.taking = false;
+                    if self.stop_source {
+                        return StreamElement::FlushAndRestart;
+                    }
+                }
+                StreamElement::FlushAndRestart => {
+                    self.taking = true;
+                    return el;
+                }
+                _ => return el,
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<Op::Out, _>("TakeWhile"))
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct MapWhile<O, F, Op>
+where
+    F: Fn(Op::Out) -> Option<O> + Clone + Send,
+    Op: Operator,
+{
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    f: F,
+    /// Whether the function has not returned `None` yet.
+    mapping: bool,
+    /// Whether the block starts with a source, which can just stop being polled once the
+    /// function returns `None`. Otherwise the previous blocks are drained until they end.
+    stop_source: bool,
+}
+
+impl<O, F, Op> Clone for MapWhile<O, F, Op>
+where
+    F: Fn(Op::Out) -> Option<O> + Clone + Send,
+    Op: Operator,
+{
+    fn clone(&self) -> Self {
+        Self {
+            prev: self.prev.clone(),
+            f: self.f.clone(),
+            mapping: self.mapping,
+            stop_source: self.stop_source,
+        }
+    }
+}
+
+impl<O, F, Op> MapWhile<O, F, Op>
+where
+    F: Fn(Op::Out) -> Option<O> + Clone + Send,
+    Op: Operator,
+{
+    fn new(prev: Op, f: F) -> Self {
+        Self {
+            prev,
+            f,
+            mapping: true,
+            stop_source: false,
+        }
+    }
+}
+
+impl<O, F, Op> Display for MapWhile<O, F, Op>
+where
+    F: Fn(Op::Out) -> Option<O> + Clone + Send,
+    Op: Operator,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, O>(f, &self.prev, "MapWhile")
+    }
+}
+
+impl<O: Send, F, Op> Operator for MapWhile<O, F, Op>
+where
+    F: Fn(Op::Out) -> Option<O> + Clone + Send,
+    Op: Operator,
+{
+    type Out = O;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        self.stop_source = self
+            .prev
+            .structure()
+            .operators
+            .first()
+            .is_some_and(|op| matches!(op.kind, OperatorKind::Source));
+    }
+
+    fn next(&mut self) -> StreamElement<O> {
+        loop {
+            if !self.mapping && self.stop_source {
+                return StreamElement::Terminate;
+            }
+            let mapped = match self.prev.next() {
+                StreamElement::Item(item) if self.mapping => {
+                    (self.f)(item).map(StreamElement::Item)
+                }
+                StreamElement::Timestamped(item, ts) if self.mapping => {
+                    (self.f)(item).map(|item| StreamElement::Timestamped(item, ts))
+                }
+                StreamElement::Item(_) | StreamElement::Timestamped(_, _) => continue,
+                StreamElement::Watermark(w) => return StreamElement::Watermark(w),
+                StreamElement::Terminate => return StreamElement::Terminate,
+                StreamElement::FlushAndRestart => {
+                    self.mapping = true;
+                    return StreamElement::FlushAndRestart;
+                }
+                StreamElement::FlushBatch => return StreamElement::FlushBatch,
+            };
+            if let Some(el) = mapped {
+                return el;
+            }
+            self.mapping = false;
+            if self.stop_source {
+                return StreamElement::FlushAndRestart;
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<O, _>("MapWhile"))
+    }
+}
+
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct SkipWhile<Op, Predicate>
+where
+    Predicate: Fn(&Op::Out) -> bool + Clone + Send,
+    Op: Operator,
+{
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    predicate: Predicate,
+    /// Whether the predicate has not failed yet.
+    skipping: bool,
+}
+
+impl<Op, Predicate> Display for SkipWhile<Op, Predicate>
+where
+    Predicate: Fn(&Op::Out) -> bool + Clone + Send,
+    Op: Operator,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, Op::Out>(f, &self.prev, "SkipWhile")
+    }
+}
+
+impl<Op, Predicate> Operator for SkipWhile<Op, Predicate>
+where
+    Predicate: Fn(&Op::Out) -> bool + Clone + Send,
+    Op: Operator,
+{
+    type Out = Op::Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+    }
+
+    #[inline]
+    fn next(&mut self) -> StreamElement<Op::Out> {
+        loop {
+            match self.prev.next() {
+                StreamElement::Item(ref item) | StreamElement::Timestamped(ref item, _)
+                    if self.skipping && (self.predicate)(item) => {}
+                el @ (StreamElement::Item(_) | StreamElement::Timestamped(_, _)) => {
+                    self.skipping = false;
+                    return el;
+                }
+                StreamElement::FlushAndRestart => {
+                    self.skipping = true;
+                    return StreamElement::FlushAndRestart;
+                }
+                el => return el,
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<Op::Out, _>("SkipWhile"))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Limit<Op: Operator> {
+    prev: Op,
+    limit: usize,
+    /// The number of elements emitted since the start of the stream.
+    emitted: usize,
+    /// Whether the block starts with a source, which can just stop being polled once the limit
+    /// is reached. Otherwise the previous blocks are drained until they end.
+    stop_source: bool,
+    /// Whether the stream has been ended after stopping the source.
+    ended: bool,
+}
+
+impl<Op: Operator> Limit<Op> {
+    fn new(prev: Op, limit: usize) -> Self {
+        Self {
+            prev,
+            limit,
+            emitted: 0,
+            stop_source: false,
+            ended: false,
+        }
+    }
+}
+
+impl<Op: Operator> Display for Limit<Op> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, Op::Out>(f, &self.prev, "Limit")
+    }
+}
+
+impl<Op: Operator> Operator for Limit<Op> {
+    type Out = Op::Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        self.stop_source = self
+            .prev
+            .structure()
+            .operators
+            .first()
+            .is_some_and(|op| matches!(op.kind, OperatorKind::Source));
+    }
+
+    fn next(&mut self) -> StreamElement<Op::Out> {
+        loop {
+            if self.emitted == self.limit && self.stop_source {
+                if self.ended {
+                    return StreamElement::Terminate;
+                }
+                self.ended = true;
+                return StreamElement::FlushAndRestart;
+            }
+            let el = self.prev.next();
+            match &el {
+                StreamElement::Item(_) | StreamElement::Timestamped(_, _) => {
+                    if self.emitted < self.limit {
+                        self.emitted += 1;
+                        return el;
+                    }
+                }
+                StreamElement::FlushAndRestart => {
+                    self.emitted = 0;
+                    return el;
+                }
+                _ => return el,
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<Op::Out, _>("Limit");
+        operator.subtitle = format!("{}", self.limit);
+        self.prev.structure().add_operator(operator)
+    }
+}
+
+impl<I, Op> Stream<Op>
+where
+    I: Data,
+    Op: Operator<Out = I> + 'static,
+{
+    /// Remove from the stream all the elements for which the provided predicate returns `false`.
+    /// The predicate also receives the index of the element.
+    ///
+    /// The index counts the elements received by each replica, starting from zero.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(10..20);
+    /// let res = s.filter_with_index(|i, _| i % 3 == 0).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![10, 13, 16, 19]);
+    /// ```
+    pub fn filter_with_index<F>(self, predicate: F) -> Stream<impl Operator<Out = I>>
+    where
+        F: Fn(usize, &I) -> bool + Clone + Send + 'static,
+    {
+        let mut index = 0;
+        self.rich_filter_map(move |item| {
+            let keep = predicate(index, &item);
+            index += 1;
+            keep.then_some(item)
+        })
+    }
+
+    /// Keep the elements of the stream until the provided predicate returns `false`, then end
+    /// the stream.
+    ///
+    /// Each replica ends its stream at the first of its elements that fails the predicate. When
+    /// this operator is in the same block of the source the source is not polled anymore, so
+    /// this can bound an infinite source. Otherwise the elements coming from the previous
+    /// blocks are discarded until they end.
+    ///
+    /// **Note**: this is very similar to [`Iterator::take_while`](std::iter::Iterator::take_while)
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..);
+    /// let res = s.take_while(|&n| n < 5).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![0, 1, 2, 3, 4]);
+    /// ```
+    pub fn take_while<F>(self, predicate: F) -> Stream<impl Operator<Out = I>>
+    where
+        F: Fn(&I) -> bool + Clone + Send + 'static,
+    {
+        self.add_operator(|prev| TakeWhile::new(prev, predicate))
+    }
+
+    /// Map the elements of the stream with the provided function until it returns `None`, then
+    /// end the stream.
+    ///
+    /// Unlike [`filter_map`](Stream::filter_map), which skips the elements mapped to `None` and
+    /// continues, this stops at the first of them: it is useful for parsing a stream until the
+    /// first invalid record, or reading until a sentinel. Each replica ends its stream at the
+    /// first of its elements mapped to `None`. When this operator is in the same block of the
+    /// source the source is not polled anymore, so this can bound an infinite source. Otherwise
+    /// the elements coming from the previous blocks are discarded until they end.
+    ///
+    /// **Note**: this is very similar to [`Iterator::map_while`](std::iter::Iterator::map_while)
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(["1", "2", "x", "3"].into_iter());
+    /// let res = s.map_while(|s| s.parse::<u32>().ok()).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![1, 2]);
+    /// ```
+    pub fn map_while<O, F>(self, f: F) -> Stream<impl Operator<Out = O>>
+    where
+        O: Send,
+        F: Fn(I) -> Option<O> + Clone + Send + 'static,
+    {
+        self.add_operator(|prev| MapWhile::new(prev, f))
+    }
+
+    /// Keep the first `limit` elements of the stream, then end the stream.
+    ///
+    /// This is useful for trying a pipeline on a sample of its input. Each replica keeps its
+    /// first `limit` elements. When this operator is in the same block of the source the source
+    /// is not polled after the last element kept, so this can bound an infinite source.
+    /// Otherwise the elements coming from the previous blocks are discarded until they end.
+    ///
+    /// **Note**: this is very similar to [`Iterator::take`](std::iter::Iterator::take)
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..);
+    /// let res = s.limit(5).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![0, 1, 2, 3, 4]);
+    /// ```
+    pub fn limit(self, limit: usize) -> Stream<impl Operator<Out = I>> {
+        self.add_operator(|prev| Limit::new(prev, limit))
+    }
+
+    /// Discard the elements of the stream while the provided predicate returns `true`, then keep
+    /// all the following elements.
+    ///
+    /// Each replica starts keeping its elements at the first one that fails the predicate.
+    ///
+    /// **Note**: this is very similar to [`Iterator::skip_while`](std::iter::Iterator::skip_while)
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter([0, 1, 5, 2, 6].into_iter());
+    /// let res = s.skip_while(|&n| n < 5).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![5, 2, 6]);
+    /// ```
+    pub fn skip_while<F>(self, predicate: F) -> Stream<impl Operator<Out = I>>
+    where
+        F: Fn(&I) -> bool + Clone + Send + 'static,
+    {
+        self.add_operator(|prev| SkipWhile {
+            prev,
+            predicate,
+            skipping: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crate::operator::take_while::{Limit, MapWhile, TakeWhile};
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+
+    #[test]
+    fn take_while_stops_infinite_source() {
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        let res = env.stream_iter(0..).take_while(|&n| n < 5).collect_vec();
+        env.execute_blocking();
+        assert_eq!(res.get().unwrap(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn take_while_drains_previous_blocks() {
+        let mut prev = FakeOperator::new(0..5u8);
+        prev.push(StreamElement::FlushAndRestart);
+        let mut take_while = TakeWhile::new(prev, |&n| n != 2);
+        take_while.setup(&mut FakeNetworkTopology::<u8>::new(0, 0).metadata());
+
+        assert_eq!(take_while.next(), StreamElement::Item(0));
+        assert_eq!(take_while.next(), StreamElement::Item(1));
+        assert_eq!(take_while.next(), StreamElement::FlushAndRestart);
+        assert_eq!(take_while.next(), StreamElement::Terminate);
+    }
+
+    #[test]
+    fn map_while_stops_source() {
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        let pulled = Arc::new(AtomicUsize::new(0));
+        let counter = pulled.clone();
+        let res = env
+            .stream_iter([1, 2, -1, 3].into_iter().inspect(move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }))
+            .map_while(|n: i32| u32::try_from(n).ok())
+            .collect_vec();
+        env.execute_blocking();
+        assert_eq!(res.get().unwrap(), vec![1, 2]);
+        // the source is not polled after the first `None`
+        assert_eq!(pulled.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn map_while_drains_previous_blocks() {
+        let mut prev = FakeOperator::new([1, 2, -1, 3].into_iter());
+        prev.push(StreamElement::FlushAndRestart);
+        let mut map_while = MapWhile::new(prev, |n: i32| u32::try_from(n).ok());
+        map_while.setup(&mut FakeNetworkTopology::<i32>::new(0, 0).metadata());
+
+        assert_eq!(map_while.next(), StreamElement::Item(1));
+        assert_eq!(map_while.next(), StreamElement::Item(2));
+        assert_eq!(map_while.next(), StreamElement::FlushAndRestart);
+        assert_eq!(map_while.next(), StreamElement::Terminate);
+    }
+
+    #[test]
+    fn limit_stops_infinite_source() {
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        let pulled = Arc::new(AtomicUsize::new(0));
+        let counter = pulled.clone();
+        let res = env
+            .stream_iter((0..).inspect(move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }))
+            .limit(100)
+            .collect_vec();
+        env.execute_blocking();
+        assert_eq!(res.get().unwrap(), (0..100).collect::<Vec<_>>());
+        assert_eq!(pulled.load(Ordering::Relaxed), 100);
+    }
+
+    #[test]
+    fn limit_drains_previous_blocks() {
+        let mut prev = FakeOperator::new(0..5u8);
+        prev.push(StreamElement::FlushAndRestart);
+        let mut limit = Limit::new(prev, 2);
+        limit.setup(&mut FakeNetworkTopology::<u8>::new(0, 0).metadata());
+
+        assert_eq!(limit.next(), StreamElement::Item(0));
+        assert_eq!(limit.next(), StreamElement::Item(1));
+        assert_eq!(limit.next(), StreamElement::FlushAndRestart);
+        assert_eq!(limit.next(), StreamElement::Terminate);
+    }
+
+    #[test]
+    fn skip_while_and_filter_with_index() {
+        let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+        let res = env
+            .stream_iter([1, 2, 7, 3, 8, 4])
+            .skip_while(|&n| n < 5)
+            .filter_with_index(|i, _| i % 2 == 0)
+            .collect_vec();
+        env.execute_blocking();
+        assert_eq!(res.get().unwrap(), vec![7, 8]);
+    }
+}
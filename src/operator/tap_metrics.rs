@@ -0,0 +1,227 @@
+use std::fmt::Display;
+
+use crate::block::{BlockStructure, OperatorStructure};
+use crate::network::Coord;
+use crate::operator::{fmt_stage, Operator, StreamElement};
+use crate::profiler::{get_profiler, Profiler};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// The handle given to the function of [`Stream::tap_metrics`] to record the metrics.
+///
+/// The metrics are aggregated by the profiler for each replica, together with the built-in
+/// ones. Recording a metric does nothing if the `profiler` feature is disabled.
+#[derive(Debug, Clone, Copy)]
+pub struct MetricsHandle {
+    coord: Coord,
+}
+
+impl MetricsHandle {
+    /// The counter with the given name.
+    pub fn counter<'a>(&self, name: &'a str) -> MetricCounter<'a> {
+        MetricCounter {
+            coord: self.coord,
+            name,
+        }
+    }
+
+    /// The histogram with the given name.
+    pub fn histogram<'a>(&self, name: &'a str) -> MetricHistogram<'a> {
+        MetricHistogram {
+            coord: self.coord,
+            name,
+        }
+    }
+}
+
+/// A counter recorded with a [`MetricsHandle`], summing its increments.
+#[derive(Debug, Clone, Copy)]
+pub struct MetricCounter<'a> {
+    coord: Coord,
+    name: &'a str,
+}
+
+impl MetricCounter<'_> {
+    /// Increase the counter by one.
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Increase the counter by `amount`.
+    pub fn add(&self, amount: u64) {
+        get_profiler().counter(self.coord, self.name, amount);
+    }
+}
+
+/// A histogram recorded with a [`MetricsHandle`], counting its values in exponentially growing
+/// buckets.
+#[derive(Debug, Clone, Copy)]
+pub struct MetricHistogram<'a> {
+    coord: Coord,
+    name: &'a str,
+}
+
+impl MetricHistogram<'_> {
+    /// Add a value to the histogram.
+    pub fn record(&self, value: u64) {
+        get_profiler().histogram(self.coord, self.name, value);
+    }
+}
+
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+struct TapMetrics<F, Op> {
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    f: F,
+    handle: Option<MetricsHandle>,
+}
+
+impl<F, Op: Operator> Display for TapMetrics<F, Op> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, Op::Out>(f, &self.prev, "TapMetrics")
+    }
+}
+
+impl<F, Op> TapMetrics<F, Op> {
+    fn new(prev: Op, f: F) -> Self {
+        Self {
+            prev,
+            f,
+            handle: None,
+        }
+    }
+}
+
+impl<F, Op> Operator for TapMetrics<F, Op>
+where
+    F: Fn(&Op::Out, &MetricsHandle) + Send + Clone,
+    Op: Operator,
+{
+    type Out = Op::Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        self.handle = Some(MetricsHandle {
+            coord: metadata.coord,
+        });
+    }
+
+    #[inline]
+    fn next(&mut self) -> StreamElement<Op::Out> {
+        let el = self.prev.next();
+        if let StreamElement::Item(item) | StreamElement::Timestamped(item, _) = &el {
+            (self.f)(item, self.handle.as_ref().unwrap());
+        }
+        el
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<Op::Out, _>("TapMetrics"))
+    }
+}
+
+impl<Op> Stream<Op>
+where
+    Op: Operator + 'static,
+{
+    /// Record custom metrics for each element of the stream, leaving the stream unchanged.
+    ///
+    /// The function receives each element and a [`MetricsHandle`], to increase the counters and
+    /// to record the values of the histograms with the given names. The metrics are aggregated by
+    /// the profiler for each replica, and reported together with the built-in ones. A name must
+    /// be used either for a counter or for a histogram.
+    ///
+    /// The metrics are recorded only if the `profiler` feature is enabled.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..100u64);
+    /// let res = s
+    ///     .tap_metrics(|n, metrics| {
+    ///         if n % 2 == 0 {
+    ///             metrics.counter("even").inc();
+    ///         }
+    ///         metrics.histogram("value").record(*n);
+    ///     })
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap().len(), 100);
+    /// ```
+    pub fn tap_metrics<F>(self, f: F) -> Stream<impl Operator<Out = Op::Out>>
+    where
+        F: Fn(&Op::Out, &MetricsHandle) + Send + Clone + 'static,
+    {
+        self.add_operator(|prev| TapMetrics::new(prev, f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::operator::tap_metrics::{MetricsHandle, TapMetrics};
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+
+    #[test]
+    fn tap_metrics_forwards() {
+        let mut prev = FakeOperator::new(0..3u64);
+        prev.push(StreamElement::FlushBatch);
+        let mut op = TapMetrics::new(prev, |n: &u64, metrics: &MetricsHandle| {
+            metrics.counter("n").add(*n)
+        });
+        op.setup(&mut FakeNetworkTopology::<u64>::new(0, 0).metadata());
+
+        for i in 0..3 {
+            assert_eq!(op.next(), StreamElement::Item(i));
+        }
+        assert_eq!(op.next(), StreamElement::FlushBatch);
+        assert_eq!(op.next(), StreamElement::Terminate);
+    }
+
+    #[cfg(feature = "profiler")]
+    #[test]
+    fn tap_metrics_in_profiler_report() {
+        use crate::profiler::bucket_profiler::CustomMetric;
+        use crate::profiler::{wait_profiler, Histogram};
+
+        let thread = std::thread::Builder::new()
+            .name("tap-metrics-test".into())
+            .spawn(|| {
+                let prev = FakeOperator::new(0..100u64);
+                let mut op = TapMetrics::new(prev, |n: &u64, metrics: &MetricsHandle| {
+                    if n.is_multiple_of(3) {
+                        metrics.counter("multiple_of_3").inc();
+                    }
+                    metrics.histogram("value").record(*n);
+                });
+                op.setup(&mut FakeNetworkTopology::<u64>::new(0, 0).metadata());
+                while op.next() != StreamElement::Terminate {}
+            })
+            .unwrap();
+        thread.join().unwrap();
+
+        let (mut count, mut histogram) = (0, Histogram::default());
+        for metrics in wait_profiler()
+            .into_iter()
+            .filter(|r| r.thread_name == "tap-metrics-test")
+            .flat_map(|r| r.buckets)
+            .flat_map(|b| b.custom_metrics)
+        {
+            match (metrics.name.as_str(), metrics.metric) {
+                ("multiple_of_3", CustomMetric::Counter(c)) => count += c,
+                ("value", CustomMetric::Histogram(h)) => histogram.merge(&h),
+                other => panic!("unexpected metric {other:?}"),
+            }
+        }
+        assert_eq!(count, 34);
+        assert_eq!(histogram.len(), 100);
+        assert_eq!(histogram.quantile_value(0.0), Some(0));
+    }
+}
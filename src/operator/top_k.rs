@@ -0,0 +1,121 @@
+use crate::block::Replication;
+use crate::operator::fold::Fold;
+use crate::operator::sink::collect_vec::CollectVecSink;
+use crate::operator::sink::{StreamOutput, StreamOutputRef};
+use crate::operator::{ExchangeData, Operator};
+use crate::Stream;
+
+/// Keep only the `k` elements with the greatest keys of `buffer`, sorted by decreasing key.
+fn retain_top_k<I, K: Ord>(buffer: &mut Vec<I>, k: usize, by: impl Fn(&I) -> K) {
+    buffer.sort_by_cached_key(|item| std::cmp::Reverse(by(item)));
+    buffer.truncate(k);
+}
+
+impl<I, Op> Stream<Op>
+where
+    I: ExchangeData,
+    Op: Operator<Out = I> + 'static,
+{
+    /// Close the stream and store the `k` elements with the greatest keys, extracted with `by`,
+    /// sorted by decreasing key.
+    ///
+    /// Each replica keeps only its top `k` elements, buffering at most `2 * k` of them, and a
+    /// single replica merges them into the global top `k`. Unlike collecting and sorting the
+    /// whole stream, only `k` elements per replica are sent over the network. The order of the
+    /// elements with the same key is unspecified.
+    ///
+    /// The result is available only when the stream ends, so this can only be used on bounded
+    /// streams: it panics if the stream comes from an unbounded source.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_par_iter(0..100u32);
+    /// let res = s.top_k(3, |n| n % 10);
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let res = res.get().unwrap();
+    /// assert_eq!(res.len(), 3);
+    /// assert!(res.iter().all(|n| n % 10 == 9));
+    /// ```
+    pub fn top_k<K, F>(self, k: usize, by: F) -> StreamOutput<Vec<I>>
+    where
+        K: Ord,
+        F: Fn(&I) -> K + Clone + Send + 'static,
+    {
+        assert!(
+            !self.block.unbounded,
+            "top_k requires a bounded stream, but the stream comes from an unbounded source"
+        );
+        let (local_by, trim_by, global_by) = (by.clone(), by.clone(), by.clone());
+        let output = StreamOutputRef::default();
+        self.add_operator(|prev| {
+            Fold::new(prev, Vec::new(), move |top: &mut Vec<I>, item| {
+                top.push(item);
+                // compact only once in a while, so each element is sorted a constant number of
+                // times on average
+                if top.len() >= 2 * k.max(1) {
+                    retain_top_k(top, k, &local_by);
+                }
+            })
+        })
+        // send only the top k, the buffer may hold up to 2k - 1 elements
+        .map(move |mut top| {
+            retain_top_k(&mut top, k, &trim_by);
+            top
+        })
+        .replication(Replication::One)
+        .add_operator(|prev| {
+            Fold::new(prev, Vec::new(), move |top: &mut Vec<I>, local| {
+                top.extend(local);
+                if top.len() >= 2 * k.max(1) {
+                    retain_top_k(top, k, &global_by);
+                }
+            })
+        })
+        .flat_map(move |mut top| {
+            retain_top_k(&mut top, k, &by);
+            top
+        })
+        .add_operator(|prev| CollectVecSink::new(prev, output.clone()))
+        .finalize_block();
+        StreamOutput::from(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+
+    #[test]
+    fn top_k_merges_replicas() {
+        const N: u64 = 10_007;
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        // a permutation of the keys, so the top k is unique
+        let key = |n: &u64| n * 7919 % N;
+        let res = env.stream_par_iter(0..N).shuffle().top_k(10, key);
+        env.execute_blocking();
+
+        let expected = (0..N).sorted_by_key(|n| std::cmp::Reverse(key(n))).take(10);
+        assert_eq!(res.get().unwrap(), expected.collect_vec());
+    }
+
+    #[test]
+    fn top_k_fewer_elements() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env.stream_par_iter(0..5u32).top_k(10, |n| *n);
+        let empty = env.stream_par_iter(0..5u32).top_k(0, |n| *n);
+        env.execute_blocking();
+
+        assert_eq!(res.get().unwrap(), vec![4, 3, 2, 1, 0]);
+        assert_eq!(empty.get().unwrap(), Vec::<u32>::new());
+    }
+}
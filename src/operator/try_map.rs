@@ -0,0 +1,220 @@
+use std::fmt::Display;
+use std::marker::PhantomData;
+
+use crate::block::{BlockStructure, OperatorStructure};
+use crate::network::Coord;
+use crate::operator::{fmt_stage, Operator, StreamElement};
+use crate::scheduler::{ErrorRecorder, ExecutionMetadata};
+use crate::Stream;
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct TryMap<O, E, F, Op> {
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    f: F,
+    coord: Option<Coord>,
+    #[derivative(Debug = "ignore")]
+    errors: ErrorRecorder,
+    _out: PhantomData<fn() -> (O, E)>,
+}
+
+impl<O, E, F: Clone, Op: Clone> Clone for TryMap<O, E, F, Op> {
+    fn clone(&self) -> Self {
+        Self {
+            prev: self.prev.clone(),
+            f: self.f.clone(),
+            coord: self.coord,
+            errors: self.errors.clone(),
+            _out: PhantomData,
+        }
+    }
+}
+
+impl<O, E, F, Op: Operator> Display for TryMap<O, E, F, Op> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, O>(f, &self.prev, "TryMap")
+    }
+}
+
+impl<O, E, F, Op> TryMap<O, E, F, Op> {
+    fn new(prev: Op, f: F) -> Self {
+        Self {
+            prev,
+            f,
+            coord: None,
+            errors: Default::default(),
+            _out: PhantomData,
+        }
+    }
+}
+
+impl<O, E, F, Op> TryMap<O, E, F, Op>
+where
+    E: Display,
+    F: Fn(Op::Out) -> Result<O, E>,
+    Op: Operator,
+{
+    /// Apply the function to an item, recording the error if it fails.
+    fn apply(&self, item: Op::Out) -> Option<O> {
+        match (self.f)(item) {
+            Ok(out) => Some(out),
+            Err(e) => {
+                self.errors
+                    .record(self.coord.unwrap(), "TryMap", e.to_string());
+                None
+            }
+        }
+    }
+}
+
+impl<O, E, F, Op> Operator for TryMap<O, E, F, Op>
+where
+    O: Send,
+    E: Display,
+    F: Fn(Op::Out) -> Result<O, E> + Send + Clone,
+    Op: Operator,
+{
+    type Out = O;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        self.coord = Some(metadata.coord);
+        self.errors = metadata.errors.clone();
+    }
+
+    #[inline]
+    fn next(&mut self) -> StreamElement<O> {
+        loop {
+            match self.prev.next() {
+                StreamElement::Item(item) => {
+                    if let Some(out) = self.apply(item) {
+                        return StreamElement::Item(out);
+                    }
+                }
+                StreamElement::Timestamped(item, ts) => {
+                    if let Some(out) = self.apply(item) {
+                        return StreamElement::Timestamped(out, ts);
+                    }
+                }
+                StreamElement::Watermark(ts) => return StreamElement::Watermark(ts),
+                StreamElement::FlushBatch => return StreamElement::FlushBatch,
+                StreamElement::FlushAndRestart => return StreamElement::FlushAndRestart,
+                StreamElement::Terminate => return StreamElement::Terminate,
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<O, _>("TryMap"))
+    }
+}
+
+impl<Op> Stream<Op>
+where
+    Op: Operator + 'static,
+{
+    /// Map the elements of the stream with a fallible function, recording the failures instead
+    /// of stopping the execution.
+    ///
+    /// The elements for which the function returns an error are dropped, while the others flow
+    /// downstream. The errors are recorded together with the replica where they happened, and
+    /// returned at the end of the execution as [`ExecutionError::Operator`] by
+    /// [`StreamContext::try_execute_blocking`] (the execution panics with
+    /// [`StreamContext::execute_blocking`]).
+    ///
+    /// [`ExecutionError::Operator`]: crate::ExecutionError::Operator
+    /// [`StreamContext::try_execute_blocking`]: crate::StreamContext::try_execute_blocking
+    /// [`StreamContext::execute_blocking`]: crate::StreamContext::execute_blocking
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(vec!["1", "x", "3"]);
+    /// let res = s.try_map(|s| s.parse::<i32>()).collect_vec();
+    ///
+    /// let err = env.try_execute_blocking().unwrap_err();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![1, 3]);
+    /// assert!(err.to_string().contains("invalid digit"));
+    /// ```
+    pub fn try_map<O, E, F>(self, f: F) -> Stream<impl Operator<Out = O>>
+    where
+        O: Send + 'static,
+        E: Display + 'static,
+        F: Fn(Op::Out) -> Result<O, E> + Send + Clone + 'static,
+    {
+        self.add_operator(|prev| TryMap::new(prev, f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::network::Coord;
+    use crate::operator::try_map::TryMap;
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+    use crate::ExecutionError;
+
+    fn half(n: u32) -> Result<u32, String> {
+        if n.is_multiple_of(2) {
+            Ok(n / 2)
+        } else {
+            Err(format!("{n} is odd"))
+        }
+    }
+
+    #[test]
+    fn try_map_skips_failures() {
+        let mut prev = FakeOperator::new([2, 3, 4].into_iter());
+        prev.push(StreamElement::FlushBatch);
+        let mut op = TryMap::new(prev, half);
+        let mut topology = FakeNetworkTopology::<u32>::new(0, 0);
+        let mut metadata = topology.metadata();
+        let errors = metadata.errors.clone();
+        op.setup(&mut metadata);
+
+        assert_eq!(op.next(), StreamElement::Item(1));
+        assert_eq!(op.next(), StreamElement::Item(2));
+        assert_eq!(op.next(), StreamElement::FlushBatch);
+        assert_eq!(op.next(), StreamElement::Terminate);
+
+        let Err(ExecutionError::Operator { errors }) = errors.finish(Ok(())) else {
+            panic!("the error was not recorded");
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].coord, Coord::new(0, 0, 0));
+        assert_eq!(errors[0].operator, "TryMap");
+        assert_eq!(errors[0].message, "3 is odd");
+    }
+
+    #[test]
+    fn try_map_errors_surface_in_execution() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env
+            .stream_par_iter(0..100u32)
+            .try_map(half)
+            .shuffle()
+            .collect_vec();
+
+        let err = env.try_execute_blocking().unwrap_err();
+
+        // the elements that did not fail reached the sink
+        let mut res = res.get().unwrap();
+        res.sort_unstable();
+        assert_eq!(res, (0..50).collect::<Vec<_>>());
+        let ExecutionError::Operator { errors } = err else {
+            panic!("unexpected error: {err}");
+        };
+        let mut failed: Vec<_> = errors.into_iter().map(|e| e.message).collect();
+        failed.sort_unstable_by_key(|m| m.split(' ').next().unwrap().parse::<u32>().unwrap());
+        let expected: Vec<_> = (1..100).step_by(2).map(|n| format!("{n} is odd")).collect();
+        assert_eq!(failed, expected);
+    }
+}
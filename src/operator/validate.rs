@@ -0,0 +1,279 @@
+use std::fmt::Display;
+
+use crate::block::{BlockStructure, OperatorStructure};
+use crate::network::Coord;
+use crate::operator::{fmt_stage, Data, ExchangeData, Operator, StreamElement};
+use crate::profiler::{get_profiler, DropReason, Profiler};
+use crate::scheduler::ExecutionMetadata;
+use crate::Stream;
+
+/// What [`Stream::validate`] does with an element that fails the validation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationFailure {
+    /// Drop the element, counting it in the profiler as `invalid`.
+    #[default]
+    Drop,
+    /// Panic, stopping the job.
+    Panic,
+}
+
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct Validate<F, Op>
+where
+    F: Fn(&Op::Out) -> Result<(), String> + Clone + Send,
+    Op: Operator,
+{
+    prev: Op,
+    #[derivative(Debug = "ignore")]
+    validator: F,
+    on_failure: ValidationFailure,
+    coord: Option<Coord>,
+    /// The number of elements dropped since the last report to the profiler.
+    dropped: usize,
+}
+
+impl<F, Op> Validate<F, Op>
+where
+    F: Fn(&Op::Out) -> Result<(), String> + Clone + Send,
+    Op: Operator,
+{
+    fn new(prev: Op, on_failure: ValidationFailure, validator: F) -> Self {
+        Self {
+            prev,
+            validator,
+            on_failure,
+            coord: None,
+            dropped: 0,
+        }
+    }
+
+    /// Report the elements dropped since the last report to the profiler.
+    fn report_dropped(&mut self) {
+        if self.dropped > 0 {
+            let dropped = std::mem::take(&mut self.dropped);
+            get_profiler().dropped(
+                self.coord.unwrap(),
+                "Validate",
+                DropReason::Invalid,
+                dropped,
+            );
+        }
+    }
+}
+
+impl<F, Op> Display for Validate<F, Op>
+where
+    F: Fn(&Op::Out) -> Result<(), String> + Clone + Send,
+    Op: Operator,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, Op::Out>(f, &self.prev, "Validate")
+    }
+}
+
+impl<F, Op> Operator for Validate<F, Op>
+where
+    F: Fn(&Op::Out) -> Result<(), String> + Clone + Send,
+    Op: Operator,
+{
+    type Out = Op::Out;
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+        self.coord = Some(metadata.coord);
+    }
+
+    #[inline]
+    fn next(&mut self) -> StreamElement<Op::Out> {
+        loop {
+            let el = self.prev.next();
+            match &el {
+                StreamElement::Item(item) | StreamElement::Timestamped(item, _) => {
+                    match (self.validator)(item) {
+                        Ok(()) => return el,
+                        Err(e) => match self.on_failure {
+                            ValidationFailure::Drop => {
+                                log::debug!("validate: dropping an invalid element: {e}");
+                                self.dropped += 1;
+                            }
+                            ValidationFailure::Panic => {
+                                panic!("validate: invalid element: {e}")
+                            }
+                        },
+                    }
+                }
+                StreamElement::Watermark(_) => return el,
+                _ => {
+                    self.report_dropped();
+                    return el;
+                }
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        let mut operator = OperatorStructure::new::<Op::Out, _>("Validate");
+        operator.subtitle = format!("{:?}", self.on_failure);
+        self.prev.structure().add_operator(operator)
+    }
+}
+
+impl<I, Op> Stream<Op>
+where
+    I: Data,
+    Op: Operator<Out = I> + 'static,
+{
+    /// Check each element of the stream with `validator`, which returns the reason why an
+    /// element is invalid.
+    ///
+    /// The valid elements pass unchanged, while the invalid ones are dropped or stop the job,
+    /// depending on `on_failure`. The dropped elements are reported to the profiler as
+    /// `invalid`. To keep the invalid elements use [`Stream::validate_dead_letter`].
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::ValidationFailure;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter([1, -2, 3].into_iter());
+    /// let res = s
+    ///     .validate(ValidationFailure::Drop, |&n| {
+    ///         if n >= 0 {
+    ///             Ok(())
+    ///         } else {
+    ///             Err(format!("{n} is negative"))
+    ///         }
+    ///     })
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![1, 3]);
+    /// ```
+    pub fn validate<F>(
+        self,
+        on_failure: ValidationFailure,
+        validator: F,
+    ) -> Stream<impl Operator<Out = I>>
+    where
+        F: Fn(&I) -> Result<(), String> + Clone + Send + 'static,
+    {
+        self.add_operator(|prev| Validate::new(prev, on_failure, validator))
+    }
+
+    /// Like [`Stream::validate`], sending the invalid elements to a dead-letter stream, together
+    /// with the reason why they are invalid.
+    ///
+    /// Returns the stream of the valid elements and the dead-letter stream.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter([1, -2, 3].into_iter());
+    /// let (valid, invalid) = s.validate_dead_letter(|&n| {
+    ///     if n >= 0 {
+    ///         Ok(())
+    ///     } else {
+    ///         Err(format!("{n} is negative"))
+    ///     }
+    /// });
+    /// let valid = valid.collect_vec();
+    /// let invalid = invalid.collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(valid.get().unwrap(), vec![1, 3]);
+    /// assert_eq!(invalid.get().unwrap(), vec![(-2, "-2 is negative".to_string())]);
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn validate_dead_letter<F>(
+        self,
+        validator: F,
+    ) -> (
+        Stream<impl Operator<Out = I>>,
+        Stream<impl Operator<Out = (I, String)>>,
+    )
+    where
+        I: ExchangeData,
+        F: Fn(&I) -> Result<(), String> + Clone + Send + 'static,
+    {
+        let mut routes = self
+            .map(move |item| match validator(&item) {
+                Ok(()) => Ok(item),
+                Err(e) => Err((item, e)),
+            })
+            .route()
+            .add_route(Result::is_ok)
+            .add_route(Result::is_err)
+            .build_inner()
+            .into_iter();
+        let valid = routes.next().unwrap().filter_map(Result::ok);
+        let invalid = routes.next().unwrap().filter_map(Result::err);
+        (valid, invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::validate::Validate;
+    use crate::operator::{Operator, StreamElement, ValidationFailure};
+    use crate::test::{FakeNetworkTopology, FakeOperator};
+
+    fn even(n: &u32) -> Result<(), String> {
+        if n.is_multiple_of(2) {
+            Ok(())
+        } else {
+            Err(format!("{n} is odd"))
+        }
+    }
+
+    #[test]
+    fn validate_drop() {
+        let mut validate = Validate::new(FakeOperator::new(0..5u32), ValidationFailure::Drop, even);
+        validate.setup(&mut FakeNetworkTopology::<u32>::new(0, 0).metadata());
+
+        assert_eq!(validate.next(), StreamElement::Item(0));
+        assert_eq!(validate.next(), StreamElement::Item(2));
+        assert_eq!(validate.next(), StreamElement::Item(4));
+        assert_eq!(validate.dropped, 2);
+        assert_eq!(validate.next(), StreamElement::Terminate);
+        assert_eq!(validate.dropped, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn validate_panic() {
+        let mut validate =
+            Validate::new(FakeOperator::new(0..5u32), ValidationFailure::Panic, even);
+        validate.setup(&mut FakeNetworkTopology::<u32>::new(0, 0).metadata());
+        while validate.next() != StreamElement::Terminate {}
+    }
+
+    #[test]
+    fn validate_dead_letter() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let (valid, invalid) = env.stream_par_iter(0..100u32).validate_dead_letter(even);
+        let valid = valid.collect_vec();
+        let invalid = invalid.collect_vec();
+        env.execute_blocking();
+
+        let mut valid = valid.get().unwrap();
+        valid.sort();
+        assert_eq!(valid, (0..100).step_by(2).collect::<Vec<_>>());
+        let mut invalid = invalid.get().unwrap();
+        invalid.sort();
+        let expected = (1..100)
+            .step_by(2)
+            .map(|n| (n, format!("{n} is odd")))
+            .collect::<Vec<_>>();
+        assert_eq!(invalid, expected);
+    }
+}
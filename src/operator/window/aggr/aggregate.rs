@@ -0,0 +1,258 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Display;
+
+use super::{super::*, Fold};
+use crate::block::{BlockStructure, NextStrategy};
+use crate::operator::end::End;
+use crate::operator::{fmt_stage, DataKey, ExchangeData, ExchangeDataKey, Operator};
+use crate::scheduler::ExecutionMetadata;
+use crate::stream::{KeyedStream, WindowedStream};
+
+/// Combine the partial aggregates of the windows computed by the different replicas.
+///
+/// The partials with a timestamp (the end of their window) are merged with the other partials of
+/// the same key and timestamp, and finished when the watermark passes the timestamp, since no
+/// replica can produce them anymore. The partials without a timestamp cannot be matched with
+/// each other, so they are finished immediately.
+#[derive(Clone)]
+struct WindowMerge<K, A, O, M, F, Op>
+where
+    Op: Operator<Out = (K, A)>,
+{
+    prev: Op,
+    merge: M,
+    finish: F,
+    partials: BTreeMap<Timestamp, HashMap<K, A, GroupHasherBuilder>>,
+    ready: VecDeque<StreamElement<(K, O)>>,
+}
+
+impl<K, A, O, M, F, Op> WindowMerge<K, A, O, M, F, Op>
+where
+    K: DataKey,
+    A: ExchangeData,
+    O: Data,
+    M: Fn(&mut A, A) + Clone + Send + 'static,
+    F: Fn(A) -> O + Clone + Send + 'static,
+    Op: Operator<Out = (K, A)>,
+{
+    fn new(prev: Op, merge: M, finish: F) -> Self {
+        Self {
+            prev,
+            merge,
+            finish,
+            partials: Default::default(),
+            ready: Default::default(),
+        }
+    }
+
+    /// Finish the windows ending before `watermark`, or all of them if it is `None`.
+    fn finish_windows(&mut self, watermark: Option<Timestamp>) {
+        let partials = match watermark {
+            Some(watermark) => {
+                let later = self.partials.split_off(&watermark);
+                std::mem::replace(&mut self.partials, later)
+            }
+            None => std::mem::take(&mut self.partials),
+        };
+        for (ts, windows) in partials {
+            for (key, acc) in windows {
+                let out = (self.finish)(acc);
+                self.ready
+                    .push_back(StreamElement::Timestamped((key, out), ts));
+            }
+        }
+    }
+}
+
+impl<K, A, O, M, F, Op> Operator for WindowMerge<K, A, O, M, F, Op>
+where
+    K: DataKey,
+    A: ExchangeData,
+    O: Data,
+    M: Fn(&mut A, A) + Clone + Send + 'static,
+    F: Fn(A) -> O + Clone + Send + 'static,
+    Op: Operator<Out = (K, A)>,
+{
+    type Out = (K, O);
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        loop {
+            if let Some(el) = self.ready.pop_front() {
+                return el;
+            }
+            match self.prev.next() {
+                StreamElement::Item((key, acc)) => {
+                    return StreamElement::Item((key, (self.finish)(acc)))
+                }
+                StreamElement::Timestamped((key, acc), ts) => {
+                    match self.partials.entry(ts).or_default().entry(key) {
+                        Entry::Occupied(mut entry) => (self.merge)(entry.get_mut(), acc),
+                        Entry::Vacant(entry) => {
+                            entry.insert(acc);
+                        }
+                    }
+                }
+                StreamElement::Watermark(ts) => {
+                    self.finish_windows(Some(ts));
+                    self.ready.push_back(StreamElement::Watermark(ts));
+                }
+                StreamElement::FlushAndRestart => {
+                    self.finish_windows(None);
+                    self.ready.push_back(StreamElement::FlushAndRestart);
+                }
+                StreamElement::FlushBatch => return StreamElement::FlushBatch,
+                StreamElement::Terminate => return StreamElement::Terminate,
+            }
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<(K, O), _>("WindowMerge"))
+    }
+}
+
+impl<K, A, O, M, F, Op> Display for WindowMerge<K, A, O, M, F, Op>
+where
+    Op: Operator<Out = (K, A)>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<A, O>(f, &self.prev, "WindowMerge")
+    }
+}
+
+impl<Key, Out, WindowDescr, OperatorChain> WindowedStream<OperatorChain, Out, WindowDescr>
+where
+    WindowDescr: WindowDescription<Out> + 'static,
+    OperatorChain: Operator<Out = (Key, Out)> + 'static,
+    Key: ExchangeDataKey,
+    Out: Data,
+{
+    /// Aggregate the elements of each window with an accumulator that can be merged.
+    ///
+    /// `create` makes the empty accumulator of a window, `add` accumulates an element of the
+    /// window into it, `merge` combines two partial accumulators of the same window and `finish`
+    /// turns the accumulator into the output.
+    ///
+    /// Each replica aggregates the elements it receives, then the partial accumulators are sent
+    /// to the replica owning their key and merged by window: the partials with the same key and
+    /// the same window end timestamp are combined, and finished when the watermark passes the end
+    /// of their window. This way a stream keyed with [`Stream::key_by`](crate::Stream::key_by),
+    /// which does not move the elements, only exchanges the partial aggregates. The windows
+    /// without a timestamp (e.g. count windows) cannot be matched across replicas, so their
+    /// partials are finished right away.
+    ///
+    /// ## Example
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::window::CountWindow;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..6);
+    /// let res = s
+    ///     .group_by(|&n| n % 2)
+    ///     .window(CountWindow::tumbling(3))
+    ///     .aggregate(
+    ///         || (0, 0),
+    ///         |(sum, count), n| {
+    ///             *sum += n;
+    ///             *count += 1;
+    ///         },
+    ///         |(sum, count), (s, c)| {
+    ///             *sum += s;
+    ///             *count += c;
+    ///         },
+    ///         |(sum, count)| sum as f64 / count as f64,
+    ///     )
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_by_key(|(k, _)| *k);
+    /// assert_eq!(res, vec![(0, 2.0), (1, 3.0)]);
+    /// ```
+    pub fn aggregate<A, NewOut, C, F, M, G>(
+        self,
+        create: C,
+        add: F,
+        merge: M,
+        finish: G,
+    ) -> KeyedStream<impl Operator<Out = (Key, NewOut)>>
+    where
+        A: ExchangeData,
+        NewOut: Data,
+        C: FnOnce() -> A,
+        F: FnMut(&mut A, Out) + Clone + Send + 'static,
+        M: Fn(&mut A, A) + Clone + Send + 'static,
+        G: Fn(A) -> NewOut + Clone + Send + 'static,
+    {
+        let acc = Fold::new(create(), add);
+        let hasher = self.inner.0.block.group_by_hasher.clone();
+        let next_strategy = NextStrategy::GroupBy(
+            move |(key, _): &(Key, A)| hasher.hash(key),
+            Default::default(),
+        );
+        let stream = self
+            .add_window_operator("WindowAggregate", acc)
+            .0
+            .split_block(End::new, next_strategy)
+            .add_operator(|prev| WindowMerge::new(prev, merge, finish));
+        KeyedStream(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::WindowMerge;
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::IteratorSource;
+    use crate::operator::window::EventTimeWindow;
+    use crate::operator::{Operator, StreamElement};
+    use crate::test::FakeOperator;
+
+    fn windowed_sum(parallelism: u64) -> Vec<(u64, u64)> {
+        let env = StreamContext::new(RuntimeConfig::local(parallelism).unwrap());
+        let res = env
+            .stream(IteratorSource::new(0..1000u64))
+            .add_timestamps(|&n| n as i64, |&n, &ts| (n % 100 == 0).then_some(ts))
+            .group_by(|n| n % 7)
+            .window(EventTimeWindow::sliding(100, 50))
+            .aggregate(|| 0, |sum, n| *sum += n, |sum, s| *sum += s, |sum| sum)
+            .collect_vec();
+        env.execute_blocking();
+        res.get().unwrap().into_iter().sorted().collect()
+    }
+
+    #[test]
+    fn aggregate_parallel_matches_single_replica() {
+        let expected = windowed_sum(1);
+        assert!(!expected.is_empty());
+        assert_eq!(windowed_sum(4), expected);
+    }
+
+    #[test]
+    fn window_merge_waits_for_window_ending_at_watermark() {
+        // a replica can still send the partial of a window ending at the watermark
+        let prev = FakeOperator::from_elements(vec![
+            StreamElement::Timestamped((1u8, 2u32), 10),
+            StreamElement::Watermark(10),
+            StreamElement::Timestamped((1, 3), 10),
+            StreamElement::Watermark(11),
+        ]);
+        let mut merge = WindowMerge::new(prev, |a: &mut u32, b| *a += b, |a| a);
+
+        assert_eq!(merge.next(), StreamElement::Watermark(10));
+        assert_eq!(merge.next(), StreamElement::Timestamped((1, 5), 10));
+        assert_eq!(merge.next(), StreamElement::Watermark(11));
+        assert_eq!(merge.next(), StreamElement::Terminate);
+    }
+}
@@ -0,0 +1,270 @@
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+
+use super::super::*;
+use crate::operator::{Data, ExchangeDataKey, Operator};
+use crate::stream::{KeyedStream, WindowedStream};
+
+/// The precision used by [`WindowedStream::approx_count_distinct`].
+pub(crate) const DEFAULT_PRECISION: u8 = 12;
+
+/// Sketch of a set estimating the number of its distinct elements with the HyperLogLog
+/// algorithm.
+///
+/// The hash of each element is split in two: the first `precision` bits select one of the
+/// `2^precision` registers, which keeps the maximum position of the first set bit among the
+/// remaining bits of the hashes it saw. The standard error of the estimate is
+/// `1.04 / sqrt(2^precision)`, using a byte for each register. Two sketches with the same
+/// precision are merged by taking the maximum of each register, so the result is the same as
+/// adding all the elements to a single sketch.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub(crate) fn new(precision: u8) -> Self {
+        assert!(
+            (4..=18).contains(&precision),
+            "the precision of the sketch must be between 4 and 18"
+        );
+        Self {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    pub(crate) fn add<T: Hash>(&mut self, item: &T) {
+        self.insert(item);
+    }
+
+    /// Add an element to the sketch, returning whether the sketch changed: when it did not, the
+    /// estimate is the same as before.
+    pub(crate) fn insert<T: Hash>(&mut self, item: &T) -> bool {
+        // a different seed than `group_by_hash`, so that the hashes are not correlated with the
+        // partitioning of the elements
+        let mut hasher = wyhash::WyHash::with_seed(0x9e3779b97f4a7c15);
+        item.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let index = (hash >> (64 - self.precision)) as usize;
+        // the guard bit bounds the rank when all the remaining bits are zero
+        let rest = (hash << self.precision) | (1 << (self.precision - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        let changed = rank > self.registers[index];
+        if changed {
+            self.registers[index] = rank;
+        }
+        changed
+    }
+
+    pub(crate) fn merge(&mut self, other: &Self) {
+        assert_eq!(
+            self.precision, other.precision,
+            "cannot merge sketches with different precisions"
+        );
+        for (r, o) in self.registers.iter_mut().zip(&other.registers) {
+            *r = (*r).max(*o);
+        }
+    }
+
+    /// Estimate the number of distinct elements added to the sketch.
+    pub(crate) fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            // linear counting is more accurate for the small cardinalities
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+impl<Key, Out, WindowDescr, OperatorChain> WindowedStream<OperatorChain, Out, WindowDescr>
+where
+    WindowDescr: WindowDescription<Out> + 'static,
+    OperatorChain: Operator<Out = (Key, Out)> + 'static,
+    Key: ExchangeDataKey,
+    Out: Data + Hash,
+{
+    /// Estimate the number of distinct elements of each window.
+    ///
+    /// Instead of keeping the set of the elements, each window keeps a HyperLogLog sketch of 4 KiB,
+    /// and the estimates have a standard error of about 1.6%. See
+    /// [`WindowedStream::approx_count_distinct_with_precision`] to trade memory for accuracy.
+    ///
+    /// The sketches of a window are merged across the replicas like the accumulators of
+    /// [`WindowedStream::aggregate`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::window::CountWindow;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter((0..1000u32).map(|n| n % 100));
+    /// let res = s
+    ///     .group_by(|_| ())
+    ///     .window(CountWindow::tumbling(1000))
+    ///     .approx_count_distinct()
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let (_, count) = res.get().unwrap()[0];
+    /// assert!(count.abs_diff(100) <= 5);
+    /// ```
+    pub fn approx_count_distinct(self) -> KeyedStream<impl Operator<Out = (Key, u64)>> {
+        self.approx_count_distinct_with_precision(DEFAULT_PRECISION)
+    }
+
+    /// Estimate the number of distinct elements of each window, like
+    /// [`WindowedStream::approx_count_distinct`], with sketches of `2^precision` bytes.
+    ///
+    /// The standard error of the estimates is `1.04 / sqrt(2^precision)`. The precision must be
+    /// between 4 (25% error, 16 bytes) and 18 (0.2% error, 256 KiB).
+    pub fn approx_count_distinct_with_precision(
+        self,
+        precision: u8,
+    ) -> KeyedStream<impl Operator<Out = (Key, u64)>> {
+        let sketch = HyperLogLog::new(precision);
+        self.aggregate(
+            move || sketch,
+            |sketch, x| sketch.add(&x),
+            |sketch, other| sketch.merge(&other),
+            |sketch| sketch.estimate(),
+        )
+    }
+}
+
+impl<Key, Out, WindowDescr, OperatorChain> WindowedStream<OperatorChain, Out, WindowDescr>
+where
+    WindowDescr: WindowDescription<Out> + 'static,
+    OperatorChain: Operator<Out = (Key, Out)> + 'static,
+    Key: ExchangeDataKey,
+    Out: Data,
+{
+    /// Estimate the number of distinct values of a field of the elements of each window.
+    ///
+    /// This is the approximate version of [`WindowedStream::count_distinct`]: `field` extracts
+    /// the value to count from each element, and each window keeps a HyperLogLog sketch of the
+    /// values like [`WindowedStream::approx_count_distinct`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::window::CountWindow;
+    /// # let mut env = StreamContext::new_local();
+    /// // (page, visitor)
+    /// let s = env.stream_iter((0..2000u32).map(|n| (n % 2, n % 200)));
+    /// let res = s
+    ///     .group_by(|&(page, _)| page)
+    ///     .window(CountWindow::tumbling(1000))
+    ///     .approx_count_distinct_by(|&(_, visitor)| visitor)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// for (_, count) in res.get().unwrap() {
+    ///     assert!(count.abs_diff(100) <= 5);
+    /// }
+    /// ```
+    pub fn approx_count_distinct_by<D, F>(
+        self,
+        field: F,
+    ) -> KeyedStream<impl Operator<Out = (Key, u64)>>
+    where
+        D: Hash,
+        F: Fn(&Out) -> D + Clone + Send + 'static,
+    {
+        let sketch = HyperLogLog::new(DEFAULT_PRECISION);
+        self.aggregate(
+            move || sketch,
+            move |sketch, x| sketch.add(&field(&x)),
+            |sketch, other| sketch.merge(&other),
+            |sketch| sketch.estimate(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HyperLogLog;
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::window::EventTimeWindow;
+
+    /// Whether `estimate` is within 3 standard errors of `exact`.
+    fn assert_within_error(estimate: u64, exact: u64, precision: u8) {
+        let error = 1.04 / ((1u64 << precision) as f64).sqrt();
+        let relative = (estimate as f64 - exact as f64).abs() / exact as f64;
+        assert!(
+            relative <= 3.0 * error,
+            "estimated {estimate}, the exact value is {exact}"
+        );
+    }
+
+    #[test]
+    fn sketch_estimate_within_error() {
+        for precision in [8, 12, 14] {
+            for exact in [10, 1_000, 100_000] {
+                let mut sketch = HyperLogLog::new(precision);
+                // every element is added more than once
+                for i in 0..3 * exact {
+                    sketch.add(&(i % exact));
+                }
+                assert_within_error(sketch.estimate(), exact, precision);
+            }
+        }
+    }
+
+    #[test]
+    fn sketch_merge() {
+        let mut first = HyperLogLog::new(10);
+        let mut second = HyperLogLog::new(10);
+        let mut all = HyperLogLog::new(10);
+        // the two halves overlap
+        for i in 0..60_000u32 {
+            first.add(&i);
+            all.add(&i);
+        }
+        for i in 40_000..100_000u32 {
+            second.add(&i);
+            all.add(&i);
+        }
+        first.merge(&second);
+        assert_eq!(first, all);
+        assert_within_error(first.estimate(), 100_000, 10);
+    }
+
+    #[test]
+    fn windowed_approx_count_distinct() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        // the elements are not moved by `key_by`, so the windows are merged across the replicas
+        let res = env
+            .stream_par_iter(0..40_000u32)
+            .add_timestamps(|&n| (n % 4) as i64, |_, _| None)
+            .key_by(|n| n % 2)
+            .window(EventTimeWindow::tumbling(4))
+            .approx_count_distinct()
+            .collect_vec();
+        env.execute_blocking();
+
+        let res = res.get().unwrap();
+        assert_eq!(res.len(), 2);
+        for (_, count) in res {
+            assert_within_error(count, 20_000, 12);
+        }
+    }
+}
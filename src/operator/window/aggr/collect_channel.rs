@@ -0,0 +1,163 @@
+use std::fmt::Display;
+use std::ops::Range;
+
+use flume::{unbounded, Receiver};
+
+use super::super::*;
+use super::Fold;
+use crate::block::BlockStructure;
+use crate::operator::sink::collect_channel::CollectChannelSink;
+use crate::operator::{fmt_stage, ExchangeData, ExchangeDataKey, Operator};
+use crate::scheduler::ExecutionMetadata;
+use crate::stream::WindowedStream;
+
+/// Turn the results of the event time windows, timestamped with the end of their window, into
+/// the pairs of the bounds of the window and the result.
+#[derive(Clone, Debug)]
+struct WindowBounds<Op> {
+    prev: Op,
+    size: Timestamp,
+}
+
+impl<Op: Operator> Display for WindowBounds<Op> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_stage::<Op::Out, (Range<Timestamp>, Op::Out)>(f, &self.prev, "WindowBounds")
+    }
+}
+
+impl<Op: Operator> Operator for WindowBounds<Op> {
+    type Out = (Range<Timestamp>, Op::Out);
+
+    fn setup(&mut self, metadata: &mut ExecutionMetadata) {
+        self.prev.setup(metadata);
+    }
+
+    fn next(&mut self) -> StreamElement<Self::Out> {
+        match self.prev.next() {
+            StreamElement::Timestamped(item, end) => {
+                StreamElement::Timestamped((end - self.size..end, item), end)
+            }
+            StreamElement::Item(_) => panic!("The results of event time windows are timestamped"),
+            StreamElement::Watermark(ts) => StreamElement::Watermark(ts),
+            StreamElement::FlushBatch => StreamElement::FlushBatch,
+            StreamElement::FlushAndRestart => StreamElement::FlushAndRestart,
+            StreamElement::Terminate => StreamElement::Terminate,
+        }
+    }
+
+    fn structure(&self) -> BlockStructure {
+        self.prev
+            .structure()
+            .add_operator(OperatorStructure::new::<Self::Out, _>("WindowBounds"))
+    }
+}
+
+impl<Key, Out, OperatorChain> WindowedStream<OperatorChain, Out, EventTimeWindow>
+where
+    OperatorChain: Operator<Out = (Key, Out)> + 'static,
+    Key: ExchangeDataKey,
+    Out: ExchangeData,
+{
+    /// Close the stream and send the elements of each window to a channel as soon as the window
+    /// closes, together with its bounds `start..end`.
+    ///
+    /// Unlike collecting the results at the end of the execution, each window is received while
+    /// the job is running, as soon as the watermark passes its end (see
+    /// [`EventTimeWindow::allowed_lateness`]). With early firing each firing sends the elements
+    /// received so far. The channel is closed when the stream ends.
+    ///
+    /// **Note**: the windows of different keys are received in an unspecified order.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::window::EventTimeWindow;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..25i64);
+    /// let rx = s
+    ///     .add_timestamps(|&n| n, |_, &ts| Some(ts))
+    ///     .group_by(|n| n % 2)
+    ///     .window(EventTimeWindow::tumbling(10))
+    ///     .collect_channel();
+    ///
+    /// let job = std::thread::spawn(move || env.execute_blocking());
+    /// // the windows are received while the job is running
+    /// let mut windows: Vec<_> = rx.iter().filter(|(_, (k, _))| *k == 0).collect();
+    /// windows.sort_by_key(|(bounds, _)| bounds.start);
+    /// assert_eq!(
+    ///     windows,
+    ///     vec![
+    ///         (0..10, (0, vec![0, 2, 4, 6, 8])),
+    ///         (10..20, (0, vec![10, 12, 14, 16, 18])),
+    ///         (20..30, (0, vec![20, 22, 24])),
+    ///     ]
+    /// );
+    /// job.join().unwrap();
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn collect_channel(self) -> Receiver<(Range<Timestamp>, (Key, Vec<Out>))> {
+        let size = self.descr.size();
+        let acc = Fold::new(Vec::new(), |v: &mut Vec<Out>, el| v.push(el));
+        let (tx, rx) = unbounded();
+        self.add_window_operator("WindowCollectChannel", acc)
+            .unkey()
+            .add_operator(|prev| WindowBounds { prev, size })
+            .replication(Replication::One)
+            .add_operator(|prev| CollectChannelSink::new(prev, tx))
+            .finalize_block();
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::window::EventTimeWindow;
+    use crate::BatchMode;
+
+    #[test]
+    fn collect_channel_live_windows() {
+        const PACE: Duration = Duration::from_millis(20);
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let rx = env
+            .stream_iter(0..40i64)
+            .map(|n| {
+                std::thread::sleep(PACE);
+                n
+            })
+            .add_timestamps(|&n| n, |_, &ts| Some(ts))
+            // send the elements and the watermarks as soon as they are produced
+            .batch_mode(BatchMode::single())
+            .group_by(|_| ())
+            .batch_mode(BatchMode::single())
+            .window(EventTimeWindow::tumbling(10))
+            .collect_channel();
+
+        let start = Instant::now();
+        let job = std::thread::spawn(move || env.execute_blocking());
+        let mut received = Vec::new();
+        for (bounds, ((), items)) in rx.iter() {
+            received.push((bounds, items, start.elapsed()));
+        }
+        let total = start.elapsed();
+        job.join().unwrap();
+
+        assert_eq!(received.len(), 4);
+        for (i, (bounds, items, at)) in received.into_iter().enumerate() {
+            let i = i as i64;
+            assert_eq!(bounds, i * 10..i * 10 + 10);
+            assert_eq!(items, (i * 10..i * 10 + 10).collect::<Vec<_>>());
+            // the window closes with the watermark of its first element after the end, long
+            // before the end of the source for all but the last window
+            if i < 3 {
+                assert!(at < total - PACE * 5, "window {i} received at {at:?}");
+            }
+        }
+    }
+}
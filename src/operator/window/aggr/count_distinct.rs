@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+
+use super::super::*;
+use crate::operator::{Data, ExchangeDataKey, Operator};
+use crate::stream::{KeyedStream, WindowedStream};
+
+impl<Key, Out, WindowDescr, OperatorChain> WindowedStream<OperatorChain, Out, WindowDescr>
+where
+    WindowDescr: WindowDescription<Out> + 'static,
+    OperatorChain: Operator<Out = (Key, Out)> + 'static,
+    Key: ExchangeDataKey,
+    Out: Data,
+{
+    /// Count the distinct values of a field of the elements of each window.
+    ///
+    /// `field` extracts the value to count from each element, and each window keeps only the set
+    /// of the distinct values, not the elements. The sets of a window are merged across the
+    /// replicas like the accumulators of [`WindowedStream::aggregate`]. To bound the memory with
+    /// many distinct values, see [`WindowedStream::approx_count_distinct_by`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::window::CountWindow;
+    /// # let mut env = StreamContext::new_local();
+    /// // (page, visitor)
+    /// let s = env.stream_iter(vec![(0, 1), (0, 2), (1, 1), (0, 1), (1, 1), (1, 1)]);
+    /// let res = s
+    ///     .group_by(|&(page, _)| page)
+    ///     .window(CountWindow::tumbling(3))
+    ///     .count_distinct(|&(_, visitor)| visitor)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort();
+    /// assert_eq!(res, vec![(0, 2), (1, 1)]);
+    /// ```
+    pub fn count_distinct<D, F>(self, field: F) -> KeyedStream<impl Operator<Out = (Key, usize)>>
+    where
+        D: ExchangeDataKey,
+        F: Fn(&Out) -> D + Clone + Send + 'static,
+    {
+        self.aggregate(
+            HashSet::new,
+            move |set, x| {
+                set.insert(field(&x));
+            },
+            |set, other| set.extend(other),
+            |set| set.len(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::window::EventTimeWindow;
+
+    #[test]
+    fn count_distinct_per_key_per_window() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        // (page, visitor, time)
+        let visits: Vec<(u8, u32, i64)> = vec![
+            (0, 1, 0),
+            (0, 2, 3),
+            (0, 1, 5),
+            (0, 1, 12),
+            (0, 3, 15),
+            (0, 4, 17),
+            (1, 2, 1),
+            (1, 2, 2),
+            (1, 1, 11),
+            (1, 2, 18),
+            (1, 4, 19),
+            (1, 4, 19),
+        ];
+        let res = env
+            .stream_iter(visits)
+            .add_timestamps(|&(_, _, time)| time, |_, _| None)
+            .group_by(|&(page, _, _)| page)
+            .window(EventTimeWindow::tumbling(10))
+            .count_distinct(|&(_, visitor, _)| visitor)
+            .collect_vec();
+        env.execute_blocking();
+
+        let res = res.get().unwrap().into_iter().sorted().collect_vec();
+        assert_eq!(res, vec![(0, 2), (0, 3), (1, 1), (1, 3)]);
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use super::super::*;
+use crate::operator::{Data, DataKey, DedupPolicy, Operator};
+use crate::stream::{KeyedStream, WindowedStream};
+
+impl<Key, Out, WindowDescr, OperatorChain> WindowedStream<OperatorChain, Out, WindowDescr>
+where
+    WindowDescr: WindowDescription<Out> + 'static,
+    OperatorChain: Operator<Out = (Key, Out)> + 'static,
+    Key: DataKey,
+    Out: Data,
+{
+    /// Remove the elements of each window with the same value of `field`, keeping only one of
+    /// them according to `policy`.
+    ///
+    /// This is the windowed version of [`Stream::dedup_by_key`]: the values of `field` are
+    /// forgotten when the window closes, and the elements kept are emitted together when the
+    /// window closes, in the order of the first element of each value. Each replica deduplicates
+    /// the elements it receives, like [`WindowedStream::fold`], so the elements with the same key
+    /// must be in the same replica.
+    ///
+    /// ## Example
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::window::CountWindow;
+    /// use renoir::operator::DedupPolicy;
+    /// # let mut env = StreamContext::new_local();
+    /// // (user, page)
+    /// let s = env.stream_iter(vec![(0, 'a'), (0, 'b'), (0, 'a'), (0, 'a'), (0, 'c'), (0, 'c')]);
+    /// let res = s
+    ///     .group_by(|&(user, _)| user)
+    ///     .window(CountWindow::tumbling(3))
+    ///     .dedup_by_key(|&(_, page)| page, DedupPolicy::KeepFirst)
+    ///     .drop_key()
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![(0, 'a'), (0, 'b'), (0, 'a'), (0, 'c')]);
+    /// ```
+    pub fn dedup_by_key<D, F>(
+        self,
+        field: F,
+        policy: DedupPolicy,
+    ) -> KeyedStream<impl Operator<Out = (Key, Out)>>
+    where
+        D: DataKey,
+        F: Fn(&Out) -> D + Clone + Send + 'static,
+    {
+        // the elements kept, and the position of the element of each value
+        let init: (Vec<Out>, HashMap<D, usize, GroupHasherBuilder>) = Default::default();
+        self.fold(init, move |(kept, positions), x| {
+            match positions.entry(field(&x)) {
+                Entry::Vacant(entry) => {
+                    entry.insert(kept.len());
+                    kept.push(x);
+                }
+                Entry::Occupied(entry) => {
+                    if policy == DedupPolicy::KeepLast {
+                        kept[*entry.get()] = x;
+                    }
+                }
+            }
+        })
+        .map(|(_, (kept, _))| kept)
+        .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::window::CountWindow;
+    use crate::operator::DedupPolicy;
+
+    fn windowed_dedup(policy: DedupPolicy) -> Vec<(char, u32)> {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        let res = env
+            .stream_iter([('a', 1), ('b', 2), ('a', 3), ('a', 4), ('a', 5)])
+            .group_by(|_| ())
+            .window(CountWindow::new(3, 3, false))
+            .dedup_by_key(|&(k, _)| k, policy)
+            .drop_key()
+            .collect_vec();
+        env.execute_blocking();
+        res.get().unwrap()
+    }
+
+    #[test]
+    fn windowed_dedup_by_key() {
+        // the second window is incomplete, and closed by the end of the stream
+        assert_eq!(
+            windowed_dedup(DedupPolicy::KeepFirst),
+            vec![('a', 1), ('b', 2), ('a', 4)]
+        );
+        assert_eq!(
+            windowed_dedup(DedupPolicy::KeepLast),
+            vec![('a', 3), ('b', 2), ('a', 5)]
+        );
+    }
+}
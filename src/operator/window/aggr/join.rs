@@ -76,6 +76,43 @@ where
     Key: ExchangeData + DataKey,
     Out: ExchangeData,
 {
+    /// Join the elements of this stream with the ones of `right` that have the same key and fall
+    /// in the same window.
+    ///
+    /// Both streams are split in the windows described by `descr`, and when a window fires all
+    /// the pairs of elements of the two streams in that window are emitted. Unlike
+    /// [`KeyedStream::interval_join`] the pairs are bounded by the fixed window boundaries: two
+    /// elements that are close in time, but fall in two different windows, are not joined.
+    ///
+    /// **Note**: this operator will split the current block.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::window::EventTimeWindow;
+    /// # let mut env = StreamContext::new_local();
+    /// // (ad, minute) of the impressions and of the clicks
+    /// let impressions = env
+    ///     .stream_iter([(1, 0), (1, 1)].into_iter())
+    ///     .add_timestamps(|&(_, t)| t, |_, &ts| Some(ts))
+    ///     .group_by(|&(ad, _)| ad);
+    /// let clicks = env
+    ///     .stream_iter([(1, 0), (1, 2)].into_iter())
+    ///     .add_timestamps(|&(_, t)| t, |_, &ts| Some(ts))
+    ///     .group_by(|&(ad, _)| ad);
+    /// let res = impressions
+    ///     .window_join(EventTimeWindow::tumbling(2), clicks)
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// // the second click is in the next window
+    /// assert_eq!(
+    ///     res.get().unwrap(),
+    ///     vec![(1, ((1, 0), (1, 0))), (1, ((1, 1), (1, 0)))]
+    /// );
+    /// ```
     pub fn window_join<Out2, OperatorChain2, WindowDescr>(
         self,
         descr: WindowDescr,
@@ -101,6 +138,8 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
 
     #[test]
     fn product_iterator() {
@@ -124,4 +163,35 @@ mod tests {
 
         assert_eq!(expected, t);
     }
+
+    #[test]
+    fn window_join_same_window_only() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        // (ad, timestamp): the windows of a key start at its first element, so each key starts
+        // at the same time in both streams
+        let impressions = env
+            .stream_iter([(1, 0), (2, 0), (2, 55), (1, 70)])
+            .add_timestamps(|&(_, ts)| ts, |_, &ts| Some(ts))
+            .group_by(|&(ad, _)| ad);
+        let clicks = env
+            .stream_iter([(1, 0), (2, 0), (1, 50), (2, 65), (1, 130)])
+            .add_timestamps(|&(_, ts)| ts, |_, &ts| Some(ts))
+            .group_by(|&(ad, _)| ad);
+        let res = impressions
+            .window_join(EventTimeWindow::tumbling(60), clicks)
+            .collect_vec();
+        env.execute_blocking();
+
+        let mut res = res.get().unwrap();
+        res.sort_unstable();
+        // (2, 55) and (2, 65) are close in time but in different windows, (1, 70) has no clicks
+        // in its window
+        let expected = vec![
+            (1, ((1, 0), (1, 0))),
+            (1, ((1, 0), (1, 50))),
+            (2, ((2, 0), (2, 0))),
+            (2, ((2, 55), (2, 0))),
+        ];
+        assert_eq!(res, expected);
+    }
 }
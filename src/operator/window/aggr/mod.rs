@@ -1,13 +1,23 @@
+mod aggregate;
 mod fold;
 // mod columnar;
 pub(super) use fold::{Fold, FoldFirst};
 
+pub(crate) use approx_count_distinct::{HyperLogLog, DEFAULT_PRECISION};
+
+mod approx_count_distinct;
+#[cfg(feature = "timestamp")]
+mod collect_channel;
 mod collect_vec;
 mod count;
+mod count_distinct;
+mod dedup_by_key;
 mod join;
 mod max;
 mod min;
 mod nth;
+mod percentiles;
+mod reduce;
 mod sum;
 #[cfg(feature = "parquet")]
 mod to_arrow;
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::super::*;
+use crate::operator::{Data, ExchangeDataKey, Operator};
+use crate::stream::{KeyedStream, WindowedStream};
+
+/// The relative error of the quantiles estimated by [`WindowedStream::percentiles`].
+const RELATIVE_ACCURACY: f64 = 0.01;
+
+/// The values smaller than this, in absolute value, are counted as zero.
+const MIN_VALUE: f64 = 1e-9;
+
+/// Sketch of a distribution estimating its quantiles with a bounded relative error.
+///
+/// The values are counted in buckets whose bounds grow exponentially (`gamma^(i-1) < x <=
+/// gamma^i`), so each bucket represents its values with a relative error of at most
+/// [`RELATIVE_ACCURACY`], and the size of the sketch grows with the logarithm of the range of the
+/// values instead of with their number. Two sketches are merged by adding their buckets.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct QuantileSketch {
+    positive: BTreeMap<i32, u64>,
+    negative: BTreeMap<i32, u64>,
+    zero: u64,
+    count: u64,
+}
+
+impl QuantileSketch {
+    fn gamma() -> f64 {
+        (1.0 + RELATIVE_ACCURACY) / (1.0 - RELATIVE_ACCURACY)
+    }
+
+    fn index(value: f64) -> i32 {
+        (value.ln() / Self::gamma().ln()).ceil() as i32
+    }
+
+    /// The value representing the bucket `index`, within the relative accuracy of its values.
+    fn value(index: i32) -> f64 {
+        let gamma = Self::gamma();
+        2.0 * gamma.powi(index) / (gamma + 1.0)
+    }
+
+    fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        if value > MIN_VALUE {
+            *self.positive.entry(Self::index(value)).or_default() += 1;
+        } else if value < -MIN_VALUE {
+            *self.negative.entry(Self::index(-value)).or_default() += 1;
+        } else {
+            self.zero += 1;
+        }
+        self.count += 1;
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (index, count) in other.positive {
+            *self.positive.entry(index).or_default() += count;
+        }
+        for (index, count) in other.negative {
+            *self.negative.entry(index).or_default() += count;
+        }
+        self.zero += other.zero;
+        self.count += other.count;
+    }
+
+    /// Estimate the quantile `q` (between 0 and 1) of the values, `NaN` if there are none.
+    fn quantile(&self, q: f64) -> f64 {
+        if self.count == 0 {
+            return f64::NAN;
+        }
+        let rank = (q.clamp(0.0, 1.0) * (self.count - 1) as f64).round() as u64;
+        // the buckets in increasing order of their values
+        let buckets = self
+            .negative
+            .iter()
+            .rev()
+            .map(|(&i, &c)| (-Self::value(i), c))
+            .chain(std::iter::once((0.0, self.zero)))
+            .chain(self.positive.iter().map(|(&i, &c)| (Self::value(i), c)));
+        let mut seen = 0;
+        for (value, count) in buckets {
+            seen += count;
+            if seen > rank {
+                return value;
+            }
+        }
+        unreachable!("the rank is smaller than the number of values")
+    }
+}
+
+impl<Key, Out, WindowDescr, OperatorChain> WindowedStream<OperatorChain, Out, WindowDescr>
+where
+    WindowDescr: WindowDescription<Out> + 'static,
+    OperatorChain: Operator<Out = (Key, Out)> + 'static,
+    Key: ExchangeDataKey,
+    Out: Data + Into<f64>,
+{
+    /// Estimate the given quantiles (between 0 and 1) of the elements of each window.
+    ///
+    /// Instead of collecting and sorting the elements, each window keeps a sketch of their
+    /// distribution whose size grows with the logarithm of the range of the values, and the
+    /// estimated quantiles have a relative error of at most 1%. The output of each window has the
+    /// quantiles in the same order as `quantiles`.
+    ///
+    /// The sketches of a window are merged across the replicas like the accumulators of
+    /// [`WindowedStream::aggregate`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::window::CountWindow;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(1..=100u32);
+    /// let res = s
+    ///     .group_by(|_| ())
+    ///     .window(CountWindow::tumbling(100))
+    ///     .percentiles(&[0.5, 0.99])
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let (_, p) = &res.get().unwrap()[0];
+    /// // the estimates are within 1% of the exact values
+    /// assert!((p[0] / 51.0 - 1.0).abs() <= 0.01);
+    /// assert!((p[1] / 99.0 - 1.0).abs() <= 0.01);
+    /// ```
+    pub fn percentiles(
+        self,
+        quantiles: &[f64],
+    ) -> KeyedStream<impl Operator<Out = (Key, Vec<f64>)>> {
+        assert!(
+            quantiles.iter().all(|q| (0.0..=1.0).contains(q)),
+            "the quantiles must be between 0 and 1"
+        );
+        let quantiles = quantiles.to_vec();
+        self.aggregate(
+            QuantileSketch::default,
+            |sketch, x| sketch.add(x.into()),
+            |sketch, other| sketch.merge(other),
+            move |sketch| quantiles.iter().map(|&q| sketch.quantile(q)).collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::{QuantileSketch, RELATIVE_ACCURACY};
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::window::EventTimeWindow;
+
+    /// The exact quantile `q` of the sorted values, with the same rank as the sketch.
+    fn exact(sorted: &[f64], q: f64) -> f64 {
+        sorted[(q * (sorted.len() - 1) as f64).round() as usize]
+    }
+
+    fn assert_close(estimate: f64, exact: f64) {
+        assert!(
+            (estimate - exact).abs() <= exact.abs() * RELATIVE_ACCURACY,
+            "estimated {estimate}, the exact value is {exact}"
+        );
+    }
+
+    #[test]
+    fn sketch_quantiles_within_accuracy() {
+        // a skewed distribution, with negative values and zeros
+        let values = (0..10_000)
+            .map(|i| (i as f64 / 100.0).exp() - 20.0)
+            .chain([0.0; 100])
+            .collect_vec();
+        let mut first = QuantileSketch::default();
+        let mut second = QuantileSketch::default();
+        for (i, &v) in values.iter().enumerate() {
+            if i % 3 == 0 {
+                first.add(v);
+            } else {
+                second.add(v);
+            }
+        }
+        first.merge(second);
+
+        let sorted = values
+            .into_iter()
+            .sorted_by(|a, b| a.total_cmp(b))
+            .collect_vec();
+        for q in [0.0, 0.01, 0.1, 0.25, 0.5, 0.75, 0.9, 0.99, 1.0] {
+            assert_close(first.quantile(q), exact(&sorted, q));
+        }
+    }
+
+    #[test]
+    fn windowed_percentiles() {
+        let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+        // the elements are not moved by `key_by`, so the windows are merged across the replicas
+        let res = env
+            .stream_par_iter(0..10_000u32)
+            .add_timestamps(|&n| n as i64, |_, &ts| (ts % 100 == 0).then_some(ts))
+            .map(|n| n % 500 + 1)
+            .key_by(|_| ())
+            .window(EventTimeWindow::tumbling(500))
+            .percentiles(&[0.5, 0.99])
+            .collect_vec();
+        env.execute_blocking();
+
+        // each window has the values from 1 to 500
+        let values = (1..=500).map(f64::from).collect_vec();
+        let res = res.get().unwrap();
+        assert_eq!(res.len(), 20);
+        for (_, p) in res {
+            assert_close(p[0], exact(&values, 0.5));
+            assert_close(p[1], exact(&values, 0.99));
+        }
+    }
+}
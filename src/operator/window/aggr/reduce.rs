@@ -0,0 +1,95 @@
+use super::super::*;
+use crate::operator::{ExchangeData, ExchangeDataKey, Operator};
+use crate::stream::{KeyedStream, WindowedStream};
+
+impl<Key, Out, WindowDescr, OperatorChain> WindowedStream<OperatorChain, Out, WindowDescr>
+where
+    WindowDescr: WindowDescription<Out> + 'static,
+    OperatorChain: Operator<Out = (Key, Out)> + 'static,
+    Key: ExchangeDataKey,
+    Out: ExchangeData,
+{
+    /// Reduce the elements of each window to a single value, combining them with an associative
+    /// function.
+    ///
+    /// Each window keeps only its running value, combined with each element as soon as it
+    /// arrives, instead of buffering all its elements. Since `f` is associative, the partial
+    /// values computed by the different replicas for the same window are combined with `f` too,
+    /// see [`WindowedStream::aggregate`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::window::CountWindow;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter([3, 8, 1, 6, 5, 2].into_iter());
+    /// let res = s
+    ///     .group_by(|&n| n % 2)
+    ///     .window(CountWindow::tumbling(3))
+    ///     .reduce(|a, b| a.max(b))
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, vec![(0, 8), (1, 5)]);
+    /// ```
+    pub fn reduce<F>(self, f: F) -> KeyedStream<impl Operator<Out = (Key, Out)>>
+    where
+        F: Fn(Out, Out) -> Out + Clone + Send + 'static,
+    {
+        let combine = move |acc: &mut Option<Out>, value: Out| {
+            *acc = Some(match acc.take() {
+                None => value,
+                Some(acc) => f(acc, value),
+            });
+        };
+        let merge = combine.clone();
+        self.aggregate(
+            || None,
+            combine,
+            move |acc, partial| {
+                if let Some(partial) = partial {
+                    merge(acc, partial)
+                }
+            },
+            |acc| acc.expect("WindowReduce output called when it has received no elements!"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::IteratorSource;
+    use crate::operator::window::EventTimeWindow;
+
+    fn windowed_max(parallelism: u64, reduce: bool) -> Vec<(u64, u64)> {
+        let env = StreamContext::new(RuntimeConfig::local(parallelism).unwrap());
+        let windowed = env
+            .stream(IteratorSource::new(0..1000u64))
+            .add_timestamps(|&n| n as i64, |&n, &ts| (n % 100 == 0).then_some(ts))
+            .map(|n| (n * 7919) % 1009)
+            .group_by(|n| n % 3)
+            .window(EventTimeWindow::sliding(100, 50));
+        let res = if reduce {
+            windowed.reduce(|a, b| a.max(b)).collect_vec()
+        } else {
+            windowed.max().collect_vec()
+        };
+        env.execute_blocking();
+        res.get().unwrap().into_iter().sorted().collect()
+    }
+
+    #[test]
+    fn reduce_sliding_max() {
+        let expected = windowed_max(1, false);
+        assert!(!expected.is_empty());
+        assert_eq!(windowed_max(1, true), expected);
+        assert_eq!(windowed_max(4, true), expected);
+    }
+}
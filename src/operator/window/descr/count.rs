@@ -15,12 +15,16 @@ pub struct CountWindowManager<A> {
     size: usize,
     slide: usize,
     exact: bool,
+    early_firing: Option<usize>,
+    emit_mode: EmitMode,
     ws: VecDeque<Slot<A>>,
 }
 
 #[derive(Clone)]
 struct Slot<A> {
     count: usize,
+    /// Number of elements processed since the last early firing.
+    pending: usize,
     acc: A,
     ts: Option<Timestamp>,
 }
@@ -30,6 +34,7 @@ impl<A> Slot<A> {
     fn new(acc: A) -> Self {
         Self {
             count: 0,
+            pending: 0,
             acc,
             ts: None,
         }
@@ -40,6 +45,7 @@ impl<A: WindowAccumulator> CountWindowManager<A> {
     #[inline]
     fn update_slot(&mut self, idx: usize, el: A::In, ts: Option<Timestamp>) {
         self.ws[idx].count += 1;
+        self.ws[idx].pending += 1;
         self.ws[idx].ts = match (self.ws[idx].ts, ts) {
             (Some(a), Some(b)) => Some(a.max(b)),
             (Some(t), None) | (None, Some(t)) => Some(t),
@@ -47,6 +53,22 @@ impl<A: WindowAccumulator> CountWindowManager<A> {
         };
         self.ws[idx].acc.process(el);
     }
+
+    /// Fire the oldest window before it is complete, if it received `early_firing` elements since
+    /// the last firing.
+    fn fire_early(&mut self) -> Option<WindowResult<A::Out>> {
+        let every = self.early_firing?;
+        let slot = self.ws.front_mut()?;
+        if slot.pending < every {
+            return None;
+        }
+        slot.pending = 0;
+        let acc = match self.emit_mode {
+            EmitMode::Accumulating => slot.acc.clone(),
+            EmitMode::Discarding => std::mem::replace(&mut slot.acc, self.init.clone()),
+        };
+        Some(WindowResult::new(acc.output(), slot.ts))
+    }
 }
 
 impl<A: WindowAccumulator> WindowManager for CountWindowManager<A>
@@ -74,16 +96,18 @@ where
                     let r = self.ws.pop_front().unwrap();
                     Some(WindowResult::new(r.acc.output(), r.ts))
                 } else {
-                    None
+                    self.fire_early()
                 }
             }
             StreamElement::FlushAndRestart | StreamElement::Terminate => {
                 let ret = if self.exact {
                     None
                 } else {
+                    // in discarding mode nothing is left if the window has just fired early
+                    let discarding = self.emit_mode == EmitMode::Discarding;
                     self.ws
                         .pop_front()
-                        .filter(|r| r.count > 0)
+                        .filter(|r| r.count > 0 && !(discarding && r.pending == 0))
                         .map(|r| WindowResult::new(r.acc.output(), r.ts))
                 };
                 self.ws.drain(..);
@@ -92,6 +116,14 @@ where
             _ => None,
         }
     }
+
+    fn open_windows(&self) -> usize {
+        self.ws.iter().filter(|w| w.count > 0).count()
+    }
+
+    fn buffered_elements(&self) -> usize {
+        self.ws.iter().map(|w| w.count).sum()
+    }
 }
 
 /// Window of fixed count of elements
@@ -102,6 +134,11 @@ pub struct CountWindow {
     /// If exact is `true`, only results from windows of size `size` will be returned.
     /// If exact is `false`, on terminate, the first incomplete window result will be returned if present
     pub exact: bool,
+    /// If set, the oldest open window also fires each time it receives this many elements,
+    /// before being complete
+    pub early_firing: Option<usize>,
+    /// What the firings of a window emit
+    pub emit_mode: EmitMode,
 }
 
 impl CountWindow {
@@ -110,7 +147,13 @@ impl CountWindow {
     /// If exact is `false`, on terminate, the first incomplete window result will be returned if present
     #[inline]
     pub fn new(size: usize, slide: usize, exact: bool) -> Self {
-        Self { size, slide, exact }
+        Self {
+            size,
+            slide,
+            exact,
+            early_firing: None,
+            emit_mode: EmitMode::default(),
+        }
     }
 
     /// Exact windows of `size` elements, generated each `slide` elements
@@ -118,22 +161,33 @@ impl CountWindow {
     pub fn sliding(size: usize, slide: usize) -> Self {
         assert!(size > 0, "window size must be > 0"); // TODO: consider using NonZeroUsize
         assert!(slide > 0, "window slide must be > 0");
-        Self {
-            size,
-            slide,
-            exact: true,
-        }
+        Self::new(size, slide, true)
     }
 
     /// Exact windows of `size` elements
     #[inline]
     pub fn tumbling(size: usize) -> Self {
         assert!(size > 0, "window size must be > 0");
-        Self {
-            size,
-            slide: size,
-            exact: true,
-        }
+        Self::new(size, size, true)
+    }
+
+    /// Fire the oldest open window every `every` elements it receives, before it is complete, to
+    /// produce early results. What each firing emits depends on the [`EmitMode`].
+    ///
+    /// With a tumbling window the windows are open one at a time, so each of them fires early
+    /// every `every` elements.
+    #[inline]
+    pub fn early_firing(mut self, every: usize) -> Self {
+        assert!(every > 0, "early firing interval must be > 0");
+        self.early_firing = Some(every);
+        self
+    }
+
+    /// Set what the firings of a window emit, see [`EmitMode`].
+    #[inline]
+    pub fn emit_mode(mut self, emit_mode: EmitMode) -> Self {
+        self.emit_mode = emit_mode;
+        self
     }
 }
 
@@ -147,6 +201,8 @@ impl<T: Data> WindowDescription<T> for CountWindow {
             size: self.size,
             slide: self.slide,
             exact: self.exact,
+            early_firing: self.early_firing,
+            emit_mode: self.emit_mode,
             ws: Default::default(),
         }
     }
@@ -239,6 +295,31 @@ mod tests {
         assert_eq!(vec![vec![1, 2, 3, 4], vec![2, 3, 4, 5], vec![3, 4, 5]], res)
     }
 
+    #[test]
+    fn early_firing_emit_modes() {
+        for (mode, expected) in [
+            (EmitMode::Accumulating, vec![3, 10, 21]),
+            (EmitMode::Discarding, vec![3, 7, 11]),
+        ] {
+            let window = CountWindow::tumbling(6).early_firing(2).emit_mode(mode);
+
+            let fold: Fold<isize, isize, _> = Fold::new(0, |s, el| *s += el);
+            let mut manager = window.build(fold);
+
+            let mut res = Vec::new();
+            // fires early after 2 and 4 elements, then finally after 6
+            for i in 1..=6 {
+                res.extend(
+                    manager
+                        .process(StreamElement::Item(i))
+                        .map(WindowResult::unwrap_item),
+                );
+            }
+            assert!(manager.process(StreamElement::Terminate).is_none());
+            assert_eq!(res, expected, "{mode:?}");
+        }
+    }
+
     #[test]
     #[cfg(feature = "timestamp")]
     fn count_window_timestamped() {
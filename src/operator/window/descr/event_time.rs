@@ -11,17 +11,28 @@ where
     init: A,
     size: Timestamp,
     slide: Timestamp,
+    /// How long the windows stay open after the watermark passes their end.
+    lateness: Timestamp,
+    early_firing: Option<usize>,
+    emit_mode: EmitMode,
     last_watermark: Option<Timestamp>,
     ws: VecDeque<Slot<A>>,
+    /// The number of elements dropped because they were too late, since the last report.
+    dropped: usize,
 }
 impl<A: WindowAccumulator> EventTimeWindowManager<A> {
+    /// The watermark minus the allowed lateness: the windows ending before it are closed.
+    fn closed_before(&self) -> Option<Timestamp> {
+        self.last_watermark.map(|w| w - self.lateness)
+    }
+
     fn alloc_windows(&mut self, ts: Timestamp) {
-        assert!(self.last_watermark.map(|w| ts >= w).unwrap_or(true));
+        assert!(self.closed_before().map(|w| ts >= w).unwrap_or(true));
 
         while self.ws.back().map(|b| b.start < ts).unwrap_or(true) {
             let mut next_start = self.ws.back().map(|b| b.start + self.slide).unwrap_or(ts);
             // Skip empty windows
-            if let Some(w) = self.last_watermark {
+            if let Some(w) = self.closed_before() {
                 next_start += (w - next_start).max(0) / self.slide * self.slide
             }
 
@@ -33,6 +44,17 @@ impl<A: WindowAccumulator> EventTimeWindowManager<A> {
             ));
         }
     }
+
+    /// Close the windows in `range`, emitting the result of the ones with elements not emitted yet.
+    fn close(&mut self, range: std::ops::RangeTo<usize>) -> Vec<WindowResult<A::Out>> {
+        // in discarding mode nothing is left if the window has just fired early
+        let discarding = self.emit_mode == EmitMode::Discarding;
+        self.ws
+            .drain(range)
+            .filter(|w| w.active && !(discarding && w.pending == 0))
+            .map(|w| WindowResult::Timestamped(w.acc.output(), w.end))
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -41,6 +63,10 @@ struct Slot<A> {
     start: Timestamp,
     end: Timestamp,
     active: bool,
+    /// The number of elements received by the window.
+    count: usize,
+    /// Number of elements received since the last early firing.
+    pending: usize,
 }
 
 impl<A> Slot<A> {
@@ -51,6 +77,8 @@ impl<A> Slot<A> {
             start,
             end,
             active: false,
+            count: 0,
+            pending: 0,
         }
     }
 }
@@ -68,33 +96,46 @@ where
     fn process(&mut self, el: StreamElement<A::In>) -> Self::Output {
         match el {
             StreamElement::Timestamped(item, ts) => {
+                if self.closed_before().is_some_and(|w| ts < w) {
+                    log::trace!("Dropping element with timestamp {ts}, too late");
+                    self.dropped += 1;
+                    return Vec::new();
+                }
                 self.alloc_windows(ts);
-                self.ws
+                let mut early = Vec::new();
+                for w in self
+                    .ws
                     .iter_mut()
                     .skip_while(|w| w.end <= ts)
                     .take_while(|w| w.start <= ts)
-                    .for_each(|w| {
-                        w.acc.process(item.clone());
-                        w.active = true;
-                    });
-
-                Vec::new()
+                {
+                    w.acc.process(item.clone());
+                    w.active = true;
+                    w.count += 1;
+                    w.pending += 1;
+                    // fire before the window is complete to produce an early result
+                    if self.early_firing.is_some_and(|every| w.pending >= every) {
+                        w.pending = 0;
+                        let acc = match self.emit_mode {
+                            EmitMode::Accumulating => w.acc.clone(),
+                            EmitMode::Discarding => {
+                                std::mem::replace(&mut w.acc, self.init.clone())
+                            }
+                        };
+                        early.push(WindowResult::Timestamped(acc.output(), w.end));
+                    }
+                }
+                early
             }
             StreamElement::Watermark(ts) => {
                 self.last_watermark = Some(ts);
-                let split = self.ws.partition_point(|w| w.end < ts);
-                self.ws
-                    .drain(..split)
-                    .filter(|w| w.active)
-                    .map(|w| WindowResult::Timestamped(w.acc.output(), w.end))
-                    .collect()
+                let closed = self.closed_before().unwrap();
+                let split = self.ws.partition_point(|w| w.end < closed);
+                self.close(..split)
+            }
+            StreamElement::FlushAndRestart | StreamElement::Terminate => {
+                self.close(..self.ws.len())
             }
-            StreamElement::FlushAndRestart | StreamElement::Terminate => self
-                .ws
-                .drain(..)
-                .filter(|w| w.active)
-                .map(|w| WindowResult::Timestamped(w.acc.output(), w.end))
-                .collect(),
             StreamElement::Item(_) => {
                 panic!("Event time windows can only handle timestamped items!")
             }
@@ -105,6 +146,18 @@ where
     fn recycle(&self) -> bool {
         self.ws.is_empty()
     }
+
+    fn open_windows(&self) -> usize {
+        self.ws.iter().filter(|w| w.active).count()
+    }
+
+    fn buffered_elements(&self) -> usize {
+        self.ws.iter().map(|w| w.count).sum()
+    }
+
+    fn take_dropped(&mut self) -> usize {
+        std::mem::take(&mut self.dropped)
+    }
 }
 
 /// Window based on event timestamps
@@ -112,6 +165,9 @@ where
 pub struct EventTimeWindow {
     size: Timestamp,
     slide: Timestamp,
+    lateness: Timestamp,
+    early_firing: Option<usize>,
+    emit_mode: EmitMode,
 }
 
 impl EventTimeWindow {
@@ -119,13 +175,78 @@ impl EventTimeWindow {
     pub fn sliding(size: Timestamp, slide: Timestamp) -> Self {
         assert!(size > 0, "window size must be > 0");
         assert!(slide > 0, "window slide must be > 0");
-        Self { size, slide }
+        Self {
+            size,
+            slide,
+            lateness: 0,
+            early_firing: None,
+            emit_mode: EmitMode::default(),
+        }
     }
 
     #[inline]
     pub fn tumbling(size: Timestamp) -> Self {
-        assert!(size > 0, "window size must be > 0");
-        Self { size, slide: size }
+        Self::sliding(size, size)
+    }
+
+    /// The duration of each window.
+    #[inline]
+    pub(crate) fn size(&self) -> Timestamp {
+        self.size
+    }
+
+    /// Keep the windows open for `lateness` after the watermark passes their end, so that the
+    /// elements arriving late are still counted in their windows, at the cost of delaying the
+    /// results.
+    ///
+    /// The elements arriving after their windows are closed are dropped and reported to the
+    /// profiler as `too_late`. By default there is no allowed lateness: the windows are closed as
+    /// soon as the watermark passes their end.
+    #[inline]
+    pub fn allowed_lateness(mut self, lateness: Timestamp) -> Self {
+        assert!(lateness >= 0, "the allowed lateness must be >= 0");
+        self.lateness = lateness;
+        self
+    }
+
+    /// Fire each open window every `every` elements it receives, before the watermark passes its
+    /// end, to produce early results. What each firing emits depends on the [`EmitMode`].
+    ///
+    /// The early results have the timestamp of the end of their window, like the final one.
+    #[inline]
+    pub fn early_firing(mut self, every: usize) -> Self {
+        assert!(every > 0, "early firing interval must be > 0");
+        self.early_firing = Some(every);
+        self
+    }
+
+    /// Set what the firings of a window emit, see [`EmitMode`].
+    #[inline]
+    pub fn emit_mode(mut self, emit_mode: EmitMode) -> Self {
+        self.emit_mode = emit_mode;
+        self
+    }
+
+    /// Trade the latency of the results for their completeness, see [`LatencyMode`] for the
+    /// settings used by each mode.
+    ///
+    /// This replaces the early firing, the emit mode and the allowed lateness set before.
+    #[inline]
+    pub fn latency_mode(self, mode: LatencyMode) -> Self {
+        let window = Self {
+            early_firing: None,
+            emit_mode: EmitMode::Accumulating,
+            lateness: 0,
+            ..self
+        };
+        match mode {
+            LatencyMode::Low => window.early_firing(1),
+            LatencyMode::Balanced => window,
+            LatencyMode::Complete => {
+                let size = window.size;
+                window.allowed_lateness(size)
+            }
+        }
     }
 }
 
@@ -138,8 +259,12 @@ impl<T: Data> WindowDescription<T> for EventTimeWindow {
             init: accumulator,
             size: self.size,
             slide: self.slide,
+            lateness: self.lateness,
+            early_firing: self.early_firing,
+            emit_mode: self.emit_mode,
             last_watermark: Default::default(),
             ws: Default::default(),
+            dropped: 0,
         }
     }
 }
@@ -206,4 +331,81 @@ mod tests {
         let expected: Vec<Vec<_>> = vec![vec![1], vec![15, 16], vec![30, 31]];
         assert_eq!(received, expected)
     }
+
+    #[test]
+    fn event_time_window_allowed_lateness() {
+        let window = EventTimeWindow::tumbling(10).allowed_lateness(5);
+
+        let fold = Fold::new(Vec::new(), |v, el| v.push(el));
+        let mut manager = window.build(fold);
+
+        let mut received = Vec::new();
+        for el in [
+            StreamElement::Timestamped(1, 1),
+            StreamElement::Timestamped(5, 5),
+            StreamElement::Watermark(12),
+            // late, but within the allowed lateness
+            StreamElement::Timestamped(8, 8),
+            StreamElement::Watermark(20),
+            // too late, its window is closed
+            StreamElement::Timestamped(3, 3),
+            StreamElement::Timestamped(16, 16),
+            StreamElement::FlushAndRestart,
+        ] {
+            save_result!(manager.process(el), received);
+        }
+
+        assert_eq!(received, vec![vec![1, 5, 8], vec![16]]);
+        assert_eq!(manager.take_dropped(), 1);
+        assert_eq!(manager.take_dropped(), 0);
+    }
+
+    #[test]
+    fn event_time_window_low_latency() {
+        let window = EventTimeWindow::tumbling(10).latency_mode(LatencyMode::Low);
+
+        let fold = Fold::new(Vec::new(), |v, el| v.push(el));
+        let mut manager = window.build(fold);
+
+        let mut received = Vec::new();
+        for el in [
+            StreamElement::Timestamped(1, 1),
+            StreamElement::Timestamped(5, 5),
+        ] {
+            save_result!(manager.process(el), received);
+        }
+        // partial results before the watermark closes the window
+        assert_eq!(received, vec![vec![1], vec![1, 5]]);
+
+        save_result!(manager.process(StreamElement::Watermark(12)), received);
+        save_result!(manager.process(StreamElement::Timestamped(8, 8)), received);
+        save_result!(manager.process(StreamElement::FlushAndRestart), received);
+        assert_eq!(received, vec![vec![1], vec![1, 5], vec![1, 5]]);
+        assert_eq!(manager.take_dropped(), 1);
+    }
+
+    #[test]
+    fn event_time_window_complete() {
+        let window = EventTimeWindow::tumbling(10).latency_mode(LatencyMode::Complete);
+
+        let fold = Fold::new(Vec::new(), |v, el| v.push(el));
+        let mut manager = window.build(fold);
+
+        let mut received = Vec::new();
+        for el in [
+            StreamElement::Timestamped(1, 1),
+            StreamElement::Timestamped(5, 5),
+            StreamElement::Watermark(12),
+            StreamElement::Timestamped(8, 8),
+            StreamElement::Watermark(19),
+        ] {
+            save_result!(manager.process(el), received);
+        }
+        // the window waits for the late elements
+        assert!(received.is_empty());
+
+        save_result!(manager.process(StreamElement::Watermark(22)), received);
+        assert_eq!(received, vec![vec![1, 5, 8]]);
+        assert_eq!(manager.take_dropped(), 0);
+    }
 }
@@ -10,7 +10,7 @@ mod processing_time;
 pub use processing_time::ProcessingTimeWindow;
 
 mod session;
-pub use session::SessionWindow;
+pub use session::{DynamicSessionWindow, SessionWindow};
 
 #[cfg(feature = "timestamp")]
 mod transaction;
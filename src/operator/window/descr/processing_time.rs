@@ -21,6 +21,8 @@ struct Slot<A> {
     start: Instant,
     end: Instant,
     active: bool,
+    /// The number of elements received by the window.
+    count: usize,
 }
 
 impl<A> Slot<A> {
@@ -31,6 +33,7 @@ impl<A> Slot<A> {
             start,
             end,
             active: false,
+            count: 0,
         }
     }
 }
@@ -65,6 +68,7 @@ where
                     .for_each(|w| {
                         w.acc.process(item.clone());
                         w.active = true;
+                        w.count += 1;
                     });
             }
             StreamElement::Terminate | StreamElement::FlushAndRestart => {
@@ -85,6 +89,14 @@ where
             .map(|w| WindowResult::Item(w.acc.output()))
             .collect()
     }
+
+    fn open_windows(&self) -> usize {
+        self.ws.iter().filter(|w| w.active).count()
+    }
+
+    fn buffered_elements(&self) -> usize {
+        self.ws.iter().map(|w| w.count).sum()
+    }
 }
 
 /// Window based on wall clock at time of processing
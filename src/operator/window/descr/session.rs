@@ -1,15 +1,41 @@
+use std::iter::Chain;
+use std::marker::PhantomData;
+use std::option;
 use std::time::{Duration, Instant};
 
 use super::super::*;
 use crate::operator::{Data, StreamElement};
 
+/// The inactivity gap after an element that closes its session window.
+pub trait SessionGap<T>: Clone + Send + 'static {
+    fn gap(&self, item: &T) -> Duration;
+}
+
+impl<T> SessionGap<T> for Duration {
+    #[inline]
+    fn gap(&self, _item: &T) -> Duration {
+        *self
+    }
+}
+
+/// The gap of a [`DynamicSessionWindow`], computed for each element.
 #[derive(Clone)]
-pub struct SessionWindowManager<A>
+pub struct DynamicGap<F>(F);
+
+impl<T, F: Fn(&T) -> Duration + Clone + Send + 'static> SessionGap<T> for DynamicGap<F> {
+    #[inline]
+    fn gap(&self, item: &T) -> Duration {
+        (self.0)(item)
+    }
+}
+
+#[derive(Clone)]
+pub struct SessionWindowManager<A, G = Duration>
 where
     A: WindowAccumulator,
 {
     init: A,
-    gap: Duration,
+    gap: G,
     w: Option<Slot<A>>,
 }
 
@@ -17,54 +43,85 @@ where
 struct Slot<A> {
     acc: A,
     last: Instant,
+    /// The gap of the last element received by the window.
+    gap: Duration,
+    /// The number of elements received by the window.
+    count: usize,
 }
 
 impl<A> Slot<A> {
     #[inline]
     fn new(acc: A, last: Instant) -> Self {
-        Self { acc, last }
+        Self {
+            acc,
+            last,
+            gap: Duration::ZERO,
+            count: 0,
+        }
     }
 }
 
-impl<A: WindowAccumulator> WindowManager for SessionWindowManager<A>
+/// The windows closed by an element: the one that expired before it and the one it closed.
+type Closed<T> = Chain<option::IntoIter<WindowResult<T>>, option::IntoIter<WindowResult<T>>>;
+
+impl<A: WindowAccumulator, G: SessionGap<A::In>> WindowManager for SessionWindowManager<A, G>
 where
     A::In: Data,
     A::Out: Data,
 {
     type In = A::In;
     type Out = A::Out;
-    type Output = Option<WindowResult<A::Out>>;
+    type Output = Closed<A::Out>;
 
     #[inline]
     fn process(&mut self, el: StreamElement<A::In>) -> Self::Output {
         let ts = Instant::now();
 
-        let ret = match &self.w {
-            Some(slot) if ts - slot.last > self.gap => {
+        let expired = match &self.w {
+            Some(slot) if ts - slot.last > slot.gap => {
                 let output = self.w.take().unwrap().acc.output();
                 Some(WindowResult::Item(output))
             }
             _ => None,
         };
 
-        match el {
+        let closed = match el {
             StreamElement::Item(item) | StreamElement::Timestamped(item, _) => {
+                let gap = self.gap.gap(&item);
                 let slot = self
                     .w
                     .get_or_insert_with(|| Slot::new(self.init.clone(), ts));
                 slot.acc.process(item);
                 slot.last = ts;
-                ret
+                slot.gap = gap;
+                slot.count += 1;
+                // nothing can join the session after an element with no gap
+                if gap.is_zero() {
+                    self.w.take().map(|s| WindowResult::Item(s.acc.output()))
+                } else {
+                    None
+                }
             }
             StreamElement::Terminate | StreamElement::FlushAndRestart => {
-                ret.or_else(|| self.w.take().map(|s| WindowResult::Item(s.acc.output())))
+                self.w.take().map(|s| WindowResult::Item(s.acc.output()))
             }
-            _ => ret,
-        }
+            _ => None,
+        };
+        expired.into_iter().chain(closed)
+    }
+
+    fn open_windows(&self) -> usize {
+        self.w.is_some() as usize
+    }
+
+    fn buffered_elements(&self) -> usize {
+        self.w.as_ref().map(|w| w.count).unwrap_or_default()
     }
 }
 
 /// Window that splits after if no element is received for a fixed wall clock duration
+///
+/// For a gap that depends on the elements see [`DynamicSessionWindow`].
 #[derive(Clone)]
 pub struct SessionWindow {
     gap: Duration,
@@ -91,6 +148,64 @@ impl<T: Data> WindowDescription<T> for SessionWindow {
     }
 }
 
+/// Window that splits after if no element is received for a wall clock duration that depends on
+/// the last element of the window
+///
+/// The `gap` function is called on each element: the session is extended by the elements
+/// received within the gap of the previous element. An element with a zero gap closes its session
+/// immediately, for example an event that ends the session of a user.
+///
+/// ## Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use renoir::{StreamContext, RuntimeConfig};
+/// # use renoir::operator::window::DynamicSessionWindow;
+/// # let mut env = StreamContext::new_local();
+/// let s = env.stream_iter(["click", "click", "logout", "click"].map(String::from));
+/// let res = s
+///     .group_by(|_| ())
+///     .window(DynamicSessionWindow::new(|event: &String| match event.as_str() {
+///         "logout" => Duration::ZERO,
+///         _ => Duration::from_secs(60),
+///     }))
+///     .count()
+///     .drop_key()
+///     .collect_vec();
+///
+/// env.execute_blocking();
+///
+/// assert_eq!(res.get().unwrap(), vec![3, 1]);
+/// ```
+#[derive(Clone)]
+pub struct DynamicSessionWindow<T, F: Fn(&T) -> Duration> {
+    gap: F,
+    _t: PhantomData<T>,
+}
+
+impl<T, F: Fn(&T) -> Duration> DynamicSessionWindow<T, F> {
+    #[inline]
+    pub fn new(gap: F) -> Self {
+        Self {
+            gap,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T: Data, F: Fn(&T) -> Duration + Data> WindowDescription<T> for DynamicSessionWindow<T, F> {
+    type Manager<A: WindowAccumulator<In = T>> = SessionWindowManager<A, DynamicGap<F>>;
+
+    #[inline]
+    fn build<A: WindowAccumulator<In = T>>(&self, accumulator: A) -> Self::Manager<A> {
+        SessionWindowManager {
+            init: accumulator,
+            gap: DynamicGap(self.gap.clone()),
+            w: Default::default(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
@@ -130,4 +245,46 @@ mod tests {
             vec![(0..33).collect(), (33..80).collect(), (80..100).collect()];
         assert_eq!(received, expected)
     }
+
+    #[test]
+    fn dynamic_gap_window() {
+        // the odd elements keep the session open, a multiple of 10 closes it
+        let window = DynamicSessionWindow::new(|&i: &i64| {
+            if i % 10 == 0 {
+                Duration::ZERO
+            } else if i % 2 == 1 {
+                Duration::from_secs(60)
+            } else {
+                Duration::from_millis(10)
+            }
+        });
+
+        let fold = Fold::new(Vec::new(), |v, el| v.push(el));
+        let mut manager = window.build(fold);
+
+        let mut received = Vec::new();
+        for i in 1..=25i64 {
+            // the long gap of 5 extends the session over the pause, the short gap of 22 does not
+            if i == 6 || i == 23 {
+                std::thread::sleep(Duration::from_millis(20))
+            }
+            save_result!(
+                manager.process(StreamElement::Timestamped(i, i / 10)),
+                received
+            );
+            if i == 10 || i == 20 {
+                // the element with a zero gap closed the window without waiting for the next one
+                assert_eq!(received.last(), Some(&(i - 9..=i).collect::<Vec<_>>()));
+            }
+        }
+        save_result!(manager.process(StreamElement::FlushAndRestart), received);
+
+        let expected: Vec<Vec<_>> = vec![
+            (1..=10).collect(),
+            (11..=20).collect(),
+            (21..=22).collect(),
+            (23..=25).collect(),
+        ];
+        assert_eq!(received, expected)
+    }
 }
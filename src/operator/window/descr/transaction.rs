@@ -30,12 +30,18 @@ where
 struct Slot<A> {
     acc: A,
     close: Option<Timestamp>,
+    /// The number of elements received by the window.
+    count: usize,
 }
 
 impl<A> Slot<A> {
     #[inline]
     fn new(acc: A) -> Self {
-        Self { acc, close: None }
+        Self {
+            acc,
+            close: None,
+            count: 0,
+        }
     }
 }
 
@@ -63,6 +69,7 @@ where
 
                 let command = (self.f)(&item);
                 slot.acc.process(item);
+                slot.count += 1;
 
                 match command {
                     TransactionOp::Commit => return_current!(),
@@ -94,6 +101,14 @@ where
     fn recycle(&self) -> bool {
         self.w.is_none()
     }
+
+    fn open_windows(&self) -> usize {
+        self.w.is_some() as usize
+    }
+
+    fn buffered_elements(&self) -> usize {
+        self.w.as_ref().map(|w| w.count).unwrap_or_default()
+    }
 }
 
 /// Window that closes according to user supplied logic
@@ -4,12 +4,15 @@ use std::collections::{HashMap, VecDeque};
 use std::fmt::Display;
 use std::marker::PhantomData;
 
+pub(crate) use aggr::{HyperLogLog, DEFAULT_PRECISION};
 pub use descr::*;
 // pub use aggregator::*;
 // pub use description::*;
 
 use crate::block::{GroupHasherBuilder, OperatorStructure, Replication};
+use crate::network::Coord;
 use crate::operator::{Data, DataKey, ExchangeData, Operator, StreamElement, Timestamp};
+use crate::profiler::{get_profiler, DropReason, Profiler, WindowState};
 use crate::stream::{KeyedStream, Stream, WindowedStream};
 
 mod aggr;
@@ -75,6 +78,59 @@ pub trait WindowManager: Clone + Send {
     fn recycle(&self) -> bool {
         false
     }
+    /// Return the number of windows currently open, used for reporting the size of the state.
+    fn open_windows(&self) -> usize {
+        0
+    }
+    /// Return the number of elements received by the windows currently open, counting an element
+    /// once for each window it belongs to. Used for reporting the size of the state.
+    fn buffered_elements(&self) -> usize {
+        0
+    }
+    /// Return the number of elements dropped because they arrived too late since the last call.
+    fn take_dropped(&mut self) -> usize {
+        0
+    }
+}
+
+/// What a window emits each time it fires, when it fires more than once (e.g. with the early
+/// firing of a [`CountWindow`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmitMode {
+    /// Each firing emits the result of all the elements received by the window so far, so the
+    /// downstream operators see the result grow until the final firing.
+    #[default]
+    Accumulating,
+    /// Each firing emits only the result of the elements received since the previous firing, so
+    /// the final result is split in incremental updates.
+    Discarding,
+}
+
+/// How an [`EventTimeWindow`] trades the latency of its results for their completeness.
+///
+/// Each mode is a preset of the lower-level settings of the window:
+///
+/// | Mode       | Early firing         | Emit mode      | Allowed lateness    |
+/// |------------|----------------------|----------------|---------------------|
+/// | `Low`      | after every element  | `Accumulating` | none                |
+/// | `Balanced` | none                 | `Accumulating` | none                |
+/// | `Complete` | none                 | `Accumulating` | one window size     |
+///
+/// Set it with [`EventTimeWindow::latency_mode`] or [`WindowedStream::latency_mode`].
+#[cfg(feature = "timestamp")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LatencyMode {
+    /// Emit a partial result each time a window receives an element, and the final result as soon
+    /// as the watermark passes the end of the window. The elements arriving after that are
+    /// dropped.
+    Low,
+    /// Emit the result once, as soon as the watermark passes the end of the window. The elements
+    /// arriving after that are dropped.
+    #[default]
+    Balanced,
+    /// Emit the result once, when the watermark passes the end of the window by another window
+    /// size, so that the elements arriving late are still counted.
+    Complete,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -137,6 +193,10 @@ where
     manager: KeyedWindowManager<Key, In, Out, W>,
     /// A buffer for storing ready items.
     output_buffer: VecDeque<StreamElement<(Key, Out)>>,
+    /// The coordinate of the replica, used for reporting the size of the state to the profiler.
+    coord: Option<Coord>,
+    /// The number of elements dropped by the windows since the last report.
+    dropped: usize,
 }
 
 impl<Key, In, Out, Prev, W> Display for WindowOperator<Key, In, Out, Prev, W>
@@ -168,6 +228,7 @@ where
 
     fn setup(&mut self, metadata: &mut crate::ExecutionMetadata) {
         self.prev.setup(metadata);
+        self.coord = Some(metadata.coord);
     }
 
     fn next(&mut self) -> StreamElement<(Key, Out)> {
@@ -189,12 +250,16 @@ where
                         .or_insert_with(|| self.manager.init.clone());
 
                     let ret = mgr.process(el);
+                    self.dropped += mgr.take_dropped();
                     self.output_buffer.extend(
                         ret.into_iter()
                             .map(|e| StreamElement::from(e).add_key(key.clone())),
                     );
                 }
-                StreamElement::FlushBatch => return StreamElement::FlushBatch,
+                StreamElement::FlushBatch => {
+                    self.report_state();
+                    return StreamElement::FlushBatch;
+                }
                 el => {
                     let (_, el) = el.take_key();
 
@@ -206,6 +271,11 @@ where
                         );
                         !mgr.recycle()
                     });
+                    // the managers created for new keys must know the watermark too
+                    if let StreamElement::Watermark(_) = el {
+                        self.manager.init.process(el.clone());
+                    }
+                    self.report_state();
 
                     // Forward system messages and watermarks
                     let msg = match el {
@@ -241,7 +311,34 @@ where
             name,
             manager,
             output_buffer: Default::default(),
+            coord: None,
+            dropped: 0,
+        }
+    }
+
+    /// Report the number of open windows and of the elements they received, and the elements
+    /// dropped since the last report, to the profiler.
+    fn report_state(&mut self) {
+        if !cfg!(feature = "profiler") {
+            return;
+        }
+        let Some(coord) = self.coord else {
+            return;
+        };
+        if self.dropped > 0 {
+            let dropped = std::mem::take(&mut self.dropped);
+            get_profiler().dropped(coord, &self.name, DropReason::TooLate, dropped);
         }
+        let mut state = WindowState::default();
+        for mgr in self.manager.windows.values() {
+            let windows = mgr.open_windows();
+            if windows > 0 {
+                state.keys += 1;
+                state.windows += windows;
+                state.elements += mgr.buffered_elements();
+            }
+        }
+        get_profiler().window_state(coord, &self.name, state);
     }
 }
 
@@ -280,6 +377,46 @@ where
     }
 }
 
+#[cfg(feature = "timestamp")]
+impl<Key, Out, OperatorChain> WindowedStream<OperatorChain, Out, EventTimeWindow>
+where
+    OperatorChain: Operator<Out = (Key, Out)> + 'static,
+    Key: DataKey,
+    Out: Data,
+{
+    /// Trade the latency of the results of the windows for their completeness, see
+    /// [`LatencyMode`] for the settings used by each mode.
+    ///
+    /// ## Example
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::window::{EventTimeWindow, LatencyMode};
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter([(0, 1), (1, 2), (11, 11)].into_iter());
+    /// let res = s
+    ///     .add_timestamps(|&(ts, _)| ts, |_, &ts| Some(ts))
+    ///     .map(|(_, v)| v)
+    ///     .keyed_window(|_| (), EventTimeWindow::tumbling(10))
+    ///     .latency_mode(LatencyMode::Low)
+    ///     .sum::<i32>()
+    ///     .drop_key()
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// // each window fires early after each element, and then once more when it closes
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, vec![1, 3, 3, 11, 11]);
+    /// ```
+    pub fn latency_mode(self, mode: LatencyMode) -> Self {
+        Self {
+            descr: self.descr.latency_mode(mode),
+            ..self
+        }
+    }
+}
+
 impl<Key: DataKey, Out: Data, OperatorChain> KeyedStream<OperatorChain>
 where
     OperatorChain: Operator<Out = (Key, Out)> + 'static,
@@ -360,4 +497,161 @@ where
             .key_by(|_| ())
             .window(descr)
     }
+
+    /// Partition the stream by key and apply a window to each partition.
+    ///
+    /// This is a shorthand for [`Stream::group_by`] followed by [`KeyedStream::window`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::window::CountWindow;
+    /// # let mut env = StreamContext::new_local();
+    /// let s = env.stream_iter(0..9);
+    /// let res = s
+    ///     .keyed_window(|&n| n % 2, CountWindow::sliding(3, 2))
+    ///     .sum()
+    ///     .collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// let mut res = res.get().unwrap();
+    /// res.sort_unstable();
+    /// assert_eq!(res, vec![(0, 0 + 2 + 4), (0, 4 + 6 + 8), (1, 1 + 3 + 5)]);
+    /// ```
+    pub fn keyed_window<Key, Fk, WinOut, WinDescr>(
+        self,
+        keyer: Fk,
+        descr: WinDescr,
+    ) -> WindowedStream<impl Operator<Out = (Key, Out)>, WinOut, WinDescr>
+    where
+        Key: DataKey,
+        Fk: Fn(&Out) -> Key + Send + Clone + 'static,
+        WinOut: Data,
+        WinDescr: WindowDescription<Out>,
+    {
+        self.group_by(keyer).window(descr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+    use crate::operator::source::IteratorSource;
+    use crate::operator::window::CountWindow;
+    use crate::operator::Operator;
+
+    #[test]
+    fn keyed_window_same_structure() {
+        let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+        let explicit = env
+            .stream(IteratorSource::new(0..10u32))
+            .group_by(|n| n % 3)
+            .window(CountWindow::tumbling(2))
+            .sum::<u32>();
+        let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+        let shorthand = env
+            .stream(IteratorSource::new(0..10u32))
+            .keyed_window(|n| n % 3, CountWindow::tumbling(2))
+            .sum::<u32>();
+
+        let explicit = serde_json::to_value(explicit.0.block.operators.structure()).unwrap();
+        let shorthand = serde_json::to_value(shorthand.0.block.operators.structure()).unwrap();
+        assert_eq!(explicit, shorthand);
+    }
+
+    #[cfg(feature = "profiler")]
+    #[test]
+    fn window_state_in_profiler_report() {
+        use crate::operator::window::aggr::Fold;
+        use crate::operator::window::{KeyedWindowManager, WindowDescription, WindowOperator};
+        use crate::operator::StreamElement;
+        use crate::profiler::{wait_profiler, WindowState};
+        use crate::test::{FakeNetworkTopology, FakeOperator};
+
+        let thread = std::thread::Builder::new()
+            .name("window-state-test".into())
+            .spawn(|| {
+                let mut prev = FakeOperator::new((0..7u32).map(|n| (n % 3, n)));
+                prev.push(StreamElement::FlushBatch);
+                let manager = KeyedWindowManager {
+                    windows: Default::default(),
+                    init: CountWindow::tumbling(4).build(Fold::new(0, |s, n| *s += n)),
+                    _in: Default::default(),
+                    _out: Default::default(),
+                };
+                let mut op = WindowOperator::new(prev, "WindowSum".into(), manager);
+                let mut topology = FakeNetworkTopology::<u32>::new(0, 0);
+                op.setup(&mut topology.metadata());
+                while op.next() != StreamElement::FlushBatch {}
+            })
+            .unwrap();
+        thread.join().unwrap();
+
+        let metrics = wait_profiler()
+            .into_iter()
+            .filter(|r| r.thread_name == "window-state-test")
+            .flat_map(|r| r.buckets)
+            .flat_map(|b| b.window_metrics)
+            .collect::<Vec<_>>();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].operator, "WindowSum");
+        // keys 0 and 1 have 3 elements, key 2 has 2 elements, all in a single open window
+        let expected = WindowState {
+            keys: 3,
+            windows: 3,
+            elements: 7,
+        };
+        assert_eq!(metrics[0].state, expected);
+    }
+
+    #[cfg(feature = "profiler")]
+    #[test]
+    fn too_late_drop_in_profiler_report() {
+        use crate::operator::window::aggr::Fold;
+        use crate::operator::window::{
+            EventTimeWindow, KeyedWindowManager, WindowDescription, WindowOperator,
+        };
+        use crate::operator::StreamElement;
+        use crate::profiler::{wait_profiler, DropReason};
+        use crate::test::{FakeNetworkTopology, FakeOperator};
+
+        let thread = std::thread::Builder::new()
+            .name("too-late-test".into())
+            .spawn(|| {
+                let mut prev = FakeOperator::empty();
+                prev.push(StreamElement::Timestamped(((), 1), 1));
+                prev.push(StreamElement::Watermark(20));
+                // the window [0, 10) closed at 15, the element is too late
+                prev.push(StreamElement::Timestamped(((), 3), 3));
+                prev.push(StreamElement::Timestamped(((), 16), 16));
+                prev.push(StreamElement::FlushBatch);
+                let manager = KeyedWindowManager {
+                    windows: Default::default(),
+                    init: EventTimeWindow::tumbling(10)
+                        .allowed_lateness(5)
+                        .build(Fold::new(0, |s, n| *s += n)),
+                    _in: Default::default(),
+                    _out: Default::default(),
+                };
+                let mut op = WindowOperator::new(prev, "WindowSum".into(), manager);
+                let mut topology = FakeNetworkTopology::<i32>::new(0, 0);
+                op.setup(&mut topology.metadata());
+                while op.next() != StreamElement::FlushBatch {}
+            })
+            .unwrap();
+        thread.join().unwrap();
+
+        let metrics = wait_profiler()
+            .into_iter()
+            .filter(|r| r.thread_name == "too-late-test")
+            .flat_map(|r| r.buckets)
+            .flat_map(|b| b.drop_metrics)
+            .collect::<Vec<_>>();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].operator, "WindowSum");
+        assert_eq!(metrics[0].reason, DropReason::TooLate);
+        assert_eq!(metrics[0].count, 1);
+    }
 }
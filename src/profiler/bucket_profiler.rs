@@ -1,16 +1,18 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crate::network::Coord;
 use crate::scheduler::BlockId;
 use flume::Sender;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde::ser::SerializeSeq;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::block::CoordHasherBuilder;
 
-use super::{get_sender, Profiler};
+use super::{
+    get_sender, DropReason, Histogram, InputWatermark, LatencyHistogram, Profiler, WindowState,
+};
 
 /// The size of a bucket, in milliseconds.
 ///
@@ -30,6 +32,8 @@ pub struct BucketProfiler {
     start: Instant,
     /// The list of all the buckets, sorted by their start time.
     buckets: Vec<MetricsBucket>,
+    /// The labels of the block run by the thread.
+    labels: BTreeMap<String, String>,
     /// The sender to use to send the profiler results back to the main thread.
     sender: Sender<ProfilerResult>,
 }
@@ -43,6 +47,7 @@ impl BucketProfiler {
                 .to_string(),
             start,
             buckets: vec![MetricsBucket::new(0)],
+            labels: Default::default(),
             sender: get_sender(),
         }
     }
@@ -58,6 +63,8 @@ impl BucketProfiler {
         let now = self.now();
         // the timestamp is outside the last bucket, create a new one
         if now >= self.buckets.last().unwrap().start_ms + BUCKET_RESOLUTION_MS {
+            #[cfg(feature = "statsd")]
+            super::statsd::publish(&self.labels, self.buckets.last().unwrap());
             let start = now - now % BUCKET_RESOLUTION_MS;
             self.buckets.push(MetricsBucket::new(start));
         }
@@ -67,10 +74,13 @@ impl BucketProfiler {
 
 impl Drop for BucketProfiler {
     fn drop(&mut self) {
+        #[cfg(feature = "statsd")]
+        super::statsd::publish(&self.labels, self.buckets.last().unwrap());
         self.sender
             .send(ProfilerResult {
                 thread_name: std::mem::take(&mut self.thread_name),
                 buckets: std::mem::take(&mut self.buckets),
+                labels: std::mem::take(&mut self.labels),
             })
             .unwrap();
     }
@@ -108,6 +118,102 @@ impl Profiler for BucketProfiler {
         let now = self.now();
         self.bucket().iteration_metrics.push((leader_block_id, now))
     }
+
+    #[inline]
+    fn window_state(&mut self, coord: Coord, operator: &str, state: WindowState) {
+        let metrics = &mut self.bucket().window_metrics;
+        // keep only the latest state of each operator in the bucket
+        match metrics
+            .iter_mut()
+            .find(|m| m.coord == coord && m.operator == operator)
+        {
+            Some(m) => m.state = state,
+            None => metrics.push(WindowMetrics {
+                coord,
+                operator: operator.to_string(),
+                state,
+            }),
+        }
+    }
+
+    #[inline]
+    fn dropped(&mut self, coord: Coord, operator: &str, reason: DropReason, amount: usize) {
+        let metrics = &mut self.bucket().drop_metrics;
+        match metrics
+            .iter_mut()
+            .find(|m| m.coord == coord && m.reason == reason && m.operator == operator)
+        {
+            Some(m) => m.count += amount,
+            None => metrics.push(DropMetrics {
+                coord,
+                operator: operator.to_string(),
+                reason,
+                count: amount,
+            }),
+        }
+    }
+
+    #[inline]
+    fn watermarks(&mut self, coord: Coord, inputs: Vec<InputWatermark>) {
+        let metrics = &mut self.bucket().watermark_metrics;
+        // keep only the latest watermarks of each block replica in the bucket
+        match metrics.iter_mut().find(|m| m.coord == coord) {
+            Some(m) => m.inputs = inputs,
+            None => metrics.push(WatermarkMetrics { coord, inputs }),
+        }
+    }
+
+    #[inline]
+    fn latency(&mut self, coord: Coord, latency: Duration) {
+        let metrics = &mut self.bucket().latency_metrics;
+        match metrics.iter_mut().find(|m| m.coord == coord) {
+            Some(m) => m.histogram.record(latency),
+            None => {
+                let mut histogram = LatencyHistogram::default();
+                histogram.record(latency);
+                metrics.push(LatencyMetrics { coord, histogram })
+            }
+        }
+    }
+
+    #[inline]
+    fn counter(&mut self, coord: Coord, name: &str, amount: u64) {
+        match self.bucket().custom_metric(coord, name) {
+            Some(CustomMetric::Counter(count)) => *count += amount,
+            Some(CustomMetric::Histogram(_)) => {
+                panic!("the metric {name} is a histogram, it cannot be used as a counter")
+            }
+            None => self.bucket().custom_metrics.push(CustomMetrics {
+                coord,
+                name: name.to_string(),
+                metric: CustomMetric::Counter(amount),
+            }),
+        }
+    }
+
+    #[inline]
+    fn histogram(&mut self, coord: Coord, name: &str, value: u64) {
+        match self.bucket().custom_metric(coord, name) {
+            Some(CustomMetric::Histogram(histogram)) => histogram.record_value(value),
+            Some(CustomMetric::Counter(_)) => {
+                panic!("the metric {name} is a counter, it cannot be used as a histogram")
+            }
+            None => {
+                let mut histogram = Histogram::default();
+                histogram.record_value(value);
+                self.bucket().custom_metrics.push(CustomMetrics {
+                    coord,
+                    name: name.to_string(),
+                    metric: CustomMetric::Histogram(histogram),
+                })
+            }
+        }
+    }
+
+    #[inline]
+    fn labels(&mut self, labels: &BTreeMap<String, String>) {
+        self.labels.clone_from(labels);
+    }
 }
 
 /// A time point.
@@ -122,6 +228,9 @@ pub struct ProfilerResult {
     pub thread_name: String,
     /// The list of collected buckets.
     pub buckets: Vec<MetricsBucket>,
+    /// The labels of the block run by the thread, see [`Stream::label`](crate::Stream::label).
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
 }
 
 /// The available metrics to be collected.
@@ -141,6 +250,69 @@ pub struct LinkMetrics {
     pub bytes_out: usize,
 }
 
+/// The size of the state of a window operator at the end of a bucket.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WindowMetrics {
+    /// The block replica of the operator.
+    pub coord: Coord,
+    /// The name of the window operator.
+    pub operator: String,
+    /// The size of its state.
+    pub state: WindowState,
+}
+
+/// The number of items discarded by an operator in a bucket.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DropMetrics {
+    /// The block replica of the operator.
+    pub coord: Coord,
+    /// The name of the operator.
+    pub operator: String,
+    /// Why the items were discarded.
+    pub reason: DropReason,
+    /// The number of items discarded.
+    pub count: usize,
+}
+
+/// The last watermarks received by a block replica at the end of a bucket.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WatermarkMetrics {
+    /// The block replica receiving the watermarks.
+    pub coord: Coord,
+    /// The last watermark from each of its previous replicas.
+    pub inputs: Vec<InputWatermark>,
+}
+
+/// The end-to-end latencies of the elements that reached a block replica in a bucket.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LatencyMetrics {
+    /// The block replica measuring the latencies.
+    pub coord: Coord,
+    /// The latencies of the elements.
+    pub histogram: LatencyHistogram,
+}
+
+/// The value of a metric defined by the user.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CustomMetric {
+    /// The sum of the increments of a counter.
+    Counter(u64),
+    /// The values recorded by a histogram.
+    Histogram(Histogram),
+}
+
+/// A metric defined by the user in a block replica, aggregated in a bucket.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CustomMetrics {
+    /// The block replica recording the metric.
+    pub coord: Coord,
+    /// The name of the metric.
+    pub name: String,
+    /// The value of the metric.
+    pub metric: CustomMetric,
+}
+
 /// A bucket with the profiler metrics.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MetricsBucket {
@@ -155,6 +327,26 @@ pub struct MetricsBucket {
     /// The time point of the end of an iteration, with the id of the leader block that manages that
     /// iteration.
     pub iteration_metrics: Vec<(BlockId, TimePoint)>,
+
+    /// The latest size of the state of the window operators.
+    #[serde(default)]
+    pub window_metrics: Vec<WindowMetrics>,
+
+    /// The number of items discarded by the operators.
+    #[serde(default)]
+    pub drop_metrics: Vec<DropMetrics>,
+
+    /// The latest watermarks received by the block replicas, with the inputs holding them back.
+    #[serde(default)]
+    pub watermark_metrics: Vec<WatermarkMetrics>,
+
+    /// The end-to-end latencies of the elements, measured from their ingestion.
+    #[serde(default)]
+    pub latency_metrics: Vec<LatencyMetrics>,
+
+    /// The metrics defined by the user.
+    #[serde(default)]
+    pub custom_metrics: Vec<CustomMetrics>,
 }
 
 impl MetricsBucket {
@@ -165,6 +357,15 @@ impl MetricsBucket {
             ..Default::default()
         }
     }
+
+    /// The metric defined by the user with the given name in a block replica, if recorded in
+    /// this bucket.
+    fn custom_metric(&mut self, coord: Coord, name: &str) -> Option<&mut CustomMetric> {
+        self.custom_metrics
+            .iter_mut()
+            .find(|m| m.coord == coord && m.name == name)
+            .map(|m| &mut m.metric)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -201,3 +402,40 @@ where
         .map(|e| ((e.from, e.to), e.value))
         .collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::network::Coord;
+    use crate::profiler::{get_profiler, wait_profiler, Profiler};
+
+    #[test]
+    fn labels_in_profiler_result() {
+        let labels = BTreeMap::from([("stage".to_string(), "enrichment".to_string())]);
+        let thread_labels = labels.clone();
+        std::thread::Builder::new()
+            .name("labels-test".into())
+            .spawn(move || {
+                get_profiler().labels(&thread_labels);
+                get_profiler().items_in(Coord::new(0, 0, 0), Coord::new(1, 0, 0), 10);
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        let results = wait_profiler()
+            .into_iter()
+            .filter(|r| r.thread_name == "labels-test")
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].labels, labels);
+        let items: usize = results[0]
+            .buckets
+            .iter()
+            .flat_map(|b| b.link_metrics.values())
+            .map(|m| m.items_in)
+            .sum();
+        assert_eq!(items, 10);
+    }
+}
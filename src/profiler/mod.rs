@@ -1,13 +1,21 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "profiler")]
 pub use with_profiler::*;
 #[cfg(not(feature = "profiler"))]
 pub use without_profiler::*;
 
-use crate::{block::BlockStructure, network::Coord, scheduler::BlockId};
+use crate::{block::BlockStructure, network::Coord, operator::Timestamp, scheduler::BlockId};
 
 #[cfg(feature = "profiler")]
-mod bucket_profiler;
+pub(crate) mod bucket_profiler;
+#[cfg(feature = "statsd")]
+mod statsd;
+
+#[cfg(feature = "statsd")]
+pub use statsd::StatsdReporter;
 
 pub const TRACING_PREFIX: &str = "__renoir_TRACING_DATA__";
 
@@ -26,6 +34,158 @@ pub trait Profiler {
     fn net_bytes_out(&mut self, from: Coord, to: Coord, amount: usize);
     /// Mark the end of an iteration.
     fn iteration_boundary(&mut self, leader_block_id: BlockId);
+    /// Record the current size of the state of a window operator of a block.
+    fn window_state(&mut self, coord: Coord, operator: &str, state: WindowState);
+    /// Increase the number of items discarded by an operator of a block.
+    fn dropped(&mut self, coord: Coord, operator: &str, reason: DropReason, amount: usize);
+    /// Record the last watermark received by a block from each of the previous replicas.
+    fn watermarks(&mut self, coord: Coord, inputs: Vec<InputWatermark>);
+    /// Record the end-to-end latency of an element that reached an operator of a block.
+    fn latency(&mut self, coord: Coord, latency: Duration);
+    /// Increase a counter defined by the user in a block.
+    fn counter(&mut self, coord: Coord, name: &str, amount: u64);
+    /// Record a value in a histogram defined by the user in a block.
+    fn histogram(&mut self, coord: Coord, name: &str, value: u64);
+    /// Set the labels of the block run by the current thread, reported with its metrics.
+    fn labels(&mut self, labels: &BTreeMap<String, String>);
+}
+
+/// Why an operator discarded some items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropReason {
+    /// The item arrived after the watermark had passed the end of its windows and their allowed
+    /// lateness.
+    TooLate,
+    /// The item was dropped by a source that could not keep up with the pipeline.
+    Backpressure,
+    /// The item failed a validation.
+    Invalid,
+}
+
+/// The size of the state of a window operator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowState {
+    /// The number of keys with at least an open window.
+    pub keys: usize,
+    /// The number of open windows, across all the keys.
+    pub windows: usize,
+    /// The number of elements received by the open windows, an element is counted once for each
+    /// window it belongs to.
+    pub elements: usize,
+}
+
+/// The last watermark received from a previous replica.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputWatermark {
+    /// The previous replica.
+    pub from: Coord,
+    /// Its last watermark, if any.
+    pub watermark: Option<Timestamp>,
+    /// Whether it is ignored by the frontier, since it sent nothing for longer than the idleness
+    /// timeout.
+    pub idle: bool,
+    /// Whether it is holding the frontier back.
+    pub constraining: bool,
+}
+
+/// The number of sub-buckets of each power of two of a [`Histogram`].
+#[cfg_attr(not(feature = "profiler"), allow(dead_code))]
+const HISTOGRAM_SUB_BUCKETS: u64 = 8;
+
+/// A histogram of non-negative integer values.
+///
+/// The buckets grow exponentially: each power of two is split into 8 buckets of the same width,
+/// so a value is counted with a relative error of at most 12.5%.
+#[cfg_attr(not(feature = "profiler"), allow(dead_code))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Histogram {
+    /// The number of values in each bucket.
+    counts: Vec<u64>,
+}
+
+/// A histogram of latencies, with a resolution of a microsecond.
+#[cfg_attr(not(feature = "profiler"), allow(dead_code))]
+pub type LatencyHistogram = Histogram;
+
+#[cfg_attr(not(feature = "profiler"), allow(dead_code))]
+impl Histogram {
+    /// The index of the bucket containing `value`.
+    fn bucket_index(value: u64) -> usize {
+        if value < HISTOGRAM_SUB_BUCKETS {
+            return value as usize;
+        }
+        let exp = 63 - value.leading_zeros() as u64;
+        let mantissa = (value >> (exp - 3)) & (HISTOGRAM_SUB_BUCKETS - 1);
+        ((exp - 2) * HISTOGRAM_SUB_BUCKETS + mantissa) as usize
+    }
+
+    /// The smallest value in the bucket at `index`.
+    fn bucket_start(index: usize) -> u64 {
+        let index = index as u64;
+        if index < HISTOGRAM_SUB_BUCKETS {
+            return index;
+        }
+        let exp = index / HISTOGRAM_SUB_BUCKETS + 2;
+        (HISTOGRAM_SUB_BUCKETS + index % HISTOGRAM_SUB_BUCKETS) << (exp - 3)
+    }
+
+    /// Add a value to the histogram.
+    pub fn record_value(&mut self, value: u64) {
+        let index = Self::bucket_index(value);
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += 1;
+    }
+
+    /// Add a latency to the histogram, in microseconds.
+    pub fn record(&mut self, latency: Duration) {
+        self.record_value(latency.as_micros().min(u64::MAX as u128) as u64);
+    }
+
+    /// Add all the values of `other` to the histogram.
+    pub fn merge(&mut self, other: &Histogram) {
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+    }
+
+    /// The number of values in the histogram.
+    pub fn len(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Whether the histogram has no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The value below which a fraction `q` of the values fall, rounded down to the start of its
+    /// bucket, or `None` if the histogram is empty.
+    pub fn quantile_value(&self, q: f64) -> Option<u64> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * len as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(Self::bucket_start(index));
+            }
+        }
+        unreachable!("the rank is at most the number of values")
+    }
+
+    /// Like [`Histogram::quantile_value`], for a histogram of latencies.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        self.quantile_value(q).map(Duration::from_micros)
+    }
 }
 
 /// Tracing information of the current execution.
@@ -56,6 +216,9 @@ pub fn log_trace(structures: Vec<(Coord, BlockStructure)>, profilers: Vec<Profil
         return;
     }
 
+    #[cfg(feature = "profiler")]
+    log_latencies(&profilers);
+
     use std::io::Write as _;
     let data = TracingData {
         structures,
@@ -122,6 +285,21 @@ mod without_profiler {
         fn net_bytes_out(&mut self, _from: Coord, _to: Coord, _amount: usize) {}
         #[inline(always)]
         fn iteration_boundary(&mut self, _leader_block_id: BlockId) {}
+        #[inline(always)]
+        fn window_state(&mut self, _coord: Coord, _operator: &str, _state: WindowState) {}
+        #[inline(always)]
+        fn dropped(&mut self, _coord: Coord, _operator: &str, _reason: DropReason, _amount: usize) {
+        }
+        #[inline(always)]
+        fn watermarks(&mut self, _coord: Coord, _inputs: Vec<InputWatermark>) {}
+        #[inline(always)]
+        fn latency(&mut self, _coord: Coord, _latency: Duration) {}
+        #[inline(always)]
+        fn counter(&mut self, _coord: Coord, _name: &str, _amount: u64) {}
+        #[inline(always)]
+        fn histogram(&mut self, _coord: Coord, _name: &str, _value: u64) {}
+        #[inline(always)]
+        fn labels(&mut self, _labels: &BTreeMap<String, String>) {}
     }
 
     /// Get a fake profiler that does nothing.
@@ -140,9 +318,12 @@ mod without_profiler {
 mod with_profiler {
     use once_cell::sync::Lazy;
     use std::cell::UnsafeCell;
+    use std::collections::BTreeMap;
     use std::time::Instant;
 
     use super::bucket_profiler::BucketProfiler;
+    use super::LatencyHistogram;
+    use crate::scheduler::BlockId;
     use flume::{Receiver, Sender};
 
     pub use super::bucket_profiler::ProfilerResult;
@@ -182,4 +363,32 @@ mod with_profiler {
     pub fn wait_profiler() -> Vec<ProfilerResult> {
         CHANNEL.1.drain().collect()
     }
+
+    /// Log the quantiles of the end-to-end latencies recorded by each block.
+    pub(crate) fn log_latencies(profilers: &[ProfilerResult]) {
+        let mut latencies: BTreeMap<BlockId, LatencyHistogram> = BTreeMap::new();
+        let metrics = profilers
+            .iter()
+            .flat_map(|p| &p.buckets)
+            .flat_map(|b| &b.latency_metrics);
+        for m in metrics {
+            latencies
+                .entry(m.coord.block_id)
+                .or_default()
+                .merge(&m.histogram);
+        }
+        for (block_id, histogram) in latencies {
+            if histogram.is_empty() {
+                continue;
+            }
+            let quantile = |q| histogram.quantile(q).unwrap();
+            log::info!(
+                "latency of block {block_id}: p50 {:?}, p99 {:?}, max {:?} ({} elements)",
+                quantile(0.5),
+                quantile(0.99),
+                quantile(1.0),
+                histogram.len()
+            );
+        }
+    }
 }
@@ -0,0 +1,387 @@
+//! Push the metrics of the profiler to a StatsD or DogStatsD server.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+
+use super::bucket_profiler::{CustomMetric, MetricsBucket};
+use super::Histogram;
+use crate::network::Coord;
+
+/// The maximum size of a packet sent to the server, so that it fits in the MTU of most networks.
+const MAX_PACKET_SIZE: usize = 1432;
+
+/// The number of reporters running: the buckets are kept for them only if there is one.
+static REPORTERS: AtomicUsize = AtomicUsize::new(0);
+
+/// A bucket closed by a profiler, with the labels of its block.
+type PendingBucket = (BTreeMap<String, String>, MetricsBucket);
+
+/// The buckets closed by the profilers since the last flush.
+static PENDING: Lazy<Mutex<Vec<PendingBucket>>> = Lazy::new(Default::default);
+
+/// Keep a bucket closed by a profiler for the next flush, if a reporter is running.
+pub(crate) fn publish(labels: &BTreeMap<String, String>, bucket: &MetricsBucket) {
+    if REPORTERS.load(Ordering::Relaxed) > 0 {
+        PENDING.lock().push((labels.clone(), bucket.clone()));
+    }
+}
+
+/// Reporter pushing the metrics of the profiler to a StatsD server over UDP while the job runs.
+///
+/// Every flush interval the metrics recorded since the previous flush are sent as StatsD lines:
+///
+/// - `items_in`, `items_out`, `net_bytes_in`, `net_bytes_out`: counters of each block replica;
+/// - `dropped`: a counter of the items discarded by each operator, with its `reason`;
+/// - `latency_us`: a histogram of the end-to-end latencies, see
+///   [`Stream::record_latency`](crate::Stream::record_latency);
+/// - the counters and histograms of [`Stream::tap_metrics`](crate::Stream::tap_metrics).
+///
+/// The names have a prefix, `renoir.` by default. Each line is tagged, in the DogStatsD format,
+/// with the `block`, `host` and `replica` of the metric, the labels of the block (see
+/// [`Stream::label`](crate::Stream::label)) and the tags of the reporter. Use
+/// [`StatsdReporter::without_tags`] for a server that does not support the tags.
+///
+/// The profiler records the metrics in buckets of 50 milliseconds, which are pushed once they are
+/// closed, so the metrics of a block that stops receiving items are pushed when it receives the
+/// next one or when it ends. The metrics are recorded only with the `profiler` feature, which is
+/// enabled by the `statsd` feature.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use renoir::{StatsdReporter, StreamContext};
+/// let env = StreamContext::new_local();
+/// env.statsd_reporter(
+///     StatsdReporter::new("127.0.0.1:8125")
+///         .flush_interval(Duration::from_secs(1))
+///         .tag("job", "ingestion"),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct StatsdReporter {
+    address: String,
+    prefix: String,
+    flush_interval: Duration,
+    tags: Vec<(String, String)>,
+    with_tags: bool,
+}
+
+impl StatsdReporter {
+    /// A reporter sending the metrics to the server at `address`, like `127.0.0.1:8125`, every 10
+    /// seconds.
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            prefix: "renoir.".to_string(),
+            flush_interval: Duration::from_secs(10),
+            tags: Vec::new(),
+            with_tags: true,
+        }
+    }
+
+    /// Set the prefix of the names of the metrics.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Set how often the metrics are sent.
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Add a tag to all the metrics.
+    pub fn tag(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push((name.into(), value.into()));
+        self
+    }
+
+    /// Send the lines without the tags, for the StatsD servers that do not support them.
+    pub fn without_tags(mut self) -> Self {
+        self.with_tags = false;
+        self
+    }
+
+    /// Start sending the metrics in a background thread, until the handle is stopped.
+    pub(crate) fn start(self) -> StatsdHandle {
+        REPORTERS.fetch_add(1, Ordering::Relaxed);
+        let (stop, stopped) = std::sync::mpsc::channel::<()>();
+        let thread = std::thread::Builder::new()
+            .name("statsd-reporter".into())
+            .spawn(move || {
+                let socket = self.connect();
+                loop {
+                    let stop = !matches!(
+                        stopped.recv_timeout(self.flush_interval),
+                        Err(RecvTimeoutError::Timeout)
+                    );
+                    if let Some(socket) = &socket {
+                        self.flush(socket);
+                    }
+                    if stop {
+                        break;
+                    }
+                }
+                REPORTERS.fetch_sub(1, Ordering::Relaxed);
+            })
+            .unwrap();
+        StatsdHandle { stop, thread }
+    }
+
+    fn connect(&self) -> Option<UdpSocket> {
+        let connect = || -> std::io::Result<UdpSocket> {
+            let address = self.address.to_socket_addrs()?.next().ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "no address found")
+            })?;
+            let local: SocketAddr = match address {
+                SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+                SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+            };
+            let socket = UdpSocket::bind(local)?;
+            socket.connect(address)?;
+            Ok(socket)
+        };
+        match connect() {
+            Ok(socket) => Some(socket),
+            Err(e) => {
+                log::warn!("statsd: cannot connect to {}: {e}", self.address);
+                None
+            }
+        }
+    }
+
+    /// Send the buckets closed since the last flush.
+    fn flush(&self, socket: &UdpSocket) {
+        let pending = std::mem::take(&mut *PENDING.lock());
+        let mut lines = Vec::new();
+        for (labels, bucket) in &pending {
+            self.format_bucket(labels, bucket, &mut lines);
+        }
+
+        let mut packet = String::new();
+        for line in lines {
+            if !packet.is_empty() && packet.len() + 1 + line.len() > MAX_PACKET_SIZE {
+                send(socket, &packet);
+                packet.clear();
+            }
+            if !packet.is_empty() {
+                packet.push('\n');
+            }
+            packet.push_str(&line);
+        }
+        if !packet.is_empty() {
+            send(socket, &packet);
+        }
+    }
+
+    /// Append the lines with the metrics of a bucket to `lines`.
+    fn format_bucket(
+        &self,
+        labels: &BTreeMap<String, String>,
+        bucket: &MetricsBucket,
+        lines: &mut Vec<String>,
+    ) {
+        let mut push = |name: &str, value: &str, coord: Coord, extra: &[(&str, &str)]| {
+            let mut line = format!("{}{name}:{value}", self.prefix);
+            if self.with_tags {
+                line.push_str("|#");
+                let coord_tags = [
+                    ("block", coord.block_id.to_string()),
+                    ("host", coord.host_id.to_string()),
+                    ("replica", coord.replica_id.to_string()),
+                ];
+                let tags = self
+                    .tags
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str()))
+                    .chain(labels.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+                    .chain(coord_tags.iter().map(|(k, v)| (*k, v.as_str())))
+                    .chain(extra.iter().copied());
+                for (i, (k, v)) in tags.enumerate() {
+                    if i > 0 {
+                        line.push(',');
+                    }
+                    write!(line, "{k}:{v}").unwrap();
+                }
+            }
+            lines.push(line);
+        };
+
+        // the links are counted by the replica receiving and by the one sending the items
+        let mut links: BTreeMap<Coord, [usize; 4]> = BTreeMap::new();
+        for (&(from, to), m) in &bucket.link_metrics {
+            let received = links.entry(to).or_default();
+            received[0] += m.items_in;
+            received[2] += m.bytes_in;
+            let sent = links.entry(from).or_default();
+            sent[1] += m.items_out;
+            sent[3] += m.bytes_out;
+        }
+        let names = ["items_in", "items_out", "net_bytes_in", "net_bytes_out"];
+        for (coord, counts) in links {
+            for (name, count) in names.iter().zip(counts) {
+                if count > 0 {
+                    push(name, &format!("{count}|c"), coord, &[]);
+                }
+            }
+        }
+        for m in &bucket.drop_metrics {
+            let reason = serde_json::to_value(m.reason).unwrap();
+            let extra = [
+                ("operator", m.operator.as_str()),
+                ("reason", reason.as_str().unwrap()),
+            ];
+            push("dropped", &format!("{}|c", m.count), m.coord, &extra);
+        }
+        for m in &bucket.latency_metrics {
+            for value in histogram_values(&m.histogram) {
+                push("latency_us", &value, m.coord, &[]);
+            }
+        }
+        for m in &bucket.custom_metrics {
+            match &m.metric {
+                CustomMetric::Counter(count) => push(&m.name, &format!("{count}|c"), m.coord, &[]),
+                CustomMetric::Histogram(histogram) => {
+                    for value in histogram_values(histogram) {
+                        push(&m.name, &value, m.coord, &[]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The values of a histogram as StatsD histogram values: the start of each bucket, with the
+/// sample rate giving its count.
+fn histogram_values(histogram: &Histogram) -> impl Iterator<Item = String> + '_ {
+    histogram
+        .counts
+        .iter()
+        .enumerate()
+        .filter(|(_, &count)| count > 0)
+        .map(|(index, &count)| {
+            let value = Histogram::bucket_start(index);
+            if count == 1 {
+                format!("{value}|h")
+            } else {
+                format!("{value}|h|@{}", 1.0 / count as f64)
+            }
+        })
+}
+
+fn send(socket: &UdpSocket, packet: &str) {
+    if let Err(e) = socket.send(packet.as_bytes()) {
+        log::warn!("statsd: cannot send the metrics: {e}");
+    }
+}
+
+/// The handle of a running [`StatsdReporter`].
+pub(crate) struct StatsdHandle {
+    stop: Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl StatsdHandle {
+    /// Send the last metrics and stop the reporter.
+    pub(crate) fn stop(self) {
+        let _ = self.stop.send(());
+        self.thread.join().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::net::UdpSocket;
+    use std::time::{Duration, Instant};
+
+    use super::{publish, StatsdReporter};
+    use crate::network::Coord;
+    use crate::profiler::bucket_profiler::{
+        CustomMetric, CustomMetrics, LinkMetrics, MetricsBucket,
+    };
+
+    fn bucket() -> MetricsBucket {
+        let mut bucket = MetricsBucket::new(0);
+        bucket.link_metrics.insert(
+            (Coord::new(0, 0, 0), Coord::new(1, 0, 1)),
+            LinkMetrics {
+                items_in: 10,
+                items_out: 7,
+                ..Default::default()
+            },
+        );
+        bucket.custom_metrics.push(CustomMetrics {
+            coord: Coord::new(1, 0, 1),
+            name: "errors".to_string(),
+            metric: CustomMetric::Counter(3),
+        });
+        bucket
+    }
+
+    #[test]
+    fn statsd_lines() {
+        let labels = BTreeMap::from([("stage".to_string(), "parse".to_string())]);
+        let reporter = StatsdReporter::new("127.0.0.1:8125").tag("job", "test");
+        let mut lines = Vec::new();
+        reporter.format_bucket(&labels, &bucket(), &mut lines);
+        assert_eq!(
+            lines,
+            vec![
+                "renoir.items_out:7|c|#job:test,stage:parse,block:0,host:0,replica:0",
+                "renoir.items_in:10|c|#job:test,stage:parse,block:1,host:0,replica:1",
+                "renoir.errors:3|c|#job:test,stage:parse,block:1,host:0,replica:1",
+            ]
+        );
+
+        let mut lines = Vec::new();
+        let reporter = reporter.without_tags().prefix("");
+        reporter.format_bucket(&labels, &bucket(), &mut lines);
+        assert_eq!(lines, vec!["items_out:7|c", "items_in:10|c", "errors:3|c"]);
+    }
+
+    #[test]
+    fn statsd_flush_interval() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let reporter = StatsdReporter::new(server.local_addr().unwrap().to_string())
+            .flush_interval(Duration::from_millis(200))
+            .without_tags();
+
+        let start = Instant::now();
+        let handle = reporter.start();
+        publish(&BTreeMap::new(), &bucket());
+        // the other tests running in the meantime may publish their metrics too
+        let mut lines = Vec::new();
+        let mut elapsed = None;
+        let mut buf = [0; 1500];
+        while !lines.contains(&"renoir.errors:3|c".to_string()) {
+            let len = server.recv(&mut buf).unwrap();
+            elapsed.get_or_insert(start.elapsed());
+            let packet = std::str::from_utf8(&buf[..len]).unwrap();
+            lines.extend(packet.lines().map(String::from));
+        }
+        handle.stop();
+
+        for line in ["renoir.items_out:7|c", "renoir.items_in:10|c"] {
+            assert!(lines.contains(&line.to_string()), "{line} not in {lines:?}");
+        }
+        let elapsed = elapsed.unwrap();
+        assert!(
+            elapsed >= Duration::from_millis(200) && elapsed < Duration::from_secs(1),
+            "the metrics were sent after {elapsed:?}"
+        );
+    }
+}
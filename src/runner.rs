@@ -12,11 +12,11 @@ use base64::{engine::general_purpose::URL_SAFE_NO_PAD as B64, Engine};
 
 use sha2::Digest;
 #[cfg(feature = "ssh")]
-use ssh2::Session;
+use ssh2::{CheckResult, HostKeyType, KnownHostFileKind, Session};
 
 use crate::config::CONFIG_ENV_VAR;
 use crate::config::HOST_ID_ENV_VAR;
-use crate::config::{HostConfig, RemoteConfig};
+use crate::config::{HostConfig, HostKeyCheck, RemoteConfig};
 use crate::profiler::try_parse_trace;
 use crate::profiler::TracingData;
 use crate::scheduler::HostId;
@@ -24,6 +24,9 @@ use crate::scheduler::HostId;
 /// Size of the buffer usedahash to send the executable file via SCP.
 pub(crate) const SCP_BUFFER_SIZE: usize = 512 * 1024;
 
+/// Serializes the updates of the known hosts files, the workers are spawned concurrently.
+static KNOWN_HOSTS_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
 /// Execution results returned by a remote worker.
 struct HostExecutionResult {
     /// Tracing data if renoir is compiled with tracing enabled.
@@ -66,6 +69,9 @@ pub(crate) fn spawn_remote_workers(config: RemoteConfig) {
     }
 
     // from now we are sure this is the process that should spawn the remote workers
+    if let Err(e) = config.resolve_hosts() {
+        panic!("{e}");
+    }
     info!("starting {} remote workers", config.hosts.len());
 
     let start = Instant::now();
@@ -168,6 +174,26 @@ fn remote_worker(
         address
     );
 
+    // verify the identity of the host before sending it the credentials
+    let known_hosts = host
+        .ssh
+        .known_hosts
+        .clone()
+        .unwrap_or_else(default_known_hosts);
+    let host_key = session
+        .host_key()
+        .expect("the SSH server did not send its host key");
+    if let Err(e) = verify_host_key(
+        &session,
+        &host.address,
+        host.ssh.ssh_port,
+        host_key,
+        host.ssh.host_key_check,
+        &known_hosts,
+    ) {
+        panic!("Failed to verify the SSH host key of host {host_id}: {e}");
+    }
+
     // try to authenticate
     let username = host.ssh.username.as_ref().unwrap().as_str();
     match (host.ssh.password.as_ref(), host.ssh.key_file.as_ref()) {
@@ -284,6 +310,95 @@ fn remote_worker(
 }
 
 /// Execute a command remotely and return the standard output and the exit code.
+/// The known hosts file of the current user, `~/.ssh/known_hosts`.
+fn default_known_hosts() -> PathBuf {
+    let home = std::env::var_os("HOME").expect("HOME is not set, set the known_hosts path");
+    Path::new(&home).join(".ssh").join("known_hosts")
+}
+
+/// Verify the key presented by a remote host against the known hosts file, following `policy`.
+///
+/// With [`HostKeyCheck::AcceptNew`] the key of an unknown host is appended to the file, so that
+/// the next connections are verified against it.
+fn verify_host_key(
+    session: &Session,
+    address: &str,
+    port: u16,
+    (key, key_type): (&[u8], HostKeyType),
+    policy: HostKeyCheck,
+    known_hosts: &Path,
+) -> Result<(), String> {
+    if policy == HostKeyCheck::Accept {
+        warn!(
+            "NOT verifying the SSH host key of {address}:{port}, the connection can be \
+            intercepted by anyone impersonating the host"
+        );
+        return Ok(());
+    }
+
+    let _lock = KNOWN_HOSTS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut hosts = session.known_hosts().map_err(|e| e.to_string())?;
+    if known_hosts.exists() {
+        hosts
+            .read_file(known_hosts, KnownHostFileKind::OpenSSH)
+            .map_err(|e| format!("cannot read {}: {e}", known_hosts.display()))?;
+    }
+    match hosts.check_port(address, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => Err(format!(
+            "the key of {address}:{port} does not match the one in {}, the host may be \
+            impersonated",
+            known_hosts.display()
+        )),
+        CheckResult::Failure => Err(format!(
+            "cannot check the key of {address}:{port} against {}",
+            known_hosts.display()
+        )),
+        CheckResult::NotFound if policy == HostKeyCheck::Strict => Err(format!(
+            "{address}:{port} is not in the known hosts file {}, add its key (e.g. with \
+            ssh-keyscan) or set host_key_check to accept_new",
+            known_hosts.display()
+        )),
+        CheckResult::NotFound => {
+            // append only the new entry, leaving the rest of the file untouched
+            let name = if port == 22 {
+                address.to_string()
+            } else {
+                format!("[{address}]:{port}")
+            };
+            let mut new = session.known_hosts().map_err(|e| e.to_string())?;
+            // libssh2 crashes adding an entry with an empty comment
+            new.add(&name, key, "added by renoir", key_type.into())
+                .map_err(|e| e.to_string())?;
+            let entry = new.hosts().map_err(|e| e.to_string())?;
+            let line = new
+                .write_string(&entry[0], KnownHostFileKind::OpenSSH)
+                .map_err(|e| e.to_string())?;
+
+            let write = || -> std::io::Result<()> {
+                if let Some(dir) = known_hosts.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let previous = std::fs::read(known_hosts).unwrap_or_default();
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(known_hosts)?;
+                if previous.last().is_some_and(|&c| c != b'\n') {
+                    writeln!(file)?;
+                }
+                writeln!(file, "{}", line.trim_end())
+            };
+            write().map_err(|e| format!("cannot write {}: {e}", known_hosts.display()))?;
+            info!(
+                "added the SSH host key of {address}:{port} to {}",
+                known_hosts.display()
+            );
+            Ok(())
+        }
+    }
+}
+
 fn run_remote_command(session: &mut Session, command: &str) -> (String, i32) {
     log::debug!("remote command: {}", command);
     let mut channel = session.channel_session().unwrap();
@@ -357,7 +472,8 @@ fn send_executable(
 
 /// Build the command for running the remote worker.
 ///
-/// This will export all the required variables before executing the binary.
+/// This will export all the required variables before executing the binary, or the
+/// `worker_command_template` of the config if set.
 fn build_remote_command(
     host_id: HostId,
     config: &RemoteConfig,
@@ -371,30 +487,149 @@ fn build_remote_command(
         .map(|arg| shell_escape::escape(arg.into()))
         .collect::<Vec<_>>()
         .join(" ");
-    let perf_cmd = if let Some(path) = perf_path.as_ref() {
-        warn!("Running remote process on host {} with perf enabled. This may cause performance regressions.", host_id);
-        format!(
-            "perf record --call-graph dwarf -o {} -- ",
-            shell_escape::escape(path.to_str().expect("non UTF-8 perf path").into())
-        )
-    } else {
-        "".to_string()
+    let binary_path = binary_path.to_str().expect("non UTF-8 executable path");
+    let (exports, command) = match &config.worker_command_template {
+        Some(template) => {
+            if perf_path.is_some() {
+                warn!("Ignoring the perf path of host {host_id}, a worker command template is set");
+            }
+            // the variables passed by the template do not need to be exported
+            let exports = [
+                (HOST_ID_ENV_VAR, "{host_id}", host_id.to_string()),
+                (CONFIG_ENV_VAR, "{config}", config_str.to_string()),
+            ]
+            .into_iter()
+            .filter(|(_, placeholder, _)| !template.contains(placeholder))
+            .map(|(var, _, value)| format!("export {var}={value};\n"))
+            .collect::<String>();
+            let command = template
+                .replace("{bin}", binary_path)
+                .replace("{args}", &args)
+                .replace("{host_id}", &host_id.to_string())
+                .replace("{config}", &config_str);
+            (exports, command)
+        }
+        None => {
+            let perf_cmd = if let Some(path) = perf_path.as_ref() {
+                warn!("Running remote process on host {} with perf enabled. This may cause performance regressions.", host_id);
+                format!(
+                    "perf record --call-graph dwarf -o {} -- ",
+                    shell_escape::escape(path.to_str().expect("non UTF-8 perf path").into())
+                )
+            } else {
+                "".to_string()
+            };
+            let exports = format!(
+                "export {HOST_ID_ENV_VAR}={host_id};\nexport {CONFIG_ENV_VAR}={config_str};\n"
+            );
+            (exports, format!("{perf_cmd}{binary_path} {args}"))
+        }
     };
     format!(
-        "export {host_id_env}={host_id};
-export {config_env}={config};
-export RUST_LOG={rust_log};
+        "{exports}export RUST_LOG={rust_log};
 export RUST_BACKTRACE={rust_backtrace};
 export RUST_LOG_STYLE=always;
-{perf_cmd}{binary_path} {args}",
-        host_id_env = HOST_ID_ENV_VAR,
-        host_id = host_id,
-        config_env = CONFIG_ENV_VAR,
-        config = config_str,
-        perf_cmd = perf_cmd,
-        binary_path = binary_path.to_str().expect("non UTF-8 executable path"),
-        args = args,
+{command}",
         rust_log = std::env::var("RUST_LOG").unwrap_or_default(),
         rust_backtrace = std::env::var("RUST_BACKTRACE").unwrap_or_default(),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use ssh2::{HostKeyType, Session};
+
+    use crate::config::{
+        ConfigBuilder, HostKeyCheck, RuntimeConfig, CONFIG_ENV_VAR, HOST_ID_ENV_VAR,
+    };
+
+    use super::{build_remote_command, verify_host_key};
+
+    fn remote_config(template: Option<&str>) -> crate::config::RemoteConfig {
+        let mut builder = ConfigBuilder::new_remote();
+        builder
+            .parse_toml_str(
+                r#"
+[[host]]
+address = "127.0.0.1"
+base_port = 21800
+num_cores = 1
+"#,
+            )
+            .unwrap();
+        if let Some(template) = template {
+            builder.worker_command_template(template);
+        }
+        match builder.build().unwrap() {
+            RuntimeConfig::Remote(remote) => remote,
+            RuntimeConfig::Local(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn default_worker_command() {
+        let config = remote_config(None);
+        let command = build_remote_command(3, &config, Path::new("/tmp/renoir/job"), &None);
+        assert!(command.contains(&format!("export {HOST_ID_ENV_VAR}=3;")));
+        assert!(command.contains(&format!("export {CONFIG_ENV_VAR}=")));
+        let last = command.lines().last().unwrap();
+        assert!(last.starts_with("/tmp/renoir/job"), "{last}");
+    }
+
+    #[test]
+    fn worker_command_template() {
+        let template =
+            "docker run -e NOIR_HOST_ID={host_id} -e NOIR_CONFIG -v {bin}:/job image /job";
+        let config = remote_config(Some(template));
+        let command = build_remote_command(3, &config, Path::new("/tmp/renoir/job"), &None);
+        // the host id is passed by the template, the config is still exported
+        assert!(!command.contains(&format!("export {HOST_ID_ENV_VAR}")));
+        assert!(command.contains(&format!("export {CONFIG_ENV_VAR}=")));
+        let last = command.lines().last().unwrap();
+        assert_eq!(
+            last,
+            "docker run -e NOIR_HOST_ID=3 -e NOIR_CONFIG -v /tmp/renoir/job:/job image /job"
+        );
+
+        let config = remote_config(Some("systemd-run --wait -E NOIR_CONFIG={config} {bin}"));
+        let command = build_remote_command(0, &config, Path::new("/tmp/renoir/job"), &None);
+        assert!(command.contains(&format!("export {HOST_ID_ENV_VAR}=0;")));
+        assert!(!command.contains(&format!("export {CONFIG_ENV_VAR}")));
+        let config_toml = shell_escape::escape(toml::to_string(&config).unwrap().into());
+        assert!(command.ends_with(&format!(
+            "systemd-run --wait -E NOIR_CONFIG={config_toml} /tmp/renoir/job"
+        )));
+    }
+
+    #[test]
+    fn host_key_check_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let known_hosts = dir.path().join("ssh").join("known_hosts");
+        let session = Session::new().unwrap();
+        let key = (&b"renoir-host-key"[..], HostKeyType::Ed255219);
+        let other = (&b"impostor-host-key"[..], HostKeyType::Ed255219);
+        let verify =
+            |key, policy| verify_host_key(&session, "host1.lan", 2222, key, policy, &known_hosts);
+
+        let err = verify(key, HostKeyCheck::Strict).unwrap_err();
+        assert!(err.contains("not in the known hosts file"), "{err}");
+        assert!(!known_hosts.exists());
+
+        // the key of the new host is recorded, then verified by the strict policy
+        verify(key, HostKeyCheck::AcceptNew).unwrap();
+        let content = std::fs::read_to_string(&known_hosts).unwrap();
+        assert!(
+            content.starts_with("[host1.lan]:2222 ssh-ed25519 "),
+            "{content}"
+        );
+        verify(key, HostKeyCheck::Strict).unwrap();
+        verify(key, HostKeyCheck::AcceptNew).unwrap();
+        assert_eq!(std::fs::read_to_string(&known_hosts).unwrap(), content);
+
+        let err = verify(other, HostKeyCheck::AcceptNew).unwrap_err();
+        assert!(err.contains("does not match"), "{err}");
+        verify(other, HostKeyCheck::Accept).unwrap();
+    }
+}
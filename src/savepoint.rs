@@ -0,0 +1,265 @@
+//! Savepoints of the state of the operators.
+//!
+//! A savepoint is a directory containing the state of the stateful operators (and the offset of
+//! the sources) at the end of an execution, it can be used to start a new execution from where the
+//! previous one stopped. Each state is stored under the identifier given to its operator and
+//! the index of the replica, so it can be read back after changing the code, as long as the
+//! identifiers and the types of the states are left unchanged.
+//!
+//! The layout of the directory is:
+//!
+//! ```text
+//! savepoint/
+//!   savepoint.json     manifest, written only when the savepoint is complete
+//!   <id>/<replica>.state
+//!   <id>/groups/<key group>.state
+//! ```
+//!
+//! The keyed state is stored by key group instead of by replica: the keys are hashed into a fixed
+//! number of [`KEY_GROUPS`], and each replica of a keyed operator owns a contiguous range of key
+//! groups. A savepoint of a keyed state can be restored with a different number of replicas, each
+//! replica loads the key groups it owns with the new number of replicas.
+
+use std::hash::Hash;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::{group_by_hash, CoordUInt};
+
+/// The version of the format of the savepoints written by this version of the library.
+pub const SAVEPOINT_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "savepoint.json";
+
+/// The number of key groups the keyed state is split into, the unit of redistribution of the
+/// keyed state among the replicas.
+///
+/// It is also the maximum useful parallelism of a keyed operator with state: with more replicas
+/// than key groups, some replicas own no keys.
+pub const KEY_GROUPS: usize = 128;
+
+/// The key group of a key.
+pub(crate) fn key_group<K: Hash + ?Sized>(key: &K) -> usize {
+    (group_by_hash(key) % KEY_GROUPS as u64) as usize
+}
+
+/// The index of the replica that owns a key group, among `replicas` replicas.
+pub(crate) fn key_group_replica(group: usize, replicas: usize) -> usize {
+    group * replicas / KEY_GROUPS
+}
+
+/// The key groups owned by a replica, the inverse of [`key_group_replica`].
+pub(crate) fn replica_key_groups(replica: usize, replicas: usize) -> Range<usize> {
+    (replica * KEY_GROUPS).div_ceil(replicas)..((replica + 1) * KEY_GROUPS).div_ceil(replicas)
+}
+
+/// Error while opening or writing a savepoint.
+#[derive(Debug, thiserror::Error)]
+pub enum SavepointError {
+    #[error("Input-Output error: {0}")]
+    IO(#[from] std::io::Error),
+
+    #[error("Invalid savepoint manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+
+    #[error("Unsupported savepoint version {found} (supported: {SAVEPOINT_VERSION})")]
+    Version { found: u32 },
+
+    #[error("Some states could not be written, the savepoint is incomplete")]
+    Incomplete,
+}
+
+/// The manifest of a complete savepoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+}
+
+/// The savepoints used by an execution: the one to restore the state from, and the one to write
+/// the state to at the end of the execution.
+#[derive(Debug, Default)]
+pub(crate) struct Savepoint {
+    restore: Option<PathBuf>,
+    save: Option<PathBuf>,
+    /// Whether writing some state failed.
+    failed: AtomicBool,
+}
+
+impl Savepoint {
+    /// Open a complete savepoint for restoring the state.
+    pub(crate) fn open(path: &Path) -> Result<PathBuf, SavepointError> {
+        let manifest = std::fs::read(path.join(MANIFEST_FILE))?;
+        let manifest: Manifest = serde_json::from_slice(&manifest)?;
+        if manifest.version != SAVEPOINT_VERSION {
+            return Err(SavepointError::Version {
+                found: manifest.version,
+            });
+        }
+        Ok(path.to_path_buf())
+    }
+
+    pub(crate) fn new(restore: Option<PathBuf>, save: Option<PathBuf>) -> Self {
+        Self {
+            restore,
+            save,
+            failed: AtomicBool::new(false),
+        }
+    }
+
+    fn state_path(dir: &Path, id: &str, global_id: CoordUInt) -> PathBuf {
+        dir.join(id).join(format!("{global_id}.state"))
+    }
+
+    fn key_group_path(dir: &Path, id: &str, group: usize) -> PathBuf {
+        dir.join(id).join("groups").join(format!("{group}.state"))
+    }
+
+    /// Read a state, `None` if the file does not exist.
+    fn read<S: DeserializeOwned>(path: &Path) -> Option<S> {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => panic!("savepoint: cannot read state {}: {e}", path.display()),
+        };
+        let state = bincode::deserialize(&data).unwrap_or_else(|e| {
+            panic!(
+                "savepoint: state {} is not compatible with {}: {e}",
+                path.display(),
+                std::any::type_name::<S>()
+            )
+        });
+        Some(state)
+    }
+
+    /// Write a state, recording the failure.
+    fn write<S: Serialize>(&self, path: &Path, state: &S) -> bool {
+        let result = bincode::serialize(state)
+            .map_err(std::io::Error::other)
+            .and_then(|data| {
+                std::fs::create_dir_all(path.parent().unwrap())?;
+                std::fs::write(path, data)
+            });
+        if let Err(e) = result {
+            log::error!("savepoint: cannot write state {}: {e}", path.display());
+            self.failed.store(true, Ordering::SeqCst);
+            return false;
+        }
+        true
+    }
+
+    /// Load the state with the given identifier of a replica, if present in the restored
+    /// savepoint.
+    ///
+    /// Panics if the state is present but cannot be read, since continuing without it would
+    /// silently lose the state.
+    pub(crate) fn load<S: DeserializeOwned>(&self, id: &str, global_id: CoordUInt) -> Option<S> {
+        let dir = self.restore.as_ref()?;
+        let Some(state) = Self::read(&Self::state_path(dir, id, global_id)) else {
+            log::warn!("savepoint: no state for {id} (replica {global_id}), starting fresh");
+            return None;
+        };
+        log::debug!("savepoint: restored state of {id} (replica {global_id})");
+        Some(state)
+    }
+
+    /// Load the keyed state with the given identifier of a key group, if present in the restored
+    /// savepoint. A key group without keys has no state.
+    pub(crate) fn load_key_group<S: DeserializeOwned>(&self, id: &str, group: usize) -> Option<S> {
+        let dir = self.restore.as_ref()?;
+        Self::read(&Self::key_group_path(dir, id, group))
+    }
+
+    /// Store the state with the given identifier of a replica, if a savepoint has to be written.
+    pub(crate) fn store<S: Serialize>(&self, id: &str, global_id: CoordUInt, state: &S) {
+        let Some(dir) = self.save.as_ref() else {
+            return;
+        };
+        if self.write(&Self::state_path(dir, id, global_id), state) {
+            log::debug!("savepoint: stored state of {id} (replica {global_id})");
+        }
+    }
+
+    /// Store the keyed state with the given identifier of a key group, if a savepoint has to be
+    /// written.
+    pub(crate) fn store_key_group<S: Serialize>(&self, id: &str, group: usize, state: &S) {
+        let Some(dir) = self.save.as_ref() else {
+            return;
+        };
+        if self.write(&Self::key_group_path(dir, id, group), state) {
+            log::debug!("savepoint: stored key group {group} of {id}");
+        }
+    }
+
+    /// Complete the savepoint (if any) writing its manifest, after all the states were stored.
+    pub(crate) fn finish(&self) -> Result<(), SavepointError> {
+        let Some(dir) = self.save.as_ref() else {
+            return Ok(());
+        };
+        if self.failed.load(Ordering::SeqCst) {
+            return Err(SavepointError::Incomplete);
+        }
+        std::fs::create_dir_all(dir)?;
+        let manifest = Manifest {
+            version: SAVEPOINT_VERSION,
+        };
+        std::fs::write(dir.join(MANIFEST_FILE), serde_json::to_vec(&manifest)?)?;
+        log::info!("savepoint written to {}", dir.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        key_group_replica, replica_key_groups, Savepoint, SavepointError, KEY_GROUPS, MANIFEST_FILE,
+    };
+
+    #[test]
+    fn savepoint_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sp");
+
+        let save = Savepoint::new(None, Some(path.clone()));
+        save.store("counter", 0, &42u64);
+        save.store("counter", 1, &7u64);
+        // not complete until finished
+        assert!(Savepoint::open(&path).is_err());
+        save.finish().unwrap();
+
+        let restore = Savepoint::new(Some(Savepoint::open(&path).unwrap()), None);
+        assert_eq!(restore.load::<u64>("counter", 0), Some(42));
+        assert_eq!(restore.load::<u64>("counter", 1), Some(7));
+        assert_eq!(restore.load::<u64>("counter", 2), None);
+        assert_eq!(restore.load::<u64>("other", 0), None);
+    }
+
+    #[test]
+    fn key_groups_partition_replicas() {
+        for replicas in [1, 2, 3, 4, 7, 128, 200] {
+            let mut next = 0;
+            for replica in 0..replicas {
+                let groups = replica_key_groups(replica, replicas);
+                assert_eq!(groups.start, next);
+                for group in groups.clone() {
+                    assert_eq!(key_group_replica(group, replicas), replica);
+                }
+                next = groups.end;
+            }
+            assert_eq!(next, KEY_GROUPS);
+        }
+    }
+
+    #[test]
+    fn savepoint_version_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(MANIFEST_FILE), r#"{"version": 999}"#).unwrap();
+        assert!(matches!(
+            Savepoint::open(dir.path()),
+            Err(SavepointError::Version { found: 999 })
+        ));
+    }
+}
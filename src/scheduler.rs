@@ -1,15 +1,22 @@
 use std::any::TypeId;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fmt::Write;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
+use std::time::Duration;
 
-use crate::block::{BatchMode, Block, BlockStructure, JobGraphGenerator, Replication};
+use crate::block::{
+    BatchMode, Block, BlockStructure, Connection, ConnectionStrategy, JobGraphGenerator,
+    Replication,
+};
 use crate::config::{LocalConfig, RemoteConfig, RuntimeConfig};
 use crate::network::{Coord, NetworkTopology};
+use crate::operator::sink::StreamOutputRef;
 use crate::operator::Operator;
 use crate::profiler::{log_trace, wait_profiler};
-use crate::worker::spawn_worker;
+use crate::savepoint::Savepoint;
+use crate::shutdown::ShutdownRecorder;
+use crate::worker::{spawn_worker, WorkerPanic};
 use crate::CoordUInt;
 
 /// Identifier of a block in the job graph.
@@ -19,8 +26,161 @@ pub type HostId = CoordUInt;
 /// The identifier of a replica of a block in the execution graph.
 pub type ReplicaId = CoordUInt;
 
-type BlockInitFn =
-    Box<dyn FnOnce(&mut ExecutionMetadata) -> (JoinHandle<()>, BlockStructure) + Send>;
+type WorkerHandle = JoinHandle<Result<(), WorkerPanic>>;
+
+type BlockInitFn = Box<dyn FnOnce(&mut ExecutionMetadata) -> (WorkerHandle, BlockStructure) + Send>;
+
+/// The preferred host of a replica, given the id of its block and its global id.
+pub(crate) type PlacementFn = Arc<dyn Fn(BlockId, CoordUInt) -> Option<HostId> + Send + Sync>;
+
+/// An error that stopped the execution of a job.
+#[derive(Debug, thiserror::Error)]
+pub enum ExecutionError {
+    /// The worker of a replica panicked. The replicas connected to it are torn down, so more
+    /// replicas may crash after it: this is the first one.
+    #[error("replica {coord} panicked: {message} ({crashed} replicas crashed in total)")]
+    Panic {
+        /// The first replica that panicked.
+        coord: Coord,
+        /// The message of its panic.
+        message: String,
+        /// The number of replicas that crashed.
+        crashed: usize,
+    },
+    /// Some operators failed to process some elements, like [`Stream::try_map`]. The execution
+    /// went on without the failed elements: these are all the errors recorded.
+    ///
+    /// [`Stream::try_map`]: crate::Stream::try_map
+    #[error("{} elements failed, the first in {}", errors.len(), errors[0])]
+    Operator {
+        /// The errors recorded by the operators, in the order they happened.
+        errors: Vec<OperatorError>,
+    },
+    /// A block of the job graph has no replicas, so the elements sent to it would never be
+    /// processed. This is checked before starting the execution.
+    #[error(
+        "block {block_id} has no replicas, check its replication and the number of cores: {block}"
+    )]
+    NoReplicas {
+        /// The block without replicas.
+        block_id: BlockId,
+        /// The description of the block.
+        block: String,
+    },
+}
+
+/// A problem in the job graph that would make the execution fail or hang, found by
+/// [`StreamContext::validate`](crate::StreamContext::validate) before the execution starts.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationError {
+    /// A stream was created but no sink was attached to it, so its block is never run.
+    #[error("block {block_id} has no sink attached")]
+    MissingSink {
+        /// The block of the stream without a sink.
+        block_id: BlockId,
+    },
+    /// A block has no replicas, so the elements sent to it would never be processed.
+    #[error(
+        "block {block_id} has no replicas, check its replication and the number of cores: {block}"
+    )]
+    NoReplicas {
+        /// The block without replicas.
+        block_id: BlockId,
+        /// The description of the block.
+        block: String,
+    },
+    /// Some blocks are connected in a cycle that is not the loop of an iteration, so they would
+    /// wait for each other forever.
+    #[error("blocks {blocks:?} are connected in a cycle outside of an iteration")]
+    Cycle {
+        /// The blocks in the cycle.
+        blocks: Vec<BlockId>,
+    },
+    /// A block sends each element to the replica of the next block with its same coordinates,
+    /// but some of its replicas have no counterpart, so their elements would be lost.
+    #[error(
+        "{} replicas of block {from} have no counterpart in block {to}, check their replication",
+        unmatched.len()
+    )]
+    IncompatibleConnection {
+        /// The block sending the elements.
+        from: BlockId,
+        /// The block receiving the elements.
+        to: BlockId,
+        /// The replicas of `from` without a replica of `to` with the same coordinates.
+        unmatched: Vec<Coord>,
+    },
+}
+
+/// An element that an operator failed to process, recorded instead of stopping the execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorError {
+    /// The replica where the element failed.
+    pub coord: Coord,
+    /// The name of the operator that failed.
+    pub operator: String,
+    /// The description of the error.
+    pub message: String,
+}
+
+impl std::fmt::Display for OperatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}: {}", self.operator, self.coord, self.message)
+    }
+}
+
+/// Collects the errors of the operators of an execution, surfaced as
+/// [`ExecutionError::Operator`] when the execution ends.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ErrorRecorder {
+    errors: Arc<Mutex<Vec<OperatorError>>>,
+}
+
+impl ErrorRecorder {
+    /// Record that the operator `operator` at `coord` failed to process an element.
+    pub(crate) fn record(&self, coord: Coord, operator: &str, message: String) {
+        self.errors.lock().unwrap().push(OperatorError {
+            coord,
+            operator: operator.to_string(),
+            message,
+        });
+    }
+
+    /// The result of an execution that ended with `res`, failing if some errors were recorded.
+    ///
+    /// A panic takes precedence over the errors of the operators.
+    pub(crate) fn finish(&self, res: Result<(), ExecutionError>) -> Result<(), ExecutionError> {
+        res?;
+        let errors = std::mem::take(&mut *self.errors.lock().unwrap());
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ExecutionError::Operator { errors })
+        }
+    }
+}
+
+/// Wait for all the workers to exit, returning the first panic among the ones that crashed.
+fn join_workers(join: Vec<WorkerHandle>) -> Result<(), ExecutionError> {
+    let mut crashed = vec![];
+    for handle in join {
+        match handle.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(panic)) => crashed.push(panic),
+            // the panic has been caught by the worker
+            Err(_) => unreachable!("the worker did not catch its panic"),
+        }
+    }
+    let count = crashed.len();
+    match crashed.into_iter().min_by_key(|panic| panic.at) {
+        None => Ok(()),
+        Some(first) => Err(ExecutionError::Panic {
+            coord: first.coord,
+            message: first.message,
+            crashed: count,
+        }),
+    }
+}
 
 /// Metadata used to initialize a block at the start of an execution
 #[derive(Debug)]
@@ -37,6 +197,24 @@ pub struct ExecutionMetadata<'a> {
     pub(crate) network: &'a mut NetworkTopology,
     /// The batching mode to use inside this block.
     pub batch_mode: BatchMode,
+    /// After how long without messages an input of this block is ignored by the watermarks.
+    pub watermark_idleness: Option<Duration>,
+    /// How often the start of this block wakes up the operators while its inputs are silent.
+    pub tick: Option<Duration>,
+    /// The imbalance among the replicas of a group-by after this block that triggers a warning.
+    pub skew_warning: Option<f64>,
+    /// How long a call of the code of the user in this block may take before a warning.
+    pub stuck_timeout: Option<Duration>,
+    /// The savepoints to restore the state from and to write the state to.
+    pub(crate) savepoint: Arc<Savepoint>,
+    /// The weight of each host, indexed by `HostId`. Empty in a local execution.
+    pub(crate) host_weights: Arc<Vec<f64>>,
+    /// The number of replicas of each block on this host.
+    pub(crate) local_replicas: Arc<HashMap<BlockId, usize, crate::block::CoordHasherBuilder>>,
+    /// Where the replicas record when they receive the end of the stream and terminate.
+    pub(crate) shutdown: ShutdownRecorder,
+    /// Where the operators record the elements they failed to process.
+    pub(crate) errors: ErrorRecorder,
 }
 
 /// Information about a block in the job graph.
@@ -50,6 +228,14 @@ struct SchedulerBlockInfo {
     global_ids: HashMap<Coord, CoordUInt, crate::block::CoordHasherBuilder>,
     /// The batching mode to use inside this block.
     batch_mode: BatchMode,
+    /// After how long without messages an input of this block is ignored by the watermarks.
+    watermark_idleness: Option<Duration>,
+    /// How often the start of this block wakes up the operators while its inputs are silent.
+    tick: Option<Duration>,
+    /// The imbalance among the replicas of a group-by after this block that triggers a warning.
+    skew_warning: Option<f64>,
+    /// How long a call of the code of the user in this block may take before a warning.
+    stuck_timeout: Option<Duration>,
     /// Whether this block has `NextStrategy::OnlyOne`.
     is_only_one_strategy: bool,
 }
@@ -69,6 +255,20 @@ pub(crate) struct Scheduler {
     block_init: Vec<(Coord, BlockInitFn)>,
     /// The network topology that keeps track of all the connections inside the execution graph.
     network: NetworkTopology,
+    /// The savepoints used by the execution.
+    pub(crate) savepoint: Arc<Savepoint>,
+    /// The weight of each host, indexed by `HostId`.
+    host_weights: Arc<Vec<f64>>,
+    /// The shutdown events of the replicas, reported at the end of the execution.
+    pub(crate) shutdown: ShutdownRecorder,
+    /// The errors of the operators, reported at the end of the execution.
+    errors: ErrorRecorder,
+    /// The structural hash of the job graph, set when the blocks are built.
+    pub(crate) job_graph_hash: StreamOutputRef<u64>,
+    /// The structure of each block, as it was before the execution.
+    block_structures: HashMap<BlockId, BlockStructure, crate::block::CoordHasherBuilder>,
+    /// The preferred host of the replicas of the blocks scheduled remotely.
+    pub(crate) placement: Option<PlacementFn>,
 }
 
 impl Scheduler {
@@ -79,6 +279,16 @@ impl Scheduler {
             block_info: Default::default(),
             block_init: Default::default(),
             network: NetworkTopology::new(config.clone()),
+            savepoint: Default::default(),
+            shutdown: Default::default(),
+            errors: Default::default(),
+            job_graph_hash: Default::default(),
+            block_structures: Default::default(),
+            placement: None,
+            host_weights: Arc::new(match config.as_ref() {
+                RuntimeConfig::Local(_) => Vec::new(),
+                RuntimeConfig::Remote(remote) => remote.hosts.iter().map(|h| h.weight()).collect(),
+            }),
             config,
         }
     }
@@ -95,6 +305,9 @@ impl Scheduler {
     {
         let block_id = block.id;
         let info = self.block_info(&block);
+        let mut structure = block.operators.structure();
+        structure.labels = block.labels.clone();
+        self.block_structures.insert(block_id, structure);
         debug!(
             "schedule block (b{:02}): {}",
             block_id,
@@ -120,10 +333,17 @@ impl Scheduler {
         self.block_info.insert(block_id, info);
 
         for (coord, block) in blocks {
+            let (core, stack_size) = match self.config.as_ref() {
+                RuntimeConfig::Local(local) => (
+                    local.core_for_replica(coord.replica_id),
+                    local.thread_stack_size,
+                ),
+                RuntimeConfig::Remote(_) => (None, None),
+            };
             // spawn the actual worker
             self.block_init.push((
                 coord,
-                Box::new(move |metadata| spawn_worker(block, metadata)),
+                Box::new(move |metadata| spawn_worker(block, metadata, core, stack_size)),
             ));
         }
     }
@@ -148,7 +368,8 @@ impl Scheduler {
         self.prev_blocks.entry(to).or_default().push((from, typ));
     }
 
-    fn build_all(&mut self) -> (Vec<JoinHandle<()>>, Vec<(Coord, BlockStructure)>) {
+    fn build_all(&mut self) -> (Vec<WorkerHandle>, Vec<(Coord, BlockStructure)>) {
+        self.shutdown.start();
         self.build_execution_graph();
         self.network.build();
         self.network.log();
@@ -156,6 +377,13 @@ impl Scheduler {
         let mut join = vec![];
         let mut block_structures = vec![];
         let mut job_graph_generator = JobGraphGenerator::new();
+        let host_id = self.config.host_id().unwrap();
+        let local_replicas: Arc<HashMap<_, _, _>> = Arc::new(
+            self.block_info
+                .iter()
+                .map(|(&block_id, info)| (block_id, info.replicas(host_id).len()))
+                .collect(),
+        );
 
         for (coord, init_fn) in self.block_init.drain(..) {
             let block_info = &self.block_info[&coord.block_id];
@@ -168,15 +396,30 @@ impl Scheduler {
                 prev: self.network.prev(coord),
                 network: &mut self.network,
                 batch_mode: block_info.batch_mode,
+                watermark_idleness: block_info.watermark_idleness,
+                tick: block_info.tick,
+                skew_warning: block_info.skew_warning,
+                stuck_timeout: block_info.stuck_timeout,
+                savepoint: self.savepoint.clone(),
+                host_weights: self.host_weights.clone(),
+                local_replicas: local_replicas.clone(),
+                shutdown: self.shutdown.clone(),
+                errors: self.errors.clone(),
             };
             let (handle, structure) = init_fn(&mut metadata);
             join.push(handle);
             block_structures.push((coord, structure.clone()));
             job_graph_generator.add_block(coord.block_id, structure);
+            job_graph_generator.describe_block(coord.block_id, block_info.repr.clone());
         }
 
-        let job_graph = job_graph_generator.finalize();
-        log::debug!("job graph:\n{}", job_graph);
+        for mismatch in job_graph_generator.validate() {
+            log::warn!("inconsistent types in the job graph: {mismatch}");
+        }
+        let hash = job_graph_generator.structural_hash();
+        log::debug!("job graph hash: {hash:016x}");
+        *self.job_graph_hash.lock().unwrap() = Some(hash);
+        log::debug!("job graph:\n{}", job_graph_generator.finalize());
 
         self.network.finalize();
 
@@ -184,8 +427,8 @@ impl Scheduler {
     }
 
     #[cfg(feature = "tokio")]
-    /// Start the computation returning the list of handles used to join the workers.
-    pub(crate) async fn start(mut self, block_count: CoordUInt) {
+    /// Start the computation and wait for the workers to exit.
+    pub(crate) async fn start(mut self, block_count: CoordUInt) -> Result<(), ExecutionError> {
         debug!("start scheduler: {:?}", self.config);
         self.log_topology();
 
@@ -196,28 +439,28 @@ impl Scheduler {
             block_count as usize,
             self.block_info.len(),
         );
+        self.check_replicas()?;
 
         let (join, block_structures) = self.build_all();
 
         let (_, join_result) = tokio::join!(
             self.network.stop_and_wait(),
-            tokio::task::spawn_blocking(move || {
-                for handle in join {
-                    handle.join().unwrap();
-                }
-            })
+            tokio::task::spawn_blocking(move || join_workers(join))
         );
 
-        join_result.expect("Could not join worker threads");
+        let res = join_result.expect("Could not join worker threads");
 
+        self.report_shutdown();
+        self.save_replay_log();
         log_trace(block_structures, wait_profiler());
+        self.errors.finish(res)
     }
 
-    /// Start the computation returning the list of handles used to join the workers.
+    /// Start the computation and wait for the workers to exit.
     ///
     /// NOTE: If running with the `tokio` feature enable, this will create a new
     /// tokio runtime.
-    pub(crate) fn start_blocking(mut self, num_blocks: CoordUInt) {
+    pub(crate) fn start_blocking(mut self, num_blocks: CoordUInt) -> Result<(), ExecutionError> {
         debug!("start scheduler: {:?}", self.config);
         self.log_topology();
 
@@ -228,6 +471,7 @@ impl Scheduler {
             num_blocks as usize,
             self.block_info.len(),
         );
+        self.check_replicas()?;
 
         #[cfg(feature = "tokio")]
         {
@@ -241,27 +485,54 @@ impl Scheduler {
 
                     let (_, join_result) = tokio::join!(
                         self.network.stop_and_wait(),
-                        tokio::task::spawn_blocking(move || {
-                            for handle in join {
-                                handle.join().unwrap();
-                            }
-                        })
+                        tokio::task::spawn_blocking(move || join_workers(join))
                     );
-                    join_result.expect("Could not join worker threads");
+                    let res = join_result.expect("Could not join worker threads");
+                    self.report_shutdown();
+                    self.save_replay_log();
                     log_trace(block_structures, wait_profiler());
-                });
+                    self.errors.finish(res)
+                })
         }
         #[cfg(not(feature = "tokio"))]
         {
             let (join, block_structures) = self.build_all();
 
-            for handle in join {
-                handle.join().unwrap();
-            }
+            let res = join_workers(join);
 
             self.network.stop_and_wait();
+            self.report_shutdown();
+            self.save_replay_log();
             let profiler_results = wait_profiler();
             log_trace(block_structures, profiler_results);
+            self.errors.finish(res)
+        }
+    }
+
+    /// Log the shutdown sequence of the blocks, now that all the workers have exited.
+    fn report_shutdown(&self) {
+        let names = self
+            .block_info
+            .iter()
+            .map(|(&block_id, info)| (block_id, info.repr.clone()))
+            .collect();
+        self.shutdown.finish(&names);
+    }
+
+    /// Save the order of the messages received, if it is recorded by a [`ReplayLog`].
+    ///
+    /// The log is saved even if the execution failed, to replay the failure.
+    ///
+    /// [`ReplayLog`]: crate::ReplayLog
+    fn save_replay_log(&self) {
+        if let RuntimeConfig::Local(LocalConfig {
+            replay_log: Some(log),
+            ..
+        }) = self.config.as_ref()
+        {
+            if let Err(e) = log.save() {
+                log::error!("cannot save the replay log to {}: {e}", log.dir().display());
+            }
         }
     }
 
@@ -298,6 +569,173 @@ impl Scheduler {
         }
     }
 
+    /// Generate the job graph of the blocks registered so far, before the execution.
+    ///
+    /// The operators connect to the next blocks only when the execution starts, so the
+    /// connections are taken from the adjacency list and drawn from the last operator of each
+    /// block.
+    fn planned_job_graph(&self) -> JobGraphGenerator {
+        let mut generator = JobGraphGenerator::new();
+        for (&block_id, structure) in &self.block_structures {
+            let mut structure = structure.clone();
+            for operator in &mut structure.operators {
+                operator.connections.clear();
+            }
+            if let Some(last) = structure.operators.last_mut() {
+                let next = self.next_blocks.get(&block_id).into_iter().flatten();
+                for &(to_block_id, _, fragile) in next {
+                    let strategy = match &last.next_strategy {
+                        _ if fragile => ConnectionStrategy::OnlyOne,
+                        Some(strategy) => strategy.clone(),
+                        None => ConnectionStrategy::Random,
+                    };
+                    last.connections.push(Connection {
+                        to_block_id,
+                        data_type: last.out_type.clone(),
+                        strategy,
+                    });
+                }
+            }
+            let info = &self.block_info[&block_id];
+            let replicas = info.replicas.values().map(Vec::len).sum();
+            generator.add_block_replicas(block_id, structure, replicas);
+            generator.describe_block(block_id, info.repr.clone());
+        }
+        generator
+    }
+
+    /// The job graph of the blocks registered so far in dot format.
+    pub(crate) fn job_graph(&self) -> String {
+        self.planned_job_graph().finalize()
+    }
+
+    /// The job graph of the blocks registered so far as a Mermaid flowchart.
+    pub(crate) fn job_graph_mermaid(&self) -> String {
+        self.planned_job_graph().finalize_mermaid()
+    }
+
+    /// Check that every block has at least one replica, returning the first block without them.
+    fn check_replicas(&self) -> Result<(), ExecutionError> {
+        match self.blocks_without_replicas().next() {
+            Some((block_id, info)) => Err(ExecutionError::NoReplicas {
+                block_id,
+                block: info.repr.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The blocks without replicas, sorted by id.
+    fn blocks_without_replicas(&self) -> impl Iterator<Item = (BlockId, &SchedulerBlockInfo)> {
+        let mut empty = self
+            .block_info
+            .iter()
+            .filter(|(_, info)| info.replicas.values().all(|r| r.is_empty()))
+            .map(|(&block_id, info)| (block_id, info))
+            .collect::<Vec<_>>();
+        empty.sort_by_key(|(block_id, _)| *block_id);
+        empty.into_iter()
+    }
+
+    /// Check the job graph of the `block_count` blocks created so far, returning all the problems
+    /// found.
+    pub(crate) fn validate(&self, block_count: CoordUInt) -> Vec<ValidationError> {
+        let mut errors = vec![];
+        for block_id in 0..block_count {
+            if !self.block_info.contains_key(&block_id) {
+                errors.push(ValidationError::MissingSink { block_id });
+            }
+        }
+        for (block_id, info) in self.blocks_without_replicas() {
+            errors.push(ValidationError::NoReplicas {
+                block_id,
+                block: info.repr.clone(),
+            });
+        }
+        let cycle = self.blocks_in_cycles();
+        if !cycle.is_empty() {
+            errors.push(ValidationError::Cycle { blocks: cycle });
+        }
+        errors.extend(self.incompatible_connections());
+        errors
+    }
+
+    /// The blocks in a cycle that does not go through the start of an iteration, sorted by id.
+    ///
+    /// The loops of the iterations end in the block of the `Iterate` or `Replay` operator, so the
+    /// connections to these blocks are ignored. The blocks that are not in a cycle are removed
+    /// one by one, starting from the ones without incoming or without outgoing connections: the
+    /// blocks left are in a cycle.
+    fn blocks_in_cycles(&self) -> Vec<BlockId> {
+        let starts_iteration = |block_id: &BlockId| {
+            self.block_structures.get(block_id).is_some_and(|s| {
+                s.operators
+                    .iter()
+                    .any(|op| op.title == "Iterate" || op.title == "Replay")
+            })
+        };
+        let mut edges: BTreeSet<(BlockId, BlockId)> = self
+            .next_blocks
+            .iter()
+            .flat_map(|(&from, next)| next.iter().map(move |&(to, _, _)| (from, to)))
+            .filter(|(_, to)| !starts_iteration(to))
+            .collect();
+        loop {
+            let sources: BTreeSet<_> = edges.iter().map(|&(from, _)| from).collect();
+            let targets: BTreeSet<_> = edges.iter().map(|&(_, to)| to).collect();
+            let before = edges.len();
+            edges.retain(|(from, to)| targets.contains(from) && sources.contains(to));
+            if edges.len() == before {
+                break;
+            }
+        }
+        let blocks: BTreeSet<_> = edges.iter().map(|&(from, _)| from).collect();
+        blocks.into_iter().collect()
+    }
+
+    /// The connections that send each element to the replica of the next block with the same
+    /// coordinates, when some of the senders have no counterpart, like in
+    /// [`Scheduler::build_execution_graph`].
+    fn incompatible_connections(&self) -> Vec<ValidationError> {
+        let mut connections: Vec<_> = self
+            .next_blocks
+            .iter()
+            .flat_map(|(&from, next)| {
+                next.iter()
+                    .map(move |&(to, _, fragile)| (from, to, fragile))
+            })
+            .collect();
+        connections.sort();
+        connections
+            .into_iter()
+            .filter_map(|(from_block_id, to_block_id, fragile)| {
+                let from = self.block_info.get(&from_block_id)?;
+                let to = self.block_info.get(&to_block_id)?;
+                let to: Vec<_> = to.replicas.values().flatten().collect();
+                if !(from.is_only_one_strategy || fragile) || to.len() <= 1 {
+                    return None;
+                }
+                let mut unmatched: Vec<_> = from
+                    .replicas
+                    .values()
+                    .flatten()
+                    .filter(|from| {
+                        !to.iter().any(|to| {
+                            to.host_id == from.host_id && to.replica_id == from.replica_id
+                        })
+                    })
+                    .copied()
+                    .collect();
+                unmatched.sort();
+                (!unmatched.is_empty()).then_some(ValidationError::IncompatibleConnection {
+                    from: from_block_id,
+                    to: to_block_id,
+                    unmatched,
+                })
+            })
+            .collect()
+    }
+
     fn log_topology(&self) {
         let mut topology = "job graph:".to_string();
         for (block_id, block) in self.block_info.iter() {
@@ -356,14 +794,20 @@ impl Scheduler {
             replicas: vec![(host_id, replicas.collect())].into_iter().collect(),
             global_ids: global_ids.into_iter().collect(),
             batch_mode: block.batch_mode,
+            watermark_idleness: block.watermark_idleness,
+            tick: block.tick,
+            skew_warning: block.skew_warning,
+            stuck_timeout: block.stuck_timeout,
             is_only_one_strategy: block.is_only_one_strategy,
         }
     }
 
     /// Extract the `SchedulerBlockInfo` of a block that runs remotely.
     ///
-    /// The block can be replicated at most `replication` times (if specified). Assign the
-    /// replicas starting from the first host giving as much replicas as possible..
+    /// The block can be replicated at most `replication` times (if specified), with at most a
+    /// replica for each core of a host. The replicas with a preferred host (see
+    /// [`Scheduler::placement`]) are placed there if it has room left, the other replicas fill the
+    /// hosts in order, starting from the first host giving as much replicas as possible.
     fn remote_block_info<OperatorChain>(
         &self,
         block: &Block<OperatorChain>,
@@ -373,52 +817,41 @@ impl Scheduler {
         OperatorChain: Operator,
     {
         let replication = block.scheduling.replication;
-        // number of replicas we can assign at most
-        let mut global_counter = 0;
-        let mut replicas: HashMap<_, Vec<_>, crate::block::CoordHasherBuilder> = HashMap::default();
-        let mut global_ids = HashMap::default();
-
-        macro_rules! add_replicas {
-            ($id:expr, $h:expr, $n:expr) => {{
-                log::debug!(
-                    "remote (b{:02})[{}]: {{ replicas: {:2}, replication: {:?}, num_cores: {} }}",
-                    block.id,
-                    $h.to_string(),
-                    $n,
-                    replication,
-                    $h.num_cores
-                );
-                let host_replicas = replicas.entry($id).or_default();
-                for replica_id in 0..$n {
-                    let coord = Coord::new(block.id, $id, replica_id);
-                    host_replicas.push(coord);
-                    global_ids.insert(coord, global_counter);
-                    global_counter += 1;
-                }
-            }};
-        }
-
-        match replication {
+        // the number of replicas each host can take, and the number of replicas of the block
+        let (capacity, instances): (Vec<CoordUInt>, CoordUInt) = match replication {
             Replication::Unlimited => {
-                for (host_id, host_info) in remote.hosts.iter().enumerate() {
-                    add_replicas!(host_id.try_into().unwrap(), host_info, host_info.num_cores);
-                }
-            }
-            Replication::Limited(mut remaining) => {
-                for (host_id, host_info) in remote.hosts.iter().enumerate() {
-                    let n = remaining.min(host_info.num_cores);
-                    add_replicas!(host_id.try_into().unwrap(), host_info, n);
-                    remaining -= n;
-                }
-            }
-            Replication::Host => {
-                for (host_id, host_info) in remote.hosts.iter().enumerate() {
-                    add_replicas!(host_id.try_into().unwrap(), host_info, 1);
-                }
+                let capacity: Vec<_> = remote.hosts.iter().map(|h| h.num_cores).collect();
+                let instances = capacity.iter().sum();
+                (capacity, instances)
             }
-            Replication::One => {
-                add_replicas!(0, remote.hosts[0], 1);
+            Replication::Limited(n) => {
+                let capacity: Vec<_> = remote.hosts.iter().map(|h| h.num_cores).collect();
+                let instances = n.min(capacity.iter().sum());
+                (capacity, instances)
             }
+            Replication::Host => (vec![1; remote.hosts.len()], remote.hosts.len() as CoordUInt),
+            Replication::One => (vec![1; remote.hosts.len()], 1),
+        };
+        let hosts = assign_replicas(block.id, &capacity, instances, self.placement.as_ref());
+
+        let mut replicas: HashMap<_, Vec<_>, crate::block::CoordHasherBuilder> = HashMap::default();
+        let mut global_ids = HashMap::default();
+        for (global_id, host_id) in hosts.into_iter().enumerate() {
+            let host_replicas = replicas.entry(host_id).or_default();
+            let coord = Coord::new(block.id, host_id, host_replicas.len() as CoordUInt);
+            host_replicas.push(coord);
+            global_ids.insert(coord, global_id as CoordUInt);
+        }
+        for (host_id, host_info) in remote.hosts.iter().enumerate() {
+            let host_id = host_id as HostId;
+            log::debug!(
+                "remote (b{:02})[{}]: {{ replicas: {:2}, replication: {:?}, num_cores: {} }}",
+                block.id,
+                host_info.to_string(),
+                replicas.get(&host_id).map(Vec::len).unwrap_or_default(),
+                replication,
+                host_info.num_cores
+            );
         }
 
         SchedulerBlockInfo {
@@ -426,11 +859,63 @@ impl Scheduler {
             replicas,
             global_ids,
             batch_mode: block.batch_mode,
+            watermark_idleness: block.watermark_idleness,
+            tick: block.tick,
+            skew_warning: block.skew_warning,
+            stuck_timeout: block.stuck_timeout,
             is_only_one_strategy: block.is_only_one_strategy,
         }
     }
 }
 
+/// Choose the host of each replica of a block, indexed by the global id of the replica.
+///
+/// `capacity` is the number of replicas each host can take. The replicas for which `placement`
+/// returns a host with room left are placed there, the others fill the remaining room of the
+/// hosts in order.
+fn assign_replicas(
+    block_id: BlockId,
+    capacity: &[CoordUInt],
+    instances: CoordUInt,
+    placement: Option<&PlacementFn>,
+) -> Vec<HostId> {
+    let mut free = capacity.to_vec();
+    let mut hosts: Vec<Option<HostId>> = vec![None; instances as usize];
+    if let Some(placement) = placement {
+        for (global_id, host) in hosts.iter_mut().enumerate() {
+            let Some(preferred) = placement(block_id, global_id as CoordUInt) else {
+                continue;
+            };
+            match free.get_mut(preferred as usize) {
+                Some(free) if *free > 0 => {
+                    *free -= 1;
+                    *host = Some(preferred);
+                }
+                Some(_) => log::warn!(
+                    "(b{block_id:02}) replica {global_id}: host {preferred} is full, using another"
+                ),
+                None => log::warn!(
+                    "(b{block_id:02}) replica {global_id}: no host {preferred}, using another"
+                ),
+            }
+        }
+    }
+
+    let mut next_host = 0;
+    hosts
+        .into_iter()
+        .map(|host| {
+            host.unwrap_or_else(|| {
+                while free[next_host] == 0 {
+                    next_host += 1;
+                }
+                free[next_host] -= 1;
+                next_host as HostId
+            })
+        })
+        .collect()
+}
+
 impl SchedulerBlockInfo {
     /// The list of replicas of the block inside a given host.
     fn replicas(&self, host_id: HostId) -> Vec<Coord> {
@@ -441,6 +926,9 @@ impl SchedulerBlockInfo {
 #[cfg(not(feature = "tokio"))]
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use super::{assign_replicas, PlacementFn};
     use crate::config::RuntimeConfig;
     use crate::environment::StreamContext;
     use crate::operator::source::IteratorSource;
@@ -462,4 +950,21 @@ mod tests {
         let _stream = env.stream(source).shuffle();
         env.execute_blocking();
     }
+
+    #[test]
+    fn test_assign_replicas_preferred_host() {
+        // without preferences the hosts are filled in order
+        assert_eq!(assign_replicas(0, &[2, 2, 2], 5, None), vec![0, 0, 1, 1, 2]);
+
+        // the third replica preferring host 2 does not fit, the fourth prefers a missing host
+        let placement: PlacementFn = Arc::new(|_, replica| match replica {
+            0..=2 => Some(2),
+            3 => Some(7),
+            _ => None,
+        });
+        assert_eq!(
+            assign_replicas(0, &[2, 2, 2], 5, Some(&placement)),
+            vec![2, 2, 0, 0, 1]
+        );
+    }
 }
@@ -0,0 +1,220 @@
+//! The report of how the end of the stream propagated through the blocks of a job.
+//!
+//! When the sources end, each block terminates after receiving the end of the stream from all
+//! the replicas of its previous blocks. At the end of the execution the times of these events are
+//! collected in a [`ShutdownReport`], which is logged and can be obtained with
+//! [`StreamContext::shutdown_report`](crate::StreamContext::shutdown_report). A block that waited
+//! long for the ends of its inputs points to a previous block that lagged in terminating.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use crate::network::Coord;
+use crate::operator::sink::StreamOutputRef;
+use crate::scheduler::BlockId;
+
+/// When a replica received the ends of its inputs and when it terminated.
+#[derive(Debug, Clone, Copy, Default)]
+struct ReplicaShutdown {
+    /// When the first end of the stream was received from the previous replicas.
+    first_end: Option<Instant>,
+    /// When the last end of the stream was received from the previous replicas.
+    last_end: Option<Instant>,
+    /// When the worker of the replica exited.
+    terminated: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    started: Instant,
+    replicas: HashMap<Coord, ReplicaShutdown>,
+}
+
+/// Collects the shutdown events of the replicas of an execution, see [`ShutdownReport`].
+#[derive(Debug, Clone)]
+pub(crate) struct ShutdownRecorder {
+    inner: Arc<Mutex<Inner>>,
+    output: StreamOutputRef<ShutdownReport>,
+}
+
+impl Default for ShutdownRecorder {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                started: Instant::now(),
+                replicas: Default::default(),
+            })),
+            output: Default::default(),
+        }
+    }
+}
+
+impl ShutdownRecorder {
+    /// Mark the start of the execution, the times of the report are relative to it.
+    pub(crate) fn start(&self) {
+        let mut inner = self.inner.lock();
+        inner.started = Instant::now();
+        inner.replicas.clear();
+    }
+
+    /// The replica at `coord` received the ends of all its inputs, the first one at `first_end`.
+    pub(crate) fn ends_received(&self, coord: Coord, first_end: Instant) {
+        let mut inner = self.inner.lock();
+        let replica = inner.replicas.entry(coord).or_default();
+        replica.first_end = Some(first_end);
+        replica.last_end = Some(Instant::now());
+    }
+
+    /// The worker of the replica at `coord` exited.
+    pub(crate) fn terminated(&self, coord: Coord) {
+        let mut inner = self.inner.lock();
+        inner.replicas.entry(coord).or_default().terminated = Some(Instant::now());
+    }
+
+    /// Build the report of the execution, log it and make it available to the user.
+    ///
+    /// `names` are the descriptions of the blocks, indexed by their id.
+    pub(crate) fn finish(&self, names: &HashMap<BlockId, String>) {
+        let report = self.report(names);
+        info!("{report}");
+        *self.output.lock().unwrap() = Some(report);
+    }
+
+    fn report(&self, names: &HashMap<BlockId, String>) -> ShutdownReport {
+        let inner = self.inner.lock();
+        let since_start = |t: Instant| t.saturating_duration_since(inner.started);
+
+        let mut blocks: HashMap<BlockId, BlockShutdown> = HashMap::new();
+        for (coord, replica) in &inner.replicas {
+            let block = blocks
+                .entry(coord.block_id)
+                .or_insert_with(|| BlockShutdown {
+                    block_id: coord.block_id,
+                    name: names.get(&coord.block_id).cloned().unwrap_or_default(),
+                    replicas: 0,
+                    ends_received: None,
+                    waited: Duration::ZERO,
+                    terminated: Duration::ZERO,
+                });
+            block.replicas += 1;
+            if let (Some(first), Some(last)) = (replica.first_end, replica.last_end) {
+                block.waited = block.waited.max(last.saturating_duration_since(first));
+                let last = since_start(last);
+                block.ends_received = Some(block.ends_received.map_or(last, |t| t.max(last)));
+            }
+            if let Some(terminated) = replica.terminated {
+                block.terminated = block.terminated.max(since_start(terminated));
+            }
+        }
+
+        // the sources end first, then the end of the stream reaches the following blocks in order
+        let mut blocks = blocks.into_values().collect::<Vec<_>>();
+        blocks.sort_by_key(|b| (b.ends_received.is_some(), b.ended(), b.block_id));
+        ShutdownReport { blocks }
+    }
+
+    /// The report built at the end of the execution.
+    pub(crate) fn output(&self) -> StreamOutputRef<ShutdownReport> {
+        self.output.clone()
+    }
+}
+
+/// How the end of the stream propagated through the blocks of a job, built at the end of the
+/// execution.
+///
+/// In a remote execution each host reports only its own replicas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// The blocks, in the order they ended: first the sources, by the time they terminated, then
+    /// the other blocks by the time they received the end of all their inputs.
+    pub blocks: Vec<BlockShutdown>,
+}
+
+/// The shutdown of the replicas of a block.
+///
+/// All the times are relative to the start of the execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockShutdown {
+    /// The identifier of the block.
+    pub block_id: BlockId,
+    /// The operators of the block.
+    pub name: String,
+    /// The number of replicas of the block that ended.
+    pub replicas: usize,
+    /// When the last replica of the block received the end of the stream from all its inputs.
+    /// `None` for the sources, which have no inputs.
+    pub ends_received: Option<Duration>,
+    /// The longest time a replica waited between the first and the last end of its inputs.
+    pub waited: Duration,
+    /// When the last replica of the block terminated.
+    pub terminated: Duration,
+}
+
+impl BlockShutdown {
+    /// When the block ended: when it received all the ends of its inputs, or when it terminated
+    /// for a source.
+    pub fn ended(&self) -> Duration {
+        self.ends_received.unwrap_or(self.terminated)
+    }
+}
+
+impl Display for ShutdownReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "shutdown sequence ({} blocks):", self.blocks.len())?;
+        for block in &self.blocks {
+            write!(f, "\n  b{:02} ", block.block_id)?;
+            match block.ends_received {
+                Some(at) => write!(
+                    f,
+                    "received all the ends at {at:.2?} after waiting {:.2?}",
+                    block.waited
+                )?,
+                None => write!(f, "source")?,
+            }
+            write!(
+                f,
+                ", {} replicas terminated at {:.2?}: {}",
+                block.replicas, block.terminated, block.name
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::config::RuntimeConfig;
+    use crate::environment::StreamContext;
+
+    #[test]
+    fn shutdown_report_two_blocks() {
+        let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+        let source = env.stream_par_iter(0..1000u32);
+        let source_id = source.block.id;
+        let shuffled = source.shuffle();
+        let shuffled_id = shuffled.block.id;
+        shuffled.for_each(|_| {});
+        let report = env.shutdown_report();
+        env.execute_blocking();
+
+        let report = report.get().unwrap();
+        let ids = report.blocks.iter().map(|b| b.block_id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![source_id, shuffled_id]);
+
+        let (source, shuffled) = (&report.blocks[0], &report.blocks[1]);
+        assert_eq!(source.replicas, 2);
+        assert_eq!(source.ends_received, None);
+        assert_eq!(source.waited, Duration::ZERO);
+        assert_eq!(shuffled.replicas, 2);
+        let ends_received = shuffled.ends_received.unwrap();
+        assert!(shuffled.waited <= ends_received);
+        assert!(ends_received <= shuffled.terminated);
+        assert!(report.to_string().contains(&format!("b{shuffled_id:02}")));
+    }
+}
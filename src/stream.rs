@@ -24,6 +24,17 @@ use crate::scheduler::BlockId;
 /// The type of the chain inside the block is `OperatorChain` and it's required as type argument of
 /// the stream. This type only represents the chain inside the last block of the stream, not all the
 /// blocks inside of it.
+///
+/// ## Ordering
+///
+/// Each replica receives the elements sent to it by each replica of the previous block in the
+/// same order they were sent: the batches of a link between two replicas are delivered in order,
+/// both through local channels and through the network, and the elements of a batch keep their
+/// order. So a chain of operators that does not redistribute the elements (e.g.
+/// [`Stream::map`], [`Stream::filter`], [`Stream::replication`]) preserves the order of each
+/// replica of the source, and a stateful operator like [`Stream::map_with_state`] sees it. The
+/// elements coming from different replicas, or redistributed by operators like
+/// [`Stream::shuffle`] and [`Stream::group_by`], are interleaved in no particular order.
 pub struct Stream<Op>
 where
     Op: Operator,
@@ -87,6 +98,7 @@ where
 ///  - [`ProcessingTimeWindow`][crate::operator::window::ProcessingTimeWindow]
 ///  - [`CountWindow`][crate::operator::window::CountWindow]
 ///  - [`SessionWindow`][crate::operator::window::SessionWindow]
+///  - [`DynamicSessionWindow`][crate::operator::window::DynamicSessionWindow]
 ///  - [`TransactionWindow`][crate::operator::window::TransactionWindow]
 ///
 pub struct WindowedStream<Op, O: Data, WinDescr>
@@ -125,6 +137,40 @@ where
         Stream::new(self.ctx, self.block.add_operator(get_operator))
     }
 
+    /// Splice a reusable component into the stream.
+    ///
+    /// A component is just a function that takes a stream and returns the stream extended with
+    /// some operators. Defining the pieces of a large job as components keeps them small, named
+    /// and reusable in different jobs, and they can be tested in isolation with
+    /// [`run_component`](crate::test::run_component).
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use renoir::{StreamContext, RuntimeConfig};
+    /// # use renoir::operator::Operator;
+    /// # use renoir::Stream;
+    /// # let mut env = StreamContext::new_local();
+    /// fn even_squares(
+    ///     s: Stream<impl Operator<Out = u32> + 'static>,
+    /// ) -> Stream<impl Operator<Out = u32>> {
+    ///     s.filter(|n| n % 2 == 0).map(|n| n * n)
+    /// }
+    ///
+    /// let res = env.stream_iter(0..10).apply(even_squares).collect_vec();
+    ///
+    /// env.execute_blocking();
+    ///
+    /// assert_eq!(res.get().unwrap(), vec![0, 4, 16, 36, 64]);
+    /// ```
+    pub fn apply<Op2, C>(self, component: C) -> Stream<Op2>
+    where
+        Op2: Operator,
+        C: FnOnce(Self) -> Stream<Op2>,
+    {
+        component(self)
+    }
+
     /// Add a new block to the stream, closing and registering the previous one. The new block is
     /// connected to the previous one.
     ///
@@ -147,6 +193,8 @@ where
         let Stream { block, ctx } = self;
         // Clone parameters for new block
         let batch_mode = block.batch_mode;
+        let unbounded = block.unbounded;
+        let group_by_hasher = block.group_by_hasher.clone();
         let iteration_ctx = block.iteration_ctx.clone();
         // Add end operator
         let mut block =
@@ -158,7 +206,9 @@ where
         let prev_id = env_lock.close_block(block);
         // Create new block
         let source = Start::single(prev_id, iteration_ctx.last().cloned());
-        let new_block = env_lock.new_block(source, batch_mode, iteration_ctx);
+        let mut new_block = env_lock.new_block(source, batch_mode, iteration_ctx);
+        new_block.unbounded = unbounded;
+        new_block.group_by_hasher = group_by_hasher;
         // Connect blocks
         env_lock.connect_blocks::<Op::Out>(prev_id, new_block.id);
 
@@ -198,6 +248,8 @@ where
         let Stream { block: b2, .. } = oth;
 
         let batch_mode = b1.batch_mode;
+        let unbounded = b1.unbounded || b2.unbounded;
+        let group_by_hasher = b1.group_by_hasher.clone();
         let is_one_1 = matches!(next_strategy1, NextStrategy::OnlyOne);
         let is_one_2 = matches!(next_strategy2, NextStrategy::OnlyOne);
         let sched_1 = b1.scheduling.clone();
@@ -250,6 +302,8 @@ where
         );
 
         let mut new_block = env_lock.new_block(source, batch_mode, iteration_ctx);
+        new_block.unbounded = unbounded;
+        new_block.group_by_hasher = group_by_hasher;
         let id_new = new_block.id;
 
         env_lock.connect_blocks::<Op::Out>(id_1, id_new);
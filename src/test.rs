@@ -1,3 +1,28 @@
+//! Utilities for unit-testing the operators.
+//!
+//! A custom [`Operator`] can be tested without running a job: feed it the elements of a
+//! [`FakeOperator`] and drive it with [`run_operator`].
+//!
+//! ```
+//! # use renoir::operator::StreamElement;
+//! # use renoir::test::{run_operator, FakeOperator};
+//! let prev = FakeOperator::from_elements(vec![
+//!     StreamElement::Item(1),
+//!     StreamElement::FlushAndRestart,
+//! ]);
+//! assert_eq!(
+//!     run_operator(prev),
+//!     vec![
+//!         StreamElement::Item(1),
+//!         StreamElement::FlushAndRestart,
+//!         StreamElement::Terminate
+//!     ]
+//! );
+//! ```
+//!
+//! A reusable component of a job, spliced in with [`Stream::apply`], can be run in isolation with
+//! [`run_component`].
+
 use std::any::TypeId;
 use std::collections::VecDeque;
 use std::fmt::Display;
@@ -6,11 +31,12 @@ use std::time::Duration;
 
 use crate::block::{BlockStructure, OperatorStructure, Replication};
 use crate::network::{Coord, NetworkSender, NetworkTopology, ReceiverEndpoint};
+use crate::operator::source::IteratorSource;
 use crate::operator::source::Source;
 use crate::operator::{Data, ExchangeData, Operator, StreamElement};
 use crate::scheduler::ExecutionMetadata;
 use crate::CoordUInt;
-use crate::{BatchMode, RuntimeConfig};
+use crate::{BatchMode, RuntimeConfig, Stream, StreamContext};
 
 /// A fake operator that can be used to unit-test the operators.
 #[derive(Debug, Clone)]
@@ -40,6 +66,13 @@ impl<Out: Data> FakeOperator<Out> {
         }
     }
 
+    /// Create a `FakeOperator` that returns the specified elements, and then `Terminate`.
+    pub fn from_elements(elements: Vec<StreamElement<Out>>) -> Self {
+        Self {
+            buffer: elements.into(),
+        }
+    }
+
     /// Add an element to the end of the list of elements to return from `next`.
     pub fn push(&mut self, item: StreamElement<Out>) {
         self.buffer.push_back(item);
@@ -70,8 +103,53 @@ impl<Out: Data> Source for FakeOperator<Out> {
     }
 }
 
+/// Set up `operator` as the only replica of a block, and call `next` until it returns
+/// `Terminate`.
+///
+/// Returns all the elements emitted by the operator, `Terminate` included. The operator is set
+/// up without any previous block, so the first operator of the chain should be a
+/// [`FakeOperator`].
+pub fn run_operator<Op: Operator>(operator: Op) -> Vec<StreamElement<Op::Out>> {
+    FakeNetworkTopology::<()>::new(0, 0).run(operator)
+}
+
+/// Run `component` in a local job that reads the items of `input` and collects the output.
+///
+/// This tests a component in isolation, without the rest of the job it is spliced into with
+/// [`Stream::apply`]. The input is read by a single replica, but the component may still
+/// distribute the items, so the order of the output is the one of
+/// [`collect_vec`](Stream::collect_vec).
+///
+/// ```
+/// # use renoir::operator::Operator;
+/// # use renoir::test::run_component;
+/// # use renoir::Stream;
+/// fn even_squares(
+///     s: Stream<impl Operator<Out = u32> + 'static>,
+/// ) -> Stream<impl Operator<Out = u32>> {
+///     s.filter(|n| n % 2 == 0).map(|n| n * n)
+/// }
+///
+/// assert_eq!(run_component(0..10, even_squares), vec![0, 4, 16, 36, 64]);
+/// ```
+pub fn run_component<I, Op, C>(input: I, component: C) -> Vec<Op::Out>
+where
+    I: IntoIterator,
+    I::IntoIter: Send + 'static,
+    I::Item: Send,
+    Op: Operator + 'static,
+    Op::Out: ExchangeData,
+    C: FnOnce(Stream<IteratorSource<I::IntoIter>>) -> Stream<Op>,
+{
+    let env = StreamContext::new_local();
+    let res = env.stream_iter(input).apply(component).collect_vec();
+    env.execute_blocking();
+    res.get().unwrap()
+}
+
 pub(crate) struct FakeNetworkTopology<T: ExchangeData> {
     topology: NetworkTopology,
+    #[cfg_attr(not(test), allow(dead_code))]
     senders: Vec<Vec<(Coord, NetworkSender<T>)>>,
     prev: Vec<(Coord, TypeId)>,
 }
@@ -116,12 +194,87 @@ impl<T: ExchangeData> FakeNetworkTopology<T> {
             prev: self.prev.clone(),
             network: &mut self.topology,
             batch_mode: BatchMode::adaptive(100, Duration::from_millis(100)),
+            watermark_idleness: None,
+            tick: None,
+            skew_warning: None,
+            stuck_timeout: None,
+            savepoint: Default::default(),
+            host_weights: Default::default(),
+            local_replicas: Default::default(),
+            shutdown: Default::default(),
+            errors: Default::default(),
+        }
+    }
+
+    /// Set up `operator` with this topology and call `next` until it returns `Terminate`,
+    /// returning all the elements it emitted.
+    pub(crate) fn run<Op: Operator>(&mut self, mut operator: Op) -> Vec<StreamElement<Op::Out>> {
+        operator.setup(&mut self.metadata());
+        let mut res = vec![];
+        loop {
+            let el = operator.next();
+            let terminate = matches!(el, StreamElement::Terminate);
+            res.push(el);
+            if terminate {
+                return res;
+            }
         }
     }
 
     /// Get a mutable reference to the fake network topology's senders.
     #[must_use]
+    #[cfg(test)]
     pub(crate) fn senders_mut(&mut self) -> &mut Vec<Vec<(Coord, NetworkSender<T>)>> {
         &mut self.senders
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::network::NetworkMessage;
+    use crate::operator::{Start, StreamElement};
+    use crate::test::{run_operator, FakeNetworkTopology, FakeOperator};
+
+    #[test]
+    fn run_fake_operator() {
+        let elements = vec![
+            StreamElement::Item(1),
+            StreamElement::Watermark(Default::default()),
+            StreamElement::FlushAndRestart,
+        ];
+        let mut expected = elements.clone();
+        expected.push(StreamElement::Terminate);
+        assert_eq!(
+            run_operator(FakeOperator::from_elements(elements)),
+            expected
+        );
+    }
+
+    #[test]
+    fn run_start_block() {
+        let mut t = FakeNetworkTopology::new(1, 2);
+        let (from1, sender1) = t.senders_mut()[0].pop().unwrap();
+        let (from2, sender2) = t.senders_mut()[0].pop().unwrap();
+        let start = Start::single(sender1.receiver_endpoint.prev_block_id, None);
+
+        for (from, sender, item) in [(from1, &sender1, 1), (from2, &sender2, 2)] {
+            let batch = vec![StreamElement::Item(item), StreamElement::FlushAndRestart];
+            sender.send(NetworkMessage::new_batch(batch, from)).unwrap();
+        }
+        for (from, sender) in [(from1, &sender1), (from2, &sender2)] {
+            let terminate = NetworkMessage::new_single(StreamElement::Terminate, from);
+            sender.send(terminate).unwrap();
+        }
+
+        // the block restarts and terminates only when all the previous replicas did
+        assert_eq!(
+            t.run(start),
+            vec![
+                StreamElement::Item(1),
+                StreamElement::Item(2),
+                StreamElement::FlushAndRestart,
+                StreamElement::Terminate
+            ]
+        );
+    }
+}
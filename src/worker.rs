@@ -1,10 +1,17 @@
-use std::cell::RefCell;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::panic::AssertUnwindSafe;
 use std::thread::JoinHandle;
+use std::time::Instant;
+
+use core_affinity::CoreId;
 
 use crate::block::{Block, BlockStructure};
 use crate::network::Coord;
 use crate::operator::{Operator, StreamElement};
+use crate::profiler::{get_profiler, Profiler};
 use crate::scheduler::ExecutionMetadata;
+use crate::shutdown::ShutdownRecorder;
 
 thread_local! {
     /// Coordinates of the replica the current worker thread is working on.
@@ -21,73 +28,117 @@ pub fn replica_coord() -> Option<Coord> {
     COORD.with(|x| *x.borrow())
 }
 
-/// Call a function if this struct goes out of scope without calling `defuse`, including during a
-/// panic stack-unwinding.
-struct CatchPanic<F: FnOnce()> {
-    /// True if the function should be called.
-    primed: bool,
-    /// Function to call.
-    ///
-    /// The `Drop` implementation will move out the function.
-    handler: Option<F>,
+/// The panic that crashed the worker of a replica.
+#[derive(Debug)]
+pub(crate) struct WorkerPanic {
+    /// The replica that crashed.
+    pub coord: Coord,
+    /// The message of the panic.
+    pub message: String,
+    /// When the worker crashed.
+    pub at: Instant,
 }
 
-impl<F: FnOnce()> CatchPanic<F> {
-    fn new(handler: F) -> Self {
-        Self {
-            primed: true,
-            handler: Some(handler),
-        }
-    }
-
-    /// Avoid calling the function on drop.
-    fn defuse(&mut self) {
-        self.primed = false;
-    }
-}
+/// Record when the worker starts unwinding from a panic, if this struct goes out of scope during a
+/// panic.
+struct PanicTime<'a>(&'a Cell<Option<Instant>>);
 
-impl<F: FnOnce()> Drop for CatchPanic<F> {
+impl Drop for PanicTime<'_> {
     fn drop(&mut self) {
-        if self.primed {
-            (self.handler.take().unwrap())();
+        if std::thread::panicking() {
+            self.0.set(Some(Instant::now()));
         }
     }
 }
 
+/// Spawn the thread of a replica of the block, pinning it to the `core` and with a stack of
+/// `stack_size` bytes if specified.
 pub(crate) fn spawn_worker<OperatorChain>(
     mut block: Block<OperatorChain>,
     metadata: &mut ExecutionMetadata,
-) -> (JoinHandle<()>, BlockStructure)
+    core: Option<CoreId>,
+    stack_size: Option<usize>,
+) -> (JoinHandle<Result<(), WorkerPanic>>, BlockStructure)
 where
     OperatorChain: Operator + 'static,
     OperatorChain::Out: Send,
 {
     let coord = metadata.coord;
+    let shutdown = metadata.shutdown.clone();
 
     debug!("starting worker {}: {}", coord, block.to_string(),);
 
     block.operators.setup(metadata);
-    let structure = block.operators.structure();
+    let mut structure = block.operators.structure();
+    structure.labels = block.labels.clone();
 
-    let join_handle = std::thread::Builder::new()
-        .name(format!("block-{}", block.id))
+    let mut builder = std::thread::Builder::new().name(format!("block-{}", block.id));
+    if let Some(stack_size) = stack_size {
+        builder = builder.stack_size(stack_size);
+    }
+    let join_handle = builder
         .spawn(move || {
+            if let Some(core) = core {
+                if !core_affinity::set_for_current(core) {
+                    warn!(
+                        "worker {}: cannot pin the thread to core {}",
+                        coord, core.id
+                    );
+                }
+            }
             // remember in the thread-local the coordinate of this block
             COORD.with(|x| *x.borrow_mut() = Some(coord));
-            do_work(block, coord)
+            if !block.labels.is_empty() {
+                get_profiler().labels(&block.labels);
+            }
+            do_work(block, coord, shutdown)
         })
         .unwrap();
 
     (join_handle, structure)
 }
 
-fn do_work<Op: Operator>(mut block: Block<Op>, coord: Coord) {
-    let mut catch_panic = CatchPanic::new(|| {
-        error!("worker {} crashed!", coord);
-    });
-    while !matches!(block.operators.next(), StreamElement::Terminate) {
-        // nothing to do
+fn do_work<Op: Operator>(
+    mut block: Block<Op>,
+    coord: Coord,
+    shutdown: ShutdownRecorder,
+) -> Result<(), WorkerPanic> {
+    let crashed_at = Cell::new(None);
+    let panic_time = PanicTime(&crashed_at);
+    // the block is moved inside, so that it is dropped while unwinding: its senders disconnect
+    // from the next blocks and its operators see the panic
+    let res = std::panic::catch_unwind(AssertUnwindSafe(move || {
+        // dropped before the block, so before the connected replicas can see the crash
+        let _panic_time = panic_time;
+        while !matches!(block.operators.next(), StreamElement::Terminate) {
+            // nothing to do
+        }
+    }));
+    match res {
+        Ok(()) => {
+            info!("worker {} completed", coord);
+            shutdown.terminated(coord);
+            Ok(())
+        }
+        Err(payload) => {
+            let message = panic_message(payload.as_ref());
+            error!("worker {} crashed: {}", coord, message);
+            Err(WorkerPanic {
+                coord,
+                message,
+                at: crashed_at.get().unwrap_or_else(Instant::now),
+            })
+        }
+    }
+}
+
+/// Extract the message of a panic from its payload.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
     }
-    catch_panic.defuse();
-    info!("worker {} completed", coord);
 }
@@ -0,0 +1,33 @@
+use itertools::Itertools;
+
+use renoir::operator::source::{IteratorSource, ParallelIteratorSource};
+use renoir::operator::WatermarkAlignment;
+use utils::TestHelper;
+
+mod utils;
+
+#[test]
+fn align_watermarks_with_single_replica_stream() {
+    TestHelper::local_remote_env(|env| {
+        let group = WatermarkAlignment::new(10);
+        // a single replica, which is on a single host
+        let single = env
+            .stream(IteratorSource::new(0..1000i64))
+            .add_timestamps(|&n| n, |_, &ts| Some(ts))
+            .align_watermarks(&group);
+        // a replica for each core of each host
+        let parallel = env
+            .stream(ParallelIteratorSource::new(|id, instances| {
+                (0..1000i64).filter(move |n| *n as u64 % instances == id)
+            }))
+            .add_timestamps(|&n| n, |_, &ts| Some(ts))
+            .align_watermarks(&group);
+
+        let res = single.shuffle().merge(parallel).collect_vec();
+        env.execute_blocking();
+        if let Some(res) = res.get() {
+            let expected = (0..1000i64).flat_map(|n| [n, n]).collect_vec();
+            assert_eq!(res.into_iter().sorted().collect_vec(), expected);
+        }
+    });
+}
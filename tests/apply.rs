@@ -0,0 +1,38 @@
+use itertools::Itertools;
+
+use renoir::operator::Operator;
+use renoir::test::run_component;
+use renoir::{Stream, StreamContext};
+
+/// Count the occurrences of each word, ignoring the case.
+fn word_count(
+    s: Stream<impl Operator<Out = String> + 'static>,
+) -> Stream<impl Operator<Out = (String, usize)>> {
+    s.map(|w| w.to_lowercase())
+        .group_by_count(|w| w.clone())
+        .unkey()
+}
+
+#[test]
+fn component_in_isolation_and_spliced() {
+    let text = "the quick brown fox jumps over The lazy dog and THE cat";
+    let words = text.split(' ').map(String::from).collect_vec();
+
+    let isolated = run_component(words.clone(), word_count)
+        .into_iter()
+        .sorted()
+        .collect_vec();
+
+    let env = StreamContext::new_local();
+    let spliced = env
+        .stream_iter(vec![text.to_string()])
+        .flat_map(|line| line.split(' ').map(String::from).collect_vec())
+        .shuffle()
+        .apply(word_count)
+        .collect_vec();
+    env.execute_blocking();
+    let spliced = spliced.get().unwrap().into_iter().sorted().collect_vec();
+
+    assert_eq!(isolated, spliced);
+    assert!(isolated.contains(&("the".to_string(), 3)));
+}
@@ -0,0 +1,55 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use itertools::Itertools;
+
+use renoir::{Autoscale, BatchMode};
+use utils::TestHelper;
+
+mod utils;
+
+#[test]
+fn autoscale_keeps_all_items() {
+    TestHelper::local_remote_env(|env| {
+        let res = env
+            .stream_iter(0..1000u32)
+            .shuffle_autoscale(Autoscale::new(1, 4))
+            .map(|x| x * 2)
+            .collect_vec();
+        env.execute_blocking();
+        if let Some(res) = res.get() {
+            let res = res.into_iter().sorted().collect_vec();
+            let expected = (0..1000u32).map(|x| x * 2).collect_vec();
+            assert_eq!(res, expected);
+        }
+    });
+}
+
+#[test]
+fn autoscale_scales_up_under_load() {
+    let used_threads = Arc::new(Mutex::new(HashSet::new()));
+    let threads = used_threads.clone();
+    let body = move |env: renoir::StreamContext| {
+        let threads = threads.clone();
+        let res = env
+            .stream_iter(0..400u32)
+            // send every item as soon as it is produced, so that the sender feels the backpressure
+            .batch_mode(BatchMode::single())
+            .shuffle_autoscale(Autoscale::new(1, 4).interval(Duration::from_millis(10)))
+            // synthetic load: each replica can process at most one item per millisecond
+            .map(move |x| {
+                std::thread::sleep(Duration::from_millis(1));
+                threads.lock().unwrap().insert(std::thread::current().id());
+                x
+            })
+            .collect_vec();
+        env.execute_blocking();
+        let res = res.get().unwrap().into_iter().sorted().collect_vec();
+        assert_eq!(res, (0..400u32).collect_vec());
+    };
+    TestHelper::local_env(Arc::new(body), 4);
+
+    let used = used_threads.lock().unwrap().len();
+    assert!(used > 1, "the load was handled by a single replica");
+}
@@ -0,0 +1,398 @@
+use renoir::config::{ConfigBuilder, ConfigError};
+use renoir::{ExecutionError, Replication, RuntimeConfig, StreamContext, ValidationError};
+
+#[test]
+fn local_shortcut() {
+    let env = StreamContext::local(4);
+    let explicit = StreamContext::new(RuntimeConfig::local(4).unwrap());
+    assert_eq!(env.config(), explicit.config());
+    assert_eq!(env.parallelism(), 4);
+
+    let res = env.stream_iter(0..10u32).map(|n| n * 2).collect_vec();
+    env.execute_blocking();
+    assert_eq!(
+        res.get().unwrap(),
+        (0..10).map(|n| n * 2).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+#[should_panic]
+fn local_shortcut_zero_cores() {
+    StreamContext::local(0);
+}
+
+#[test]
+fn from_config_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(
+        &path,
+        r#"
+[[host]]
+address = "127.0.0.1"
+base_port = 21400
+num_cores = 2
+"#,
+    )
+    .unwrap();
+    let env = StreamContext::from_config_file(&path).unwrap();
+    assert_eq!(*env.config(), RuntimeConfig::remote(&path).unwrap());
+
+    assert!(StreamContext::from_config_file(dir.path().join("missing.toml")).is_err());
+}
+
+#[test]
+fn remote_with_secrets_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(
+        &path,
+        r#"
+[[host]]
+address = "127.0.0.1"
+base_port = 21800
+num_cores = 2
+[host.ssh]
+username = "renoir"
+
+[[host]]
+address = "127.0.0.1"
+base_port = 21900
+num_cores = 2
+[host.ssh]
+key_file = "/home/renoir/.ssh/id_ed25519"
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("config.secrets.toml"),
+        r#"
+[[host]]
+address = "127.0.0.1"
+base_port = 21800
+password = "hunter2"
+
+[[host]]
+address = "127.0.0.1"
+base_port = 21900
+key_passphrase = "correct horse"
+"#,
+    )
+    .unwrap();
+
+    // the secrets file next to the configuration is found by convention
+    let RuntimeConfig::Remote(remote) = RuntimeConfig::remote(&path).unwrap() else {
+        unreachable!()
+    };
+    let ssh = &remote.hosts[0].ssh;
+    assert_eq!(ssh.username.as_deref(), Some("renoir"));
+    assert_eq!(ssh.password.as_deref(), Some("hunter2"));
+    assert_eq!(ssh.key_passphrase, None);
+    let ssh = &remote.hosts[1].ssh;
+    assert_eq!(ssh.password, None);
+    assert_eq!(ssh.key_passphrase.as_deref(), Some("correct horse"));
+
+    // the secrets must match a host
+    let secrets = dir.path().join("other.toml");
+    std::fs::write(
+        &secrets,
+        "[[host]]\naddress = \"10.0.0.1\"\npassword = \"x\"\n",
+    )
+    .unwrap();
+    let err = RuntimeConfig::remote_with_secrets(&path, &secrets).unwrap_err();
+    assert!(err.to_string().contains("do not match any host"), "{err}");
+
+    // and cannot give a password to a host with a key file
+    std::fs::write(
+        &secrets,
+        "[[host]]\naddress = \"127.0.0.1\"\nbase_port = 21900\npassword = \"x\"\n",
+    )
+    .unwrap();
+    let err = RuntimeConfig::remote_with_secrets(&path, &secrets).unwrap_err();
+    assert!(
+        err.to_string().contains("both password and key file"),
+        "{err}"
+    );
+}
+
+#[test]
+fn remote_from_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let host =
+        |address: &str| format!("address = \"{address}\"\nbase_port = 21600\nnum_cores = 1\n");
+    for name in ["charlie", "alpha", "bravo"] {
+        std::fs::write(dir.path().join(format!("{name}.toml")), host(name)).unwrap();
+    }
+    std::fs::write(dir.path().join("README.md"), "not a host").unwrap();
+
+    let RuntimeConfig::Remote(remote) = RuntimeConfig::remote_from_dir(dir.path()).unwrap() else {
+        unreachable!()
+    };
+    let addresses = remote
+        .hosts
+        .iter()
+        .map(|h| h.address.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(addresses, ["alpha", "bravo", "charlie"]);
+
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, format!("[[host]]\n{}", host("x"))).unwrap();
+    let err = RuntimeConfig::remote_from_dir(dir.path()).unwrap_err();
+    assert!(matches!(err, ConfigError::Invalid(..)), "{err}");
+}
+
+#[test]
+fn remote_from_dir_host_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |name: &str| {
+        let host = format!("address = \"{name}\"\nbase_port = 21700\nnum_cores = 1\n");
+        std::fs::write(dir.path().join(format!("{name}.toml")), host).unwrap();
+    };
+    for name in ["node-10", "node-8", "node-9"] {
+        write(name);
+    }
+    let RuntimeConfig::Remote(remote) = RuntimeConfig::remote_from_dir(dir.path()).unwrap() else {
+        unreachable!()
+    };
+    let addresses = remote
+        .hosts
+        .iter()
+        .map(|h| h.address.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(addresses, ["node-8", "node-9", "node-10"]);
+
+    write("node-12");
+    let err = RuntimeConfig::remote_from_dir(dir.path()).unwrap_err();
+    assert!(err.to_string().contains("missing host index"), "{err}");
+
+    write("worker-12");
+    let err = RuntimeConfig::remote_from_dir(dir.path()).unwrap_err();
+    assert!(err.to_string().contains("duplicate host index 12"), "{err}");
+}
+
+#[test]
+fn thread_stack_size() {
+    let mut config = RuntimeConfig::local(2).unwrap();
+    if let RuntimeConfig::Local(local) = &mut config {
+        local.thread_stack_size = Some(256 << 20);
+    }
+    let env = StreamContext::new(config);
+    let res = env
+        .stream_iter(0..4u64)
+        .map(|n| {
+            // far larger than the default stack of the threads
+            let buffer = std::hint::black_box([n as u8; 32 << 20]);
+            buffer.iter().map(|&b| b as u64).sum::<u64>()
+        })
+        .collect_vec();
+    env.execute_blocking();
+
+    let sums = res.get().unwrap();
+    assert_eq!(sums, (0..4).map(|n| n * (32 << 20)).collect::<Vec<_>>());
+}
+
+#[test]
+fn unresolvable_host() {
+    let config = ConfigBuilder::new_remote()
+        .parse_toml_str(
+            r#"
+[[host]]
+address = "127.0.0.1"
+base_port = 21500
+num_cores = 1
+
+[[host]]
+address = "renoir-missing-host.invalid"
+base_port = 21500
+num_cores = 1
+"#,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+    let RuntimeConfig::Remote(remote) = config else {
+        unreachable!()
+    };
+
+    let err = remote.resolve_hosts().unwrap_err();
+    assert!(matches!(err, ConfigError::Unresolvable(..)), "{err}");
+    assert!(
+        err.to_string().contains("renoir-missing-host.invalid"),
+        "{err}"
+    );
+}
+
+#[test]
+fn panicking_replica() {
+    let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+    let res = env
+        .stream_par_iter(0..1000u32)
+        .shuffle()
+        .map(|n| {
+            assert_ne!(n, 500, "cannot process the item");
+            n
+        })
+        .shuffle()
+        .collect_vec();
+
+    // the replicas connected to the crashed one are torn down instead of waiting for it
+    let err = env.try_execute_blocking().unwrap_err();
+    let ExecutionError::Panic {
+        message, crashed, ..
+    } = &err
+    else {
+        panic!("unexpected error: {err}");
+    };
+    assert!(message.contains("cannot process the item"), "{err}");
+    assert!(*crashed >= 1, "{err}");
+    assert!(res.get().is_none());
+}
+
+#[test]
+fn start_new_block_in_job_graph() {
+    let blocks = |split: bool| {
+        let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+        let s = env.stream_par_iter(0..100u32).map(|n| n * 2);
+        let res = if split {
+            s.start_new_block().map(|n| n + 1).collect_vec()
+        } else {
+            s.map(|n| n + 1).collect_vec()
+        };
+        let graph = env.job_graph();
+        env.execute_blocking();
+
+        let mut res = res.get().unwrap();
+        res.sort();
+        assert_eq!(res, (0..100).map(|n| n * 2 + 1).collect::<Vec<_>>());
+        graph.matches("subgraph cluster_block").count()
+    };
+
+    // the sink is always in a block of its own, with a single replica
+    assert_eq!(blocks(false), 2);
+    assert_eq!(blocks(true), 3);
+}
+
+#[test]
+fn labels_in_job_graph() {
+    let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+    let res = env
+        .stream_par_iter(0..10u32)
+        .label("stage", "ingest")
+        .shuffle()
+        .label("team", "ads")
+        .label("stage", "enrichment")
+        .collect_vec();
+    let graph = env.job_graph();
+    env.execute_blocking();
+    assert_eq!(res.get().unwrap().len(), 10);
+
+    // the labels are not propagated to the next blocks
+    assert_eq!(graph.matches("[stage=ingest]").count(), 1, "{graph}");
+    assert_eq!(
+        graph.matches("[stage=enrichment, team=ads]").count(),
+        1,
+        "{graph}"
+    );
+}
+
+#[test]
+fn job_graph_before_execution() {
+    let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+    let res = env
+        .stream_iter(0..10u32)
+        .group_by(|n| n % 2)
+        .fold(0, |acc, n| *acc += n)
+        .unkey()
+        .shuffle()
+        .collect_vec();
+
+    // the graph is available without executing the job
+    let graph = env.job_graph();
+    assert!(graph.starts_with("digraph renoir"), "{graph}");
+    // the sink is in a block of its own, with a single replica
+    for block_id in 0..4 {
+        assert!(
+            graph.contains(&format!("subgraph cluster_block{block_id} ")),
+            "{graph}"
+        );
+    }
+    assert!(!graph.contains("cluster_block4"), "{graph}");
+    assert!(graph.contains("group-by (1 -> 2)"), "{graph}");
+    assert!(graph.contains("shuffle (2 -> 2)"), "{graph}");
+    assert!(graph.contains("only-one (2 -> 1)"), "{graph}");
+
+    let mermaid = env.job_graph_mermaid();
+    assert!(mermaid.starts_with("flowchart TD"), "{mermaid}");
+    assert_eq!(mermaid.matches("  subgraph block").count(), 4, "{mermaid}");
+
+    // and it does not prevent the execution
+    env.execute_blocking();
+    let mut res = res.get().unwrap();
+    res.sort();
+    assert_eq!(res, vec![(0, 20), (1, 25)]);
+}
+
+#[test]
+fn block_without_replicas() {
+    let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+    let res = env
+        .stream_iter(0..10u32)
+        .replication(Replication::Limited(0))
+        .map(|n| n + 1)
+        .collect_vec();
+
+    // the execution fails before starting instead of waiting forever for the missing replicas
+    let err = env.try_execute_blocking().unwrap_err();
+    let ExecutionError::NoReplicas { block_id, .. } = &err else {
+        panic!("unexpected error: {err}");
+    };
+    assert_eq!(*block_id, 1, "{err}");
+    assert!(err.to_string().contains("block 1 has no replicas"), "{err}");
+    assert!(res.get().is_none());
+}
+
+#[test]
+fn validate_reports_all_problems() {
+    let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+    let res = env
+        .stream_iter(0..10u32)
+        .replication(Replication::Limited(0))
+        .map(|n| n + 1)
+        .collect_vec();
+    // a stream without a sink
+    env.stream_iter(0..10u32).map(|n| n * 2);
+
+    let errors = env.validate().unwrap_err();
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert_eq!(errors[0], ValidationError::MissingSink { block_id: 3 });
+    let ValidationError::NoReplicas { block_id, .. } = &errors[1] else {
+        panic!("unexpected error: {}", errors[1]);
+    };
+    assert_eq!(*block_id, 1);
+    assert!(errors[1].to_string().contains("block 1 has no replicas"));
+    assert!(res.get().is_none());
+}
+
+#[test]
+fn validate_iteration() {
+    let env = StreamContext::new(RuntimeConfig::local(4).unwrap());
+    let (state, items) = env.stream_iter(0..10u32).shuffle().iterate(
+        3,
+        0u32,
+        |s, _| s.map(|n| n + 1),
+        |delta: &mut u32, n| *delta += n,
+        |state, delta| *state += delta,
+        |_| true,
+    );
+    let state = state.collect_vec();
+    let items = items.collect_vec();
+
+    // the loop of the iteration is not a cycle
+    assert_eq!(env.validate(), Ok(()));
+    env.execute_blocking();
+    assert_eq!(state.get().unwrap().len(), 1);
+    let mut items = items.get().unwrap();
+    items.sort();
+    assert_eq!(items, (3..13).collect::<Vec<_>>());
+}
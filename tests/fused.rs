@@ -0,0 +1,30 @@
+use itertools::Itertools;
+
+use utils::TestHelper;
+
+mod utils;
+
+#[test]
+fn fused_stream() {
+    TestHelper::local_remote_env(|env| {
+        let res = env
+            .stream_iter(0..100u32)
+            .fused(|f| {
+                f.map(|n| n * 3)
+                    .filter(|n| n % 2 == 0)
+                    .filter_map(|n| n.checked_sub(30))
+                    .map(|n| n.to_string())
+            })
+            .collect_vec();
+        env.execute_blocking();
+        if let Some(res) = res.get() {
+            let expected = (0..100u32)
+                .map(|n| n * 3)
+                .filter(|n| n % 2 == 0)
+                .filter_map(|n| n.checked_sub(30))
+                .map(|n| n.to_string())
+                .collect_vec();
+            assert_eq!(res, expected);
+        }
+    });
+}
@@ -24,3 +24,22 @@ fn group_by_stream() {
         }
     });
 }
+
+#[test]
+fn group_by_multi_stream() {
+    TestHelper::local_remote_env(|env| {
+        let source = IteratorSource::new(
+            vec![vec!['a', 'b'], vec!['b'], vec![], vec!['c', 'a']].into_iter(),
+        );
+        let res = env
+            .stream(source)
+            .group_by_multi(|keys| keys.clone())
+            .fold(0, |count, _| *count += 1)
+            .collect_vec();
+        env.execute_blocking();
+        if let Some(res) = res.get() {
+            let res = res.into_iter().sorted().collect_vec();
+            assert_eq!(res, vec![('a', 2), ('b', 2), ('c', 1)]);
+        }
+    });
+}
@@ -168,3 +168,36 @@ fn test_iterate_primes() {
         }
     });
 }
+
+#[test]
+fn test_iterate_increment_converges() {
+    TestHelper::local_remote_env(|env| {
+        let n = 5u64;
+        let target = 10u64;
+
+        let source = IteratorSource::new(0..n);
+        let (state, res) = env.stream(source).shuffle().iterate(
+            100,
+            (0usize, 0u64),
+            // every round moves the items one step closer to the target
+            move |s, _state| s.map(move |x| (x + 1).min(target)),
+            move |below: &mut u64, x| *below += (x < target) as u64,
+            |state, below| state.1 += below,
+            // stop once no item is below the target anymore
+            |state| {
+                state.0 += 1;
+                std::mem::take(&mut state.1) > 0
+            },
+        );
+        let state = state.collect_vec();
+        let res = res.collect_vec();
+        env.execute_blocking();
+
+        if let Some(state) = state.get() {
+            assert_eq!(state, vec![(target as usize, 0)]);
+        }
+        if let Some(res) = res.get() {
+            assert_eq!(res, vec![target; n as usize]);
+        }
+    });
+}
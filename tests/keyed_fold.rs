@@ -92,3 +92,27 @@ fn fold_shuffled_keyed_stream() {
         }
     });
 }
+
+#[test]
+fn group_by_fold_matches_group_by_then_fold() {
+    TestHelper::local_remote_env(|env| {
+        let fused = env
+            .stream(IteratorSource::new(0..1000u64))
+            .shuffle()
+            .group_by_fold(|n| n % 7, 0u64, |s, n| *s += n, |s1, s2| *s1 += s2)
+            .collect_vec();
+        let separate = env
+            .stream(IteratorSource::new(0..1000u64))
+            .shuffle()
+            .group_by(|n| n % 7)
+            .fold(0u64, |s, n| *s += n)
+            .collect_vec();
+        env.execute_blocking();
+        if let (Some(mut fused), Some(mut separate)) = (fused.get(), separate.get()) {
+            fused.sort_unstable();
+            separate.sort_unstable();
+            assert_eq!(fused.len(), 7);
+            assert_eq!(fused, separate);
+        }
+    });
+}
@@ -3,6 +3,7 @@ use std::str::FromStr;
 use itertools::Itertools;
 
 use renoir::operator::source::IteratorSource;
+use renoir::{BatchMode, Replication};
 use utils::TestHelper;
 
 mod utils;
@@ -43,3 +44,45 @@ fn map_keyed_stream() {
         }
     });
 }
+
+#[test]
+fn map_with_state_stream() {
+    TestHelper::local_remote_env(|env| {
+        let source = IteratorSource::new(0..10u8);
+        let res = env
+            .stream(source)
+            .map_with_state(0usize, |count, n| {
+                *count += 1;
+                (n, *count)
+            })
+            .collect_vec();
+        env.execute_blocking();
+        if let Some(res) = res.get() {
+            let expected = (0..10u8).zip(1..=10usize).collect_vec();
+            assert_eq!(res, expected);
+        }
+    });
+}
+
+#[test]
+fn map_with_state_keeps_source_order() {
+    TestHelper::local_remote_env(|env| {
+        let source = IteratorSource::new(0..1000u64);
+        let res = env
+            .stream(source)
+            // many small batches, through a chain of single replica blocks
+            .batch_mode(BatchMode::fixed(7))
+            .replication(Replication::One)
+            .map_with_state(0u64, |sum, n| {
+                *sum += n;
+                (n, *sum)
+            })
+            .replication(Replication::One)
+            .collect_vec();
+        env.execute_blocking();
+        if let Some(res) = res.get() {
+            let expected = (0..1000u64).map(|n| (n, n * (n + 1) / 2)).collect_vec();
+            assert_eq!(res, expected);
+        }
+    });
+}
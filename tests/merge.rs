@@ -4,7 +4,8 @@ use std::sync::Arc;
 use itertools::Itertools;
 
 use renoir::operator::source::IteratorSource;
-use renoir::Replication;
+use renoir::operator::MergeElement;
+use renoir::{GroupByHasher, Replication, StreamContext};
 use utils::{TestHelper, WatermarkChecker};
 
 mod utils;
@@ -142,3 +143,40 @@ fn merge_keyed_stream() {
         }
     });
 }
+
+#[test]
+#[should_panic(expected = "must be partitioned the same way")]
+fn merge_keyed_stream_different_hasher() {
+    let env = StreamContext::new_local();
+    let stream1 = env.stream_iter(0..100u64).group_by(|x| x % 3);
+    let stream2 = env
+        .stream_iter(100..200u64)
+        .group_by_hasher(GroupByHasher::SipHash(1, 2))
+        .group_by(|x| x % 3);
+
+    stream1.merge(stream2).reduce(|x, y| *x += y).collect_vec();
+}
+
+#[test]
+fn co_process_different_types() {
+    TestHelper::local_remote_env(|env| {
+        let numbers = env.stream(IteratorSource::new(0..100i32));
+        let words = env.stream(IteratorSource::new((0..10).map(|n| "x".repeat(n))));
+
+        let res = numbers
+            .co_process(words, |e| match e {
+                MergeElement::Left(n) => format!("number {n}"),
+                MergeElement::Right(w) => format!("word of {}", w.len()),
+            })
+            .collect_vec();
+        env.execute_blocking();
+        if let Some(res) = res.get() {
+            let expected = (0..100)
+                .map(|n| format!("number {n}"))
+                .chain((0..10).map(|n| format!("word of {n}")))
+                .sorted()
+                .collect_vec();
+            assert_eq!(res.into_iter().sorted().collect_vec(), expected);
+        }
+    });
+}
@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+
+use renoir::operator::StreamElement;
+use renoir::structure::ConnectionStrategy;
+use renoir::{group_by_hash, RuntimeConfig, StreamContext};
+
+#[test]
+fn group_by_routing() {
+    let (config, log) = RuntimeConfig::local_recorded(2).unwrap();
+    let env = StreamContext::new(config);
+    let res = env
+        .stream_iter(0..100u32)
+        .group_by(|n| n % 4)
+        .map(|(_, n)| n as u64)
+        .unkey()
+        .collect_vec();
+    env.execute_blocking();
+    assert_eq!(res.get().unwrap().len(), 100);
+
+    // the items of type u32 cross only the edge from the source to the group by
+    let messages = log.messages::<u32>();
+    let senders = messages.iter().map(|m| m.sender).collect::<HashSet<_>>();
+    assert_eq!(senders.len(), 1, "the source has a single replica");
+    let receivers = messages.iter().map(|m| m.receiver).collect::<HashSet<_>>();
+    assert_eq!(receivers.len(), 2);
+
+    let mut items = vec![];
+    for message in &messages {
+        for el in &message.elements {
+            if let StreamElement::Item(n) = el {
+                let replica = group_by_hash(&(n % 4)) % 2;
+                assert_eq!(
+                    message.receiver.replica_id, replica,
+                    "{n} sent to the wrong replica"
+                );
+                items.push(*n);
+            }
+        }
+    }
+    items.sort_unstable();
+    assert_eq!(items, (0..100).collect::<Vec<_>>());
+
+    // each replica of the group by receives the end of the stream once, as the last element
+    for receiver in receivers {
+        let elements = messages
+            .iter()
+            .filter(|m| m.receiver == receiver)
+            .flat_map(|m| m.elements.iter())
+            .collect::<Vec<_>>();
+        let terminates = elements
+            .iter()
+            .filter(|el| matches!(el, StreamElement::Terminate))
+            .count();
+        assert_eq!(terminates, 1);
+        assert_eq!(elements.last(), Some(&&StreamElement::Terminate));
+    }
+}
+
+#[test]
+fn connect_with_all() {
+    let (config, log) = RuntimeConfig::local_recorded(2).unwrap();
+    let env = StreamContext::new(config);
+    let res = env
+        .stream_iter(0..10u32)
+        .connect_with(ConnectionStrategy::All)
+        .map(|n| n as u64)
+        .collect_vec();
+    env.execute_blocking();
+    // each of the two replicas after the connection received all the items
+    assert_eq!(res.get().unwrap().len(), 20);
+
+    let messages = log.messages::<u32>();
+    let receivers = messages.iter().map(|m| m.receiver).collect::<HashSet<_>>();
+    assert_eq!(receivers.len(), 2);
+    for receiver in receivers {
+        let items = messages
+            .iter()
+            .filter(|m| m.receiver == receiver)
+            .flat_map(|m| m.elements.iter().filter_map(|el| el.value()))
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(items, (0..10).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+#[should_panic(expected = "needs a key")]
+fn connect_with_group_by_without_key() {
+    let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+    let _ = env
+        .stream_iter(0..10u32)
+        .connect_with(ConnectionStrategy::GroupBy);
+}
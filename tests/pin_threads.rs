@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use itertools::Itertools;
+
+use renoir::RuntimeConfig;
+use utils::TestHelper;
+
+mod utils;
+
+#[test]
+fn pinned_threads() {
+    let parallelism = 3;
+    let cores = Arc::new(Mutex::new(HashMap::new()));
+    let cores2 = cores.clone();
+    let body = move |env: renoir::StreamContext| {
+        let cores = cores2.clone();
+        let res = env
+            .stream_par_iter(|i, _| i..i + 1)
+            .map(move |x| {
+                // on linux the available cores are the ones in the affinity mask of the thread
+                let available = core_affinity::get_core_ids().unwrap_or_default();
+                cores
+                    .lock()
+                    .unwrap()
+                    .insert(std::thread::current().id(), available.len());
+                x
+            })
+            .collect_vec();
+        env.execute_blocking();
+        assert_eq!(res.get().unwrap().len(), parallelism);
+    };
+    let config = RuntimeConfig::local_pinned(parallelism as u64, Some(vec![0])).unwrap();
+    TestHelper::env_with_config(config, Arc::new(body));
+
+    let cores = cores.lock().unwrap();
+    assert_eq!(cores.len(), parallelism, "one thread per replica");
+    if cfg!(target_os = "linux") {
+        assert!(
+            cores.values().all(|&n| n == 1),
+            "{:?}",
+            cores.values().collect_vec()
+        );
+    }
+}
+
+#[test]
+fn pinned_threads_empty_core_list() {
+    assert!(RuntimeConfig::local_pinned(2, Some(vec![])).is_err());
+}
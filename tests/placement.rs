@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use renoir::StreamContext;
+use utils::TestHelper;
+
+mod utils;
+
+#[test]
+fn replicas_on_preferred_hosts() {
+    TestHelper::remote_env(
+        Arc::new(|env: StreamContext| {
+            let host_id = env.config().host_id().unwrap();
+            // the first two replicas of the source on the last host, the next two on the second
+            // one, the others where the scheduler places them
+            env.replica_placement(|block_id, replica| match (block_id, replica) {
+                (0, 0..=1) => Some(2),
+                (0, 2..=3) => Some(1),
+                _ => None,
+            });
+            // a replica for each of the 6 cores, each one generating 2 elements
+            let res = env
+                .stream_par_iter(0..12u32)
+                .map(move |n| (n, host_id))
+                .collect_vec();
+            env.execute_blocking();
+            if let Some(res) = res.get() {
+                let res = res.into_iter().sorted().collect_vec();
+                let expected = (0..12u32)
+                    .map(|n| (n, [2, 1, 0][n as usize / 4]))
+                    .collect_vec();
+                assert_eq!(res, expected);
+            }
+        }),
+        3,
+        2,
+    );
+}
@@ -0,0 +1,30 @@
+use renoir::config::LocalConfigBuilder;
+use renoir::{ReplayLog, StreamContext};
+
+/// A job whose output depends on the order in which the replicas receive their messages: the
+/// items of each key are concatenated in the order they arrive from the replicas of the sources.
+fn run(log: ReplayLog) -> Vec<u8> {
+    let config = LocalConfigBuilder::new(4).replay_log(log).build().unwrap();
+    let env = StreamContext::new(config);
+    let evens = env.stream_par_iter(0..2000u32).map(|n| n * 2);
+    let odds = env.stream_par_iter(0..2000u32).map(|n| n * 2 + 1);
+    let res = evens
+        .merge(odds)
+        .group_by(|n| n % 3)
+        .fold(Vec::new(), |v, n| v.push(n))
+        .collect_vec();
+    env.execute_blocking();
+    serde_json::to_vec(&res.get().unwrap()).unwrap()
+}
+
+#[test]
+fn replay_same_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let recorded = run(ReplayLog::record(dir.path()));
+    assert!(std::fs::read_dir(dir.path()).unwrap().count() > 0);
+
+    for _ in 0..3 {
+        let replayed = run(ReplayLog::replay(dir.path()).unwrap());
+        assert_eq!(replayed, recorded);
+    }
+}
@@ -55,3 +55,24 @@ fn rich_filter_map_keyed_stream() {
         }
     });
 }
+
+#[test]
+fn distinct_until_changed_keyed_stream() {
+    TestHelper::local_remote_env(|env| {
+        let keys = [0, 1, 0, 0, 1, 0, 1, 0];
+        let values = [1, 5, 1, 2, 5, 2, 6, 1];
+        let source = IteratorSource::new(keys.into_iter().zip(values));
+        let res = env
+            .stream(source)
+            .group_by(|&(key, _)| key)
+            .map(|(_, (_, value))| value)
+            .distinct_until_changed()
+            .collect_vec();
+        env.execute_blocking();
+        if let Some(mut res) = res.get() {
+            // the runs of each key are collapsed, even if interleaved with the other key
+            res.sort_by_key(|&(key, _)| key);
+            assert_eq!(res, &[(0, 1), (0, 2), (0, 1), (1, 5), (1, 6)]);
+        }
+    });
+}
@@ -0,0 +1,110 @@
+use itertools::Itertools;
+
+use renoir::operator::source::IteratorSource;
+use renoir::{RuntimeConfig, StreamContext};
+
+fn counting_job(env: &StreamContext, n: u64) -> renoir::prelude::StreamOutput<Vec<u64>> {
+    let source = IteratorSource::new(0..n).savepoint_id("numbers");
+    env.stream(source)
+        .stateful_map("count", 0u64, |count, _| {
+            *count += 1;
+            *count
+        })
+        .collect_vec()
+}
+
+#[test]
+fn savepoint_resume_counter() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("first");
+    let second = dir.path().join("second");
+    let config = RuntimeConfig::local(2).unwrap();
+
+    let env = StreamContext::new(config.clone());
+    env.savepoint(&first);
+    let res = counting_job(&env, 100);
+    env.execute_blocking();
+    assert_eq!(res.get().unwrap().last(), Some(&100));
+
+    // the source now has 50 more items: only those are counted, starting from 100
+    let env = StreamContext::from_savepoint(&first, config.clone()).unwrap();
+    env.savepoint(&second);
+    let res = counting_job(&env, 150);
+    env.execute_blocking();
+    assert_eq!(res.get().unwrap(), (101..=150).collect_vec());
+
+    let env = StreamContext::from_savepoint(&second, config).unwrap();
+    let res = counting_job(&env, 160);
+    env.execute_blocking();
+    assert_eq!(res.get().unwrap(), (151..=160).collect_vec());
+}
+
+/// Count the occurrences of each key, `n % 10`, among `0..n`.
+fn keyed_counting_job(
+    env: &StreamContext,
+    n: u64,
+) -> renoir::prelude::StreamOutput<Vec<(u64, u64)>> {
+    env.stream_par_iter(0..n)
+        .keyed_stateful_map(
+            "counts",
+            |n| n % 10,
+            0u64,
+            |count, _| {
+                *count += 1;
+                *count
+            },
+        )
+        .collect_vec()
+}
+
+/// The last count of each key.
+fn last_counts(res: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    res.into_iter()
+        .into_group_map()
+        .into_iter()
+        .map(|(key, counts)| (key, counts.into_iter().max().unwrap()))
+        .sorted()
+        .collect()
+}
+
+#[test]
+fn savepoint_rescale_keyed_state() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("first");
+    let second = dir.path().join("second");
+
+    let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+    env.savepoint(&first);
+    let res = keyed_counting_job(&env, 100);
+    env.execute_blocking();
+    assert_eq!(
+        last_counts(res.get().unwrap()),
+        (0..10).map(|k| (k, 10)).collect_vec()
+    );
+
+    // with more replicas each key is received by the replica that restored its state
+    let env = StreamContext::from_savepoint(&first, RuntimeConfig::local(4).unwrap()).unwrap();
+    env.savepoint(&second);
+    let res = keyed_counting_job(&env, 50);
+    env.execute_blocking();
+    let res = res.get().unwrap();
+    assert_eq!(res.len(), 50);
+    assert!(res.iter().all(|(_, count)| (11..=15).contains(count)));
+    assert_eq!(last_counts(res), (0..10).map(|k| (k, 15)).collect_vec());
+
+    // and with fewer replicas
+    let env = StreamContext::from_savepoint(&second, RuntimeConfig::local(1).unwrap()).unwrap();
+    let res = keyed_counting_job(&env, 10);
+    env.execute_blocking();
+    assert_eq!(
+        last_counts(res.get().unwrap()),
+        (0..10).map(|k| (k, 16)).collect_vec()
+    );
+}
+
+#[test]
+fn savepoint_incomplete_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = RuntimeConfig::local(2).unwrap();
+    assert!(StreamContext::from_savepoint(dir.path().join("missing"), config).is_err());
+}
@@ -32,3 +32,27 @@ fn shuffle_stream() {
         }
     });
 }
+
+#[test]
+fn partition_custom_stream() {
+    TestHelper::local_remote_env(|env| {
+        let source = IteratorSource::new(-50..50i32);
+        let parallelism = env.parallelism();
+        let res = env
+            .stream(source)
+            .partition_custom(|&n, num_partitions| if n < 0 { 0 } else { num_partitions - 1 })
+            // each replica receives either only negative or only non-negative numbers
+            .map_with_state(None, move |negative, n| {
+                if parallelism > 1 {
+                    assert_eq!(*negative.get_or_insert(n < 0), n < 0, "{n} routed wrongly");
+                }
+                n
+            })
+            .collect_vec();
+        env.execute_blocking();
+        if let Some(res) = res.get() {
+            let res_sorted = res.into_iter().sorted().collect_vec();
+            assert_eq!(res_sorted, (-50..50).collect_vec());
+        }
+    });
+}
@@ -1,6 +1,7 @@
 use itertools::Itertools;
 
 use renoir::operator::source::IteratorSource;
+use renoir::{RuntimeConfig, StreamContext};
 use utils::TestHelper;
 
 mod utils;
@@ -53,3 +54,25 @@ fn double_split_stream() {
         }
     });
 }
+
+#[test]
+fn tee_to_two_sinks() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.csv");
+    let env = StreamContext::new(RuntimeConfig::local(2).unwrap());
+    let (all, persisted) = env.stream_par_iter(0..100u32).tee();
+    let sum = all.fold(0, |acc, n| *acc += n).collect_vec();
+    persisted
+        .filter(|n| n % 10 == 0)
+        .write_csv_one(&path, false);
+    env.execute_blocking();
+
+    assert_eq!(sum.get().unwrap(), vec![(0..100).sum::<u32>()]);
+    let lines = std::fs::read_to_string(&path).unwrap();
+    let lines = lines
+        .lines()
+        .map(|l| l.parse::<u32>().unwrap())
+        .sorted()
+        .collect_vec();
+    assert_eq!(lines, (0..100).step_by(10).collect_vec());
+}
@@ -157,8 +157,10 @@ impl TestHelper {
             let address = format!("127.{high_part}.{low_part}.{host_id}");
             hosts.push(HostConfig {
                 address,
+                bind_address: None,
                 base_port: TEST_BASE_PORT,
                 num_cores: cores_per_host,
+                weight: None,
                 ssh: Default::default(),
                 perf_path: None,
             });
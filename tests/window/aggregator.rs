@@ -181,3 +181,20 @@ fn test_map_window() {
         }
     });
 }
+
+#[test]
+fn test_sliding_count_window_step_one() {
+    TestHelper::local_remote_env(|env| {
+        let source = IteratorSource::new(1..=4u8);
+        let res = env
+            .stream(source)
+            .window_all(CountWindow::sliding(3, 1))
+            .to_vec()
+            .drop_key()
+            .collect_vec();
+        env.execute_blocking();
+        if let Some(res) = res.get() {
+            assert_eq!(res, vec![vec![1, 2, 3], vec![2, 3, 4]]);
+        }
+    });
+}
@@ -189,3 +189,30 @@ fn test_map_window_keyed() {
         }
     });
 }
+
+#[test]
+fn test_sliding_count_window_step_one_keyed() {
+    TestHelper::local_remote_env(|env| {
+        // the elements of the two keys are interleaved
+        let source = IteratorSource::new((1..=4u8).flat_map(|x| [(0, x), (1, 10 * x)]));
+        let res = env
+            .stream(source)
+            .group_by(|&(k, _)| k)
+            .window(CountWindow::sliding(3, 1))
+            .map(|w| w.into_iter().map(|(_, x)| x).collect_vec())
+            .collect_vec();
+        env.execute_blocking();
+        if let Some(mut res) = res.get() {
+            res.sort_unstable();
+            assert_eq!(
+                res,
+                vec![
+                    (0, vec![1, 2, 3]),
+                    (0, vec![2, 3, 4]),
+                    (1, vec![10, 20, 30]),
+                    (1, vec![20, 30, 40]),
+                ]
+            );
+        }
+    });
+}
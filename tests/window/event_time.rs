@@ -1,5 +1,8 @@
+use std::time::{Duration, Instant};
+
 use renoir::operator::source::IteratorSource;
 use renoir::operator::window::EventTimeWindow;
+use renoir::{RuntimeConfig, StreamContext};
 
 use super::utils::TestHelper;
 
@@ -59,3 +62,41 @@ fn tumbling_event_time() {
         }
     });
 }
+
+#[test]
+fn tumbling_event_time_idle_input() {
+    let env = StreamContext::new(RuntimeConfig::local(1).unwrap());
+    let start = Instant::now();
+
+    let active = env
+        .stream_iter((0..20i64).inspect(|_| std::thread::sleep(Duration::from_millis(10))))
+        .add_timestamps(|&x| x, |_, &ts| Some(ts));
+    // an input that sends nothing for a long time before ending
+    let idle = env
+        .stream_iter(std::iter::from_fn(|| {
+            std::thread::sleep(Duration::from_secs(3));
+            None::<i64>
+        }))
+        .add_timestamps(|&x| x, |_, &ts| Some(ts));
+
+    let res = active
+        .merge(idle)
+        .with_idleness(Duration::from_millis(100))
+        .key_by(|_| ())
+        .window(EventTimeWindow::tumbling(5))
+        .sum::<i64>()
+        .drop_key()
+        .map(move |sum| (sum, start.elapsed()))
+        .collect_vec();
+    env.execute_blocking();
+
+    let res = res.get().unwrap();
+    assert_eq!(
+        res.iter().map(|(sum, _)| *sum).collect::<Vec<_>>(),
+        vec![10, 35, 60, 85]
+    );
+    // the windows fired without waiting for the idle input to end
+    assert!(res
+        .iter()
+        .all(|(_, elapsed)| *elapsed < Duration::from_secs(2)));
+}
@@ -0,0 +1,43 @@
+use renoir::config::ConfigBuilder;
+use renoir::StreamContext;
+
+#[test]
+fn worker_logs_json_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = format!(
+        r#"
+tracing_dir = "{}"
+worker_logs = true
+
+[[host]]
+address = "127.0.0.1"
+base_port = 21300
+num_cores = 2
+"#,
+        dir.path().display()
+    );
+    let config = ConfigBuilder::new_remote()
+        .parse_toml_str(&config)
+        .unwrap()
+        .host_id(0)
+        .build()
+        .unwrap();
+
+    let env = StreamContext::new(config);
+    let res = env.stream_iter(0..10).collect_vec();
+    env.execute_blocking();
+    assert_eq!(res.get().unwrap().len(), 10);
+
+    let logs = std::fs::read_to_string(dir.path().join("renoir-worker-0.log")).unwrap();
+    let lines = logs
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert!(lines.iter().all(|line| line["host_id"] == 0));
+    // the workers of the replicas tag their events with their coordinates
+    assert!(lines.iter().any(|line| {
+        line["message"].as_str().unwrap().contains("completed")
+            && line["coord"].is_string()
+            && line["block_id"].is_u64()
+    }));
+}